@@ -1,5 +1,32 @@
 /* auto-generated by NAPI-RS */
 /* eslint-disable */
+export declare const enum AlbumField {
+  Album = 'Album',
+  AlbumArtists = 'AlbumArtists',
+  Year = 'Year',
+  Genre = 'Genre',
+  DiscTotal = 'DiscTotal',
+  Cover = 'Cover',
+}
+
+export interface ArtworkCacheEntry {
+  hash?: string
+  thumbnailPath?: string
+  error?: string
+}
+
+export interface ArtworkCacheOptions {
+  size?: number
+}
+
+export interface ArtworkPolicy {
+  mp4CoverFormat?: Mp4CoverFormat
+  flacPictureStorage?: FlacPictureStorage
+  id3PictureEncoding?: Id3PictureEncoding
+}
+
+export declare function audioHash(source: string | Buffer): Promise<string>
+
 export declare const enum AudioImageType {
   Icon = 'Icon',
   OtherIcon = 'OtherIcon',
@@ -24,29 +51,369 @@ export declare const enum AudioImageType {
   Other = 'Other',
 }
 
+export interface AudioProperties {
+  durationMs: number
+  bitrate?: number
+  sampleRate?: number
+  bitDepth?: number
+  channels?: number
+}
+
 export interface AudioTags {
   title?: string
+  subtitle?: string
   artists?: Array<string>
   album?: string
   year?: number
   genre?: string
+  genres?: Array<string>
   track?: Position
   albumArtists?: Array<string>
   comment?: string
+  comments?: Array<Comment>
   disc?: Position
+  discSubtitle?: string
   image?: Image
   allImages?: Array<Image>
+  composer?: string
+  lyricist?: string
+  conductor?: string
+  remixer?: string
+  publisher?: string
+  copyright?: string
+  lyrics?: string
+  isrc?: string
+  barcode?: string
+  catalogNumber?: string
+  musicbrainzRecordingId?: string
+  musicbrainzTrackId?: string
+  musicbrainzReleaseId?: string
+  musicbrainzReleaseGroupId?: string
+  musicbrainzArtistId?: string
+  musicbrainzAlbumArtistId?: string
+  replayGainTrackGain?: number
+  replayGainTrackPeak?: number
+  replayGainAlbumGain?: number
+  replayGainAlbumPeak?: number
+  bpm?: number
+  initialKey?: string
+  mood?: string
+  rating?: number
+  playCount?: number
+  compilation?: boolean
+  grouping?: string
+  work?: string
+  movement?: string
+  movementNumber?: number
+  titleSort?: string
+  albumSort?: string
+  artistSort?: string
+  albumArtistSort?: string
+  composerSort?: string
+  encoderSoftware?: string
+  encoderSettings?: string
+  customFields?: Record<string, Array<string>>
+  bwf?: Bwf
+  mp4Freeform?: Record<string, Buffer>
+  binaryObjects?: Array<BinaryObject>
+  truncated?: boolean
+  warnings?: Array<string>
+  gaplessInfo?: GaplessInfo
+}
+
+export interface BatchFileResult {
+  filePath: string
+  error?: string
+  hookError?: string
+}
+
+export interface BatchReadResult {
+  filePath: string
+  tags?: AudioTags
+  error?: string
+}
+
+export interface BinaryObject {
+  description: string
+  mimeType?: string
+  filename?: string
+  data: Buffer
 }
 
-export declare function clearTags(filePath: string): Promise<void>
+export interface BufferProbe {
+  fileType?: string
+  tagTypes: Array<string>
+  id3V2Size?: number
+}
+
+export declare function buildArtworkCache(paths: Array<string | Buffer>, cacheDir: string | Buffer, options?: ArtworkCacheOptions | undefined | null): Promise<Record<string, ArtworkCacheEntry>>
+
+export interface Bwf {
+  description?: string
+  originator?: string
+  originatorReference?: string
+  originationDate?: string
+  originationTime?: string
+  timeReference?: number
+}
+
+export declare function canWrite(filePath: string | Buffer): Promise<Writability>
+
+export interface Chapter {
+  startMs: number
+  endMs: number
+  title?: string
+  url?: string
+  image?: Image
+}
+
+export interface ClearOptions {
+  keepImages?: boolean
+  keepTechnical?: boolean
+  tagType?: TagType
+  backup?: boolean | string
+  preserveMtime?: boolean
+}
+
+export declare function clearTags(filePath: string | Buffer, options?: ClearOptions | undefined | null): Promise<void>
+
+export declare function clearTagsBatch(paths: Array<string | Buffer>, options?: ClearOptions | undefined | null, signal?: AbortSignal, onProgress?: (done: number, total: number, currentPath: string) => void, hooks?: WriteHooks | undefined | null): Promise<Array<BatchFileResult>>
+
+export declare function clearTagsSync(filePath: string | Buffer, options?: ClearOptions | undefined | null): void
+
+export declare function clearTagsToBuffer(buffer: Buffer, options?: ClearOptions | undefined | null): Promise<Buffer>
+
+export declare function clearTagsToBufferSync(buffer: Buffer, options?: ClearOptions | undefined | null): Buffer
+
+export declare const enum CollisionPolicy {
+  AddSuffix = 'AddSuffix',
+  Skip = 'Skip',
+  Fail = 'Fail',
+}
 
-export declare function clearTagsToBuffer(buffer: Buffer): Promise<Buffer>
+export interface Comment {
+  language?: string
+  description?: string
+  text: string
+}
+
+export declare function copyTags(sourcePath: string | Buffer, destPath: string | Buffer, options?: WriteOptions | undefined | null): Promise<void>
+
+export declare function copyTagsSync(sourcePath: string | Buffer, destPath: string | Buffer, options?: WriteOptions | undefined | null): void
+
+export declare function copyTagsToBuffer(source: Buffer, dest: Buffer, options?: WriteOptions | undefined | null): Promise<Buffer>
+
+export declare function copyTagsToBufferSync(source: Buffer, dest: Buffer, options?: WriteOptions | undefined | null): Buffer
+
+export interface CoverBatchOptions {
+  maxBytesPerImage?: number
+}
+
+export interface CoverImageOptions {
+  picType?: AudioImageType
+  description?: string
+  mimeType?: string
+  backup?: boolean | string
+  preserveMtime?: boolean
+  imageLimits?: ImageLimits
+  resize?: ResizeOptions
+}
+
+export declare function diffTags(filePath: string | Buffer, tags: AudioTags, options?: WriteOptions | undefined | null): Promise<Array<TagChange>>
+
+export declare function diffTagsSync(filePath: string | Buffer, tags: AudioTags, options?: WriteOptions | undefined | null): Array<TagChange>
+
+export declare class DirectoryWatcher {
+  close(): void
+}
+
+export declare const enum ExportFormat {
+  Json = 'Json',
+  Csv = 'Csv',
+}
+
+export declare function exportTags(root: string | Buffer, format: ExportFormat, outPath: string | Buffer, options?: ScanOptions | undefined | null, signal?: AbortSignal, onProgress?: (done: number, total: number, currentPath: string) => void): Promise<number>
+
+export interface FieldMergeRule {
+  field: TagField
+  rule: MergeRule
+}
+
+export interface FileProbe {
+  fileType: string
+  primaryTagType: string
+  presentTagTypes: Array<string>
+  hasArtwork: boolean
+  tagSizeBytes?: number
+}
+
+export declare const enum FindingKind {
+  MissingField = 'MissingField',
+  NumberExceedsTotal = 'NumberExceedsTotal',
+  YearOutOfRange = 'YearOutOfRange',
+  OversizedImage = 'OversizedImage',
+  MixedEncodings = 'MixedEncodings',
+  Id3v1Mismatch = 'Id3v1Mismatch',
+}
+
+export declare const enum FlacPictureStorage {
+  PictureBlock = 'PictureBlock',
+  VorbisComment = 'VorbisComment',
+}
+
+export interface FormatSupport {
+  fileType: string
+  extensions: Array<string>
+  mimeType: string
+  primaryTagType: string
+  tagTypes: Array<string>
+  canRead: boolean
+  canWrite: boolean
+}
+
+export interface GaplessInfo {
+  source: GaplessSource
+  encoderDelay: number
+  encoderPadding: number
+  originalSamples?: number
+}
+
+export declare const enum GaplessSource {
+  LameHeader = 'LameHeader',
+  ITunSmpb = 'iTunSMPB',
+}
+
+export declare const enum Id3PictureEncoding {
+  Utf8 = 'Utf8',
+  Utf16 = 'Utf16',
+  Latin1 = 'Latin1',
+}
+
+export declare const enum Id3TextEncoding {
+  Utf8 = 'Utf8',
+  Utf16 = 'Utf16',
+  Latin1 = 'Latin1',
+}
+
+export declare const enum Id3v2Version {
+  V2_3 = '2.3',
+  V2_4 = '2.4',
+}
+
+export declare const enum IdentifierValidation {
+  Reject = 'Reject',
+  Warn = 'Warn',
+  PassThrough = 'PassThrough',
+}
 
 export interface Image {
   data: Buffer
   picType: AudioImageType
   mimeType?: string
   description?: string
+  mimeMismatch?: boolean
+  width?: number
+  height?: number
+  hasAlpha?: boolean
+}
+
+export interface ImageInfo {
+  picType: AudioImageType
+  mimeType?: string
+  description?: string
+  size: number
+  width?: number
+  height?: number
+}
+
+export interface ImageLimits {
+  maxBytes?: number
+  maxWidth?: number
+  maxHeight?: number
+}
+
+export interface ImportOptions {
+  dryRun?: boolean
+  matchBy?: MatchBy
+  root?: string
+}
+
+export interface ImportResult {
+  row: number
+  filePath?: string
+  changed: Array<string>
+  error?: string
+}
+
+export declare function importTags(manifestPath: string | Buffer, options?: ImportOptions | undefined | null): Promise<Array<ImportResult>>
+
+export interface InferOptions {
+  write?: boolean
+}
+
+export declare function inferTagsFromFilename(filePath: string | Buffer, pattern: string, options?: InferOptions | undefined | null): Promise<AudioTags>
+
+export interface Ixml {
+  project?: string
+  scene?: string
+  take?: string
+  tape?: string
+  note?: string
+  tracks?: Array<IxmlTrack>
+}
+
+export interface IxmlTrack {
+  channelIndex?: number
+  interleaveIndex?: number
+  name?: string
+}
+
+export interface LyricLine {
+  timeMs: number
+  text: string
+}
+
+export declare const enum MatchBy {
+  Path = 'Path',
+  Isrc = 'Isrc',
+}
+
+export declare const enum MergeRule {
+  PreferIncoming = 'PreferIncoming',
+  PreferExisting = 'PreferExisting',
+  FillEmptyOnly = 'FillEmptyOnly',
+}
+
+export interface MergeStrategy {
+  rule?: MergeRule
+  overrides?: Array<FieldMergeRule>
+}
+
+export declare function mergeTags(base: AudioTags, incoming: AudioTags, strategy?: MergeRule | MergeStrategy | undefined | null): AudioTags
+
+export declare const enum MimeInference {
+  Sniff = 'Sniff',
+  TrustDeclared = 'TrustDeclared',
+  Off = 'Off',
+}
+
+export declare const enum Mp4CoverFormat {
+  Keep = 'Keep',
+  Jpeg = 'Jpeg',
+  Png = 'Png',
+}
+
+export declare function parseLrc(lrc: string): SyncedLyrics
+
+export declare const enum ParsingMode {
+  Strict = 'Strict',
+  BestAttempt = 'BestAttempt',
+  Relaxed = 'Relaxed',
+}
+
+export declare const enum PopmScale {
+  WindowsMedia = 'WindowsMedia',
+  MediaMonkey = 'MediaMonkey',
 }
 
 export interface Position {
@@ -54,18 +421,515 @@ export interface Position {
   of?: number
 }
 
-export declare function readCoverImageFromBuffer(buffer: Buffer): Promise<Buffer | null>
+export declare function probeBuffer(buffer: Buffer, options?: ProbeOptions | undefined | null): BufferProbe
+
+export declare function probeFile(source: string | Buffer): Promise<FileProbe>
+
+export declare function probeFileSync(source: string | Buffer): FileProbe
+
+export interface ProbeOptions {
+  maxBytes?: number
+}
+
+export declare function propagateAlbumFields(paths: Array<string | Buffer>, fields: Array<AlbumField>, options?: PropagateOptions | undefined | null): Promise<AudioTags>
+
+export interface PropagateOptions {
+  reference?: string
+}
+
+export interface RawTagItem {
+  tagType: string
+  key: string
+  keyString?: string
+  valueType: RawValueType
+  value: string | Buffer
+}
+
+export declare const enum RawValueType {
+  Text = 'Text',
+  Locator = 'Locator',
+  Binary = 'Binary',
+}
+
+export declare function readAllTags(filePath: string | Buffer): Promise<Array<TagEntry>>
+
+export declare function readAllTagsFromBuffer(buffer: Buffer): Promise<Array<TagEntry>>
+
+export declare function readAllTagsFromBufferSync(buffer: Buffer): Array<TagEntry>
+
+export declare function readAllTagsSync(filePath: string | Buffer): Array<TagEntry>
+
+export declare function readAudioProperties(filePath: string | Buffer): Promise<AudioProperties>
+
+export declare function readAudioPropertiesFromBuffer(buffer: Buffer): Promise<AudioProperties>
+
+export declare function readAudioPropertiesFromBufferSync(buffer: Buffer): AudioProperties
+
+export declare function readAudioPropertiesSync(filePath: string | Buffer): AudioProperties
+
+export declare function readChapters(filePath: string | Buffer): Promise<Array<Chapter>>
+
+export declare function readChaptersFromBuffer(buffer: Buffer): Promise<Array<Chapter>>
+
+export declare function readChaptersFromBufferSync(buffer: Buffer): Array<Chapter>
+
+export declare function readChaptersSync(filePath: string | Buffer): Array<Chapter>
+
+export declare function readCoverImageDataUrl(source: string | Buffer): Promise<string | null>
+
+export declare function readCoverImageDataUrlSync(source: string | Buffer): string | null
+
+export declare function readCoverImageFromBuffer(buffer: Buffer, picType?: AudioImageType | undefined | null): Promise<Buffer | null>
+
+export declare function readCoverImageFromBufferSync(buffer: Buffer, picType?: AudioImageType | undefined | null): Buffer | null
+
+export declare function readCoverImageFromFile(filePath: string | Buffer, picType?: AudioImageType | undefined | null): Promise<Buffer | null>
+
+export declare function readCoverImageFromFileSync(filePath: string | Buffer, picType?: AudioImageType | undefined | null): Buffer | null
+
+export declare function readCoverImagesBatch(paths: Array<string | Buffer>, options?: CoverBatchOptions | undefined | null, signal?: AbortSignal, onProgress?: (done: number, total: number, currentPath: string) => void): Promise<Array<Buffer | undefined | null>>
+
+export declare function readCoverThumbnail(filePath: string | Buffer, maxEdge?: number | undefined | null): Promise<Buffer | null>
+
+export declare function readCoverThumbnailSync(filePath: string | Buffer, maxEdge?: number | undefined | null): Buffer | null
+
+export declare function readImageInfo(source: string | Buffer): Promise<Array<ImageInfo>>
+
+export declare function readImageInfoSync(source: string | Buffer): Array<ImageInfo>
+
+export declare function readIxml(source: string | Buffer): Promise<Ixml | null>
+
+export declare function readIxmlSync(source: string | Buffer): Ixml | null
+
+export declare function readLyrics(filePath: string | Buffer): Promise<string | null>
+
+export declare function readLyricsFromBuffer(buffer: Buffer): Promise<string | null>
+
+export declare function readLyricsFromBufferSync(buffer: Buffer): string | null
+
+export declare function readLyricsSync(filePath: string | Buffer): string | null
+
+export interface ReadOptions {
+  tolerateTruncation?: boolean
+  preservePictureOrder?: boolean
+  artistSeparator?: string
+  multiValueMode?: boolean
+  id3v1Fallback?: boolean
+  skipImages?: boolean
+  reportWarnings?: boolean
+  parsingMode?: ParsingMode
+  maxAllocationBytes?: number
+  skipProperties?: boolean
+  legacyEncoding?: string
+  gaplessInfo?: boolean
+}
+
+export declare function readPrimaryArtwork(source: string | Buffer): Promise<Image | null>
+
+export declare function readPrimaryArtworkSync(source: string | Buffer): Image | null
+
+export declare function readRawTagItems(filePath: string | Buffer): Promise<Array<RawTagItem>>
+
+export declare function readRawTagItemsFromBuffer(buffer: Buffer): Promise<Array<RawTagItem>>
+
+export declare function readRawTagItemsFromBufferSync(buffer: Buffer): Array<RawTagItem>
+
+export declare function readRawTagItemsSync(filePath: string | Buffer): Array<RawTagItem>
+
+export declare function readSyncedLyrics(filePath: string | Buffer): Promise<SyncedLyrics | null>
+
+export declare function readSyncedLyricsFromBuffer(buffer: Buffer): Promise<SyncedLyrics | null>
+
+export declare function readSyncedLyricsFromBufferSync(buffer: Buffer): SyncedLyrics | null
+
+export declare function readSyncedLyricsSync(filePath: string | Buffer): SyncedLyrics | null
+
+export declare function readTags(file: string | Buffer | number | import('fs/promises').FileHandle, options?: ReadOptions | undefined | null): Promise<AudioTags>
+
+export declare function readTagsBatch(paths: Array<string | Buffer>, concurrency?: number | undefined | null, signal?: AbortSignal, onProgress?: (done: number, total: number, currentPath: string) => void, options?: ReadOptions | undefined | null): Promise<Array<BatchReadResult>>
+
+export declare function readTagsBatchEach(paths: Array<string | Buffer>, onResult: (result: BatchReadResult) => void | Promise<void>, concurrency?: number | undefined | null, signal?: AbortSignal, onProgress?: (done: number, total: number, currentPath: string) => void, options?: ReadOptions | undefined | null): Promise<void>
+
+export declare function readTagsFromBuffer(buffer: Buffer, options?: ReadOptions | undefined | null): Promise<AudioTags>
+
+export declare function readTagsFromBufferSync(buffer: Buffer, options?: ReadOptions | undefined | null): AudioTags
+
+export declare function readTagsFromStream(pull: (size: number) => Buffer | null | Promise<Buffer | null>, options?: ReadOptions): Promise<AudioTags>
+
+export declare function readTagsSync(file: string | Buffer | number | import('fs/promises').FileHandle, options?: ReadOptions | undefined | null): AudioTags
+
+export declare function removeCoverImageFromBuffer(buffer: Buffer): Promise<Buffer>
+
+export declare function removeCoverImageFromBufferSync(buffer: Buffer): Buffer
+
+export declare function removeCoverImageFromFile(filePath: string | Buffer, options?: SaveOptions | undefined | null): Promise<void>
+
+export declare function removeCoverImageFromFileSync(filePath: string | Buffer, options?: SaveOptions | undefined | null): void
+
+export declare function removeImages(filePath: string | Buffer, picTypes?: Array<AudioImageType> | undefined | null, options?: SaveOptions | undefined | null): Promise<void>
+
+export declare function removeImagesFromBuffer(buffer: Buffer, picTypes?: Array<AudioImageType> | undefined | null): Promise<Buffer>
+
+export declare function removeImagesFromBufferSync(buffer: Buffer, picTypes?: Array<AudioImageType> | undefined | null): Buffer
+
+export declare function removeImagesSync(filePath: string | Buffer, picTypes?: Array<AudioImageType> | undefined | null, options?: SaveOptions | undefined | null): void
+
+export declare function renameFromTags(filePath: string | Buffer, template: string, options?: RenameOptions | undefined | null): Promise<RenameOutcome>
+
+export interface RenameOptions {
+  root?: string
+  dryRun?: boolean
+  onCollision?: CollisionPolicy
+}
+
+export interface RenameOutcome {
+  filePath: string
+  newPath: string
+  renamed: boolean
+}
+
+export declare const enum ResizeFormat {
+  Jpeg = 'jpeg',
+  Png = 'png',
+}
+
+export interface ResizeOptions {
+  maxEdge: number
+  format?: ResizeFormat
+  quality?: number
+}
+
+export interface SaveOptions {
+  preserveMtime?: boolean
+}
+
+export declare function scanDirectory(root: string | Buffer, options?: ScanOptions | undefined | null, signal?: AbortSignal, onProgress?: (done: number, total: number, currentPath: string) => void): Promise<Array<ScanResult>>
+
+export declare function scanDirectoryEach(root: string | Buffer, onResult: (result: ScanResult) => void | Promise<void>, options?: ScanOptions | undefined | null, signal?: AbortSignal, onProgress?: (done: number, total: number, currentPath: string) => void): Promise<number>
+
+export interface ScanOptions {
+  recursive?: boolean
+  extensions?: Array<string>
+  followSymlinks?: boolean
+  concurrency?: number
+}
+
+export interface ScanResult {
+  filePath: string
+  tags?: AudioTags
+  properties?: AudioProperties
+  error?: string
+}
+
+export declare function stripAllTags(filePath: string | Buffer, options?: SaveOptions | undefined | null): Promise<Array<TagType>>
+
+export declare function stripAllTagsSync(filePath: string | Buffer, options?: SaveOptions | undefined | null): Array<TagType>
+
+export declare function stripAllTagsToBuffer(buffer: Buffer): Promise<StripResult>
+
+export declare function stripAllTagsToBufferSync(buffer: Buffer): StripResult
+
+export interface StripResult {
+  buffer: Buffer
+  removed: Array<TagType>
+}
+
+export declare function stripTagTypes(filePath: string | Buffer, tagTypes: Array<TagType>, options?: SaveOptions | undefined | null): Promise<void>
+
+export declare function stripTagTypesSync(filePath: string | Buffer, tagTypes: Array<TagType>, options?: SaveOptions | undefined | null): void
+
+export declare function stripTagTypesToBuffer(buffer: Buffer, tagTypes: Array<TagType>): Promise<Buffer>
+
+export declare function stripTagTypesToBufferSync(buffer: Buffer, tagTypes: Array<TagType>): Buffer
+
+export declare function supportedFormats(): Array<FormatSupport>
+
+export interface SyncedLyrics {
+  language?: string
+  description?: string
+  lines: Array<LyricLine>
+}
+
+export interface TagChange {
+  field: string
+  oldValue?: any
+  newValue?: any
+}
+
+export interface TagEntry {
+  tagType: string
+  tags: AudioTags
+}
+
+export declare const enum TagField {
+  Title = 'Title',
+  Artists = 'Artists',
+  Album = 'Album',
+  Year = 'Year',
+  Genre = 'Genre',
+  Track = 'Track',
+  AlbumArtists = 'AlbumArtists',
+  Comment = 'Comment',
+  Disc = 'Disc',
+  Image = 'Image',
+  AllImages = 'AllImages',
+  Composer = 'Composer',
+  Lyricist = 'Lyricist',
+  Conductor = 'Conductor',
+  Remixer = 'Remixer',
+  Publisher = 'Publisher',
+  Copyright = 'Copyright',
+  Lyrics = 'Lyrics',
+  Isrc = 'Isrc',
+  Barcode = 'Barcode',
+  CatalogNumber = 'CatalogNumber',
+  MusicBrainzRecordingId = 'MusicBrainzRecordingId',
+  MusicBrainzTrackId = 'MusicBrainzTrackId',
+  MusicBrainzReleaseId = 'MusicBrainzReleaseId',
+  MusicBrainzReleaseGroupId = 'MusicBrainzReleaseGroupId',
+  MusicBrainzArtistId = 'MusicBrainzArtistId',
+  MusicBrainzAlbumArtistId = 'MusicBrainzAlbumArtistId',
+  ReplayGain = 'ReplayGain',
+  Bpm = 'Bpm',
+  InitialKey = 'InitialKey',
+  Mood = 'Mood',
+  Rating = 'Rating',
+  PlayCount = 'PlayCount',
+  Compilation = 'Compilation',
+  Grouping = 'Grouping',
+  Work = 'Work',
+  Movement = 'Movement',
+  MovementNumber = 'MovementNumber',
+  Subtitle = 'Subtitle',
+  DiscSubtitle = 'DiscSubtitle',
+  TitleSort = 'TitleSort',
+  AlbumSort = 'AlbumSort',
+  ArtistSort = 'ArtistSort',
+  AlbumArtistSort = 'AlbumArtistSort',
+  ComposerSort = 'ComposerSort',
+  EncoderSoftware = 'EncoderSoftware',
+  EncoderSettings = 'EncoderSettings',
+}
+
+export declare function tagFingerprint(source: string | AudioTags): Promise<string>
+
+export interface TagPatch {
+  set?: AudioTags
+  clear?: Array<TagField>
+}
+
+export declare function tagsEqual(a: AudioTags, b: AudioTags, options?: TagsEqualOptions | undefined | null): boolean
+
+export interface TagsEqualOptions {
+  ignoreImages?: boolean
+}
+
+export declare const enum TagType {
+  Ape = 'Ape',
+  Id3v1 = 'Id3v1',
+  Id3v2 = 'Id3v2',
+  Mp4Ilst = 'Mp4Ilst',
+  VorbisComments = 'VorbisComments',
+  RiffInfo = 'RiffInfo',
+  AiffText = 'AiffText',
+}
+
+export interface TagWriteEntry {
+  filePath: string
+  tags: AudioTags
+}
+
+export declare function toLrc(lyrics: SyncedLyrics): string
+
+export declare const enum TransformAction {
+  TitleCase = 'TitleCase',
+  SentenceCase = 'SentenceCase',
+  FixFeaturing = 'FixFeaturing',
+  Replace = 'Replace',
+}
+
+export interface TransformRule {
+  action: TransformAction
+  fields?: Array<TagField>
+  pattern?: string
+  replacement?: string
+}
+
+export declare function transformTags(filePath: string | Buffer, rules: Array<TransformRule>): Promise<Array<TagChange>>
+
+export declare function transformTagsBatch(paths: Array<string | Buffer>, rules: Array<TransformRule>, signal?: AbortSignal, onProgress?: (done: number, total: number, currentPath: string) => void, hooks?: WriteHooks | undefined | null): Promise<Array<BatchFileResult>>
+
+export declare function transformTagsSync(filePath: string | Buffer, rules: Array<TransformRule>): Array<TagChange>
+
+export declare function transformTagsToBuffer(buffer: Buffer, rules: Array<TransformRule>): Promise<Buffer>
+
+export declare function transformTagsToBufferSync(buffer: Buffer, rules: Array<TransformRule>): Buffer
+
+export declare function undoLastWrite(filePath: string | Buffer, journalDir?: string | Buffer): Promise<boolean>
+
+export declare function undoLastWriteSync(filePath: string | Buffer, journalDir?: string | Buffer): boolean
+
+export declare function updateTags(filePath: string | Buffer, patch: TagPatch, options?: SaveOptions | undefined | null): Promise<void>
+
+export declare function updateTagsSync(filePath: string | Buffer, patch: TagPatch, options?: SaveOptions | undefined | null): void
+
+export declare function updateTagsToBuffer(buffer: Buffer, patch: TagPatch): Promise<Buffer>
+
+export declare function updateTagsToBufferSync(buffer: Buffer, patch: TagPatch): Buffer
+
+export declare function validateTags(filePath: string | Buffer, rules?: ValidationRules | undefined | null): Promise<Array<ValidationFinding>>
+
+export declare function validateTagsFromBuffer(buffer: Buffer, rules?: ValidationRules | undefined | null): Promise<Array<ValidationFinding>>
+
+export declare function validateTagsFromBufferSync(buffer: Buffer, rules?: ValidationRules | undefined | null): Array<ValidationFinding>
+
+export declare function validateTagsSync(filePath: string | Buffer, rules?: ValidationRules | undefined | null): Array<ValidationFinding>
+
+export interface ValidationFinding {
+  kind: FindingKind
+  field?: TagField
+  message: string
+}
+
+export interface ValidationRules {
+  requiredFields?: Array<TagField>
+  minYear?: number
+  maxYear?: number
+  maxImageBytes?: number
+  checkEncodings?: boolean
+  checkId3v1?: boolean
+}
+
+export declare function watchDirectory(root: string | Buffer, onEvent: (event: WatchEvent) => void, options?: WatchOptions | undefined | null): DirectoryWatcher
+
+export interface WatchEvent {
+  filePath: string
+  kind: WatchEventKind
+  tags?: AudioTags
+  error?: string
+}
+
+export declare const enum WatchEventKind {
+  Added = 'Added',
+  Changed = 'Changed',
+  Removed = 'Removed',
+}
+
+export interface WatchOptions {
+  recursive?: boolean
+  extensions?: Array<string>
+  debounceMs?: number
+}
+
+export interface Writability {
+  writable: boolean
+  reason?: string
+  requiredBytes: number
+  availableBytes?: number
+}
+
+export declare function writeChapters(filePath: string | Buffer, chapters: Array<Chapter>, options?: SaveOptions | undefined | null): Promise<void>
+
+export declare function writeChaptersSync(filePath: string | Buffer, chapters: Array<Chapter>, options?: SaveOptions | undefined | null): void
+
+export declare function writeChaptersToBuffer(buffer: Buffer, chapters: Array<Chapter>): Promise<Buffer>
+
+export declare function writeChaptersToBufferSync(buffer: Buffer, chapters: Array<Chapter>): Buffer
+
+export declare function writeCoverImageFromPath(filePath: string | Buffer, imagePath: string | Buffer, options?: CoverImageOptions | undefined | null): Promise<void>
+
+export declare function writeCoverImageFromPathSync(filePath: string | Buffer, imagePath: string | Buffer, options?: CoverImageOptions | undefined | null): void
+
+export declare function writeCoverImageToBuffer(buffer: Buffer, imageData: Buffer, options?: CoverImageOptions | undefined | null): Promise<Buffer>
+
+export declare function writeCoverImageToBufferSync(buffer: Buffer, imageData: Buffer, options?: CoverImageOptions | undefined | null): Buffer
+
+export declare function writeCoverImageToFile(filePath: string | Buffer, imageData: Buffer, options?: CoverImageOptions | undefined | null): Promise<void>
+
+export declare function writeCoverImageToFileSync(filePath: string | Buffer, imageData: Buffer, options?: CoverImageOptions | undefined | null): void
+
+export interface WriteHookEvent {
+  filePath: string
+  oldTags: AudioTags
+  newTags: AudioTags
+}
+
+export interface WriteHooks {
+  beforeWrite?: (event: WriteHookEvent) => boolean | AudioTags | void | Promise<boolean | AudioTags | void>
+  afterWrite?: (event: WriteHookEvent) => void
+}
+
+export declare function writeIxml(filePath: string | Buffer, ixml: Ixml, options?: SaveOptions | undefined | null): Promise<void>
+
+export declare function writeIxmlSync(filePath: string | Buffer, ixml: Ixml, options?: SaveOptions | undefined | null): void
+
+export declare function writeIxmlToBuffer(buffer: Buffer, ixml: Ixml): Promise<Buffer>
+
+export declare function writeIxmlToBufferSync(buffer: Buffer, ixml: Ixml): Buffer
+
+export declare function writeLyrics(filePath: string | Buffer, lyrics: string, options?: SaveOptions | undefined | null): Promise<void>
+
+export declare function writeLyricsSync(filePath: string | Buffer, lyrics: string, options?: SaveOptions | undefined | null): void
+
+export declare function writeLyricsToBuffer(buffer: Buffer, lyrics: string): Promise<Buffer>
+
+export declare function writeLyricsToBufferSync(buffer: Buffer, lyrics: string): Buffer
+
+export interface WriteOptions {
+  journal?: boolean
+  journalDir?: string
+  mimeInference?: MimeInference
+  preservePictureOrder?: boolean
+  deterministic?: boolean
+  skipUnchanged?: boolean
+  artworkPolicy?: ArtworkPolicy
+  identifierValidation?: IdentifierValidation
+  preserveUnknown?: boolean
+  tagType?: TagType
+  id3v2Version?: Id3v2Version
+  id3TextEncoding?: Id3TextEncoding
+  padding?: number
+  removeOthers?: boolean
+  lowercaseId3v2Chunk?: boolean
+  artistSeparator?: string
+  multiValueMode?: boolean
+  popmScale?: PopmScale
+  atomic?: boolean
+  preserveMtime?: boolean
+  backup?: boolean | string
+  imageLimits?: ImageLimits
+  id3V1?: boolean
+  preserveBinaryObjects?: boolean
+  normalizeText?: boolean
+}
+
+export declare function writeRawTagItems(filePath: string | Buffer, items: Array<RawTagItem>, options?: SaveOptions | undefined | null): Promise<void>
+
+export declare function writeRawTagItemsSync(filePath: string | Buffer, items: Array<RawTagItem>, options?: SaveOptions | undefined | null): void
+
+export declare function writeRawTagItemsToBuffer(buffer: Buffer, items: Array<RawTagItem>): Promise<Buffer>
+
+export declare function writeRawTagItemsToBufferSync(buffer: Buffer, items: Array<RawTagItem>): Buffer
+
+export interface WriteResult {
+  changed: boolean
+  warnings: Array<string>
+}
+
+export declare function writeSyncedLyrics(filePath: string | Buffer, lyrics: SyncedLyrics, options?: SaveOptions | undefined | null): Promise<void>
+
+export declare function writeSyncedLyricsSync(filePath: string | Buffer, lyrics: SyncedLyrics, options?: SaveOptions | undefined | null): void
 
-export declare function readCoverImageFromFile(filePath: string): Promise<Buffer | null>
+export declare function writeSyncedLyricsToBuffer(buffer: Buffer, lyrics: SyncedLyrics): Promise<Buffer>
 
-export declare function readTags(filePath: string): Promise<AudioTags>
+export declare function writeSyncedLyricsToBufferSync(buffer: Buffer, lyrics: SyncedLyrics): Buffer
 
-export declare function readTagsFromBuffer(buffer: Buffer): Promise<AudioTags>
+export declare function writeTags(file: string | Buffer | number | import('fs/promises').FileHandle, tags: AudioTags, options?: WriteOptions | undefined | null): Promise<WriteResult>
 
-export declare function writeCoverImageToBuffer(buffer: Buffer, imageData: Buffer): Promise<Buffer>
+export declare function writeTagsSync(file: string | Buffer | number | import('fs/promises').FileHandle, tags: AudioTags, options?: WriteOptions | undefined | null): WriteResult
 
-export declare function writeCoverImageToFile(filePath: string, imageData: Buffer): Promise<void>
+export declare function writeTagsToBuffer(buffer: Buffer, tags: AudioTags, options?: WriteOptions | undefined | null): Promise<Buffer>
 
-export declare function writeTags(filePath: string, tags: AudioTags): Promise<void>
+export declare function writeTagsToBufferSync(buffer: Buffer, tags: AudioTags, options?: WriteOptions | undefined | null): Buffer
 
-export declare function writeTagsToBuffer(buffer: Buffer, tags: AudioTags): Promise<Buffer>
+export declare function writeTagsTransaction(entries: Array<TagWriteEntry>, hooks?: WriteHooks | undefined | null, signal?: AbortSignal): Promise<Array<string>>
@@ -508,15 +508,190 @@ if (!nativeBinding) {
 }
 
 module.exports = nativeBinding
+module.exports.AlbumField = nativeBinding.AlbumField
+module.exports.ApiAlbumField = nativeBinding.ApiAlbumField
+module.exports.audioHash = nativeBinding.audioHash
 module.exports.AudioImageType = nativeBinding.AudioImageType
 module.exports.ApiAudioImageType = nativeBinding.ApiAudioImageType
+module.exports.buildArtworkCache = nativeBinding.buildArtworkCache
+module.exports.canWrite = nativeBinding.canWrite
 module.exports.clearTags = nativeBinding.clearTags
+module.exports.clearTagsBatch = nativeBinding.clearTagsBatch
+module.exports.clearTagsSync = nativeBinding.clearTagsSync
 module.exports.clearTagsToBuffer = nativeBinding.clearTagsToBuffer
+module.exports.clearTagsToBufferSync = nativeBinding.clearTagsToBufferSync
+module.exports.CollisionPolicy = nativeBinding.CollisionPolicy
+module.exports.ApiCollisionPolicy = nativeBinding.ApiCollisionPolicy
+module.exports.copyTags = nativeBinding.copyTags
+module.exports.copyTagsSync = nativeBinding.copyTagsSync
+module.exports.copyTagsToBuffer = nativeBinding.copyTagsToBuffer
+module.exports.copyTagsToBufferSync = nativeBinding.copyTagsToBufferSync
+module.exports.diffTags = nativeBinding.diffTags
+module.exports.diffTagsSync = nativeBinding.diffTagsSync
+module.exports.DirectoryWatcher = nativeBinding.DirectoryWatcher
+module.exports.ApiDirectoryWatcher = nativeBinding.ApiDirectoryWatcher
+module.exports.ExportFormat = nativeBinding.ExportFormat
+module.exports.ApiExportFormat = nativeBinding.ApiExportFormat
+module.exports.exportTags = nativeBinding.exportTags
+module.exports.FindingKind = nativeBinding.FindingKind
+module.exports.ApiFindingKind = nativeBinding.ApiFindingKind
+module.exports.FlacPictureStorage = nativeBinding.FlacPictureStorage
+module.exports.ApiFlacPictureStorage = nativeBinding.ApiFlacPictureStorage
+module.exports.GaplessSource = nativeBinding.GaplessSource
+module.exports.ApiGaplessSource = nativeBinding.ApiGaplessSource
+module.exports.Id3PictureEncoding = nativeBinding.Id3PictureEncoding
+module.exports.ApiId3PictureEncoding = nativeBinding.ApiId3PictureEncoding
+module.exports.Id3TextEncoding = nativeBinding.Id3TextEncoding
+module.exports.ApiId3TextEncoding = nativeBinding.ApiId3TextEncoding
+module.exports.Id3v2Version = nativeBinding.Id3v2Version
+module.exports.ApiId3v2Version = nativeBinding.ApiId3v2Version
+module.exports.IdentifierValidation = nativeBinding.IdentifierValidation
+module.exports.ApiIdentifierValidation = nativeBinding.ApiIdentifierValidation
+module.exports.importTags = nativeBinding.importTags
+module.exports.inferTagsFromFilename = nativeBinding.inferTagsFromFilename
+module.exports.MatchBy = nativeBinding.MatchBy
+module.exports.ApiMatchBy = nativeBinding.ApiMatchBy
+module.exports.MergeRule = nativeBinding.MergeRule
+module.exports.ApiMergeRule = nativeBinding.ApiMergeRule
+module.exports.mergeTags = nativeBinding.mergeTags
+module.exports.MimeInference = nativeBinding.MimeInference
+module.exports.ApiMimeInference = nativeBinding.ApiMimeInference
+module.exports.Mp4CoverFormat = nativeBinding.Mp4CoverFormat
+module.exports.ApiMp4CoverFormat = nativeBinding.ApiMp4CoverFormat
+module.exports.parseLrc = nativeBinding.parseLrc
+module.exports.ParsingMode = nativeBinding.ParsingMode
+module.exports.ApiParsingMode = nativeBinding.ApiParsingMode
+module.exports.PopmScale = nativeBinding.PopmScale
+module.exports.ApiPopmScale = nativeBinding.ApiPopmScale
+module.exports.probeBuffer = nativeBinding.probeBuffer
+module.exports.probeFile = nativeBinding.probeFile
+module.exports.probeFileSync = nativeBinding.probeFileSync
+module.exports.propagateAlbumFields = nativeBinding.propagateAlbumFields
+module.exports.RawValueType = nativeBinding.RawValueType
+module.exports.ApiRawValueType = nativeBinding.ApiRawValueType
+module.exports.readAllTags = nativeBinding.readAllTags
+module.exports.readAllTagsFromBuffer = nativeBinding.readAllTagsFromBuffer
+module.exports.readAllTagsFromBufferSync = nativeBinding.readAllTagsFromBufferSync
+module.exports.readAllTagsSync = nativeBinding.readAllTagsSync
+module.exports.readAudioProperties = nativeBinding.readAudioProperties
+module.exports.readAudioPropertiesFromBuffer = nativeBinding.readAudioPropertiesFromBuffer
+module.exports.readAudioPropertiesFromBufferSync = nativeBinding.readAudioPropertiesFromBufferSync
+module.exports.readAudioPropertiesSync = nativeBinding.readAudioPropertiesSync
+module.exports.readChapters = nativeBinding.readChapters
+module.exports.readChaptersFromBuffer = nativeBinding.readChaptersFromBuffer
+module.exports.readChaptersFromBufferSync = nativeBinding.readChaptersFromBufferSync
+module.exports.readChaptersSync = nativeBinding.readChaptersSync
+module.exports.readCoverImageDataUrl = nativeBinding.readCoverImageDataUrl
+module.exports.readCoverImageDataUrlSync = nativeBinding.readCoverImageDataUrlSync
 module.exports.readCoverImageFromBuffer = nativeBinding.readCoverImageFromBuffer
+module.exports.readCoverImageFromBufferSync = nativeBinding.readCoverImageFromBufferSync
 module.exports.readCoverImageFromFile = nativeBinding.readCoverImageFromFile
+module.exports.readCoverImageFromFileSync = nativeBinding.readCoverImageFromFileSync
+module.exports.readCoverImagesBatch = nativeBinding.readCoverImagesBatch
+module.exports.readCoverThumbnail = nativeBinding.readCoverThumbnail
+module.exports.readCoverThumbnailSync = nativeBinding.readCoverThumbnailSync
+module.exports.readImageInfo = nativeBinding.readImageInfo
+module.exports.readImageInfoSync = nativeBinding.readImageInfoSync
+module.exports.readIxml = nativeBinding.readIxml
+module.exports.readIxmlSync = nativeBinding.readIxmlSync
+module.exports.readLyrics = nativeBinding.readLyrics
+module.exports.readLyricsFromBuffer = nativeBinding.readLyricsFromBuffer
+module.exports.readLyricsFromBufferSync = nativeBinding.readLyricsFromBufferSync
+module.exports.readLyricsSync = nativeBinding.readLyricsSync
+module.exports.readPrimaryArtwork = nativeBinding.readPrimaryArtwork
+module.exports.readPrimaryArtworkSync = nativeBinding.readPrimaryArtworkSync
+module.exports.readRawTagItems = nativeBinding.readRawTagItems
+module.exports.readRawTagItemsFromBuffer = nativeBinding.readRawTagItemsFromBuffer
+module.exports.readRawTagItemsFromBufferSync = nativeBinding.readRawTagItemsFromBufferSync
+module.exports.readRawTagItemsSync = nativeBinding.readRawTagItemsSync
+module.exports.readSyncedLyrics = nativeBinding.readSyncedLyrics
+module.exports.readSyncedLyricsFromBuffer = nativeBinding.readSyncedLyricsFromBuffer
+module.exports.readSyncedLyricsFromBufferSync = nativeBinding.readSyncedLyricsFromBufferSync
+module.exports.readSyncedLyricsSync = nativeBinding.readSyncedLyricsSync
 module.exports.readTags = nativeBinding.readTags
+module.exports.readTagsBatch = nativeBinding.readTagsBatch
+module.exports.readTagsBatchEach = nativeBinding.readTagsBatchEach
 module.exports.readTagsFromBuffer = nativeBinding.readTagsFromBuffer
+module.exports.readTagsFromBufferSync = nativeBinding.readTagsFromBufferSync
+module.exports.readTagsFromStream = nativeBinding.readTagsFromStream
+module.exports.readTagsSync = nativeBinding.readTagsSync
+module.exports.removeCoverImageFromBuffer = nativeBinding.removeCoverImageFromBuffer
+module.exports.removeCoverImageFromBufferSync = nativeBinding.removeCoverImageFromBufferSync
+module.exports.removeCoverImageFromFile = nativeBinding.removeCoverImageFromFile
+module.exports.removeCoverImageFromFileSync = nativeBinding.removeCoverImageFromFileSync
+module.exports.removeImages = nativeBinding.removeImages
+module.exports.removeImagesFromBuffer = nativeBinding.removeImagesFromBuffer
+module.exports.removeImagesFromBufferSync = nativeBinding.removeImagesFromBufferSync
+module.exports.removeImagesSync = nativeBinding.removeImagesSync
+module.exports.renameFromTags = nativeBinding.renameFromTags
+module.exports.ResizeFormat = nativeBinding.ResizeFormat
+module.exports.ApiResizeFormat = nativeBinding.ApiResizeFormat
+module.exports.scanDirectory = nativeBinding.scanDirectory
+module.exports.scanDirectoryEach = nativeBinding.scanDirectoryEach
+module.exports.stripAllTags = nativeBinding.stripAllTags
+module.exports.stripAllTagsSync = nativeBinding.stripAllTagsSync
+module.exports.stripAllTagsToBuffer = nativeBinding.stripAllTagsToBuffer
+module.exports.stripAllTagsToBufferSync = nativeBinding.stripAllTagsToBufferSync
+module.exports.stripTagTypes = nativeBinding.stripTagTypes
+module.exports.stripTagTypesSync = nativeBinding.stripTagTypesSync
+module.exports.stripTagTypesToBuffer = nativeBinding.stripTagTypesToBuffer
+module.exports.stripTagTypesToBufferSync = nativeBinding.stripTagTypesToBufferSync
+module.exports.supportedFormats = nativeBinding.supportedFormats
+module.exports.TagField = nativeBinding.TagField
+module.exports.ApiTagField = nativeBinding.ApiTagField
+module.exports.tagFingerprint = nativeBinding.tagFingerprint
+module.exports.tagsEqual = nativeBinding.tagsEqual
+module.exports.TagType = nativeBinding.TagType
+module.exports.ApiTagType = nativeBinding.ApiTagType
+module.exports.toLrc = nativeBinding.toLrc
+module.exports.TransformAction = nativeBinding.TransformAction
+module.exports.ApiTransformAction = nativeBinding.ApiTransformAction
+module.exports.transformTags = nativeBinding.transformTags
+module.exports.transformTagsBatch = nativeBinding.transformTagsBatch
+module.exports.transformTagsSync = nativeBinding.transformTagsSync
+module.exports.transformTagsToBuffer = nativeBinding.transformTagsToBuffer
+module.exports.transformTagsToBufferSync = nativeBinding.transformTagsToBufferSync
+module.exports.undoLastWrite = nativeBinding.undoLastWrite
+module.exports.undoLastWriteSync = nativeBinding.undoLastWriteSync
+module.exports.updateTags = nativeBinding.updateTags
+module.exports.updateTagsSync = nativeBinding.updateTagsSync
+module.exports.updateTagsToBuffer = nativeBinding.updateTagsToBuffer
+module.exports.updateTagsToBufferSync = nativeBinding.updateTagsToBufferSync
+module.exports.validateTags = nativeBinding.validateTags
+module.exports.validateTagsFromBuffer = nativeBinding.validateTagsFromBuffer
+module.exports.validateTagsFromBufferSync = nativeBinding.validateTagsFromBufferSync
+module.exports.validateTagsSync = nativeBinding.validateTagsSync
+module.exports.watchDirectory = nativeBinding.watchDirectory
+module.exports.WatchEventKind = nativeBinding.WatchEventKind
+module.exports.ApiWatchEventKind = nativeBinding.ApiWatchEventKind
+module.exports.writeChapters = nativeBinding.writeChapters
+module.exports.writeChaptersSync = nativeBinding.writeChaptersSync
+module.exports.writeChaptersToBuffer = nativeBinding.writeChaptersToBuffer
+module.exports.writeChaptersToBufferSync = nativeBinding.writeChaptersToBufferSync
+module.exports.writeCoverImageFromPath = nativeBinding.writeCoverImageFromPath
+module.exports.writeCoverImageFromPathSync = nativeBinding.writeCoverImageFromPathSync
 module.exports.writeCoverImageToBuffer = nativeBinding.writeCoverImageToBuffer
+module.exports.writeCoverImageToBufferSync = nativeBinding.writeCoverImageToBufferSync
 module.exports.writeCoverImageToFile = nativeBinding.writeCoverImageToFile
+module.exports.writeCoverImageToFileSync = nativeBinding.writeCoverImageToFileSync
+module.exports.writeIxml = nativeBinding.writeIxml
+module.exports.writeIxmlSync = nativeBinding.writeIxmlSync
+module.exports.writeIxmlToBuffer = nativeBinding.writeIxmlToBuffer
+module.exports.writeIxmlToBufferSync = nativeBinding.writeIxmlToBufferSync
+module.exports.writeLyrics = nativeBinding.writeLyrics
+module.exports.writeLyricsSync = nativeBinding.writeLyricsSync
+module.exports.writeLyricsToBuffer = nativeBinding.writeLyricsToBuffer
+module.exports.writeLyricsToBufferSync = nativeBinding.writeLyricsToBufferSync
+module.exports.writeRawTagItems = nativeBinding.writeRawTagItems
+module.exports.writeRawTagItemsSync = nativeBinding.writeRawTagItemsSync
+module.exports.writeRawTagItemsToBuffer = nativeBinding.writeRawTagItemsToBuffer
+module.exports.writeRawTagItemsToBufferSync = nativeBinding.writeRawTagItemsToBufferSync
+module.exports.writeSyncedLyrics = nativeBinding.writeSyncedLyrics
+module.exports.writeSyncedLyricsSync = nativeBinding.writeSyncedLyricsSync
+module.exports.writeSyncedLyricsToBuffer = nativeBinding.writeSyncedLyricsToBuffer
+module.exports.writeSyncedLyricsToBufferSync = nativeBinding.writeSyncedLyricsToBufferSync
 module.exports.writeTags = nativeBinding.writeTags
+module.exports.writeTagsSync = nativeBinding.writeTagsSync
 module.exports.writeTagsToBuffer = nativeBinding.writeTagsToBuffer
+module.exports.writeTagsToBufferSync = nativeBinding.writeTagsToBufferSync
+module.exports.writeTagsTransaction = nativeBinding.writeTagsTransaction
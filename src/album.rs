@@ -0,0 +1,223 @@
+use crate::batch;
+use crate::transaction::{self, TagWriteEntry};
+use crate::util::{self, AudioTags, Position};
+use std::path::PathBuf;
+
+// The fields shared by every track of an album
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum AlbumField {
+  Album,
+  AlbumArtists,
+  Year,
+  Genre,
+  DiscTotal,
+  Cover,
+}
+
+// the most common value, ties going to the value seen first
+fn consensus<T: PartialEq>(values: impl Iterator<Item = Option<T>>) -> Option<T> {
+  let mut counts: Vec<(T, usize)> = Vec::new();
+  for value in values.flatten() {
+    match counts.iter_mut().find(|(counted, _)| *counted == value) {
+      Some((_, count)) => *count += 1,
+      None => counts.push((value, 1)),
+    }
+  }
+  counts
+    .into_iter()
+    .rev()
+    .max_by_key(|(_, count)| *count)
+    .map(|(value, _)| value)
+}
+
+/**
+ * Pick the album-level values of a set of tags, by consensus when there are
+ * several. Fields without any value are left out.
+ * @param tags - The tags of the tracks, or only those of the reference track
+ * @param fields - The fields to pick
+ */
+fn album_tags(tags: &[AudioTags], fields: &[AlbumField]) -> AudioTags {
+  let non_empty = |value: &Option<String>| value.clone().filter(|value| !value.is_empty());
+  let mut album = AudioTags::default();
+  for field in fields {
+    match field {
+      AlbumField::Album => album.album = consensus(tags.iter().map(|t| non_empty(&t.album))),
+      AlbumField::AlbumArtists => {
+        album.album_artists = consensus(tags.iter().map(|t| {
+          t.album_artists
+            .clone()
+            .filter(|album_artists| !album_artists.is_empty())
+        }))
+      }
+      AlbumField::Year => album.year = consensus(tags.iter().map(|t| t.year)),
+      AlbumField::Genre => album.genre = consensus(tags.iter().map(|t| non_empty(&t.genre))),
+      AlbumField::DiscTotal => {
+        album.disc = consensus(
+          tags
+            .iter()
+            .map(|t| t.disc.as_ref().and_then(|disc| disc.of)),
+        )
+        .map(|of| Position {
+          no: None,
+          of: Some(of),
+        })
+      }
+      AlbumField::Cover => album.image = consensus(tags.iter().map(|t| t.image.clone())),
+    }
+  }
+  album
+}
+
+// the errors name the file that failed
+async fn read_all(paths: Vec<PathBuf>) -> Result<Vec<AudioTags>, String> {
+  batch::map_concurrent(paths, batch::default_concurrency(), util::read_tags)
+    .await
+    .into_iter()
+    .collect()
+}
+
+/**
+ * Write the album-level fields to every file of an album. The values come from
+ * the reference file when given, otherwise from the most common value among the
+ * files. The files are written as one transaction, so either all or none of
+ * them are updated.
+ * @param paths - The tracks of the album
+ * @param fields - The fields to propagate
+ * @param reference - The file to take the values from, None for the consensus
+ * @returns The values written; fields no file has a value for are left untouched
+ */
+pub async fn propagate_album_fields(
+  paths: Vec<PathBuf>,
+  fields: Vec<AlbumField>,
+  reference: Option<PathBuf>,
+) -> Result<AudioTags, String> {
+  let source = match reference {
+    Some(reference) => vec![util::read_tags(reference).await?],
+    None => read_all(paths.clone()).await?,
+  };
+  let album = album_tags(&source, &fields);
+  if album == AudioTags::default() {
+    return Ok(album);
+  }
+  let entries = paths
+    .into_iter()
+    .map(|file_path| TagWriteEntry {
+      file_path,
+      tags: album.clone(),
+    })
+    .collect();
+  transaction::write_tags_transaction(entries).await?;
+  Ok(album)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_support::{sample_mp3, write_fixture};
+  use crate::util::{AudioImageType, Image};
+  use std::path::Path;
+
+  const ALL_FIELDS: [AlbumField; 6] = [
+    AlbumField::Album,
+    AlbumField::AlbumArtists,
+    AlbumField::Year,
+    AlbumField::Genre,
+    AlbumField::DiscTotal,
+    AlbumField::Cover,
+  ];
+
+  async fn track(dir: &Path, name: &str, tags: AudioTags) -> String {
+    let buffer = util::write_tags_to_buffer(sample_mp3(), tags)
+      .await
+      .unwrap();
+    write_fixture(dir, name, &buffer)
+  }
+
+  fn tagged(album: &str, year: Option<u32>, title: &str) -> AudioTags {
+    AudioTags {
+      title: Some(title.to_string()),
+      album: Some(album.to_string()),
+      year,
+      ..Default::default()
+    }
+  }
+
+  #[tokio::test]
+  async fn test_propagate_album_fields_by_consensus() {
+    let dir = tempfile::tempdir().unwrap();
+    let cover = Image {
+      data: vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10],
+      pic_type: AudioImageType::CoverFront,
+      ..Default::default()
+    };
+    let paths = vec![
+      track(
+        dir.path(),
+        "01.mp3",
+        AudioTags {
+          image: Some(cover.clone()),
+          ..tagged("Album", Some(2020), "One")
+        },
+      )
+      .await,
+      track(dir.path(), "02.mp3", tagged("Album", Some(2020), "Two")).await,
+      track(dir.path(), "03.mp3", tagged("Albun", None, "Three")).await,
+    ];
+
+    let album = propagate_album_fields(
+      paths.iter().map(PathBuf::from).collect(),
+      ALL_FIELDS.to_vec(),
+      None,
+    )
+    .await
+    .unwrap();
+    assert_eq!(album.album, Some("Album".to_string()));
+    assert_eq!(album.year, Some(2020));
+    assert_eq!(album.genre, None);
+
+    for (path, title) in paths.into_iter().zip(["One", "Two", "Three"]) {
+      let tags = util::read_tags(path).await.unwrap();
+      assert_eq!(tags.title, Some(title.to_string()));
+      assert_eq!(tags.album, Some("Album".to_string()));
+      assert_eq!(tags.year, Some(2020));
+      assert_eq!(tags.image.unwrap().data, cover.data);
+    }
+  }
+
+  #[tokio::test]
+  async fn test_propagate_album_fields_from_reference() {
+    let dir = tempfile::tempdir().unwrap();
+    let first = track(dir.path(), "01.mp3", tagged("Album", Some(2020), "One")).await;
+    let second = track(dir.path(), "02.mp3", tagged("Album", Some(2020), "Two")).await;
+    let reference = track(dir.path(), "03.mp3", tagged("Other", None, "Three")).await;
+
+    propagate_album_fields(
+      vec![first.clone().into(), second.into()],
+      vec![AlbumField::Album, AlbumField::Year],
+      Some(reference.into()),
+    )
+    .await
+    .unwrap();
+    let tags = util::read_tags(first).await.unwrap();
+    assert_eq!(tags.album, Some("Other".to_string()));
+    // the reference has no year, so the files keep theirs
+    assert_eq!(tags.year, Some(2020));
+  }
+
+  #[tokio::test]
+  async fn test_propagate_album_fields_fails_without_writing() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = track(dir.path(), "01.mp3", tagged("Album", None, "One")).await;
+    let before = std::fs::read(&path).unwrap();
+    let broken = write_fixture(dir.path(), "02.mp3", b"not audio");
+
+    let result = propagate_album_fields(
+      vec![path.clone().into(), broken.clone().into()],
+      vec![AlbumField::Album],
+      None,
+    )
+    .await;
+    assert!(result.unwrap_err().starts_with(&broken));
+    assert_eq!(std::fs::read(&path).unwrap(), before);
+  }
+}
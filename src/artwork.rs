@@ -0,0 +1,803 @@
+use crate::batch;
+use crate::transaction::sibling_path;
+use crate::util::{self, AudioImageType, Image, ReadTagsOptions};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use image::{DynamicImage, ImageDecoder, ImageReader};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_THUMBNAIL_SIZE: u32 = 256;
+const THUMBNAIL_QUALITY: u8 = 85;
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ArtworkCacheEntry {
+  // sha256 of the embedded image, shared by files with the same artwork
+  pub hash: Option<String>,
+  pub thumbnail_path: Option<String>,
+  pub error: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ImageInfo {
+  pub pic_type: AudioImageType,
+  pub mime_type: Option<String>,
+  pub description: Option<String>,
+  // size of the encoded image in bytes
+  pub size: usize,
+  // None when the image header can't be parsed
+  pub width: Option<u32>,
+  pub height: Option<u32>,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct ImageHeader {
+  pub width: u32,
+  pub height: u32,
+  pub has_alpha: bool,
+}
+
+/**
+ * Parse the header of an image, the pixels are not decoded
+ * @param data - The encoded image
+ * @returns None for formats this build can't read
+ */
+pub fn image_header(data: &[u8]) -> Option<ImageHeader> {
+  let decoder = ImageReader::new(Cursor::new(data))
+    .with_guessed_format()
+    .ok()?
+    .into_decoder()
+    .ok()?;
+  let (width, height) = decoder.dimensions();
+  Some(ImageHeader {
+    width,
+    height,
+    has_alpha: decoder.color_type().has_alpha(),
+  })
+}
+
+// the dimensions in the image header, the pixels are not decoded
+fn image_dimensions(data: &[u8]) -> Option<(u32, u32)> {
+  image_header(data).map(|header| (header.width, header.height))
+}
+
+// Limits a picture must meet to be written. Whatever the limits, data that
+// isn't an image is rejected.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ImageLimits {
+  pub max_bytes: Option<usize>,
+  pub max_width: Option<u32>,
+  pub max_height: Option<u32>,
+}
+
+// Why a picture was not written
+#[derive(Debug, PartialEq, Clone)]
+pub enum ImageRejection {
+  NotAnImage,
+  TooLarge { size: usize, max_bytes: usize },
+  // the header can't be parsed, so the dimensions can't be checked
+  UnknownDimensions,
+  TooWide { width: u32, max_width: u32 },
+  TooHigh { height: u32, max_height: u32 },
+}
+
+impl fmt::Display for ImageRejection {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::NotAnImage => write!(f, "Invalid image: not an image"),
+      Self::TooLarge { size, max_bytes } => write!(
+        f,
+        "Invalid image: {} bytes, over the limit of {}",
+        size, max_bytes
+      ),
+      Self::UnknownDimensions => write!(f, "Invalid image: unknown dimensions"),
+      Self::TooWide { width, max_width } => write!(
+        f,
+        "Invalid image: {} pixels wide, over the limit of {}",
+        width, max_width
+      ),
+      Self::TooHigh { height, max_height } => write!(
+        f,
+        "Invalid image: {} pixels high, over the limit of {}",
+        height, max_height
+      ),
+    }
+  }
+}
+
+/**
+ * Check a picture against limits before it is written
+ * @param data - The encoded image
+ * @param limits - The limits
+ */
+pub fn validate_image(data: &[u8], limits: &ImageLimits) -> Result<(), ImageRejection> {
+  if !infer::get(data).is_some_and(|kind| kind.matcher_type() == infer::MatcherType::Image) {
+    return Err(ImageRejection::NotAnImage);
+  }
+  if let Some(max_bytes) = limits.max_bytes.filter(|max_bytes| data.len() > *max_bytes) {
+    return Err(ImageRejection::TooLarge {
+      size: data.len(),
+      max_bytes,
+    });
+  }
+  if limits.max_width.is_none() && limits.max_height.is_none() {
+    return Ok(());
+  }
+  let (width, height) = image_dimensions(data).ok_or(ImageRejection::UnknownDimensions)?;
+  if let Some(max_width) = limits.max_width.filter(|max_width| width > *max_width) {
+    return Err(ImageRejection::TooWide { width, max_width });
+  }
+  if let Some(max_height) = limits.max_height.filter(|max_height| height > *max_height) {
+    return Err(ImageRejection::TooHigh { height, max_height });
+  }
+  Ok(())
+}
+
+impl ImageInfo {
+  // the dimensions are those parsed when the picture was read
+  pub fn from_image(image: &Image) -> Self {
+    Self {
+      pic_type: image.pic_type,
+      mime_type: image.mime_type.clone(),
+      description: image.description.clone(),
+      size: image.data.len(),
+      width: image.width,
+      height: image.height,
+    }
+  }
+}
+
+fn images_info(images: Option<Vec<Image>>) -> Vec<ImageInfo> {
+  images
+    .unwrap_or_default()
+    .iter()
+    .map(ImageInfo::from_image)
+    .collect()
+}
+
+// the pictures in tag order
+fn tag_order() -> ReadTagsOptions {
+  ReadTagsOptions {
+    preserve_picture_order: true,
+    ..Default::default()
+  }
+}
+
+/**
+ * Describe every picture embedded in a file: type, mime type, description, size
+ * and the dimensions read from the image header
+ * @param file_path - The path to the audio file
+ * @returns One entry per picture, in tag order
+ */
+pub async fn read_image_info(file_path: impl AsRef<Path>) -> Result<Vec<ImageInfo>, String> {
+  let outcome = util::read_tags_with_options(file_path, tag_order()).await?;
+  Ok(images_info(outcome.tags.all_images))
+}
+
+pub async fn read_image_info_from_buffer(buffer: Vec<u8>) -> Result<Vec<ImageInfo>, String> {
+  let outcome = util::read_tags_from_buffer_with_options(buffer, tag_order()).await?;
+  Ok(images_info(outcome.tags.all_images))
+}
+
+// The picture types most likely to show the album, best first: the front
+// cover, pictures of no particular type (often a cover written by a tool that
+// doesn't set one), then the other sleeve pictures. Types missing here rank
+// after these, and the file icons last.
+pub const ARTWORK_PRIORITY: [AudioImageType; 7] = [
+  AudioImageType::CoverFront,
+  AudioImageType::Other,
+  AudioImageType::Media,
+  AudioImageType::Illustration,
+  AudioImageType::CoverBack,
+  AudioImageType::Leaflet,
+  AudioImageType::BandLogo,
+];
+
+fn artwork_rank(pic_type: AudioImageType) -> usize {
+  match pic_type {
+    AudioImageType::OtherIcon => ARTWORK_PRIORITY.len() + 1,
+    AudioImageType::Icon => ARTWORK_PRIORITY.len() + 2,
+    pic_type => ARTWORK_PRIORITY
+      .iter()
+      .position(|priority| *priority == pic_type)
+      .unwrap_or(ARTWORK_PRIORITY.len()),
+  }
+}
+
+// the best ranked picture, the first in tag order among equals
+fn primary_artwork(images: Option<Vec<Image>>) -> Option<Image> {
+  images
+    .unwrap_or_default()
+    .into_iter()
+    .enumerate()
+    .min_by_key(|(index, image)| (artwork_rank(image.pic_type), *index))
+    .map(|(_, image)| image)
+}
+
+/**
+ * Read the picture that best represents a file's artwork, whatever its type,
+ * see `ARTWORK_PRIORITY`
+ * @param file_path - The path to the audio file
+ * @returns The picture, none when the file has no pictures
+ */
+pub async fn read_primary_artwork(file_path: impl AsRef<Path>) -> Result<Option<Image>, String> {
+  let outcome = util::read_tags_with_options(file_path, tag_order()).await?;
+  Ok(primary_artwork(outcome.tags.all_images))
+}
+
+pub async fn read_primary_artwork_from_buffer(buffer: Vec<u8>) -> Result<Option<Image>, String> {
+  let outcome = util::read_tags_from_buffer_with_options(buffer, tag_order()).await?;
+  Ok(primary_artwork(outcome.tags.all_images))
+}
+
+/**
+ * A picture as a data URL, e.g. "data:image/jpeg;base64,/9j/4AAQ...", which
+ * webviews take as the source of an image as is
+ * @param image - The picture, its mime type sniffed from the data when missing
+ */
+pub fn data_url(image: &Image) -> String {
+  let mime_type = image
+    .mime_type
+    .clone()
+    .or_else(|| infer::get(&image.data).map(|kind| kind.mime_type().to_string()))
+    .unwrap_or_else(|| "application/octet-stream".to_string());
+  format!("data:{};base64,{}", mime_type, STANDARD.encode(&image.data))
+}
+
+/**
+ * Read the front cover of a file as a data URL, see `data_url`
+ * @param file_path - The path to the audio file
+ * @returns None when the file has no front cover
+ */
+pub async fn read_cover_image_data_url(
+  file_path: impl AsRef<Path>,
+) -> Result<Option<String>, String> {
+  let tags = util::read_tags(file_path).await?;
+  Ok(tags.image.as_ref().map(data_url))
+}
+
+pub async fn read_cover_image_data_url_from_buffer(
+  buffer: Vec<u8>,
+) -> Result<Option<String>, String> {
+  let tags = util::read_tags_from_buffer(buffer).await?;
+  Ok(tags.image.as_ref().map(data_url))
+}
+
+pub(crate) fn sha256_hex(data: &[u8]) -> String {
+  Sha256::digest(data)
+    .iter()
+    .map(|byte| format!("{:02x}", byte))
+    .collect()
+}
+
+// The format a resized picture is encoded in
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum ResizeFormat {
+  #[default]
+  Jpeg,
+  Png,
+}
+
+impl ResizeFormat {
+  pub fn mime_type(self) -> &'static str {
+    match self {
+      Self::Jpeg => "image/jpeg",
+      Self::Png => "image/png",
+    }
+  }
+}
+
+// How oversized pictures are downscaled before they are written
+#[derive(Debug, PartialEq, Clone)]
+pub struct ResizeOptions {
+  // the maximum width and height
+  pub max_edge: u32,
+  pub format: ResizeFormat,
+  // JPEG quality from 1 to 100
+  pub quality: u8,
+}
+
+impl Default for ResizeOptions {
+  fn default() -> Self {
+    Self {
+      max_edge: 1000,
+      format: ResizeFormat::Jpeg,
+      quality: THUMBNAIL_QUALITY,
+    }
+  }
+}
+
+fn decode(data: &[u8]) -> Result<DynamicImage, String> {
+  image::load_from_memory(data).map_err(|e| format!("Failed to decode image: {}", e))
+}
+
+// downsized so that neither side exceeds max_edge, never upscaled
+fn fit(image: DynamicImage, max_edge: u32) -> DynamicImage {
+  if image.width() > max_edge || image.height() > max_edge {
+    image.thumbnail(max_edge, max_edge)
+  } else {
+    image
+  }
+}
+
+fn encode(image: &DynamicImage, format: ResizeFormat, quality: u8) -> Result<Vec<u8>, String> {
+  let mut out = Cursor::new(Vec::new());
+  match format {
+    ResizeFormat::Jpeg => {
+      JpegEncoder::new_with_quality(&mut out, quality.clamp(1, 100)).encode_image(&image.to_rgb8())
+    }
+    ResizeFormat::Png => image.write_with_encoder(PngEncoder::new(&mut out)),
+  }
+  .map_err(|e| format!("Failed to encode image: {}", e))?;
+  Ok(out.into_inner())
+}
+
+/**
+ * Downsize an image so that neither side exceeds `max_edge` and encode it as JPEG
+ * @param data - The encoded source image
+ * @param max_edge - The maximum width and height of the thumbnail
+ */
+pub fn thumbnail_jpeg(data: &[u8], max_edge: u32) -> Result<Vec<u8>, String> {
+  encode(
+    &fit(decode(data)?, max_edge),
+    ResizeFormat::Jpeg,
+    THUMBNAIL_QUALITY,
+  )
+}
+
+/**
+ * Downscale and re-encode a picture when a side exceeds `options.max_edge`
+ * @param data - The encoded image
+ * @param options - The size and encoding of the result
+ * @returns The re-encoded picture, none when it already fits
+ */
+pub fn resize_image(data: &[u8], options: &ResizeOptions) -> Result<Option<Vec<u8>>, String> {
+  // the header is enough to tell whether the picture fits
+  if let Some((width, height)) = image_dimensions(data) {
+    if width <= options.max_edge && height <= options.max_edge {
+      return Ok(None);
+    }
+  }
+  let image = decode(data)?;
+  if image.width() <= options.max_edge && image.height() <= options.max_edge {
+    return Ok(None);
+  }
+  encode(
+    &fit(image, options.max_edge),
+    options.format,
+    options.quality,
+  )
+  .map(Some)
+}
+
+async fn cache_artwork(file_path: PathBuf, cache_dir: PathBuf, size: u32) -> ArtworkCacheEntry {
+  let data = match util::read_cover_image_from_file(file_path).await {
+    Ok(Some(data)) => data,
+    Ok(None) => return ArtworkCacheEntry::default(),
+    Err(e) => {
+      return ArtworkCacheEntry {
+        error: Some(e),
+        ..Default::default()
+      }
+    }
+  };
+  let hash = sha256_hex(&data);
+  let target = cache_dir.join(format!("{}-{}.jpg", hash, size));
+  if !target.exists() {
+    let result = tokio::task::spawn_blocking(move || thumbnail_jpeg(&data, size))
+      .await
+      .map_err(|e| format!("Thumbnail task failed: {}", e))
+      .and_then(|thumbnail| thumbnail)
+      .and_then(|thumbnail| write_atomically(&target, &thumbnail));
+    if let Err(e) = result {
+      return ArtworkCacheEntry {
+        hash: Some(hash),
+        thumbnail_path: None,
+        error: Some(e),
+      };
+    }
+  }
+  ArtworkCacheEntry {
+    hash: Some(hash),
+    thumbnail_path: Some(target.to_string_lossy().to_string()),
+    error: None,
+  }
+}
+
+// files sharing artwork may produce the same thumbnail concurrently, so each
+// writer goes through its own temp file and readers never see a partial image
+fn write_atomically(target: &Path, data: &[u8]) -> Result<(), String> {
+  let temp = sibling_path(target, "tagpilot-tmp");
+  fs::write(&temp, data)
+    .and_then(|_| fs::rename(&temp, target))
+    .map_err(|e| {
+      let _ = fs::remove_file(&temp);
+      format!("Failed to write thumbnail {}: {}", target.display(), e)
+    })
+}
+
+/**
+ * Make a JPEG thumbnail of the front cover of a file, e.g. for a grid view that
+ * shouldn't decode full size covers
+ * @param file_path - The path to the audio file
+ * @param max_edge - The maximum width and height of the thumbnail
+ * @returns The thumbnail, none when the file has no front cover
+ */
+pub async fn read_cover_thumbnail(
+  file_path: impl AsRef<Path>,
+  max_edge: u32,
+) -> Result<Option<Vec<u8>>, String> {
+  if max_edge == 0 {
+    return Err("Thumbnail size must be greater than zero".to_string());
+  }
+  match util::read_cover_image_from_file(file_path).await? {
+    Some(data) => thumbnail_jpeg(&data, max_edge).map(Some),
+    None => Ok(None),
+  }
+}
+
+/**
+ * Extract the cover of every file into `cache_dir` as a JPEG thumbnail. Thumbnails
+ * are named after the hash of the embedded image, so files sharing artwork share
+ * one thumbnail and thumbnails already in the cache are reused.
+ * @param paths - The audio files
+ * @param cache_dir - The directory holding the thumbnails, created when missing
+ * @param size - The maximum width and height of the thumbnails
+ * @returns One entry per file path, as a string; files without a cover get an
+ * empty entry
+ */
+pub async fn build_artwork_cache(
+  paths: Vec<PathBuf>,
+  cache_dir: impl AsRef<Path>,
+  size: u32,
+) -> Result<HashMap<String, ArtworkCacheEntry>, String> {
+  if size == 0 {
+    return Err("Thumbnail size must be greater than zero".to_string());
+  }
+  let cache_dir = cache_dir.as_ref().to_path_buf();
+  fs::create_dir_all(&cache_dir).map_err(|e| {
+    format!(
+      "Failed to create cache directory {}: {}",
+      cache_dir.display(),
+      e
+    )
+  })?;
+  let entries = batch::map_concurrent(
+    paths.clone(),
+    batch::default_concurrency(),
+    move |file_path| cache_artwork(file_path, cache_dir.clone(), size),
+  )
+  .await;
+  Ok(
+    paths
+      .into_iter()
+      .map(|path| path.to_string_lossy().into_owned())
+      .zip(entries)
+      .collect(),
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_support::{sample_mp3, write_fixture};
+
+  fn png(width: u32, height: u32, shade: u8) -> Vec<u8> {
+    let image = image::RgbImage::from_pixel(width, height, image::Rgb([shade, 0, 255 - shade]));
+    let mut out = Cursor::new(Vec::new());
+    image.write_to(&mut out, image::ImageFormat::Png).unwrap();
+    out.into_inner()
+  }
+
+  async fn mp3_with_cover(dir: &Path, name: &str, cover: Option<Vec<u8>>) -> String {
+    let mut buffer = sample_mp3();
+    if let Some(cover) = cover {
+      buffer = util::write_cover_image_to_buffer(buffer, cover)
+        .await
+        .unwrap();
+    }
+    write_fixture(dir, name, &buffer)
+  }
+
+  #[tokio::test]
+  async fn test_read_cover_thumbnail() {
+    let dir = tempfile::tempdir().unwrap();
+    let cover = mp3_with_cover(dir.path(), "01.mp3", Some(png(300, 150, 20))).await;
+    let bare = mp3_with_cover(dir.path(), "02.mp3", None).await;
+
+    let thumbnail = read_cover_thumbnail(cover.clone(), 64)
+      .await
+      .unwrap()
+      .unwrap();
+    assert_eq!(
+      image::guess_format(&thumbnail).unwrap(),
+      image::ImageFormat::Jpeg
+    );
+    assert_eq!(image_dimensions(&thumbnail), Some((64, 32)));
+    assert_eq!(read_cover_thumbnail(bare, 64).await.unwrap(), None);
+    assert!(read_cover_thumbnail(cover, 0).await.is_err());
+  }
+
+  #[tokio::test]
+  async fn test_read_cover_image_data_url() {
+    let dir = tempfile::tempdir().unwrap();
+    let cover = png(2, 2, 20);
+    let file_path = mp3_with_cover(dir.path(), "01.mp3", Some(cover.clone())).await;
+    let bare = mp3_with_cover(dir.path(), "02.mp3", None).await;
+
+    let url = read_cover_image_data_url(file_path).await.unwrap().unwrap();
+    let encoded = url.strip_prefix("data:image/png;base64,").unwrap();
+    assert_eq!(STANDARD.decode(encoded).unwrap(), cover);
+    assert_eq!(read_cover_image_data_url(bare).await.unwrap(), None);
+
+    let unknown = Image {
+      data: vec![0xFF, 0x00, 0xFE],
+      ..Default::default()
+    };
+    assert_eq!(
+      data_url(&unknown),
+      "data:application/octet-stream;base64,/wD+"
+    );
+  }
+
+  #[tokio::test]
+  async fn test_read_image_header() {
+    let mut rgba = Cursor::new(Vec::new());
+    image::RgbaImage::from_pixel(40, 30, image::Rgba([1, 2, 3, 128]))
+      .write_to(&mut rgba, image::ImageFormat::Png)
+      .unwrap();
+    let buffer = util::write_cover_image_to_buffer(sample_mp3(), rgba.into_inner())
+      .await
+      .unwrap();
+    let image = util::read_tags_from_buffer(buffer)
+      .await
+      .unwrap()
+      .image
+      .unwrap();
+    assert_eq!(
+      (image.width, image.height, image.has_alpha),
+      (Some(40), Some(30), Some(true))
+    );
+
+    let header = image_header(&png(20, 10, 1)).unwrap();
+    assert!(!header.has_alpha);
+    assert_eq!(image_header(b"not an image"), None);
+  }
+
+  #[test]
+  fn test_resize_image() {
+    let options = ResizeOptions {
+      max_edge: 100,
+      format: ResizeFormat::Png,
+      ..Default::default()
+    };
+    assert_eq!(resize_image(&png(100, 50, 1), &options), Ok(None));
+    let resized = resize_image(&png(400, 200, 1), &options).unwrap().unwrap();
+    assert_eq!(image_dimensions(&resized), Some((100, 50)));
+    assert_eq!(
+      image::guess_format(&resized).unwrap(),
+      image::ImageFormat::Png
+    );
+    assert!(resize_image(b"not an image", &options).is_err());
+  }
+
+  #[test]
+  fn test_validate_image() {
+    let limits = ImageLimits::default();
+    assert_eq!(validate_image(&png(10, 10, 1), &limits), Ok(()));
+    assert_eq!(
+      validate_image(b"not an image", &limits),
+      Err(ImageRejection::NotAnImage)
+    );
+
+    let image = png(400, 200, 1);
+    let limits = ImageLimits {
+      max_bytes: Some(10),
+      ..Default::default()
+    };
+    assert_eq!(
+      validate_image(&image, &limits),
+      Err(ImageRejection::TooLarge {
+        size: image.len(),
+        max_bytes: 10
+      })
+    );
+    let limits = ImageLimits {
+      max_width: Some(300),
+      max_height: Some(300),
+      ..Default::default()
+    };
+    assert_eq!(
+      validate_image(&image, &limits),
+      Err(ImageRejection::TooWide {
+        width: 400,
+        max_width: 300
+      })
+    );
+    assert_eq!(validate_image(&png(300, 300, 1), &limits), Ok(()));
+    assert_eq!(
+      ImageRejection::TooHigh {
+        height: 400,
+        max_height: 300
+      }
+      .to_string(),
+      "Invalid image: 400 pixels high, over the limit of 300"
+    );
+  }
+
+  #[tokio::test]
+  async fn test_read_primary_artwork() {
+    let add = |buffer: Vec<u8>, data: Vec<u8>, pic_type: AudioImageType| {
+      util::write_cover_image_to_buffer_with_options(
+        buffer,
+        data,
+        util::CoverImageOptions {
+          pic_type,
+          ..Default::default()
+        },
+      )
+    };
+    let icon = png(32, 32, 1);
+    let other = png(300, 300, 2);
+    let buffer = add(sample_mp3(), icon.clone(), AudioImageType::Icon)
+      .await
+      .unwrap();
+    let primary = read_primary_artwork_from_buffer(buffer.clone())
+      .await
+      .unwrap()
+      .unwrap();
+    assert_eq!(primary.data, icon);
+    assert_eq!(
+      util::read_cover_image_from_buffer(buffer.clone())
+        .await
+        .unwrap(),
+      None
+    );
+
+    let buffer = add(buffer, other.clone(), AudioImageType::Other)
+      .await
+      .unwrap();
+    let primary = read_primary_artwork_from_buffer(buffer)
+      .await
+      .unwrap()
+      .unwrap();
+    assert_eq!(primary.pic_type, AudioImageType::Other);
+    assert_eq!(primary.data, other);
+
+    assert_eq!(
+      read_primary_artwork_from_buffer(sample_mp3())
+        .await
+        .unwrap(),
+      None
+    );
+  }
+
+  #[test]
+  fn test_thumbnail_jpeg_keeps_aspect_ratio() {
+    let thumbnail = thumbnail_jpeg(&png(400, 200, 10), 100).unwrap();
+    let decoded = image::load_from_memory(&thumbnail).unwrap();
+    assert_eq!((decoded.width(), decoded.height()), (100, 50));
+
+    // small images are never upscaled
+    let thumbnail = thumbnail_jpeg(&png(40, 20, 10), 100).unwrap();
+    let decoded = image::load_from_memory(&thumbnail).unwrap();
+    assert_eq!((decoded.width(), decoded.height()), (40, 20));
+  }
+
+  #[tokio::test]
+  async fn test_build_artwork_cache_dedupes_shared_artwork() {
+    let dir = tempfile::tempdir().unwrap();
+    let cache = dir.path().join("cache");
+    let cover = png(300, 300, 20);
+    let first = mp3_with_cover(dir.path(), "01.mp3", Some(cover.clone())).await;
+    let second = mp3_with_cover(dir.path(), "02.mp3", Some(cover)).await;
+    let other = mp3_with_cover(dir.path(), "03.mp3", Some(png(300, 300, 200))).await;
+    let bare = mp3_with_cover(dir.path(), "04.mp3", None).await;
+    let broken = write_fixture(dir.path(), "05.mp3", b"not audio");
+
+    let entries = build_artwork_cache(
+      vec![
+        first.clone().into(),
+        second.clone().into(),
+        other.clone().into(),
+        bare.clone().into(),
+        broken.clone().into(),
+      ],
+      cache.to_string_lossy().to_string(),
+      64,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(entries.len(), 5);
+    assert_eq!(entries[&first], entries[&second]);
+    assert_ne!(entries[&first].hash, entries[&other].hash);
+    assert_eq!(entries[&bare], ArtworkCacheEntry::default());
+    assert!(entries[&broken].error.is_some());
+    assert_eq!(fs::read_dir(&cache).unwrap().count(), 2);
+
+    let thumbnail = entries[&first].thumbnail_path.clone().unwrap();
+    let decoded = image::open(&thumbnail).unwrap();
+    assert_eq!((decoded.width(), decoded.height()), (64, 64));
+  }
+
+  #[tokio::test]
+  async fn test_build_artwork_cache_reuses_existing_thumbnails() {
+    let dir = tempfile::tempdir().unwrap();
+    let cache = dir.path().join("cache").to_string_lossy().to_string();
+    let path = mp3_with_cover(dir.path(), "01.mp3", Some(png(120, 120, 50))).await;
+
+    let entries = build_artwork_cache(vec![path.clone().into()], cache.clone(), 32)
+      .await
+      .unwrap();
+    let thumbnail = entries[&path].thumbnail_path.clone().unwrap();
+    fs::write(&thumbnail, b"cached").unwrap();
+
+    build_artwork_cache(vec![path.clone().into()], cache, 32)
+      .await
+      .unwrap();
+    assert_eq!(fs::read(&thumbnail).unwrap(), b"cached");
+  }
+
+  #[tokio::test]
+  async fn test_read_image_info() {
+    let dir = tempfile::tempdir().unwrap();
+    let cover = png(120, 80, 50);
+    let back = Image {
+      data: vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10],
+      pic_type: AudioImageType::CoverBack,
+      description: Some("Back".to_string()),
+      ..Default::default()
+    };
+    let tags = util::AudioTags {
+      all_images: Some(vec![
+        Image {
+          data: cover.clone(),
+          pic_type: AudioImageType::CoverFront,
+          ..Default::default()
+        },
+        back.clone(),
+      ]),
+      ..Default::default()
+    };
+    let buffer = util::write_tags_to_buffer(sample_mp3(), tags)
+      .await
+      .unwrap();
+    let path = write_fixture(dir.path(), "song.mp3", &buffer);
+
+    let info = read_image_info(path).await.unwrap();
+    assert_eq!(
+      info,
+      vec![
+        ImageInfo {
+          pic_type: AudioImageType::CoverFront,
+          mime_type: Some("image/png".to_string()),
+          description: None,
+          size: cover.len(),
+          width: Some(120),
+          height: Some(80),
+        },
+        // a truncated JPEG header has no dimensions
+        ImageInfo {
+          pic_type: AudioImageType::CoverBack,
+          mime_type: Some("image/jpeg".to_string()),
+          description: Some("Back".to_string()),
+          size: back.data.len(),
+          width: None,
+          height: None,
+        },
+      ]
+    );
+    assert_eq!(read_image_info_from_buffer(buffer).await.unwrap(), info);
+    assert!(read_image_info_from_buffer(sample_mp3())
+      .await
+      .unwrap()
+      .is_empty());
+  }
+}
@@ -0,0 +1,154 @@
+use crate::transaction::sibling_path;
+use crate::util::io_error;
+use std::fs::{self, File, FileTimes, OpenOptions};
+use std::path::{Path, PathBuf};
+
+const TEMP_EXTENSION: &str = "tagpilot-tmp";
+
+// A file opened to be written: the file itself, or with `atomic` a copy next
+// to it that replaces it on commit, so a crash mid-save leaves the original
+// intact. Dropped without a commit, the copy is removed.
+pub struct WriteTarget {
+  file: File,
+  // the file written, symlinks resolved when atomic
+  path: PathBuf,
+  temp: Option<PathBuf>,
+  // the access and modification times to restore on commit
+  times: Option<FileTimes>,
+}
+
+impl WriteTarget {
+  /**
+   * Open a file for writing
+   * @param path - The file
+   * @param atomic - Write to a copy in the same directory, renamed over the
+   * file on commit. The copy keeps the file's permissions.
+   * @param preserve_mtime - Restore the file's access and modification times
+   * on commit
+   */
+  pub fn open(path: &Path, atomic: bool, preserve_mtime: bool) -> Result<Self, String> {
+    let open_error =
+      |e: std::io::Error| io_error(&format!("Failed to open file {}", path.display()), &e);
+    let times = if preserve_mtime {
+      let metadata = fs::metadata(path).map_err(open_error)?;
+      Some(
+        FileTimes::new()
+          .set_accessed(metadata.accessed().map_err(open_error)?)
+          .set_modified(metadata.modified().map_err(open_error)?),
+      )
+    } else {
+      None
+    };
+    let open = |path: &Path| OpenOptions::new().read(true).write(true).open(path);
+    if !atomic {
+      return Ok(Self {
+        file: open(path).map_err(open_error)?,
+        path: path.to_path_buf(),
+        temp: None,
+        times,
+      });
+    }
+    // renaming over a symlink would replace the link, not the file
+    let path = fs::canonicalize(path).map_err(open_error)?;
+    let temp = sibling_path(&path, TEMP_EXTENSION);
+    // copies the permissions along
+    fs::copy(&path, &temp).map_err(open_error)?;
+    match open(&temp) {
+      Ok(file) => Ok(Self {
+        file,
+        path,
+        temp: Some(temp),
+        times,
+      }),
+      Err(e) => {
+        let _ = fs::remove_file(&temp);
+        Err(open_error(e))
+      }
+    }
+  }
+
+  pub fn file(&mut self) -> &mut File {
+    &mut self.file
+  }
+
+  /**
+   * Keep what was written: restore the times of the file, and put the copy in
+   * place of the file when atomic
+   */
+  pub fn commit(mut self) -> Result<(), String> {
+    let commit_error = |e: std::io::Error| format!("Failed to save file: {}", e);
+    if let Some(times) = self.times {
+      self.file.set_times(times).map_err(commit_error)?;
+    }
+    if let Some(temp) = self.temp.take() {
+      self.file.sync_all().map_err(commit_error)?;
+      if let Err(e) = fs::rename(&temp, &self.path) {
+        let _ = fs::remove_file(&temp);
+        return Err(commit_error(e));
+      }
+    }
+    Ok(())
+  }
+}
+
+impl Drop for WriteTarget {
+  fn drop(&mut self) {
+    if let Some(temp) = self.temp.take() {
+      let _ = fs::remove_file(temp);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::io::Write;
+  use std::time::{Duration, SystemTime};
+
+  #[test]
+  fn test_atomic_write() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("song.mp3");
+    fs::write(&path, b"original").unwrap();
+    let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+    File::options()
+      .write(true)
+      .open(&path)
+      .unwrap()
+      .set_modified(modified)
+      .unwrap();
+
+    // dropped without commit
+    let mut target = WriteTarget::open(&path, true, true).unwrap();
+    target.file().write_all(b"changed").unwrap();
+    drop(target);
+    assert_eq!(fs::read(&path).unwrap(), b"original");
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+
+    let mut target = WriteTarget::open(&path, true, true).unwrap();
+    target.file().write_all(b"replaced").unwrap();
+    assert_eq!(fs::read(&path).unwrap(), b"original");
+    target.commit().unwrap();
+    assert_eq!(fs::read(&path).unwrap(), b"replaced");
+    assert_eq!(fs::metadata(&path).unwrap().modified().unwrap(), modified);
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+  }
+
+  #[test]
+  fn test_concurrent_atomic_writes_use_their_own_copies() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("song.mp3");
+    fs::write(&path, b"original").unwrap();
+
+    let mut first = WriteTarget::open(&path, true, false).unwrap();
+    let mut second = WriteTarget::open(&path, true, false).unwrap();
+    assert_ne!(first.temp, second.temp);
+    first.file().write_all(b"first   ").unwrap();
+    second.file().write_all(b"second  ").unwrap();
+    first.commit().unwrap();
+    assert_eq!(fs::read(&path).unwrap(), b"first   ");
+    second.commit().unwrap();
+    assert_eq!(fs::read(&path).unwrap(), b"second  ");
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+  }
+}
@@ -0,0 +1,295 @@
+use crate::dsd;
+use crate::probe::{id3v2_size, trailing_tag_size};
+use crate::riff;
+use crate::util::{in_file, io_error, open_file};
+use lofty::file::FileType;
+use lofty::probe::Probe;
+use sha2::{Digest, Sha256};
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::ops::Range;
+use std::path::Path;
+
+fn read_array<R: Read, const N: usize>(reader: &mut R) -> Result<[u8; N], String> {
+  let mut bytes = [0; N];
+  reader
+    .read_exact(&mut bytes)
+    .map_err(|e| io_error("Failed to read audio file", &e))?;
+  Ok(bytes)
+}
+
+// the audio frames of an MPEG, AAC, APE, WavPack or Musepack file: everything
+// between a leading ID3v2 tag and the APE and ID3v1 tags at the end
+fn framed_audio<R: Read + Seek>(reader: &mut R, len: u64) -> Result<Range<u64>, String> {
+  let mut head = [0u8; 10];
+  reader
+    .rewind()
+    .map_err(|e| io_error("Failed to read audio file", &e))?;
+  let start = match reader.read_exact(&mut head) {
+    Ok(()) => id3v2_size(&head).map_or(0, u64::from),
+    Err(_) => 0,
+  };
+  let end = len.saturating_sub(trailing_tag_size(reader));
+  Ok(start.min(end)..end)
+}
+
+// the FLAC frames, after the marker and the metadata blocks, which hold the
+// tags and the padding
+fn flac_audio<R: Read + Seek>(reader: &mut R, len: u64) -> Result<Range<u64>, String> {
+  let frames = framed_audio(reader, len)?;
+  let mut offset = frames.start + 4;
+  loop {
+    reader
+      .seek(SeekFrom::Start(offset))
+      .map_err(|e| io_error("Failed to read audio file", &e))?;
+    let header: [u8; 4] = read_array(reader)?;
+    offset += 4 + u64::from(u32::from_be_bytes([0, header[1], header[2], header[3]]));
+    if header[0] & 0x80 != 0 {
+      break;
+    }
+  }
+  if offset > frames.end {
+    return Err("Malformed Flac file".to_string());
+  }
+  Ok(offset..frames.end)
+}
+
+// the content of the top level mdat atoms of an MP4 file
+fn mp4_audio<R: Read + Seek>(reader: &mut R, len: u64) -> Result<Vec<Range<u64>>, String> {
+  let mut ranges = Vec::new();
+  let mut offset = 0;
+  while offset + 8 <= len {
+    reader
+      .seek(SeekFrom::Start(offset))
+      .map_err(|e| io_error("Failed to read audio file", &e))?;
+    let header: [u8; 8] = read_array(reader)?;
+    let (header_len, size) = match u32::from_be_bytes([header[0], header[1], header[2], header[3]])
+    {
+      // runs to the end of the file
+      0 => (8, len - offset),
+      1 => (16, u64::from_be_bytes(read_array(reader)?)),
+      size => (8, u64::from(size)),
+    };
+    if size < header_len || offset + size > len {
+      return Err("Invalid MP4 file: an atom doesn't fit".to_string());
+    }
+    if &header[4..] == b"mdat" {
+      ranges.push(offset + header_len..offset + size);
+    }
+    offset += size;
+  }
+  Ok(ranges)
+}
+
+// how many packets of an Ogg stream are headers, from its first packet
+fn ogg_header_packets(first_packet: &[u8]) -> Result<usize, String> {
+  if first_packet.starts_with(b"\x01vorbis") {
+    // identification, comments and setup
+    Ok(3)
+  } else if first_packet.starts_with(b"OpusHead") {
+    Ok(2)
+  } else if first_packet.starts_with(b"Speex   ") && first_packet.len() >= 72 {
+    let extra: [u8; 4] = first_packet[68..72].try_into().unwrap_or_default();
+    Ok(2 + u32::from_le_bytes(extra) as usize)
+  } else {
+    Err("Unsupported Ogg stream".to_string())
+  }
+}
+
+// the payload of the Ogg pages after the header packets. Header packets end
+// on a page of their own, and the pages are numbered and checksummed, so a
+// longer comment header only changes the pages around the payload.
+fn ogg_audio<R: Read + Seek>(reader: &mut R, len: u64) -> Result<Vec<Range<u64>>, String> {
+  let mut ranges = Vec::new();
+  let mut headers_left = None;
+  let mut offset = 0;
+  while offset + 27 <= len {
+    reader
+      .seek(SeekFrom::Start(offset))
+      .map_err(|e| io_error("Failed to read audio file", &e))?;
+    let header: [u8; 27] = read_array(reader)?;
+    if &header[..4] != b"OggS" {
+      return Err("Invalid Ogg file: missing page header".to_string());
+    }
+    let mut lacing = vec![0; usize::from(header[26])];
+    reader
+      .read_exact(&mut lacing)
+      .map_err(|e| io_error("Failed to read audio file", &e))?;
+    let start = offset + 27 + lacing.len() as u64;
+    let end = start + lacing.iter().map(|size| u64::from(*size)).sum::<u64>();
+    if end > len {
+      return Err("Invalid Ogg file: a page doesn't fit".to_string());
+    }
+    let headers = match headers_left {
+      Some(headers) => headers,
+      None => {
+        let mut first_packet = vec![0; (end - start).min(80) as usize];
+        reader
+          .read_exact(&mut first_packet)
+          .map_err(|e| io_error("Failed to read audio file", &e))?;
+        ogg_header_packets(&first_packet)?
+      }
+    };
+    if headers == 0 {
+      ranges.push(start..end);
+    }
+    // a lacing value under 255 ends a packet
+    let ended = lacing.iter().filter(|size| **size < 255).count();
+    headers_left = Some(headers.saturating_sub(ended));
+    offset = end;
+  }
+  Ok(ranges)
+}
+
+// the byte ranges of a file that hold its audio, in file order
+pub(crate) fn audio_ranges<R: Read + Seek>(reader: &mut R) -> Result<Vec<Range<u64>>, String> {
+  let len = reader
+    .seek(SeekFrom::End(0))
+    .map_err(|e| io_error("Failed to read audio file", &e))?;
+  if let Some(format) = dsd::format(reader) {
+    return Ok(vec![dsd::audio_span(reader, format)?]);
+  }
+  reader
+    .rewind()
+    .map_err(|e| io_error("Failed to read audio file", &e))?;
+  let file_type = Probe::new(&mut *reader)
+    .guess_file_type()
+    .map_err(|e| io_error("Failed to read audio file", &e))?
+    .file_type();
+  match file_type {
+    Some(FileType::Mpeg | FileType::Aac | FileType::Ape | FileType::WavPack | FileType::Mpc) => {
+      Ok(vec![framed_audio(reader, len)?])
+    }
+    Some(FileType::Flac) => Ok(vec![flac_audio(reader, len)?]),
+    Some(FileType::Mp4) => mp4_audio(reader, len),
+    Some(FileType::Opus | FileType::Vorbis | FileType::Speex) => ogg_audio(reader, len),
+    Some(FileType::Wav) => riff::chunk_range(reader, b"data")?
+      .map(|range| vec![range])
+      .ok_or_else(|| "Invalid WAV file: no data chunk".to_string()),
+    Some(FileType::Aiff) => riff::chunk_range(reader, b"SSND")?
+      .map(|range| vec![range])
+      .ok_or_else(|| "Invalid AIFF file: no SSND chunk".to_string()),
+    _ => Err("Unsupported format: the audio of this file can't be located".to_string()),
+  }
+}
+
+fn hash_audio<R: Read + Seek>(reader: &mut R) -> Result<String, String> {
+  let mut hasher = Sha256::new();
+  for range in audio_ranges(reader)? {
+    reader
+      .seek(SeekFrom::Start(range.start))
+      .map_err(|e| io_error("Failed to read audio file", &e))?;
+    let mut chunk = reader.by_ref().take(range.end - range.start);
+    std::io::copy(&mut chunk, &mut hasher)
+      .map_err(|e| io_error("Failed to read audio file", &e))?;
+  }
+  Ok(
+    hasher
+      .finalize()
+      .iter()
+      .map(|byte| format!("{:02x}", byte))
+      .collect(),
+  )
+}
+
+/**
+ * Hash the audio of a file, leaving out its tags: the hash stays the same
+ * when the file is retagged and changes when the audio does, which backup and
+ * duplicate detection tools can use as the identity of a recording's file.
+ * Only the bytes of the audio are hashed, without decoding them, so files
+ * encoded twice from the same source hash differently.
+ * @param file_path - The path to the audio file
+ * @returns The hex encoded SHA-256 of the audio
+ */
+pub async fn audio_hash(file_path: impl AsRef<Path>) -> Result<String, String> {
+  let path = file_path.as_ref();
+  let mut file = open_file(path)?;
+  hash_audio(&mut file).map_err(in_file(path))
+}
+
+/**
+ * Hash the audio of a file in memory, see `audio_hash`
+ * @param buffer - The audio file
+ */
+pub async fn audio_hash_from_buffer(buffer: Vec<u8>) -> Result<String, String> {
+  hash_audio(&mut Cursor::new(buffer))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_support::{
+    sample_dff, sample_dsf, sample_m4a, sample_mp3, sample_opus, sample_wav,
+  };
+  use crate::util::{self, AudioImageType, AudioTags, Image};
+
+  fn tags() -> AudioTags {
+    AudioTags {
+      title: Some("Retagged".to_string()),
+      artists: Some(vec!["Artist".to_string()]),
+      comment: Some("A much longer comment than the file had before".repeat(20)),
+      image: Some(Image {
+        data: std::fs::read(crate::test_support::fixture_path("test-files/cover.jpg")).unwrap(),
+        pic_type: AudioImageType::CoverFront,
+        mime_type: Some("image/jpeg".to_string()),
+        description: None,
+        ..Default::default()
+      }),
+      ..Default::default()
+    }
+  }
+
+  #[tokio::test]
+  async fn test_audio_hash_ignores_tags() {
+    let samples = [
+      ("mp3", sample_mp3()),
+      ("m4a", sample_m4a()),
+      ("wav", sample_wav()),
+      ("opus", sample_opus()),
+      ("dsf", sample_dsf()),
+      ("dff", sample_dff()),
+    ];
+    for (name, sample) in samples {
+      let before = audio_hash_from_buffer(sample.clone()).await.unwrap();
+      let tagged = util::write_tags_to_buffer(sample, tags()).await.unwrap();
+      let after = audio_hash_from_buffer(tagged.clone()).await.unwrap();
+      assert_eq!(after, before, "{}", name);
+
+      let emptied = util::write_tags_to_buffer(tagged, AudioTags::default())
+        .await
+        .unwrap();
+      assert_eq!(
+        audio_hash_from_buffer(emptied).await.unwrap(),
+        before,
+        "{}",
+        name
+      );
+    }
+  }
+
+  #[tokio::test]
+  async fn test_audio_hash_changes_with_audio() {
+    let before = audio_hash_from_buffer(sample_wav()).await.unwrap();
+    let mut wav = sample_wav();
+    let last = wav.len() - 1;
+    wav[last] = 1;
+    assert_ne!(audio_hash_from_buffer(wav).await.unwrap(), before);
+  }
+
+  #[tokio::test]
+  async fn test_audio_hash_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = crate::test_support::write_fixture(dir.path(), "song.mp3", &sample_mp3());
+    let before = audio_hash(path.clone()).await.unwrap();
+    assert_eq!(before.len(), 64);
+    util::write_tags(path.clone(), tags()).await.unwrap();
+    assert_eq!(audio_hash(path).await.unwrap(), before);
+
+    let path = crate::test_support::write_fixture(dir.path(), "notes.txt", b"not audio at all");
+    let e = audio_hash(path).await.unwrap_err();
+    assert!(
+      e.starts_with(&format!("{}/notes.txt: ", dir.path().display())),
+      "{}",
+      e
+    );
+  }
+}
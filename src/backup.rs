@@ -0,0 +1,87 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_SUFFIX: &str = ".bak";
+
+// Where the copy of a file is kept before a write modifies it
+#[derive(Debug, PartialEq, Clone)]
+pub enum Backup {
+  // next to the file, under its name followed by the suffix; a copy left by an
+  // earlier write is replaced
+  Suffix(String),
+  // in the directory, under the file's name; numbered when the name is taken,
+  // as files of different folders may share it
+  Dir(String),
+}
+
+// the name with " (n)" before the extension
+fn numbered(name: &Path, n: u32) -> PathBuf {
+  let stem = name
+    .file_stem()
+    .map(|stem| stem.to_string_lossy().to_string())
+    .unwrap_or_default();
+  match name.extension() {
+    Some(extension) => PathBuf::from(format!("{} ({}).{}", stem, n, extension.to_string_lossy())),
+    None => PathBuf::from(format!("{} ({})", stem, n)),
+  }
+}
+
+/**
+ * Copy a file before it is modified
+ * @param path - The file about to be written
+ * @param backup - Where the copy goes
+ * @returns The path of the copy
+ */
+pub fn back_up(path: &Path, backup: &Backup) -> Result<PathBuf, String> {
+  let name = path
+    .file_name()
+    .ok_or_else(|| format!("Failed to back up file: {} has no name", path.display()))?;
+  let copy = match backup {
+    Backup::Suffix(suffix) => {
+      let mut name = name.to_os_string();
+      name.push(suffix);
+      path.with_file_name(name)
+    }
+    Backup::Dir(dir) => {
+      let dir = Path::new(dir);
+      fs::create_dir_all(dir).map_err(|e| format!("Failed to back up file: {}", e))?;
+      let mut copy = dir.join(name);
+      let mut n = 0;
+      while copy.exists() {
+        n += 1;
+        copy = dir.join(numbered(Path::new(name), n));
+      }
+      copy
+    }
+  };
+  fs::copy(path, &copy).map_err(|e| format!("Failed to back up file: {}", e))?;
+  Ok(copy)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_back_up() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("song.mp3");
+    fs::write(&path, b"first").unwrap();
+
+    let suffix = Backup::Suffix(DEFAULT_SUFFIX.to_string());
+    let copy = back_up(&path, &suffix).unwrap();
+    assert_eq!(copy, dir.path().join("song.mp3.bak"));
+    fs::write(&path, b"second").unwrap();
+    back_up(&path, &suffix).unwrap();
+    assert_eq!(fs::read(&copy).unwrap(), b"second");
+
+    let backups = dir.path().join("backups");
+    let in_dir = Backup::Dir(backups.to_string_lossy().to_string());
+    assert_eq!(back_up(&path, &in_dir).unwrap(), backups.join("song.mp3"));
+    assert_eq!(
+      back_up(&path, &in_dir).unwrap(),
+      backups.join("song (1).mp3")
+    );
+    assert!(back_up(&dir.path().join("missing.mp3"), &suffix).is_err());
+  }
+}
@@ -0,0 +1,962 @@
+use crate::atomic::WriteTarget;
+use crate::cancel::{Cancellation, ABORTED};
+use crate::hooks::{HookDecision, WriteHooks};
+use crate::patch::{TagField, TagPatch};
+use crate::transform::{self, TransformRule};
+use crate::util::{self, io_error, AudioTags, ClearTagsOptions};
+use std::future::Future;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct BatchFileResult {
+  // lossy for names that aren't valid Unicode
+  pub file_path: String,
+  // None when the operation succeeded for this file
+  pub error: Option<String>,
+  // the error of the after-write hook, which leaves the file written
+  pub hook_error: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct BatchReadResult {
+  pub file_path: String,
+  // None when the file could not be read
+  pub tags: Option<AudioTags>,
+  pub error: Option<String>,
+}
+
+// Told about every file a batch operation is done with, in completion order
+pub trait Progress: Send + Sync + 'static {
+  fn report(&self, done: usize, total: usize, file_path: &str);
+}
+
+// for callers that don't report progress
+pub(crate) struct NoProgress;
+
+impl Progress for NoProgress {
+  fn report(&self, _done: usize, _total: usize, _file_path: &str) {}
+}
+
+// counts the files done for a Progress, shared by the calls of a batch
+pub(crate) struct ProgressCounter<P> {
+  progress: P,
+  done: AtomicUsize,
+  total: usize,
+}
+
+impl<P: Progress> ProgressCounter<P> {
+  pub(crate) fn new(progress: P, total: usize) -> Arc<Self> {
+    Arc::new(Self {
+      progress,
+      done: AtomicUsize::new(0),
+      total,
+    })
+  }
+
+  pub(crate) fn file_done(&self, file_path: &str) {
+    let done = self.done.fetch_add(1, Ordering::SeqCst) + 1;
+    self.progress.report(done, self.total, file_path);
+  }
+}
+
+pub fn default_concurrency() -> usize {
+  std::thread::available_parallelism()
+    .map(|n| n.get())
+    .unwrap_or(4)
+}
+
+/**
+ * Run `f` over every item with at most `concurrency` calls in flight
+ * @param items - The inputs
+ * @param concurrency - Maximum number of concurrent calls (at least one)
+ * @param f - The operation to run for each input
+ * @returns The results in the same order as the inputs
+ */
+pub async fn map_concurrent<T, R, F, Fut>(items: Vec<T>, concurrency: usize, f: F) -> Vec<R>
+where
+  T: Send + 'static,
+  R: Send + 'static,
+  F: Fn(T) -> Fut + Send + Sync + 'static,
+  Fut: Future<Output = R> + Send + 'static,
+{
+  map_concurrent_cancellable(items, concurrency, &Cancellation::default(), f)
+    .await
+    .expect("an operation without a signal is never cancelled")
+}
+
+// waits for the calls in flight, the others never start
+async fn abort<T: 'static>(mut tasks: JoinSet<T>) -> String {
+  tasks.abort_all();
+  while let Some(joined) = tasks.join_next().await {
+    if let Err(e) = joined {
+      if e.is_panic() {
+        std::panic::resume_unwind(e.into_panic());
+      }
+    }
+  }
+  ABORTED.to_string()
+}
+
+/**
+ * Same as map_concurrent, stopping once `cancel` is cancelled: no further call
+ * is started and the calls in flight are waited for
+ * @param items - The inputs
+ * @param concurrency - Maximum number of concurrent calls (at least one)
+ * @param cancel - Cancelled when the caller aborts
+ * @param f - The operation to run for each input
+ * @returns The results in the same order as the inputs, an error once cancelled
+ */
+pub async fn map_concurrent_cancellable<T, R, F, Fut>(
+  items: Vec<T>,
+  concurrency: usize,
+  cancel: &Cancellation,
+  f: F,
+) -> Result<Vec<R>, String>
+where
+  T: Send + 'static,
+  R: Send + 'static,
+  F: Fn(T) -> Fut + Send + Sync + 'static,
+  Fut: Future<Output = R> + Send + 'static,
+{
+  let mut results: Vec<Option<R>> = (0..items.len()).map(|_| None).collect();
+  let f = Arc::new(f);
+  for_each_concurrent_cancellable(
+    items.into_iter().enumerate().collect(),
+    concurrency,
+    cancel,
+    move |(index, item)| {
+      let f = f.clone();
+      async move { (index, f(item).await) }
+    },
+    |(index, result)| {
+      results[index] = Some(result);
+      std::future::ready(Ok(()))
+    },
+  )
+  .await?;
+  Ok(
+    results
+      .into_iter()
+      .map(|result| result.expect("every batch task reports a result"))
+      .collect(),
+  )
+}
+
+/**
+ * Run `f` over every item with at most `concurrency` calls in flight, handing
+ * every result to `sink` as soon as it is ready instead of collecting them.
+ * The sink is awaited before the next result is handed over, and at most
+ * `concurrency` results wait for it, so a slow sink holds up the calls rather
+ * than letting results pile up. Stops once `cancel` is cancelled, as
+ * map_concurrent_cancellable does.
+ * @param items - The inputs
+ * @param concurrency - Maximum number of concurrent calls (at least one)
+ * @param cancel - Cancelled when the caller aborts
+ * @param f - The operation to run for each input
+ * @param sink - Takes the results, in completion order; an error stops the
+ * calls as cancelling does
+ * @returns An error once cancelled, or the error of the sink
+ */
+pub async fn for_each_concurrent_cancellable<T, R, F, Fut, S, SinkFut>(
+  items: Vec<T>,
+  concurrency: usize,
+  cancel: &Cancellation,
+  f: F,
+  mut sink: S,
+) -> Result<(), String>
+where
+  T: Send + 'static,
+  R: Send + 'static,
+  F: Fn(T) -> Fut + Send + Sync + 'static,
+  Fut: Future<Output = R> + Send + 'static,
+  S: FnMut(R) -> SinkFut,
+  SinkFut: Future<Output = Result<(), String>>,
+{
+  let semaphore = Arc::new(Semaphore::new(concurrency.max(1)));
+  let f = Arc::new(f);
+  let mut tasks = JoinSet::new();
+  let mut items = items.into_iter();
+  while !(tasks.is_empty() && items.as_slice().is_empty()) {
+    // start calls while there are permits, and hand over results as they come
+    let joined = tokio::select! {
+      biased;
+      _ = cancel.cancelled() => return Err(abort(tasks).await),
+      Some(joined) = tasks.join_next(), if !tasks.is_empty() => joined,
+      permit = semaphore.clone().acquire_owned(), if !items.as_slice().is_empty() => {
+        let permit = permit.expect("batch semaphore is never closed");
+        let item = items.next().expect("checked that an item is left");
+        let f = f.clone();
+        tasks.spawn(async move {
+          let result = f(item).await;
+          drop(permit);
+          result
+        });
+        continue;
+      }
+    };
+    match joined {
+      Ok(result) => {
+        if let Err(error) = sink(result).await {
+          abort(tasks).await;
+          return Err(error);
+        }
+      }
+      Err(e) => std::panic::resume_unwind(e.into_panic()),
+    }
+  }
+  Ok(())
+}
+
+/**
+ * Read the cover image of many files in parallel
+ * @param paths - The audio files
+ * @param max_bytes_per_image - Covers larger than this are skipped
+ * @param cancel - Cancelled when the caller aborts
+ * @param progress - Told about every file read
+ * @returns The cover of every file in input order, None when the file has no
+ * cover, could not be read or the cover is too large
+ */
+pub async fn read_cover_images_batch<P: Progress>(
+  paths: Vec<PathBuf>,
+  max_bytes_per_image: Option<usize>,
+  cancel: &Cancellation,
+  progress: P,
+) -> Result<Vec<Option<Vec<u8>>>, String> {
+  let counter = ProgressCounter::new(progress, paths.len());
+  map_concurrent_cancellable(paths, default_concurrency(), cancel, move |file_path| {
+    let counter = counter.clone();
+    async move {
+      let cover = util::read_cover_image_from_file(&file_path)
+        .await
+        .ok()
+        .flatten()
+        .filter(|data| max_bytes_per_image.is_none_or(|max| data.len() <= max));
+      counter.file_done(&file_path.to_string_lossy());
+      cover
+    }
+  })
+  .await
+}
+
+// What a batch write does to every file
+enum BatchWrite {
+  Clear(ClearTagsOptions),
+  Transform(Vec<TransformRule>),
+}
+
+impl BatchWrite {
+  async fn run(&self, file_path: &Path) -> Result<(), String> {
+    match self {
+      Self::Clear(options) => util::clear_tags_with_options(file_path, options.clone()).await,
+      Self::Transform(rules) => transform::transform_tags(file_path, rules.clone())
+        .await
+        .map(|_| ()),
+    }
+  }
+
+  // the write run on a copy of the file in memory
+  async fn apply(&self, buffer: Vec<u8>) -> Result<Vec<u8>, String> {
+    match self {
+      Self::Clear(options) => {
+        util::clear_tags_to_buffer_with_options(buffer, options.clone()).await
+      }
+      Self::Transform(rules) => transform::transform_tags_to_buffer(buffer, rules.clone()).await,
+    }
+  }
+}
+
+// a patch leaving exactly `tags`: every field they don't set is cleared
+fn replacement_patch(tags: AudioTags) -> TagPatch {
+  TagPatch {
+    clear: TagField::ALL
+      .into_iter()
+      .filter(|field| !field.is_set(&tags))
+      .collect(),
+    set: tags,
+  }
+}
+
+// replace a file with `contents` through a copy renamed over it
+fn replace_file(path: &Path, contents: &[u8]) -> Result<(), String> {
+  let mut target = WriteTarget::open(path, true, false)?;
+  let file = target.file();
+  file
+    .set_len(0)
+    .and_then(|_| file.write_all(contents))
+    .map_err(|e| io_error(&format!("Failed to write file {}", path.display()), &e))?;
+  target.commit()
+}
+
+/**
+ * Run a batch write on one file between the hooks. The before-write hook is
+ * shown the tags the write would leave and may veto it, or adjust them: the
+ * adjusted tags replace the result. The write and the adjustment are made in
+ * memory and saved in one atomic write.
+ * @returns The error of the after-write hook
+ */
+async fn hooked_write<H: WriteHooks>(
+  path: &Path,
+  write: &BatchWrite,
+  hooks: &H,
+) -> Result<Option<String>, String> {
+  let file_path = &*path.to_string_lossy();
+  let buffer = std::fs::read(path).map_err(|e| io_error("Failed to read file", &e))?;
+  let old_tags = util::read_tags_from_buffer(&buffer).await?;
+  let written = write.apply(buffer.clone()).await?;
+  let new_tags = util::read_tags_from_buffer(&written).await?;
+  let tags = match hooks
+    .before_write(file_path, &old_tags, new_tags.clone())
+    .await
+    .map_err(|e| format!("Write hook failed: {}", e))?
+  {
+    HookDecision::Proceed(tags) => *tags,
+    HookDecision::Veto => return Ok(None),
+  };
+  let written = if tags != new_tags {
+    util::update_tags_to_buffer(written, replacement_patch(tags.clone())).await?
+  } else {
+    written
+  };
+  if written != buffer {
+    replace_file(path, &written)?;
+  }
+  Ok(
+    hooks
+      .after_write(file_path, &old_tags, &tags)
+      .await
+      .err()
+      .map(|e| format!("After-write hook failed: {}", e)),
+  )
+}
+
+async fn write_one<H: WriteHooks>(
+  file_path: PathBuf,
+  write: &BatchWrite,
+  hooks: &H,
+) -> BatchFileResult {
+  let (error, hook_error) = if hooks.is_active() {
+    match hooked_write(&file_path, write, hooks).await {
+      Ok(hook_error) => (None, hook_error),
+      Err(e) => (Some(e), None),
+    }
+  } else {
+    (write.run(&file_path).await.err(), None)
+  };
+  BatchFileResult {
+    file_path: file_path.to_string_lossy().into_owned(),
+    error,
+    hook_error,
+  }
+}
+
+// runs `write` on every file, between the hooks
+async fn write_batch<P: Progress, H>(
+  paths: Vec<PathBuf>,
+  write: BatchWrite,
+  cancel: &Cancellation,
+  progress: P,
+  hooks: H,
+) -> Result<Vec<BatchFileResult>, String>
+where
+  H: WriteHooks + Send + Sync + 'static,
+{
+  let counter = ProgressCounter::new(progress, paths.len());
+  let write = Arc::new(write);
+  let hooks = Arc::new(hooks);
+  map_concurrent_cancellable(paths, default_concurrency(), cancel, move |file_path| {
+    let (write, hooks, counter) = (write.clone(), hooks.clone(), counter.clone());
+    async move {
+      let result = write_one(file_path, &write, &*hooks).await;
+      counter.file_done(&result.file_path);
+      result
+    }
+  })
+  .await
+}
+
+/**
+ * Clear the tags of many files in parallel. A failing file doesn't stop the others.
+ * @param paths - The audio files
+ * @param options - What to keep in the cleared tags
+ * @param cancel - Cancelled when the caller aborts, the files cleared so far stay cleared
+ * @param progress - Told about every file cleared or failed
+ * @param hooks - Run around every file, see `hooked_write`
+ * @returns One result per file in input order
+ */
+pub async fn clear_tags_batch<P: Progress, H>(
+  paths: Vec<PathBuf>,
+  options: ClearTagsOptions,
+  cancel: &Cancellation,
+  progress: P,
+  hooks: H,
+) -> Result<Vec<BatchFileResult>, String>
+where
+  H: WriteHooks + Send + Sync + 'static,
+{
+  write_batch(paths, BatchWrite::Clear(options), cancel, progress, hooks).await
+}
+
+/**
+ * Apply clean-up rules to the tags of many files in parallel. A failing file
+ * doesn't stop the others.
+ * @param paths - The audio files
+ * @param rules - The rules, applied in order to every file
+ * @param cancel - Cancelled when the caller aborts, the files transformed so far stay transformed
+ * @param progress - Told about every file transformed or failed
+ * @param hooks - Run around every file, see `hooked_write`
+ * @returns One result per file in input order
+ */
+pub async fn transform_tags_batch<P: Progress, H>(
+  paths: Vec<PathBuf>,
+  rules: Vec<TransformRule>,
+  cancel: &Cancellation,
+  progress: P,
+  hooks: H,
+) -> Result<Vec<BatchFileResult>, String>
+where
+  H: WriteHooks + Send + Sync + 'static,
+{
+  // the same for every file, so it fails the batch rather than each file
+  transform::validate_rules(&rules)?;
+  write_batch(paths, BatchWrite::Transform(rules), cancel, progress, hooks).await
+}
+
+/**
+ * Read the tags of many files in parallel. A failing file doesn't stop the others.
+ * @param paths - The audio files
+ * @param concurrency - Maximum number of files read at once, None for one per core
+ * @param cancel - Cancelled when the caller aborts
+ * @param progress - Told about every file read or failed
+ * @param options - How every file is read
+ * @returns One result per file in input order
+ */
+pub async fn read_tags_batch<P: Progress>(
+  paths: Vec<PathBuf>,
+  concurrency: Option<usize>,
+  cancel: &Cancellation,
+  progress: P,
+  options: util::ReadTagsOptions,
+) -> Result<Vec<BatchReadResult>, String> {
+  let concurrency = concurrency.unwrap_or_else(default_concurrency);
+  let counter = ProgressCounter::new(progress, paths.len());
+  map_concurrent_cancellable(paths, concurrency, cancel, move |file_path| {
+    read_one(file_path, options.clone(), counter.clone())
+  })
+  .await
+}
+
+/**
+ * Same as read_tags_batch, handing every result over as soon as the file is
+ * read instead of collecting them, so the first results of a long batch can
+ * be shown right away
+ * @param paths - The audio files
+ * @param concurrency - Maximum number of files read at once, None for one per core
+ * @param cancel - Cancelled when the caller aborts
+ * @param progress - Told about every file read or failed
+ * @param options - How every file is read
+ * @param on_result - Takes the results in completion order, awaited before the
+ * next one; an error stops the batch
+ */
+pub async fn read_tags_batch_each<P, S, SinkFut>(
+  paths: Vec<PathBuf>,
+  concurrency: Option<usize>,
+  cancel: &Cancellation,
+  progress: P,
+  options: util::ReadTagsOptions,
+  on_result: S,
+) -> Result<(), String>
+where
+  P: Progress,
+  S: FnMut(BatchReadResult) -> SinkFut,
+  SinkFut: Future<Output = Result<(), String>>,
+{
+  let concurrency = concurrency.unwrap_or_else(default_concurrency);
+  let counter = ProgressCounter::new(progress, paths.len());
+  for_each_concurrent_cancellable(
+    paths,
+    concurrency,
+    cancel,
+    move |file_path| read_one(file_path, options.clone(), counter.clone()),
+    on_result,
+  )
+  .await
+}
+
+async fn read_one<P: Progress>(
+  path: PathBuf,
+  options: util::ReadTagsOptions,
+  counter: Arc<ProgressCounter<P>>,
+) -> BatchReadResult {
+  let result = util::read_tags_with_options(&path, options)
+    .await
+    .map(|outcome| outcome.tags);
+  let file_path = path.to_string_lossy().into_owned();
+  counter.file_done(&file_path);
+  match result {
+    Ok(tags) => BatchReadResult {
+      file_path,
+      tags: Some(tags),
+      error: None,
+    },
+    Err(error) => BatchReadResult {
+      file_path,
+      tags: None,
+      error: Some(error),
+    },
+  }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+  use super::*;
+  use crate::hooks::NoHooks;
+  use crate::test_support::{sample_mp3, write_fixture};
+  use std::sync::Mutex;
+
+  // records every report
+  #[derive(Default)]
+  struct Reports(Arc<Mutex<Vec<(usize, usize, String)>>>);
+
+  impl Progress for Reports {
+    fn report(&self, done: usize, total: usize, file_path: &str) {
+      self
+        .0
+        .lock()
+        .unwrap()
+        .push((done, total, file_path.to_string()));
+    }
+  }
+
+  #[tokio::test]
+  async fn test_map_concurrent_keeps_input_order() {
+    let results = map_concurrent(vec![30u64, 10, 20], 3, |delay| async move {
+      tokio::time::sleep(std::time::Duration::from_millis(delay)).await;
+      delay * 2
+    })
+    .await;
+    assert_eq!(results, vec![60, 20, 40]);
+  }
+
+  #[tokio::test]
+  async fn test_map_concurrent_bounds_in_flight_calls() {
+    let in_flight = Arc::new(AtomicUsize::new(0));
+    let peak = Arc::new(AtomicUsize::new(0));
+    let (in_flight_c, peak_c) = (in_flight.clone(), peak.clone());
+    map_concurrent((0..8).collect(), 2, move |_: i32| {
+      let (in_flight, peak) = (in_flight_c.clone(), peak_c.clone());
+      async move {
+        let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        peak.fetch_max(now, Ordering::SeqCst);
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        in_flight.fetch_sub(1, Ordering::SeqCst);
+      }
+    })
+    .await;
+    assert!(peak.load(Ordering::SeqCst) <= 2);
+    assert_eq!(in_flight.load(Ordering::SeqCst), 0);
+  }
+
+  #[tokio::test]
+  async fn test_map_concurrent_stops_once_cancelled() {
+    let started = Arc::new(AtomicUsize::new(0));
+    let cancel = Cancellation::default();
+    let (started_c, cancel_c) = (started.clone(), cancel.clone());
+    let result = map_concurrent_cancellable((0..8).collect(), 2, &cancel, move |i: i32| {
+      let (started, cancel) = (started_c.clone(), cancel_c.clone());
+      async move {
+        started.fetch_add(1, Ordering::SeqCst);
+        if i == 2 {
+          cancel.cancel();
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+      }
+    })
+    .await;
+    assert_eq!(result.unwrap_err(), ABORTED);
+    assert!(started.load(Ordering::SeqCst) < 8);
+
+    // an operation cancelled up front starts nothing
+    let result = map_concurrent_cancellable(vec![1], 2, &cancel, |i: i32| async move { i }).await;
+    assert_eq!(result.unwrap_err(), ABORTED);
+  }
+
+  #[tokio::test]
+  async fn test_for_each_concurrent_hands_results_over_as_ready() {
+    let mut seen = Vec::new();
+    for_each_concurrent_cancellable(
+      vec![30u64, 10, 20],
+      3,
+      &Cancellation::default(),
+      |delay| async move {
+        tokio::time::sleep(std::time::Duration::from_millis(delay)).await;
+        delay
+      },
+      |delay| {
+        seen.push(delay);
+        std::future::ready(Ok(()))
+      },
+    )
+    .await
+    .unwrap();
+    assert_eq!(seen, vec![10, 20, 30]);
+
+    // a failing sink stops the calls
+    let started = Arc::new(AtomicUsize::new(0));
+    let started_c = started.clone();
+    let result = for_each_concurrent_cancellable(
+      (0..8).collect(),
+      1,
+      &Cancellation::default(),
+      move |i: i32| {
+        started_c.fetch_add(1, Ordering::SeqCst);
+        async move { i }
+      },
+      |i| {
+        std::future::ready(if i == 1 {
+          Err("Stop".to_string())
+        } else {
+          Ok(())
+        })
+      },
+    )
+    .await;
+    assert_eq!(result.unwrap_err(), "Stop");
+    assert!(started.load(Ordering::SeqCst) < 8);
+  }
+
+  #[tokio::test]
+  async fn test_read_cover_images_batch() {
+    let dir = tempfile::tempdir().unwrap();
+    let small = vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10, 0xFF, 0xD9];
+    let large = [small.clone(), vec![0; 64]].concat();
+    let with_small = util::write_cover_image_to_buffer(sample_mp3(), small.clone())
+      .await
+      .unwrap();
+    let with_large = util::write_cover_image_to_buffer(sample_mp3(), large.clone())
+      .await
+      .unwrap();
+    let paths = vec![
+      write_fixture(dir.path(), "small.mp3", &with_small),
+      write_fixture(dir.path(), "bare.mp3", &sample_mp3()),
+      write_fixture(dir.path(), "large.mp3", &with_large),
+      dir.path().join("missing.mp3").to_string_lossy().to_string(),
+    ];
+
+    let covers = read_cover_images_batch(
+      paths.iter().map(PathBuf::from).collect(),
+      None,
+      &Cancellation::default(),
+      NoProgress,
+    )
+    .await
+    .unwrap();
+    assert_eq!(covers, vec![Some(small.clone()), None, Some(large), None]);
+
+    let covers = read_cover_images_batch(
+      paths.into_iter().map(PathBuf::from).collect(),
+      Some(small.len()),
+      &Cancellation::default(),
+      NoProgress,
+    )
+    .await
+    .unwrap();
+    assert_eq!(covers, vec![Some(small), None, None, None]);
+  }
+
+  #[tokio::test]
+  async fn test_clear_tags_batch_reports_per_file_results() {
+    let dir = tempfile::tempdir().unwrap();
+    let tags = util::AudioTags {
+      title: Some("Delivery".to_string()),
+      ..Default::default()
+    };
+    let tagged = util::write_tags_to_buffer(sample_mp3(), tags)
+      .await
+      .unwrap();
+    let first = write_fixture(dir.path(), "01.mp3", &tagged);
+    let second = write_fixture(dir.path(), "02.mp3", &tagged);
+    let broken = write_fixture(dir.path(), "03.mp3", b"not audio");
+
+    let results = clear_tags_batch(
+      vec![
+        first.clone().into(),
+        broken.clone().into(),
+        second.clone().into(),
+      ],
+      ClearTagsOptions::default(),
+      &Cancellation::default(),
+      NoProgress,
+      NoHooks,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+      results[0],
+      BatchFileResult {
+        file_path: first.clone(),
+        error: None,
+        hook_error: None,
+      }
+    );
+    assert_eq!(results[1].file_path, broken);
+    assert!(results[1].error.is_some());
+    assert_eq!(results[2].error, None);
+    for path in [first, second] {
+      assert_eq!(util::read_tags(path).await.unwrap().title, None);
+    }
+  }
+
+  #[tokio::test]
+  async fn test_transform_tags_batch() {
+    use crate::patch::TagField;
+    use crate::transform::{TransformAction, DEFAULT_FIELDS};
+    let dir = tempfile::tempdir().unwrap();
+    let tags = util::AudioTags {
+      title: Some("song ft. guest".to_string()),
+      ..Default::default()
+    };
+    let tagged = util::write_tags_to_buffer(sample_mp3(), tags)
+      .await
+      .unwrap();
+    let first = write_fixture(dir.path(), "01.mp3", &tagged);
+    let broken = write_fixture(dir.path(), "02.mp3", b"not audio");
+    let rules = vec![
+      TransformRule {
+        action: TransformAction::FixFeaturing,
+        fields: DEFAULT_FIELDS.to_vec(),
+      },
+      TransformRule {
+        action: TransformAction::TitleCase,
+        fields: vec![TagField::Title],
+      },
+    ];
+
+    let results = transform_tags_batch(
+      vec![first.clone().into(), broken.clone().into()],
+      rules,
+      &Cancellation::default(),
+      NoProgress,
+      NoHooks,
+    )
+    .await
+    .unwrap();
+    assert_eq!(results[0].error, None);
+    assert!(results[1].error.is_some());
+    assert_eq!(
+      util::read_tags(first).await.unwrap().title.as_deref(),
+      Some("Song feat. Guest")
+    );
+
+    let invalid = vec![TransformRule {
+      action: TransformAction::TitleCase,
+      fields: vec![TagField::Track],
+    }];
+    let error = transform_tags_batch(
+      vec![broken.into()],
+      invalid,
+      &Cancellation::default(),
+      NoProgress,
+      NoHooks,
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(error, "Track is not a text field");
+  }
+
+  // keeps the ISRC, leaves files named "locked" alone and fails to report
+  // files named "unreported"
+  struct KeepIsrc;
+
+  impl WriteHooks for KeepIsrc {
+    async fn before_write(
+      &self,
+      file_path: &str,
+      old_tags: &AudioTags,
+      mut new_tags: AudioTags,
+    ) -> Result<HookDecision, String> {
+      if file_path.contains("locked") {
+        return Ok(HookDecision::Veto);
+      }
+      new_tags.isrc = old_tags.isrc.clone();
+      Ok(HookDecision::Proceed(Box::new(new_tags)))
+    }
+
+    async fn after_write(
+      &self,
+      file_path: &str,
+      _old_tags: &AudioTags,
+      _new_tags: &AudioTags,
+    ) -> Result<(), String> {
+      match file_path.contains("unreported") {
+        true => Err("notification failed".to_string()),
+        false => Ok(()),
+      }
+    }
+  }
+
+  #[tokio::test]
+  async fn test_clear_tags_batch_runs_hooks() {
+    let dir = tempfile::tempdir().unwrap();
+    let tags = util::AudioTags {
+      title: Some("Delivery".to_string()),
+      isrc: Some("GBAYE0601498".to_string()),
+      ..Default::default()
+    };
+    let tagged = util::write_tags_to_buffer(sample_mp3(), tags)
+      .await
+      .unwrap();
+    let cleared = write_fixture(dir.path(), "cleared.mp3", &tagged);
+    let locked = write_fixture(dir.path(), "locked.mp3", &tagged);
+    let unreported = write_fixture(dir.path(), "unreported.mp3", &tagged);
+
+    let results = clear_tags_batch(
+      vec![
+        cleared.clone().into(),
+        locked.clone().into(),
+        unreported.clone().into(),
+      ],
+      ClearTagsOptions::default(),
+      &Cancellation::default(),
+      NoProgress,
+      KeepIsrc,
+    )
+    .await
+    .unwrap();
+
+    assert!(results.iter().all(|result| result.error.is_none()));
+    assert_eq!(results[0].hook_error, None);
+    assert_eq!(
+      results[2].hook_error.as_deref(),
+      Some("After-write hook failed: notification failed")
+    );
+    for path in [cleared, unreported] {
+      let tags = util::read_tags(path).await.unwrap();
+      assert_eq!(tags.title, None);
+      assert_eq!(tags.isrc.as_deref(), Some("GBAYE0601498"));
+    }
+    assert_eq!(std::fs::read(locked).unwrap(), tagged);
+  }
+
+  // removes the album of every file written
+  struct DropAlbum;
+
+  impl WriteHooks for DropAlbum {
+    async fn before_write(
+      &self,
+      _file_path: &str,
+      _old_tags: &AudioTags,
+      mut new_tags: AudioTags,
+    ) -> Result<HookDecision, String> {
+      new_tags.album = None;
+      Ok(HookDecision::Proceed(Box::new(new_tags)))
+    }
+
+    async fn after_write(
+      &self,
+      _file_path: &str,
+      _old_tags: &AudioTags,
+      _new_tags: &AudioTags,
+    ) -> Result<(), String> {
+      Ok(())
+    }
+  }
+
+  #[tokio::test]
+  async fn test_transform_tags_batch_hook_clears_field() {
+    use crate::patch::TagField;
+    use crate::transform::TransformAction;
+    let dir = tempfile::tempdir().unwrap();
+    let tags = util::AudioTags {
+      title: Some("the sound of silence".to_string()),
+      album: Some("Sounds of Silence".to_string()),
+      ..Default::default()
+    };
+    let tagged = util::write_tags_to_buffer(sample_mp3(), tags)
+      .await
+      .unwrap();
+    let path = write_fixture(dir.path(), "song.mp3", &tagged);
+    let rules = vec![TransformRule {
+      action: TransformAction::TitleCase,
+      fields: vec![TagField::Title],
+    }];
+
+    let results = transform_tags_batch(
+      vec![path.clone().into()],
+      rules,
+      &Cancellation::default(),
+      NoProgress,
+      DropAlbum,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(results[0].error, None);
+    let tags = util::read_tags(&path).await.unwrap();
+    assert_eq!(tags.title.as_deref(), Some("The Sound of Silence"));
+    assert_eq!(tags.album, None);
+    // the file was replaced, not left with its copy
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+  }
+
+  #[tokio::test]
+  async fn test_read_tags_batch() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut paths = Vec::new();
+    for i in 0..5 {
+      let tags = util::AudioTags {
+        title: Some(format!("Track {}", i)),
+        ..Default::default()
+      };
+      let tagged = util::write_tags_to_buffer(sample_mp3(), tags)
+        .await
+        .unwrap();
+      paths.push(write_fixture(dir.path(), &format!("{}.mp3", i), &tagged));
+    }
+    let broken = write_fixture(dir.path(), "broken.mp3", b"not audio");
+    paths.insert(2, broken.clone());
+
+    let reports = Reports::default();
+    let reported = reports.0.clone();
+    let results = read_tags_batch(
+      paths.iter().map(PathBuf::from).collect(),
+      Some(2),
+      &Cancellation::default(),
+      reports,
+      util::ReadTagsOptions::default(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(results.len(), 6);
+    // one report per file, the failing one included
+    let reported = reported.lock().unwrap().clone();
+    let counts: Vec<(usize, usize)> = reported
+      .iter()
+      .map(|(done, total, _)| (*done, *total))
+      .collect();
+    assert_eq!(counts, (1..=6).map(|done| (done, 6)).collect::<Vec<_>>());
+    let mut reported_paths: Vec<String> = reported.into_iter().map(|(_, _, path)| path).collect();
+    reported_paths.sort();
+    let mut sorted_paths = paths.clone();
+    sorted_paths.sort();
+    assert_eq!(reported_paths, sorted_paths);
+    assert_eq!(results[2].file_path, broken);
+    assert_eq!(results[2].tags, None);
+    assert!(results[2].error.is_some());
+    let titles: Vec<Option<String>> = results
+      .iter()
+      .filter_map(|result| result.tags.as_ref())
+      .map(|tags| tags.title.clone())
+      .collect();
+    let expected: Vec<Option<String>> = (0..5).map(|i| Some(format!("Track {}", i))).collect();
+    assert_eq!(titles, expected);
+    for (result, path) in results.iter().zip(&paths) {
+      assert_eq!(&result.file_path, path);
+    }
+  }
+}
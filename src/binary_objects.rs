@@ -0,0 +1,137 @@
+use crate::util::Id3v2Version;
+use lofty::id3::v2::{BinaryFrame, Frame, FrameId, GeneralEncapsulatedObject, Id3v2Tag};
+use lofty::tag::{Tag, TagType};
+use lofty::TextEncoding;
+use std::borrow::Cow;
+
+const GEOB: &str = "GEOB";
+
+// An object embedded in a tag as is, e.g. the cue points DJ software stores
+// in ID3v2 GEOB frames
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct BinaryObject {
+  // identifies the object, e.g. "Serato Markers2"
+  pub description: String,
+  pub mime_type: Option<String>,
+  pub filename: Option<String>,
+  pub data: Vec<u8>,
+}
+
+fn parse_geob(binary: &BinaryFrame) -> Option<BinaryObject> {
+  let object = GeneralEncapsulatedObject::parse(&binary.data, binary.flags()).ok()?;
+  let non_empty = |text: Option<String>| text.filter(|text| !text.is_empty());
+  Some(BinaryObject {
+    description: object.descriptor.unwrap_or_default(),
+    mime_type: non_empty(object.mime_type),
+    filename: non_empty(object.file_name),
+    data: object.data,
+  })
+}
+
+fn geob_objects(id3v2: &Id3v2Tag) -> impl Iterator<Item = (&Frame<'static>, BinaryObject)> {
+  id3v2.into_iter().filter_map(|frame| match frame {
+    Frame::Binary(binary) if binary.id().as_str() == GEOB => {
+      parse_geob(binary).map(|object| (frame, object))
+    }
+    _ => None,
+  })
+}
+
+/**
+ * Read the GEOB frames of an ID3v2 tag, in tag order
+ * @param tag - The tag
+ * @returns None for other tags, or when there are no objects
+ */
+pub fn binary_objects_from_tag(tag: &Tag) -> Option<Vec<BinaryObject>> {
+  if tag.tag_type() != TagType::Id3v2 {
+    return None;
+  }
+  let id3v2: Id3v2Tag = tag.clone().into();
+  let objects: Vec<BinaryObject> = geob_objects(&id3v2).map(|(_, object)| object).collect();
+  (!objects.is_empty()).then_some(objects)
+}
+
+/**
+ * Store objects in an ID3v2 tag, replacing its GEOB frames. The frames holding
+ * one of the objects are kept byte for byte, the others are written in UTF-8,
+ * or UTF-16 for ID3v2.3 tags. Other tags are left alone.
+ * @param tag - The tag
+ * @param objects - The objects to store, none removes the frames
+ * @param version - The ID3v2 version the tag is written in
+ */
+pub fn set_binary_objects(tag: &mut Tag, objects: &[BinaryObject], version: Id3v2Version) {
+  if tag.tag_type() != TagType::Id3v2 {
+    return;
+  }
+  let mut id3v2: Id3v2Tag = tag.clone().into();
+  let mut missing = objects.to_vec();
+  let kept: Vec<Frame<'static>> = geob_objects(&id3v2)
+    .filter_map(|(frame, object)| {
+      let i = missing.iter().position(|missing| *missing == object)?;
+      missing.remove(i);
+      Some(frame.clone())
+    })
+    .collect();
+  id3v2.retain(|frame| frame.id_str() != GEOB);
+  let encoding = match version {
+    Id3v2Version::V3 => TextEncoding::UTF16,
+    Id3v2Version::V4 => TextEncoding::UTF8,
+  };
+  let written = missing.into_iter().map(|object| {
+    let geob = GeneralEncapsulatedObject::new(
+      encoding,
+      object.mime_type,
+      object.filename,
+      Some(object.description),
+      object.data,
+    );
+    Frame::Binary(BinaryFrame::new(
+      FrameId::Valid(Cow::Borrowed(GEOB)),
+      geob.as_bytes(),
+    ))
+  });
+  for frame in kept.into_iter().chain(written) {
+    id3v2.insert(frame);
+  }
+  // converting back keeps the frames in the frames written along with the tag
+  *tag = id3v2.into();
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_binary_objects_in_tags() {
+    let markers = BinaryObject {
+      description: "Serato Markers2".to_string(),
+      mime_type: Some("application/octet-stream".to_string()),
+      filename: None,
+      data: vec![0x01, 0x01, 0x00, 0xFF],
+    };
+    let overview = BinaryObject {
+      description: "Serato Overview".to_string(),
+      data: vec![0x01, 0x05],
+      ..Default::default()
+    };
+    let mut tag = Tag::new(TagType::Id3v2);
+    assert_eq!(binary_objects_from_tag(&tag), None);
+    let objects = vec![markers.clone(), overview.clone()];
+    set_binary_objects(&mut tag, &objects, Id3v2Version::V4);
+    assert_eq!(binary_objects_from_tag(&tag), Some(objects));
+
+    set_binary_objects(&mut tag, std::slice::from_ref(&overview), Id3v2Version::V3);
+    assert_eq!(binary_objects_from_tag(&tag), Some(vec![overview]));
+    set_binary_objects(&mut tag, &[], Id3v2Version::V4);
+    assert_eq!(binary_objects_from_tag(&tag), None);
+
+    let mut v3 = Tag::new(TagType::Id3v2);
+    set_binary_objects(&mut v3, std::slice::from_ref(&markers), Id3v2Version::V3);
+    assert_eq!(binary_objects_from_tag(&v3), Some(vec![markers.clone()]));
+
+    let mut vorbis = Tag::new(TagType::VorbisComments);
+    set_binary_objects(&mut vorbis, &[markers], Id3v2Version::V4);
+    assert_eq!(binary_objects_from_tag(&vorbis), None);
+    assert_eq!(vorbis.item_count(), 0);
+  }
+}
@@ -0,0 +1,48 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::Notify;
+
+pub const ABORTED: &str = "The operation was aborted";
+
+#[derive(Debug, Default)]
+struct State {
+  cancelled: AtomicBool,
+  notify: Notify,
+}
+
+// Set from the JS thread when an AbortSignal fires; long operations check it
+// between files, a file already being processed is finished
+#[derive(Debug, Clone, Default)]
+pub struct Cancellation(Arc<State>);
+
+impl Cancellation {
+  pub fn cancel(&self) {
+    self.0.cancelled.store(true, Ordering::SeqCst);
+    self.0.notify.notify_waiters();
+  }
+
+  pub fn is_cancelled(&self) -> bool {
+    self.0.cancelled.load(Ordering::SeqCst)
+  }
+
+  // the abort error once cancelled
+  pub fn check(&self) -> Result<(), String> {
+    if self.is_cancelled() {
+      Err(ABORTED.to_string())
+    } else {
+      Ok(())
+    }
+  }
+
+  // resolves once cancelled
+  pub async fn cancelled(&self) {
+    loop {
+      // registered before the check, so a cancel in between isn't missed
+      let notified = self.0.notify.notified();
+      if self.is_cancelled() {
+        return;
+      }
+      notified.await;
+    }
+  }
+}
@@ -0,0 +1,303 @@
+use crate::util::{Image, MimeInference};
+use lofty::id3::v2::util::synchsafe::SynchsafeInteger;
+use lofty::id3::v2::{
+  AttachedPictureFrame, BinaryFrame, ExtendedUrlFrame, Frame, FrameFlags, FrameId, Id3v2Tag,
+  Id3v2Version, TextInformationFrame,
+};
+use lofty::tag::Tag;
+use lofty::TextEncoding;
+use std::borrow::Cow;
+
+const CHAP: &str = "CHAP";
+const CTOC: &str = "CTOC";
+const TITLE: &str = "TIT2";
+const URL: &str = "WXXX";
+const PICTURE: &str = "APIC";
+// the element id of the table of contents written along with the chapters
+const TOC_ID: &str = "toc";
+// CTOC flags: top-level, ordered
+const TOC_FLAGS: u8 = 0b11;
+// a CHAP frame without byte offsets into the audio
+const NO_OFFSET: [u8; 4] = [0xFF; 4];
+// the CTOC entry count is a single byte
+const MAX_CHAPTERS: usize = 255;
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Chapter {
+  // from the start of the audio
+  pub start_ms: u32,
+  pub end_ms: u32,
+  pub title: Option<String>,
+  // only stored in ID3v2
+  pub url: Option<String>,
+  // only stored in ID3v2
+  pub image: Option<Image>,
+}
+
+// the sub-frames embedded in a CHAP frame, by id. Their sizes are synchsafe in
+// ID3v2.4 tags and plain integers in ID3v2.3 tags, which a frame doesn't tell.
+fn sub_frames(data: &[u8], synchsafe: bool) -> Option<Vec<(&str, &[u8])>> {
+  let mut frames = Vec::new();
+  let mut rest = data;
+  // anything shorter than a frame header is padding
+  while rest.len() >= 10 && rest[0] != 0 {
+    let id = std::str::from_utf8(&rest[..4]).ok()?;
+    let size = u32::from_be_bytes(rest[4..8].try_into().ok()?);
+    let size = if synchsafe {
+      if size & 0x8080_8080 != 0 {
+        return None;
+      }
+      size.unsynch()
+    } else {
+      size
+    } as usize;
+    frames.push((id, rest.get(10..10 + size)?));
+    rest = &rest[10 + size..];
+  }
+  Some(frames)
+}
+
+fn parse_chap(data: &[u8]) -> Option<Chapter> {
+  let id_end = data.iter().position(|b| *b == 0)?;
+  let times = data.get(id_end + 1..id_end + 17)?;
+  let sub_data = &data[id_end + 17..];
+  let mut chapter = Chapter {
+    start_ms: u32::from_be_bytes(times[0..4].try_into().ok()?),
+    end_ms: u32::from_be_bytes(times[4..8].try_into().ok()?),
+    ..Default::default()
+  };
+  let frames = sub_frames(sub_data, true)
+    .or_else(|| sub_frames(sub_data, false))
+    .unwrap_or_default();
+  for (id, mut body) in frames {
+    let flags = FrameFlags::default();
+    match id {
+      TITLE => {
+        chapter.title = TextInformationFrame::parse(
+          &mut body,
+          FrameId::Valid(Cow::Borrowed(TITLE)),
+          flags,
+          Id3v2Version::V4,
+        )
+        .ok()
+        .flatten()
+        .map(|frame| frame.value)
+      }
+      URL => {
+        chapter.url = ExtendedUrlFrame::parse(&mut body, flags, Id3v2Version::V4)
+          .ok()
+          .flatten()
+          .map(|frame| frame.content)
+      }
+      PICTURE => {
+        chapter.image = AttachedPictureFrame::parse(&mut body, flags, Id3v2Version::V4)
+          .ok()
+          .map(|frame| Image::from_picture(&frame.picture))
+      }
+      _ => {}
+    }
+  }
+  Some(chapter)
+}
+
+/**
+ * Read the ID3v2 CHAP frames of a tag. Sub-frames of either ID3v2 version are
+ * read, so are the chapters of ID3v2.3 tags.
+ * @param tag - The ID3v2 tag
+ * @returns The chapters ordered by start time
+ */
+pub fn chapters_from_tag(tag: &Tag) -> Vec<Chapter> {
+  let id3v2: Id3v2Tag = tag.clone().into();
+  let mut chapters: Vec<Chapter> = id3v2
+    .into_iter()
+    .filter_map(|frame| match frame {
+      Frame::Binary(binary) if binary.id().as_str() == CHAP => parse_chap(&binary.data),
+      _ => None,
+    })
+    .collect();
+  chapters.sort_by_key(|chapter| chapter.start_ms);
+  chapters
+}
+
+/**
+ * Check that chapters can be stored, before anything is written
+ * @param chapters - The chapters about to be written
+ */
+pub fn validate(chapters: &[Chapter]) -> Result<(), String> {
+  if chapters.len() > MAX_CHAPTERS {
+    return Err(format!(
+      "Too many chapters: {}, at most {} are supported",
+      chapters.len(),
+      MAX_CHAPTERS
+    ));
+  }
+  match chapters
+    .iter()
+    .position(|chapter| chapter.end_ms < chapter.start_ms)
+  {
+    Some(i) => Err(format!("Chapter {} ends before it starts", i + 1)),
+    None => Ok(()),
+  }
+}
+
+// an ID3v2.4 frame with its header, as embedded in CHAP and CTOC frames
+fn sub_frame(id: &str, body: Vec<u8>) -> Result<Vec<u8>, String> {
+  let size = u32::try_from(body.len())
+    .ok()
+    .and_then(|size| size.synch().ok())
+    .ok_or(format!("The {} frame of a chapter is too large", id))?;
+  let mut frame = id.as_bytes().to_vec();
+  frame.extend_from_slice(&size.to_be_bytes());
+  frame.extend_from_slice(&[0, 0]);
+  frame.extend(body);
+  Ok(frame)
+}
+
+fn element_id(i: usize) -> String {
+  format!("chp{}", i)
+}
+
+fn chap_data(i: usize, chapter: &Chapter) -> Result<Vec<u8>, String> {
+  let mut data = element_id(i).into_bytes();
+  data.push(0);
+  data.extend_from_slice(&chapter.start_ms.to_be_bytes());
+  data.extend_from_slice(&chapter.end_ms.to_be_bytes());
+  data.extend_from_slice(&NO_OFFSET);
+  data.extend_from_slice(&NO_OFFSET);
+  if let Some(title) = &chapter.title {
+    let frame = TextInformationFrame::new(
+      FrameId::Valid(Cow::Borrowed(TITLE)),
+      TextEncoding::UTF8,
+      title.clone(),
+    );
+    data.extend(sub_frame(TITLE, frame.as_bytes(false))?);
+  }
+  if let Some(url) = &chapter.url {
+    let frame = ExtendedUrlFrame::new(TextEncoding::UTF8, String::new(), url.clone());
+    data.extend(sub_frame(URL, frame.as_bytes(false))?);
+  }
+  if let Some(image) = &chapter.image {
+    let frame = AttachedPictureFrame::new(
+      TextEncoding::UTF8,
+      image.to_picture(MimeInference::default()),
+    );
+    let body = frame
+      .as_bytes(Id3v2Version::V4)
+      .map_err(|e| format!("Failed to encode chapter image: {}", e))?;
+    data.extend(sub_frame(PICTURE, body)?);
+  }
+  Ok(data)
+}
+
+fn ctoc_data(count: usize) -> Vec<u8> {
+  let mut data = TOC_ID.as_bytes().to_vec();
+  data.extend_from_slice(&[0, TOC_FLAGS, count as u8]);
+  for i in 0..count {
+    data.extend(element_id(i).into_bytes());
+    data.push(0);
+  }
+  data
+}
+
+fn binary_frame(id: &'static str, data: Vec<u8>) -> Result<Frame<'static>, String> {
+  let id = FrameId::new(id).map_err(|e| format!("Invalid frame id: {}", e))?;
+  Ok(Frame::Binary(BinaryFrame::new(id, data)))
+}
+
+/**
+ * Store chapters in an ID3v2 tag, replacing its CHAP and CTOC frames: one CHAP
+ * frame per chapter, ordered by start time, and a top-level CTOC frame listing
+ * them. No chapters only removes the frames.
+ * @param tag - The ID3v2 tag
+ * @param chapters - The chapters to store
+ */
+pub fn set_chapters(tag: &mut Tag, chapters: &[Chapter]) -> Result<(), String> {
+  validate(chapters)?;
+  let mut chapters = chapters.to_vec();
+  chapters.sort_by_key(|chapter| chapter.start_ms);
+
+  let mut id3v2: Id3v2Tag = tag.clone().into();
+  id3v2.retain(|frame| frame.id_str() != CHAP && frame.id_str() != CTOC);
+  if !chapters.is_empty() {
+    id3v2.insert(binary_frame(CTOC, ctoc_data(chapters.len()))?);
+    for (i, chapter) in chapters.iter().enumerate() {
+      id3v2.insert(binary_frame(CHAP, chap_data(i, chapter)?)?);
+    }
+  }
+  // converting back keeps the frames in the frames written along with the tag
+  *tag = id3v2.into();
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use lofty::tag::TagType;
+
+  fn chapter(start_ms: u32, end_ms: u32, title: &str) -> Chapter {
+    Chapter {
+      start_ms,
+      end_ms,
+      title: Some(title.to_string()),
+      ..Default::default()
+    }
+  }
+
+  #[test]
+  fn test_chapters_in_tags() {
+    let intro = Chapter {
+      url: Some("https://example.com/intro".to_string()),
+      ..chapter(0, 30000, "Intro")
+    };
+    let news = chapter(30000, 95500, "News");
+    let mut tag = Tag::new(TagType::Id3v2);
+    set_chapters(&mut tag, &[news.clone(), intro.clone()]).unwrap();
+    assert_eq!(chapters_from_tag(&tag), vec![intro, news.clone()]);
+
+    let id3v2: Id3v2Tag = tag.clone().into();
+    let toc = id3v2
+      .into_iter()
+      .find_map(|frame| match frame {
+        Frame::Binary(binary) if binary.id().as_str() == CTOC => Some(binary.data.clone()),
+        _ => None,
+      })
+      .unwrap();
+    assert_eq!(toc, b"toc\0\x03\x02chp0\0chp1\0");
+
+    set_chapters(&mut tag, &[]).unwrap();
+    assert!(chapters_from_tag(&tag).is_empty());
+  }
+
+  #[test]
+  fn test_id3v23_sub_frames() {
+    // a 200 byte title, whose size isn't the same synchsafe and plain
+    let title = "a".repeat(199);
+    let mut data = b"ch1\0".to_vec();
+    data.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0x03, 0xE8]);
+    data.extend_from_slice(&NO_OFFSET);
+    data.extend_from_slice(&NO_OFFSET);
+    data.extend_from_slice(b"TIT2\x00\x00\x00\xC8\x00\x00\x00");
+    data.extend(title.as_bytes());
+    assert_eq!(
+      parse_chap(&data),
+      Some(Chapter {
+        end_ms: 1000,
+        title: Some(title),
+        ..Default::default()
+      })
+    );
+  }
+
+  #[test]
+  fn test_validate_chapters() {
+    assert_eq!(
+      validate(&[chapter(0, 1000, "One"), chapter(2000, 1500, "Two")]).unwrap_err(),
+      "Chapter 2 ends before it starts"
+    );
+    let many = vec![chapter(0, 0, "Chapter"); 256];
+    assert_eq!(
+      validate(&many).unwrap_err(),
+      "Too many chapters: 256, at most 255 are supported"
+    );
+  }
+}
@@ -0,0 +1,216 @@
+use crate::util::{in_file, open_file};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use rusty_chromaprint::{Configuration, FingerprintCompressor, Fingerprinter};
+use std::path::Path;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+// fpcalc, and so AcoustID, only fingerprints the start of a track
+const MAX_FINGERPRINT_SECONDS: u64 = 120;
+
+// An AcoustID fingerprint, as fpcalc prints it
+#[derive(Debug, Clone, PartialEq)]
+pub struct AcoustidFingerprint {
+  // the compressed fingerprint in URL-safe base64
+  pub fingerprint: String,
+  // whole seconds, as the AcoustID lookup takes it
+  pub duration: u32,
+}
+
+fn decode_error(e: SymphoniaError) -> String {
+  format!("Failed to decode audio: {}", e)
+}
+
+// decodes the audio, feeding the first two minutes to the fingerprinter; the
+// rest is only decoded when the container doesn't tell the length
+fn fingerprint_source(
+  source: MediaSourceStream,
+  hint: &Hint,
+) -> Result<AcoustidFingerprint, String> {
+  let probed = symphonia::default::get_probe()
+    .format(
+      hint,
+      source,
+      &FormatOptions::default(),
+      &MetadataOptions::default(),
+    )
+    .map_err(decode_error)?;
+  let mut format = probed.format;
+  let track = format
+    .tracks()
+    .iter()
+    .find(|track| track.codec_params.codec != CODEC_TYPE_NULL)
+    .ok_or_else(|| "Failed to decode audio: no audio track".to_string())?;
+  let track_id = track.id;
+  let params = track.codec_params.clone();
+  let sample_rate = params
+    .sample_rate
+    .ok_or_else(|| "Failed to decode audio: unknown sample rate".to_string())?;
+  let mut decoder = symphonia::default::get_codecs()
+    .make(&params, &DecoderOptions::default())
+    .map_err(decode_error)?;
+
+  let config = Configuration::preset_test2();
+  let mut printer = Fingerprinter::new(&config);
+  let max_frames = MAX_FINGERPRINT_SECONDS * u64::from(sample_rate);
+  let mut frames = 0u64;
+  let mut started = false;
+  loop {
+    if frames >= max_frames && params.n_frames.is_some() {
+      break;
+    }
+    let packet = match format.next_packet() {
+      Ok(packet) => packet,
+      Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+      Err(e) => return Err(decode_error(e)),
+    };
+    if packet.track_id() != track_id {
+      continue;
+    }
+    let decoded = match decoder.decode(&packet) {
+      Ok(decoded) => decoded,
+      // a corrupt packet is skipped, as players do
+      Err(SymphoniaError::DecodeError(_)) => continue,
+      Err(e) => return Err(decode_error(e)),
+    };
+    let spec = *decoded.spec();
+    let channels = spec.channels.count();
+    if !started {
+      printer
+        .start(spec.rate, channels as u32)
+        .map_err(|e| format!("Failed to decode audio: {:?}", e))?;
+      started = true;
+    }
+    let remaining = max_frames.saturating_sub(frames) as usize;
+    if remaining > 0 {
+      let mut samples = SampleBuffer::<i16>::new(decoded.capacity() as u64, spec);
+      samples.copy_interleaved_ref(decoded.clone());
+      let taken = remaining.min(decoded.frames()) * channels;
+      printer.consume(&samples.samples()[..taken]);
+    }
+    frames += decoded.frames() as u64;
+  }
+  if !started {
+    return Err("Failed to decode audio: no audio frames".to_string());
+  }
+  printer.finish();
+  if printer.fingerprint().is_empty() {
+    return Err("Audio too short to fingerprint".to_string());
+  }
+
+  let frames = params.n_frames.unwrap_or(frames);
+  let compressed = FingerprintCompressor::from(&config).compress(printer.fingerprint());
+  Ok(AcoustidFingerprint {
+    fingerprint: URL_SAFE_NO_PAD.encode(compressed),
+    duration: (frames / u64::from(sample_rate)) as u32,
+  })
+}
+
+/**
+ * Compute the Chromaprint fingerprint of a file's audio, which AcoustID looks
+ * up recordings by. Only the audio is decoded, so the tags don't change it.
+ * @param file_path - The path to the audio file
+ */
+pub async fn compute_fingerprint(
+  file_path: impl AsRef<Path>,
+) -> Result<AcoustidFingerprint, String> {
+  tokio::task::spawn_blocking(move || {
+    let path = file_path.as_ref();
+    let file = open_file(path)?;
+    let mut hint = Hint::new();
+    if let Some(extension) = path.extension().and_then(|extension| extension.to_str()) {
+      hint.with_extension(extension);
+    }
+    let source = MediaSourceStream::new(Box::new(file), Default::default());
+    fingerprint_source(source, &hint).map_err(in_file(path))
+  })
+  .await
+  .map_err(|e| format!("Fingerprint task failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_support::{fixture_path, write_fixture};
+  use crate::util::{self, AudioTags};
+
+  const RATE: u32 = 11025;
+
+  // A WAV file playing each note, in Hz, for a second: 16 bit mono PCM
+  fn melody_wav(notes: &[f64]) -> Vec<u8> {
+    let mut data = Vec::new();
+    for note in notes {
+      for i in 0..RATE {
+        let t = f64::from(i) / f64::from(RATE);
+        let sample = (8000.0 * (2.0 * std::f64::consts::PI * note * t).sin()) as i16;
+        data.extend_from_slice(&sample.to_le_bytes());
+      }
+    }
+    let mut fmt = vec![1, 0, 1, 0];
+    fmt.extend_from_slice(&RATE.to_le_bytes());
+    fmt.extend_from_slice(&(RATE * 2).to_le_bytes());
+    fmt.extend_from_slice(&[2, 0, 16, 0]);
+    let mut wav = b"RIFF".to_vec();
+    wav.extend_from_slice(&((data.len() + 36) as u32).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&(fmt.len() as u32).to_le_bytes());
+    wav.extend(fmt);
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    wav.extend(data);
+    wav
+  }
+
+  const SCALE: [f64; 8] = [261.6, 293.7, 329.6, 349.2, 392.0, 440.0, 493.9, 523.3];
+
+  #[tokio::test]
+  async fn test_compute_fingerprint() {
+    let dir = tempfile::tempdir().unwrap();
+    let up = write_fixture(dir.path(), "up.wav", &melody_wav(&SCALE));
+    let fingerprint = compute_fingerprint(up).await.unwrap();
+    // algorithm 2, as fpcalc: the first byte of the compressed data
+    assert!(fingerprint.fingerprint.starts_with("AQ"));
+    assert!(fingerprint.fingerprint.len() > 10);
+    assert_eq!(fingerprint.duration, 8);
+
+    let mut reversed = SCALE;
+    reversed.reverse();
+    let down = write_fixture(dir.path(), "down.wav", &melody_wav(&reversed));
+    let other = compute_fingerprint(down).await.unwrap();
+    assert_ne!(other.fingerprint, fingerprint.fingerprint);
+  }
+
+  #[tokio::test]
+  async fn test_compute_fingerprint_ignores_tags() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_fixture(dir.path(), "song.wav", &melody_wav(&SCALE));
+    let before = compute_fingerprint(path.clone()).await.unwrap();
+
+    let tags = AudioTags {
+      title: Some("Retagged".to_string()),
+      ..Default::default()
+    };
+    util::write_tags(path.clone(), tags).await.unwrap();
+    assert_eq!(compute_fingerprint(path).await.unwrap(), before);
+  }
+
+  #[tokio::test]
+  async fn test_compute_fingerprint_errors() {
+    let path = fixture_path("music/notes-A4.mp3");
+    let e = compute_fingerprint(path.to_string_lossy().to_string())
+      .await
+      .unwrap_err();
+    assert!(e.ends_with("Audio too short to fingerprint"), "{}", e);
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_fixture(dir.path(), "notes.txt", b"not audio at all");
+    let e = compute_fingerprint(path).await.unwrap_err();
+    assert!(e.contains("Failed to decode audio"), "{}", e);
+  }
+}
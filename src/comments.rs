@@ -0,0 +1,143 @@
+use lofty::tag::items::UNKNOWN_LANGUAGE;
+use lofty::tag::{ItemKey, ItemValue, Tag, TagItem};
+
+// A comment with the language and description ID3v2 COMM frames carry, e.g.
+// the iTunNORM comment of iTunes next to the comment of the user
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Comment {
+  // ISO-639-2 code, only stored in ID3v2
+  pub language: Option<String>,
+  // only stored in ID3v2, None for the comment shown by players
+  pub description: Option<String>,
+  pub text: String,
+}
+
+fn comment_item(comment: &Comment) -> TagItem {
+  let mut item = TagItem::new(ItemKey::Comment, ItemValue::Text(comment.text.clone()));
+  // anything but a 3 letter code is stored as an undetermined language
+  if let Some(language) = comment
+    .language
+    .as_deref()
+    .filter(|language| language.chars().all(|c| c.is_ascii_alphabetic()))
+    .and_then(|language| language.as_bytes().try_into().ok())
+  {
+    item.set_lang(language);
+  }
+  if let Some(description) = comment.description.clone() {
+    item.set_description(description);
+  }
+  item
+}
+
+/**
+ * Read every comment of a tag, in tag order
+ * @param tag - The primary tag
+ */
+pub fn comments_from_tag(tag: &Tag) -> Vec<Comment> {
+  tag
+    .items()
+    .filter(|item| *item.key() == ItemKey::Comment)
+    .filter_map(|item| {
+      let language = *item.lang();
+      Some(Comment {
+        language: (language != UNKNOWN_LANGUAGE)
+          .then(|| String::from_utf8_lossy(&language).to_string()),
+        description: Some(item.description().to_string()).filter(|d| !d.is_empty()),
+        text: item.value().text()?.to_string(),
+      })
+    })
+    .collect()
+}
+
+/**
+ * The comment players show: the first one without a description
+ * @param tag - The primary tag
+ */
+pub fn default_comment(tag: &Tag) -> Option<String> {
+  comments_from_tag(tag)
+    .into_iter()
+    .find(|comment| comment.description.is_none())
+    .map(|comment| comment.text)
+}
+
+/**
+ * Whether a tag holds comments with a language or a description
+ * @param tag - The primary tag
+ */
+pub fn has_comment_details(tag: &Tag) -> bool {
+  tag.items().any(|item| {
+    *item.key() == ItemKey::Comment
+      && (*item.lang() != UNKNOWN_LANGUAGE || !item.description().is_empty())
+  })
+}
+
+/**
+ * Replace every comment of a tag
+ * @param tag - The primary tag
+ * @param comments - The comments to store, in order
+ */
+pub fn set_comments(tag: &mut Tag, comments: &[Comment]) {
+  tag.remove_key(&ItemKey::Comment);
+  for comment in comments {
+    tag.push_unchecked(comment_item(comment));
+  }
+}
+
+/**
+ * Replace the comments without a description, the others are kept
+ * @param tag - The primary tag
+ * @param text - The comment players show
+ */
+pub fn set_default_comment(tag: &mut Tag, text: &str) {
+  tag.retain(|item| *item.key() != ItemKey::Comment || !item.description().is_empty());
+  tag.push_unchecked(comment_item(&Comment {
+    text: text.to_string(),
+    ..Default::default()
+  }));
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use lofty::tag::TagType;
+
+  #[test]
+  fn test_comments_in_tags() {
+    let normalization = Comment {
+      language: Some("eng".to_string()),
+      description: Some("iTunNORM".to_string()),
+      text: " 0000044E 00000590".to_string(),
+    };
+    let mut tag = Tag::new(TagType::Id3v2);
+    set_comments(
+      &mut tag,
+      &[
+        normalization.clone(),
+        Comment {
+          text: "Old".to_string(),
+          ..Default::default()
+        },
+      ],
+    );
+    set_default_comment(&mut tag, "Ripped from vinyl");
+    assert_eq!(default_comment(&tag), Some("Ripped from vinyl".to_string()));
+    assert_eq!(
+      comments_from_tag(&tag),
+      vec![
+        normalization,
+        Comment {
+          text: "Ripped from vinyl".to_string(),
+          ..Default::default()
+        },
+      ]
+    );
+
+    let invalid = Comment {
+      language: Some("english".to_string()),
+      text: "Text".to_string(),
+      ..Default::default()
+    };
+    set_comments(&mut tag, &[invalid]);
+    assert_eq!(comments_from_tag(&tag)[0].language, None);
+  }
+}
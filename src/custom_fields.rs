@@ -0,0 +1,215 @@
+use crate::rating;
+use lofty::tag::{ItemKey, ItemValue, Tag, TagItem, TagType};
+use std::collections::BTreeMap;
+
+// The `mean` of the MP4 freeform atoms written by iTunes and most taggers
+const MP4_FREEFORM_PREFIX: &str = "----:com.apple.iTunes:";
+
+// FLAC pictures stored as Vorbis comments, see placement.rs
+const FLAC_PICTURE_KEY: &str = "METADATA_BLOCK_PICTURE";
+
+// the keys AudioTags has a field for
+fn is_standard_key(key: &ItemKey) -> bool {
+  if let ItemKey::Unknown(name) = key {
+    return rating::is_rating_field(name);
+  }
+  matches!(
+    key,
+    ItemKey::TrackTitle
+      | ItemKey::TrackArtist
+      | ItemKey::TrackArtists
+      | ItemKey::AlbumTitle
+      | ItemKey::Year
+      | ItemKey::RecordingDate
+      | ItemKey::Genre
+      | ItemKey::TrackNumber
+      | ItemKey::TrackTotal
+      | ItemKey::DiscNumber
+      | ItemKey::DiscTotal
+      | ItemKey::AlbumArtist
+      | ItemKey::Comment
+      | ItemKey::Composer
+      | ItemKey::Lyricist
+      | ItemKey::Conductor
+      | ItemKey::Remixer
+      | ItemKey::Publisher
+      | ItemKey::Label
+      | ItemKey::CopyrightMessage
+      | ItemKey::Lyrics
+      | ItemKey::Isrc
+      | ItemKey::Barcode
+      | ItemKey::CatalogNumber
+      | ItemKey::MusicBrainzRecordingId
+      | ItemKey::MusicBrainzTrackId
+      | ItemKey::MusicBrainzReleaseId
+      | ItemKey::MusicBrainzReleaseGroupId
+      | ItemKey::MusicBrainzArtistId
+      | ItemKey::MusicBrainzReleaseArtistId
+      | ItemKey::ReplayGainTrackGain
+      | ItemKey::ReplayGainTrackPeak
+      | ItemKey::ReplayGainAlbumGain
+      | ItemKey::ReplayGainAlbumPeak
+      | ItemKey::Bpm
+      | ItemKey::IntegerBpm
+      | ItemKey::InitialKey
+      | ItemKey::Mood
+      | ItemKey::Popularimeter
+      | ItemKey::FlagCompilation
+      | ItemKey::ContentGroup
+      | ItemKey::AppleId3v2ContentGroup
+      | ItemKey::Work
+      | ItemKey::Movement
+      | ItemKey::MovementNumber
+      | ItemKey::MovementTotal
+      | ItemKey::TrackSubtitle
+      | ItemKey::SetSubtitle
+      | ItemKey::TrackTitleSortOrder
+      | ItemKey::AlbumTitleSortOrder
+      | ItemKey::TrackArtistSortOrder
+      | ItemKey::AlbumArtistSortOrder
+      | ItemKey::ComposerSortOrder
+      | ItemKey::EncoderSoftware
+      | ItemKey::EncoderSettings
+  )
+}
+
+// the name of a key in the tag's own format, e.g. "TXXX" descriptions for
+// ID3v2 or Vorbis comment field names
+fn field_name(key: &ItemKey, tag_type: TagType) -> Option<String> {
+  if let ItemKey::Unknown(name) = key {
+    if name == FLAC_PICTURE_KEY {
+      return None;
+    }
+  }
+  let name = key.map_key(tag_type, true)?;
+  Some(
+    name
+      .strip_prefix(MP4_FREEFORM_PREFIX)
+      .unwrap_or(name)
+      .to_string(),
+  )
+}
+
+pub(crate) fn item_key(name: &str, tag_type: TagType) -> ItemKey {
+  match ItemKey::from_key(tag_type, name) {
+    ItemKey::Unknown(_) if tag_type == TagType::Mp4Ilst && !name.starts_with("----:") => {
+      ItemKey::from_key(tag_type, &format!("{}{}", MP4_FREEFORM_PREFIX, name))
+    }
+    key => key,
+  }
+}
+
+/**
+ * Collect the text items of a tag that AudioTags has no field for
+ * @param tag - The tag to read
+ * @returns The values by field name, None when there are none
+ */
+pub fn read_custom_fields(tag: &Tag) -> Option<BTreeMap<String, Vec<String>>> {
+  let mut fields: BTreeMap<String, Vec<String>> = BTreeMap::new();
+  for item in tag.items() {
+    let (key, ItemValue::Text(value)) = (item.key(), item.value()) else {
+      continue;
+    };
+    if is_standard_key(key) {
+      continue;
+    }
+    if let Some(name) = field_name(key, tag.tag_type()) {
+      fields.entry(name).or_default().push(value.clone());
+    }
+  }
+  (!fields.is_empty()).then_some(fields)
+}
+
+/**
+ * Write custom fields to a tag. Every listed field replaces the values already
+ * stored under its name, an empty list removes the field, and fields that are
+ * not listed are kept.
+ * @param tag - The tag to update
+ * @param fields - The values by field name
+ */
+pub fn write_custom_fields(tag: &mut Tag, fields: &BTreeMap<String, Vec<String>>) {
+  let tag_type = tag.tag_type();
+  for (name, values) in fields {
+    let key = item_key(name, tag_type);
+    tag.remove_key(&key);
+    for value in values {
+      tag.push_unchecked(TagItem::new(key.clone(), ItemValue::Text(value.clone())));
+    }
+  }
+}
+
+/**
+ * Copy the text items lofty has no key for to a tag of another format, under
+ * the same field name, e.g. a TXXX frame to a Vorbis comment
+ * @param source - The tag to copy from
+ * @param dest - The tag to copy to
+ */
+pub fn copy_unknown_fields(source: &Tag, dest: &mut Tag) {
+  for item in source.items() {
+    let (key @ ItemKey::Unknown(_), ItemValue::Text(value)) = (item.key(), item.value()) else {
+      continue;
+    };
+    if let Some(name) = field_name(key, source.tag_type()) {
+      let key = item_key(&name, dest.tag_type());
+      dest.push_unchecked(TagItem::new(key, ItemValue::Text(value.clone())));
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn fields(entries: &[(&str, &[&str])]) -> BTreeMap<String, Vec<String>> {
+    entries
+      .iter()
+      .map(|(name, values)| {
+        (
+          name.to_string(),
+          values.iter().map(|value| value.to_string()).collect(),
+        )
+      })
+      .collect()
+  }
+
+  #[test]
+  fn test_custom_fields_round_trip() {
+    let custom = fields(&[
+      ("RELEASECOUNTRY", &["GB"]),
+      ("EDITION", &["Deluxe", "Remastered"]),
+    ]);
+    for tag_type in [TagType::VorbisComments, TagType::Mp4Ilst] {
+      let mut tag = Tag::new(tag_type);
+      tag.insert_text(ItemKey::TrackTitle, "Title".to_string());
+      write_custom_fields(&mut tag, &custom);
+      assert_eq!(
+        read_custom_fields(&tag),
+        Some(custom.clone()),
+        "{:?}",
+        tag_type
+      );
+    }
+
+    // MP4 names are freeform atoms of the iTunes namespace
+    let mut tag = Tag::new(TagType::Mp4Ilst);
+    write_custom_fields(&mut tag, &fields(&[("MY FIELD", &["value"])]));
+    assert_eq!(
+      tag.items().next().unwrap().key(),
+      &ItemKey::Unknown("----:com.apple.iTunes:MY FIELD".to_string())
+    );
+  }
+
+  #[test]
+  fn test_write_custom_fields_replaces_and_removes() {
+    let mut tag = Tag::new(TagType::VorbisComments);
+    write_custom_fields(
+      &mut tag,
+      &fields(&[("KEEP", &["1"]), ("REPLACE", &["old"]), ("REMOVE", &["x"])]),
+    );
+    write_custom_fields(&mut tag, &fields(&[("REPLACE", &["new"]), ("REMOVE", &[])]));
+    assert_eq!(
+      read_custom_fields(&tag),
+      Some(fields(&[("KEEP", &["1"]), ("REPLACE", &["new"])]))
+    );
+  }
+}
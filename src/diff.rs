@@ -0,0 +1,124 @@
+use crate::artwork::sha256_hex;
+use crate::export;
+use crate::util::{self, AudioTags, WriteTagsOptions};
+use serde_json::{json, Value};
+use std::fs;
+use std::path::Path;
+
+// A field a write would change, with its value before and after; a missing
+// value is None
+#[derive(Debug, PartialEq, Clone)]
+pub struct TagChange {
+  // the camelCase name of the field, as in exports and manifests
+  pub field: String,
+  pub old_value: Option<Value>,
+  pub new_value: Option<Value>,
+}
+
+// the pictures of read tags, described by their hash rather than their bytes
+fn pictures(tags: &AudioTags) -> Value {
+  let images = tags
+    .all_images
+    .iter()
+    .flatten()
+    .chain(tags.image.iter().filter(|_| tags.all_images.is_none()));
+  let pictures: Vec<Value> = images
+    .map(|image| {
+      json!({
+        "picType": format!("{:?}", image.pic_type),
+        "mimeType": image.mime_type,
+        "description": image.description,
+        "sha256": sha256_hex(&image.data),
+      })
+    })
+    .collect();
+  if pictures.is_empty() {
+    Value::Null
+  } else {
+    Value::Array(pictures)
+  }
+}
+
+fn columns(tags: &AudioTags) -> Vec<(&'static str, Value)> {
+  let mut columns = export::tag_columns(tags);
+  columns.push(("pictures", pictures(tags)));
+  columns
+}
+
+/**
+ * The fields that differ between two sets of tags, in the order of the export
+ * columns
+ * @param old - The tags before
+ * @param new - The tags after
+ */
+pub fn changes(old: &AudioTags, new: &AudioTags) -> Vec<TagChange> {
+  columns(old)
+    .into_iter()
+    .zip(columns(new))
+    .filter(|((_, old_value), (_, new_value))| old_value != new_value)
+    .map(|((field, old_value), (_, new_value))| TagChange {
+      field: field.to_string(),
+      old_value: Some(old_value).filter(|value| !value.is_null()),
+      new_value: Some(new_value).filter(|value| !value.is_null()),
+    })
+    .collect()
+}
+
+/**
+ * Preview a write: the tags are written to a copy of the file in memory and
+ * read back, so the changes are those `write_tags_with_options` would make,
+ * including values the format can't hold. The file is not modified.
+ * @param file_path - The path to the audio file
+ * @param tags - The tags that would be written
+ * @param options - The options the write would use
+ * @returns The fields that would change
+ */
+pub async fn diff_tags(
+  file_path: impl AsRef<Path>,
+  tags: AudioTags,
+  options: WriteTagsOptions,
+) -> Result<Vec<TagChange>, String> {
+  let buffer = fs::read(&file_path).map_err(|e| format!("Failed to read file: {}", e))?;
+  let old = util::read_tags_from_buffer(buffer.clone()).await?;
+  let written = util::write_tags_to_buffer_with_options(buffer, tags, options).await?;
+  let new = util::read_tags_from_buffer(written).await?;
+  Ok(changes(&old, &new))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_support::{sample_mp3, write_fixture};
+
+  #[tokio::test]
+  async fn test_diff_tags() {
+    let dir = tempfile::tempdir().unwrap();
+    let tags = AudioTags {
+      title: Some("Old".to_string()),
+      artists: Some(vec!["A".to_string()]),
+      year: Some(1999),
+      ..Default::default()
+    };
+    let tagged = util::write_tags_to_buffer(sample_mp3(), tags.clone())
+      .await
+      .unwrap();
+    let file_path = write_fixture(dir.path(), "a.mp3", &tagged);
+
+    let new_tags = AudioTags {
+      title: Some("New".to_string()),
+      artists: Some(vec!["A".to_string(), "B".to_string()]),
+      album: Some("Album".to_string()),
+      ..tags
+    };
+    let changes = diff_tags(file_path.clone(), new_tags, WriteTagsOptions::default())
+      .await
+      .unwrap();
+    let fields: Vec<&str> = changes.iter().map(|change| change.field.as_str()).collect();
+    assert_eq!(fields, ["title", "artists", "album"]);
+    assert_eq!(changes[0].old_value, Some(json!("Old")));
+    assert_eq!(changes[0].new_value, Some(json!("New")));
+    assert_eq!(changes[1].new_value, Some(json!(["A", "B"])));
+    assert_eq!(changes[2].old_value, None);
+    assert_eq!(fs::read(file_path).unwrap(), tagged);
+  }
+}
@@ -0,0 +1,262 @@
+use crate::probe;
+use crate::util;
+use lofty::io::FileLike;
+use std::io::{Read, Seek, SeekFrom};
+use std::ops::Range;
+
+// the DSF header: "DSD ", its size, the size of the file and where the
+// metadata (an ID3v2 tag running to the end of the file) starts, 0 for none
+const DSF_HEADER_LEN: u64 = 28;
+// the DSDIFF header: "FRM8", the size of what follows, then "DSD "
+const DFF_HEADER_LEN: u64 = 16;
+const DFF_ID3_CHUNK: &[u8; 4] = b"ID3 ";
+
+// The DSD containers, which lofty doesn't know, holding an ID3v2 tag
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) enum Dsd {
+  Dsf,
+  Dff,
+}
+
+impl Dsd {
+  pub(crate) fn name(self) -> &'static str {
+    match self {
+      Dsd::Dsf => "Dsf",
+      Dsd::Dff => "Dff",
+    }
+  }
+}
+
+fn read_error(e: std::io::Error) -> String {
+  format!("Failed to read audio file: {}", e)
+}
+
+fn write_error(e: std::io::Error) -> String {
+  format!("Failed to write audio to buffer: {}", e)
+}
+
+/**
+ * The DSD container of a file, None for any other file, read errors left to
+ * whatever reads the file next
+ * @param reader - The file, rewound afterwards
+ */
+pub(crate) fn format<R: Read + Seek>(reader: &mut R) -> Option<Dsd> {
+  let mut header = [0; DFF_HEADER_LEN as usize];
+  let read = reader.rewind().and_then(|_| reader.read_exact(&mut header));
+  reader.rewind().ok()?;
+  read.ok()?;
+  match &header[..4] {
+    b"DSD " => Some(Dsd::Dsf),
+    b"FRM8" if &header[12..] == b"DSD " => Some(Dsd::Dff),
+    _ => None,
+  }
+}
+
+fn read_u64<R: Read>(reader: &mut R, big_endian: bool) -> Result<u64, String> {
+  let mut bytes = [0; 8];
+  reader.read_exact(&mut bytes).map_err(read_error)?;
+  Ok(if big_endian {
+    u64::from_be_bytes(bytes)
+  } else {
+    u64::from_le_bytes(bytes)
+  })
+}
+
+// where the tag of the file is, its chunk header included; an empty span at
+// the end of the file when it has none
+fn tag_span<R: Read + Seek>(reader: &mut R, format: Dsd) -> Result<Range<u64>, String> {
+  let len = reader.seek(SeekFrom::End(0)).map_err(read_error)?;
+  let malformed = || format!("Malformed {} file", format.name());
+  match format {
+    Dsd::Dsf => {
+      reader.seek(SeekFrom::Start(20)).map_err(read_error)?;
+      match read_u64(reader, false)? {
+        0 => Ok(len..len),
+        start if (DSF_HEADER_LEN..=len).contains(&start) => Ok(start..len),
+        _ => Err(malformed()),
+      }
+    }
+    Dsd::Dff => Ok(dff_chunk(reader, DFF_ID3_CHUNK, len)?.unwrap_or(len..len)),
+  }
+}
+
+// where the first chunk of `id` of a DFF file is, its header included
+fn dff_chunk<R: Read + Seek>(
+  reader: &mut R,
+  id: &[u8; 4],
+  len: u64,
+) -> Result<Option<Range<u64>>, String> {
+  let mut offset = DFF_HEADER_LEN;
+  while offset + 12 <= len {
+    reader.seek(SeekFrom::Start(offset)).map_err(read_error)?;
+    let mut chunk_id = [0; 4];
+    reader.read_exact(&mut chunk_id).map_err(read_error)?;
+    let size = read_u64(reader, true)?;
+    // chunks are padded to an even size
+    let end = (offset + 12)
+      .checked_add(size + (size & 1))
+      .filter(|end| *end <= len + (size & 1))
+      .ok_or_else(|| format!("Malformed {} file", Dsd::Dff.name()))?;
+    if &chunk_id == id {
+      return Ok(Some(offset..end.min(len)));
+    }
+    offset = end;
+  }
+  Ok(None)
+}
+
+/**
+ * Where the audio of a DSD file is: the content of the DSF data chunk or of
+ * the DFF sound chunk
+ * @param reader - The file
+ * @param format - Its container
+ */
+pub(crate) fn audio_span<R: Read + Seek>(
+  reader: &mut R,
+  format: Dsd,
+) -> Result<Range<u64>, String> {
+  let len = reader.seek(SeekFrom::End(0)).map_err(read_error)?;
+  let malformed = || format!("Malformed {} file", format.name());
+  match format {
+    Dsd::Dsf => {
+      // DSF chunk sizes count the 12 byte chunk header
+      let mut offset = DSF_HEADER_LEN;
+      while offset + 12 <= len {
+        reader.seek(SeekFrom::Start(offset)).map_err(read_error)?;
+        let mut id = [0; 4];
+        reader.read_exact(&mut id).map_err(read_error)?;
+        let size = read_u64(reader, false)?;
+        let end = offset
+          .checked_add(size)
+          .filter(|end| size >= 12 && *end <= len)
+          .ok_or_else(malformed)?;
+        if &id == b"data" {
+          return Ok(offset + 12..end);
+        }
+        offset = end;
+      }
+      Err(malformed())
+    }
+    Dsd::Dff => dff_chunk(reader, b"DSD ", len)?
+      .map(|span| span.start + 12..span.end)
+      .ok_or_else(malformed),
+  }
+}
+
+/**
+ * Read the ID3v2 tag of a DSD file
+ * @param reader - The file
+ * @param format - Its container
+ * @returns None when the file has no tag
+ */
+pub(crate) fn read_id3v2<R: Read + Seek>(
+  reader: &mut R,
+  format: Dsd,
+) -> Result<Option<Vec<u8>>, String> {
+  let span = tag_span(reader, format)?;
+  let start = match format {
+    Dsd::Dsf => span.start,
+    Dsd::Dff => span.start + 12,
+  };
+  if start >= span.end {
+    return Ok(None);
+  }
+  let mut tag = Vec::new();
+  reader.seek(SeekFrom::Start(start)).map_err(read_error)?;
+  reader
+    .by_ref()
+    .take(span.end - start)
+    .read_to_end(&mut tag)
+    .map_err(read_error)?;
+  let Some(size) = probe::id3v2_size(&tag) else {
+    return Ok(None);
+  };
+  tag.truncate(size as usize);
+  Ok(Some(tag))
+}
+
+/**
+ * Replace the ID3v2 tag of a DSD file, at the end of the file as players
+ * expect it
+ * @param file - The file
+ * @param format - Its container
+ * @param tag - The whole tag, empty to remove it
+ */
+pub(crate) fn write_id3v2<F: FileLike>(
+  file: &mut F,
+  format: Dsd,
+  tag: &[u8],
+) -> Result<(), String> {
+  let span = tag_span(file, format)?;
+  // whatever follows the tag moves up in its place
+  let mut tail = Vec::new();
+  file.seek(SeekFrom::Start(span.end)).map_err(write_error)?;
+  file.read_to_end(&mut tail).map_err(write_error)?;
+  file
+    .seek(SeekFrom::Start(span.start))
+    .map_err(write_error)?;
+  file.write_all(&tail).map_err(write_error)?;
+
+  let tag_start = span.start + tail.len() as u64;
+  let block = match format {
+    _ if tag.is_empty() => Vec::new(),
+    Dsd::Dsf => tag.to_vec(),
+    Dsd::Dff => {
+      let mut chunk = DFF_ID3_CHUNK.to_vec();
+      chunk.extend_from_slice(&(tag.len() as u64).to_be_bytes());
+      chunk.extend_from_slice(tag);
+      if tag.len() % 2 == 1 {
+        chunk.push(0);
+      }
+      chunk
+    }
+  };
+  file.write_all(&block).map_err(write_error)?;
+  let len = tag_start + block.len() as u64;
+  file
+    .truncate(len)
+    .map_err(|e| util::lofty_error("Failed to write audio to buffer", &e.into()))?;
+
+  match format {
+    Dsd::Dsf => {
+      let pointer = if block.is_empty() { 0 } else { tag_start };
+      file.seek(SeekFrom::Start(12)).map_err(write_error)?;
+      file.write_all(&len.to_le_bytes()).map_err(write_error)?;
+      file
+        .write_all(&pointer.to_le_bytes())
+        .map_err(write_error)?;
+    }
+    Dsd::Dff => {
+      file.seek(SeekFrom::Start(4)).map_err(write_error)?;
+      file
+        .write_all(&(len - 12).to_be_bytes())
+        .map_err(write_error)?;
+    }
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_support::{sample_dff, sample_dsf};
+  use std::io::Cursor;
+
+  #[test]
+  fn test_write_and_read_id3v2() {
+    for (data, format) in [(sample_dsf(), Dsd::Dsf), (sample_dff(), Dsd::Dff)] {
+      let mut file = Cursor::new(data.clone());
+      assert_eq!(super::format(&mut file), Some(format));
+      assert_eq!(read_id3v2(&mut file, format).unwrap(), None);
+
+      // an odd size, which DSDIFF pads
+      let mut tag = b"ID3\x04\0\0\0\0\0\x05".to_vec();
+      tag.extend_from_slice(&[0; 5]);
+      write_id3v2(&mut file, format, &tag).unwrap();
+      assert_eq!(read_id3v2(&mut file, format).unwrap(), Some(tag));
+      write_id3v2(&mut file, format, b"").unwrap();
+      assert_eq!(file.into_inner(), data);
+    }
+    assert_eq!(super::format(&mut Cursor::new(b"RIFF".to_vec())), None);
+  }
+}
@@ -0,0 +1,294 @@
+use crate::batch::Progress;
+use crate::cancel::Cancellation;
+use crate::scan::{self, ScanOptions, ScanResult};
+use crate::util::AudioTags;
+use serde_json::{json, Map, Value};
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ExportFormat {
+  // an array with one object per file
+  Json,
+  // a header row, then one row per file
+  Csv,
+}
+
+// the columns of the tag fields, in order; every column is present, missing
+// values are null. Pictures are left out.
+pub(crate) fn tag_columns(tags: &AudioTags) -> Vec<(&'static str, Value)> {
+  let track = tags.track.as_ref();
+  let disc = tags.disc.as_ref();
+  let comments: Option<Vec<Value>> = tags.comments.as_ref().map(|comments| {
+    comments
+      .iter()
+      .map(|comment| {
+        json!({
+          "language": comment.language,
+          "description": comment.description,
+          "text": comment.text,
+        })
+      })
+      .collect()
+  });
+  vec![
+    ("title", json!(tags.title)),
+    ("subtitle", json!(tags.subtitle)),
+    ("artists", json!(tags.artists)),
+    ("album", json!(tags.album)),
+    ("year", json!(tags.year)),
+    ("genre", json!(tags.genre)),
+    ("genres", json!(tags.genres)),
+    ("track", json!(track.and_then(|track| track.no))),
+    ("trackTotal", json!(track.and_then(|track| track.of))),
+    ("albumArtists", json!(tags.album_artists)),
+    ("comment", json!(tags.comment)),
+    ("comments", json!(comments)),
+    ("disc", json!(disc.and_then(|disc| disc.no))),
+    ("discTotal", json!(disc.and_then(|disc| disc.of))),
+    ("discSubtitle", json!(tags.disc_subtitle)),
+    ("composer", json!(tags.composer)),
+    ("lyricist", json!(tags.lyricist)),
+    ("conductor", json!(tags.conductor)),
+    ("remixer", json!(tags.remixer)),
+    ("publisher", json!(tags.publisher)),
+    ("copyright", json!(tags.copyright)),
+    ("lyrics", json!(tags.lyrics)),
+    ("isrc", json!(tags.isrc)),
+    ("barcode", json!(tags.barcode)),
+    ("catalogNumber", json!(tags.catalog_number)),
+    (
+      "musicbrainzRecordingId",
+      json!(tags.musicbrainz_recording_id),
+    ),
+    ("musicbrainzTrackId", json!(tags.musicbrainz_track_id)),
+    ("musicbrainzReleaseId", json!(tags.musicbrainz_release_id)),
+    (
+      "musicbrainzReleaseGroupId",
+      json!(tags.musicbrainz_release_group_id),
+    ),
+    ("musicbrainzArtistId", json!(tags.musicbrainz_artist_id)),
+    (
+      "musicbrainzAlbumArtistId",
+      json!(tags.musicbrainz_album_artist_id),
+    ),
+    ("replayGainTrackGain", json!(tags.replay_gain_track_gain)),
+    ("replayGainTrackPeak", json!(tags.replay_gain_track_peak)),
+    ("replayGainAlbumGain", json!(tags.replay_gain_album_gain)),
+    ("replayGainAlbumPeak", json!(tags.replay_gain_album_peak)),
+    ("bpm", json!(tags.bpm)),
+    ("initialKey", json!(tags.initial_key)),
+    ("mood", json!(tags.mood)),
+    ("rating", json!(tags.rating)),
+    ("playCount", json!(tags.play_count)),
+    ("compilation", json!(tags.compilation)),
+    ("grouping", json!(tags.grouping)),
+    ("work", json!(tags.work)),
+    ("movement", json!(tags.movement)),
+    ("movementNumber", json!(tags.movement_number)),
+    ("titleSort", json!(tags.title_sort)),
+    ("albumSort", json!(tags.album_sort)),
+    ("artistSort", json!(tags.artist_sort)),
+    ("albumArtistSort", json!(tags.album_artist_sort)),
+    ("composerSort", json!(tags.composer_sort)),
+    ("encoderSoftware", json!(tags.encoder_software)),
+    ("encoderSettings", json!(tags.encoder_settings)),
+    ("customFields", json!(tags.custom_fields)),
+  ]
+}
+
+// the columns of a file: its path and error, the tag fields and the audio
+// properties
+fn columns(result: &ScanResult) -> Vec<(&'static str, Value)> {
+  let default_tags = AudioTags::default();
+  let properties = result.properties.as_ref();
+  let mut columns = vec![
+    ("filePath", json!(result.file_path)),
+    ("error", json!(result.error)),
+  ];
+  columns.extend(tag_columns(result.tags.as_ref().unwrap_or(&default_tags)));
+  columns.extend([
+    (
+      "durationMs",
+      json!(properties.map(|properties| properties.duration_ms)),
+    ),
+    (
+      "bitrate",
+      json!(properties.and_then(|properties| properties.bitrate)),
+    ),
+    (
+      "sampleRate",
+      json!(properties.and_then(|properties| properties.sample_rate)),
+    ),
+    (
+      "bitDepth",
+      json!(properties.and_then(|properties| properties.bit_depth)),
+    ),
+    (
+      "channels",
+      json!(properties.and_then(|properties| properties.channels)),
+    ),
+  ]);
+  columns
+}
+
+// a JSON object of the columns that have a value
+fn json_entry(result: &ScanResult) -> Value {
+  let entry: Map<String, Value> = columns(result)
+    .into_iter()
+    .filter(|(_, value)| !value.is_null())
+    .map(|(name, value)| (name.to_string(), value))
+    .collect();
+  Value::Object(entry)
+}
+
+// a value as the text of a CSV cell: lists of text joined with "; ", other
+// lists and objects as JSON
+fn csv_text(value: &Value) -> String {
+  match value {
+    Value::Null => String::new(),
+    Value::String(text) => text.clone(),
+    Value::Array(items) if items.iter().all(Value::is_string) => items
+      .iter()
+      .filter_map(Value::as_str)
+      .collect::<Vec<_>>()
+      .join("; "),
+    value => value.to_string(),
+  }
+}
+
+// quoted when it holds a separator, a quote or a line break (RFC 4180)
+fn csv_cell(text: &str) -> String {
+  if text.contains([',', '"', '\n', '\r']) {
+    format!("\"{}\"", text.replace('"', "\"\""))
+  } else {
+    text.to_string()
+  }
+}
+
+fn csv_row<'a>(cells: impl Iterator<Item = &'a str>) -> String {
+  let cells: Vec<String> = cells.map(csv_cell).collect();
+  format!("{}\r\n", cells.join(","))
+}
+
+/**
+ * Render the results of a scan in an export format
+ * @param results - The results, one per file
+ * @param format - The format
+ */
+fn render(results: &[ScanResult], format: ExportFormat) -> String {
+  match format {
+    ExportFormat::Json => {
+      // one file per line
+      let entries: Vec<String> = results
+        .iter()
+        .map(|result| json_entry(result).to_string())
+        .collect();
+      if entries.is_empty() {
+        "[]\n".to_string()
+      } else {
+        format!("[\n{}\n]\n", entries.join(",\n"))
+      }
+    }
+    ExportFormat::Csv => {
+      let header = columns(&ScanResult::default());
+      let mut csv = csv_row(header.iter().map(|(name, _)| *name));
+      for result in results {
+        let texts: Vec<String> = columns(result)
+          .iter()
+          .map(|(_, value)| csv_text(value))
+          .collect();
+        csv.push_str(&csv_row(texts.iter().map(String::as_str)));
+      }
+      csv
+    }
+  }
+}
+
+/**
+ * Export the tags and audio properties of every audio file under a directory
+ * to a file, e.g. as a backup of the metadata. Files that could not be read
+ * are exported with their error.
+ * @param root - The directory to scan
+ * @param format - The format of the export
+ * @param out_path - The file to write the export to, replaced if it exists
+ * @param options - The scan options
+ * @param cancel - Cancelled when the caller aborts
+ * @param progress - Told about every file read or failed
+ * @returns The number of files exported
+ */
+pub async fn export_tags<P: Progress>(
+  root: impl AsRef<Path>,
+  format: ExportFormat,
+  out_path: impl AsRef<Path>,
+  options: ScanOptions,
+  cancel: &Cancellation,
+  progress: P,
+) -> Result<usize, String> {
+  let results = scan::scan_directory(root, options, cancel, progress).await?;
+  fs::write(&out_path, render(&results, format))
+    .map_err(|e| format!("Failed to write export: {}", e))?;
+  Ok(results.len())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::batch;
+  use crate::test_support::{sample_mp3, write_fixture};
+  use crate::util::{self, Position};
+
+  #[tokio::test]
+  async fn test_export_tags() {
+    let dir = tempfile::tempdir().unwrap();
+    let tags = AudioTags {
+      title: Some("Say \"Hi\", again".to_string()),
+      artists: Some(vec!["A".to_string(), "B".to_string()]),
+      track: Some(Position {
+        no: Some(2),
+        of: None,
+      }),
+      ..Default::default()
+    };
+    let tagged = util::write_tags_to_buffer(sample_mp3(), tags)
+      .await
+      .unwrap();
+    write_fixture(dir.path(), "a.mp3", &tagged);
+    write_fixture(dir.path(), "b.flac", b"not audio");
+    let root = dir.path().to_string_lossy().to_string();
+    let export = |format: ExportFormat, name: &str| {
+      let out_path = dir.path().join(name).to_string_lossy().to_string();
+      let root = root.clone();
+      async move {
+        let count = export_tags(
+          root,
+          format,
+          out_path.clone(),
+          ScanOptions::default(),
+          &Cancellation::default(),
+          batch::NoProgress,
+        )
+        .await
+        .unwrap();
+        (count, fs::read_to_string(out_path).unwrap())
+      }
+    };
+
+    let (count, json) = export(ExportFormat::Json, "export.json").await;
+    assert_eq!(count, 2);
+    let entries: Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(entries[0]["title"], "Say \"Hi\", again");
+    assert_eq!(entries[0]["artists"], json!(["A", "B"]));
+    assert_eq!(entries[0]["track"], 2);
+    assert!(entries[0]["durationMs"].as_u64().unwrap() > 0);
+    assert!(entries[0].get("trackTotal").is_none());
+    assert!(entries[1]["error"].is_string());
+
+    let (count, csv) = export(ExportFormat::Csv, "export.csv").await;
+    assert_eq!(count, 2);
+    let rows: Vec<&str> = csv.split("\r\n").collect();
+    assert!(rows[0].starts_with("filePath,error,title,subtitle,artists,"));
+    assert!(rows[1].contains(",,\"Say \"\"Hi\"\", again\",,A; B,"));
+    assert_eq!(rows.len(), 4);
+  }
+}
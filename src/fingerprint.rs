@@ -0,0 +1,354 @@
+use crate::artwork::sha256_hex;
+use crate::comments::Comment;
+use crate::util::{self, AudioTags, Image, Position};
+use std::path::Path;
+
+// Accumulates `name:length:value` records, so no value can be mistaken for the
+// start of the next field
+#[derive(Default)]
+struct Canonical(String);
+
+impl Canonical {
+  fn field(&mut self, name: &str, value: Option<String>) {
+    let Some(value) = value.filter(|value| !value.is_empty()) else {
+      return;
+    };
+    self
+      .0
+      .push_str(&format!("{}:{}:{}\n", name, value.len(), value));
+  }
+
+  fn list(&mut self, name: &str, values: Option<&Vec<String>>) {
+    for value in values.into_iter().flatten() {
+      self.field(name, Some(value.clone()));
+    }
+  }
+
+  fn position(&mut self, name: &str, position: Option<&Position>) {
+    if let Some(position) = position {
+      self.field(
+        &format!("{}.no", name),
+        position.no.map(|no| no.to_string()),
+      );
+      self.field(
+        &format!("{}.of", name),
+        position.of.map(|of| of.to_string()),
+      );
+    }
+  }
+}
+
+// the pictures a write of these tags stores: all_images wins over image
+fn pictures(tags: &AudioTags) -> Vec<&Image> {
+  match (tags.all_images.as_ref(), tags.image.as_ref()) {
+    (Some(all_images), _) => all_images.iter().collect(),
+    (None, Some(image)) => vec![image],
+    (None, None) => Vec::new(),
+  }
+}
+
+// the genres a write of these tags stores: genres wins over genre
+fn genres(tags: &AudioTags) -> Vec<String> {
+  match (tags.genres.as_ref(), tags.genre.as_ref()) {
+    (Some(genres), _) if !genres.is_empty() => genres.clone(),
+    (_, Some(genre)) => vec![genre.clone()],
+    _ => Vec::new(),
+  }
+}
+
+// the comments a write of these tags stores: comment replaces the ones
+// without a description
+fn comments(tags: &AudioTags) -> Vec<Comment> {
+  let mut comments = tags.comments.clone().unwrap_or_default();
+  if let Some(text) = tags.comment.as_ref() {
+    comments.retain(|comment| comment.description.is_some());
+    comments.push(Comment {
+      text: text.clone(),
+      ..Default::default()
+    });
+  }
+  comments
+}
+
+// How tags_equal compares two sets of tags
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct TagsEqualOptions {
+  // leave the pictures out of the comparison
+  pub ignore_images: bool,
+}
+
+// The normalized contents of a set of tags: empty values count as missing,
+// pictures are described by type, description and content regardless of their
+// order
+fn canonical(tags: &AudioTags, ignore_images: bool) -> String {
+  let mut canonical = Canonical::default();
+  canonical.field("title", tags.title.clone());
+  canonical.list("artist", tags.artists.as_ref());
+  canonical.field("album", tags.album.clone());
+  canonical.field("year", tags.year.map(|year| year.to_string()));
+  canonical.list("genre", Some(&genres(tags)));
+  canonical.position("track", tags.track.as_ref());
+  canonical.list("albumArtist", tags.album_artists.as_ref());
+  for comment in comments(tags) {
+    let name = match comment.description.as_ref() {
+      Some(description) => format!("comment.{}", description),
+      None => "comment".to_string(),
+    };
+    let value = match comment.language {
+      Some(language) if !comment.text.is_empty() => format!("{}:{}", language, comment.text),
+      _ => comment.text,
+    };
+    canonical.field(&name, Some(value));
+  }
+  canonical.position("disc", tags.disc.as_ref());
+  canonical.field("composer", tags.composer.clone());
+  canonical.field("lyricist", tags.lyricist.clone());
+  canonical.field("conductor", tags.conductor.clone());
+  canonical.field("remixer", tags.remixer.clone());
+  canonical.field("publisher", tags.publisher.clone());
+  canonical.field("copyright", tags.copyright.clone());
+  canonical.field("lyrics", tags.lyrics.clone());
+  canonical.field("isrc", tags.isrc.clone());
+  canonical.field("barcode", tags.barcode.clone());
+  canonical.field("catalogNumber", tags.catalog_number.clone());
+  canonical.field(
+    "musicbrainzRecordingId",
+    tags.musicbrainz_recording_id.clone(),
+  );
+  canonical.field("musicbrainzTrackId", tags.musicbrainz_track_id.clone());
+  canonical.field("musicbrainzReleaseId", tags.musicbrainz_release_id.clone());
+  canonical.field(
+    "musicbrainzReleaseGroupId",
+    tags.musicbrainz_release_group_id.clone(),
+  );
+  canonical.field("musicbrainzArtistId", tags.musicbrainz_artist_id.clone());
+  canonical.field(
+    "musicbrainzAlbumArtistId",
+    tags.musicbrainz_album_artist_id.clone(),
+  );
+  for (name, value) in [
+    ("replayGainTrackGain", tags.replay_gain_track_gain),
+    ("replayGainTrackPeak", tags.replay_gain_track_peak),
+    ("replayGainAlbumGain", tags.replay_gain_album_gain),
+    ("replayGainAlbumPeak", tags.replay_gain_album_peak),
+  ] {
+    canonical.field(name, value.map(|value| value.to_string()));
+  }
+  canonical.field("bpm", tags.bpm.map(|bpm| bpm.to_string()));
+  canonical.field("initialKey", tags.initial_key.clone());
+  canonical.field("mood", tags.mood.clone());
+  canonical.field("rating", tags.rating.map(|rating| rating.to_string()));
+  canonical.field(
+    "playCount",
+    tags.play_count.map(|play_count| play_count.to_string()),
+  );
+  canonical.field(
+    "compilation",
+    tags.compilation.map(|compilation| compilation.to_string()),
+  );
+  canonical.field("grouping", tags.grouping.clone());
+  canonical.field("work", tags.work.clone());
+  canonical.field("movement", tags.movement.clone());
+  canonical.field(
+    "movementNumber",
+    tags.movement_number.map(|no| no.to_string()),
+  );
+  canonical.field("subtitle", tags.subtitle.clone());
+  canonical.field("discSubtitle", tags.disc_subtitle.clone());
+  canonical.field("titleSort", tags.title_sort.clone());
+  canonical.field("albumSort", tags.album_sort.clone());
+  canonical.field("artistSort", tags.artist_sort.clone());
+  canonical.field("albumArtistSort", tags.album_artist_sort.clone());
+  canonical.field("composerSort", tags.composer_sort.clone());
+  canonical.field("encoderSoftware", tags.encoder_software.clone());
+  canonical.field("encoderSettings", tags.encoder_settings.clone());
+
+  for (name, values) in tags.custom_fields.iter().flatten() {
+    canonical.list(&format!("custom.{}", name), Some(values));
+  }
+  if let Some(bwf) = tags.bwf.as_ref() {
+    canonical.field("bwf.description", bwf.description.clone());
+    canonical.field("bwf.originator", bwf.originator.clone());
+    canonical.field("bwf.originatorReference", bwf.originator_reference.clone());
+    canonical.field("bwf.originationDate", bwf.origination_date.clone());
+    canonical.field("bwf.originationTime", bwf.origination_time.clone());
+    canonical.field(
+      "bwf.timeReference",
+      bwf.time_reference.map(|samples| samples.to_string()),
+    );
+  }
+  for (name, data) in tags.mp4_freeform.iter().flatten() {
+    let data = Some(data).filter(|data| !data.is_empty());
+    canonical.field(
+      &format!("freeform.{}", name),
+      data.map(|data| sha256_hex(data)),
+    );
+  }
+  for object in tags.binary_objects.iter().flatten() {
+    let value = format!(
+      "{}:{}:{}:{}",
+      object.mime_type.clone().unwrap_or_default(),
+      object.filename.clone().unwrap_or_default(),
+      sha256_hex(&object.data),
+      object.description
+    );
+    canonical.field("binaryObject", Some(value));
+  }
+
+  if ignore_images {
+    return canonical.0;
+  }
+  let mut pictures: Vec<String> = pictures(tags)
+    .into_iter()
+    .map(|image| {
+      format!(
+        "{:?}:{}:{}",
+        image.pic_type,
+        sha256_hex(&image.data),
+        image.description.clone().unwrap_or_default()
+      )
+    })
+    .collect();
+  pictures.sort();
+  for picture in pictures {
+    canonical.field("picture", Some(picture));
+  }
+  canonical.0
+}
+
+/**
+ * Hash the normalized contents of a set of tags. Empty values count as missing
+ * and pictures are compared by type, description and content regardless of
+ * their order, so only a change of the metadata itself changes the fingerprint.
+ * @param tags - The tags to fingerprint
+ * @returns The hex encoded SHA-256 of the normalized tags
+ */
+pub fn tag_fingerprint(tags: &AudioTags) -> String {
+  sha256_hex(canonical(tags, false).as_bytes())
+}
+
+/**
+ * Whether two sets of tags hold the same metadata, compared as their
+ * fingerprints are, e.g. to skip writing a file that already holds the tags
+ * @param a - The first tags
+ * @param b - The second tags
+ * @param options - What the comparison leaves out
+ */
+pub fn tags_equal(a: &AudioTags, b: &AudioTags, options: &TagsEqualOptions) -> bool {
+  canonical(a, options.ignore_images) == canonical(b, options.ignore_images)
+}
+
+/**
+ * Fingerprint the tags of an audio file, see `tag_fingerprint`
+ * @param file_path - The path to the audio file
+ */
+pub async fn file_tag_fingerprint(file_path: impl AsRef<Path>) -> Result<String, String> {
+  let tags = util::read_tags(file_path).await?;
+  Ok(tag_fingerprint(&tags))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_support::{sample_mp3, write_fixture};
+  use crate::util::AudioImageType;
+
+  fn image(data: Vec<u8>, pic_type: AudioImageType) -> Image {
+    Image {
+      data,
+      pic_type,
+      ..Default::default()
+    }
+  }
+
+  #[test]
+  fn test_tag_fingerprint_ignores_representation() {
+    let tags = AudioTags {
+      title: Some("Title".to_string()),
+      artists: Some(vec!["A".to_string(), "B".to_string()]),
+      all_images: Some(vec![
+        image(vec![1, 2, 3], AudioImageType::CoverFront),
+        image(vec![4, 5, 6], AudioImageType::CoverBack),
+      ]),
+      ..Default::default()
+    };
+    let same = AudioTags {
+      comment: Some(String::new()),
+      album_artists: Some(Vec::new()),
+      all_images: Some(vec![
+        image(vec![4, 5, 6], AudioImageType::CoverBack),
+        image(vec![1, 2, 3], AudioImageType::CoverFront),
+      ]),
+      ..tags.clone()
+    };
+    assert_eq!(tag_fingerprint(&tags), tag_fingerprint(&same));
+
+    let retitled = AudioTags {
+      title: Some("Other".to_string()),
+      ..tags.clone()
+    };
+    assert_ne!(tag_fingerprint(&tags), tag_fingerprint(&retitled));
+    let reordered_artists = AudioTags {
+      artists: Some(vec!["B".to_string(), "A".to_string()]),
+      ..tags.clone()
+    };
+    assert_ne!(tag_fingerprint(&tags), tag_fingerprint(&reordered_artists));
+  }
+
+  #[test]
+  fn test_tags_equal() {
+    let tags = AudioTags {
+      title: Some("Title".to_string()),
+      image: Some(image(vec![1, 2, 3], AudioImageType::CoverFront)),
+      ..Default::default()
+    };
+    let new_cover = AudioTags {
+      artists: Some(Vec::new()),
+      image: Some(image(vec![4, 5, 6], AudioImageType::CoverFront)),
+      ..tags.clone()
+    };
+    let options = TagsEqualOptions::default();
+    assert!(tags_equal(&tags, &tags.clone(), &options));
+    assert!(!tags_equal(&tags, &new_cover, &options));
+    let ignore_images = TagsEqualOptions {
+      ignore_images: true,
+    };
+    assert!(tags_equal(&tags, &new_cover, &ignore_images));
+    let retitled = AudioTags {
+      title: Some("Other".to_string()),
+      ..new_cover
+    };
+    assert!(!tags_equal(&tags, &retitled, &ignore_images));
+  }
+
+  #[tokio::test]
+  async fn test_file_tag_fingerprint_matches_written_tags() {
+    let dir = tempfile::tempdir().unwrap();
+    let tags = AudioTags {
+      title: Some("Title".to_string()),
+      album: Some("Album".to_string()),
+      year: Some(2024),
+      track: Some(Position {
+        no: Some(3),
+        of: Some(12),
+      }),
+      image: Some(image(
+        vec![0xFF, 0xD8, 0xFF, 0xE0],
+        AudioImageType::CoverFront,
+      )),
+      ..Default::default()
+    };
+    let buffer = util::write_tags_to_buffer(sample_mp3(), tags.clone())
+      .await
+      .unwrap();
+    let path = write_fixture(dir.path(), "tagged.mp3", &buffer);
+
+    let fingerprint = file_tag_fingerprint(path).await.unwrap();
+    // the encoder frame of the sample is kept by the write
+    let tags = AudioTags {
+      encoder_software: Some("Lavf61.7.100".to_string()),
+      ..tags
+    };
+    assert_eq!(fingerprint, tag_fingerprint(&tags));
+  }
+}
@@ -0,0 +1,120 @@
+use crate::probe::file_type_name;
+use crate::util::ALL_TAG_TYPES;
+use lofty::file::FileType;
+
+// the formats lofty reads and writes, with their extensions (without the dot)
+// and their usual mime type
+const FORMATS: [(FileType, &[&str], &str); 12] = [
+  (FileType::Aac, &["aac"], "audio/aac"),
+  (FileType::Aiff, &["aif", "aifc", "aiff"], "audio/aiff"),
+  (FileType::Ape, &["ape"], "audio/ape"),
+  (FileType::Flac, &["flac"], "audio/flac"),
+  (FileType::Mp4, &["m4a", "m4b", "m4r", "mp4"], "audio/mp4"),
+  (FileType::Mpeg, &["mp3"], "audio/mpeg"),
+  (FileType::Mpc, &["mpc"], "audio/musepack"),
+  (FileType::Opus, &["opus"], "audio/opus"),
+  (FileType::Speex, &["spx"], "audio/speex"),
+  (FileType::Vorbis, &["oga", "ogg"], "audio/ogg"),
+  (FileType::Wav, &["wav"], "audio/wav"),
+  (FileType::WavPack, &["wv"], "audio/wavpack"),
+];
+
+// the DSD formats lofty doesn't know, whose ID3v2 tag is read and written
+// without it
+const DSD_FORMATS: [(&str, &[&str], &str); 2] = [
+  ("Dsf", &["dsf"], "audio/dsf"),
+  ("Dff", &["dff"], "audio/dff"),
+];
+
+// A file type this build handles and what it can do with it
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct FormatSupport {
+  pub file_type: String,
+  // lowercase, without the leading dot
+  pub extensions: Vec<String>,
+  pub mime_type: String,
+  // the tag type written by default
+  pub primary_tag_type: String,
+  // every tag type the format can hold
+  pub tag_types: Vec<String>,
+  pub can_read: bool,
+  pub can_write: bool,
+}
+
+// the extensions of every supported format
+pub(crate) fn audio_extensions() -> impl Iterator<Item = &'static str> {
+  let dsd_extensions = DSD_FORMATS
+    .iter()
+    .flat_map(|(_, extensions, _)| extensions.iter().copied());
+  FORMATS
+    .iter()
+    .flat_map(|(_, extensions, _)| extensions.iter().copied())
+    .chain(dsd_extensions)
+}
+
+/**
+ * The file types this build of the library supports, with their extensions
+ * and what can be done with them, e.g. to filter a file picker
+ */
+pub fn supported_formats() -> Vec<FormatSupport> {
+  FORMATS
+    .iter()
+    .map(|(file_type, extensions, mime_type)| FormatSupport {
+      file_type: file_type_name(*file_type),
+      extensions: extensions.iter().map(|e| e.to_string()).collect(),
+      mime_type: mime_type.to_string(),
+      primary_tag_type: format!("{:?}", file_type.primary_tag_type()),
+      tag_types: ALL_TAG_TYPES
+        .iter()
+        .filter(|tag_type| file_type.supports_tag_type(**tag_type))
+        .map(|tag_type| format!("{:?}", tag_type))
+        .collect(),
+      // lofty writes every format it reads
+      can_read: true,
+      can_write: true,
+    })
+    .chain(
+      DSD_FORMATS
+        .iter()
+        .map(|(file_type, extensions, mime_type)| FormatSupport {
+          file_type: file_type.to_string(),
+          extensions: extensions.iter().map(|e| e.to_string()).collect(),
+          mime_type: mime_type.to_string(),
+          primary_tag_type: "Id3v2".to_string(),
+          tag_types: vec!["Id3v2".to_string()],
+          can_read: true,
+          can_write: true,
+        }),
+    )
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_supported_formats() {
+    let formats = supported_formats();
+    let mpeg = formats
+      .iter()
+      .find(|format| format.file_type == "Mpeg")
+      .unwrap();
+    assert_eq!(mpeg.extensions, vec!["mp3".to_string()]);
+    assert_eq!(mpeg.primary_tag_type, "Id3v2");
+    assert_eq!(mpeg.tag_types, vec!["Ape", "Id3v1", "Id3v2"]);
+    assert!(mpeg.can_read && mpeg.can_write);
+    assert!(audio_extensions().any(|extension| extension == "dsf"));
+    // every extension resolves to its format
+    for format in &formats {
+      for extension in &format.extensions {
+        let file_type = FileType::from_ext(extension).map(file_type_name);
+        assert!(
+          file_type.is_none() || file_type.as_ref() == Some(&format.file_type),
+          "{}",
+          extension
+        );
+      }
+    }
+  }
+}
@@ -0,0 +1,247 @@
+use crate::probe::id3v2_size;
+use crate::util::AudioTags;
+use std::io::{Read, Seek, SeekFrom};
+
+// the Xing header and the LAME extension fit well within the first frame
+const FIRST_FRAME_BYTES: usize = 512;
+// the name of the comment or freeform atom iTunes stores gapless info in
+const ITUNSMPB: &str = "iTunSMPB";
+
+// Where the encoder delay and padding were found
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum GaplessSource {
+  // the LAME extension of the Xing or Info header of an MP3 file
+  LameHeader,
+  // the iTunSMPB comment or freeform atom of iTunes
+  ITunSmpb,
+}
+
+// What a player needs to drop the silence encoders add around the audio
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct GaplessInfo {
+  pub source: GaplessSource,
+  // samples the encoder added before the audio, without the delay of the
+  // decoder (529 samples for MP3)
+  pub encoder_delay: u32,
+  // samples the encoder added after the audio
+  pub encoder_padding: u32,
+  // samples of the audio without the delay and padding, None when the header
+  // doesn't tell
+  pub original_samples: Option<u64>,
+}
+
+// the side information between an MPEG audio frame header and its data, where
+// the Xing header goes
+fn side_info_len(mpeg1: bool, mono: bool) -> usize {
+  match (mpeg1, mono) {
+    (true, true) => 17,
+    (true, false) => 32,
+    (false, true) => 9,
+    (false, false) => 17,
+  }
+}
+
+/**
+ * Read the LAME extension of the Xing or Info header of an MP3 file, in the
+ * frame right after its ID3v2 tag
+ * @param frame - The bytes from the start of the first frame
+ */
+fn parse_lame_header(frame: &[u8]) -> Option<GaplessInfo> {
+  let header = frame.get(..4)?;
+  if header[0] != 0xFF || header[1] & 0xE0 != 0xE0 {
+    return None;
+  }
+  let version = (header[1] >> 3) & 0x03;
+  // only Layer III has a LAME header
+  if version == 0x01 || (header[1] >> 1) & 0x03 != 0x01 {
+    return None;
+  }
+  let mpeg1 = version == 0x03;
+  let mono = header[3] >> 6 == 0x03;
+  let samples_per_frame: u64 = if mpeg1 { 1152 } else { 576 };
+
+  let mut at = 4 + side_info_len(mpeg1, mono);
+  let tag = frame.get(at..at + 4)?;
+  if tag != b"Xing" && tag != b"Info" {
+    return None;
+  }
+  let flags = u32::from_be_bytes(frame.get(at + 4..at + 8)?.try_into().ok()?);
+  at += 8;
+  let mut frames = None;
+  if flags & 0x1 != 0 {
+    frames = Some(u32::from_be_bytes(frame.get(at..at + 4)?.try_into().ok()?));
+    at += 4;
+  }
+  // byte count, table of contents and quality
+  for (flag, len) in [(0x2, 4), (0x4, 100), (0x8, 4)] {
+    if flags & flag != 0 {
+      at += len;
+    }
+  }
+
+  // the encoder version, then the delay and padding as two 12 bit numbers
+  let lame = frame.get(at..at + 24)?;
+  if !lame.starts_with(b"LAME") && !lame.starts_with(b"Lav") {
+    return None;
+  }
+  let encoder_delay = (u32::from(lame[21]) << 4) | (u32::from(lame[22]) >> 4);
+  let encoder_padding = (u32::from(lame[22] & 0x0F) << 8) | u32::from(lame[23]);
+  Some(GaplessInfo {
+    source: GaplessSource::LameHeader,
+    encoder_delay,
+    encoder_padding,
+    original_samples: frames.map(|frames| {
+      (u64::from(frames) * samples_per_frame)
+        .saturating_sub(u64::from(encoder_delay) + u64::from(encoder_padding))
+    }),
+  })
+}
+
+// the LAME header of a file, None for files that aren't MP3
+fn read_lame_header<R: Read + Seek>(reader: &mut R) -> Option<GaplessInfo> {
+  let mut head = [0u8; 10];
+  reader.seek(SeekFrom::Start(0)).ok()?;
+  reader.read_exact(&mut head).ok()?;
+  let start = id3v2_size(&head).unwrap_or(0);
+  reader.seek(SeekFrom::Start(u64::from(start))).ok()?;
+  let mut frame = Vec::with_capacity(FIRST_FRAME_BYTES);
+  reader
+    .take(FIRST_FRAME_BYTES as u64)
+    .read_to_end(&mut frame)
+    .ok()?;
+  parse_lame_header(&frame)
+}
+
+/**
+ * Parse an iTunSMPB value: hex numbers of which the second is the delay, the
+ * third the padding and the fourth the number of samples of the audio, e.g.
+ * " 00000000 00000840 000001CA 00000000003F31F6 ..."
+ * @param value - The value
+ */
+fn parse_itunsmpb(value: &str) -> Option<GaplessInfo> {
+  let fields: Vec<&str> = value.split_whitespace().collect();
+  let number = |i: usize| {
+    fields
+      .get(i)
+      .and_then(|field| u64::from_str_radix(field, 16).ok())
+  };
+  Some(GaplessInfo {
+    source: GaplessSource::ITunSmpb,
+    encoder_delay: u32::try_from(number(1)?).ok()?,
+    encoder_padding: u32::try_from(number(2)?).ok()?,
+    original_samples: number(3).filter(|samples| *samples > 0),
+  })
+}
+
+// the iTunSMPB of the tags: an ID3v2 comment, an MP4 freeform atom or a
+// Vorbis comment of that name
+fn itunsmpb(tags: &AudioTags) -> Option<GaplessInfo> {
+  let comment = tags
+    .comments
+    .iter()
+    .flatten()
+    .find(|comment| comment.description.as_deref() == Some(ITUNSMPB))
+    .map(|comment| comment.text.as_str());
+  let field = tags
+    .custom_fields
+    .iter()
+    .flatten()
+    .find(|(name, _)| name.eq_ignore_ascii_case(ITUNSMPB))
+    .and_then(|(_, values)| values.first())
+    .map(String::as_str);
+  comment.or(field).and_then(parse_itunsmpb)
+}
+
+/**
+ * Read the encoder delay and padding of a file, from the LAME header of an MP3
+ * file or else from the iTunSMPB iTunes stores in the tags
+ * @param reader - The file
+ * @param tags - The tags read from it
+ * @returns None when the file has neither
+ */
+pub(crate) fn read_gapless_info<R: Read + Seek>(
+  reader: &mut R,
+  tags: &AudioTags,
+) -> Option<GaplessInfo> {
+  read_lame_header(reader).or_else(|| itunsmpb(tags))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::comments::Comment;
+  use std::collections::BTreeMap;
+  use std::io::Cursor;
+
+  // an MPEG-1 Layer III stereo frame with an Info header counting `frames`
+  // frames and a LAME extension
+  fn lame_frame(frames: u32, delay: u32, padding: u32) -> Vec<u8> {
+    let mut frame = vec![0xFF, 0xFB, 0x90, 0x44];
+    frame.resize(4 + 32, 0);
+    frame.extend_from_slice(b"Info");
+    frame.extend_from_slice(&0x1u32.to_be_bytes());
+    frame.extend_from_slice(&frames.to_be_bytes());
+    let mut lame = b"LAME3.100".to_vec();
+    lame.resize(21, 0);
+    lame.push((delay >> 4) as u8);
+    lame.push((((delay & 0x0F) << 4) | (padding >> 8)) as u8);
+    lame.push((padding & 0xFF) as u8);
+    frame.extend(lame);
+    frame.resize(417, 0);
+    frame
+  }
+
+  #[test]
+  fn test_read_lame_header() {
+    let mut id3v2 = b"ID3\x04\x00\x00\x00\x00\x00\x0A".to_vec();
+    id3v2.resize(20, 0);
+    let file = [id3v2, lame_frame(100, 576, 1234)].concat();
+    let info = read_lame_header(&mut Cursor::new(file)).unwrap();
+    assert_eq!(
+      info,
+      GaplessInfo {
+        source: GaplessSource::LameHeader,
+        encoder_delay: 576,
+        encoder_padding: 1234,
+        original_samples: Some(100 * 1152 - 576 - 1234),
+      }
+    );
+
+    // a plain frame, without Xing or Info header
+    let mut plain = lame_frame(100, 576, 1234);
+    plain[36..40].copy_from_slice(&[0; 4]);
+    assert_eq!(read_lame_header(&mut Cursor::new(plain)), None);
+  }
+
+  #[test]
+  fn test_itunsmpb() {
+    let value = " 00000000 00000840 000001CA 00000000003F31F6 00000000 00000000";
+    let mp3 = AudioTags {
+      comments: Some(vec![Comment {
+        language: None,
+        description: Some("iTunSMPB".to_string()),
+        text: value.to_string(),
+      }]),
+      ..Default::default()
+    };
+    let expected = GaplessInfo {
+      source: GaplessSource::ITunSmpb,
+      encoder_delay: 0x840,
+      encoder_padding: 0x1CA,
+      original_samples: Some(0x3F31F6),
+    };
+    assert_eq!(itunsmpb(&mp3), Some(expected));
+
+    let m4a = AudioTags {
+      custom_fields: Some(BTreeMap::from([(
+        "iTunSMPB".to_string(),
+        vec![value.to_string()],
+      )])),
+      ..Default::default()
+    };
+    assert_eq!(itunsmpb(&m4a), Some(expected));
+
+    assert_eq!(parse_itunsmpb("not hex at all"), None);
+    assert_eq!(itunsmpb(&AudioTags::default()), None);
+  }
+}
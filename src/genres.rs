@@ -0,0 +1,92 @@
+use lofty::id3::v1::GENRES;
+
+// the name of an ID3 genre reference: an ID3v1 genre number, or the "RX"
+// (remix) and "CR" (cover) keywords of ID3v2.3
+fn reference_name(reference: &str) -> Option<&'static str> {
+  match reference {
+    "RX" => Some("Remix"),
+    "CR" => Some("Cover"),
+    _ if reference.len() <= 3 && reference.chars().all(|c| c.is_ascii_digit()) => {
+      GENRES.get(reference.parse::<usize>().ok()?).copied()
+    }
+    _ => None,
+  }
+}
+
+/**
+ * Resolve the ID3 genre references of a stored genre value into names: "17"
+ * and "(17)" read as "Rock", "(17)(31)" as "Rock" and "Trance". Text after the
+ * references refines the last one, so "(4)Eurodisco" reads as "Eurodisco", and
+ * "((" escapes a genre starting with a parenthesis.
+ * @param value - The genre as stored in the tag
+ * @returns The genre names, the value itself when it holds no reference
+ */
+pub fn genre_names(value: &str) -> Vec<String> {
+  if let Some(name) = reference_name(value) {
+    return vec![name.to_string()];
+  }
+  let mut names = Vec::new();
+  let mut rest = value;
+  while let Some(reference) = rest
+    .strip_prefix('(')
+    .filter(|reference| !reference.starts_with('('))
+    .and_then(|reference| reference.split_once(')'))
+  {
+    let Some(name) = reference_name(reference.0) else {
+      break;
+    };
+    names.push(name.to_string());
+    rest = reference.1;
+  }
+  let refinement = rest
+    .strip_prefix('(')
+    .filter(|_| rest.starts_with("(("))
+    .unwrap_or(rest);
+  if !refinement.is_empty() {
+    names.pop();
+    names.push(refinement.to_string());
+  } else if names.is_empty() {
+    names.push(value.to_string());
+  }
+  names
+}
+
+/**
+ * The ID3v1 name of a genre, which ID3v1 stores as a number
+ * @param genre - A genre name, in any case, or an ID3 genre reference
+ * @returns The name as listed by ID3v1, None when ID3v1 has no such genre
+ */
+pub fn id3v1_genre(genre: &str) -> Option<&'static str> {
+  let genre = genre_names(genre).into_iter().next()?;
+  GENRES
+    .iter()
+    .find(|name| name.eq_ignore_ascii_case(genre.trim()))
+    .copied()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_genre_names() {
+    assert_eq!(genre_names("17"), vec!["Rock"]);
+    assert_eq!(genre_names("(17)"), vec!["Rock"]);
+    assert_eq!(genre_names("(17)Rock"), vec!["Rock"]);
+    assert_eq!(genre_names("(17)(31)"), vec!["Rock", "Trance"]);
+    assert_eq!(genre_names("(4)Eurodisco"), vec!["Eurodisco"]);
+    assert_eq!(genre_names("(RX)(CR)"), vec!["Remix", "Cover"]);
+    assert_eq!(genre_names("((Not) a reference"), vec!["(Not) a reference"]);
+    assert_eq!(genre_names("Vaporwave"), vec!["Vaporwave"]);
+    assert_eq!(genre_names("1984"), vec!["1984"]);
+    assert_eq!(genre_names("(999)"), vec!["(999)"]);
+    assert_eq!(genre_names(""), vec![""]);
+  }
+
+  #[test]
+  fn test_id3v1_genre() {
+    assert_eq!(id3v1_genre("hip-hop"), Some("Hip-Hop"));
+    assert_eq!(id3v1_genre("(17)"), Some("Rock"));
+    assert_eq!(id3v1_genre("Vaporwave"), None);
+  }
+}
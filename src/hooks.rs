@@ -0,0 +1,59 @@
+use crate::util::AudioTags;
+use std::future::Future;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum HookDecision {
+  // write these tags (possibly adjusted by the hook)
+  Proceed(Box<AudioTags>),
+  // leave the file untouched
+  Veto,
+}
+
+// Callbacks run around every file written by the batch operations
+pub trait WriteHooks {
+  fn before_write(
+    &self,
+    file_path: &str,
+    old_tags: &AudioTags,
+    new_tags: AudioTags,
+  ) -> impl Future<Output = Result<HookDecision, String>> + Send;
+
+  fn after_write(
+    &self,
+    file_path: &str,
+    old_tags: &AudioTags,
+    new_tags: &AudioTags,
+  ) -> impl Future<Output = Result<(), String>> + Send;
+
+  // false when there is nothing to run, so the batch writes can skip working
+  // out the tags they would leave
+  fn is_active(&self) -> bool {
+    true
+  }
+}
+
+pub struct NoHooks;
+
+impl WriteHooks for NoHooks {
+  async fn before_write(
+    &self,
+    _file_path: &str,
+    _old_tags: &AudioTags,
+    new_tags: AudioTags,
+  ) -> Result<HookDecision, String> {
+    Ok(HookDecision::Proceed(Box::new(new_tags)))
+  }
+
+  async fn after_write(
+    &self,
+    _file_path: &str,
+    _old_tags: &AudioTags,
+    _new_tags: &AudioTags,
+  ) -> Result<(), String> {
+    Ok(())
+  }
+
+  fn is_active(&self) -> bool {
+    false
+  }
+}
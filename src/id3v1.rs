@@ -0,0 +1,102 @@
+use crate::genres;
+use crate::util::AudioTags;
+use lofty::tag::{ItemKey, Tag, TagType};
+
+// the length of the title, artist and album fields
+const FIELD_LENGTH: usize = 30;
+// the comment gives up two bytes to the track number (ID3v1.1)
+const COMMENT_LENGTH: usize = 28;
+
+// the ASCII letters of U+00C0 to U+00FF, the accented letters of Latin-1
+const LATIN1_LETTERS: &str = "AAAAAAACEEEEIIIIDNOOOOOxOUUUUYTsaaaaaaaceeeeiiiidnooooo/ouuuuyty";
+
+/**
+ * Text as an ID3v1 field: accented Latin letters lose their accents and other
+ * characters outside ASCII become "?", as players read the field as Latin-1
+ * and lofty writes it as is. Cut to `length` characters.
+ * @param text - The value of the field
+ * @param length - The size of the field
+ */
+fn field(text: &str, length: usize) -> String {
+  text
+    .chars()
+    .map(|c| match c {
+      c if c.is_ascii() => c,
+      '\u{C0}'..='\u{FF}' => LATIN1_LETTERS.chars().nth(c as usize - 0xC0).unwrap_or('?'),
+      _ => '?',
+    })
+    .take(length)
+    .collect()
+}
+
+/**
+ * The ID3v1 tag of a set of tags, for players that read nothing else. The
+ * artists are joined with ", ", the genre is the first one ID3v1 lists, the
+ * track and year are left out when they don't fit.
+ * @param tags - The tags written to the primary tag
+ */
+pub fn id3v1_tag(tags: &AudioTags) -> Tag {
+  let mut tag = Tag::new(TagType::Id3v1);
+  let mut insert = |key: ItemKey, value: Option<&str>, length: usize| {
+    if let Some(value) = value.filter(|value| !value.is_empty()) {
+      tag.insert_text(key, field(value, length));
+    }
+  };
+  insert(ItemKey::TrackTitle, tags.title.as_deref(), FIELD_LENGTH);
+  let artists = tags.artists.as_ref().map(|artists| artists.join(", "));
+  insert(ItemKey::TrackArtist, artists.as_deref(), FIELD_LENGTH);
+  insert(ItemKey::AlbumTitle, tags.album.as_deref(), FIELD_LENGTH);
+  insert(ItemKey::Comment, tags.comment.as_deref(), COMMENT_LENGTH);
+  let year = tags
+    .year
+    .filter(|year| *year <= 9999)
+    .map(|year| year.to_string());
+  insert(ItemKey::Year, year.as_deref(), 4);
+  let track = tags
+    .track
+    .as_ref()
+    .and_then(|track| track.no)
+    .filter(|no| (1..=255).contains(no))
+    .map(|no| no.to_string());
+  insert(ItemKey::TrackNumber, track.as_deref(), 3);
+  let genre = tags
+    .genres
+    .iter()
+    .flatten()
+    .chain(&tags.genre)
+    .find_map(|genre| genres::id3v1_genre(genre));
+  insert(ItemKey::Genre, genre, FIELD_LENGTH);
+  tag
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::util::Position;
+  use lofty::id3::v1::Id3v1Tag;
+
+  #[test]
+  fn test_id3v1_tag() {
+    let tags = AudioTags {
+      title: Some("Für Elise (Bagatelle No. 25 in A minor)".to_string()),
+      artists: Some(vec!["Beethoven".to_string(), "Ōkami".to_string()]),
+      year: Some(1810),
+      track: Some(Position {
+        no: Some(300),
+        of: None,
+      }),
+      genres: Some(vec!["Vaporwave".to_string(), "Classical".to_string()]),
+      ..Default::default()
+    };
+    let id3v1 = Id3v1Tag::from(id3v1_tag(&tags));
+    assert_eq!(
+      id3v1.title.as_deref(),
+      Some("Fur Elise (Bagatelle No. 25 in")
+    );
+    assert_eq!(id3v1.artist.as_deref(), Some("Beethoven, ?kami"));
+    assert_eq!(id3v1.year.as_deref(), Some("1810"));
+    assert_eq!(id3v1.track_number, None);
+    // "Vaporwave" isn't an ID3v1 genre
+    assert_eq!(id3v1.genre, Some(32));
+  }
+}
@@ -0,0 +1,142 @@
+use crate::util::AudioTags;
+
+// What a write does with identifiers that don't have the expected shape
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum IdentifierValidation {
+  // fail the write
+  #[default]
+  Reject,
+  // write them and report them in the outcome
+  Warn,
+  // write them without looking
+  PassThrough,
+}
+
+// the name of an identifier, its value and the check of its shape
+type IdentifierCheck<'a> = (&'static str, &'a Option<String>, fn(&str) -> bool);
+
+// 8-4-4-4-12 hexadecimal digits
+fn is_uuid(value: &str) -> bool {
+  let groups: Vec<&str> = value.split('-').collect();
+  groups.len() == 5
+    && groups
+      .iter()
+      .zip([8, 4, 4, 4, 12])
+      .all(|(group, len)| group.len() == len && group.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+// CC-XXX-YY-NNNNN: country, registrant, year and designation code; the
+// hyphens are optional
+fn is_isrc(value: &str) -> bool {
+  let code: Vec<char> = value.chars().filter(|c| *c != '-').collect();
+  code.len() == 12
+    && code[..2].iter().all(|c| c.is_ascii_uppercase())
+    && code[2..5]
+      .iter()
+      .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+    && code[5..].iter().all(|c| c.is_ascii_digit())
+}
+
+// EAN-8, UPC-A, EAN-13 or GTIN-14 with a valid check digit
+fn is_barcode(value: &str) -> bool {
+  if ![8, 12, 13, 14].contains(&value.len()) || !value.chars().all(|c| c.is_ascii_digit()) {
+    return false;
+  }
+  // weights alternate 3, 1, 3, ... from the digit left of the check digit
+  let sum: u32 = value
+    .bytes()
+    .rev()
+    .map(|digit| u32::from(digit - b'0'))
+    .enumerate()
+    .map(|(i, digit)| if i % 2 == 1 { digit * 3 } else { digit })
+    .sum();
+  sum.is_multiple_of(10)
+}
+
+/**
+ * Check the identifier fields of a set of tags. Empty values are not checked,
+ * they clear nothing and are skipped on write like any other empty field.
+ * @param tags - The tags about to be written
+ * @returns One message per malformed identifier
+ */
+pub fn validate_identifiers(tags: &AudioTags) -> Vec<String> {
+  let checks: [IdentifierCheck; 8] = [
+    ("ISRC", &tags.isrc, is_isrc),
+    ("barcode", &tags.barcode, is_barcode),
+    (
+      "MusicBrainz recording id",
+      &tags.musicbrainz_recording_id,
+      is_uuid,
+    ),
+    ("MusicBrainz track id", &tags.musicbrainz_track_id, is_uuid),
+    (
+      "MusicBrainz release id",
+      &tags.musicbrainz_release_id,
+      is_uuid,
+    ),
+    (
+      "MusicBrainz release group id",
+      &tags.musicbrainz_release_group_id,
+      is_uuid,
+    ),
+    (
+      "MusicBrainz artist id",
+      &tags.musicbrainz_artist_id,
+      is_uuid,
+    ),
+    (
+      "MusicBrainz album artist id",
+      &tags.musicbrainz_album_artist_id,
+      is_uuid,
+    ),
+  ];
+  checks
+    .into_iter()
+    .filter_map(|(name, value, is_valid)| {
+      let value = value.as_deref().filter(|value| !value.is_empty())?;
+      (!is_valid(value)).then(|| format!("Invalid {}: {}", name, value))
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_identifier_shapes() {
+    assert!(is_uuid("f4a261c5-6ebb-4a0f-a0b6-0d5a0c1f0c3e"));
+    assert!(!is_uuid("f4a261c5-6ebb-4a0f-a0b6-0d5a0c1f0c3"));
+    assert!(!is_uuid("g4a261c5-6ebb-4a0f-a0b6-0d5a0c1f0c3e"));
+
+    assert!(is_isrc("USRC17607839"));
+    assert!(is_isrc("US-RC1-76-07839"));
+    assert!(!is_isrc("usrc17607839"));
+    assert!(!is_isrc("USRC1760783"));
+
+    assert!(is_barcode("4006381333931"));
+    assert!(is_barcode("036000291452"));
+    assert!(is_barcode("96385074"));
+    assert!(!is_barcode("4006381333932"));
+    assert!(!is_barcode("40063813339"));
+  }
+
+  #[test]
+  fn test_validate_identifiers() {
+    let tags = AudioTags {
+      isrc: Some("USRC17607839".to_string()),
+      barcode: Some("4006381333932".to_string()),
+      musicbrainz_release_id: Some("not-a-uuid".to_string()),
+      musicbrainz_artist_id: Some(String::new()),
+      ..Default::default()
+    };
+    assert_eq!(
+      validate_identifiers(&tags),
+      vec![
+        "Invalid barcode: 4006381333932".to_string(),
+        "Invalid MusicBrainz release id: not-a-uuid".to_string(),
+      ]
+    );
+    assert!(validate_identifiers(&AudioTags::default()).is_empty());
+  }
+}
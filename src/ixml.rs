@@ -0,0 +1,299 @@
+use crate::atomic::WriteTarget;
+use crate::riff;
+use crate::util::{self, SaveOptions};
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+
+const CHUNK_ID: &[u8; 4] = b"iXML";
+const ROOT: &str = "BWFXML";
+const IXML_VERSION: &str = "2.10";
+
+// A track of a recording, as the recorder named it
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct IxmlTrack {
+  // the channel of the recorder, from 1
+  pub channel_index: Option<u32>,
+  // the position of the track in the file, from 1
+  pub interleave_index: Option<u32>,
+  pub name: Option<String>,
+}
+
+// The production fields of the iXML chunk field recorders write to WAV and
+// AIFF files; a missing value is left alone on write
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Ixml {
+  pub project: Option<String>,
+  pub scene: Option<String>,
+  pub take: Option<String>,
+  pub tape: Option<String>,
+  pub note: Option<String>,
+  // replaces the whole track list on write
+  pub tracks: Option<Vec<IxmlTrack>>,
+}
+
+fn escape(text: &str) -> String {
+  text
+    .replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+    .replace('"', "&quot;")
+}
+
+fn unescape(text: &str) -> String {
+  text
+    .replace("&lt;", "<")
+    .replace("&gt;", ">")
+    .replace("&quot;", "\"")
+    .replace("&apos;", "'")
+    .replace("&amp;", "&")
+}
+
+// where the first element `name` is in `xml`, the tags included, and where
+// its content is; an empty element has empty content
+fn element(xml: &str, name: &str) -> Option<(std::ops::Range<usize>, std::ops::Range<usize>)> {
+  let open = format!("<{}>", name);
+  let empty = format!("<{}/>", name);
+  let close = format!("</{}>", name);
+  let open_at = xml.find(&open);
+  let empty_at = xml.find(&empty);
+  match (open_at, empty_at) {
+    (Some(start), empty_at) if empty_at.is_none_or(|empty_at| start < empty_at) => {
+      let content_start = start + open.len();
+      let content_end = content_start + xml[content_start..].find(&close)?;
+      Some((start..content_end + close.len(), content_start..content_end))
+    }
+    (_, Some(start)) => {
+      let end = start + empty.len();
+      Some((start..end, end..end))
+    }
+    _ => None,
+  }
+}
+
+// the trimmed text of the first element `name`, None when missing or empty
+fn text(xml: &str, name: &str) -> Option<String> {
+  let (_, content) = element(xml, name)?;
+  let text = unescape(xml[content].trim());
+  Some(text).filter(|text| !text.is_empty())
+}
+
+fn number(xml: &str, name: &str) -> Option<u32> {
+  text(xml, name)?.parse().ok()
+}
+
+/**
+ * Read the production fields of an iXML document
+ * @param xml - The document
+ */
+pub fn parse(xml: &str) -> Ixml {
+  let tracks = element(xml, "TRACK_LIST").map(|(_, content)| {
+    let mut list = &xml[content];
+    let mut tracks = Vec::new();
+    while let Some((outer, track)) = element(list, "TRACK") {
+      let track_xml = &list[track];
+      tracks.push(IxmlTrack {
+        channel_index: number(track_xml, "CHANNEL_INDEX"),
+        interleave_index: number(track_xml, "INTERLEAVE_INDEX"),
+        name: text(track_xml, "NAME"),
+      });
+      list = &list[outer.end..];
+    }
+    tracks
+  });
+  Ixml {
+    project: text(xml, "PROJECT"),
+    scene: text(xml, "SCENE"),
+    take: text(xml, "TAKE"),
+    tape: text(xml, "TAPE"),
+    note: text(xml, "NOTE"),
+    tracks,
+  }
+}
+
+fn track_list(tracks: &[IxmlTrack]) -> String {
+  let mut list = format!("<TRACK_COUNT>{}</TRACK_COUNT>", tracks.len());
+  for track in tracks {
+    list.push_str("<TRACK>");
+    if let Some(index) = track.channel_index {
+      list.push_str(&format!("<CHANNEL_INDEX>{}</CHANNEL_INDEX>", index));
+    }
+    if let Some(index) = track.interleave_index {
+      list.push_str(&format!("<INTERLEAVE_INDEX>{}</INTERLEAVE_INDEX>", index));
+    }
+    if let Some(name) = track.name.as_deref() {
+      list.push_str(&format!("<NAME>{}</NAME>", escape(name)));
+    }
+    list.push_str("</TRACK>");
+  }
+  list
+}
+
+// `xml` with the content of the element `name` replaced, the element added at
+// the end of the root when missing
+fn set_element(xml: &mut String, name: &str, content: &str) {
+  let element_xml = format!("<{0}>{1}</{0}>", name, content);
+  if let Some((outer, _)) = element(xml, name) {
+    xml.replace_range(outer, &element_xml);
+  } else if let Some(end) = xml.rfind(&format!("</{}>", ROOT)) {
+    xml.insert_str(end, &format!("{}\n", element_xml));
+  }
+}
+
+/**
+ * Set production fields in an iXML document, keeping every other element
+ * @param xml - The document, None to start a new one
+ * @param ixml - The fields to set
+ * @returns The updated document
+ */
+pub fn update(xml: Option<&str>, ixml: &Ixml) -> String {
+  let mut xml = match xml.filter(|xml| xml.contains(&format!("</{}>", ROOT))) {
+    Some(xml) => xml.to_string(),
+    None => format!(
+      "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<{0}>\n<IXML_VERSION>{1}</IXML_VERSION>\n</{0}>\n",
+      ROOT, IXML_VERSION
+    ),
+  };
+  let fields = [
+    ("PROJECT", &ixml.project),
+    ("SCENE", &ixml.scene),
+    ("TAKE", &ixml.take),
+    ("TAPE", &ixml.tape),
+    ("NOTE", &ixml.note),
+  ];
+  for (name, value) in fields {
+    if let Some(value) = value {
+      set_element(&mut xml, name, &escape(value));
+    }
+  }
+  if let Some(tracks) = ixml.tracks.as_ref() {
+    set_element(&mut xml, "TRACK_LIST", &track_list(tracks));
+  }
+  xml
+}
+
+// the document of an iXML chunk, which some recorders pad with NULs
+fn chunk_text(content: Vec<u8>) -> String {
+  String::from_utf8_lossy(&content)
+    .trim_end_matches('\0')
+    .to_string()
+}
+
+/**
+ * Read the iXML chunk of a WAV or AIFF file
+ * @param file_path - The path to the audio file
+ * @returns None when the file has no iXML chunk
+ */
+pub async fn read_ixml(file_path: impl AsRef<Path>) -> Result<Option<Ixml>, String> {
+  let path = file_path.as_ref();
+  let mut file = util::open_file(path)?;
+  let content = riff::read_chunk(&mut file, CHUNK_ID).map_err(util::in_file(path))?;
+  Ok(content.map(|content| parse(&chunk_text(content))))
+}
+
+pub async fn read_ixml_from_buffer(buffer: Vec<u8>) -> Result<Option<Ixml>, String> {
+  let content = riff::read_chunk(&mut Cursor::new(buffer), CHUNK_ID)?;
+  Ok(content.map(|content| parse(&chunk_text(content))))
+}
+
+/**
+ * Set production fields in the iXML chunk of a WAV or AIFF buffer, adding the
+ * chunk when missing. The other elements of the document are kept.
+ * @param buffer - The audio data
+ * @param ixml - The fields to set
+ */
+pub async fn write_ixml_to_buffer(buffer: Vec<u8>, ixml: Ixml) -> Result<Vec<u8>, String> {
+  let xml = riff::chunk_content(&buffer, CHUNK_ID)?.map(chunk_text);
+  let xml = update(xml.as_deref(), &ixml);
+  riff::set_chunk(buffer, CHUNK_ID, xml.as_bytes())
+}
+
+pub async fn write_ixml(file_path: impl AsRef<Path>, ixml: Ixml) -> Result<(), String> {
+  write_ixml_with_options(file_path, ixml, SaveOptions::default()).await
+}
+
+pub async fn write_ixml_with_options(
+  file_path: impl AsRef<Path>,
+  ixml: Ixml,
+  options: SaveOptions,
+) -> Result<(), String> {
+  let path = file_path.as_ref();
+  let target = WriteTarget::open(path, false, options.preserve_mtime)?;
+  // the chunk may grow, which moves the audio, so the whole file is rewritten;
+  // the target still refers to it, so its times are restored on commit
+  let buffer = fs::read(path).map_err(|e| format!("Failed to read file: {}", e))?;
+  let buffer = write_ixml_to_buffer(buffer, ixml).await?;
+  fs::write(path, buffer).map_err(|e| format!("Failed to write file: {}", e))?;
+  target.commit()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_support::{sample_wav, write_fixture};
+
+  #[test]
+  fn test_update_keeps_other_elements() {
+    let xml = "<?xml version=\"1.0\"?>\n<BWFXML><IXML_VERSION>1.5</IXML_VERSION>\
+      <SCENE>12A</SCENE><TAKE/><SPEED><TIMECODE_RATE>25/1</TIMECODE_RATE></SPEED>\
+      <TRACK_LIST><TRACK_COUNT>1</TRACK_COUNT><TRACK><CHANNEL_INDEX>1</CHANNEL_INDEX>\
+      <NAME>Boom</NAME></TRACK></TRACK_LIST></BWFXML>";
+    let ixml = parse(xml);
+    assert_eq!(ixml.scene.as_deref(), Some("12A"));
+    assert_eq!(ixml.take, None);
+    assert_eq!(
+      ixml.tracks.as_ref().unwrap()[0].name.as_deref(),
+      Some("Boom")
+    );
+
+    let updated = update(
+      Some(xml),
+      &Ixml {
+        take: Some("3".to_string()),
+        note: Some("Plane <overhead> & wind".to_string()),
+        ..Default::default()
+      },
+    );
+    assert!(updated.contains("<TIMECODE_RATE>25/1</TIMECODE_RATE>"));
+    let reparsed = parse(&updated);
+    assert_eq!(reparsed.scene.as_deref(), Some("12A"));
+    assert_eq!(reparsed.take.as_deref(), Some("3"));
+    assert_eq!(reparsed.note.as_deref(), Some("Plane <overhead> & wind"));
+    assert_eq!(reparsed.tracks, ixml.tracks);
+  }
+
+  #[tokio::test]
+  async fn test_write_and_read_ixml() {
+    let dir = tempfile::tempdir().unwrap();
+    let file_path = write_fixture(dir.path(), "take.wav", &sample_wav());
+    assert_eq!(read_ixml(file_path.clone()).await.unwrap(), None);
+
+    let ixml = Ixml {
+      project: Some("Feature".to_string()),
+      scene: Some("12A".to_string()),
+      take: Some("3".to_string()),
+      tape: Some("Day 4".to_string()),
+      note: None,
+      tracks: Some(vec![
+        IxmlTrack {
+          channel_index: Some(1),
+          interleave_index: Some(1),
+          name: Some("Boom".to_string()),
+        },
+        IxmlTrack {
+          channel_index: Some(2),
+          interleave_index: Some(2),
+          name: Some("Lav".to_string()),
+        },
+      ]),
+    };
+    write_ixml(file_path.clone(), ixml.clone()).await.unwrap();
+    assert_eq!(read_ixml(file_path.clone()).await.unwrap(), Some(ixml));
+    // the file is still a readable WAV
+    crate::util::read_tags(file_path).await.unwrap();
+
+    assert!(write_ixml_to_buffer(b"not audio".to_vec(), Ixml::default())
+      .await
+      .is_err());
+  }
+}
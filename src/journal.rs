@@ -0,0 +1,441 @@
+use crate::atomic::WriteTarget;
+use crate::audio_hash::audio_ranges;
+use crate::util::{in_file, io_error, open_file};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+const JOURNAL_EXTENSION: &str = "tagpilot-undo";
+
+// An entry keeps what a tag write can change: the bytes of the file around
+// its audio. It starts with ENTRY_MAGIC, the number of audio ranges and the
+// length of each, then holds the bytes before, between and after them, each
+// run prefixed with its length. The numbers are little endian u64. A file
+// whose audio can't be located has no audio ranges and is saved whole.
+const ENTRY_MAGIC: &[u8; 4] = b"TPJ1";
+
+// Without a journal directory entries are kept as hidden sidecars next to the
+// audio file; with one, entries are keyed by a hash of the absolute path so
+// files with the same name in different folders don't collide.
+fn journal_location(path: &Path, journal_dir: Option<&Path>) -> Result<(PathBuf, String), String> {
+  match journal_dir {
+    None => {
+      let dir = path
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| PathBuf::from("."));
+      let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+      Ok((dir, format!(".{}", file_name)))
+    }
+    Some(dir) => {
+      let absolute = fs::canonicalize(path)
+        .map_err(|e| format!("Failed to resolve {}: {}", path.display(), e))?;
+      let digest = Sha256::digest(absolute.to_string_lossy().as_bytes());
+      let key = digest
+        .iter()
+        .take(16)
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<String>();
+      Ok((dir.to_path_buf(), key))
+    }
+  }
+}
+
+fn journal_entries(dir: &Path, key: &str) -> Vec<(u64, PathBuf)> {
+  let prefix = format!("{}.", key);
+  let suffix = format!(".{}", JOURNAL_EXTENSION);
+  let Ok(read_dir) = fs::read_dir(dir) else {
+    return Vec::new();
+  };
+  let mut entries: Vec<(u64, PathBuf)> = read_dir
+    .filter_map(|entry| entry.ok())
+    .filter_map(|entry| {
+      let name = entry.file_name().to_string_lossy().to_string();
+      let seq = name.strip_prefix(&prefix)?.strip_suffix(&suffix)?;
+      seq.parse::<u64>().ok().map(|seq| (seq, entry.path()))
+    })
+    .collect();
+  entries.sort_by_key(|(seq, _)| *seq);
+  entries
+}
+
+fn write_error(e: std::io::Error) -> String {
+  io_error("Failed to write journal entry", &e)
+}
+
+fn write_entry(path: &Path, entry: &Path) -> Result<(), String> {
+  let mut file = open_file(path)?;
+  let len = file
+    .seek(SeekFrom::End(0))
+    .map_err(|e| io_error("Failed to read audio file", &e))?;
+  let audio = audio_ranges(&mut file).unwrap_or_default();
+  let mut out = BufWriter::new(File::create(entry).map_err(write_error)?);
+  out.write_all(ENTRY_MAGIC).map_err(write_error)?;
+  out
+    .write_all(&(audio.len() as u64).to_le_bytes())
+    .map_err(write_error)?;
+  for range in &audio {
+    out
+      .write_all(&(range.end - range.start).to_le_bytes())
+      .map_err(write_error)?;
+  }
+  let starts = std::iter::once(0).chain(audio.iter().map(|range| range.end));
+  let ends = audio
+    .iter()
+    .map(|range| range.start)
+    .chain(std::iter::once(len));
+  for (start, end) in starts.zip(ends) {
+    let run = end.saturating_sub(start);
+    out.write_all(&run.to_le_bytes()).map_err(write_error)?;
+    file
+      .seek(SeekFrom::Start(start))
+      .map_err(|e| io_error("Failed to read audio file", &e))?;
+    std::io::copy(&mut (&mut file).take(run), &mut out).map_err(write_error)?;
+  }
+  out
+    .into_inner()
+    .map_err(|e| write_error(e.into_error()))?
+    .sync_all()
+    .map_err(write_error)
+}
+
+// the audio range lengths and the runs of bytes around them
+fn parse_entry(entry: &[u8]) -> Result<(Vec<u64>, Vec<&[u8]>), String> {
+  let invalid = || "Invalid journal entry".to_string();
+  let mut rest = entry.strip_prefix(ENTRY_MAGIC).ok_or_else(invalid)?;
+  let next_u64 = |rest: &mut &[u8]| {
+    let (head, tail) = rest.split_first_chunk::<8>().ok_or_else(invalid)?;
+    *rest = tail;
+    Ok::<_, String>(u64::from_le_bytes(*head))
+  };
+  let count = next_u64(&mut rest)?;
+  let audio = (0..count)
+    .map(|_| next_u64(&mut rest))
+    .collect::<Result<Vec<_>, _>>()?;
+  let mut runs = Vec::with_capacity(audio.len() + 1);
+  for _ in 0..=count {
+    let len = usize::try_from(next_u64(&mut rest)?).map_err(|_| invalid())?;
+    if len > rest.len() {
+      return Err(invalid());
+    }
+    let (run, tail) = rest.split_at(len);
+    runs.push(run);
+    rest = tail;
+  }
+  Ok((audio, runs))
+}
+
+// the audio of a file read as one stream, its ranges one after the other
+struct AudioStream<'a> {
+  file: &'a mut File,
+  ranges: std::vec::IntoIter<Range<u64>>,
+  current: Range<u64>,
+}
+
+impl Read for AudioStream<'_> {
+  fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+    while self.current.is_empty() {
+      match self.ranges.next() {
+        Some(range) => {
+          self.file.seek(SeekFrom::Start(range.start))?;
+          self.current = range;
+        }
+        None => return Ok(0),
+      }
+    }
+    let len = buf
+      .len()
+      .min(usize::try_from(self.current.end - self.current.start).unwrap_or(usize::MAX));
+    let read = self.file.read(&mut buf[..len])?;
+    if read == 0 {
+      return Err(std::io::ErrorKind::UnexpectedEof.into());
+    }
+    self.current.start += read as u64;
+    Ok(read)
+  }
+}
+
+/**
+ * Save the parts of `path` a tag write can change into the journal before it
+ * is modified
+ * @param path - The file about to be written
+ * @param journal_dir - Directory holding the journal, sidecar files are used when None
+ * @returns The path of the new journal entry
+ */
+pub fn record(path: &Path, journal_dir: Option<&Path>) -> Result<PathBuf, String> {
+  let (dir, key) = journal_location(path, journal_dir)?;
+  fs::create_dir_all(&dir).map_err(|e| format!("Failed to create journal directory: {}", e))?;
+  let next = journal_entries(&dir, &key)
+    .last()
+    .map(|(seq, _)| seq + 1)
+    .unwrap_or(1);
+  let name = format!("{}.{}.{}", key, next, JOURNAL_EXTENSION);
+  let entry = dir.join(&name);
+  // written under another name first, so a crash never leaves a partial entry
+  let partial = dir.join(format!("{}.part", name));
+  if let Err(e) = write_entry(path, &partial) {
+    let _ = fs::remove_file(&partial);
+    return Err(e);
+  }
+  fs::rename(&partial, &entry).map_err(write_error)?;
+  Ok(entry)
+}
+
+/**
+ * Restore the file as it was before the most recent journaled write of
+ * `file_path`: the saved bytes around the audio are put back around the
+ * file's current audio, through a copy renamed over the file
+ * @param file_path - The file to restore
+ * @param journal_dir - The journal directory used for the write, sidecar files when None
+ * @returns false when there is nothing to undo
+ */
+pub async fn undo_last_write(
+  file_path: impl AsRef<Path>,
+  journal_dir: Option<PathBuf>,
+) -> Result<bool, String> {
+  let path = file_path.as_ref();
+  let (dir, key) = journal_location(path, journal_dir.as_deref())?;
+  let Some((_, entry)) = journal_entries(&dir, &key).pop() else {
+    return Ok(false);
+  };
+  let saved = fs::read(&entry).map_err(|e| io_error("Failed to read journal entry", &e))?;
+  let (audio_lens, runs) = parse_entry(&saved)?;
+  let mut source = open_file(path)?;
+  let audio = if audio_lens.is_empty() {
+    Vec::new()
+  } else {
+    audio_ranges(&mut source).map_err(in_file(path))?
+  };
+  let audio_len: u64 = audio.iter().map(|range| range.end - range.start).sum();
+  if audio_len != audio_lens.iter().sum::<u64>() {
+    return Err(format!(
+      "Failed to restore {}: its audio changed since the write",
+      path.display()
+    ));
+  }
+
+  let restore_error =
+    |e: std::io::Error| io_error(&format!("Failed to restore {}", path.display()), &e);
+  let mut stream = AudioStream {
+    file: &mut source,
+    ranges: audio.into_iter(),
+    current: 0..0,
+  };
+  let mut target = WriteTarget::open(path, true, false)?;
+  let out = target.file();
+  out.set_len(0).map_err(restore_error)?;
+  for (i, run) in runs.iter().enumerate() {
+    out.write_all(run).map_err(restore_error)?;
+    if let Some(&len) = audio_lens.get(i) {
+      std::io::copy(&mut stream.by_ref().take(len), out).map_err(restore_error)?;
+    }
+  }
+  target.commit()?;
+  fs::remove_file(&entry).map_err(|e| format!("Failed to remove journal entry: {}", e))?;
+  Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_support::{sample_m4a, sample_mp3, sample_opus, write_fixture};
+  use crate::util::{self, AudioTags, WriteTagsOptions};
+
+  fn titled(title: &str) -> AudioTags {
+    AudioTags {
+      title: Some(title.to_string()),
+      ..Default::default()
+    }
+  }
+
+  #[tokio::test]
+  async fn test_undo_last_write_with_sidecar_journal() {
+    let dir = tempfile::tempdir().unwrap();
+    let original = sample_mp3();
+    let path = write_fixture(dir.path(), "song.mp3", &original);
+    let options = WriteTagsOptions {
+      journal: true,
+      ..Default::default()
+    };
+
+    util::write_tags_with_options(path.clone(), titled("First"), options.clone())
+      .await
+      .unwrap();
+    let after_first = fs::read(&path).unwrap();
+    util::write_tags_with_options(path.clone(), titled("Second"), options)
+      .await
+      .unwrap();
+
+    assert!(undo_last_write(path.clone(), None).await.unwrap());
+    assert_eq!(fs::read(&path).unwrap(), after_first);
+    assert!(undo_last_write(path.clone(), None).await.unwrap());
+    assert_eq!(fs::read(&path).unwrap(), original);
+    assert!(!undo_last_write(path.clone(), None).await.unwrap());
+  }
+
+  #[tokio::test]
+  async fn test_undo_last_write_with_journal_directory() {
+    let dir = tempfile::tempdir().unwrap();
+    let journal_dir = tempfile::tempdir().unwrap();
+    let journal_path = journal_dir.path().to_string_lossy().to_string();
+    let original = sample_mp3();
+    let path = write_fixture(dir.path(), "song.mp3", &original);
+
+    util::write_tags_with_options(
+      path.clone(),
+      titled("Changed"),
+      WriteTagsOptions {
+        journal: true,
+        journal_dir: Some(journal_path.clone()),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+
+    // nothing is written next to the audio file
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    assert_eq!(fs::read_dir(journal_dir.path()).unwrap().count(), 1);
+
+    assert!(undo_last_write(path.clone(), Some(journal_path.into()))
+      .await
+      .unwrap());
+    assert_eq!(fs::read(&path).unwrap(), original);
+    assert_eq!(fs::read_dir(journal_dir.path()).unwrap().count(), 0);
+  }
+
+  #[tokio::test]
+  async fn test_failed_atomic_write_does_not_leave_journal_entry() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_fixture(dir.path(), "broken.mp3", b"not audio");
+
+    let result = util::write_tags_with_options(
+      path.clone(),
+      titled("Changed"),
+      WriteTagsOptions {
+        journal: true,
+        atomic: true,
+        ..Default::default()
+      },
+    )
+    .await;
+
+    assert!(result.is_err());
+    assert!(!undo_last_write(path, None).await.unwrap());
+  }
+
+  #[tokio::test]
+  async fn test_failed_in_place_write_can_be_undone() {
+    let dir = tempfile::tempdir().unwrap();
+    let original = b"not audio".to_vec();
+    let path = write_fixture(dir.path(), "broken.mp3", &original);
+
+    let result = util::write_tags_with_options(
+      path.clone(),
+      titled("Changed"),
+      WriteTagsOptions {
+        journal: true,
+        ..Default::default()
+      },
+    )
+    .await;
+    assert!(result.is_err());
+    // as a write that failed halfway would leave it
+    fs::write(&path, b"not aud\0\0\0\0").unwrap();
+
+    assert!(undo_last_write(path.clone(), None).await.unwrap());
+    assert_eq!(fs::read(&path).unwrap(), original);
+  }
+
+  #[tokio::test]
+  async fn test_journal_entry_leaves_out_the_audio() {
+    let dir = tempfile::tempdir().unwrap();
+    let journal_dir = tempfile::tempdir().unwrap();
+    let original = sample_mp3();
+    let path = write_fixture(dir.path(), "song.mp3", &original);
+    let audio_len: u64 = audio_ranges(&mut File::open(&path).unwrap())
+      .unwrap()
+      .iter()
+      .map(|range| range.end - range.start)
+      .sum();
+    assert!(audio_len > 0);
+
+    let entry = record(Path::new(&path), Some(journal_dir.path())).unwrap();
+
+    let entry_len = fs::metadata(&entry).unwrap().len();
+    assert!(entry_len < original.len() as u64 - audio_len + 64);
+    assert_eq!(fs::read_dir(journal_dir.path()).unwrap().count(), 1);
+  }
+
+  #[tokio::test]
+  async fn test_undo_last_write_restores_mp4_and_ogg_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let options = WriteTagsOptions {
+      journal: true,
+      ..Default::default()
+    };
+    for (name, original) in [("song.m4a", sample_m4a()), ("song.opus", sample_opus())] {
+      let path = write_fixture(dir.path(), name, &original);
+      util::write_tags_with_options(
+        path.clone(),
+        titled(&"Long title ".repeat(500)),
+        options.clone(),
+      )
+      .await
+      .unwrap();
+      assert_ne!(fs::read(&path).unwrap(), original);
+
+      assert!(undo_last_write(path.clone(), None).await.unwrap());
+      assert_eq!(fs::read(&path).unwrap(), original, "{}", name);
+    }
+  }
+
+  #[tokio::test]
+  async fn test_undo_last_write_refuses_changed_audio() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_fixture(dir.path(), "song.mp3", &sample_mp3());
+    record(Path::new(&path), None).unwrap();
+    // the same tags over other audio
+    let mut replaced = fs::read(&path).unwrap();
+    replaced.truncate(replaced.len() - 100);
+    fs::write(&path, &replaced).unwrap();
+
+    let error = undo_last_write(path.clone(), None).await.unwrap_err();
+
+    assert!(error.contains("audio changed"), "{}", error);
+    assert_eq!(fs::read(&path).unwrap(), replaced);
+  }
+
+  #[tokio::test]
+  async fn test_undo_last_write_keeps_permissions() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_fixture(dir.path(), "song.mp3", &sample_mp3());
+    #[cfg(unix)]
+    {
+      use std::os::unix::fs::PermissionsExt;
+      fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+    }
+    let before = fs::metadata(&path).unwrap().permissions();
+    util::write_tags_with_options(
+      path.clone(),
+      titled("Changed"),
+      WriteTagsOptions {
+        journal: true,
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+
+    assert!(undo_last_write(path.clone(), None).await.unwrap());
+
+    assert_eq!(fs::metadata(&path).unwrap().permissions(), before);
+    // the copy used for the restore is gone
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+  }
+}
@@ -0,0 +1,213 @@
+use crate::dsd;
+use crate::util::mpeg_stand_in;
+use encoding_rs::Encoding;
+use lofty::aac::AacFile;
+use lofty::config::ParseOptions;
+use lofty::file::{AudioFile, FileType};
+use lofty::id3::v2::{Frame, Id3v2Tag};
+use lofty::iff::aiff::AiffFile;
+use lofty::iff::wav::WavFile;
+use lofty::mpeg::MpegFile;
+use lofty::tag::{ItemValue, Tag, TagItem, TagType};
+use lofty::TextEncoding;
+use std::collections::HashSet;
+use std::io::{Cursor, Read, Seek};
+
+/**
+ * The encoding of a WHATWG label, e.g. "windows-1251", "gbk" or "shift_jis"
+ * @param label - The label
+ */
+pub(crate) fn encoding(label: &str) -> Result<&'static Encoding, String> {
+  Encoding::for_label(label.trim().as_bytes())
+    .ok_or_else(|| format!("Unknown legacy encoding: {}", label))
+}
+
+// the bytes behind text decoded as Latin-1, None for text that wasn't
+fn latin1_bytes(text: &str) -> Option<Vec<u8>> {
+  text.chars().map(|c| u8::try_from(c).ok()).collect()
+}
+
+// text a legacy tagger stored in `encoding` while declaring Latin-1; None
+// when there is nothing to decode or the bytes aren't valid in `encoding`
+fn redecode(text: &str, encoding: &'static Encoding) -> Option<String> {
+  let bytes = latin1_bytes(text).filter(|bytes| !bytes.is_ascii())?;
+  encoding
+    .decode_without_bom_handling_and_without_replacement(&bytes)
+    .map(|decoded| decoded.into_owned())
+}
+
+// the ID3v2 tag of the file as lofty reads it, which keeps the encoding of
+// each frame
+fn read_id3v2<R: Read + Seek>(reader: &mut R, file_type: FileType) -> Option<Id3v2Tag> {
+  let options = ParseOptions::new()
+    .read_properties(false)
+    .read_cover_art(false);
+  if let Some(format) = dsd::format(reader) {
+    let tag = dsd::read_id3v2(reader, format).ok()??;
+    let mut stand_in = Cursor::new(mpeg_stand_in(&tag));
+    return MpegFile::read_from(&mut stand_in, options)
+      .ok()?
+      .id3v2()
+      .cloned();
+  }
+  reader.rewind().ok()?;
+  match file_type {
+    FileType::Mpeg => MpegFile::read_from(reader, options).ok()?.id3v2().cloned(),
+    FileType::Wav => WavFile::read_from(reader, options).ok()?.id3v2().cloned(),
+    FileType::Aiff => AiffFile::read_from(reader, options).ok()?.id3v2().cloned(),
+    FileType::Aac => AacFile::read_from(reader, options).ok()?.id3v2().cloned(),
+    _ => None,
+  }
+}
+
+// the text of the frames declared Latin-1, the values of multi-value frames
+// one by one as lofty splits them into items
+fn latin1_texts(id3v2: &Id3v2Tag) -> HashSet<String> {
+  let mut texts = HashSet::new();
+  for frame in id3v2 {
+    match frame {
+      Frame::Text(frame) if frame.encoding == TextEncoding::Latin1 => {
+        texts.extend(frame.value.split('\0').map(str::to_string));
+      }
+      Frame::UserText(frame) if frame.encoding == TextEncoding::Latin1 => {
+        texts.insert(frame.content.clone());
+      }
+      Frame::Comment(frame) if frame.encoding == TextEncoding::Latin1 => {
+        texts.insert(frame.content.clone());
+      }
+      Frame::UnsynchronizedText(frame) if frame.encoding == TextEncoding::Latin1 => {
+        texts.insert(frame.content.clone());
+      }
+      _ => {}
+    }
+  }
+  texts
+}
+
+/**
+ * Decode the text of a tag that a legacy tagger stored in a local encoding,
+ * e.g. CP1251 or Shift-JIS, while declaring Latin-1. Only ID3v1 tags, which are
+ * Latin-1 throughout, and the Latin-1 frames of ID3v2 tags are decoded; text
+ * that isn't valid in the encoding is kept as read.
+ * @param tag - The tag as read
+ * @param reader - The file the tag was read from
+ * @param file_type - The type of the file
+ * @param encoding - The encoding the text was stored in
+ */
+pub(crate) fn decode_tag<R: Read + Seek>(
+  tag: &Tag,
+  reader: &mut R,
+  file_type: FileType,
+  encoding: &'static Encoding,
+) -> Tag {
+  let latin1 = match tag.tag_type() {
+    TagType::Id3v1 => None,
+    TagType::Id3v2 => match read_id3v2(reader, file_type) {
+      Some(id3v2) => Some(latin1_texts(&id3v2)),
+      None => return tag.clone(),
+    },
+    _ => return tag.clone(),
+  };
+  let mut decoded = tag.clone();
+  decoded.retain(|_| false);
+  for item in tag.items() {
+    let text = match item.value() {
+      ItemValue::Text(text) if latin1.as_ref().is_none_or(|texts| texts.contains(text)) => {
+        redecode(text, encoding)
+      }
+      _ => None,
+    };
+    let Some(text) = text else {
+      decoded.push_unchecked(item.clone());
+      continue;
+    };
+    let mut redecoded = TagItem::new(item.key().clone(), ItemValue::Text(text));
+    redecoded.set_lang(*item.lang());
+    redecoded.set_description(item.description().to_string());
+    decoded.push_unchecked(redecoded);
+  }
+  decoded
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_support::sample_mp3;
+  use lofty::config::WriteOptions;
+  use lofty::id3::v1::Id3v1Tag;
+  use lofty::id3::v2::{FrameId, TextInformationFrame};
+  use lofty::tag::{Accessor, TagExt};
+  use std::borrow::Cow;
+
+  // "Привет" in CP1251, as a Latin-1 reader sees it
+  fn cp1251_as_latin1() -> String {
+    [0xCF, 0xF0, 0xE8, 0xE2, 0xE5, 0xF2]
+      .into_iter()
+      .map(char::from)
+      .collect()
+  }
+
+  fn text_frame(id: &'static str, encoding: TextEncoding, value: String) -> Frame<'static> {
+    Frame::Text(TextInformationFrame::new(
+      FrameId::Valid(Cow::Borrowed(id)),
+      encoding,
+      value,
+    ))
+  }
+
+  #[test]
+  fn test_encoding_labels() {
+    assert_eq!(encoding("windows-1251").unwrap().name(), "windows-1251");
+    assert_eq!(encoding("cp1251").unwrap().name(), "windows-1251");
+    assert_eq!(encoding("Shift_JIS").unwrap().name(), "Shift_JIS");
+    assert_eq!(
+      encoding("klingon").unwrap_err(),
+      "Unknown legacy encoding: klingon"
+    );
+  }
+
+  #[test]
+  fn test_decode_latin1_id3v2_frames() {
+    let mut id3v2 = Id3v2Tag::new();
+    id3v2.insert(text_frame("TIT2", TextEncoding::Latin1, cp1251_as_latin1()));
+    // declared UTF-8, so not legacy text
+    id3v2.insert(text_frame("TALB", TextEncoding::UTF8, "Café".to_string()));
+    let mut file = Cursor::new(sample_mp3());
+    id3v2.save_to(&mut file, WriteOptions::default()).unwrap();
+
+    let tag: Tag = id3v2.into();
+    let cp1251 = encoding("windows-1251").unwrap();
+    let decoded = decode_tag(&tag, &mut file, FileType::Mpeg, cp1251);
+    assert_eq!(decoded.title().as_deref(), Some("Привет"));
+    assert_eq!(decoded.album().as_deref(), Some("Café"));
+  }
+
+  #[test]
+  fn test_decode_id3v1_tag() {
+    let id3v1 = Id3v1Tag {
+      title: Some(cp1251_as_latin1()),
+      artist: Some("Artist".to_string()),
+      ..Default::default()
+    };
+    let tag: Tag = id3v1.into();
+    let cp1251 = encoding("windows-1251").unwrap();
+    let decoded = decode_tag(&tag, &mut Cursor::new(Vec::new()), FileType::Mpeg, cp1251);
+    assert_eq!(decoded.title().as_deref(), Some("Привет"));
+    assert_eq!(decoded.artist().as_deref(), Some("Artist"));
+  }
+
+  #[test]
+  fn test_decode_keeps_invalid_text() {
+    let id3v1 = Id3v1Tag {
+      // UTF-8 stored as Latin-1, next to actual Latin-1
+      title: Some("CafÃ©".to_string()),
+      artist: Some("Café".to_string()),
+      ..Default::default()
+    };
+    let tag: Tag = id3v1.into();
+    let utf8 = encoding("utf-8").unwrap();
+    let decoded = decode_tag(&tag, &mut Cursor::new(Vec::new()), FileType::Mpeg, utf8);
+    assert_eq!(decoded.title().as_deref(), Some("Café"));
+    assert_eq!(decoded.artist().as_deref(), Some("Café"));
+  }
+}
@@ -1,11 +1,109 @@
 #![deny(clippy::all)]
 
+mod album;
+mod artwork;
+mod atomic;
+mod audio_hash;
+mod backup;
+mod batch;
+mod binary_objects;
+mod cancel;
+mod chapters;
+#[cfg(feature = "chromaprint")]
+mod chromaprint;
+mod comments;
+mod custom_fields;
+mod diff;
+mod dsd;
+mod export;
+mod fingerprint;
+mod formats;
+mod gapless;
+mod genres;
+mod hooks;
+mod id3v1;
+mod identifiers;
+mod ixml;
+mod journal;
+mod legacy_encoding;
+mod manifest;
+mod mp4_chapters;
+mod mp4_freeform;
+mod normalize;
+mod patch;
+mod placement;
+mod pool;
+mod probe;
+mod rating;
+mod raw_items;
+mod read_warnings;
+mod rename;
+mod riff;
+mod scan;
+mod stream;
+mod synced_lyrics;
+#[cfg(test)]
+mod test_support;
+mod transaction;
+mod transform;
 mod util;
+mod validate;
+mod watch;
+mod writability;
 
-use crate::util::{AudioImageType, AudioTags, Image, Position};
-use napi::bindgen_prelude::Buffer;
-use napi::Result;
+use crate::album::AlbumField;
+use crate::artwork::{ArtworkCacheEntry, ImageInfo, ImageLimits, ResizeFormat, ResizeOptions};
+use crate::backup::Backup;
+use crate::batch::{BatchFileResult, BatchReadResult, Progress};
+use crate::binary_objects::BinaryObject;
+use crate::cancel::Cancellation;
+use crate::chapters::Chapter;
+use crate::comments::Comment;
+use crate::diff::TagChange;
+use crate::export::ExportFormat;
+use crate::fingerprint::TagsEqualOptions;
+use crate::formats::FormatSupport;
+use crate::gapless::{GaplessInfo, GaplessSource};
+use crate::hooks::{HookDecision, NoHooks, WriteHooks};
+use crate::identifiers::IdentifierValidation;
+use crate::ixml::{Ixml, IxmlTrack};
+use crate::manifest::{ImportOptions, ImportResult, MatchBy};
+use crate::patch::{TagField, TagPatch};
+use crate::placement::{ArtworkPolicy, FlacPictureStorage, Id3PictureEncoding, Mp4CoverFormat};
+use crate::pool::BUFFER_POOL;
+use crate::probe::{BufferProbe, FileProbe};
+use crate::rating::PopmScale;
+use crate::raw_items::RawTagItem;
+use crate::rename::{CollisionPolicy, InferOptions, RenameOptions, RenameOutcome};
+use crate::riff::Bwf;
+use crate::scan::{ScanOptions, ScanResult};
+use crate::stream::ByteSource;
+use crate::synced_lyrics::{LyricLine, SyncedLyrics};
+use crate::transaction::TagWriteEntry;
+use crate::transform::{TransformAction, TransformRule, DEFAULT_FIELDS};
+use crate::util::{
+  ArtistSeparator, AudioImageType, AudioProperties, AudioTags, ClearTagsOptions, CoverImageOptions,
+  Id3TextEncoding, Id3v2Version, Image, MergeRule, MergeStrategy, MimeInference, Position,
+  ReadTagsOptions, ReadTagsOutcome, SaveOptions, TagEntry, WriteTagsOptions, WriteTagsOutcome,
+};
+use crate::validate::{FindingKind, ValidationFinding, ValidationRules};
+use crate::watch::{DirectoryWatch, WatchEvent, WatchEventKind, WatchOptions};
+use crate::writability::Writability;
+use lofty::config::ParsingMode;
+use lofty::tag::{ItemValue, TagType};
+use napi::bindgen_prelude::{
+  block_on, AbortSignal, Buffer, Either, Either4, FnArgs, FromNapiValue, JsObjectValue, Object,
+  Promise, PromiseRaw, ToNapiValue, Utf16String,
+};
+use napi::threadsafe_function::{
+  ThreadsafeFunction, ThreadsafeFunctionCallMode, UnknownReturnValue,
+};
+use napi::{Env, Result, Status};
 use napi_derive::napi;
+use regex::Regex;
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
 
 #[napi(js_name = "Position", object)]
 #[derive(Debug, PartialEq)]
@@ -30,6 +128,139 @@ impl ApiPosition {
   }
 }
 
+#[napi(js_name = "Bwf", object)]
+#[derive(Debug, PartialEq)]
+pub struct ApiBwf {
+  pub description: Option<String>,
+  pub originator: Option<String>,
+  pub originator_reference: Option<String>,
+  pub origination_date: Option<String>,
+  pub origination_time: Option<String>,
+  // samples since midnight, beyond 32 bits at common sample rates
+  pub time_reference: Option<i64>,
+}
+
+impl ApiBwf {
+  pub fn from_bwf(bwf: Bwf) -> Self {
+    Self {
+      description: bwf.description,
+      originator: bwf.originator,
+      originator_reference: bwf.originator_reference,
+      origination_date: bwf.origination_date,
+      origination_time: bwf.origination_time,
+      time_reference: bwf.time_reference.map(|samples| samples as i64),
+    }
+  }
+
+  pub fn into_bwf(self) -> Bwf {
+    Bwf {
+      description: self.description,
+      originator: self.originator,
+      originator_reference: self.originator_reference,
+      origination_date: self.origination_date,
+      origination_time: self.origination_time,
+      time_reference: self.time_reference.map(|samples| samples.max(0) as u64),
+    }
+  }
+}
+
+#[napi(js_name = "BinaryObject", object)]
+pub struct ApiBinaryObject {
+  pub description: String,
+  pub mime_type: Option<String>,
+  pub filename: Option<String>,
+  pub data: Buffer,
+}
+
+impl ApiBinaryObject {
+  pub fn from_binary_object(object: BinaryObject) -> Self {
+    Self {
+      description: object.description,
+      mime_type: object.mime_type,
+      filename: object.filename,
+      data: object.data.into(),
+    }
+  }
+
+  pub fn into_binary_object(self) -> BinaryObject {
+    BinaryObject {
+      description: self.description,
+      mime_type: self.mime_type,
+      filename: self.filename,
+      data: self.data.to_vec(),
+    }
+  }
+}
+
+#[napi(js_name = "IxmlTrack", object)]
+pub struct ApiIxmlTrack {
+  pub channel_index: Option<u32>,
+  pub interleave_index: Option<u32>,
+  pub name: Option<String>,
+}
+
+impl ApiIxmlTrack {
+  pub fn from_ixml_track(track: IxmlTrack) -> Self {
+    Self {
+      channel_index: track.channel_index,
+      interleave_index: track.interleave_index,
+      name: track.name,
+    }
+  }
+
+  pub fn into_ixml_track(self) -> IxmlTrack {
+    IxmlTrack {
+      channel_index: self.channel_index,
+      interleave_index: self.interleave_index,
+      name: self.name,
+    }
+  }
+}
+
+#[napi(js_name = "Ixml", object)]
+pub struct ApiIxml {
+  pub project: Option<String>,
+  pub scene: Option<String>,
+  pub take: Option<String>,
+  pub tape: Option<String>,
+  pub note: Option<String>,
+  pub tracks: Option<Vec<ApiIxmlTrack>>,
+}
+
+impl ApiIxml {
+  pub fn from_ixml(ixml: Ixml) -> Self {
+    Self {
+      project: ixml.project,
+      scene: ixml.scene,
+      take: ixml.take,
+      tape: ixml.tape,
+      note: ixml.note,
+      tracks: ixml.tracks.map(|tracks| {
+        tracks
+          .into_iter()
+          .map(ApiIxmlTrack::from_ixml_track)
+          .collect()
+      }),
+    }
+  }
+
+  pub fn into_ixml(self) -> Ixml {
+    Ixml {
+      project: self.project,
+      scene: self.scene,
+      take: self.take,
+      tape: self.tape,
+      note: self.note,
+      tracks: self.tracks.map(|tracks| {
+        tracks
+          .into_iter()
+          .map(ApiIxmlTrack::into_ixml_track)
+          .collect()
+      }),
+    }
+  }
+}
+
 #[napi(js_name = "AudioImageType", string_enum)]
 pub enum ApiAudioImageType {
   Icon,
@@ -115,6 +346,11 @@ pub struct ApiImage {
   pub pic_type: ApiAudioImageType,
   pub mime_type: Option<String>,
   pub description: Option<String>,
+  // only set on read, ignored on write
+  pub mime_mismatch: Option<bool>,
+  pub width: Option<u32>,
+  pub height: Option<u32>,
+  pub has_alpha: Option<bool>,
 }
 
 impl ApiImage {
@@ -124,6 +360,10 @@ impl ApiImage {
       pic_type: ApiAudioImageType::from_audio_image_type(image.pic_type),
       mime_type: image.mime_type,
       description: image.description,
+      mime_mismatch: Some(image.mime_mismatch),
+      width: image.width,
+      height: image.height,
+      has_alpha: image.has_alpha,
     }
   }
 
@@ -133,6 +373,32 @@ impl ApiImage {
       pic_type: self.pic_type.into_audio_image_type(),
       mime_type: self.mime_type,
       description: self.description,
+      ..Default::default()
+    }
+  }
+}
+
+#[napi(js_name = "Comment", object)]
+pub struct ApiComment {
+  pub language: Option<String>,
+  pub description: Option<String>,
+  pub text: String,
+}
+
+impl ApiComment {
+  pub fn from_comment(comment: Comment) -> Self {
+    Self {
+      language: comment.language,
+      description: comment.description,
+      text: comment.text,
+    }
+  }
+
+  pub fn into_comment(self) -> Comment {
+    Comment {
+      language: self.language,
+      description: self.description,
+      text: self.text,
     }
   }
 }
@@ -141,132 +407,3729 @@ impl ApiImage {
 #[derive(Default)]
 pub struct ApiAudioTags {
   pub title: Option<String>,
+  pub subtitle: Option<String>,
   pub artists: Option<Vec<String>>,
   pub album: Option<String>,
   pub year: Option<u32>,
   pub genre: Option<String>,
+  pub genres: Option<Vec<String>>,
   pub track: Option<ApiPosition>,
   pub album_artists: Option<Vec<String>>,
   pub comment: Option<String>,
+  pub comments: Option<Vec<ApiComment>>,
   pub disc: Option<ApiPosition>,
+  pub disc_subtitle: Option<String>,
   pub image: Option<ApiImage>,
   pub all_images: Option<Vec<ApiImage>>,
+  pub composer: Option<String>,
+  pub lyricist: Option<String>,
+  pub conductor: Option<String>,
+  pub remixer: Option<String>,
+  pub publisher: Option<String>,
+  pub copyright: Option<String>,
+  pub lyrics: Option<String>,
+  pub isrc: Option<String>,
+  pub barcode: Option<String>,
+  pub catalog_number: Option<String>,
+  pub musicbrainz_recording_id: Option<String>,
+  pub musicbrainz_track_id: Option<String>,
+  pub musicbrainz_release_id: Option<String>,
+  pub musicbrainz_release_group_id: Option<String>,
+  pub musicbrainz_artist_id: Option<String>,
+  pub musicbrainz_album_artist_id: Option<String>,
+  pub replay_gain_track_gain: Option<f64>,
+  pub replay_gain_track_peak: Option<f64>,
+  pub replay_gain_album_gain: Option<f64>,
+  pub replay_gain_album_peak: Option<f64>,
+  pub bpm: Option<f64>,
+  pub initial_key: Option<String>,
+  pub mood: Option<String>,
+  pub rating: Option<u8>,
+  pub play_count: Option<u32>,
+  pub compilation: Option<bool>,
+  pub grouping: Option<String>,
+  pub work: Option<String>,
+  pub movement: Option<String>,
+  pub movement_number: Option<u32>,
+  pub title_sort: Option<String>,
+  pub album_sort: Option<String>,
+  pub artist_sort: Option<String>,
+  pub album_artist_sort: Option<String>,
+  pub composer_sort: Option<String>,
+  pub encoder_software: Option<String>,
+  pub encoder_settings: Option<String>,
+  pub custom_fields: Option<HashMap<String, Vec<String>>>,
+  pub bwf: Option<ApiBwf>,
+  pub mp4_freeform: Option<HashMap<String, Buffer>>,
+  pub binary_objects: Option<Vec<ApiBinaryObject>>,
+  // only set by reads with tolerateTruncation, ignored on write
+  pub truncated: Option<bool>,
+  // only set by reads with reportWarnings, ignored on write
+  pub warnings: Option<Vec<String>>,
+  // only set by reads with gaplessInfo, ignored on write
+  pub gapless_info: Option<ApiGaplessInfo>,
 }
 
 impl ApiAudioTags {
   pub fn from_audio_tags(audio_tags: AudioTags) -> Self {
     Self {
       title: audio_tags.title,
+      subtitle: audio_tags.subtitle,
       artists: audio_tags.artists,
       album: audio_tags.album,
       year: audio_tags.year,
       genre: audio_tags.genre,
+      genres: audio_tags.genres,
       track: audio_tags.track.map(ApiPosition::from_position),
       album_artists: audio_tags.album_artists,
       comment: audio_tags.comment,
+      comments: audio_tags
+        .comments
+        .map(|comments| comments.into_iter().map(ApiComment::from_comment).collect()),
       disc: audio_tags.disc.map(ApiPosition::from_position),
+      disc_subtitle: audio_tags.disc_subtitle,
       image: audio_tags.image.map(ApiImage::from_image),
       all_images: audio_tags
         .all_images
         .map(|images| images.into_iter().map(ApiImage::from_image).collect()),
+      composer: audio_tags.composer,
+      lyricist: audio_tags.lyricist,
+      conductor: audio_tags.conductor,
+      remixer: audio_tags.remixer,
+      publisher: audio_tags.publisher,
+      copyright: audio_tags.copyright,
+      lyrics: audio_tags.lyrics,
+      isrc: audio_tags.isrc,
+      barcode: audio_tags.barcode,
+      catalog_number: audio_tags.catalog_number,
+      musicbrainz_recording_id: audio_tags.musicbrainz_recording_id,
+      musicbrainz_track_id: audio_tags.musicbrainz_track_id,
+      musicbrainz_release_id: audio_tags.musicbrainz_release_id,
+      musicbrainz_release_group_id: audio_tags.musicbrainz_release_group_id,
+      musicbrainz_artist_id: audio_tags.musicbrainz_artist_id,
+      musicbrainz_album_artist_id: audio_tags.musicbrainz_album_artist_id,
+      replay_gain_track_gain: audio_tags.replay_gain_track_gain,
+      replay_gain_track_peak: audio_tags.replay_gain_track_peak,
+      replay_gain_album_gain: audio_tags.replay_gain_album_gain,
+      replay_gain_album_peak: audio_tags.replay_gain_album_peak,
+      bpm: audio_tags.bpm,
+      initial_key: audio_tags.initial_key,
+      mood: audio_tags.mood,
+      rating: audio_tags.rating,
+      play_count: audio_tags.play_count,
+      compilation: audio_tags.compilation,
+      grouping: audio_tags.grouping,
+      work: audio_tags.work,
+      movement: audio_tags.movement,
+      movement_number: audio_tags.movement_number,
+      title_sort: audio_tags.title_sort,
+      album_sort: audio_tags.album_sort,
+      artist_sort: audio_tags.artist_sort,
+      album_artist_sort: audio_tags.album_artist_sort,
+      composer_sort: audio_tags.composer_sort,
+      encoder_software: audio_tags.encoder_software,
+      encoder_settings: audio_tags.encoder_settings,
+      custom_fields: audio_tags
+        .custom_fields
+        .map(|fields| fields.into_iter().collect()),
+      bwf: audio_tags.bwf.map(ApiBwf::from_bwf),
+      mp4_freeform: audio_tags.mp4_freeform.map(|atoms| {
+        atoms
+          .into_iter()
+          .map(|(name, data)| (name, data.into()))
+          .collect()
+      }),
+      binary_objects: audio_tags.binary_objects.map(|objects| {
+        objects
+          .into_iter()
+          .map(ApiBinaryObject::from_binary_object)
+          .collect()
+      }),
+      truncated: None,
+      warnings: None,
+      gapless_info: None,
+    }
+  }
+
+  pub fn from_read_tags_outcome(outcome: ReadTagsOutcome) -> Self {
+    Self {
+      truncated: Some(outcome.truncated),
+      warnings: outcome.warnings,
+      gapless_info: outcome.gapless_info.map(ApiGaplessInfo::from_gapless_info),
+      ..Self::from_audio_tags(outcome.tags)
     }
   }
 
   pub fn into_audio_tags(self) -> AudioTags {
     AudioTags {
       title: self.title,
+      subtitle: self.subtitle,
       artists: self.artists,
       album: self.album,
       year: self.year,
       genre: self.genre,
+      genres: self.genres,
       track: self.track.map(|position| position.into_position()),
       album_artists: self.album_artists,
       comment: self.comment,
+      comments: self
+        .comments
+        .map(|comments| comments.into_iter().map(ApiComment::into_comment).collect()),
       disc: self.disc.map(|position| position.into_position()),
+      disc_subtitle: self.disc_subtitle,
       image: self.image.map(|image| image.into_image()),
       all_images: self
         .all_images
         .map(|images| images.into_iter().map(ApiImage::into_image).collect()),
+      composer: self.composer,
+      lyricist: self.lyricist,
+      conductor: self.conductor,
+      remixer: self.remixer,
+      publisher: self.publisher,
+      copyright: self.copyright,
+      lyrics: self.lyrics,
+      isrc: self.isrc,
+      barcode: self.barcode,
+      catalog_number: self.catalog_number,
+      musicbrainz_recording_id: self.musicbrainz_recording_id,
+      musicbrainz_track_id: self.musicbrainz_track_id,
+      musicbrainz_release_id: self.musicbrainz_release_id,
+      musicbrainz_release_group_id: self.musicbrainz_release_group_id,
+      musicbrainz_artist_id: self.musicbrainz_artist_id,
+      musicbrainz_album_artist_id: self.musicbrainz_album_artist_id,
+      replay_gain_track_gain: self.replay_gain_track_gain,
+      replay_gain_track_peak: self.replay_gain_track_peak,
+      replay_gain_album_gain: self.replay_gain_album_gain,
+      replay_gain_album_peak: self.replay_gain_album_peak,
+      bpm: self.bpm,
+      initial_key: self.initial_key,
+      mood: self.mood,
+      rating: self.rating,
+      play_count: self.play_count,
+      compilation: self.compilation,
+      grouping: self.grouping,
+      work: self.work,
+      movement: self.movement,
+      movement_number: self.movement_number,
+      title_sort: self.title_sort,
+      album_sort: self.album_sort,
+      artist_sort: self.artist_sort,
+      album_artist_sort: self.album_artist_sort,
+      composer_sort: self.composer_sort,
+      encoder_software: self.encoder_software,
+      encoder_settings: self.encoder_settings,
+      custom_fields: self
+        .custom_fields
+        .map(|fields| fields.into_iter().collect()),
+      bwf: self.bwf.map(ApiBwf::into_bwf),
+      mp4_freeform: self.mp4_freeform.map(|atoms| {
+        atoms
+          .into_iter()
+          .map(|(name, data)| (name, data.to_vec()))
+          .collect()
+      }),
+      binary_objects: self.binary_objects.map(|objects| {
+        objects
+          .into_iter()
+          .map(ApiBinaryObject::into_binary_object)
+          .collect()
+      }),
     }
   }
 }
 
-#[napi]
-pub async fn read_tags(file_path: String) -> Result<ApiAudioTags> {
-  let tags = util::read_tags(file_path)
-    .await
-    .map_err(napi::Error::from_reason)?;
-  Ok(ApiAudioTags::from_audio_tags(tags))
+#[napi(js_name = "TagWriteEntry", object)]
+pub struct ApiTagWriteEntry {
+  pub file_path: String,
+  pub tags: ApiAudioTags,
 }
 
-#[napi]
-pub async fn read_tags_from_buffer(buffer: napi::bindgen_prelude::Buffer) -> Result<ApiAudioTags> {
-  let tags = util::read_tags_from_buffer(buffer.to_vec())
-    .await
-    .map_err(napi::Error::from_reason)?;
-  Ok(ApiAudioTags::from_audio_tags(tags))
+impl ApiTagWriteEntry {
+  pub fn into_tag_write_entry(self) -> TagWriteEntry {
+    TagWriteEntry {
+      file_path: self.file_path.into(),
+      tags: self.tags.into_audio_tags(),
+    }
+  }
 }
 
-#[napi]
-pub async fn write_tags(file_path: String, tags: ApiAudioTags) -> Result<()> {
-  util::write_tags(file_path, tags.into_audio_tags())
-    .await
-    .map_err(napi::Error::from_reason)
+#[napi(js_name = "WriteHookEvent", object)]
+pub struct ApiWriteHookEvent {
+  pub file_path: String,
+  pub old_tags: ApiAudioTags,
+  pub new_tags: ApiAudioTags,
 }
 
-#[napi]
-pub async fn write_tags_to_buffer(
-  buffer: napi::bindgen_prelude::Buffer,
-  tags: ApiAudioTags,
-) -> Result<napi::bindgen_prelude::Buffer> {
-  let result = util::write_tags_to_buffer(buffer.to_vec(), tags.into_audio_tags())
-    .await
-    .map_err(napi::Error::from_reason)?;
-  Ok(Buffer::from(result))
+impl ApiWriteHookEvent {
+  pub fn new(file_path: &str, old_tags: &AudioTags, new_tags: &AudioTags) -> Self {
+    Self {
+      file_path: file_path.to_string(),
+      old_tags: ApiAudioTags::from_audio_tags(old_tags.clone()),
+      new_tags: ApiAudioTags::from_audio_tags(new_tags.clone()),
+    }
+  }
 }
 
-#[napi]
-pub async fn clear_tags(file_path: String) -> Result<()> {
-  util::clear_tags(file_path)
-    .await
-    .map_err(napi::Error::from_reason)
+type BeforeWriteCallback = ThreadsafeFunction<
+  ApiWriteHookEvent,
+  Either<Promise<Option<Either<bool, ApiAudioTags>>>, Option<Either<bool, ApiAudioTags>>>,
+  ApiWriteHookEvent,
+  Status,
+  false,
+>;
+
+// beforeWrite returns false to veto the write, replacement tags to adjust it,
+// or nothing to let it through unchanged
+#[napi(js_name = "WriteHooks", object, object_to_js = false)]
+pub struct ApiWriteHooks {
+  #[napi(
+    ts_type = "(event: WriteHookEvent) => boolean | AudioTags | void | Promise<boolean | AudioTags | void>"
+  )]
+  pub before_write: Option<BeforeWriteCallback>,
+  #[napi(ts_type = "(event: WriteHookEvent) => void")]
+  pub after_write: Option<
+    ThreadsafeFunction<ApiWriteHookEvent, UnknownReturnValue, ApiWriteHookEvent, Status, false>,
+  >,
 }
 
-#[napi]
-pub async fn clear_tags_to_buffer(buffer: Buffer) -> Result<Buffer> {
-  let result = util::clear_tags_to_buffer(buffer.to_vec())
-    .await
-    .map_err(napi::Error::from_reason)?;
-  Ok(Buffer::from(result))
+impl WriteHooks for ApiWriteHooks {
+  fn before_write(
+    &self,
+    file_path: &str,
+    old_tags: &AudioTags,
+    new_tags: AudioTags,
+  ) -> impl Future<Output = std::result::Result<HookDecision, String>> + Send {
+    let event = ApiWriteHookEvent::new(file_path, old_tags, &new_tags);
+    async move {
+      let Some(before_write) = self.before_write.as_ref() else {
+        return Ok(HookDecision::Proceed(Box::new(new_tags)));
+      };
+      let result = match before_write
+        .call_async_catch(event)
+        .await
+        .map_err(|e| e.reason)?
+      {
+        Either::A(promise) => promise.await.map_err(|e| e.reason)?,
+        Either::B(value) => value,
+      };
+      Ok(match result {
+        Some(Either::A(false)) => HookDecision::Veto,
+        Some(Either::B(tags)) => HookDecision::Proceed(Box::new(tags.into_audio_tags())),
+        _ => HookDecision::Proceed(Box::new(new_tags)),
+      })
+    }
+  }
+
+  fn after_write(
+    &self,
+    file_path: &str,
+    old_tags: &AudioTags,
+    new_tags: &AudioTags,
+  ) -> impl Future<Output = std::result::Result<(), String>> + Send {
+    let event = ApiWriteHookEvent::new(file_path, old_tags, new_tags);
+    async move {
+      if let Some(after_write) = self.after_write.as_ref() {
+        after_write
+          .call_async_catch(event)
+          .await
+          .map_err(|e| e.reason)?;
+      }
+      Ok(())
+    }
+  }
+
+  fn is_active(&self) -> bool {
+    self.before_write.is_some() || self.after_write.is_some()
+  }
 }
 
-#[napi]
-pub async fn read_cover_image_from_buffer(buffer: Buffer) -> Result<Option<Buffer>> {
-  let result = util::read_cover_image_from_buffer(buffer.to_vec())
-    .await
-    .map_err(napi::Error::from_reason)?;
-  Ok(result.map(Buffer::from))
+#[napi(js_name = "ReadOptions", object)]
+pub struct ApiReadOptions {
+  pub tolerate_truncation: Option<bool>,
+  pub preserve_picture_order: Option<bool>,
+  pub artist_separator: Option<String>,
+  pub multi_value_mode: Option<bool>,
+  #[napi(js_name = "id3v1Fallback")]
+  pub id3v1_fallback: Option<bool>,
+  pub skip_images: Option<bool>,
+  pub report_warnings: Option<bool>,
+  pub parsing_mode: Option<ApiParsingMode>,
+  pub max_allocation_bytes: Option<u32>,
+  pub skip_properties: Option<bool>,
+  pub legacy_encoding: Option<String>,
+  pub gapless_info: Option<bool>,
 }
 
-#[napi]
-pub async fn write_cover_image_to_buffer(buffer: Buffer, image_data: Buffer) -> Result<Buffer> {
-  let result = util::write_cover_image_to_buffer(buffer.to_vec(), image_data.to_vec())
-    .await
-    .map_err(napi::Error::from_reason)?;
-  Ok(Buffer::from(result))
+impl ApiReadOptions {
+  pub fn into_read_tags_options(self) -> ReadTagsOptions {
+    ReadTagsOptions {
+      tolerate_truncation: self.tolerate_truncation.unwrap_or(false),
+      preserve_picture_order: self.preserve_picture_order.unwrap_or(false),
+      artist_separator: artist_separator(self.artist_separator, self.multi_value_mode),
+      id3v1_fallback: self.id3v1_fallback.unwrap_or(false),
+      skip_images: self.skip_images.unwrap_or(false),
+      report_warnings: self.report_warnings.unwrap_or(false),
+      parsing_mode: self
+        .parsing_mode
+        .map(ApiParsingMode::into_parsing_mode)
+        .unwrap_or_default(),
+      max_allocation_bytes: self.max_allocation_bytes.map(|bytes| bytes as usize),
+      skip_properties: self.skip_properties.unwrap_or(false),
+      legacy_encoding: self.legacy_encoding,
+      gapless_info: self.gapless_info.unwrap_or(false),
+    }
+  }
 }
 
-#[napi]
-pub async fn read_cover_image_from_file(file_path: String) -> Result<Option<Buffer>> {
-  let result = util::read_cover_image_from_file(file_path)
-    .await
-    .map_err(napi::Error::from_reason)?;
-  Ok(result.map(Buffer::from))
+#[napi(js_name = "GaplessSource", string_enum)]
+pub enum ApiGaplessSource {
+  LameHeader,
+  #[napi(value = "iTunSMPB")]
+  ITunSmpb,
 }
 
-#[napi]
-pub async fn write_cover_image_to_file(file_path: String, image_data: Buffer) -> Result<()> {
-  util::write_cover_image_to_file(file_path, image_data.to_vec())
-    .await
-    .map_err(napi::Error::from_reason)
+#[napi(js_name = "GaplessInfo", object)]
+pub struct ApiGaplessInfo {
+  pub source: ApiGaplessSource,
+  pub encoder_delay: u32,
+  pub encoder_padding: u32,
+  pub original_samples: Option<i64>,
+}
+
+impl ApiGaplessInfo {
+  pub fn from_gapless_info(info: GaplessInfo) -> Self {
+    Self {
+      source: match info.source {
+        GaplessSource::LameHeader => ApiGaplessSource::LameHeader,
+        GaplessSource::ITunSmpb => ApiGaplessSource::ITunSmpb,
+      },
+      encoder_delay: info.encoder_delay,
+      encoder_padding: info.encoder_padding,
+      original_samples: info.original_samples.map(|samples| samples as i64),
+    }
+  }
+}
+
+#[napi(js_name = "ParsingMode", string_enum)]
+pub enum ApiParsingMode {
+  Strict,
+  BestAttempt,
+  Relaxed,
+}
+
+impl ApiParsingMode {
+  pub fn into_parsing_mode(self) -> ParsingMode {
+    match self {
+      Self::Strict => ParsingMode::Strict,
+      Self::BestAttempt => ParsingMode::BestAttempt,
+      Self::Relaxed => ParsingMode::Relaxed,
+    }
+  }
+}
+
+// the multi-value mode takes precedence over a separator
+fn artist_separator(separator: Option<String>, multi_value: Option<bool>) -> ArtistSeparator {
+  match (separator, multi_value) {
+    (_, Some(true)) => ArtistSeparator::MultiValue,
+    (Some(separator), _) => ArtistSeparator::Text(separator),
+    (None, _) => ArtistSeparator::default(),
+  }
+}
+
+// true backs up next to the file with ".bak"; a string is a suffix when it
+// starts with a dot and names no folder, a directory otherwise
+fn backup(backup: Option<Either<bool, String>>) -> Option<Backup> {
+  match backup? {
+    Either::A(true) => Some(Backup::Suffix(backup::DEFAULT_SUFFIX.to_string())),
+    Either::A(false) => None,
+    Either::B(suffix) if suffix.starts_with('.') && !suffix.contains(['/', '\\']) => {
+      Some(Backup::Suffix(suffix))
+    }
+    Either::B(dir) => Some(Backup::Dir(dir)),
+  }
+}
+
+#[napi(js_name = "MimeInference", string_enum)]
+pub enum ApiMimeInference {
+  Sniff,
+  TrustDeclared,
+  Off,
+}
+
+impl ApiMimeInference {
+  pub fn into_mime_inference(self) -> MimeInference {
+    match self {
+      Self::Sniff => MimeInference::Sniff,
+      Self::TrustDeclared => MimeInference::TrustDeclared,
+      Self::Off => MimeInference::Off,
+    }
+  }
+}
+
+#[napi(js_name = "TagType", string_enum)]
+pub enum ApiTagType {
+  Ape,
+  Id3v1,
+  Id3v2,
+  Mp4Ilst,
+  VorbisComments,
+  RiffInfo,
+  AiffText,
+}
+
+impl ApiTagType {
+  pub fn into_tag_type(self) -> TagType {
+    match self {
+      Self::Ape => TagType::Ape,
+      Self::Id3v1 => TagType::Id3v1,
+      Self::Id3v2 => TagType::Id3v2,
+      Self::Mp4Ilst => TagType::Mp4Ilst,
+      Self::VorbisComments => TagType::VorbisComments,
+      Self::RiffInfo => TagType::RiffInfo,
+      Self::AiffText => TagType::AiffText,
+    }
+  }
+
+  pub fn from_tag_type(tag_type: TagType) -> Option<Self> {
+    match tag_type {
+      TagType::Ape => Some(Self::Ape),
+      TagType::Id3v1 => Some(Self::Id3v1),
+      TagType::Id3v2 => Some(Self::Id3v2),
+      TagType::Mp4Ilst => Some(Self::Mp4Ilst),
+      TagType::VorbisComments => Some(Self::VorbisComments),
+      TagType::RiffInfo => Some(Self::RiffInfo),
+      TagType::AiffText => Some(Self::AiffText),
+      _ => None,
+    }
+  }
+}
+
+#[napi(js_name = "Id3v2Version", string_enum)]
+pub enum ApiId3v2Version {
+  #[napi(value = "2.3")]
+  V2_3,
+  #[napi(value = "2.4")]
+  V2_4,
+}
+
+#[napi(js_name = "Id3TextEncoding", string_enum)]
+pub enum ApiId3TextEncoding {
+  Utf8,
+  Utf16,
+  Latin1,
+}
+
+#[napi(js_name = "Mp4CoverFormat", string_enum)]
+pub enum ApiMp4CoverFormat {
+  Keep,
+  Jpeg,
+  Png,
+}
+
+#[napi(js_name = "FlacPictureStorage", string_enum)]
+pub enum ApiFlacPictureStorage {
+  PictureBlock,
+  VorbisComment,
+}
+
+#[napi(js_name = "Id3PictureEncoding", string_enum)]
+pub enum ApiId3PictureEncoding {
+  Utf8,
+  Utf16,
+  Latin1,
+}
+
+#[napi(js_name = "ArtworkPolicy", object)]
+pub struct ApiArtworkPolicy {
+  pub mp4_cover_format: Option<ApiMp4CoverFormat>,
+  pub flac_picture_storage: Option<ApiFlacPictureStorage>,
+  pub id3_picture_encoding: Option<ApiId3PictureEncoding>,
+}
+
+impl ApiArtworkPolicy {
+  pub fn into_artwork_policy(self) -> ArtworkPolicy {
+    ArtworkPolicy {
+      mp4_cover_format: match self.mp4_cover_format {
+        Some(ApiMp4CoverFormat::Jpeg) => Mp4CoverFormat::Jpeg,
+        Some(ApiMp4CoverFormat::Png) => Mp4CoverFormat::Png,
+        Some(ApiMp4CoverFormat::Keep) | None => Mp4CoverFormat::Keep,
+      },
+      flac_picture_storage: match self.flac_picture_storage {
+        Some(ApiFlacPictureStorage::VorbisComment) => FlacPictureStorage::VorbisComment,
+        Some(ApiFlacPictureStorage::PictureBlock) | None => FlacPictureStorage::PictureBlock,
+      },
+      id3_picture_encoding: match self.id3_picture_encoding {
+        Some(ApiId3PictureEncoding::Utf16) => Id3PictureEncoding::Utf16,
+        Some(ApiId3PictureEncoding::Latin1) => Id3PictureEncoding::Latin1,
+        Some(ApiId3PictureEncoding::Utf8) | None => Id3PictureEncoding::Utf8,
+      },
+    }
+  }
+}
+
+#[napi(js_name = "IdentifierValidation", string_enum)]
+pub enum ApiIdentifierValidation {
+  Reject,
+  Warn,
+  PassThrough,
+}
+
+impl ApiIdentifierValidation {
+  pub fn into_identifier_validation(self) -> IdentifierValidation {
+    match self {
+      Self::Reject => IdentifierValidation::Reject,
+      Self::Warn => IdentifierValidation::Warn,
+      Self::PassThrough => IdentifierValidation::PassThrough,
+    }
+  }
+}
+
+#[napi(js_name = "PopmScale", string_enum)]
+pub enum ApiPopmScale {
+  WindowsMedia,
+  MediaMonkey,
+}
+
+impl ApiPopmScale {
+  pub fn into_popm_scale(self) -> PopmScale {
+    match self {
+      Self::WindowsMedia => PopmScale::WindowsMedia,
+      Self::MediaMonkey => PopmScale::MediaMonkey,
+    }
+  }
+}
+
+#[napi(js_name = "WriteOptions", object)]
+pub struct ApiWriteOptions {
+  pub journal: Option<bool>,
+  pub journal_dir: Option<String>,
+  pub mime_inference: Option<ApiMimeInference>,
+  pub preserve_picture_order: Option<bool>,
+  pub deterministic: Option<bool>,
+  pub skip_unchanged: Option<bool>,
+  pub artwork_policy: Option<ApiArtworkPolicy>,
+  pub identifier_validation: Option<ApiIdentifierValidation>,
+  pub preserve_unknown: Option<bool>,
+  pub tag_type: Option<ApiTagType>,
+  #[napi(js_name = "id3v2Version")]
+  pub id3v2_version: Option<ApiId3v2Version>,
+  pub id3_text_encoding: Option<ApiId3TextEncoding>,
+  pub padding: Option<u32>,
+  pub remove_others: Option<bool>,
+  #[napi(js_name = "lowercaseId3v2Chunk")]
+  pub lowercase_id3v2_chunk: Option<bool>,
+  pub artist_separator: Option<String>,
+  pub multi_value_mode: Option<bool>,
+  pub popm_scale: Option<ApiPopmScale>,
+  pub atomic: Option<bool>,
+  pub preserve_mtime: Option<bool>,
+  pub backup: Option<Either<bool, String>>,
+  pub image_limits: Option<ApiImageLimits>,
+  pub id3v1: Option<bool>,
+  pub preserve_binary_objects: Option<bool>,
+  pub normalize_text: Option<bool>,
+}
+
+impl ApiWriteOptions {
+  pub fn into_write_tags_options(self) -> WriteTagsOptions {
+    WriteTagsOptions {
+      journal: self.journal.unwrap_or(false) || self.journal_dir.is_some(),
+      journal_dir: self.journal_dir,
+      mime_inference: self
+        .mime_inference
+        .map(ApiMimeInference::into_mime_inference)
+        .unwrap_or_default(),
+      preserve_picture_order: self.preserve_picture_order.unwrap_or(false),
+      deterministic: self.deterministic.unwrap_or(false),
+      skip_unchanged: self.skip_unchanged.unwrap_or(false),
+      artwork_policy: self
+        .artwork_policy
+        .map(ApiArtworkPolicy::into_artwork_policy)
+        .unwrap_or_default(),
+      identifier_validation: self
+        .identifier_validation
+        .map(ApiIdentifierValidation::into_identifier_validation)
+        .unwrap_or_default(),
+      preserve_unknown: self.preserve_unknown.unwrap_or(false),
+      tag_type: self.tag_type.map(ApiTagType::into_tag_type),
+      id3v2_version: match self.id3v2_version {
+        Some(ApiId3v2Version::V2_3) => Id3v2Version::V3,
+        Some(ApiId3v2Version::V2_4) | None => Id3v2Version::V4,
+      },
+      id3_text_encoding: match self.id3_text_encoding {
+        Some(ApiId3TextEncoding::Utf16) => Id3TextEncoding::Utf16,
+        Some(ApiId3TextEncoding::Latin1) => Id3TextEncoding::Latin1,
+        Some(ApiId3TextEncoding::Utf8) | None => Id3TextEncoding::Utf8,
+      },
+      padding: self.padding,
+      remove_others: self.remove_others.unwrap_or(false),
+      lowercase_id3v2_chunk: self.lowercase_id3v2_chunk.unwrap_or(false),
+      artist_separator: artist_separator(self.artist_separator, self.multi_value_mode),
+      popm_scale: self
+        .popm_scale
+        .map(ApiPopmScale::into_popm_scale)
+        .unwrap_or_default(),
+      atomic: self.atomic.unwrap_or(false),
+      preserve_mtime: self.preserve_mtime.unwrap_or(false),
+      backup: backup(self.backup),
+      image_limits: self.image_limits.map(ApiImageLimits::into_image_limits),
+      id3v1: self.id3v1.unwrap_or(false),
+      preserve_binary_objects: self.preserve_binary_objects.unwrap_or(false),
+      normalize_text: self.normalize_text.unwrap_or(false),
+    }
+  }
+}
+
+#[napi(js_name = "ImageLimits", object)]
+pub struct ApiImageLimits {
+  pub max_bytes: Option<u32>,
+  pub max_width: Option<u32>,
+  pub max_height: Option<u32>,
+}
+
+impl ApiImageLimits {
+  pub fn into_image_limits(self) -> ImageLimits {
+    ImageLimits {
+      max_bytes: self.max_bytes.map(|max_bytes| max_bytes as usize),
+      max_width: self.max_width,
+      max_height: self.max_height,
+    }
+  }
+}
+
+#[napi(js_name = "ResizeFormat", string_enum)]
+pub enum ApiResizeFormat {
+  #[napi(value = "jpeg")]
+  Jpeg,
+  #[napi(value = "png")]
+  Png,
+}
+
+#[napi(js_name = "ResizeOptions", object)]
+pub struct ApiResizeOptions {
+  pub max_edge: u32,
+  pub format: Option<ApiResizeFormat>,
+  pub quality: Option<u32>,
+}
+
+impl ApiResizeOptions {
+  pub fn into_resize_options(self) -> ResizeOptions {
+    let default = ResizeOptions::default();
+    ResizeOptions {
+      max_edge: self.max_edge,
+      format: match self.format {
+        Some(ApiResizeFormat::Png) => ResizeFormat::Png,
+        Some(ApiResizeFormat::Jpeg) | None => ResizeFormat::Jpeg,
+      },
+      quality: self
+        .quality
+        .map(|quality| quality.clamp(1, 100) as u8)
+        .unwrap_or(default.quality),
+    }
+  }
+}
+
+#[napi(js_name = "SaveOptions", object)]
+pub struct ApiSaveOptions {
+  pub preserve_mtime: Option<bool>,
+}
+
+impl ApiSaveOptions {
+  pub fn into_save_options(self) -> SaveOptions {
+    SaveOptions {
+      preserve_mtime: self.preserve_mtime.unwrap_or(false),
+    }
+  }
+}
+
+#[napi(js_name = "TagChange", object)]
+pub struct ApiTagChange {
+  pub field: String,
+  pub old_value: Option<serde_json::Value>,
+  pub new_value: Option<serde_json::Value>,
+}
+
+impl ApiTagChange {
+  pub fn from_tag_change(change: TagChange) -> Self {
+    Self {
+      field: change.field,
+      old_value: change.old_value,
+      new_value: change.new_value,
+    }
+  }
+}
+
+#[napi(js_name = "WriteResult", object)]
+pub struct ApiWriteResult {
+  pub changed: bool,
+  pub warnings: Vec<String>,
+}
+
+impl ApiWriteResult {
+  pub fn from_write_tags_outcome(outcome: WriteTagsOutcome) -> Self {
+    Self {
+      changed: outcome.changed,
+      warnings: outcome.warnings,
+    }
+  }
+}
+
+#[napi(js_name = "CoverImageOptions", object)]
+pub struct ApiCoverImageOptions {
+  pub pic_type: Option<ApiAudioImageType>,
+  pub description: Option<String>,
+  pub mime_type: Option<String>,
+  pub backup: Option<Either<bool, String>>,
+  pub preserve_mtime: Option<bool>,
+  pub image_limits: Option<ApiImageLimits>,
+  pub resize: Option<ApiResizeOptions>,
+}
+
+impl ApiCoverImageOptions {
+  pub fn into_cover_image_options(self) -> CoverImageOptions {
+    CoverImageOptions {
+      pic_type: self
+        .pic_type
+        .map(ApiAudioImageType::into_audio_image_type)
+        .unwrap_or(AudioImageType::CoverFront),
+      description: self.description,
+      mime_type: self.mime_type,
+      backup: backup(self.backup),
+      preserve_mtime: self.preserve_mtime.unwrap_or(false),
+      image_limits: self.image_limits.map(ApiImageLimits::into_image_limits),
+      resize: self.resize.map(ApiResizeOptions::into_resize_options),
+    }
+  }
+}
+
+#[napi(js_name = "ClearOptions", object)]
+pub struct ApiClearOptions {
+  pub keep_images: Option<bool>,
+  pub keep_technical: Option<bool>,
+  pub tag_type: Option<ApiTagType>,
+  pub backup: Option<Either<bool, String>>,
+  pub preserve_mtime: Option<bool>,
+}
+
+impl ApiClearOptions {
+  pub fn into_clear_tags_options(self) -> ClearTagsOptions {
+    ClearTagsOptions {
+      keep_images: self.keep_images.unwrap_or(false),
+      keep_technical: self.keep_technical.unwrap_or(false),
+      tag_type: self.tag_type.map(ApiTagType::into_tag_type),
+      backup: backup(self.backup),
+      preserve_mtime: self.preserve_mtime.unwrap_or(false),
+    }
+  }
+}
+
+#[napi(js_name = "BatchFileResult", object)]
+pub struct ApiBatchFileResult {
+  pub file_path: String,
+  pub error: Option<String>,
+  pub hook_error: Option<String>,
+}
+
+impl ApiBatchFileResult {
+  pub fn from_batch_file_result(result: BatchFileResult) -> Self {
+    Self {
+      file_path: result.file_path,
+      error: result.error,
+      hook_error: result.hook_error,
+    }
+  }
+}
+
+#[napi(js_name = "BatchReadResult", object)]
+pub struct ApiBatchReadResult {
+  pub file_path: String,
+  pub tags: Option<ApiAudioTags>,
+  pub error: Option<String>,
+}
+
+impl ApiBatchReadResult {
+  pub fn from_batch_read_result(result: BatchReadResult) -> Self {
+    Self {
+      file_path: result.file_path,
+      tags: result.tags.map(ApiAudioTags::from_audio_tags),
+      error: result.error,
+    }
+  }
+}
+
+#[napi(js_name = "ProbeOptions", object)]
+pub struct ApiProbeOptions {
+  pub max_bytes: Option<u32>,
+}
+
+#[napi(js_name = "BufferProbe", object)]
+pub struct ApiBufferProbe {
+  pub file_type: Option<String>,
+  pub tag_types: Vec<String>,
+  pub id3v2_size: Option<u32>,
+}
+
+impl ApiBufferProbe {
+  pub fn from_buffer_probe(probe: BufferProbe) -> Self {
+    Self {
+      file_type: probe.file_type,
+      tag_types: probe.tag_types,
+      id3v2_size: probe.id3v2_size,
+    }
+  }
+}
+
+#[napi(js_name = "FileProbe", object)]
+pub struct ApiFileProbe {
+  pub file_type: String,
+  pub primary_tag_type: String,
+  pub present_tag_types: Vec<String>,
+  pub has_artwork: bool,
+  // None when the file has tags inside a container whose size isn't measured
+  pub tag_size_bytes: Option<i64>,
+}
+
+impl ApiFileProbe {
+  pub fn from_file_probe(probe: FileProbe) -> Self {
+    Self {
+      file_type: probe.file_type,
+      primary_tag_type: probe.primary_tag_type,
+      present_tag_types: probe.present_tag_types,
+      has_artwork: probe.has_artwork,
+      tag_size_bytes: probe.tag_size_bytes.map(|size| size as i64),
+    }
+  }
+}
+
+#[napi(js_name = "FormatSupport", object)]
+pub struct ApiFormatSupport {
+  pub file_type: String,
+  pub extensions: Vec<String>,
+  pub mime_type: String,
+  pub primary_tag_type: String,
+  pub tag_types: Vec<String>,
+  pub can_read: bool,
+  pub can_write: bool,
+}
+
+impl ApiFormatSupport {
+  pub fn from_format_support(format: FormatSupport) -> Self {
+    Self {
+      file_type: format.file_type,
+      extensions: format.extensions,
+      mime_type: format.mime_type,
+      primary_tag_type: format.primary_tag_type,
+      tag_types: format.tag_types,
+      can_read: format.can_read,
+      can_write: format.can_write,
+    }
+  }
+}
+
+#[napi(js_name = "AlbumField", string_enum)]
+pub enum ApiAlbumField {
+  Album,
+  AlbumArtists,
+  Year,
+  Genre,
+  DiscTotal,
+  Cover,
+}
+
+impl ApiAlbumField {
+  pub fn into_album_field(self) -> AlbumField {
+    match self {
+      Self::Album => AlbumField::Album,
+      Self::AlbumArtists => AlbumField::AlbumArtists,
+      Self::Year => AlbumField::Year,
+      Self::Genre => AlbumField::Genre,
+      Self::DiscTotal => AlbumField::DiscTotal,
+      Self::Cover => AlbumField::Cover,
+    }
+  }
+}
+
+#[napi(js_name = "TagField", string_enum)]
+pub enum ApiTagField {
+  Title,
+  Artists,
+  Album,
+  Year,
+  Genre,
+  Track,
+  AlbumArtists,
+  Comment,
+  Disc,
+  Image,
+  AllImages,
+  Composer,
+  Lyricist,
+  Conductor,
+  Remixer,
+  Publisher,
+  Copyright,
+  Lyrics,
+  Isrc,
+  Barcode,
+  CatalogNumber,
+  MusicBrainzRecordingId,
+  MusicBrainzTrackId,
+  MusicBrainzReleaseId,
+  MusicBrainzReleaseGroupId,
+  MusicBrainzArtistId,
+  MusicBrainzAlbumArtistId,
+  ReplayGain,
+  Bpm,
+  InitialKey,
+  Mood,
+  Rating,
+  PlayCount,
+  Compilation,
+  Grouping,
+  Work,
+  Movement,
+  MovementNumber,
+  Subtitle,
+  DiscSubtitle,
+  TitleSort,
+  AlbumSort,
+  ArtistSort,
+  AlbumArtistSort,
+  ComposerSort,
+  EncoderSoftware,
+  EncoderSettings,
+}
+
+impl ApiTagField {
+  pub fn into_tag_field(self) -> TagField {
+    match self {
+      Self::Title => TagField::Title,
+      Self::Artists => TagField::Artists,
+      Self::Album => TagField::Album,
+      Self::Year => TagField::Year,
+      Self::Genre => TagField::Genre,
+      Self::Track => TagField::Track,
+      Self::AlbumArtists => TagField::AlbumArtists,
+      Self::Comment => TagField::Comment,
+      Self::Disc => TagField::Disc,
+      Self::Image => TagField::Image,
+      Self::AllImages => TagField::AllImages,
+      Self::Composer => TagField::Composer,
+      Self::Lyricist => TagField::Lyricist,
+      Self::Conductor => TagField::Conductor,
+      Self::Remixer => TagField::Remixer,
+      Self::Publisher => TagField::Publisher,
+      Self::Copyright => TagField::Copyright,
+      Self::Lyrics => TagField::Lyrics,
+      Self::Isrc => TagField::Isrc,
+      Self::Barcode => TagField::Barcode,
+      Self::CatalogNumber => TagField::CatalogNumber,
+      Self::MusicBrainzRecordingId => TagField::MusicBrainzRecordingId,
+      Self::MusicBrainzTrackId => TagField::MusicBrainzTrackId,
+      Self::MusicBrainzReleaseId => TagField::MusicBrainzReleaseId,
+      Self::MusicBrainzReleaseGroupId => TagField::MusicBrainzReleaseGroupId,
+      Self::MusicBrainzArtistId => TagField::MusicBrainzArtistId,
+      Self::MusicBrainzAlbumArtistId => TagField::MusicBrainzAlbumArtistId,
+      Self::ReplayGain => TagField::ReplayGain,
+      Self::Bpm => TagField::Bpm,
+      Self::InitialKey => TagField::InitialKey,
+      Self::Mood => TagField::Mood,
+      Self::Rating => TagField::Rating,
+      Self::PlayCount => TagField::PlayCount,
+      Self::Compilation => TagField::Compilation,
+      Self::Grouping => TagField::Grouping,
+      Self::Work => TagField::Work,
+      Self::Movement => TagField::Movement,
+      Self::MovementNumber => TagField::MovementNumber,
+      Self::Subtitle => TagField::Subtitle,
+      Self::DiscSubtitle => TagField::DiscSubtitle,
+      Self::TitleSort => TagField::TitleSort,
+      Self::AlbumSort => TagField::AlbumSort,
+      Self::ArtistSort => TagField::ArtistSort,
+      Self::AlbumArtistSort => TagField::AlbumArtistSort,
+      Self::ComposerSort => TagField::ComposerSort,
+      Self::EncoderSoftware => TagField::EncoderSoftware,
+      Self::EncoderSettings => TagField::EncoderSettings,
+    }
+  }
+
+  pub fn from_tag_field(field: TagField) -> Self {
+    match field {
+      TagField::Title => Self::Title,
+      TagField::Artists => Self::Artists,
+      TagField::Album => Self::Album,
+      TagField::Year => Self::Year,
+      TagField::Genre => Self::Genre,
+      TagField::Track => Self::Track,
+      TagField::AlbumArtists => Self::AlbumArtists,
+      TagField::Comment => Self::Comment,
+      TagField::Disc => Self::Disc,
+      TagField::Image => Self::Image,
+      TagField::AllImages => Self::AllImages,
+      TagField::Composer => Self::Composer,
+      TagField::Lyricist => Self::Lyricist,
+      TagField::Conductor => Self::Conductor,
+      TagField::Remixer => Self::Remixer,
+      TagField::Publisher => Self::Publisher,
+      TagField::Copyright => Self::Copyright,
+      TagField::Lyrics => Self::Lyrics,
+      TagField::Isrc => Self::Isrc,
+      TagField::Barcode => Self::Barcode,
+      TagField::CatalogNumber => Self::CatalogNumber,
+      TagField::MusicBrainzRecordingId => Self::MusicBrainzRecordingId,
+      TagField::MusicBrainzTrackId => Self::MusicBrainzTrackId,
+      TagField::MusicBrainzReleaseId => Self::MusicBrainzReleaseId,
+      TagField::MusicBrainzReleaseGroupId => Self::MusicBrainzReleaseGroupId,
+      TagField::MusicBrainzArtistId => Self::MusicBrainzArtistId,
+      TagField::MusicBrainzAlbumArtistId => Self::MusicBrainzAlbumArtistId,
+      TagField::ReplayGain => Self::ReplayGain,
+      TagField::Bpm => Self::Bpm,
+      TagField::InitialKey => Self::InitialKey,
+      TagField::Mood => Self::Mood,
+      TagField::Rating => Self::Rating,
+      TagField::PlayCount => Self::PlayCount,
+      TagField::Compilation => Self::Compilation,
+      TagField::Grouping => Self::Grouping,
+      TagField::Work => Self::Work,
+      TagField::Movement => Self::Movement,
+      TagField::MovementNumber => Self::MovementNumber,
+      TagField::Subtitle => Self::Subtitle,
+      TagField::DiscSubtitle => Self::DiscSubtitle,
+      TagField::TitleSort => Self::TitleSort,
+      TagField::AlbumSort => Self::AlbumSort,
+      TagField::ArtistSort => Self::ArtistSort,
+      TagField::AlbumArtistSort => Self::AlbumArtistSort,
+      TagField::ComposerSort => Self::ComposerSort,
+      TagField::EncoderSoftware => Self::EncoderSoftware,
+      TagField::EncoderSettings => Self::EncoderSettings,
+    }
+  }
+}
+
+#[napi(js_name = "TransformAction", string_enum)]
+pub enum ApiTransformAction {
+  TitleCase,
+  SentenceCase,
+  FixFeaturing,
+  Replace,
+}
+
+#[napi(js_name = "TransformRule", object)]
+pub struct ApiTransformRule {
+  pub action: ApiTransformAction,
+  pub fields: Option<Vec<ApiTagField>>,
+  pub pattern: Option<String>,
+  pub replacement: Option<String>,
+}
+
+impl ApiTransformRule {
+  pub fn into_transform_rule(self) -> std::result::Result<TransformRule, String> {
+    let action = match self.action {
+      ApiTransformAction::TitleCase => TransformAction::TitleCase,
+      ApiTransformAction::SentenceCase => TransformAction::SentenceCase,
+      ApiTransformAction::FixFeaturing => TransformAction::FixFeaturing,
+      ApiTransformAction::Replace => {
+        let pattern = self
+          .pattern
+          .ok_or_else(|| "A replace rule needs a pattern".to_string())?;
+        TransformAction::Replace {
+          pattern: Regex::new(&pattern)
+            .map_err(|e| format!("Invalid pattern {}: {}", pattern, e))?,
+          replacement: self.replacement.unwrap_or_default(),
+        }
+      }
+    };
+    let fields = match self.fields {
+      Some(fields) => fields
+        .into_iter()
+        .map(ApiTagField::into_tag_field)
+        .collect(),
+      None => DEFAULT_FIELDS.to_vec(),
+    };
+    Ok(TransformRule { action, fields })
+  }
+}
+
+fn transform_rules(rules: Vec<ApiTransformRule>) -> Result<Vec<TransformRule>> {
+  rules
+    .into_iter()
+    .map(ApiTransformRule::into_transform_rule)
+    .collect::<std::result::Result<_, _>>()
+    .map_err(napi::Error::from_reason)
+}
+
+#[napi(js_name = "TagPatch", object)]
+pub struct ApiTagPatch {
+  pub set: Option<ApiAudioTags>,
+  pub clear: Option<Vec<ApiTagField>>,
+}
+
+impl ApiTagPatch {
+  pub fn into_tag_patch(self) -> TagPatch {
+    TagPatch {
+      set: self
+        .set
+        .map(ApiAudioTags::into_audio_tags)
+        .unwrap_or_default(),
+      clear: self
+        .clear
+        .unwrap_or_default()
+        .into_iter()
+        .map(ApiTagField::into_tag_field)
+        .collect(),
+    }
+  }
+}
+
+#[napi(js_name = "ValidationRules", object)]
+pub struct ApiValidationRules {
+  pub required_fields: Option<Vec<ApiTagField>>,
+  pub min_year: Option<u32>,
+  pub max_year: Option<u32>,
+  // 0 for no limit
+  pub max_image_bytes: Option<u32>,
+  pub check_encodings: Option<bool>,
+  #[napi(js_name = "checkId3v1")]
+  pub check_id3v1: Option<bool>,
+}
+
+impl ApiValidationRules {
+  pub fn into_validation_rules(self) -> ValidationRules {
+    let defaults = ValidationRules::default();
+    ValidationRules {
+      required_fields: self
+        .required_fields
+        .unwrap_or_default()
+        .into_iter()
+        .map(ApiTagField::into_tag_field)
+        .collect(),
+      min_year: self.min_year.unwrap_or(defaults.min_year),
+      max_year: self.max_year,
+      max_image_bytes: match self.max_image_bytes {
+        Some(0) => None,
+        Some(max) => Some(max as usize),
+        None => defaults.max_image_bytes,
+      },
+      check_encodings: self.check_encodings.unwrap_or(defaults.check_encodings),
+      check_id3v1: self.check_id3v1.unwrap_or(defaults.check_id3v1),
+    }
+  }
+}
+
+#[napi(js_name = "FindingKind", string_enum)]
+pub enum ApiFindingKind {
+  MissingField,
+  NumberExceedsTotal,
+  YearOutOfRange,
+  OversizedImage,
+  MixedEncodings,
+  Id3v1Mismatch,
+}
+
+impl ApiFindingKind {
+  pub fn from_finding_kind(kind: FindingKind) -> Self {
+    match kind {
+      FindingKind::MissingField => Self::MissingField,
+      FindingKind::NumberExceedsTotal => Self::NumberExceedsTotal,
+      FindingKind::YearOutOfRange => Self::YearOutOfRange,
+      FindingKind::OversizedImage => Self::OversizedImage,
+      FindingKind::MixedEncodings => Self::MixedEncodings,
+      FindingKind::Id3v1Mismatch => Self::Id3v1Mismatch,
+    }
+  }
+}
+
+#[napi(js_name = "ValidationFinding", object)]
+pub struct ApiValidationFinding {
+  pub kind: ApiFindingKind,
+  pub field: Option<ApiTagField>,
+  pub message: String,
+}
+
+impl ApiValidationFinding {
+  pub fn from_validation_finding(finding: ValidationFinding) -> Self {
+    Self {
+      kind: ApiFindingKind::from_finding_kind(finding.kind),
+      field: finding.field.map(ApiTagField::from_tag_field),
+      message: finding.message,
+    }
+  }
+}
+
+#[napi(js_name = "MergeRule", string_enum)]
+pub enum ApiMergeRule {
+  PreferIncoming,
+  PreferExisting,
+  FillEmptyOnly,
+}
+
+impl ApiMergeRule {
+  pub fn into_merge_rule(self) -> MergeRule {
+    match self {
+      Self::PreferIncoming => MergeRule::PreferIncoming,
+      Self::PreferExisting => MergeRule::PreferExisting,
+      Self::FillEmptyOnly => MergeRule::FillEmptyOnly,
+    }
+  }
+}
+
+#[napi(js_name = "FieldMergeRule", object)]
+pub struct ApiFieldMergeRule {
+  pub field: ApiTagField,
+  pub rule: ApiMergeRule,
+}
+
+#[napi(js_name = "MergeStrategy", object)]
+pub struct ApiMergeStrategy {
+  pub rule: Option<ApiMergeRule>,
+  pub overrides: Option<Vec<ApiFieldMergeRule>>,
+}
+
+impl ApiMergeStrategy {
+  pub fn into_merge_strategy(self) -> MergeStrategy {
+    MergeStrategy {
+      rule: self
+        .rule
+        .map(ApiMergeRule::into_merge_rule)
+        .unwrap_or_default(),
+      overrides: self
+        .overrides
+        .unwrap_or_default()
+        .into_iter()
+        .map(|entry| (entry.field.into_tag_field(), entry.rule.into_merge_rule()))
+        .collect(),
+    }
+  }
+}
+
+#[napi(js_name = "PropagateOptions", object)]
+pub struct ApiPropagateOptions {
+  pub reference: Option<String>,
+}
+
+#[napi(js_name = "CollisionPolicy", string_enum)]
+pub enum ApiCollisionPolicy {
+  AddSuffix,
+  Skip,
+  Fail,
+}
+
+#[napi(js_name = "RenameOptions", object)]
+pub struct ApiRenameOptions {
+  pub root: Option<String>,
+  pub dry_run: Option<bool>,
+  pub on_collision: Option<ApiCollisionPolicy>,
+}
+
+impl ApiRenameOptions {
+  pub fn into_rename_options(self) -> RenameOptions {
+    RenameOptions {
+      root: self.root,
+      dry_run: self.dry_run.unwrap_or(false),
+      on_collision: match self.on_collision {
+        Some(ApiCollisionPolicy::Skip) => CollisionPolicy::Skip,
+        Some(ApiCollisionPolicy::Fail) => CollisionPolicy::Fail,
+        Some(ApiCollisionPolicy::AddSuffix) | None => CollisionPolicy::AddSuffix,
+      },
+    }
+  }
+}
+
+#[napi(js_name = "InferOptions", object)]
+pub struct ApiInferOptions {
+  pub write: Option<bool>,
+}
+
+impl ApiInferOptions {
+  pub fn into_infer_options(self) -> InferOptions {
+    InferOptions {
+      write: self.write.unwrap_or(false),
+    }
+  }
+}
+
+#[napi(js_name = "RenameOutcome", object)]
+pub struct ApiRenameOutcome {
+  pub file_path: String,
+  pub new_path: String,
+  pub renamed: bool,
+}
+
+impl ApiRenameOutcome {
+  pub fn from_rename_outcome(outcome: RenameOutcome) -> Self {
+    Self {
+      file_path: outcome.file_path,
+      new_path: outcome.new_path,
+      renamed: outcome.renamed,
+    }
+  }
+}
+
+#[napi(js_name = "Writability", object)]
+pub struct ApiWritability {
+  pub writable: bool,
+  pub reason: Option<String>,
+  pub required_bytes: i64,
+  pub available_bytes: Option<i64>,
+}
+
+impl ApiWritability {
+  pub fn from_writability(writability: Writability) -> Self {
+    Self {
+      writable: writability.writable,
+      reason: writability.reason,
+      required_bytes: writability.required_bytes as i64,
+      available_bytes: writability.available_bytes.map(|bytes| bytes as i64),
+    }
+  }
+}
+
+#[napi(js_name = "AudioProperties", object)]
+pub struct ApiAudioProperties {
+  pub duration_ms: i64,
+  pub bitrate: Option<u32>,
+  pub sample_rate: Option<u32>,
+  pub bit_depth: Option<u32>,
+  pub channels: Option<u32>,
+}
+
+#[napi(js_name = "TagEntry", object)]
+pub struct ApiTagEntry {
+  pub tag_type: String,
+  pub tags: ApiAudioTags,
+}
+
+impl ApiTagEntry {
+  pub fn from_tag_entry(entry: TagEntry) -> Self {
+    Self {
+      tag_type: entry.tag_type,
+      tags: ApiAudioTags::from_audio_tags(entry.tags),
+    }
+  }
+}
+
+#[napi(js_name = "RawValueType", string_enum)]
+pub enum ApiRawValueType {
+  Text,
+  Locator,
+  Binary,
+}
+
+#[napi(js_name = "RawTagItem", object)]
+pub struct ApiRawTagItem {
+  pub tag_type: String,
+  pub key: String,
+  pub key_string: Option<String>,
+  pub value_type: ApiRawValueType,
+  // a string, or a Buffer for binary values
+  pub value: Either<String, Buffer>,
+}
+
+impl ApiRawTagItem {
+  pub fn from_raw_tag_item(item: RawTagItem) -> Self {
+    let (value_type, value) = match item.value {
+      ItemValue::Text(text) => (ApiRawValueType::Text, Either::A(text)),
+      ItemValue::Locator(locator) => (ApiRawValueType::Locator, Either::A(locator)),
+      ItemValue::Binary(data) => (ApiRawValueType::Binary, Either::B(data.into())),
+    };
+    Self {
+      tag_type: item.tag_type,
+      key: item.key,
+      key_string: item.key_string,
+      value_type,
+      value,
+    }
+  }
+
+  pub fn into_raw_tag_item(self) -> RawTagItem {
+    let value = match (self.value_type, self.value) {
+      (ApiRawValueType::Text, Either::A(text)) => ItemValue::Text(text),
+      (ApiRawValueType::Locator, Either::A(locator)) => ItemValue::Locator(locator),
+      (ApiRawValueType::Binary, Either::A(text)) => ItemValue::Binary(text.into_bytes()),
+      (_, Either::B(data)) => ItemValue::Binary(data.to_vec()),
+    };
+    RawTagItem {
+      tag_type: self.tag_type,
+      key: self.key,
+      key_string: self.key_string,
+      value,
+    }
+  }
+}
+
+impl ApiAudioProperties {
+  pub fn from_audio_properties(properties: AudioProperties) -> Self {
+    Self {
+      duration_ms: properties.duration_ms as i64,
+      bitrate: properties.bitrate,
+      sample_rate: properties.sample_rate,
+      bit_depth: properties.bit_depth.map(u32::from),
+      channels: properties.channels.map(u32::from),
+    }
+  }
+}
+
+#[napi(js_name = "LyricLine", object)]
+pub struct ApiLyricLine {
+  pub time_ms: u32,
+  pub text: String,
+}
+
+#[napi(js_name = "SyncedLyrics", object)]
+pub struct ApiSyncedLyrics {
+  pub language: Option<String>,
+  pub description: Option<String>,
+  pub lines: Vec<ApiLyricLine>,
+}
+
+impl ApiSyncedLyrics {
+  pub fn from_synced_lyrics(lyrics: SyncedLyrics) -> Self {
+    Self {
+      language: lyrics.language,
+      description: lyrics.description,
+      lines: lyrics
+        .lines
+        .into_iter()
+        .map(|line| ApiLyricLine {
+          time_ms: line.time_ms,
+          text: line.text,
+        })
+        .collect(),
+    }
+  }
+
+  pub fn into_synced_lyrics(self) -> SyncedLyrics {
+    SyncedLyrics {
+      language: self.language,
+      description: self.description,
+      lines: self
+        .lines
+        .into_iter()
+        .map(|line| LyricLine {
+          time_ms: line.time_ms,
+          text: line.text,
+        })
+        .collect(),
+    }
+  }
+}
+
+#[napi(js_name = "ArtworkCacheOptions", object)]
+pub struct ApiArtworkCacheOptions {
+  pub size: Option<u32>,
+}
+
+#[napi(js_name = "ArtworkCacheEntry", object)]
+pub struct ApiArtworkCacheEntry {
+  pub hash: Option<String>,
+  pub thumbnail_path: Option<String>,
+  pub error: Option<String>,
+}
+
+impl ApiArtworkCacheEntry {
+  pub fn from_artwork_cache_entry(entry: ArtworkCacheEntry) -> Self {
+    Self {
+      hash: entry.hash,
+      thumbnail_path: entry.thumbnail_path,
+      error: entry.error,
+    }
+  }
+}
+
+#[napi(js_name = "ImageInfo", object)]
+pub struct ApiImageInfo {
+  pub pic_type: ApiAudioImageType,
+  pub mime_type: Option<String>,
+  pub description: Option<String>,
+  pub size: u32,
+  pub width: Option<u32>,
+  pub height: Option<u32>,
+}
+
+impl ApiImageInfo {
+  pub fn from_image_info(info: ImageInfo) -> Self {
+    Self {
+      pic_type: ApiAudioImageType::from_audio_image_type(info.pic_type),
+      mime_type: info.mime_type,
+      description: info.description,
+      size: info.size as u32,
+      width: info.width,
+      height: info.height,
+    }
+  }
+}
+
+#[napi(js_name = "CoverBatchOptions", object)]
+pub struct ApiCoverBatchOptions {
+  pub max_bytes_per_image: Option<u32>,
+}
+
+#[napi(js_name = "ScanOptions", object)]
+pub struct ApiScanOptions {
+  pub recursive: Option<bool>,
+  pub extensions: Option<Vec<String>>,
+  pub follow_symlinks: Option<bool>,
+  pub concurrency: Option<u32>,
+}
+
+impl ApiScanOptions {
+  pub fn into_scan_options(self) -> ScanOptions {
+    ScanOptions {
+      recursive: self.recursive.unwrap_or(false),
+      extensions: self.extensions,
+      follow_symlinks: self.follow_symlinks.unwrap_or(false),
+      concurrency: self.concurrency.map(|n| n as usize),
+    }
+  }
+}
+
+#[napi(js_name = "ExportFormat", string_enum)]
+pub enum ApiExportFormat {
+  Json,
+  Csv,
+}
+
+impl ApiExportFormat {
+  pub fn into_export_format(self) -> ExportFormat {
+    match self {
+      Self::Json => ExportFormat::Json,
+      Self::Csv => ExportFormat::Csv,
+    }
+  }
+}
+
+#[napi(js_name = "MatchBy", string_enum)]
+pub enum ApiMatchBy {
+  Path,
+  Isrc,
+}
+
+#[napi(js_name = "ImportOptions", object)]
+pub struct ApiImportOptions {
+  pub dry_run: Option<bool>,
+  pub match_by: Option<ApiMatchBy>,
+  pub root: Option<String>,
+}
+
+impl ApiImportOptions {
+  pub fn into_import_options(self) -> ImportOptions {
+    ImportOptions {
+      dry_run: self.dry_run.unwrap_or(false),
+      match_by: match self.match_by {
+        Some(ApiMatchBy::Isrc) => MatchBy::Isrc,
+        Some(ApiMatchBy::Path) | None => MatchBy::Path,
+      },
+      root: self.root,
+    }
+  }
+}
+
+#[napi(js_name = "ImportResult", object)]
+pub struct ApiImportResult {
+  pub row: u32,
+  pub file_path: Option<String>,
+  pub changed: Vec<String>,
+  pub error: Option<String>,
+}
+
+impl ApiImportResult {
+  pub fn from_import_result(result: ImportResult) -> Self {
+    Self {
+      row: result.row as u32,
+      file_path: result.file_path,
+      changed: result.changed,
+      error: result.error,
+    }
+  }
+}
+
+#[napi(js_name = "ScanResult", object)]
+pub struct ApiScanResult {
+  pub file_path: String,
+  pub tags: Option<ApiAudioTags>,
+  pub properties: Option<ApiAudioProperties>,
+  pub error: Option<String>,
+}
+
+impl ApiScanResult {
+  pub fn from_scan_result(result: ScanResult) -> Self {
+    Self {
+      file_path: result.file_path,
+      tags: result.tags.map(ApiAudioTags::from_audio_tags),
+      properties: result
+        .properties
+        .map(ApiAudioProperties::from_audio_properties),
+      error: result.error,
+    }
+  }
+}
+
+#[napi(js_name = "WatchEventKind", string_enum)]
+pub enum ApiWatchEventKind {
+  Added,
+  Changed,
+  Removed,
+}
+
+impl ApiWatchEventKind {
+  pub fn from_watch_event_kind(kind: WatchEventKind) -> Self {
+    match kind {
+      WatchEventKind::Added => Self::Added,
+      WatchEventKind::Changed => Self::Changed,
+      WatchEventKind::Removed => Self::Removed,
+    }
+  }
+}
+
+#[napi(js_name = "WatchEvent", object)]
+pub struct ApiWatchEvent {
+  pub file_path: String,
+  pub kind: ApiWatchEventKind,
+  pub tags: Option<ApiAudioTags>,
+  pub error: Option<String>,
+}
+
+impl ApiWatchEvent {
+  pub fn from_watch_event(event: WatchEvent) -> Self {
+    Self {
+      file_path: event.file_path,
+      kind: ApiWatchEventKind::from_watch_event_kind(event.kind),
+      tags: event.tags.map(ApiAudioTags::from_audio_tags),
+      error: event.error,
+    }
+  }
+}
+
+#[napi(js_name = "WatchOptions", object)]
+pub struct ApiWatchOptions {
+  pub recursive: Option<bool>,
+  pub extensions: Option<Vec<String>>,
+  pub debounce_ms: Option<u32>,
+}
+
+impl ApiWatchOptions {
+  pub fn into_watch_options(self) -> WatchOptions {
+    let defaults = WatchOptions::default();
+    WatchOptions {
+      recursive: self.recursive.unwrap_or(defaults.recursive),
+      extensions: self.extensions,
+      debounce_ms: self
+        .debounce_ms
+        .map(u64::from)
+        .unwrap_or(defaults.debounce_ms),
+    }
+  }
+}
+
+// A path given as a string, which is read as UTF-16 on Windows so that file
+// names that aren't valid Unicode survive the trip
+#[cfg(windows)]
+fn path_from_string(path: Utf16String) -> PathBuf {
+  use std::os::windows::ffi::OsStringExt;
+  std::ffi::OsString::from_wide(&path).into()
+}
+
+#[cfg(not(windows))]
+fn path_from_string(path: Utf16String) -> PathBuf {
+  String::from_utf16_lossy(&path).into()
+}
+
+// A path given as a Buffer, as Node's fs accepts: the bytes of the path, which
+// on Unix needn't be UTF-8
+#[cfg(unix)]
+fn path_from_buffer(path: Buffer) -> Result<PathBuf> {
+  use std::os::unix::ffi::OsStrExt;
+  Ok(std::ffi::OsStr::from_bytes(&path).into())
+}
+
+#[cfg(not(unix))]
+fn path_from_buffer(path: Buffer) -> Result<PathBuf> {
+  String::from_utf8(path.to_vec())
+    .map(PathBuf::from)
+    .map_err(|_| napi::Error::from_reason("Path is not valid UTF-8"))
+}
+
+// A file to read or write: its path, as a string or a Buffer, or a file
+// descriptor the caller holds, given as a number or as a Node FileHandle
+pub enum ApiFileSource {
+  Path(PathBuf),
+  Fd(i32),
+}
+
+impl FromNapiValue for ApiFileSource {
+  unsafe fn from_napi_value(
+    env: napi::sys::napi_env,
+    value: napi::sys::napi_value,
+  ) -> Result<Self> {
+    Ok(
+      match Either4::<Utf16String, Buffer, i32, Object>::from_napi_value(env, value)? {
+        Either4::A(path) => Self::Path(path_from_string(path)),
+        Either4::B(path) => Self::Path(path_from_buffer(path)?),
+        Either4::C(fd) => Self::Fd(fd),
+        Either4::D(handle) => Self::Fd(handle.get_named_property::<i32>("fd")?),
+      },
+    )
+  }
+}
+
+// A path given as a string or a Buffer
+pub struct ApiPath(PathBuf);
+
+impl FromNapiValue for ApiPath {
+  unsafe fn from_napi_value(
+    env: napi::sys::napi_env,
+    value: napi::sys::napi_value,
+  ) -> Result<Self> {
+    match Either::<Utf16String, Buffer>::from_napi_value(env, value)? {
+      Either::A(path) => Ok(Self(path_from_string(path))),
+      Either::B(path) => path_from_buffer(path).map(Self),
+    }
+  }
+}
+
+impl AsRef<Path> for ApiPath {
+  fn as_ref(&self) -> &Path {
+    &self.0
+  }
+}
+
+fn into_paths(paths: Vec<ApiPath>) -> Vec<PathBuf> {
+  paths.into_iter().map(|path| path.0).collect()
+}
+
+#[napi]
+pub async fn read_tags(
+  #[napi(ts_arg_type = "string | Buffer | number | import('fs/promises').FileHandle")]
+  file: ApiFileSource,
+  options: Option<ApiReadOptions>,
+) -> Result<ApiAudioTags> {
+  match (file, options) {
+    (ApiFileSource::Path(file_path), Some(options)) => {
+      util::read_tags_with_options(file_path, options.into_read_tags_options())
+        .await
+        .map(ApiAudioTags::from_read_tags_outcome)
+    }
+    (ApiFileSource::Path(file_path), None) => util::read_tags(file_path)
+      .await
+      .map(ApiAudioTags::from_audio_tags),
+    (ApiFileSource::Fd(fd), options) => {
+      let options = options
+        .map(ApiReadOptions::into_read_tags_options)
+        .unwrap_or_default();
+      util::read_tags_from_fd(fd, options)
+        .await
+        .map(ApiAudioTags::from_read_tags_outcome)
+    }
+  }
+  .map_err(napi::Error::from_reason)
+}
+
+#[napi]
+pub async fn read_tags_from_buffer(
+  buffer: napi::bindgen_prelude::Buffer,
+  options: Option<ApiReadOptions>,
+) -> Result<ApiAudioTags> {
+  // the read runs off the JavaScript thread, on a pooled copy of the buffer
+  let mut copy = BUFFER_POOL.take(buffer.len());
+  copy.extend_from_slice(&buffer);
+  match options {
+    Some(options) => {
+      util::read_tags_from_buffer_with_options(copy, options.into_read_tags_options())
+        .await
+        .map(ApiAudioTags::from_read_tags_outcome)
+    }
+    None => util::read_tags_from_buffer(copy)
+      .await
+      .map(ApiAudioTags::from_audio_tags),
+  }
+  .map_err(napi::Error::from_reason)
+}
+
+type PullCallback =
+  ThreadsafeFunction<u32, Either<Promise<Option<Buffer>>, Option<Buffer>>, u32, Status, false>;
+
+// A stream read through a JS callback resolving to its next bytes
+struct ApiByteSource(PullCallback);
+
+impl ByteSource for ApiByteSource {
+  async fn pull(&self, size: usize) -> std::result::Result<Option<Vec<u8>>, String> {
+    let size = u32::try_from(size).unwrap_or(u32::MAX);
+    let chunk = match self.0.call_async_catch(size).await.map_err(|e| e.reason)? {
+      Either::A(promise) => promise.await.map_err(|e| e.reason)?,
+      Either::B(chunk) => chunk,
+    };
+    Ok(chunk.map(|chunk| chunk.to_vec()))
+  }
+}
+
+// there is no sync variant, it would block the thread the callback runs on
+#[napi(
+  ts_args_type = "pull: (size: number) => Buffer | null | Promise<Buffer | null>, options?: ReadOptions"
+)]
+pub async fn read_tags_from_stream(
+  pull: PullCallback,
+  options: Option<ApiReadOptions>,
+) -> Result<ApiAudioTags> {
+  let options = options
+    .map(ApiReadOptions::into_read_tags_options)
+    .unwrap_or_default();
+  util::read_tags_from_stream(&ApiByteSource(pull), options)
+    .await
+    .map(ApiAudioTags::from_audio_tags)
+    .map_err(napi::Error::from_reason)
+}
+
+#[napi]
+pub async fn read_all_tags(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+) -> Result<Vec<ApiTagEntry>> {
+  util::read_all_tags(file_path)
+    .await
+    .map(|entries| {
+      entries
+        .into_iter()
+        .map(ApiTagEntry::from_tag_entry)
+        .collect()
+    })
+    .map_err(napi::Error::from_reason)
+}
+
+#[napi]
+pub async fn read_all_tags_from_buffer(
+  buffer: napi::bindgen_prelude::Buffer,
+) -> Result<Vec<ApiTagEntry>> {
+  util::read_all_tags_from_buffer(buffer.to_vec())
+    .await
+    .map(|entries| {
+      entries
+        .into_iter()
+        .map(ApiTagEntry::from_tag_entry)
+        .collect()
+    })
+    .map_err(napi::Error::from_reason)
+}
+
+#[napi]
+pub async fn read_raw_tag_items(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+) -> Result<Vec<ApiRawTagItem>> {
+  util::read_raw_tag_items(file_path)
+    .await
+    .map(|items| {
+      items
+        .into_iter()
+        .map(ApiRawTagItem::from_raw_tag_item)
+        .collect()
+    })
+    .map_err(napi::Error::from_reason)
+}
+
+#[napi]
+pub async fn read_raw_tag_items_from_buffer(buffer: Buffer) -> Result<Vec<ApiRawTagItem>> {
+  util::read_raw_tag_items_from_buffer(buffer.to_vec())
+    .await
+    .map(|items| {
+      items
+        .into_iter()
+        .map(ApiRawTagItem::from_raw_tag_item)
+        .collect()
+    })
+    .map_err(napi::Error::from_reason)
+}
+
+#[napi]
+pub async fn write_raw_tag_items(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+  items: Vec<ApiRawTagItem>,
+  options: Option<ApiSaveOptions>,
+) -> Result<()> {
+  let items = items
+    .into_iter()
+    .map(ApiRawTagItem::into_raw_tag_item)
+    .collect();
+  match options {
+    Some(options) => {
+      util::write_raw_tag_items_with_options(file_path, items, options.into_save_options()).await
+    }
+    None => util::write_raw_tag_items(file_path, items).await,
+  }
+  .map_err(napi::Error::from_reason)
+}
+
+#[napi]
+pub async fn write_raw_tag_items_to_buffer(
+  buffer: Buffer,
+  items: Vec<ApiRawTagItem>,
+) -> Result<Buffer> {
+  let items = items
+    .into_iter()
+    .map(ApiRawTagItem::into_raw_tag_item)
+    .collect();
+  let result = util::write_raw_tag_items_to_buffer(buffer.to_vec(), items)
+    .await
+    .map_err(napi::Error::from_reason)?;
+  Ok(Buffer::from(result))
+}
+
+#[napi]
+pub async fn read_audio_properties(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+) -> Result<ApiAudioProperties> {
+  util::read_audio_properties(file_path)
+    .await
+    .map(ApiAudioProperties::from_audio_properties)
+    .map_err(napi::Error::from_reason)
+}
+
+#[napi]
+pub async fn read_audio_properties_from_buffer(
+  buffer: napi::bindgen_prelude::Buffer,
+) -> Result<ApiAudioProperties> {
+  util::read_audio_properties_from_buffer(buffer.to_vec())
+    .await
+    .map(ApiAudioProperties::from_audio_properties)
+    .map_err(napi::Error::from_reason)
+}
+
+#[napi]
+pub async fn write_tags(
+  #[napi(ts_arg_type = "string | Buffer | number | import('fs/promises').FileHandle")]
+  file: ApiFileSource,
+  tags: ApiAudioTags,
+  options: Option<ApiWriteOptions>,
+) -> Result<ApiWriteResult> {
+  let tags = tags.into_audio_tags();
+  match (file, options) {
+    (ApiFileSource::Path(file_path), Some(options)) => {
+      util::write_tags_with_options(file_path, tags, options.into_write_tags_options())
+        .await
+        .map(ApiWriteResult::from_write_tags_outcome)
+    }
+    (ApiFileSource::Fd(fd), options) => {
+      let options = options
+        .map(ApiWriteOptions::into_write_tags_options)
+        .unwrap_or_default();
+      util::write_tags_to_fd(fd, tags, options)
+        .await
+        .map(ApiWriteResult::from_write_tags_outcome)
+    }
+    (ApiFileSource::Path(file_path), None) => {
+      util::write_tags(file_path, tags)
+        .await
+        .map(|_| ApiWriteResult {
+          changed: true,
+          warnings: Vec::new(),
+        })
+    }
+  }
+  .map_err(napi::Error::from_reason)
+}
+
+#[napi]
+pub async fn validate_tags(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+  rules: Option<ApiValidationRules>,
+) -> Result<Vec<ApiValidationFinding>> {
+  let rules = rules
+    .map(ApiValidationRules::into_validation_rules)
+    .unwrap_or_default();
+  validate::validate_tags(file_path, rules)
+    .await
+    .map(|findings| {
+      findings
+        .into_iter()
+        .map(ApiValidationFinding::from_validation_finding)
+        .collect()
+    })
+    .map_err(napi::Error::from_reason)
+}
+
+#[napi]
+pub async fn validate_tags_from_buffer(
+  buffer: Buffer,
+  rules: Option<ApiValidationRules>,
+) -> Result<Vec<ApiValidationFinding>> {
+  let rules = rules
+    .map(ApiValidationRules::into_validation_rules)
+    .unwrap_or_default();
+  validate::validate_tags_from_buffer(buffer.to_vec(), rules)
+    .await
+    .map(|findings| {
+      findings
+        .into_iter()
+        .map(ApiValidationFinding::from_validation_finding)
+        .collect()
+    })
+    .map_err(napi::Error::from_reason)
+}
+
+// writes to a copy in memory, the file is left untouched
+#[napi]
+pub async fn diff_tags(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+  tags: ApiAudioTags,
+  options: Option<ApiWriteOptions>,
+) -> Result<Vec<ApiTagChange>> {
+  let options = options
+    .map(ApiWriteOptions::into_write_tags_options)
+    .unwrap_or_default();
+  diff::diff_tags(file_path, tags.into_audio_tags(), options)
+    .await
+    .map(|changes| {
+      changes
+        .into_iter()
+        .map(ApiTagChange::from_tag_change)
+        .collect()
+    })
+    .map_err(napi::Error::from_reason)
+}
+
+// only looks at a bounded number of bytes, so it runs synchronously
+#[napi]
+pub fn probe_buffer(buffer: Buffer, options: Option<ApiProbeOptions>) -> ApiBufferProbe {
+  let max_bytes = options
+    .and_then(|options| options.max_bytes)
+    .map(|max_bytes| max_bytes as usize)
+    .unwrap_or(probe::DEFAULT_PROBE_BYTES);
+  ApiBufferProbe::from_buffer_probe(probe::probe_buffer(&buffer, max_bytes))
+}
+
+#[napi]
+pub fn supported_formats() -> Vec<ApiFormatSupport> {
+  formats::supported_formats()
+    .into_iter()
+    .map(ApiFormatSupport::from_format_support)
+    .collect()
+}
+
+#[napi]
+pub async fn read_ixml(source: Either<Utf16String, Buffer>) -> Result<Option<ApiIxml>> {
+  match source {
+    Either::A(file_path) => ixml::read_ixml(path_from_string(file_path)).await,
+    Either::B(buffer) => ixml::read_ixml_from_buffer(buffer.to_vec()).await,
+  }
+  .map(|ixml| ixml.map(ApiIxml::from_ixml))
+  .map_err(napi::Error::from_reason)
+}
+
+#[napi]
+pub async fn write_ixml(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+  ixml: ApiIxml,
+  options: Option<ApiSaveOptions>,
+) -> Result<()> {
+  match options {
+    Some(options) => {
+      ixml::write_ixml_with_options(file_path, ixml.into_ixml(), options.into_save_options()).await
+    }
+    None => ixml::write_ixml(file_path, ixml.into_ixml()).await,
+  }
+  .map_err(napi::Error::from_reason)
+}
+
+#[napi]
+pub async fn write_ixml_to_buffer(buffer: Buffer, ixml: ApiIxml) -> Result<Buffer> {
+  ixml::write_ixml_to_buffer(buffer.to_vec(), ixml.into_ixml())
+    .await
+    .map(Buffer::from)
+    .map_err(napi::Error::from_reason)
+}
+
+#[napi]
+pub async fn probe_file(source: Either<Utf16String, Buffer>) -> Result<ApiFileProbe> {
+  match source {
+    Either::A(file_path) => probe::probe_file(path_from_string(file_path)).await,
+    Either::B(buffer) => probe::probe_file_from_buffer(buffer.to_vec()).await,
+  }
+  .map(ApiFileProbe::from_file_probe)
+  .map_err(napi::Error::from_reason)
+}
+
+#[napi]
+pub async fn write_tags_to_buffer(
+  buffer: napi::bindgen_prelude::Buffer,
+  tags: ApiAudioTags,
+  options: Option<ApiWriteOptions>,
+) -> Result<napi::bindgen_prelude::Buffer> {
+  let buffer = buffer.to_vec();
+  let tags = tags.into_audio_tags();
+  let result = match options {
+    Some(options) => {
+      util::write_tags_to_buffer_with_options(buffer, tags, options.into_write_tags_options()).await
+    }
+    None => util::write_tags_to_buffer(buffer, tags).await,
+  }
+  .map_err(napi::Error::from_reason)?;
+  Ok(Buffer::from(result))
+}
+
+#[napi]
+pub async fn update_tags(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+  patch: ApiTagPatch,
+  options: Option<ApiSaveOptions>,
+) -> Result<()> {
+  let options = options
+    .map(ApiSaveOptions::into_save_options)
+    .unwrap_or_default();
+  util::update_tags_with_options(file_path, patch.into_tag_patch(), options)
+    .await
+    .map_err(napi::Error::from_reason)
+}
+
+#[napi]
+pub async fn update_tags_to_buffer(buffer: Buffer, patch: ApiTagPatch) -> Result<Buffer> {
+  let result = util::update_tags_to_buffer(buffer.to_vec(), patch.into_tag_patch())
+    .await
+    .map_err(napi::Error::from_reason)?;
+  Ok(Buffer::from(result))
+}
+
+#[napi]
+pub async fn transform_tags(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+  rules: Vec<ApiTransformRule>,
+) -> Result<Vec<ApiTagChange>> {
+  transform::transform_tags(file_path, transform_rules(rules)?)
+    .await
+    .map(|changes| {
+      changes
+        .into_iter()
+        .map(ApiTagChange::from_tag_change)
+        .collect()
+    })
+    .map_err(napi::Error::from_reason)
+}
+
+#[napi]
+pub async fn transform_tags_to_buffer(
+  buffer: Buffer,
+  rules: Vec<ApiTransformRule>,
+) -> Result<Buffer> {
+  let result = transform::transform_tags_to_buffer(buffer.to_vec(), transform_rules(rules)?)
+    .await
+    .map_err(napi::Error::from_reason)?;
+  Ok(Buffer::from(result))
+}
+
+#[napi]
+pub async fn copy_tags(
+  #[napi(ts_arg_type = "string | Buffer")] source_path: ApiPath,
+  #[napi(ts_arg_type = "string | Buffer")] dest_path: ApiPath,
+  options: Option<ApiWriteOptions>,
+) -> Result<()> {
+  let options = options
+    .map(ApiWriteOptions::into_write_tags_options)
+    .unwrap_or_default();
+  util::copy_tags(source_path, dest_path, options)
+    .await
+    .map_err(napi::Error::from_reason)
+}
+
+#[napi]
+pub async fn copy_tags_to_buffer(
+  source: Buffer,
+  dest: Buffer,
+  options: Option<ApiWriteOptions>,
+) -> Result<Buffer> {
+  let options = options
+    .map(ApiWriteOptions::into_write_tags_options)
+    .unwrap_or_default();
+  let result = util::copy_tags_to_buffer(source.to_vec(), dest.to_vec(), options)
+    .await
+    .map_err(napi::Error::from_reason)?;
+  Ok(Buffer::from(result))
+}
+
+#[napi]
+pub async fn clear_tags(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+  options: Option<ApiClearOptions>,
+) -> Result<()> {
+  match options {
+    Some(options) => {
+      util::clear_tags_with_options(file_path, options.into_clear_tags_options()).await
+    }
+    None => util::clear_tags(file_path).await,
+  }
+  .map_err(napi::Error::from_reason)
+}
+
+#[napi]
+pub async fn clear_tags_to_buffer(
+  buffer: Buffer,
+  options: Option<ApiClearOptions>,
+) -> Result<Buffer> {
+  let buffer = buffer.to_vec();
+  let result = match options {
+    Some(options) => {
+      util::clear_tags_to_buffer_with_options(buffer, options.into_clear_tags_options()).await
+    }
+    None => util::clear_tags_to_buffer(buffer).await,
+  }
+  .map_err(napi::Error::from_reason)?;
+  Ok(Buffer::from(result))
+}
+
+#[napi]
+pub async fn strip_tag_types(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+  tag_types: Vec<ApiTagType>,
+  options: Option<ApiSaveOptions>,
+) -> Result<()> {
+  let tag_types = tag_types
+    .into_iter()
+    .map(ApiTagType::into_tag_type)
+    .collect();
+  match options {
+    Some(options) => {
+      util::strip_tag_types_with_options(file_path, tag_types, options.into_save_options()).await
+    }
+    None => util::strip_tag_types(file_path, tag_types).await,
+  }
+  .map_err(napi::Error::from_reason)
+}
+
+#[napi]
+pub async fn strip_tag_types_to_buffer(
+  buffer: Buffer,
+  tag_types: Vec<ApiTagType>,
+) -> Result<Buffer> {
+  let tag_types = tag_types
+    .into_iter()
+    .map(ApiTagType::into_tag_type)
+    .collect();
+  let result = util::strip_tag_types_to_buffer(buffer.to_vec(), tag_types)
+    .await
+    .map_err(napi::Error::from_reason)?;
+  Ok(Buffer::from(result))
+}
+
+#[napi]
+pub async fn strip_all_tags(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+  options: Option<ApiSaveOptions>,
+) -> Result<Vec<ApiTagType>> {
+  match options {
+    Some(options) => {
+      util::strip_all_tags_with_options(file_path, options.into_save_options()).await
+    }
+    None => util::strip_all_tags(file_path).await,
+  }
+  .map(|removed| {
+    removed
+      .into_iter()
+      .filter_map(ApiTagType::from_tag_type)
+      .collect()
+  })
+  .map_err(napi::Error::from_reason)
+}
+
+#[napi(js_name = "StripResult", object)]
+pub struct ApiStripResult {
+  pub buffer: Buffer,
+  pub removed: Vec<ApiTagType>,
+}
+
+#[napi]
+pub async fn strip_all_tags_to_buffer(buffer: Buffer) -> Result<ApiStripResult> {
+  let (result, removed) = util::strip_all_tags_to_buffer(buffer.to_vec())
+    .await
+    .map_err(napi::Error::from_reason)?;
+  Ok(ApiStripResult {
+    // a buffer without tags comes back as it was passed in
+    buffer: if removed.is_empty() {
+      buffer
+    } else {
+      Buffer::from(result)
+    },
+    removed: removed
+      .into_iter()
+      .filter_map(ApiTagType::from_tag_type)
+      .collect(),
+  })
+}
+
+// An AbortSignal, along with whether it had fired before it was passed in
+pub struct ApiAbortSignal {
+  aborted: bool,
+  signal: AbortSignal,
+}
+
+impl FromNapiValue for ApiAbortSignal {
+  unsafe fn from_napi_value(
+    env: napi::sys::napi_env,
+    value: napi::sys::napi_value,
+  ) -> Result<Self> {
+    let aborted = Object::from_napi_value(env, value)?.get_named_property::<bool>("aborted")?;
+    Ok(Self {
+      aborted,
+      signal: AbortSignal::from_napi_value(env, value)?,
+    })
+  }
+}
+
+// cancelled once the signal fires, or right away when it already has
+fn cancel_on_abort(signal: Option<ApiAbortSignal>) -> Cancellation {
+  let cancel = Cancellation::default();
+  match signal {
+    Some(signal) if signal.aborted => cancel.cancel(),
+    Some(signal) => {
+      let cancel = cancel.clone();
+      signal.signal.on_abort(move || cancel.cancel());
+    }
+    None => {}
+  }
+  cancel
+}
+
+type ProgressCallback = ThreadsafeFunction<
+  FnArgs<(u32, u32, String)>,
+  UnknownReturnValue,
+  FnArgs<(u32, u32, String)>,
+  Status,
+  false,
+>;
+
+// Reports the progress of a batch to a JS callback, without waiting for it
+pub struct ApiProgress(Option<ProgressCallback>);
+
+impl Progress for ApiProgress {
+  fn report(&self, done: usize, total: usize, file_path: &str) {
+    if let Some(on_progress) = &self.0 {
+      on_progress.call(
+        (done as u32, total as u32, file_path.to_string()).into(),
+        ThreadsafeFunctionCallMode::NonBlocking,
+      );
+    }
+  }
+}
+
+// the batch functions aren't async fns: the signal has to be watched on the
+// JS thread before the work is spawned
+#[napi]
+pub fn read_tags_batch<'env>(
+  env: &'env Env,
+  #[napi(ts_arg_type = "Array<string | Buffer>")] paths: Vec<ApiPath>,
+  concurrency: Option<u32>,
+  #[napi(ts_arg_type = "AbortSignal")] signal: Option<ApiAbortSignal>,
+  #[napi(ts_arg_type = "(done: number, total: number, currentPath: string) => void")]
+  on_progress: Option<ProgressCallback>,
+  options: Option<ApiReadOptions>,
+) -> Result<PromiseRaw<'env, Vec<ApiBatchReadResult>>> {
+  let cancel = cancel_on_abort(signal);
+  let progress = ApiProgress(on_progress);
+  let options = options
+    .map(ApiReadOptions::into_read_tags_options)
+    .unwrap_or_default();
+  env.spawn_future(async move {
+    let concurrency = concurrency.map(|n| n as usize);
+    let results =
+      batch::read_tags_batch(into_paths(paths), concurrency, &cancel, progress, options)
+        .await
+        .map_err(napi::Error::from_reason)?;
+    Ok(
+      results
+        .into_iter()
+        .map(ApiBatchReadResult::from_batch_read_result)
+        .collect(),
+    )
+  })
+}
+
+#[napi]
+pub fn scan_directory<'env>(
+  env: &'env Env,
+  #[napi(ts_arg_type = "string | Buffer")] root: ApiPath,
+  options: Option<ApiScanOptions>,
+  #[napi(ts_arg_type = "AbortSignal")] signal: Option<ApiAbortSignal>,
+  #[napi(ts_arg_type = "(done: number, total: number, currentPath: string) => void")]
+  on_progress: Option<ProgressCallback>,
+) -> Result<PromiseRaw<'env, Vec<ApiScanResult>>> {
+  let options = options
+    .map(ApiScanOptions::into_scan_options)
+    .unwrap_or_default();
+  let cancel = cancel_on_abort(signal);
+  let progress = ApiProgress(on_progress);
+  env.spawn_future(async move {
+    let results = scan::scan_directory(root, options, &cancel, progress)
+      .await
+      .map_err(napi::Error::from_reason)?;
+    Ok(
+      results
+        .into_iter()
+        .map(ApiScanResult::from_scan_result)
+        .collect(),
+    )
+  })
+}
+
+type ResultCallback<T> =
+  ThreadsafeFunction<T, Either<Promise<()>, UnknownReturnValue>, T, Status, false>;
+
+// Hands a result to a JS callback, waiting for the promise it may return; a
+// callback that throws or rejects stops the batch
+async fn call_result_callback<T: ToNapiValue + 'static>(
+  on_result: &ResultCallback<T>,
+  result: T,
+) -> std::result::Result<(), String> {
+  if let Either::A(promise) = on_result
+    .call_async_catch(result)
+    .await
+    .map_err(|e| e.reason)?
+  {
+    promise.await.map_err(|e| e.reason)?;
+  }
+  Ok(())
+}
+
+#[napi(ts_return_type = "Promise<void>")]
+pub fn read_tags_batch_each<'env>(
+  env: &'env Env,
+  #[napi(ts_arg_type = "Array<string | Buffer>")] paths: Vec<ApiPath>,
+  #[napi(ts_arg_type = "(result: BatchReadResult) => void | Promise<void>")]
+  on_result: ResultCallback<ApiBatchReadResult>,
+  concurrency: Option<u32>,
+  #[napi(ts_arg_type = "AbortSignal")] signal: Option<ApiAbortSignal>,
+  #[napi(ts_arg_type = "(done: number, total: number, currentPath: string) => void")]
+  on_progress: Option<ProgressCallback>,
+  options: Option<ApiReadOptions>,
+) -> Result<PromiseRaw<'env, ()>> {
+  let cancel = cancel_on_abort(signal);
+  let progress = ApiProgress(on_progress);
+  let options = options
+    .map(ApiReadOptions::into_read_tags_options)
+    .unwrap_or_default();
+  env.spawn_future(async move {
+    let concurrency = concurrency.map(|n| n as usize);
+    batch::read_tags_batch_each(
+      into_paths(paths),
+      concurrency,
+      &cancel,
+      progress,
+      options,
+      |result| {
+        call_result_callback(
+          &on_result,
+          ApiBatchReadResult::from_batch_read_result(result),
+        )
+      },
+    )
+    .await
+    .map_err(napi::Error::from_reason)
+  })
+}
+
+#[napi]
+pub fn scan_directory_each<'env>(
+  env: &'env Env,
+  #[napi(ts_arg_type = "string | Buffer")] root: ApiPath,
+  #[napi(ts_arg_type = "(result: ScanResult) => void | Promise<void>")] on_result: ResultCallback<
+    ApiScanResult,
+  >,
+  options: Option<ApiScanOptions>,
+  #[napi(ts_arg_type = "AbortSignal")] signal: Option<ApiAbortSignal>,
+  #[napi(ts_arg_type = "(done: number, total: number, currentPath: string) => void")]
+  on_progress: Option<ProgressCallback>,
+) -> Result<PromiseRaw<'env, u32>> {
+  let options = options
+    .map(ApiScanOptions::into_scan_options)
+    .unwrap_or_default();
+  let cancel = cancel_on_abort(signal);
+  let progress = ApiProgress(on_progress);
+  env.spawn_future(async move {
+    let count = scan::scan_directory_each(root, options, &cancel, progress, |result| {
+      call_result_callback(&on_result, ApiScanResult::from_scan_result(result))
+    })
+    .await
+    .map_err(napi::Error::from_reason)?;
+    Ok(count as u32)
+  })
+}
+
+#[napi]
+pub fn export_tags<'env>(
+  env: &'env Env,
+  #[napi(ts_arg_type = "string | Buffer")] root: ApiPath,
+  format: ApiExportFormat,
+  #[napi(ts_arg_type = "string | Buffer")] out_path: ApiPath,
+  options: Option<ApiScanOptions>,
+  #[napi(ts_arg_type = "AbortSignal")] signal: Option<ApiAbortSignal>,
+  #[napi(ts_arg_type = "(done: number, total: number, currentPath: string) => void")]
+  on_progress: Option<ProgressCallback>,
+) -> Result<PromiseRaw<'env, u32>> {
+  let format = format.into_export_format();
+  let options = options
+    .map(ApiScanOptions::into_scan_options)
+    .unwrap_or_default();
+  let cancel = cancel_on_abort(signal);
+  let progress = ApiProgress(on_progress);
+  env.spawn_future(async move {
+    let count = export::export_tags(root, format, out_path, options, &cancel, progress)
+      .await
+      .map_err(napi::Error::from_reason)?;
+    Ok(count as u32)
+  })
+}
+
+#[napi]
+pub async fn import_tags(
+  #[napi(ts_arg_type = "string | Buffer")] manifest_path: ApiPath,
+  options: Option<ApiImportOptions>,
+) -> Result<Vec<ApiImportResult>> {
+  let options = options
+    .map(ApiImportOptions::into_import_options)
+    .unwrap_or_default();
+  let results = manifest::import_tags(manifest_path, options)
+    .await
+    .map_err(napi::Error::from_reason)?;
+  Ok(
+    results
+      .into_iter()
+      .map(ApiImportResult::from_import_result)
+      .collect(),
+  )
+}
+
+type WatchCallback =
+  ThreadsafeFunction<ApiWatchEvent, UnknownReturnValue, ApiWatchEvent, Status, false>;
+
+// A running watch; it keeps the process alive until closed
+#[napi(js_name = "DirectoryWatcher")]
+pub struct ApiDirectoryWatcher {
+  watch: Option<DirectoryWatch>,
+}
+
+#[napi]
+impl ApiDirectoryWatcher {
+  // stop watching, events not reported yet are dropped
+  #[napi]
+  pub fn close(&mut self) {
+    self.watch = None;
+  }
+}
+
+#[napi]
+pub fn watch_directory(
+  #[napi(ts_arg_type = "string | Buffer")] root: ApiPath,
+  #[napi(ts_arg_type = "(event: WatchEvent) => void")] on_event: WatchCallback,
+  options: Option<ApiWatchOptions>,
+) -> Result<ApiDirectoryWatcher> {
+  let options = options
+    .map(ApiWatchOptions::into_watch_options)
+    .unwrap_or_default();
+  let watch = watch::watch_directory(root.as_ref(), options, move |event| {
+    on_event.call(
+      ApiWatchEvent::from_watch_event(event),
+      ThreadsafeFunctionCallMode::NonBlocking,
+    );
+  })
+  .map_err(napi::Error::from_reason)?;
+  Ok(ApiDirectoryWatcher { watch: Some(watch) })
+}
+
+#[napi]
+pub fn clear_tags_batch<'env>(
+  env: &'env Env,
+  #[napi(ts_arg_type = "Array<string | Buffer>")] paths: Vec<ApiPath>,
+  options: Option<ApiClearOptions>,
+  #[napi(ts_arg_type = "AbortSignal")] signal: Option<ApiAbortSignal>,
+  #[napi(ts_arg_type = "(done: number, total: number, currentPath: string) => void")]
+  on_progress: Option<ProgressCallback>,
+  hooks: Option<ApiWriteHooks>,
+) -> Result<PromiseRaw<'env, Vec<ApiBatchFileResult>>> {
+  let options = options
+    .map(ApiClearOptions::into_clear_tags_options)
+    .unwrap_or_default();
+  let cancel = cancel_on_abort(signal);
+  let progress = ApiProgress(on_progress);
+  env.spawn_future(async move {
+    let results = match hooks {
+      Some(hooks) => {
+        batch::clear_tags_batch(into_paths(paths), options, &cancel, progress, hooks).await
+      }
+      None => batch::clear_tags_batch(into_paths(paths), options, &cancel, progress, NoHooks).await,
+    }
+    .map_err(napi::Error::from_reason)?;
+    Ok(
+      results
+        .into_iter()
+        .map(ApiBatchFileResult::from_batch_file_result)
+        .collect(),
+    )
+  })
+}
+
+#[napi]
+pub fn transform_tags_batch<'env>(
+  env: &'env Env,
+  #[napi(ts_arg_type = "Array<string | Buffer>")] paths: Vec<ApiPath>,
+  rules: Vec<ApiTransformRule>,
+  #[napi(ts_arg_type = "AbortSignal")] signal: Option<ApiAbortSignal>,
+  #[napi(ts_arg_type = "(done: number, total: number, currentPath: string) => void")]
+  on_progress: Option<ProgressCallback>,
+  hooks: Option<ApiWriteHooks>,
+) -> Result<PromiseRaw<'env, Vec<ApiBatchFileResult>>> {
+  let rules = transform_rules(rules)?;
+  let cancel = cancel_on_abort(signal);
+  let progress = ApiProgress(on_progress);
+  env.spawn_future(async move {
+    let results = match hooks {
+      Some(hooks) => {
+        batch::transform_tags_batch(into_paths(paths), rules, &cancel, progress, hooks).await
+      }
+      None => {
+        batch::transform_tags_batch(into_paths(paths), rules, &cancel, progress, NoHooks).await
+      }
+    }
+    .map_err(napi::Error::from_reason)?;
+    Ok(
+      results
+        .into_iter()
+        .map(ApiBatchFileResult::from_batch_file_result)
+        .collect(),
+    )
+  })
+}
+
+#[napi]
+pub async fn read_cover_image_from_buffer(
+  buffer: Buffer,
+  pic_type: Option<ApiAudioImageType>,
+) -> Result<Option<Buffer>> {
+  let buffer = buffer.to_vec();
+  let result = match pic_type {
+    Some(pic_type) => util::read_image_from_buffer(buffer, pic_type.into_audio_image_type()).await,
+    None => util::read_cover_image_from_buffer(buffer).await,
+  }
+  .map_err(napi::Error::from_reason)?;
+  Ok(result.map(Buffer::from))
+}
+
+#[napi]
+pub async fn write_cover_image_to_buffer(
+  buffer: Buffer,
+  image_data: Buffer,
+  options: Option<ApiCoverImageOptions>,
+) -> Result<Buffer> {
+  let (buffer, image_data) = (buffer.to_vec(), image_data.to_vec());
+  let result = match options {
+    Some(options) => {
+      util::write_cover_image_to_buffer_with_options(
+        buffer,
+        image_data,
+        options.into_cover_image_options(),
+      )
+      .await
+    }
+    None => util::write_cover_image_to_buffer(buffer, image_data).await,
+  }
+  .map_err(napi::Error::from_reason)?;
+  Ok(Buffer::from(result))
+}
+
+#[napi]
+pub async fn remove_cover_image_from_buffer(buffer: Buffer) -> Result<Buffer> {
+  let result = util::remove_cover_image_from_buffer(buffer.to_vec())
+    .await
+    .map_err(napi::Error::from_reason)?;
+  Ok(Buffer::from(result))
+}
+
+#[napi]
+pub async fn remove_images_from_buffer(
+  buffer: Buffer,
+  pic_types: Option<Vec<ApiAudioImageType>>,
+) -> Result<Buffer> {
+  let pic_types = pic_types.map(|pic_types| {
+    pic_types
+      .into_iter()
+      .map(ApiAudioImageType::into_audio_image_type)
+      .collect()
+  });
+  let result = util::remove_images_from_buffer(buffer.to_vec(), pic_types)
+    .await
+    .map_err(napi::Error::from_reason)?;
+  Ok(Buffer::from(result))
+}
+
+#[napi]
+pub async fn read_lyrics(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+) -> Result<Option<String>> {
+  util::read_lyrics(file_path)
+    .await
+    .map_err(napi::Error::from_reason)
+}
+
+#[napi]
+pub async fn read_lyrics_from_buffer(buffer: Buffer) -> Result<Option<String>> {
+  util::read_lyrics_from_buffer(buffer.to_vec())
+    .await
+    .map_err(napi::Error::from_reason)
+}
+
+#[napi]
+pub async fn write_lyrics(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+  lyrics: String,
+  options: Option<ApiSaveOptions>,
+) -> Result<()> {
+  match options {
+    Some(options) => {
+      util::write_lyrics_with_options(file_path, lyrics, options.into_save_options()).await
+    }
+    None => util::write_lyrics(file_path, lyrics).await,
+  }
+  .map_err(napi::Error::from_reason)
+}
+
+#[napi]
+pub async fn write_lyrics_to_buffer(buffer: Buffer, lyrics: String) -> Result<Buffer> {
+  let result = util::write_lyrics_to_buffer(buffer.to_vec(), lyrics)
+    .await
+    .map_err(napi::Error::from_reason)?;
+  Ok(Buffer::from(result))
+}
+
+#[napi]
+pub async fn read_synced_lyrics(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+) -> Result<Option<ApiSyncedLyrics>> {
+  util::read_synced_lyrics(file_path)
+    .await
+    .map(|lyrics| lyrics.map(ApiSyncedLyrics::from_synced_lyrics))
+    .map_err(napi::Error::from_reason)
+}
+
+#[napi]
+pub async fn read_synced_lyrics_from_buffer(buffer: Buffer) -> Result<Option<ApiSyncedLyrics>> {
+  util::read_synced_lyrics_from_buffer(buffer.to_vec())
+    .await
+    .map(|lyrics| lyrics.map(ApiSyncedLyrics::from_synced_lyrics))
+    .map_err(napi::Error::from_reason)
+}
+
+#[napi]
+pub async fn write_synced_lyrics(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+  lyrics: ApiSyncedLyrics,
+  options: Option<ApiSaveOptions>,
+) -> Result<()> {
+  let lyrics = lyrics.into_synced_lyrics();
+  match options {
+    Some(options) => {
+      util::write_synced_lyrics_with_options(file_path, lyrics, options.into_save_options()).await
+    }
+    None => util::write_synced_lyrics(file_path, lyrics).await,
+  }
+  .map_err(napi::Error::from_reason)
+}
+
+#[napi]
+pub async fn write_synced_lyrics_to_buffer(
+  buffer: Buffer,
+  lyrics: ApiSyncedLyrics,
+) -> Result<Buffer> {
+  let result = util::write_synced_lyrics_to_buffer(buffer.to_vec(), lyrics.into_synced_lyrics())
+    .await
+    .map_err(napi::Error::from_reason)?;
+  Ok(Buffer::from(result))
+}
+
+#[napi]
+pub fn merge_tags(
+  base: ApiAudioTags,
+  incoming: ApiAudioTags,
+  strategy: Option<Either<ApiMergeRule, ApiMergeStrategy>>,
+) -> ApiAudioTags {
+  let strategy = match strategy {
+    Some(Either::A(rule)) => MergeStrategy {
+      rule: rule.into_merge_rule(),
+      ..Default::default()
+    },
+    Some(Either::B(strategy)) => strategy.into_merge_strategy(),
+    None => MergeStrategy::default(),
+  };
+  ApiAudioTags::from_audio_tags(util::merge_tags(
+    base.into_audio_tags(),
+    incoming.into_audio_tags(),
+    &strategy,
+  ))
+}
+
+#[napi]
+pub fn parse_lrc(lrc: String) -> ApiSyncedLyrics {
+  ApiSyncedLyrics::from_synced_lyrics(synced_lyrics::parse_lrc(&lrc))
+}
+
+#[napi]
+pub fn to_lrc(lyrics: ApiSyncedLyrics) -> String {
+  synced_lyrics::to_lrc(&lyrics.into_synced_lyrics())
+}
+
+#[napi(js_name = "Chapter", object)]
+pub struct ApiChapter {
+  pub start_ms: u32,
+  pub end_ms: u32,
+  pub title: Option<String>,
+  pub url: Option<String>,
+  pub image: Option<ApiImage>,
+}
+
+impl ApiChapter {
+  pub fn from_chapter(chapter: Chapter) -> Self {
+    Self {
+      start_ms: chapter.start_ms,
+      end_ms: chapter.end_ms,
+      title: chapter.title,
+      url: chapter.url,
+      image: chapter.image.map(ApiImage::from_image),
+    }
+  }
+
+  pub fn into_chapter(self) -> Chapter {
+    Chapter {
+      start_ms: self.start_ms,
+      end_ms: self.end_ms,
+      title: self.title,
+      url: self.url,
+      image: self.image.map(|image| image.into_image()),
+    }
+  }
+}
+
+#[napi]
+pub async fn read_chapters(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+) -> Result<Vec<ApiChapter>> {
+  util::read_chapters(file_path)
+    .await
+    .map(|chapters| chapters.into_iter().map(ApiChapter::from_chapter).collect())
+    .map_err(napi::Error::from_reason)
+}
+
+#[napi]
+pub async fn read_chapters_from_buffer(buffer: Buffer) -> Result<Vec<ApiChapter>> {
+  util::read_chapters_from_buffer(buffer.to_vec())
+    .await
+    .map(|chapters| chapters.into_iter().map(ApiChapter::from_chapter).collect())
+    .map_err(napi::Error::from_reason)
+}
+
+#[napi]
+pub async fn write_chapters(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+  chapters: Vec<ApiChapter>,
+  options: Option<ApiSaveOptions>,
+) -> Result<()> {
+  let chapters = chapters.into_iter().map(ApiChapter::into_chapter).collect();
+  match options {
+    Some(options) => {
+      util::write_chapters_with_options(file_path, chapters, options.into_save_options()).await
+    }
+    None => util::write_chapters(file_path, chapters).await,
+  }
+  .map_err(napi::Error::from_reason)
+}
+
+#[napi]
+pub async fn write_chapters_to_buffer(buffer: Buffer, chapters: Vec<ApiChapter>) -> Result<Buffer> {
+  let chapters = chapters.into_iter().map(ApiChapter::into_chapter).collect();
+  let result = util::write_chapters_to_buffer(buffer.to_vec(), chapters)
+    .await
+    .map_err(napi::Error::from_reason)?;
+  Ok(Buffer::from(result))
+}
+
+#[napi]
+pub async fn read_cover_image_from_file(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+  pic_type: Option<ApiAudioImageType>,
+) -> Result<Option<Buffer>> {
+  let result = match pic_type {
+    Some(pic_type) => util::read_image_from_file(file_path, pic_type.into_audio_image_type()).await,
+    None => util::read_cover_image_from_file(file_path).await,
+  }
+  .map_err(napi::Error::from_reason)?;
+  Ok(result.map(Buffer::from))
+}
+
+#[napi]
+pub async fn write_cover_image_to_file(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+  image_data: Buffer,
+  options: Option<ApiCoverImageOptions>,
+) -> Result<()> {
+  let image_data = image_data.to_vec();
+  match options {
+    Some(options) => {
+      util::write_cover_image_to_file_with_options(
+        file_path,
+        image_data,
+        options.into_cover_image_options(),
+      )
+      .await
+    }
+    None => util::write_cover_image_to_file(file_path, image_data).await,
+  }
+  .map_err(napi::Error::from_reason)
+}
+
+#[napi]
+pub async fn write_cover_image_from_path(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+  #[napi(ts_arg_type = "string | Buffer")] image_path: ApiPath,
+  options: Option<ApiCoverImageOptions>,
+) -> Result<()> {
+  let options = options
+    .map(ApiCoverImageOptions::into_cover_image_options)
+    .unwrap_or_default();
+  util::write_cover_image_from_path(file_path, image_path, options)
+    .await
+    .map_err(napi::Error::from_reason)
+}
+
+// only built with the http feature
+#[cfg(feature = "http")]
+#[napi]
+pub async fn write_cover_image_from_url(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+  url: String,
+  options: Option<ApiCoverImageOptions>,
+) -> Result<()> {
+  let options = options
+    .map(ApiCoverImageOptions::into_cover_image_options)
+    .unwrap_or_default();
+  util::write_cover_image_from_url(file_path, url, options)
+    .await
+    .map_err(napi::Error::from_reason)
+}
+
+#[napi]
+pub async fn remove_cover_image_from_file(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+  options: Option<ApiSaveOptions>,
+) -> Result<()> {
+  match options {
+    Some(options) => {
+      util::remove_cover_image_from_file_with_options(file_path, options.into_save_options()).await
+    }
+    None => util::remove_cover_image_from_file(file_path).await,
+  }
+  .map_err(napi::Error::from_reason)
+}
+
+#[napi]
+pub async fn remove_images(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+  pic_types: Option<Vec<ApiAudioImageType>>,
+  options: Option<ApiSaveOptions>,
+) -> Result<()> {
+  let pic_types = pic_types.map(|pic_types| {
+    pic_types
+      .into_iter()
+      .map(ApiAudioImageType::into_audio_image_type)
+      .collect()
+  });
+  match options {
+    Some(options) => {
+      util::remove_images_with_options(file_path, pic_types, options.into_save_options()).await
+    }
+    None => util::remove_images(file_path, pic_types).await,
+  }
+  .map_err(napi::Error::from_reason)
+}
+
+#[napi]
+pub fn read_cover_images_batch<'env>(
+  env: &'env Env,
+  #[napi(ts_arg_type = "Array<string | Buffer>")] paths: Vec<ApiPath>,
+  options: Option<ApiCoverBatchOptions>,
+  #[napi(ts_arg_type = "AbortSignal")] signal: Option<ApiAbortSignal>,
+  #[napi(ts_arg_type = "(done: number, total: number, currentPath: string) => void")]
+  on_progress: Option<ProgressCallback>,
+) -> Result<PromiseRaw<'env, Vec<Option<Buffer>>>> {
+  let max_bytes_per_image = options
+    .and_then(|options| options.max_bytes_per_image)
+    .map(|max| max as usize);
+  let cancel = cancel_on_abort(signal);
+  let progress = ApiProgress(on_progress);
+  env.spawn_future(async move {
+    let covers =
+      batch::read_cover_images_batch(into_paths(paths), max_bytes_per_image, &cancel, progress)
+        .await
+        .map_err(napi::Error::from_reason)?;
+    Ok(
+      covers
+        .into_iter()
+        .map(|cover| cover.map(Buffer::from))
+        .collect(),
+    )
+  })
+}
+
+#[napi]
+pub fn write_tags_transaction<'env>(
+  env: &'env Env,
+  entries: Vec<ApiTagWriteEntry>,
+  hooks: Option<ApiWriteHooks>,
+  #[napi(ts_arg_type = "AbortSignal")] signal: Option<ApiAbortSignal>,
+) -> Result<PromiseRaw<'env, Vec<String>>> {
+  let entries = entries
+    .into_iter()
+    .map(ApiTagWriteEntry::into_tag_write_entry)
+    .collect();
+  let cancel = cancel_on_abort(signal);
+  env.spawn_future(async move {
+    match hooks {
+      Some(hooks) => transaction::write_tags_transaction_with_hooks(entries, &hooks, &cancel).await,
+      None => transaction::write_tags_transaction_with_hooks(entries, &NoHooks, &cancel).await,
+    }
+    .map_err(napi::Error::from_reason)
+  })
+}
+
+#[napi]
+pub async fn undo_last_write(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+  #[napi(ts_arg_type = "string | Buffer")] journal_dir: Option<ApiPath>,
+) -> Result<bool> {
+  journal::undo_last_write(file_path, journal_dir.map(|dir| dir.0))
+    .await
+    .map_err(napi::Error::from_reason)
+}
+
+#[napi]
+pub async fn read_cover_thumbnail(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+  max_edge: Option<u32>,
+) -> Result<Option<Buffer>> {
+  let max_edge = max_edge.unwrap_or(artwork::DEFAULT_THUMBNAIL_SIZE);
+  artwork::read_cover_thumbnail(file_path, max_edge)
+    .await
+    .map(|thumbnail| thumbnail.map(Buffer::from))
+    .map_err(napi::Error::from_reason)
+}
+
+#[napi]
+pub async fn build_artwork_cache(
+  #[napi(ts_arg_type = "Array<string | Buffer>")] paths: Vec<ApiPath>,
+  #[napi(ts_arg_type = "string | Buffer")] cache_dir: ApiPath,
+  options: Option<ApiArtworkCacheOptions>,
+) -> Result<HashMap<String, ApiArtworkCacheEntry>> {
+  let size = options
+    .and_then(|options| options.size)
+    .unwrap_or(artwork::DEFAULT_THUMBNAIL_SIZE);
+  let entries = artwork::build_artwork_cache(into_paths(paths), cache_dir, size)
+    .await
+    .map_err(napi::Error::from_reason)?;
+  Ok(
+    entries
+      .into_iter()
+      .map(|(path, entry)| (path, ApiArtworkCacheEntry::from_artwork_cache_entry(entry)))
+      .collect(),
+  )
+}
+
+#[napi]
+pub async fn tag_fingerprint(source: Either<Utf16String, ApiAudioTags>) -> Result<String> {
+  match source {
+    Either::A(file_path) => fingerprint::file_tag_fingerprint(path_from_string(file_path))
+      .await
+      .map_err(napi::Error::from_reason),
+    Either::B(tags) => Ok(fingerprint::tag_fingerprint(&tags.into_audio_tags())),
+  }
+}
+
+#[napi]
+pub async fn audio_hash(source: Either<Utf16String, Buffer>) -> Result<String> {
+  match source {
+    Either::A(file_path) => audio_hash::audio_hash(path_from_string(file_path)).await,
+    Either::B(buffer) => audio_hash::audio_hash_from_buffer(buffer.to_vec()).await,
+  }
+  .map_err(napi::Error::from_reason)
+}
+
+#[napi(js_name = "TagsEqualOptions", object)]
+pub struct ApiTagsEqualOptions {
+  pub ignore_images: Option<bool>,
+}
+
+#[napi]
+pub fn tags_equal(a: ApiAudioTags, b: ApiAudioTags, options: Option<ApiTagsEqualOptions>) -> bool {
+  let options = TagsEqualOptions {
+    ignore_images: options
+      .and_then(|options| options.ignore_images)
+      .unwrap_or(false),
+  };
+  fingerprint::tags_equal(&a.into_audio_tags(), &b.into_audio_tags(), &options)
+}
+
+#[cfg(feature = "chromaprint")]
+#[napi(js_name = "AcoustidFingerprint", object)]
+pub struct ApiAcoustidFingerprint {
+  pub fingerprint: String,
+  pub duration: u32,
+}
+
+// only built with the chromaprint feature
+#[cfg(feature = "chromaprint")]
+#[napi]
+pub async fn compute_fingerprint(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+) -> Result<ApiAcoustidFingerprint> {
+  let result = chromaprint::compute_fingerprint(file_path)
+    .await
+    .map_err(napi::Error::from_reason)?;
+  Ok(ApiAcoustidFingerprint {
+    fingerprint: result.fingerprint,
+    duration: result.duration,
+  })
+}
+
+#[napi]
+pub async fn read_cover_image_data_url(
+  source: Either<Utf16String, Buffer>,
+) -> Result<Option<String>> {
+  match source {
+    Either::A(file_path) => artwork::read_cover_image_data_url(path_from_string(file_path)).await,
+    Either::B(buffer) => artwork::read_cover_image_data_url_from_buffer(buffer.to_vec()).await,
+  }
+  .map_err(napi::Error::from_reason)
+}
+
+#[napi]
+pub async fn read_image_info(source: Either<Utf16String, Buffer>) -> Result<Vec<ApiImageInfo>> {
+  let info = match source {
+    Either::A(file_path) => artwork::read_image_info(path_from_string(file_path)).await,
+    Either::B(buffer) => artwork::read_image_info_from_buffer(buffer.to_vec()).await,
+  }
+  .map_err(napi::Error::from_reason)?;
+  Ok(
+    info
+      .into_iter()
+      .map(ApiImageInfo::from_image_info)
+      .collect(),
+  )
+}
+
+#[napi]
+pub async fn read_primary_artwork(source: Either<Utf16String, Buffer>) -> Result<Option<ApiImage>> {
+  match source {
+    Either::A(file_path) => artwork::read_primary_artwork(path_from_string(file_path)).await,
+    Either::B(buffer) => artwork::read_primary_artwork_from_buffer(buffer.to_vec()).await,
+  }
+  .map(|image| image.map(ApiImage::from_image))
+  .map_err(napi::Error::from_reason)
+}
+
+#[napi]
+pub async fn can_write(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+) -> ApiWritability {
+  ApiWritability::from_writability(writability::can_write(file_path).await)
+}
+
+#[napi]
+pub async fn propagate_album_fields(
+  #[napi(ts_arg_type = "Array<string | Buffer>")] paths: Vec<ApiPath>,
+  fields: Vec<ApiAlbumField>,
+  options: Option<ApiPropagateOptions>,
+) -> Result<ApiAudioTags> {
+  let fields = fields
+    .into_iter()
+    .map(ApiAlbumField::into_album_field)
+    .collect();
+  let reference = options
+    .and_then(|options| options.reference)
+    .map(PathBuf::from);
+  album::propagate_album_fields(into_paths(paths), fields, reference)
+    .await
+    .map(ApiAudioTags::from_audio_tags)
+    .map_err(napi::Error::from_reason)
+}
+
+#[napi]
+pub async fn rename_from_tags(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+  template: String,
+  options: Option<ApiRenameOptions>,
+) -> Result<ApiRenameOutcome> {
+  let options = options
+    .map(ApiRenameOptions::into_rename_options)
+    .unwrap_or_default();
+  rename::rename_from_tags(file_path, template, options)
+    .await
+    .map(ApiRenameOutcome::from_rename_outcome)
+    .map_err(napi::Error::from_reason)
+}
+
+#[napi]
+pub async fn infer_tags_from_filename(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+  pattern: String,
+  options: Option<ApiInferOptions>,
+) -> Result<ApiAudioTags> {
+  let options = options
+    .map(ApiInferOptions::into_infer_options)
+    .unwrap_or_default();
+  rename::infer_tags_from_filename(file_path, pattern, options)
+    .await
+    .map(ApiAudioTags::from_audio_tags)
+    .map_err(napi::Error::from_reason)
+}
+
+// Blocking variants for CLI tools and worker threads. They run the async
+// functions above to completion on the calling thread.
+
+#[napi]
+pub fn read_tags_sync(
+  #[napi(ts_arg_type = "string | Buffer | number | import('fs/promises').FileHandle")]
+  file: ApiFileSource,
+  options: Option<ApiReadOptions>,
+) -> Result<ApiAudioTags> {
+  block_on(read_tags(file, options))
+}
+
+#[napi]
+pub fn read_tags_from_buffer_sync(
+  buffer: Buffer,
+  options: Option<ApiReadOptions>,
+) -> Result<ApiAudioTags> {
+  block_on(read_tags_from_buffer(buffer, options))
+}
+
+#[napi]
+pub fn read_all_tags_sync(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+) -> Result<Vec<ApiTagEntry>> {
+  block_on(read_all_tags(file_path))
+}
+
+#[napi]
+pub fn read_all_tags_from_buffer_sync(buffer: Buffer) -> Result<Vec<ApiTagEntry>> {
+  block_on(read_all_tags_from_buffer(buffer))
+}
+
+#[napi]
+pub fn read_raw_tag_items_sync(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+) -> Result<Vec<ApiRawTagItem>> {
+  block_on(read_raw_tag_items(file_path))
+}
+
+#[napi]
+pub fn read_raw_tag_items_from_buffer_sync(buffer: Buffer) -> Result<Vec<ApiRawTagItem>> {
+  block_on(read_raw_tag_items_from_buffer(buffer))
+}
+
+#[napi]
+pub fn write_raw_tag_items_sync(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+  items: Vec<ApiRawTagItem>,
+  options: Option<ApiSaveOptions>,
+) -> Result<()> {
+  block_on(write_raw_tag_items(file_path, items, options))
+}
+
+#[napi]
+pub fn write_raw_tag_items_to_buffer_sync(
+  buffer: Buffer,
+  items: Vec<ApiRawTagItem>,
+) -> Result<Buffer> {
+  block_on(write_raw_tag_items_to_buffer(buffer, items))
+}
+
+#[napi]
+pub fn read_audio_properties_sync(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+) -> Result<ApiAudioProperties> {
+  block_on(read_audio_properties(file_path))
+}
+
+#[napi]
+pub fn read_audio_properties_from_buffer_sync(buffer: Buffer) -> Result<ApiAudioProperties> {
+  block_on(read_audio_properties_from_buffer(buffer))
+}
+
+#[napi]
+pub fn write_tags_sync(
+  #[napi(ts_arg_type = "string | Buffer | number | import('fs/promises').FileHandle")]
+  file: ApiFileSource,
+  tags: ApiAudioTags,
+  options: Option<ApiWriteOptions>,
+) -> Result<ApiWriteResult> {
+  block_on(write_tags(file, tags, options))
+}
+
+#[napi]
+pub fn write_tags_to_buffer_sync(
+  buffer: Buffer,
+  tags: ApiAudioTags,
+  options: Option<ApiWriteOptions>,
+) -> Result<Buffer> {
+  block_on(write_tags_to_buffer(buffer, tags, options))
+}
+
+#[napi]
+pub fn update_tags_sync(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+  patch: ApiTagPatch,
+  options: Option<ApiSaveOptions>,
+) -> Result<()> {
+  block_on(update_tags(file_path, patch, options))
+}
+
+#[napi]
+pub fn update_tags_to_buffer_sync(buffer: Buffer, patch: ApiTagPatch) -> Result<Buffer> {
+  block_on(update_tags_to_buffer(buffer, patch))
+}
+
+#[napi]
+pub fn transform_tags_sync(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+  rules: Vec<ApiTransformRule>,
+) -> Result<Vec<ApiTagChange>> {
+  block_on(transform_tags(file_path, rules))
+}
+
+#[napi]
+pub fn transform_tags_to_buffer_sync(
+  buffer: Buffer,
+  rules: Vec<ApiTransformRule>,
+) -> Result<Buffer> {
+  block_on(transform_tags_to_buffer(buffer, rules))
+}
+
+#[napi]
+pub fn diff_tags_sync(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+  tags: ApiAudioTags,
+  options: Option<ApiWriteOptions>,
+) -> Result<Vec<ApiTagChange>> {
+  block_on(diff_tags(file_path, tags, options))
+}
+
+#[napi]
+pub fn validate_tags_sync(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+  rules: Option<ApiValidationRules>,
+) -> Result<Vec<ApiValidationFinding>> {
+  block_on(validate_tags(file_path, rules))
+}
+
+#[napi]
+pub fn validate_tags_from_buffer_sync(
+  buffer: Buffer,
+  rules: Option<ApiValidationRules>,
+) -> Result<Vec<ApiValidationFinding>> {
+  block_on(validate_tags_from_buffer(buffer, rules))
+}
+
+#[napi]
+pub fn copy_tags_sync(
+  #[napi(ts_arg_type = "string | Buffer")] source_path: ApiPath,
+  #[napi(ts_arg_type = "string | Buffer")] dest_path: ApiPath,
+  options: Option<ApiWriteOptions>,
+) -> Result<()> {
+  block_on(copy_tags(source_path, dest_path, options))
+}
+
+#[napi]
+pub fn copy_tags_to_buffer_sync(
+  source: Buffer,
+  dest: Buffer,
+  options: Option<ApiWriteOptions>,
+) -> Result<Buffer> {
+  block_on(copy_tags_to_buffer(source, dest, options))
+}
+
+#[napi]
+pub fn clear_tags_sync(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+  options: Option<ApiClearOptions>,
+) -> Result<()> {
+  block_on(clear_tags(file_path, options))
+}
+
+#[napi]
+pub fn clear_tags_to_buffer_sync(
+  buffer: Buffer,
+  options: Option<ApiClearOptions>,
+) -> Result<Buffer> {
+  block_on(clear_tags_to_buffer(buffer, options))
+}
+
+#[napi]
+pub fn strip_tag_types_sync(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+  tag_types: Vec<ApiTagType>,
+  options: Option<ApiSaveOptions>,
+) -> Result<()> {
+  block_on(strip_tag_types(file_path, tag_types, options))
+}
+
+#[napi]
+pub fn strip_tag_types_to_buffer_sync(
+  buffer: Buffer,
+  tag_types: Vec<ApiTagType>,
+) -> Result<Buffer> {
+  block_on(strip_tag_types_to_buffer(buffer, tag_types))
+}
+
+#[napi]
+pub fn strip_all_tags_sync(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+  options: Option<ApiSaveOptions>,
+) -> Result<Vec<ApiTagType>> {
+  block_on(strip_all_tags(file_path, options))
+}
+
+#[napi]
+pub fn strip_all_tags_to_buffer_sync(buffer: Buffer) -> Result<ApiStripResult> {
+  block_on(strip_all_tags_to_buffer(buffer))
+}
+
+#[napi]
+pub fn read_cover_image_from_buffer_sync(
+  buffer: Buffer,
+  pic_type: Option<ApiAudioImageType>,
+) -> Result<Option<Buffer>> {
+  block_on(read_cover_image_from_buffer(buffer, pic_type))
+}
+
+#[napi]
+pub fn read_cover_image_from_file_sync(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+  pic_type: Option<ApiAudioImageType>,
+) -> Result<Option<Buffer>> {
+  block_on(read_cover_image_from_file(file_path, pic_type))
+}
+
+#[napi]
+pub fn read_image_info_sync(source: Either<Utf16String, Buffer>) -> Result<Vec<ApiImageInfo>> {
+  block_on(read_image_info(source))
+}
+
+#[napi]
+pub fn read_cover_image_data_url_sync(
+  source: Either<Utf16String, Buffer>,
+) -> Result<Option<String>> {
+  block_on(read_cover_image_data_url(source))
+}
+
+#[napi]
+pub fn read_cover_thumbnail_sync(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+  max_edge: Option<u32>,
+) -> Result<Option<Buffer>> {
+  block_on(read_cover_thumbnail(file_path, max_edge))
+}
+
+#[napi]
+pub fn read_primary_artwork_sync(source: Either<Utf16String, Buffer>) -> Result<Option<ApiImage>> {
+  block_on(read_primary_artwork(source))
+}
+
+#[napi]
+pub fn read_ixml_sync(source: Either<Utf16String, Buffer>) -> Result<Option<ApiIxml>> {
+  block_on(read_ixml(source))
+}
+
+#[napi]
+pub fn write_ixml_sync(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+  ixml: ApiIxml,
+  options: Option<ApiSaveOptions>,
+) -> Result<()> {
+  block_on(write_ixml(file_path, ixml, options))
+}
+
+#[napi]
+pub fn write_ixml_to_buffer_sync(buffer: Buffer, ixml: ApiIxml) -> Result<Buffer> {
+  block_on(write_ixml_to_buffer(buffer, ixml))
+}
+
+#[napi]
+pub fn probe_file_sync(source: Either<Utf16String, Buffer>) -> Result<ApiFileProbe> {
+  block_on(probe_file(source))
+}
+
+#[napi]
+pub fn write_cover_image_to_buffer_sync(
+  buffer: Buffer,
+  image_data: Buffer,
+  options: Option<ApiCoverImageOptions>,
+) -> Result<Buffer> {
+  block_on(write_cover_image_to_buffer(buffer, image_data, options))
+}
+
+#[napi]
+pub fn write_cover_image_to_file_sync(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+  image_data: Buffer,
+  options: Option<ApiCoverImageOptions>,
+) -> Result<()> {
+  block_on(write_cover_image_to_file(file_path, image_data, options))
+}
+
+#[napi]
+pub fn write_cover_image_from_path_sync(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+  #[napi(ts_arg_type = "string | Buffer")] image_path: ApiPath,
+  options: Option<ApiCoverImageOptions>,
+) -> Result<()> {
+  block_on(write_cover_image_from_path(file_path, image_path, options))
+}
+
+#[napi]
+pub fn remove_cover_image_from_buffer_sync(buffer: Buffer) -> Result<Buffer> {
+  block_on(remove_cover_image_from_buffer(buffer))
+}
+
+#[napi]
+pub fn remove_cover_image_from_file_sync(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+  options: Option<ApiSaveOptions>,
+) -> Result<()> {
+  block_on(remove_cover_image_from_file(file_path, options))
+}
+
+#[napi]
+pub fn remove_images_from_buffer_sync(
+  buffer: Buffer,
+  pic_types: Option<Vec<ApiAudioImageType>>,
+) -> Result<Buffer> {
+  block_on(remove_images_from_buffer(buffer, pic_types))
+}
+
+#[napi]
+pub fn remove_images_sync(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+  pic_types: Option<Vec<ApiAudioImageType>>,
+  options: Option<ApiSaveOptions>,
+) -> Result<()> {
+  block_on(remove_images(file_path, pic_types, options))
+}
+
+#[napi]
+pub fn undo_last_write_sync(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+  #[napi(ts_arg_type = "string | Buffer")] journal_dir: Option<ApiPath>,
+) -> Result<bool> {
+  block_on(undo_last_write(file_path, journal_dir))
+}
+
+#[napi]
+pub fn read_lyrics_sync(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+) -> Result<Option<String>> {
+  block_on(read_lyrics(file_path))
+}
+
+#[napi]
+pub fn read_lyrics_from_buffer_sync(buffer: Buffer) -> Result<Option<String>> {
+  block_on(read_lyrics_from_buffer(buffer))
+}
+
+#[napi]
+pub fn write_lyrics_sync(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+  lyrics: String,
+  options: Option<ApiSaveOptions>,
+) -> Result<()> {
+  block_on(write_lyrics(file_path, lyrics, options))
+}
+
+#[napi]
+pub fn write_lyrics_to_buffer_sync(buffer: Buffer, lyrics: String) -> Result<Buffer> {
+  block_on(write_lyrics_to_buffer(buffer, lyrics))
+}
+
+#[napi]
+pub fn read_synced_lyrics_sync(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+) -> Result<Option<ApiSyncedLyrics>> {
+  block_on(read_synced_lyrics(file_path))
+}
+
+#[napi]
+pub fn read_synced_lyrics_from_buffer_sync(buffer: Buffer) -> Result<Option<ApiSyncedLyrics>> {
+  block_on(read_synced_lyrics_from_buffer(buffer))
+}
+
+#[napi]
+pub fn write_synced_lyrics_sync(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+  lyrics: ApiSyncedLyrics,
+  options: Option<ApiSaveOptions>,
+) -> Result<()> {
+  block_on(write_synced_lyrics(file_path, lyrics, options))
+}
+
+#[napi]
+pub fn write_synced_lyrics_to_buffer_sync(
+  buffer: Buffer,
+  lyrics: ApiSyncedLyrics,
+) -> Result<Buffer> {
+  block_on(write_synced_lyrics_to_buffer(buffer, lyrics))
+}
+
+#[napi]
+pub fn read_chapters_sync(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+) -> Result<Vec<ApiChapter>> {
+  block_on(read_chapters(file_path))
+}
+
+#[napi]
+pub fn read_chapters_from_buffer_sync(buffer: Buffer) -> Result<Vec<ApiChapter>> {
+  block_on(read_chapters_from_buffer(buffer))
+}
+
+#[napi]
+pub fn write_chapters_sync(
+  #[napi(ts_arg_type = "string | Buffer")] file_path: ApiPath,
+  chapters: Vec<ApiChapter>,
+  options: Option<ApiSaveOptions>,
+) -> Result<()> {
+  block_on(write_chapters(file_path, chapters, options))
+}
+
+#[napi]
+pub fn write_chapters_to_buffer_sync(buffer: Buffer, chapters: Vec<ApiChapter>) -> Result<Buffer> {
+  block_on(write_chapters_to_buffer(buffer, chapters))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_path_from_string() {
+    let utf16 = |text: &str| Utf16String::from(text.encode_utf16().collect::<Vec<u16>>());
+    assert_eq!(
+      path_from_string(utf16("music/01 Tráck ☃.mp3")),
+      PathBuf::from("music/01 Tráck ☃.mp3")
+    );
+
+    // a lone surrogate, which NTFS allows in file names
+    let unpaired: Vec<u16> = "01 "
+      .encode_utf16()
+      .chain([0xD800])
+      .chain(".mp3".encode_utf16())
+      .collect();
+    let path = path_from_string(Utf16String::from(unpaired.clone()));
+    #[cfg(windows)]
+    {
+      use std::os::windows::ffi::OsStrExt;
+      assert_eq!(
+        path.as_os_str().encode_wide().collect::<Vec<u16>>(),
+        unpaired
+      );
+    }
+    #[cfg(not(windows))]
+    assert_eq!(path, PathBuf::from("01 \u{FFFD}.mp3"));
+  }
 }
@@ -0,0 +1,635 @@
+use crate::batch::{self, NoProgress};
+use crate::cancel::Cancellation;
+use crate::comments::Comment;
+use crate::export;
+use crate::patch::{TagField, TagPatch};
+use crate::scan::{self, ScanOptions};
+use crate::util::{self, AudioTags, Position};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+// How the rows of a manifest find their files
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum MatchBy {
+  // the filePath column
+  #[default]
+  Path,
+  // the isrc column, among the files under the root
+  Isrc,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ImportOptions {
+  // work out the changes without writing them
+  pub dry_run: bool,
+  pub match_by: MatchBy,
+  // the directory searched for ISRCs, recursively
+  pub root: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ImportResult {
+  // the row of the manifest, the first after the header being 1
+  pub row: usize,
+  // None when no file matched the row
+  pub file_path: Option<String>,
+  // the columns whose values differ from the file's, written unless dry run
+  pub changed: Vec<String>,
+  pub error: Option<String>,
+}
+
+// How the text of a column is read
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum ColumnKind {
+  Text,
+  // text values, "; " between them in CSV
+  List,
+  Number,
+  Decimal,
+  Flag,
+  // JSON, also in CSV
+  Json,
+  // exported but not written back
+  Info,
+}
+
+fn column_kind(column: &str) -> Option<ColumnKind> {
+  Some(match column {
+    "filePath" | "error" | "durationMs" | "bitrate" | "sampleRate" | "bitDepth" | "channels" => {
+      ColumnKind::Info
+    }
+    "artists" | "genres" | "albumArtists" => ColumnKind::List,
+    "year" | "track" | "trackTotal" | "disc" | "discTotal" | "rating" | "playCount"
+    | "movementNumber" => ColumnKind::Number,
+    "replayGainTrackGain"
+    | "replayGainTrackPeak"
+    | "replayGainAlbumGain"
+    | "replayGainAlbumPeak"
+    | "bpm" => ColumnKind::Decimal,
+    "compilation" => ColumnKind::Flag,
+    "comments" | "customFields" => ColumnKind::Json,
+    column
+      if export::tag_columns(&AudioTags::default())
+        .iter()
+        .any(|(name, _)| *name == column) =>
+    {
+      ColumnKind::Text
+    }
+    _ => return None,
+  })
+}
+
+// the field a column is cleared through; columns sharing one are cleared together
+fn column_field(column: &str) -> Option<TagField> {
+  Some(match column {
+    "title" => TagField::Title,
+    "subtitle" => TagField::Subtitle,
+    "artists" => TagField::Artists,
+    "album" => TagField::Album,
+    "year" => TagField::Year,
+    "genre" | "genres" => TagField::Genre,
+    "track" | "trackTotal" => TagField::Track,
+    "albumArtists" => TagField::AlbumArtists,
+    "comment" | "comments" => TagField::Comment,
+    "disc" | "discTotal" => TagField::Disc,
+    "discSubtitle" => TagField::DiscSubtitle,
+    "composer" => TagField::Composer,
+    "lyricist" => TagField::Lyricist,
+    "conductor" => TagField::Conductor,
+    "remixer" => TagField::Remixer,
+    "publisher" => TagField::Publisher,
+    "copyright" => TagField::Copyright,
+    "lyrics" => TagField::Lyrics,
+    "isrc" => TagField::Isrc,
+    "barcode" => TagField::Barcode,
+    "catalogNumber" => TagField::CatalogNumber,
+    "musicbrainzRecordingId" => TagField::MusicBrainzRecordingId,
+    "musicbrainzTrackId" => TagField::MusicBrainzTrackId,
+    "musicbrainzReleaseId" => TagField::MusicBrainzReleaseId,
+    "musicbrainzReleaseGroupId" => TagField::MusicBrainzReleaseGroupId,
+    "musicbrainzArtistId" => TagField::MusicBrainzArtistId,
+    "musicbrainzAlbumArtistId" => TagField::MusicBrainzAlbumArtistId,
+    "replayGainTrackGain"
+    | "replayGainTrackPeak"
+    | "replayGainAlbumGain"
+    | "replayGainAlbumPeak" => TagField::ReplayGain,
+    "bpm" => TagField::Bpm,
+    "initialKey" => TagField::InitialKey,
+    "mood" => TagField::Mood,
+    "rating" => TagField::Rating,
+    "playCount" => TagField::PlayCount,
+    "compilation" => TagField::Compilation,
+    "grouping" => TagField::Grouping,
+    "work" => TagField::Work,
+    "movement" => TagField::Movement,
+    "movementNumber" => TagField::MovementNumber,
+    "titleSort" => TagField::TitleSort,
+    "albumSort" => TagField::AlbumSort,
+    "artistSort" => TagField::ArtistSort,
+    "albumArtistSort" => TagField::AlbumArtistSort,
+    "composerSort" => TagField::ComposerSort,
+    "encoderSoftware" => TagField::EncoderSoftware,
+    "encoderSettings" => TagField::EncoderSettings,
+    // custom fields are only ever added to
+    _ => return None,
+  })
+}
+
+/**
+ * A value of a manifest as exported: JSON values as they are, the text of CSV
+ * cells parsed. Empty text and empty lists are null.
+ * @param column - The column of the value
+ * @param value - The value
+ */
+fn typed_value(column: &str, value: &Value) -> Result<Value, String> {
+  let kind = column_kind(column).ok_or_else(|| format!("Unknown column: {}", column))?;
+  let invalid = || format!("Invalid value for {}: {}", column, value);
+  let text = match value {
+    Value::Null => return Ok(Value::Null),
+    Value::String(text) if text.trim().is_empty() => return Ok(Value::Null),
+    Value::String(text) => Some(text.trim()),
+    _ => None,
+  };
+  Ok(match (kind, text) {
+    (ColumnKind::Info, _) => Value::Null,
+    (ColumnKind::Text, Some(text)) => json!(text),
+    (ColumnKind::List, Some(text)) => json!(text
+      .split(';')
+      .map(str::trim)
+      .filter(|item| !item.is_empty())
+      .collect::<Vec<_>>()),
+    (ColumnKind::List, None) => match value.as_array() {
+      Some(items) if items.is_empty() => Value::Null,
+      Some(items) if items.iter().all(Value::is_string) => value.clone(),
+      _ => return Err(invalid()),
+    },
+    (ColumnKind::Number, Some(text)) => json!(text.parse::<u64>().map_err(|_| invalid())?),
+    (ColumnKind::Number, None) if value.is_u64() => value.clone(),
+    (ColumnKind::Decimal, Some(text)) => json!(text.parse::<f64>().map_err(|_| invalid())?),
+    (ColumnKind::Decimal, None) => json!(value.as_f64().ok_or_else(invalid)?),
+    (ColumnKind::Flag, Some(text)) => json!(text.parse::<bool>().map_err(|_| invalid())?),
+    (ColumnKind::Flag, None) if value.is_boolean() => value.clone(),
+    (ColumnKind::Json, Some(text)) => serde_json::from_str(text).map_err(|_| invalid())?,
+    (ColumnKind::Json, None) => value.clone(),
+    _ => return Err(invalid()),
+  })
+}
+
+/**
+ * Set a column in a patch
+ * @param set - The fields the patch sets
+ * @param column - The column
+ * @param value - The typed value, not null
+ */
+fn set_column(set: &mut AudioTags, column: &str, value: Value) -> Result<(), String> {
+  fn from<T: serde::de::DeserializeOwned>(column: &str, value: Value) -> Result<T, String> {
+    serde_json::from_value(value).map_err(|e| format!("Invalid value for {}: {}", column, e))
+  }
+  let unset = || Position { no: None, of: None };
+  match column {
+    "title" => set.title = from(column, value)?,
+    "subtitle" => set.subtitle = from(column, value)?,
+    "artists" => set.artists = from(column, value)?,
+    "album" => set.album = from(column, value)?,
+    "year" => set.year = from(column, value)?,
+    "genre" => set.genre = from(column, value)?,
+    "genres" => set.genres = from(column, value)?,
+    "track" => set.track.get_or_insert_with(unset).no = from(column, value)?,
+    "trackTotal" => set.track.get_or_insert_with(unset).of = from(column, value)?,
+    "albumArtists" => set.album_artists = from(column, value)?,
+    "comment" => set.comment = from(column, value)?,
+    "comments" => {
+      let comments: Vec<Map<String, Value>> = from(column, value)?;
+      let text = |comment: &Map<String, Value>, key: &str| {
+        comment.get(key).and_then(Value::as_str).map(str::to_string)
+      };
+      set.comments = Some(
+        comments
+          .iter()
+          .map(|comment| Comment {
+            language: text(comment, "language"),
+            description: text(comment, "description"),
+            text: text(comment, "text").unwrap_or_default(),
+          })
+          .collect(),
+      );
+    }
+    "disc" => set.disc.get_or_insert_with(unset).no = from(column, value)?,
+    "discTotal" => set.disc.get_or_insert_with(unset).of = from(column, value)?,
+    "discSubtitle" => set.disc_subtitle = from(column, value)?,
+    "composer" => set.composer = from(column, value)?,
+    "lyricist" => set.lyricist = from(column, value)?,
+    "conductor" => set.conductor = from(column, value)?,
+    "remixer" => set.remixer = from(column, value)?,
+    "publisher" => set.publisher = from(column, value)?,
+    "copyright" => set.copyright = from(column, value)?,
+    "lyrics" => set.lyrics = from(column, value)?,
+    "isrc" => set.isrc = from(column, value)?,
+    "barcode" => set.barcode = from(column, value)?,
+    "catalogNumber" => set.catalog_number = from(column, value)?,
+    "musicbrainzRecordingId" => set.musicbrainz_recording_id = from(column, value)?,
+    "musicbrainzTrackId" => set.musicbrainz_track_id = from(column, value)?,
+    "musicbrainzReleaseId" => set.musicbrainz_release_id = from(column, value)?,
+    "musicbrainzReleaseGroupId" => set.musicbrainz_release_group_id = from(column, value)?,
+    "musicbrainzArtistId" => set.musicbrainz_artist_id = from(column, value)?,
+    "musicbrainzAlbumArtistId" => set.musicbrainz_album_artist_id = from(column, value)?,
+    "replayGainTrackGain" => set.replay_gain_track_gain = from(column, value)?,
+    "replayGainTrackPeak" => set.replay_gain_track_peak = from(column, value)?,
+    "replayGainAlbumGain" => set.replay_gain_album_gain = from(column, value)?,
+    "replayGainAlbumPeak" => set.replay_gain_album_peak = from(column, value)?,
+    "bpm" => set.bpm = from(column, value)?,
+    "initialKey" => set.initial_key = from(column, value)?,
+    "mood" => set.mood = from(column, value)?,
+    "rating" => set.rating = from(column, value)?,
+    "playCount" => set.play_count = from(column, value)?,
+    "compilation" => set.compilation = from(column, value)?,
+    "grouping" => set.grouping = from(column, value)?,
+    "work" => set.work = from(column, value)?,
+    "movement" => set.movement = from(column, value)?,
+    "movementNumber" => set.movement_number = from(column, value)?,
+    "titleSort" => set.title_sort = from(column, value)?,
+    "albumSort" => set.album_sort = from(column, value)?,
+    "artistSort" => set.artist_sort = from(column, value)?,
+    "albumArtistSort" => set.album_artist_sort = from(column, value)?,
+    "composerSort" => set.composer_sort = from(column, value)?,
+    "encoderSoftware" => set.encoder_software = from(column, value)?,
+    "encoderSettings" => set.encoder_settings = from(column, value)?,
+    "customFields" => set.custom_fields = from(column, value)?,
+    _ => {}
+  }
+  Ok(())
+}
+
+/**
+ * The patch turning the tags of a file into those of a manifest row. Only the
+ * fields that differ are written, along with the columns cleared together
+ * with them: a track number can't be removed without its total.
+ * @param row - The columns of the row; missing columns are kept, null ones cleared
+ * @param current - The tags of the file
+ * @returns The patch, and the columns that differ
+ */
+fn row_patch(
+  row: &Map<String, Value>,
+  current: &AudioTags,
+) -> Result<(TagPatch, Vec<String>), String> {
+  // in the order of the export
+  let mut wanted = Vec::new();
+  let mut changed = Vec::new();
+  for (column, current) in export::tag_columns(current) {
+    if let Some(value) = row.get(column) {
+      let value = typed_value(column, value)?;
+      if value != current {
+        changed.push(column.to_string());
+      }
+      wanted.push((column, value));
+    }
+  }
+  if let Some(column) = row.keys().find(|column| column_kind(column).is_none()) {
+    return Err(format!("Unknown column: {}", column));
+  }
+
+  let mut patch = TagPatch::default();
+  for (column, value) in wanted {
+    let field = column_field(column);
+    let touched = changed
+      .iter()
+      .any(|changed| changed == column || (field.is_some() && column_field(changed) == field));
+    if !touched {
+      continue;
+    }
+    match (value, field) {
+      (Value::Null, Some(field)) => {
+        if !patch.clear.contains(&field) {
+          patch.clear.push(field);
+        }
+      }
+      (Value::Null, None) => {}
+      (value, _) => set_column(&mut patch.set, column, value)?,
+    }
+  }
+  let set = &patch.set;
+  patch.clear.retain(|field| !field.is_set(set));
+  Ok((patch, changed))
+}
+
+// split CSV text into rows of cells (RFC 4180)
+fn csv_records(text: &str) -> Result<Vec<Vec<String>>, String> {
+  let mut records = Vec::new();
+  let mut record = Vec::new();
+  let mut cell = String::new();
+  let mut quoted = false;
+  let mut chars = text.chars().peekable();
+  while let Some(c) = chars.next() {
+    match (quoted, c) {
+      (true, '"') if chars.peek() == Some(&'"') => {
+        chars.next();
+        cell.push('"');
+      }
+      (true, '"') => quoted = false,
+      (true, c) => cell.push(c),
+      (false, '"') if cell.is_empty() => quoted = true,
+      (false, ',') => record.push(std::mem::take(&mut cell)),
+      (false, '\r') if chars.peek() == Some(&'\n') => {}
+      (false, '\n') => {
+        record.push(std::mem::take(&mut cell));
+        records.push(std::mem::take(&mut record));
+      }
+      (false, c) => cell.push(c),
+    }
+  }
+  if quoted {
+    return Err("Invalid CSV: unclosed quote".to_string());
+  }
+  if !cell.is_empty() || !record.is_empty() {
+    record.push(cell);
+    records.push(record);
+  }
+  Ok(records)
+}
+
+/**
+ * The rows of a manifest: a JSON array of objects, or CSV with a header row
+ * @param text - The manifest
+ */
+fn parse_manifest(text: &str) -> Result<Vec<Map<String, Value>>, String> {
+  let text = text.trim_start_matches('\u{feff}');
+  if text.trim_start().starts_with('[') {
+    let rows: Vec<Value> =
+      serde_json::from_str(text).map_err(|e| format!("Invalid JSON manifest: {}", e))?;
+    return rows
+      .into_iter()
+      .map(|row| match row {
+        Value::Object(row) => Ok(row),
+        _ => Err("Invalid JSON manifest: rows must be objects".to_string()),
+      })
+      .collect();
+  }
+  let mut records = csv_records(text)?.into_iter();
+  let header = records.next().unwrap_or_default();
+  records
+    // blank lines
+    .filter(|record| record.iter().any(|cell| !cell.is_empty()))
+    .map(|record| {
+      if record.len() != header.len() {
+        return Err(format!(
+          "Invalid CSV manifest: a row has {} cells for {} columns",
+          record.len(),
+          header.len()
+        ));
+      }
+      Ok(
+        header
+          .iter()
+          .cloned()
+          .zip(record.into_iter().map(Value::String))
+          .collect(),
+      )
+    })
+    .collect()
+}
+
+// an ISRC compared without its hyphens and case
+fn isrc_key(isrc: &str) -> String {
+  isrc.replace('-', "").trim().to_uppercase()
+}
+
+/**
+ * The files of the rows of a manifest
+ * @returns The row numbers with their file, or the error of a row no file matched
+ */
+async fn match_rows(
+  rows: &[Map<String, Value>],
+  options: &ImportOptions,
+) -> Result<Vec<(usize, Result<String, String>)>, String> {
+  let text = |row: &Map<String, Value>, column: &str| {
+    row
+      .get(column)
+      .and_then(Value::as_str)
+      .map(str::trim)
+      .filter(|value| !value.is_empty())
+      .map(str::to_string)
+  };
+  match options.match_by {
+    MatchBy::Path => Ok(
+      rows
+        .iter()
+        .enumerate()
+        .map(|(i, row)| {
+          let file_path = text(row, "filePath").ok_or_else(|| "No filePath".to_string());
+          (i + 1, file_path)
+        })
+        .collect(),
+    ),
+    MatchBy::Isrc => {
+      let root = options
+        .root
+        .clone()
+        .ok_or_else(|| "Matching by ISRC needs a root directory".to_string())?;
+      let scan_options = ScanOptions {
+        recursive: true,
+        ..Default::default()
+      };
+      let results =
+        scan::scan_directory(root, scan_options, &Cancellation::default(), NoProgress).await?;
+      let mut files: HashMap<String, Vec<String>> = HashMap::new();
+      for result in results {
+        if let Some(isrc) = result.tags.and_then(|tags| tags.isrc) {
+          files
+            .entry(isrc_key(&isrc))
+            .or_default()
+            .push(result.file_path);
+        }
+      }
+      let mut matches = Vec::new();
+      for (i, row) in rows.iter().enumerate() {
+        let Some(isrc) = text(row, "isrc") else {
+          matches.push((i + 1, Err("No isrc".to_string())));
+          continue;
+        };
+        match files.get(&isrc_key(&isrc)) {
+          Some(paths) => matches.extend(paths.iter().map(|path| (i + 1, Ok(path.clone())))),
+          None => matches.push((i + 1, Err(format!("No file with ISRC {}", isrc)))),
+        }
+      }
+      Ok(matches)
+    }
+  }
+}
+
+/**
+ * Apply the tags of a manifest, as written by `export_tags` and possibly
+ * edited, to the files its rows match. Columns missing from a row are kept,
+ * empty ones cleared, and only the fields that differ are written. A failing
+ * row doesn't stop the others.
+ * @param manifest_path - The manifest, JSON or CSV
+ * @param options - How rows find their files, and whether to write
+ * @returns One result per file matched, or per row that matched none
+ */
+pub async fn import_tags(
+  manifest_path: impl AsRef<Path>,
+  options: ImportOptions,
+) -> Result<Vec<ImportResult>, String> {
+  let text =
+    fs::read_to_string(&manifest_path).map_err(|e| format!("Failed to read manifest: {}", e))?;
+  let rows = parse_manifest(&text)?;
+  let matches = match_rows(&rows, &options).await?;
+  let dry_run = options.dry_run;
+  let work: Vec<_> = matches
+    .into_iter()
+    .map(|(row, file_path)| {
+      let mut columns = rows[row - 1].clone();
+      // the key the row was matched by, written as it is
+      if options.match_by == MatchBy::Isrc {
+        columns.remove("isrc");
+      }
+      (row, file_path, columns)
+    })
+    .collect();
+  let results = batch::map_concurrent(
+    work,
+    batch::default_concurrency(),
+    move |(row, file_path, columns)| async move {
+      let file_path = match file_path {
+        Ok(file_path) => file_path,
+        Err(error) => {
+          return ImportResult {
+            row,
+            error: Some(error),
+            ..Default::default()
+          }
+        }
+      };
+      let outcome = async {
+        let current = util::read_tags(file_path.clone()).await?;
+        let (patch, changed) = row_patch(&columns, &current)?;
+        if !dry_run && !changed.is_empty() {
+          util::update_tags(file_path.clone(), patch).await?;
+        }
+        Ok::<_, String>(changed)
+      }
+      .await;
+      match outcome {
+        Ok(changed) => ImportResult {
+          row,
+          file_path: Some(file_path),
+          changed,
+          error: None,
+        },
+        Err(error) => ImportResult {
+          row,
+          file_path: Some(file_path),
+          error: Some(error),
+          ..Default::default()
+        },
+      }
+    },
+  )
+  .await;
+  Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_support::{sample_mp3, write_fixture};
+
+  #[test]
+  fn test_parse_manifest() {
+    let csv = "filePath,title,artists\r\na.mp3,\"Say \"\"Hi\"\", again\",A; B\r\n\r\nb.mp3,,\r\n";
+    let rows = parse_manifest(csv).unwrap();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0]["title"], "Say \"Hi\", again");
+    assert_eq!(
+      typed_value("artists", &rows[0]["artists"]).unwrap(),
+      json!(["A", "B"])
+    );
+    assert_eq!(
+      typed_value("title", &rows[1]["title"]).unwrap(),
+      Value::Null
+    );
+    assert!(parse_manifest("filePath,title\na.mp3\n").is_err());
+
+    let rows = parse_manifest(r#"[{"filePath": "a.mp3", "year": 1999}]"#).unwrap();
+    assert_eq!(typed_value("year", &rows[0]["year"]).unwrap(), json!(1999));
+    assert!(typed_value("year", &json!("soon")).is_err());
+    assert!(typed_value("nope", &json!("x")).is_err());
+  }
+
+  #[tokio::test]
+  async fn test_import_tags() {
+    let dir = tempfile::tempdir().unwrap();
+    let tags = AudioTags {
+      title: Some("Old".to_string()),
+      album: Some("Album".to_string()),
+      comment: Some("Ripped".to_string()),
+      isrc: Some("USRC17607839".to_string()),
+      track: Some(Position {
+        no: Some(1),
+        of: Some(10),
+      }),
+      ..Default::default()
+    };
+    let tagged = util::write_tags_to_buffer(sample_mp3(), tags)
+      .await
+      .unwrap();
+    let path = write_fixture(dir.path(), "a.mp3", &tagged);
+    let manifest = format!(
+      "filePath,title,album,comment,track,trackTotal,durationMs\n{},New,Album,,2,10,1234\n{},,,,,,\n",
+      path,
+      dir.path().join("missing.mp3").to_string_lossy()
+    );
+    let manifest_path = write_fixture(dir.path(), "tags.csv", manifest.as_bytes());
+
+    let options = ImportOptions {
+      dry_run: true,
+      ..Default::default()
+    };
+    let results = import_tags(manifest_path.clone(), options).await.unwrap();
+    assert_eq!(results[0].changed, vec!["title", "track", "comment"]);
+    assert_eq!(results[0].error, None);
+    assert_eq!(results[1].row, 2);
+    assert!(results[1].error.is_some());
+    assert_eq!(
+      util::read_tags(path.clone()).await.unwrap().title,
+      Some("Old".to_string())
+    );
+
+    import_tags(manifest_path, ImportOptions::default())
+      .await
+      .unwrap();
+    let tags = util::read_tags(path.clone()).await.unwrap();
+    assert_eq!(tags.title, Some("New".to_string()));
+    assert_eq!(tags.comment, None);
+    assert_eq!(
+      tags.track,
+      Some(Position {
+        no: Some(2),
+        of: Some(10)
+      })
+    );
+
+    // by ISRC, a JSON manifest
+    let manifest = r#"[{"isrc": "US-RC1-76-07839", "album": "Found"}, {"isrc": "XX0000000000"}]"#;
+    let manifest_path = write_fixture(dir.path(), "tags.json", manifest.as_bytes());
+    let options = ImportOptions {
+      match_by: MatchBy::Isrc,
+      root: Some(dir.path().to_string_lossy().to_string()),
+      ..Default::default()
+    };
+    let results = import_tags(manifest_path, options).await.unwrap();
+    assert_eq!(results[0].file_path, Some(path.clone()));
+    assert_eq!(results[0].changed, vec!["album"]);
+    assert_eq!(results[1].file_path, None);
+    assert!(results[1]
+      .error
+      .as_ref()
+      .unwrap()
+      .starts_with("No file with ISRC"));
+    assert_eq!(
+      util::read_tags(path).await.unwrap().album,
+      Some("Found".to_string())
+    );
+  }
+}
@@ -0,0 +1,691 @@
+use crate::chapters::Chapter;
+use std::ops::Range;
+
+// the atoms on the way to the chapter data that hold other atoms, every other
+// atom is kept as is
+const CONTAINERS: [&[u8; 4]; 9] = [
+  b"moov", b"trak", b"mdia", b"minf", b"stbl", b"tref", b"udta", b"edts", b"dinf",
+];
+// the timescale of the chapter tracks written here
+const CHAPTER_TIMESCALE: u32 = 1000;
+// Nero chapter times are in units of 100 nanoseconds
+const NERO_UNITS_PER_MS: u64 = 10_000;
+// ISO-639-2/T "und", packed as three 5 bit letters
+const UNDETERMINED_LANGUAGE: u16 = 0x55C4;
+// the `encd` atom following the text of a sample, for UTF-8
+const UTF8_ENCODING: [u8; 12] = [0, 0, 0, 12, b'e', b'n', b'c', b'd', 0, 0, 1, 0];
+const IDENTITY_MATRIX: [u32; 9] = [0x0001_0000, 0, 0, 0, 0x0001_0000, 0, 0, 0, 0x4000_0000];
+
+fn invalid(what: &str) -> String {
+  format!("Invalid MP4 file: {}", what)
+}
+
+// Where an atom is in its parent
+struct Span {
+  kind: [u8; 4],
+  // the header included
+  range: Range<usize>,
+  header_len: usize,
+}
+
+impl Span {
+  fn content<'a>(&self, data: &'a [u8]) -> &'a [u8] {
+    &data[self.range.start + self.header_len..self.range.end]
+  }
+}
+
+// The atoms of `data`. Fewer than 8 bytes left are padding, which QuickTime
+// writes at the end of some atoms.
+fn atom_spans(data: &[u8]) -> Result<Vec<Span>, String> {
+  let mut spans = Vec::new();
+  let mut start = 0;
+  while data.len() - start >= 8 {
+    let header = &data[start..];
+    let kind: [u8; 4] = header[4..8].try_into().unwrap_or_default();
+    let (header_len, size) = match u32::from_be_bytes(header[0..4].try_into().unwrap_or_default()) {
+      // runs to the end of the data
+      0 => (8, header.len()),
+      1 => {
+        let size = header
+          .get(8..16)
+          .and_then(|size| size.try_into().ok())
+          .map(u64::from_be_bytes)
+          .ok_or_else(|| invalid("truncated atom header"))?;
+        (16, usize::try_from(size).unwrap_or(usize::MAX))
+      }
+      size => (8, size as usize),
+    };
+    if size < header_len || size > header.len() {
+      return Err(invalid(&format!(
+        "the {} atom doesn't fit",
+        String::from_utf8_lossy(&kind)
+      )));
+    }
+    spans.push(Span {
+      kind,
+      range: start..start + size,
+      header_len,
+    });
+    start += size;
+  }
+  Ok(spans)
+}
+
+// An atom of the moov tree: containers hold their children, other atoms their
+// content
+struct Atom {
+  kind: [u8; 4],
+  content: Vec<u8>,
+  children: Vec<Atom>,
+}
+
+impl Atom {
+  fn parse(kind: [u8; 4], content: &[u8]) -> Result<Self, String> {
+    if !CONTAINERS.contains(&&kind) {
+      return Ok(Self::leaf(&kind, content.to_vec()));
+    }
+    let children = atom_spans(content)?
+      .into_iter()
+      .map(|span| Self::parse(span.kind, span.content(content)))
+      .collect::<Result<_, _>>()?;
+    Ok(Self::container(&kind, children))
+  }
+
+  fn leaf(kind: &[u8; 4], content: Vec<u8>) -> Self {
+    Self {
+      kind: *kind,
+      content,
+      children: Vec::new(),
+    }
+  }
+
+  fn container(kind: &[u8; 4], children: Vec<Atom>) -> Self {
+    Self {
+      kind: *kind,
+      content: Vec::new(),
+      children,
+    }
+  }
+
+  fn child(&self, kind: &[u8; 4]) -> Option<&Atom> {
+    self.children.iter().find(|child| &child.kind == kind)
+  }
+
+  fn child_mut(&mut self, kind: &[u8; 4]) -> Option<&mut Atom> {
+    self.children.iter_mut().find(|child| &child.kind == kind)
+  }
+
+  fn path(&self, kinds: &[&[u8; 4]]) -> Option<&Atom> {
+    kinds.iter().try_fold(self, |atom, kind| atom.child(kind))
+  }
+
+  fn write(&self, out: &mut Vec<u8>) {
+    let start = out.len();
+    out.extend_from_slice(&[0; 4]);
+    out.extend_from_slice(&self.kind);
+    out.extend_from_slice(&self.content);
+    for child in &self.children {
+      child.write(out);
+    }
+    let size = (out.len() - start) as u32;
+    out[start..start + 4].copy_from_slice(&size.to_be_bytes());
+  }
+
+  fn to_bytes(&self) -> Vec<u8> {
+    let mut out = Vec::new();
+    self.write(&mut out);
+    out
+  }
+}
+
+fn u16_at(data: &[u8], at: usize) -> Option<u16> {
+  Some(u16::from_be_bytes(data.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn u32_at(data: &[u8], at: usize) -> Option<u32> {
+  Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn u64_at(data: &[u8], at: usize) -> Option<u64> {
+  Some(u64::from_be_bytes(data.get(at..at + 8)?.try_into().ok()?))
+}
+
+// a field of a full atom at `v0` in version 0 atoms, whose 32 bit times and
+// durations are 64 bit in version 1 atoms
+fn versioned_u32(atom: &Atom, v0: usize, v1: usize) -> Option<u32> {
+  u32_at(
+    &atom.content,
+    if atom.content.first()? == &1 { v1 } else { v0 },
+  )
+}
+
+fn track_id(trak: &Atom) -> Option<u32> {
+  versioned_u32(trak.child(b"tkhd")?, 12, 20)
+}
+
+fn handler_type(trak: &Atom) -> Option<&[u8]> {
+  trak.path(&[b"mdia", b"hdlr"])?.content.get(8..12)
+}
+
+fn traks(moov: &Atom) -> impl Iterator<Item = &Atom> {
+  moov.children.iter().filter(|child| &child.kind == b"trak")
+}
+
+// the ids of the tracks other tracks refer to as their chapters
+fn chapter_track_ids(moov: &Atom) -> Vec<u32> {
+  traks(moov)
+    .filter_map(|trak| trak.path(&[b"tref", b"chap"]))
+    .flat_map(|chap| chap.content.chunks_exact(4).filter_map(|id| u32_at(id, 0)))
+    .collect()
+}
+
+fn movie_duration_ms(moov: &Atom) -> Option<u32> {
+  let mvhd = moov.child(b"mvhd")?;
+  let timescale = versioned_u32(mvhd, 12, 20)?;
+  let duration = if mvhd.content.first()? == &1 {
+    u64_at(&mvhd.content, 24)?
+  } else {
+    u64::from(u32_at(&mvhd.content, 16)?)
+  };
+  (timescale != 0).then(|| (duration * 1000 / u64::from(timescale)) as u32)
+}
+
+// the file offsets of the samples of a track, from its sample table
+fn sample_offsets(stbl: &Atom, sizes: &[u32]) -> Option<Vec<usize>> {
+  let chunk_offsets: Vec<u64> = if let Some(stco) = stbl.child(b"stco") {
+    let count = u32_at(&stco.content, 4)? as usize;
+    (0..count)
+      .map(|i| u32_at(&stco.content, 8 + 4 * i).map(u64::from))
+      .collect::<Option<_>>()?
+  } else {
+    let co64 = stbl.child(b"co64")?;
+    let count = u32_at(&co64.content, 4)? as usize;
+    (0..count)
+      .map(|i| u64_at(&co64.content, 8 + 8 * i))
+      .collect::<Option<_>>()?
+  };
+  let stsc = &stbl.child(b"stsc")?.content;
+  let runs: Vec<(u32, u32)> = (0..u32_at(stsc, 4)? as usize)
+    .map(|i| Some((u32_at(stsc, 8 + 12 * i)?, u32_at(stsc, 12 + 12 * i)?)))
+    .collect::<Option<_>>()?;
+
+  let mut offsets = Vec::with_capacity(sizes.len());
+  for (chunk, chunk_offset) in chunk_offsets.into_iter().enumerate() {
+    let per_chunk = runs
+      .iter()
+      .rev()
+      .find(|(first_chunk, _)| *first_chunk as usize <= chunk + 1)?
+      .1;
+    let mut offset = usize::try_from(chunk_offset).ok()?;
+    for _ in 0..per_chunk {
+      let size = *sizes.get(offsets.len())?;
+      offsets.push(offset);
+      offset += size as usize;
+    }
+  }
+  Some(offsets)
+}
+
+// the text of a chapter sample: a 16 bit length, then UTF-8 or UTF-16 text
+fn sample_text(sample: &[u8]) -> Option<String> {
+  let len = u16_at(sample, 0)? as usize;
+  let text = sample.get(2..2 + len)?;
+  if let Some(utf16) = text.strip_prefix(&[0xFE, 0xFF]) {
+    let units: Vec<u16> = utf16
+      .chunks_exact(2)
+      .map(|unit| u16::from_be_bytes([unit[0], unit[1]]))
+      .collect();
+    return Some(String::from_utf16_lossy(&units));
+  }
+  Some(String::from_utf8_lossy(text).to_string())
+}
+
+// the chapters of a QuickTime chapter track, one text sample per chapter
+fn track_chapters(data: &[u8], trak: &Atom) -> Option<Vec<Chapter>> {
+  let timescale = u64::from(versioned_u32(trak.path(&[b"mdia", b"mdhd"])?, 12, 20)?);
+  let stbl = trak.path(&[b"mdia", b"minf", b"stbl"])?;
+
+  let stsz = &stbl.child(b"stsz")?.content;
+  let (sample_size, count) = (u32_at(stsz, 4)?, u32_at(stsz, 8)? as usize);
+  let sizes: Vec<u32> = if sample_size != 0 {
+    vec![sample_size; count]
+  } else {
+    (0..count)
+      .map(|i| u32_at(stsz, 12 + 4 * i))
+      .collect::<Option<_>>()?
+  };
+
+  let stts = &stbl.child(b"stts")?.content;
+  let mut durations = Vec::with_capacity(count);
+  for i in 0..u32_at(stts, 4)? as usize {
+    let (run, duration) = (u32_at(stts, 8 + 8 * i)?, u32_at(stts, 12 + 8 * i)?);
+    durations.extend(std::iter::repeat_n(u64::from(duration), run as usize));
+  }
+
+  let offsets = sample_offsets(stbl, &sizes)?;
+  let to_ms = |time: u64| (time * 1000 / timescale.max(1)) as u32;
+  let mut time = 0;
+  let mut chapters = Vec::with_capacity(count);
+  for ((offset, size), duration) in offsets.iter().zip(&sizes).zip(durations) {
+    let sample = data.get(*offset..*offset + *size as usize)?;
+    chapters.push(Chapter {
+      start_ms: to_ms(time),
+      end_ms: to_ms(time + duration),
+      title: sample_text(sample).filter(|title| !title.is_empty()),
+      ..Default::default()
+    });
+    time += duration;
+  }
+  Some(chapters)
+}
+
+// the Nero chapters of the `chpl` atom, which only hold start times and titles
+fn nero_chapters(moov: &Atom) -> Option<Vec<Chapter>> {
+  let chpl = &moov.path(&[b"udta", b"chpl"])?.content;
+  let mut at = if chpl.first()? == &1 { 8 } else { 4 };
+  let count = *chpl.get(at)?;
+  at += 1;
+  let mut chapters: Vec<Chapter> = Vec::with_capacity(count.into());
+  for _ in 0..count {
+    let start = u64_at(chpl, at)?;
+    let len = *chpl.get(at + 8)? as usize;
+    let title = String::from_utf8_lossy(chpl.get(at + 9..at + 9 + len)?).to_string();
+    at += 9 + len;
+    chapters.push(Chapter {
+      start_ms: (start / NERO_UNITS_PER_MS) as u32,
+      title: Some(title).filter(|title| !title.is_empty()),
+      ..Default::default()
+    });
+  }
+  // a chapter ends where the next one starts, the last with the movie
+  let end = movie_duration_ms(moov).unwrap_or(0);
+  let starts: Vec<u32> = chapters.iter().skip(1).map(|c| c.start_ms).collect();
+  for (chapter, next_start) in chapters.iter_mut().zip(starts.into_iter().chain([end])) {
+    chapter.end_ms = next_start.max(chapter.start_ms);
+  }
+  Some(chapters)
+}
+
+fn read_moov(data: &[u8]) -> Result<(Atom, Range<usize>), String> {
+  let span = atom_spans(data)?
+    .into_iter()
+    .find(|span| &span.kind == b"moov")
+    .ok_or_else(|| invalid("no moov atom"))?;
+  let moov = Atom::parse(span.kind, span.content(data))?;
+  Ok((moov, span.range))
+}
+
+/**
+ * Read the chapters of an MP4 file: its QuickTime chapter track, as written by
+ * Apple, or else its Nero `chpl` atom
+ * @param data - The MP4 file
+ * @returns The chapters ordered by start time
+ */
+pub fn read_chapters(data: &[u8]) -> Result<Vec<Chapter>, String> {
+  let (moov, _) = read_moov(data)?;
+  let chapter_ids = chapter_track_ids(&moov);
+  let track = traks(&moov)
+    .filter(|trak| track_id(trak).is_some_and(|id| chapter_ids.contains(&id)))
+    .find_map(|trak| track_chapters(data, trak));
+  Ok(track.or_else(|| nero_chapters(&moov)).unwrap_or_default())
+}
+
+fn full_atom(kind: &[u8; 4], fields: &[u32]) -> Atom {
+  let mut content = vec![0; 4];
+  for field in fields {
+    content.extend_from_slice(&field.to_be_bytes());
+  }
+  Atom::leaf(kind, content)
+}
+
+// a disabled text track with one sample per chapter, stored in one chunk at
+// `chunk_offset`
+fn chapter_trak(
+  id: u32,
+  movie_duration: u32,
+  durations: &[u32],
+  sizes: &[u32],
+  chunk_offset: u32,
+) -> Atom {
+  let count = durations.len() as u32;
+  let duration: u32 = durations.iter().sum();
+
+  let mut tkhd = full_atom(b"tkhd", &[0, 0, id, 0, movie_duration, 0, 0, 0, 0]);
+  tkhd
+    .content
+    .extend(IDENTITY_MATRIX.iter().flat_map(|v| v.to_be_bytes()));
+  tkhd.content.extend_from_slice(&[0; 8]);
+
+  let mut mdhd = full_atom(b"mdhd", &[0, 0, CHAPTER_TIMESCALE, duration]);
+  mdhd
+    .content
+    .extend_from_slice(&UNDETERMINED_LANGUAGE.to_be_bytes());
+  mdhd.content.extend_from_slice(&[0; 2]);
+
+  let mut hdlr = full_atom(b"hdlr", &[0]);
+  hdlr.content.extend_from_slice(b"text");
+  hdlr.content.extend_from_slice(&[0; 13]);
+
+  // the QuickTime text media header: graphics mode and colors, then a matrix
+  let mut gmin = full_atom(b"gmin", &[]);
+  gmin
+    .content
+    .extend_from_slice(&[0, 0x40, 0x80, 0, 0x80, 0, 0x80, 0, 0, 0, 0, 0]);
+  let text = Atom::leaf(
+    b"text",
+    IDENTITY_MATRIX
+      .iter()
+      .flat_map(|v| v.to_be_bytes())
+      .collect(),
+  );
+  let gmhd = Atom::container(b"gmhd", vec![gmin, text]);
+
+  let mut dref = full_atom(b"dref", &[1]);
+  // the samples are in this file
+  dref
+    .content
+    .extend_from_slice(&[0, 0, 0, 12, b'u', b'r', b'l', b' ', 0, 0, 0, 1]);
+  let dinf = Atom::container(b"dinf", vec![dref]);
+
+  // a text sample description with default display settings
+  let mut stsd = full_atom(b"stsd", &[1]);
+  let mut description = vec![0, 0, 0, 0, 0, 0, 0, 1];
+  description.extend_from_slice(&[0; 44]);
+  Atom::leaf(b"text", description).write(&mut stsd.content);
+
+  let stts_fields: Vec<u32> = std::iter::once(count)
+    .chain(durations.iter().flat_map(|duration| [1, *duration]))
+    .collect();
+  let stsz_fields: Vec<u32> = [0, count]
+    .into_iter()
+    .chain(sizes.iter().copied())
+    .collect();
+  let stbl = Atom::container(
+    b"stbl",
+    vec![
+      stsd,
+      full_atom(b"stts", &stts_fields),
+      full_atom(b"stsc", &[1, 1, count, 1]),
+      full_atom(b"stsz", &stsz_fields),
+      full_atom(b"stco", &[1, chunk_offset]),
+    ],
+  );
+
+  let minf = Atom::container(b"minf", vec![gmhd, dinf, stbl]);
+  let mdia = Atom::container(b"mdia", vec![mdhd, hdlr, minf]);
+  Atom::container(b"trak", vec![tkhd, mdia])
+}
+
+// add `delta` to the chunk offsets of a track that point past `from`
+fn shift_chunk_offsets(trak: &mut Atom, from: usize, delta: i64) -> Result<(), String> {
+  let Some(stbl) = trak
+    .child_mut(b"mdia")
+    .and_then(|mdia| mdia.child_mut(b"minf"))
+    .and_then(|minf| minf.child_mut(b"stbl"))
+  else {
+    return Ok(());
+  };
+  let overflow = || "The MP4 file is too large for its chunk offsets".to_string();
+  for atom in stbl.children.iter_mut() {
+    let width = match &atom.kind {
+      b"stco" => 4,
+      b"co64" => 8,
+      _ => continue,
+    };
+    let count = u32_at(&atom.content, 4).unwrap_or(0) as usize;
+    for i in 0..count {
+      let at = 8 + width * i;
+      let Some(entry) = atom.content.get_mut(at..at + width) else {
+        break;
+      };
+      let offset = if width == 4 {
+        u64::from(u32_at(entry, 0).unwrap_or(0))
+      } else {
+        u64_at(entry, 0).unwrap_or(0)
+      };
+      if offset < from as u64 {
+        continue;
+      }
+      let shifted = offset.checked_add_signed(delta).ok_or_else(overflow)?;
+      if width == 4 {
+        let shifted = u32::try_from(shifted).map_err(|_| overflow())?;
+        entry.copy_from_slice(&shifted.to_be_bytes());
+      } else {
+        entry.copy_from_slice(&shifted.to_be_bytes());
+      }
+    }
+  }
+  Ok(())
+}
+
+// the text samples of the chapters and their durations in milliseconds; the
+// samples run back to back from the start, so the first chapter starts at 0
+fn chapter_samples(chapters: &[Chapter]) -> (Vec<Vec<u8>>, Vec<u32>) {
+  let ends = chapters
+    .iter()
+    .skip(1)
+    .map(|chapter| chapter.start_ms)
+    .chain(chapters.last().map(|chapter| chapter.end_ms));
+  let mut start = 0;
+  let mut durations = Vec::with_capacity(chapters.len());
+  for end in ends {
+    durations.push(end.saturating_sub(start));
+    start = start.max(end);
+  }
+  let samples = chapters
+    .iter()
+    .map(|chapter| {
+      let title = chapter.title.as_deref().unwrap_or_default().as_bytes();
+      let title = &title[..title.len().min(u16::MAX.into())];
+      let mut sample = (title.len() as u16).to_be_bytes().to_vec();
+      sample.extend_from_slice(title);
+      sample.extend_from_slice(&UTF8_ENCODING);
+      sample
+    })
+    .collect();
+  (samples, durations)
+}
+
+/**
+ * Store chapters in an MP4 file as a QuickTime chapter track, which Apple
+ * players and most others read. The chapter tracks and Nero chapters already
+ * present are dropped, and the samples of the new track go in an `mdat` atom
+ * appended to the file. A chapter track has no gaps: the first chapter starts
+ * at 0 and each one ends where the next one starts.
+ * @param data - The MP4 file
+ * @param chapters - The chapters to store, no chapters only removes them
+ * @returns The updated file
+ */
+pub fn write_chapters(data: Vec<u8>, chapters: &[Chapter]) -> Result<Vec<u8>, String> {
+  let (mut moov, span) = read_moov(&data)?;
+
+  let old_ids = chapter_track_ids(&moov);
+  moov.children.retain(|child| {
+    &child.kind != b"trak" || !track_id(child).is_some_and(|id| old_ids.contains(&id))
+  });
+  for trak in moov
+    .children
+    .iter_mut()
+    .filter(|child| &child.kind == b"trak")
+  {
+    if let Some(tref) = trak.child_mut(b"tref") {
+      tref.children.retain(|child| &child.kind != b"chap");
+    }
+    trak
+      .children
+      .retain(|child| &child.kind != b"tref" || !child.children.is_empty());
+  }
+  if let Some(udta) = moov.child_mut(b"udta") {
+    udta.children.retain(|child| &child.kind != b"chpl");
+  }
+
+  let mut chapters = chapters.to_vec();
+  chapters.sort_by_key(|chapter| chapter.start_ms);
+  let (samples, durations) = chapter_samples(&chapters);
+  let mut mdat = Vec::new();
+  let mut new_trak = None;
+  if !chapters.is_empty() {
+    let mvhd = moov
+      .child_mut(b"mvhd")
+      .ok_or_else(|| invalid("no mvhd atom"))?;
+    let next_id_at = if mvhd.content.first() == Some(&1) {
+      108
+    } else {
+      96
+    };
+    let id = u32_at(&mvhd.content, next_id_at).ok_or_else(|| invalid("truncated mvhd atom"))?;
+    mvhd.content[next_id_at..next_id_at + 4].copy_from_slice(&(id + 1).to_be_bytes());
+    let timescale = u64::from(versioned_u32(mvhd, 12, 20).unwrap_or(CHAPTER_TIMESCALE));
+
+    let audio = moov
+      .children
+      .iter_mut()
+      .filter(|child| &child.kind == b"trak")
+      .find(|trak| handler_type(trak) == Some(b"soun"))
+      .ok_or("The MP4 file has no audio track to add chapters to")?;
+    let chap = Atom::leaf(b"chap", id.to_be_bytes().to_vec());
+    match audio.child_mut(b"tref") {
+      Some(tref) => tref.children.push(chap),
+      None => {
+        let at = audio
+          .children
+          .iter()
+          .position(|child| &child.kind == b"tkhd");
+        let at = at.map_or(0, |at| at + 1);
+        audio
+          .children
+          .insert(at, Atom::container(b"tref", vec![chap]));
+      }
+    }
+
+    let total: u64 = durations.iter().map(|duration| u64::from(*duration)).sum();
+    let movie_duration = u32::try_from(total * timescale / 1000).unwrap_or(u32::MAX);
+    let sizes: Vec<u32> = samples.iter().map(|sample| sample.len() as u32).collect();
+    new_trak = Some(chapter_trak(id, movie_duration, &durations, &sizes, 0));
+
+    mdat.extend_from_slice(&((8 + sizes.iter().sum::<u32>()).to_be_bytes()));
+    mdat.extend_from_slice(b"mdat");
+    for sample in samples {
+      mdat.extend(sample);
+    }
+  }
+
+  // the audio data after the moov atom moves by as much as the atom grows
+  let chapter_bytes = new_trak.as_ref().map_or(0, |trak| trak.to_bytes().len());
+  let delta = (moov.to_bytes().len() + chapter_bytes) as i64 - span.len() as i64;
+  for trak in moov
+    .children
+    .iter_mut()
+    .filter(|child| &child.kind == b"trak")
+  {
+    shift_chunk_offsets(trak, span.end, delta)?;
+  }
+  if let Some(mut trak) = new_trak {
+    let moov_len = moov.to_bytes().len() + chapter_bytes;
+    let chunk_offset = u32::try_from(span.start + moov_len + (data.len() - span.end) + 8)
+      .map_err(|_| "The MP4 file is too large for a chapter track".to_string())?;
+    let stco = trak
+      .child_mut(b"mdia")
+      .and_then(|mdia| mdia.child_mut(b"minf"))
+      .and_then(|minf| minf.child_mut(b"stbl"))
+      .and_then(|stbl| stbl.child_mut(b"stco"))
+      .ok_or_else(|| invalid("chapter track without chunk offsets"))?;
+    stco.content[8..12].copy_from_slice(&chunk_offset.to_be_bytes());
+    let at = moov
+      .children
+      .iter()
+      .rposition(|child| &child.kind == b"trak")
+      .map_or(moov.children.len(), |at| at + 1);
+    moov.children.insert(at, trak);
+  }
+
+  let mut out = Vec::with_capacity(data.len() + chapter_bytes + mdat.len());
+  out.extend_from_slice(&data[..span.start]);
+  moov.write(&mut out);
+  out.extend_from_slice(&data[span.end..]);
+  out.extend(mdat);
+  Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_support::sample_m4a;
+
+  fn chapter(start_ms: u32, end_ms: u32, title: &str) -> Chapter {
+    Chapter {
+      start_ms,
+      end_ms,
+      title: Some(title.to_string()),
+      ..Default::default()
+    }
+  }
+
+  // the first chunk offset of the audio track
+  fn audio_offset(data: &[u8]) -> u32 {
+    let (moov, _) = read_moov(data).unwrap();
+    let trak = traks(&moov)
+      .find(|trak| handler_type(trak) == Some(b"soun"))
+      .unwrap();
+    let stco = trak.path(&[b"mdia", b"minf", b"stbl", b"stco"]).unwrap();
+    u32_at(&stco.content, 8).unwrap()
+  }
+
+  #[test]
+  fn test_chapter_track_round_trip() {
+    let m4a = sample_m4a();
+    assert_eq!(read_chapters(&m4a).unwrap(), vec![]);
+    let audio = audio_offset(&m4a) as usize;
+
+    let chapters = vec![
+      chapter(0, 1500, "Intro"),
+      chapter(1500, 4000, "Ünïcode"),
+      chapter(4000, 5000, ""),
+    ];
+    let written = write_chapters(m4a.clone(), &chapters).unwrap();
+    let expected = vec![
+      chapter(0, 1500, "Intro"),
+      chapter(1500, 4000, "Ünïcode"),
+      Chapter {
+        title: None,
+        ..chapter(4000, 5000, "")
+      },
+    ];
+    assert_eq!(read_chapters(&written).unwrap(), expected);
+    // the moov atom comes first, the audio moved along with it
+    let moved = audio_offset(&written) as usize;
+    assert_eq!(written[moved..moved + 4], m4a[audio..audio + 4]);
+
+    // writing again replaces the track
+    let rewritten = write_chapters(written, &[chapter(0, 5000, "Only")]).unwrap();
+    assert_eq!(
+      read_chapters(&rewritten).unwrap(),
+      vec![chapter(0, 5000, "Only")]
+    );
+    let (moov, _) = read_moov(&rewritten).unwrap();
+    assert_eq!(traks(&moov).count(), 2);
+
+    let removed = write_chapters(rewritten, &[]).unwrap();
+    assert_eq!(read_chapters(&removed).unwrap(), vec![]);
+    let (moov, _) = read_moov(&removed).unwrap();
+    assert_eq!(traks(&moov).count(), 1);
+    assert_eq!(audio_offset(&removed), audio as u32);
+  }
+
+  #[test]
+  fn test_nero_chapters() {
+    let mut chpl = vec![1, 0, 0, 0, 0, 0, 0, 0, 2];
+    for (start, title) in [(0u64, "One"), (25_000_000, "Two")] {
+      chpl.extend_from_slice(&start.to_be_bytes());
+      chpl.push(title.len() as u8);
+      chpl.extend_from_slice(title.as_bytes());
+    }
+    let mvhd = full_atom(b"mvhd", &[0, 0, 1000, 5000]);
+    let udta = Atom::container(b"udta", vec![Atom::leaf(b"chpl", chpl)]);
+    let moov = Atom::container(b"moov", vec![mvhd, udta]);
+    assert_eq!(
+      nero_chapters(&moov),
+      Some(vec![chapter(0, 2500, "One"), chapter(2500, 5000, "Two")])
+    );
+  }
+}
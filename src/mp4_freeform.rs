@@ -0,0 +1,147 @@
+use lofty::config::{ParseOptions, WriteOptions};
+use lofty::error::LoftyError;
+use lofty::file::AudioFile;
+use lofty::io::{FileLike, Length, Truncate};
+use lofty::mp4::{Atom, AtomData, AtomIdent, DataType, Mp4File};
+use lofty::tag::TagExt;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::io::{Read, Seek};
+
+// the namespace of the freeform atoms written by iTunes and most taggers
+const ITUNES_MEAN: &str = "com.apple.iTunes";
+
+// the atom of a field name: a freeform atom of the iTunes namespace, or of the
+// namespace given as "----:mean:name"
+fn ident(name: &str) -> AtomIdent<'static> {
+  let (mean, name) = name
+    .strip_prefix("----:")
+    .and_then(|full| full.split_once(':'))
+    .unwrap_or((ITUNES_MEAN, name));
+  AtomIdent::Freeform {
+    mean: Cow::Owned(mean.to_string()),
+    name: Cow::Owned(name.to_string()),
+  }
+}
+
+// the field name of a freeform atom, as customFields names it
+fn field_name(mean: &str, name: &str) -> String {
+  if mean == ITUNES_MEAN {
+    name.to_string()
+  } else {
+    format!("----:{}:{}", mean, name)
+  }
+}
+
+fn read_mp4<R: Read + Seek>(reader: &mut R) -> Result<Mp4File, String> {
+  reader
+    .rewind()
+    .map_err(|e| format!("Failed to read audio file: {}", e))?;
+  Mp4File::read_from(reader, ParseOptions::new().read_properties(false))
+    .map_err(|e| format!("Failed to read audio file: {}", e))
+}
+
+/**
+ * Read the freeform atoms of an MP4 file that hold binary data, e.g. iTunes'
+ * "Encoding Params". Freeform atoms holding text are custom fields.
+ * @param reader - The MP4 file
+ * @returns The data by field name, None when there are none
+ */
+pub(crate) fn read_mp4_freeform<R: Read + Seek>(
+  reader: &mut R,
+) -> Result<Option<BTreeMap<String, Vec<u8>>>, String> {
+  let mp4 = read_mp4(reader)?;
+  let mut atoms = BTreeMap::new();
+  for atom in mp4.ilst().into_iter().flatten() {
+    let AtomIdent::Freeform { mean, name } = atom.ident() else {
+      continue;
+    };
+    if let Some(AtomData::Unknown { data, .. }) = atom.data().next() {
+      atoms.insert(field_name(mean, name), data.clone());
+    }
+  }
+  Ok((!atoms.is_empty()).then_some(atoms))
+}
+
+/**
+ * Write binary freeform atoms to an MP4 file. Every listed atom replaces the
+ * atom of that name, empty data removes it, and atoms that are not listed are
+ * kept.
+ * @param file - The MP4 file
+ * @param atoms - The data by field name
+ * @returns Whether the file changed
+ */
+pub(crate) fn write_mp4_freeform<F>(
+  file: &mut F,
+  atoms: &BTreeMap<String, Vec<u8>>,
+  write_options: WriteOptions,
+) -> Result<bool, String>
+where
+  F: FileLike,
+  LoftyError: From<<F as Truncate>::Error>,
+  LoftyError: From<<F as Length>::Error>,
+{
+  let mp4 = read_mp4(file)?;
+  let mut ilst = mp4.ilst().cloned().unwrap_or_default();
+  let mut changed = false;
+  for (name, data) in atoms {
+    let ident = ident(name);
+    let current = ilst.get(&ident).and_then(|atom| match atom.data().next() {
+      Some(AtomData::Unknown { data, .. }) => Some(data.as_slice()),
+      _ => None,
+    });
+    if current == Some(data.as_slice()) || (data.is_empty() && ilst.get(&ident).is_none()) {
+      continue;
+    }
+    changed = true;
+    if data.is_empty() {
+      let _ = ilst.remove(&ident);
+    } else {
+      ilst.replace_atom(Atom::new(
+        ident,
+        AtomData::Unknown {
+          code: DataType::Reserved,
+          data: data.clone(),
+        },
+      ));
+    }
+  }
+  if !changed {
+    return Ok(false);
+  }
+  file
+    .rewind()
+    .map_err(|e| format!("Failed to write audio to buffer: {}", e))?;
+  ilst
+    .save_to(file, write_options)
+    .map_err(|e| format!("Failed to write audio to buffer: {}", e))?;
+  Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_field_names() {
+    assert_eq!(
+      ident("Encoding Params"),
+      AtomIdent::Freeform {
+        mean: Cow::Borrowed("com.apple.iTunes"),
+        name: Cow::Borrowed("Encoding Params"),
+      }
+    );
+    assert_eq!(
+      ident("----:com.example.app:state"),
+      AtomIdent::Freeform {
+        mean: Cow::Borrowed("com.example.app"),
+        name: Cow::Borrowed("state"),
+      }
+    );
+    assert_eq!(field_name("com.apple.iTunes", "iTunSMPB"), "iTunSMPB");
+    assert_eq!(
+      field_name("com.example.app", "state"),
+      "----:com.example.app:state"
+    );
+  }
+}
@@ -0,0 +1,92 @@
+use lofty::tag::{ItemKey, ItemValue, Tag, TagItem};
+use unicode_normalization::UnicodeNormalization;
+
+// zero-width spaces and joiners, the word joiner and the byte order mark,
+// which copy-pasted values carry invisibly
+const ZERO_WIDTH: [char; 5] = ['\u{200B}', '\u{200C}', '\u{200D}', '\u{2060}', '\u{FEFF}'];
+
+/**
+ * Normalize a value so that values looking the same are stored the same: NFC
+ * composed, without zero-width characters, runs of spaces collapsed to one and
+ * trimmed. Line breaks, e.g. of lyrics, are kept.
+ * @param text - The value
+ */
+pub fn normalize_text(text: &str) -> String {
+  let mut normalized = String::with_capacity(text.len());
+  for c in text.nfc().filter(|c| !ZERO_WIDTH.contains(c)) {
+    if c == ' ' && normalized.ends_with(' ') {
+      continue;
+    }
+    normalized.push(c);
+  }
+  normalized.trim().to_string()
+}
+
+/**
+ * Normalize the text items of a tag with one of the given keys. Items with a
+ * description, such as the iTunNORM comment of iTunes, hold data rather than
+ * text and are left alone.
+ * @param tag - The tag
+ * @param keys - The keys of the items to normalize
+ */
+pub fn normalize_items(tag: &mut Tag, keys: &[ItemKey]) {
+  let items: Vec<TagItem> = tag.items().cloned().collect();
+  tag.retain(|_| false);
+  for item in items {
+    let normalized = match item.value() {
+      ItemValue::Text(text) if keys.contains(item.key()) && item.description().is_empty() => {
+        normalize_text(text)
+      }
+      _ => {
+        tag.push_unchecked(item);
+        continue;
+      }
+    };
+    let mut normalized_item = TagItem::new(item.key().clone(), ItemValue::Text(normalized));
+    normalized_item.set_lang(*item.lang());
+    tag.push_unchecked(normalized_item);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use lofty::tag::{Accessor, TagType};
+
+  #[test]
+  fn test_normalize_text() {
+    // "é" as "e" and a combining acute accent
+    assert_eq!(normalize_text("Beyonce\u{301}"), "Beyoncé");
+    assert_eq!(normalize_text("  The  Beatles \t"), "The Beatles");
+    assert_eq!(normalize_text("Sig\u{200B}ur Ro\u{FEFF}s"), "Sigur Ros");
+    // removing a zero-width character leaves no double space
+    assert_eq!(normalize_text("Daft \u{200B} Punk"), "Daft Punk");
+    assert_eq!(
+      normalize_text("line one\n  line two"),
+      "line one\n line two"
+    );
+    assert_eq!(normalize_text("Already clean"), "Already clean");
+  }
+
+  #[test]
+  fn test_normalize_items() {
+    let mut tag = Tag::new(TagType::Id3v2);
+    tag.set_title(" Title ".to_string());
+    tag.set_album(" Album ".to_string());
+    let mut itunnorm = TagItem::new(
+      ItemKey::Comment,
+      ItemValue::Text(" 00000318 000002F3".to_string()),
+    );
+    itunnorm.set_description("iTunNORM".to_string());
+    tag.push_unchecked(itunnorm);
+
+    normalize_items(&mut tag, &[ItemKey::TrackTitle, ItemKey::Comment]);
+    assert_eq!(tag.title().as_deref(), Some("Title"));
+    // not among the keys
+    assert_eq!(tag.album().as_deref(), Some(" Album "));
+    assert_eq!(
+      tag.get_string(&ItemKey::Comment),
+      Some(" 00000318 000002F3")
+    );
+  }
+}
@@ -0,0 +1,348 @@
+use crate::rating;
+use crate::util::AudioTags;
+use lofty::picture::PictureType;
+use lofty::tag::{ItemKey, Tag, TagType};
+
+// A field of AudioTags that a patch can clear
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TagField {
+  Title,
+  Artists,
+  Album,
+  Year,
+  Genre,
+  Track,
+  AlbumArtists,
+  Comment,
+  Disc,
+  // the front covers only
+  Image,
+  // every picture
+  AllImages,
+  Composer,
+  Lyricist,
+  Conductor,
+  Remixer,
+  Publisher,
+  Copyright,
+  Lyrics,
+  Isrc,
+  Barcode,
+  CatalogNumber,
+  MusicBrainzRecordingId,
+  MusicBrainzTrackId,
+  MusicBrainzReleaseId,
+  MusicBrainzReleaseGroupId,
+  MusicBrainzArtistId,
+  MusicBrainzAlbumArtistId,
+  // the track and album gains and peaks
+  ReplayGain,
+  Bpm,
+  InitialKey,
+  Mood,
+  Rating,
+  PlayCount,
+  Compilation,
+  Grouping,
+  Work,
+  Movement,
+  MovementNumber,
+  Subtitle,
+  DiscSubtitle,
+  TitleSort,
+  AlbumSort,
+  ArtistSort,
+  AlbumArtistSort,
+  ComposerSort,
+  EncoderSoftware,
+  EncoderSettings,
+}
+
+// A partial update: every field is kept, unless it has a value in `set` or is
+// listed in `clear`
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct TagPatch {
+  pub set: AudioTags,
+  pub clear: Vec<TagField>,
+}
+
+impl TagField {
+  pub const ALL: [TagField; 47] = [
+    Self::Title,
+    Self::Artists,
+    Self::Album,
+    Self::Year,
+    Self::Genre,
+    Self::Track,
+    Self::AlbumArtists,
+    Self::Comment,
+    Self::Disc,
+    Self::Image,
+    Self::AllImages,
+    Self::Composer,
+    Self::Lyricist,
+    Self::Conductor,
+    Self::Remixer,
+    Self::Publisher,
+    Self::Copyright,
+    Self::Lyrics,
+    Self::Isrc,
+    Self::Barcode,
+    Self::CatalogNumber,
+    Self::MusicBrainzRecordingId,
+    Self::MusicBrainzTrackId,
+    Self::MusicBrainzReleaseId,
+    Self::MusicBrainzReleaseGroupId,
+    Self::MusicBrainzArtistId,
+    Self::MusicBrainzAlbumArtistId,
+    Self::ReplayGain,
+    Self::Bpm,
+    Self::InitialKey,
+    Self::Mood,
+    Self::Rating,
+    Self::PlayCount,
+    Self::Compilation,
+    Self::Grouping,
+    Self::Work,
+    Self::Movement,
+    Self::MovementNumber,
+    Self::Subtitle,
+    Self::DiscSubtitle,
+    Self::TitleSort,
+    Self::AlbumSort,
+    Self::ArtistSort,
+    Self::AlbumArtistSort,
+    Self::ComposerSort,
+    Self::EncoderSoftware,
+    Self::EncoderSettings,
+  ];
+
+  // the items the field is stored in, in a tag of the given type
+  pub(crate) fn item_keys(self, tag_type: TagType) -> &'static [ItemKey] {
+    match self {
+      Self::Title => &[ItemKey::TrackTitle],
+      Self::Artists => &[ItemKey::TrackArtist, ItemKey::TrackArtists],
+      Self::Album => &[ItemKey::AlbumTitle],
+      Self::Year => &[ItemKey::Year, ItemKey::RecordingDate],
+      Self::Genre => &[ItemKey::Genre],
+      Self::Track => &[ItemKey::TrackNumber, ItemKey::TrackTotal],
+      Self::AlbumArtists => &[ItemKey::AlbumArtist],
+      Self::Comment => &[ItemKey::Comment],
+      Self::Disc => &[ItemKey::DiscNumber, ItemKey::DiscTotal],
+      // stored with the ratings, see rating::clear_rating
+      Self::Image | Self::AllImages | Self::Rating | Self::PlayCount => &[],
+      Self::Composer => &[ItemKey::Composer],
+      Self::Lyricist => &[ItemKey::Lyricist],
+      Self::Conductor => &[ItemKey::Conductor],
+      Self::Remixer => &[ItemKey::Remixer],
+      Self::Publisher => &[ItemKey::Publisher, ItemKey::Label],
+      Self::Copyright => &[ItemKey::CopyrightMessage],
+      Self::Lyrics => &[ItemKey::Lyrics],
+      Self::Isrc => &[ItemKey::Isrc],
+      Self::Barcode => &[ItemKey::Barcode],
+      Self::CatalogNumber => &[ItemKey::CatalogNumber],
+      Self::MusicBrainzRecordingId => &[ItemKey::MusicBrainzRecordingId],
+      Self::MusicBrainzTrackId => &[ItemKey::MusicBrainzTrackId],
+      Self::MusicBrainzReleaseId => &[ItemKey::MusicBrainzReleaseId],
+      Self::MusicBrainzReleaseGroupId => &[ItemKey::MusicBrainzReleaseGroupId],
+      Self::MusicBrainzArtistId => &[ItemKey::MusicBrainzArtistId],
+      Self::MusicBrainzAlbumArtistId => &[ItemKey::MusicBrainzReleaseArtistId],
+      Self::ReplayGain => &[
+        ItemKey::ReplayGainTrackGain,
+        ItemKey::ReplayGainTrackPeak,
+        ItemKey::ReplayGainAlbumGain,
+        ItemKey::ReplayGainAlbumPeak,
+      ],
+      Self::Bpm => &[ItemKey::Bpm, ItemKey::IntegerBpm],
+      Self::InitialKey => &[ItemKey::InitialKey],
+      Self::Mood => &[ItemKey::Mood],
+      Self::Compilation => &[ItemKey::FlagCompilation],
+      // ID3v2 follows Apple: GRP1 is the grouping and TIT1 the work
+      Self::Grouping if tag_type == TagType::Id3v2 => &[ItemKey::AppleId3v2ContentGroup],
+      Self::Grouping => &[ItemKey::ContentGroup],
+      Self::Work if tag_type == TagType::Id3v2 => &[ItemKey::ContentGroup, ItemKey::Work],
+      Self::Work => &[ItemKey::Work],
+      Self::Movement => &[ItemKey::Movement],
+      Self::MovementNumber => &[ItemKey::MovementNumber, ItemKey::MovementTotal],
+      Self::Subtitle => &[ItemKey::TrackSubtitle],
+      Self::DiscSubtitle => &[ItemKey::SetSubtitle],
+      Self::TitleSort => &[ItemKey::TrackTitleSortOrder],
+      Self::AlbumSort => &[ItemKey::AlbumTitleSortOrder],
+      Self::ArtistSort => &[ItemKey::TrackArtistSortOrder],
+      Self::AlbumArtistSort => &[ItemKey::AlbumArtistSortOrder],
+      Self::ComposerSort => &[ItemKey::ComposerSortOrder],
+      Self::EncoderSoftware => &[ItemKey::EncoderSoftware],
+      Self::EncoderSettings => &[ItemKey::EncoderSettings],
+    }
+  }
+
+  pub(crate) fn is_set(self, tags: &AudioTags) -> bool {
+    match self {
+      Self::Title => tags.title.is_some(),
+      Self::Artists => tags.artists.is_some(),
+      Self::Album => tags.album.is_some(),
+      Self::Year => tags.year.is_some(),
+      Self::Genre => tags.genre.is_some() || tags.genres.is_some(),
+      Self::Track => tags.track.is_some(),
+      Self::AlbumArtists => tags.album_artists.is_some(),
+      Self::Comment => tags.comment.is_some() || tags.comments.is_some(),
+      Self::Disc => tags.disc.is_some(),
+      Self::Image => tags.image.is_some(),
+      Self::AllImages => tags.all_images.is_some(),
+      Self::Composer => tags.composer.is_some(),
+      Self::Lyricist => tags.lyricist.is_some(),
+      Self::Conductor => tags.conductor.is_some(),
+      Self::Remixer => tags.remixer.is_some(),
+      Self::Publisher => tags.publisher.is_some(),
+      Self::Copyright => tags.copyright.is_some(),
+      Self::Lyrics => tags.lyrics.is_some(),
+      Self::Isrc => tags.isrc.is_some(),
+      Self::Barcode => tags.barcode.is_some(),
+      Self::CatalogNumber => tags.catalog_number.is_some(),
+      Self::MusicBrainzRecordingId => tags.musicbrainz_recording_id.is_some(),
+      Self::MusicBrainzTrackId => tags.musicbrainz_track_id.is_some(),
+      Self::MusicBrainzReleaseId => tags.musicbrainz_release_id.is_some(),
+      Self::MusicBrainzReleaseGroupId => tags.musicbrainz_release_group_id.is_some(),
+      Self::MusicBrainzArtistId => tags.musicbrainz_artist_id.is_some(),
+      Self::MusicBrainzAlbumArtistId => tags.musicbrainz_album_artist_id.is_some(),
+      Self::ReplayGain => {
+        tags.replay_gain_track_gain.is_some()
+          || tags.replay_gain_track_peak.is_some()
+          || tags.replay_gain_album_gain.is_some()
+          || tags.replay_gain_album_peak.is_some()
+      }
+      Self::Bpm => tags.bpm.is_some(),
+      Self::InitialKey => tags.initial_key.is_some(),
+      Self::Mood => tags.mood.is_some(),
+      Self::Rating => tags.rating.is_some(),
+      Self::PlayCount => tags.play_count.is_some(),
+      Self::Compilation => tags.compilation.is_some(),
+      Self::Grouping => tags.grouping.is_some(),
+      Self::Work => tags.work.is_some(),
+      Self::Movement => tags.movement.is_some(),
+      Self::MovementNumber => tags.movement_number.is_some(),
+      Self::Subtitle => tags.subtitle.is_some(),
+      Self::DiscSubtitle => tags.disc_subtitle.is_some(),
+      Self::TitleSort => tags.title_sort.is_some(),
+      Self::AlbumSort => tags.album_sort.is_some(),
+      Self::ArtistSort => tags.artist_sort.is_some(),
+      Self::AlbumArtistSort => tags.album_artist_sort.is_some(),
+      Self::ComposerSort => tags.composer_sort.is_some(),
+      Self::EncoderSoftware => tags.encoder_software.is_some(),
+      Self::EncoderSettings => tags.encoder_settings.is_some(),
+    }
+  }
+}
+
+impl TagPatch {
+  /**
+   * Make sure no field is both set and cleared
+   */
+  pub fn validate(&self) -> Result<(), String> {
+    match self.clear.iter().find(|field| field.is_set(&self.set)) {
+      Some(field) => Err(format!("{:?} is both set and cleared", field)),
+      None => Ok(()),
+    }
+  }
+
+  /**
+   * Remove the cleared fields from a tag, the set fields are written afterwards
+   * @param primary_tag - The tag being updated
+   */
+  pub fn clear_fields(&self, primary_tag: &mut Tag) {
+    for field in &self.clear {
+      for key in field.item_keys(primary_tag.tag_type()) {
+        primary_tag.remove_key(key);
+      }
+      let clears_picture = |picture_type: PictureType| match field {
+        TagField::AllImages => true,
+        TagField::Image => picture_type == PictureType::CoverFront,
+        _ => false,
+      };
+      match field {
+        TagField::Rating => rating::clear_rating(primary_tag, true, false),
+        TagField::PlayCount => rating::clear_rating(primary_tag, false, true),
+        _ => {}
+      }
+      let len = primary_tag.pictures().len();
+      for i in (0..len).rev() {
+        if clears_picture(primary_tag.pictures()[i].pic_type()) {
+          primary_tag.remove_picture(i);
+        }
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_support::{sample_mp3, write_fixture};
+  use crate::util::{self, AudioImageType, Image};
+
+  #[test]
+  fn test_validate_rejects_conflicting_fields() {
+    let patch = TagPatch {
+      set: AudioTags {
+        comment: Some("Comment".to_string()),
+        ..Default::default()
+      },
+      clear: vec![TagField::Title, TagField::Comment],
+    };
+    assert_eq!(
+      patch.validate().unwrap_err(),
+      "Comment is both set and cleared"
+    );
+    assert!(TagPatch::default().validate().is_ok());
+  }
+
+  #[tokio::test]
+  async fn test_update_tags_keeps_sets_and_clears() {
+    let dir = tempfile::tempdir().unwrap();
+    let cover = Image {
+      data: vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10],
+      pic_type: AudioImageType::CoverFront,
+      ..Default::default()
+    };
+    let back = Image {
+      data: vec![0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A],
+      pic_type: AudioImageType::CoverBack,
+      ..Default::default()
+    };
+    let tags = AudioTags {
+      title: Some("Title".to_string()),
+      album: Some("Album".to_string()),
+      comment: Some("Ripped by someone".to_string()),
+      year: Some(2020),
+      all_images: Some(vec![cover, back.clone()]),
+      ..Default::default()
+    };
+    let buffer = util::write_tags_to_buffer(sample_mp3(), tags)
+      .await
+      .unwrap();
+    let path = write_fixture(dir.path(), "song.mp3", &buffer);
+
+    let patch = TagPatch {
+      set: AudioTags {
+        title: Some("New Title".to_string()),
+        ..Default::default()
+      },
+      clear: vec![TagField::Comment, TagField::Year, TagField::Image],
+    };
+    util::update_tags(path.clone(), patch).await.unwrap();
+
+    let tags = util::read_tags(path).await.unwrap();
+    assert_eq!(tags.title, Some("New Title".to_string()));
+    assert_eq!(tags.album, Some("Album".to_string()));
+    assert_eq!(tags.comment, None);
+    assert_eq!(tags.year, None);
+    assert_eq!(tags.image, None);
+    let pictures: Vec<Vec<u8>> = tags
+      .all_images
+      .unwrap()
+      .into_iter()
+      .map(|image| image.data)
+      .collect();
+    assert_eq!(pictures, vec![back.data]);
+  }
+}
@@ -0,0 +1,350 @@
+use crate::comments;
+use crate::util::Id3TextEncoding;
+use image::ImageFormat;
+use lofty::config::WriteOptions;
+use lofty::error::LoftyError;
+use lofty::id3::v2::{AttachedPictureFrame, Frame, Id3v2Tag};
+use lofty::io::{FileLike, Length, Truncate};
+use lofty::picture::{MimeType, Picture, PictureInformation};
+use lofty::tag::{ItemKey, ItemValue, Tag, TagExt, TagItem, TagType};
+use lofty::TextEncoding;
+use std::io::Cursor;
+
+const FLAC_PICTURE_KEY: &str = "METADATA_BLOCK_PICTURE";
+
+// The image format of MP4 `covr` atoms
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum Mp4CoverFormat {
+  // store the images as given
+  #[default]
+  Keep,
+  Jpeg,
+  Png,
+}
+
+// Where FLAC files keep their pictures
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum FlacPictureStorage {
+  // PICTURE metadata blocks
+  #[default]
+  PictureBlock,
+  // base64 METADATA_BLOCK_PICTURE fields of the Vorbis comments
+  VorbisComment,
+}
+
+// The text encoding of ID3v2 APIC frames, which applies to the description
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum Id3PictureEncoding {
+  #[default]
+  Utf8,
+  Utf16,
+  // descriptions outside of Latin-1 fall back to UTF-16
+  Latin1,
+}
+
+// How pictures are stored in each format, for devices that only read one convention
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ArtworkPolicy {
+  pub mp4_cover_format: Mp4CoverFormat,
+  pub flac_picture_storage: FlacPictureStorage,
+  pub id3_picture_encoding: Id3PictureEncoding,
+}
+
+fn transcode(
+  picture: &Picture,
+  format: ImageFormat,
+  mime_type: MimeType,
+) -> Result<Picture, String> {
+  if picture.mime_type() == Some(&mime_type) {
+    return Ok(picture.clone());
+  }
+  let image = image::load_from_memory(picture.data())
+    .map_err(|e| format!("Failed to decode picture: {}", e))?;
+  // JPEG has no alpha channel
+  let image = match format {
+    ImageFormat::Jpeg => image::DynamicImage::ImageRgb8(image.to_rgb8()),
+    _ => image,
+  };
+  let mut out = Cursor::new(Vec::new());
+  image
+    .write_to(&mut out, format)
+    .map_err(|e| format!("Failed to encode picture: {}", e))?;
+  Ok(Picture::new_unchecked(
+    picture.pic_type(),
+    Some(mime_type),
+    picture.description().map(|s| s.to_string()),
+    out.into_inner(),
+  ))
+}
+
+fn replace_pictures<F>(primary_tag: &mut Tag, mut f: F) -> Result<(), String>
+where
+  F: FnMut(&Picture) -> Result<Picture, String>,
+{
+  for i in 0..primary_tag.pictures().len() {
+    let picture = f(&primary_tag.pictures()[i])?;
+    primary_tag.set_picture(i, picture);
+  }
+  Ok(())
+}
+
+fn move_pictures_to_comments(primary_tag: &mut Tag) -> Result<(), String> {
+  let mut items = Vec::with_capacity(primary_tag.pictures().len());
+  for picture in primary_tag.pictures() {
+    let information = PictureInformation::from_picture(picture)
+      .map_err(|e| format!("Failed to read picture information: {}", e))?;
+    let encoded = String::from_utf8(picture.as_flac_bytes(information, true))
+      .map_err(|e| format!("Failed to encode picture: {}", e))?;
+    items.push(TagItem::new(
+      ItemKey::Unknown(FLAC_PICTURE_KEY.to_string()),
+      ItemValue::Text(encoded),
+    ));
+  }
+  while !primary_tag.pictures().is_empty() {
+    primary_tag.remove_picture(0);
+  }
+  for item in items {
+    primary_tag.push_unchecked(item);
+  }
+  Ok(())
+}
+
+/**
+ * Rework the pictures of a tag about to be saved according to the policy
+ * @param primary_tag - The tag about to be saved
+ * @param policy - The placement policy
+ */
+pub fn apply_artwork_policy(primary_tag: &mut Tag, policy: &ArtworkPolicy) -> Result<(), String> {
+  match (primary_tag.tag_type(), policy.mp4_cover_format) {
+    (TagType::Mp4Ilst, Mp4CoverFormat::Jpeg) => replace_pictures(primary_tag, |picture| {
+      transcode(picture, ImageFormat::Jpeg, MimeType::Jpeg)
+    })?,
+    (TagType::Mp4Ilst, Mp4CoverFormat::Png) => replace_pictures(primary_tag, |picture| {
+      transcode(picture, ImageFormat::Png, MimeType::Png)
+    })?,
+    _ => {}
+  }
+  // only FLAC has a choice, Ogg files store their pictures this way regardless
+  if primary_tag.tag_type() == TagType::VorbisComments
+    && policy.flac_picture_storage == FlacPictureStorage::VorbisComment
+  {
+    move_pictures_to_comments(primary_tag)?;
+  }
+  Ok(())
+}
+
+fn is_latin1(text: &str) -> bool {
+  text.chars().all(|c| u32::from(c) <= 0xFF)
+}
+
+// the encoding of a text frame holding `texts`
+fn frame_encoding(encoding: Id3TextEncoding, texts: &[&str]) -> TextEncoding {
+  match encoding {
+    Id3TextEncoding::Utf8 => TextEncoding::UTF8,
+    Id3TextEncoding::Latin1 if texts.iter().all(|text| is_latin1(text)) => TextEncoding::Latin1,
+    _ => TextEncoding::UTF16,
+  }
+}
+
+fn encode_text_frame(frame: Frame<'static>, encoding: Id3TextEncoding) -> Frame<'static> {
+  match frame {
+    Frame::Text(mut frame) => {
+      frame.encoding = frame_encoding(encoding, &[&frame.value]);
+      Frame::Text(frame)
+    }
+    Frame::UserText(mut frame) => {
+      frame.encoding = frame_encoding(encoding, &[&frame.description, &frame.content]);
+      Frame::UserText(frame)
+    }
+    Frame::Comment(mut frame) => {
+      frame.encoding = frame_encoding(encoding, &[&frame.description, &frame.content]);
+      Frame::Comment(frame)
+    }
+    Frame::UnsynchronizedText(mut frame) => {
+      frame.encoding = frame_encoding(encoding, &[&frame.description, &frame.content]);
+      Frame::UnsynchronizedText(frame)
+    }
+    frame => frame,
+  }
+}
+
+/**
+ * Save an ID3v2 primary tag with the APIC frames in the policy's encoding.
+ * This also goes through a full ID3v2 tag when there is a MusicBrainz recording
+ * id, which lofty only turns into its UFID frame on that conversion, and for
+ * ID3v2.3 output, as lofty only splits the recording date into TYER/TDAT for
+ * the timestamp frames of a full tag. The same goes for comments with a
+ * language or description, which the regular way writes without them, and for
+ * text frames in an encoding other than UTF-8.
+ * @param id3v23 - Whether `write_options` ask for ID3v2.3
+ * @param text_encoding - The encoding of the text frames
+ * @returns false when the tag is not an ID3v2 tag or needs nothing of the
+ *   above, and it should be saved the regular way
+ */
+pub fn save_id3v2_with_policy<F>(
+  primary_tag: &Tag,
+  policy: &ArtworkPolicy,
+  out: &mut F,
+  write_options: WriteOptions,
+  id3v23: bool,
+  text_encoding: Id3TextEncoding,
+) -> Result<bool, String>
+where
+  F: FileLike,
+  LoftyError: From<<F as Truncate>::Error>,
+  LoftyError: From<<F as Length>::Error>,
+{
+  if primary_tag.tag_type() != TagType::Id3v2
+    || (policy.id3_picture_encoding == Id3PictureEncoding::Utf8
+      && text_encoding == Id3TextEncoding::Utf8
+      && !id3v23
+      && primary_tag
+        .get_string(&ItemKey::MusicBrainzRecordingId)
+        .is_none()
+      && !comments::has_comment_details(primary_tag))
+  {
+    return Ok(false);
+  }
+  let converted: Id3v2Tag = primary_tag.clone().into();
+  // frames can't be changed in place, the tag is rebuilt in the same order
+  let mut id3v2 = Id3v2Tag::new();
+  id3v2.set_flags(*converted.flags());
+  for frame in converted {
+    if !matches!(frame, Frame::Picture(_)) {
+      id3v2.insert(encode_text_frame(frame, text_encoding));
+    }
+  }
+  for picture in primary_tag.pictures() {
+    let encoding = match policy.id3_picture_encoding {
+      Id3PictureEncoding::Utf8 => TextEncoding::UTF8,
+      Id3PictureEncoding::Latin1 if picture.description().is_none_or(is_latin1) => {
+        TextEncoding::Latin1
+      }
+      _ => TextEncoding::UTF16,
+    };
+    id3v2.insert(Frame::Picture(AttachedPictureFrame::new(
+      encoding,
+      picture.clone(),
+    )));
+  }
+  id3v2
+    .save_to(out, write_options)
+    .map_err(|e| format!("Failed to write audio to buffer: {}", e))?;
+  Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use lofty::file::AudioFile;
+  use lofty::picture::PictureType;
+
+  fn png() -> Vec<u8> {
+    let image = image::RgbaImage::from_pixel(4, 4, image::Rgba([10, 20, 30, 128]));
+    let mut out = Cursor::new(Vec::new());
+    image.write_to(&mut out, ImageFormat::Png).unwrap();
+    out.into_inner()
+  }
+
+  fn tag_with_cover(tag_type: TagType) -> Tag {
+    let mut tag = Tag::new(tag_type);
+    tag.push_picture(Picture::new_unchecked(
+      PictureType::CoverFront,
+      Some(MimeType::Png),
+      Some("Front".to_string()),
+      png(),
+    ));
+    tag
+  }
+
+  #[test]
+  fn test_mp4_cover_format() {
+    let policy = ArtworkPolicy {
+      mp4_cover_format: Mp4CoverFormat::Jpeg,
+      ..Default::default()
+    };
+    let mut tag = tag_with_cover(TagType::Mp4Ilst);
+    apply_artwork_policy(&mut tag, &policy).unwrap();
+    let picture = &tag.pictures()[0];
+    assert_eq!(picture.mime_type(), Some(&MimeType::Jpeg));
+    assert_eq!(picture.description(), Some("Front"));
+    assert_eq!(
+      image::guess_format(picture.data()).unwrap(),
+      ImageFormat::Jpeg
+    );
+
+    // other formats are left alone
+    let mut tag = tag_with_cover(TagType::Id3v2);
+    apply_artwork_policy(&mut tag, &policy).unwrap();
+    assert_eq!(tag.pictures()[0].data(), png().as_slice());
+  }
+
+  #[test]
+  fn test_flac_pictures_as_vorbis_comments() {
+    let policy = ArtworkPolicy {
+      flac_picture_storage: FlacPictureStorage::VorbisComment,
+      ..Default::default()
+    };
+    let mut tag = tag_with_cover(TagType::VorbisComments);
+    apply_artwork_policy(&mut tag, &policy).unwrap();
+    assert!(tag.pictures().is_empty());
+    let encoded = tag
+      .get_string(&ItemKey::Unknown(FLAC_PICTURE_KEY.to_string()))
+      .unwrap();
+    let (picture, _) =
+      Picture::from_flac_bytes(encoded.as_bytes(), true, Default::default()).unwrap();
+    assert_eq!(picture.data(), png().as_slice());
+    assert_eq!(picture.description(), Some("Front"));
+  }
+
+  #[test]
+  fn test_id3_picture_encoding() {
+    let policy = ArtworkPolicy {
+      id3_picture_encoding: Id3PictureEncoding::Latin1,
+      ..Default::default()
+    };
+    let mut tag = tag_with_cover(TagType::Id3v2);
+    tag.push_picture(Picture::new_unchecked(
+      PictureType::CoverBack,
+      Some(MimeType::Png),
+      Some("Rückseite ✓".to_string()),
+      png(),
+    ));
+    let mut out = Cursor::new(crate::test_support::sample_mp3());
+    assert!(save_id3v2_with_policy(
+      &tag,
+      &policy,
+      &mut out,
+      WriteOptions::default(),
+      false,
+      Id3TextEncoding::Utf8
+    )
+    .unwrap());
+
+    out.set_position(0);
+    let id3v2 = lofty::mpeg::MpegFile::read_from(&mut out, Default::default())
+      .unwrap()
+      .id3v2()
+      .cloned()
+      .unwrap();
+    let encodings: Vec<TextEncoding> = id3v2
+      .into_iter()
+      .filter_map(|frame| match frame {
+        Frame::Picture(frame) => Some(frame.encoding),
+        _ => None,
+      })
+      .collect();
+    assert_eq!(encodings, vec![TextEncoding::Latin1, TextEncoding::UTF16]);
+
+    let regular = ArtworkPolicy::default();
+    assert!(!save_id3v2_with_policy(
+      &tag,
+      &regular,
+      &mut out,
+      WriteOptions::default(),
+      false,
+      Id3TextEncoding::Utf8
+    )
+    .unwrap());
+  }
+}
@@ -0,0 +1,128 @@
+use std::ops::{Deref, DerefMut};
+use std::sync::Mutex;
+
+// Copies of the input of the buffer-based read paths. The read runs off the
+// JavaScript thread, so every call needs a full-size copy of its input; reusing
+// them keeps a busy service from allocating (and faulting in) that memory on
+// every request.
+pub static BUFFER_POOL: BufferPool = BufferPool::new(8, 64 * 1024 * 1024);
+
+pub struct BufferPool {
+  buffers: Mutex<Vec<Vec<u8>>>,
+  // idle buffers kept around
+  max_buffers: usize,
+  // larger buffers are released instead of pooled
+  max_capacity: usize,
+}
+
+impl BufferPool {
+  pub const fn new(max_buffers: usize, max_capacity: usize) -> Self {
+    Self {
+      buffers: Mutex::new(Vec::new()),
+      max_buffers,
+      max_capacity,
+    }
+  }
+
+  /**
+   * Take an empty buffer able to hold at least `capacity` bytes. The buffer goes
+   * back to the pool when the returned guard is dropped.
+   * @param capacity - The number of bytes the caller is about to write
+   */
+  pub fn take(&self, capacity: usize) -> PooledBuffer<'_> {
+    let reused = {
+      let mut buffers = self.buffers.lock().unwrap_or_else(|e| e.into_inner());
+      // smallest idle buffer that fits, so large ones stay available
+      let best = buffers
+        .iter()
+        .enumerate()
+        .filter(|(_, buffer)| buffer.capacity() >= capacity)
+        .min_by_key(|(_, buffer)| buffer.capacity())
+        .map(|(i, _)| i);
+      best.map(|i| buffers.swap_remove(i))
+    };
+    PooledBuffer {
+      pool: self,
+      buffer: reused.unwrap_or_else(|| Vec::with_capacity(capacity)),
+    }
+  }
+
+  fn give_back(&self, mut buffer: Vec<u8>) {
+    if buffer.capacity() > self.max_capacity {
+      return;
+    }
+    buffer.clear();
+    let mut buffers = self.buffers.lock().unwrap_or_else(|e| e.into_inner());
+    if buffers.len() < self.max_buffers {
+      buffers.push(buffer);
+    }
+  }
+
+  #[cfg(test)]
+  fn idle(&self) -> usize {
+    self.buffers.lock().unwrap().len()
+  }
+}
+
+pub struct PooledBuffer<'a> {
+  pool: &'a BufferPool,
+  buffer: Vec<u8>,
+}
+
+impl AsRef<[u8]> for PooledBuffer<'_> {
+  fn as_ref(&self) -> &[u8] {
+    &self.buffer
+  }
+}
+
+impl Deref for PooledBuffer<'_> {
+  type Target = Vec<u8>;
+
+  fn deref(&self) -> &Vec<u8> {
+    &self.buffer
+  }
+}
+
+impl DerefMut for PooledBuffer<'_> {
+  fn deref_mut(&mut self) -> &mut Vec<u8> {
+    &mut self.buffer
+  }
+}
+
+impl Drop for PooledBuffer<'_> {
+  fn drop(&mut self) {
+    self.pool.give_back(std::mem::take(&mut self.buffer));
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_buffer_pool_reuses_allocations() {
+    let pool = BufferPool::new(2, 1024);
+    let first_ptr = {
+      let mut buffer = pool.take(100);
+      buffer.extend_from_slice(&[1; 100]);
+      buffer.as_ptr()
+    };
+    assert_eq!(pool.idle(), 1);
+
+    let buffer = pool.take(50);
+    assert!(buffer.is_empty());
+    assert_eq!(buffer.as_ptr(), first_ptr);
+    assert_eq!(pool.idle(), 0);
+  }
+
+  #[test]
+  fn test_buffer_pool_bounds_what_it_keeps() {
+    let pool = BufferPool::new(2, 1024);
+    drop(pool.take(4096));
+    assert_eq!(pool.idle(), 0);
+
+    let buffers: Vec<_> = (0..3).map(|_| pool.take(16)).collect();
+    drop(buffers);
+    assert_eq!(pool.idle(), 2);
+  }
+}
@@ -0,0 +1,342 @@
+use crate::util;
+use lofty::config::ParseOptions;
+use lofty::file::{FileType, TaggedFileExt};
+use lofty::probe::Probe;
+use lofty::tag::TagType;
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::path::Path;
+
+pub const DEFAULT_PROBE_BYTES: usize = 64 * 1024;
+
+const ID3V1_SIZE: usize = 128;
+const APE_FOOTER_SIZE: usize = 32;
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct BufferProbe {
+  // None when the inspected bytes are not enough to tell
+  pub file_type: Option<String>,
+  // tags found in the inspected bytes, in file order
+  pub tag_types: Vec<String>,
+  // total size of the leading ID3v2 tag, the bytes needed to read it completely
+  pub id3v2_size: Option<u32>,
+}
+
+// What a file is and which tags it carries, read without the audio
+// properties
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct FileProbe {
+  pub file_type: String,
+  // the tag type written by default for the format
+  pub primary_tag_type: String,
+  // the tags the file has, in the order lofty reads them
+  pub present_tag_types: Vec<String>,
+  // whether any tag holds a picture
+  pub has_artwork: bool,
+  // the bytes taken by the tags; None when the file has tags inside a
+  // container (MP4, Ogg, RIFF, AIFF) whose size isn't measured
+  pub tag_size_bytes: Option<u64>,
+}
+
+pub(crate) fn file_type_name(file_type: FileType) -> String {
+  match file_type {
+    FileType::Custom(name) => name.to_string(),
+    other => format!("{:?}", other),
+  }
+}
+
+pub(crate) fn synchsafe(bytes: &[u8]) -> u32 {
+  bytes
+    .iter()
+    .fold(0, |size, byte| (size << 7) | u32::from(byte & 0x7F))
+}
+
+// the full size of an ID3v2 tag starting at `buf`, header and footer included
+pub(crate) fn id3v2_size(buf: &[u8]) -> Option<u32> {
+  if buf.len() < 10 || &buf[..3] != b"ID3" {
+    return None;
+  }
+  let footer = if buf[5] & 0x10 != 0 { 10 } else { 0 };
+  Some(10 + synchsafe(&buf[6..10]) + footer)
+}
+
+// FLAC metadata blocks follow the marker, each with a 4 byte header
+// (last-block flag + type, 24 bit length); walk as far as the head allows
+fn flac_tag_types(buf: &[u8]) -> Vec<String> {
+  let mut tag_types = Vec::new();
+  let mut offset = 4;
+  while offset + 4 <= buf.len() {
+    let header = &buf[offset..offset + 4];
+    let block_type = header[0] & 0x7F;
+    let length = u32::from_be_bytes([0, header[1], header[2], header[3]]) as usize;
+    let name = match block_type {
+      4 => Some("VorbisComments"),
+      6 => Some("Picture"),
+      _ => None,
+    };
+    if let Some(name) = name {
+      if !tag_types.iter().any(|tag_type| tag_type == name) {
+        tag_types.push(name.to_string());
+      }
+    }
+    if header[0] & 0x80 != 0 {
+      break;
+    }
+    offset += 4 + length;
+  }
+  tag_types
+}
+
+fn read_at<R: Read + Seek>(reader: &mut R, pos: SeekFrom, buf: &mut [u8]) -> bool {
+  reader.seek(pos).is_ok() && reader.read_exact(buf).is_ok()
+}
+
+// the bytes of the FLAC metadata blocks holding tags: Vorbis comments and
+// pictures, block headers included
+fn flac_tag_size<R: Read + Seek>(reader: &mut R, start: u64) -> u64 {
+  let mut size = 0;
+  let mut offset = start + 4;
+  let mut header = [0u8; 4];
+  while read_at(reader, SeekFrom::Start(offset), &mut header) {
+    let length = u64::from(u32::from_be_bytes([0, header[1], header[2], header[3]]));
+    if matches!(header[0] & 0x7F, 4 | 6) {
+      size += 4 + length;
+    }
+    if header[0] & 0x80 != 0 {
+      break;
+    }
+    offset += 4 + length;
+  }
+  size
+}
+
+// the sizes of the ID3v2 tag and FLAC metadata at the start of a file, and of
+// the APE and ID3v1 tags at its end, reading only their headers
+fn tag_size<R: Read + Seek>(reader: &mut R) -> u64 {
+  let mut size = 0;
+  let mut head = [0u8; 10];
+  let mut audio_start = 0;
+  if read_at(reader, SeekFrom::Start(0), &mut head) {
+    if let Some(id3v2) = id3v2_size(&head) {
+      size += u64::from(id3v2);
+      audio_start = u64::from(id3v2);
+    }
+  }
+  let mut marker = [0u8; 4];
+  if read_at(reader, SeekFrom::Start(audio_start), &mut marker) && &marker == b"fLaC" {
+    size += flac_tag_size(reader, audio_start);
+  }
+
+  size + trailing_tag_size(reader)
+}
+
+// the size of the APE and ID3v1 tags at the end of a file
+pub(crate) fn trailing_tag_size<R: Read + Seek>(reader: &mut R) -> u64 {
+  let mut size = 0;
+  let mut tail_end = 0;
+  let mut id3v1 = [0u8; 3];
+  if read_at(reader, SeekFrom::End(-(ID3V1_SIZE as i64)), &mut id3v1) && &id3v1 == b"TAG" {
+    size += ID3V1_SIZE as u64;
+    tail_end = ID3V1_SIZE as i64;
+  }
+  let mut footer = [0u8; APE_FOOTER_SIZE];
+  let footer_pos = SeekFrom::End(-(tail_end + APE_FOOTER_SIZE as i64));
+  if read_at(reader, footer_pos, &mut footer) && footer.starts_with(b"APETAGEX") {
+    // the size counts the items and the footer, the header is flagged apart
+    let ape = u32::from_le_bytes([footer[12], footer[13], footer[14], footer[15]]);
+    let flags = u32::from_le_bytes([footer[20], footer[21], footer[22], footer[23]]);
+    let header = if flags & 0x8000_0000 != 0 {
+      APE_FOOTER_SIZE as u32
+    } else {
+      0
+    };
+    size += u64::from(ape + header);
+  }
+  size
+}
+
+fn probe_reader<R: Read + Seek>(mut reader: R) -> Result<FileProbe, String> {
+  let size = tag_size(&mut reader);
+  reader
+    .seek(SeekFrom::Start(0))
+    .map_err(|e| format!("Failed to read file: {}", e))?;
+  let probe = Probe::new(reader)
+    .options(ParseOptions::new().read_properties(false))
+    .guess_file_type()
+    .map_err(|e| util::io_error("Failed to guess file type", &e))?;
+  let tagged_file = probe
+    .read()
+    .map_err(|e| util::lofty_error("Failed to read audio file", &e))?;
+  let file_type = tagged_file.file_type();
+  let tags = tagged_file.tags();
+  let measured = tags.iter().all(|tag| match tag.tag_type() {
+    TagType::Id3v2 | TagType::Id3v1 | TagType::Ape => true,
+    TagType::VorbisComments => file_type == FileType::Flac,
+    _ => false,
+  });
+  Ok(FileProbe {
+    file_type: file_type_name(file_type),
+    primary_tag_type: format!("{:?}", tagged_file.primary_tag_type()),
+    present_tag_types: tags
+      .iter()
+      .map(|tag| format!("{:?}", tag.tag_type()))
+      .collect(),
+    has_artwork: tags.iter().any(|tag| tag.picture_count() > 0),
+    tag_size_bytes: if measured { Some(size) } else { None },
+  })
+}
+
+/**
+ * Classify a file: its format, its tags and whether it has artwork. The audio
+ * properties are not read and the pictures are not decoded, which keeps it
+ * cheap enough to run over a whole library before deciding what to do.
+ * @param file_path - The path to the audio file
+ */
+pub async fn probe_file(file_path: impl AsRef<Path>) -> Result<FileProbe, String> {
+  let path = file_path.as_ref();
+  let file = util::open_file(path)?;
+  probe_reader(file).map_err(util::in_file(path))
+}
+
+/**
+ * Classify a buffer, like `probe_file`
+ * @param buffer - The audio data
+ */
+pub async fn probe_file_from_buffer(buffer: Vec<u8>) -> Result<FileProbe, String> {
+  probe_reader(Cursor::new(buffer))
+}
+
+/**
+ * Determine the format of a buffer and which tags it carries by inspecting at
+ * most `max_bytes` at its start and at its end. The buffer doesn't need to be
+ * the complete file, which lets servers route an upload from its first chunk.
+ * @param buffer - The audio data, possibly incomplete
+ * @param max_bytes - How many bytes to inspect at each end of the buffer
+ */
+pub fn probe_buffer(buffer: &[u8], max_bytes: usize) -> BufferProbe {
+  let head = &buffer[..buffer.len().min(max_bytes)];
+  let tail = &buffer[buffer.len().saturating_sub(max_bytes)..];
+  let mut probe = BufferProbe::default();
+
+  let mut audio_start = 0;
+  if let Some(size) = id3v2_size(head) {
+    probe.tag_types.push("Id3v2".to_string());
+    probe.id3v2_size = Some(size);
+    audio_start = size as usize;
+  }
+  if audio_start < head.len() {
+    let audio = &head[audio_start..];
+    probe.file_type = FileType::from_buffer(audio).map(file_type_name);
+    if audio.starts_with(b"fLaC") {
+      probe.tag_types.extend(flac_tag_types(audio));
+    }
+  }
+
+  let has_id3v1 = tail.len() >= ID3V1_SIZE && tail[tail.len() - ID3V1_SIZE..].starts_with(b"TAG");
+  let ape_end = tail.len() - if has_id3v1 { ID3V1_SIZE } else { 0 };
+  if ape_end >= APE_FOOTER_SIZE && tail[ape_end - APE_FOOTER_SIZE..].starts_with(b"APETAGEX") {
+    probe.tag_types.push("Ape".to_string());
+  }
+  if has_id3v1 {
+    probe.tag_types.push("Id3v1".to_string());
+  }
+  probe
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_support::sample_mp3;
+
+  #[test]
+  fn test_probe_buffer_detects_mpeg_with_id3v2() {
+    let probe = probe_buffer(&sample_mp3(), DEFAULT_PROBE_BYTES);
+    assert_eq!(probe.file_type, Some("Mpeg".to_string()));
+    assert_eq!(probe.tag_types, vec!["Id3v2".to_string()]);
+    assert!(probe.id3v2_size.is_some());
+  }
+
+  #[test]
+  fn test_probe_buffer_reports_tag_larger_than_head() {
+    // an ID3v2 tag declaring 1000 bytes, only the first chunk has arrived
+    let mut chunk = b"ID3\x04\x00\x00\x00\x00\x07\x68".to_vec();
+    chunk.resize(64, 0);
+    let probe = probe_buffer(&chunk, 32);
+    assert_eq!(probe.file_type, None);
+    assert_eq!(probe.tag_types, vec!["Id3v2".to_string()]);
+    assert_eq!(probe.id3v2_size, Some(1010));
+  }
+
+  #[test]
+  fn test_probe_buffer_detects_trailing_tags() {
+    let mut buffer = vec![0xFF, 0xFB, 0x90, 0x44];
+    buffer.resize(512, 0);
+    let mut ape_footer = b"APETAGEX".to_vec();
+    ape_footer.resize(APE_FOOTER_SIZE, 0);
+    buffer.extend(ape_footer);
+    let mut id3v1 = b"TAG".to_vec();
+    id3v1.resize(ID3V1_SIZE, 0);
+    buffer.extend(id3v1);
+
+    let probe = probe_buffer(&buffer, 256);
+    assert_eq!(probe.file_type, Some("Mpeg".to_string()));
+    assert_eq!(
+      probe.tag_types,
+      vec!["Ape".to_string(), "Id3v1".to_string()]
+    );
+    assert_eq!(probe.id3v2_size, None);
+  }
+
+  #[tokio::test]
+  async fn test_probe_file() {
+    let tags = crate::util::AudioTags {
+      title: Some("Title".to_string()),
+      image: Some(crate::util::Image {
+        data: vec![0xFF, 0xD8, 0xFF, 0xE0],
+        mime_type: Some("image/jpeg".to_string()),
+        ..Default::default()
+      }),
+      ..Default::default()
+    };
+    let buffer = crate::util::write_tags_to_buffer(sample_mp3(), tags)
+      .await
+      .unwrap();
+    let id3v2 = id3v2_size(&buffer).map(u64::from);
+    let probe = probe_file_from_buffer(buffer).await.unwrap();
+    assert_eq!(probe.file_type, "Mpeg");
+    assert_eq!(probe.primary_tag_type, "Id3v2");
+    assert_eq!(probe.present_tag_types, vec!["Id3v2".to_string()]);
+    assert!(probe.has_artwork);
+    assert_eq!(probe.tag_size_bytes, id3v2);
+
+    let mut untagged = sample_mp3();
+    let start = id3v2_size(&untagged).unwrap_or(0) as usize;
+    untagged.drain(..start);
+    let mut id3v1 = b"TAG".to_vec();
+    id3v1.resize(ID3V1_SIZE, 0);
+    untagged.extend(id3v1);
+    let probe = probe_file_from_buffer(untagged).await.unwrap();
+    assert_eq!(probe.present_tag_types, vec!["Id3v1".to_string()]);
+    assert!(!probe.has_artwork);
+    assert_eq!(probe.tag_size_bytes, Some(ID3V1_SIZE as u64));
+    assert!(probe_file("missing.mp3".to_string()).await.is_err());
+  }
+
+  #[test]
+  fn test_probe_buffer_walks_flac_metadata() {
+    let mut buffer = b"fLaC".to_vec();
+    // STREAMINFO, VORBIS_COMMENT, then the last block: PICTURE
+    buffer.extend([0x00, 0x00, 0x00, 0x22]);
+    buffer.extend([0u8; 0x22]);
+    buffer.extend([0x04, 0x00, 0x00, 0x08]);
+    buffer.extend([0u8; 8]);
+    buffer.extend([0x86, 0x00, 0x00, 0x04]);
+    buffer.extend([0u8; 4]);
+
+    let probe = probe_buffer(&buffer, DEFAULT_PROBE_BYTES);
+    assert_eq!(probe.file_type, Some("Flac".to_string()));
+    assert_eq!(
+      probe.tag_types,
+      vec!["VorbisComments".to_string(), "Picture".to_string()]
+    );
+  }
+}
@@ -0,0 +1,257 @@
+use lofty::id3::v2::{Frame, Id3v2Tag, PopularimeterFrame};
+use lofty::tag::{ItemKey, ItemValue, Tag, TagItem, TagType};
+
+const POPM: &str = "POPM";
+// the Vorbis comments of the FMPS specification, a rating from 0.0 to 1.0 and
+// a play count
+const FMPS_RATING: &str = "FMPS_RATING";
+const FMPS_PLAYCOUNT: &str = "FMPS_PLAYCOUNT";
+
+// How a rating maps to the byte of an ID3v2 POPM frame, which players read
+// differently
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum PopmScale {
+  // whole stars, as written by Windows Media Player and read by most players
+  #[default]
+  WindowsMedia,
+  // half stars, as written by MediaMonkey
+  MediaMonkey,
+}
+
+// the bytes of the ratings 0, 20, ..., 100
+const WINDOWS_MEDIA_BYTES: [u8; 6] = [0, 1, 64, 128, 196, 255];
+// the bytes of the ratings 0, 10, ..., 100, every Windows Media byte included
+const MEDIA_MONKEY_BYTES: [u8; 11] = [0, 13, 1, 54, 64, 118, 128, 186, 196, 242, 255];
+
+impl PopmScale {
+  // the email the player identifies its POPM frame with
+  fn email(self) -> &'static str {
+    match self {
+      Self::WindowsMedia => "Windows Media Player 9 Series",
+      Self::MediaMonkey => "no@email",
+    }
+  }
+
+  fn popm_byte(self, rating: u8) -> u8 {
+    let rating = usize::from(rating.min(100));
+    match self {
+      Self::WindowsMedia => WINDOWS_MEDIA_BYTES[(rating + 10) / 20],
+      Self::MediaMonkey => MEDIA_MONKEY_BYTES[(rating + 5) / 10],
+    }
+  }
+}
+
+// bytes written by other players read as the nearest whole star
+fn rating_from_popm_byte(byte: u8) -> u8 {
+  if let Some(i) = MEDIA_MONKEY_BYTES.iter().position(|known| *known == byte) {
+    return i as u8 * 10;
+  }
+  match byte {
+    ..=31 => 20,
+    32..=95 => 40,
+    96..=159 => 60,
+    160..=223 => 80,
+    _ => 100,
+  }
+}
+
+/**
+ * Whether a field name is one of the rating comments, which AudioTags has a
+ * field for
+ * @param name - A field name of the tag's format
+ */
+pub fn is_rating_field(name: &str) -> bool {
+  name.eq_ignore_ascii_case(FMPS_RATING) || name.eq_ignore_ascii_case(FMPS_PLAYCOUNT)
+}
+
+fn unknown_text<'a>(tag: &'a Tag, name: &str) -> Option<&'a str> {
+  tag.items().find_map(|item| match item.key() {
+    ItemKey::Unknown(key) if key.eq_ignore_ascii_case(name) => item.value().text(),
+    _ => None,
+  })
+}
+
+fn remove_unknown(tag: &mut Tag, name: &str) {
+  tag.retain(|item| !matches!(item.key(), ItemKey::Unknown(key) if key.eq_ignore_ascii_case(name)));
+}
+
+fn first_popm(id3v2: &Id3v2Tag) -> Option<PopularimeterFrame<'static>> {
+  id3v2.into_iter().find_map(|frame| match frame {
+    Frame::Popularimeter(popm) => Some(popm.clone()),
+    _ => None,
+  })
+}
+
+// replace the POPM frames of a tag with the frame `update` makes of the first
+// one, or with none
+fn update_popm<U>(tag: &mut Tag, update: U)
+where
+  U: FnOnce(Option<PopularimeterFrame<'static>>) -> Option<PopularimeterFrame<'static>>,
+{
+  let mut id3v2: Id3v2Tag = tag.clone().into();
+  let popm = update(first_popm(&id3v2));
+  id3v2.retain(|frame| frame.id_str() != POPM);
+  if let Some(popm) = popm {
+    id3v2.insert(Frame::Popularimeter(popm));
+  }
+  // converting back keeps the POPM frame in the frames written along with the tag
+  *tag = id3v2.into();
+}
+
+/**
+ * Read the rating and play count of a tag: the first ID3v2 POPM frame, the
+ * RATING and FMPS Vorbis comments or the MP4 rate atom
+ * @param tag - The primary tag
+ * @returns The rating from 0 to 100 and the play count
+ */
+pub fn read_rating(tag: &Tag) -> (Option<u8>, Option<u32>) {
+  if tag.tag_type() == TagType::Id3v2 {
+    let id3v2: Id3v2Tag = tag.clone().into();
+    let Some(popm) = first_popm(&id3v2) else {
+      return (None, None);
+    };
+    let rating = (popm.rating != 0).then(|| rating_from_popm_byte(popm.rating));
+    let play_count = (popm.counter != 0).then(|| u32::try_from(popm.counter).unwrap_or(u32::MAX));
+    return (rating, play_count);
+  }
+  let rating = unknown_text(tag, FMPS_RATING)
+    .and_then(|value| value.trim().parse::<f64>().ok())
+    .map(|value| value * 100.0)
+    .or_else(|| tag.get_string(&ItemKey::Popularimeter)?.trim().parse().ok())
+    .map(|value: f64| value.round().clamp(0.0, 100.0) as u8);
+  let play_count = unknown_text(tag, FMPS_PLAYCOUNT).and_then(|value| value.trim().parse().ok());
+  (rating, play_count)
+}
+
+/**
+ * Store a rating and a play count, replacing the ones already present. ID3v2
+ * tags get a single POPM frame with the byte and email of the player scale,
+ * Vorbis comments RATING from 0 to 100 along with the FMPS comments, and MP4
+ * the rate atom. Only ID3v2 and Vorbis comments hold a play count.
+ * @param tag - The primary tag
+ * @param rating - The rating from 0 to 100, higher values count as 100
+ * @param play_count - The play count
+ * @param scale - The POPM byte scale
+ */
+pub fn set_rating(tag: &mut Tag, rating: Option<u8>, play_count: Option<u32>, scale: PopmScale) {
+  match tag.tag_type() {
+    TagType::Id3v2 => update_popm(tag, |existing| {
+      let email = match (rating, &existing) {
+        (None, Some(existing)) => existing.email.clone(),
+        _ => scale.email().to_string(),
+      };
+      let byte = rating
+        .map(|rating| scale.popm_byte(rating))
+        .or(existing.as_ref().map(|popm| popm.rating))
+        .unwrap_or(0);
+      let counter = play_count
+        .map(u64::from)
+        .or(existing.as_ref().map(|popm| popm.counter))
+        .unwrap_or(0);
+      Some(PopularimeterFrame::new(email, byte, counter))
+    }),
+    tag_type => {
+      if let Some(rating) = rating.map(|rating| rating.min(100)) {
+        tag.remove_key(&ItemKey::Popularimeter);
+        tag.insert_text(ItemKey::Popularimeter, rating.to_string());
+        if tag_type == TagType::VorbisComments {
+          remove_unknown(tag, FMPS_RATING);
+          tag.push_unchecked(TagItem::new(
+            ItemKey::Unknown(FMPS_RATING.to_string()),
+            ItemValue::Text((f64::from(rating) / 100.0).to_string()),
+          ));
+        }
+      }
+      if let Some(play_count) = play_count.filter(|_| tag_type == TagType::VorbisComments) {
+        remove_unknown(tag, FMPS_PLAYCOUNT);
+        tag.push_unchecked(TagItem::new(
+          ItemKey::Unknown(FMPS_PLAYCOUNT.to_string()),
+          ItemValue::Text(play_count.to_string()),
+        ));
+      }
+    }
+  }
+}
+
+/**
+ * Remove the rating or the play count of a tag, a POPM frame keeps the part
+ * that is not cleared
+ * @param tag - The primary tag
+ * @param rating - Whether to remove the rating
+ * @param play_count - Whether to remove the play count
+ */
+pub fn clear_rating(tag: &mut Tag, rating: bool, play_count: bool) {
+  if tag.tag_type() == TagType::Id3v2 {
+    update_popm(tag, |existing| {
+      let mut popm = existing?;
+      if rating {
+        popm.rating = 0;
+      }
+      if play_count {
+        popm.counter = 0;
+      }
+      (popm.rating != 0 || popm.counter != 0).then_some(popm)
+    });
+    return;
+  }
+  if rating {
+    tag.remove_key(&ItemKey::Popularimeter);
+    remove_unknown(tag, FMPS_RATING);
+  }
+  if play_count {
+    remove_unknown(tag, FMPS_PLAYCOUNT);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_popm_scales() {
+    let ratings = [0, 20, 40, 60, 80, 100];
+    let bytes: Vec<u8> = ratings
+      .iter()
+      .map(|rating| PopmScale::WindowsMedia.popm_byte(*rating))
+      .collect();
+    assert_eq!(bytes, WINDOWS_MEDIA_BYTES);
+    assert_eq!(PopmScale::MediaMonkey.popm_byte(50), 118);
+    assert_eq!(PopmScale::WindowsMedia.popm_byte(50), 128);
+    assert_eq!(PopmScale::WindowsMedia.popm_byte(200), 255);
+
+    for rating in (0..=100).step_by(10) {
+      let byte = PopmScale::MediaMonkey.popm_byte(rating);
+      assert_eq!(rating_from_popm_byte(byte), rating);
+    }
+    // bytes of other players read as the nearest star
+    assert_eq!(rating_from_popm_byte(3), 20);
+    assert_eq!(rating_from_popm_byte(150), 60);
+    assert_eq!(rating_from_popm_byte(250), 100);
+  }
+
+  #[test]
+  fn test_rating_in_tags() {
+    let mut id3v2 = Tag::new(TagType::Id3v2);
+    set_rating(&mut id3v2, Some(80), Some(12), PopmScale::WindowsMedia);
+    assert_eq!(read_rating(&id3v2), (Some(80), Some(12)));
+    let popm = first_popm(&id3v2.clone().into()).unwrap();
+    assert_eq!(popm.rating, 196);
+    assert_eq!(popm.email, "Windows Media Player 9 Series");
+
+    // the play count alone keeps the rating
+    set_rating(&mut id3v2, None, Some(13), PopmScale::MediaMonkey);
+    assert_eq!(read_rating(&id3v2), (Some(80), Some(13)));
+    clear_rating(&mut id3v2, true, false);
+    assert_eq!(read_rating(&id3v2), (None, Some(13)));
+    clear_rating(&mut id3v2, false, true);
+    assert!(first_popm(&id3v2.into()).is_none());
+
+    let mut vorbis = Tag::new(TagType::VorbisComments);
+    set_rating(&mut vorbis, Some(70), Some(3), PopmScale::WindowsMedia);
+    assert_eq!(vorbis.get_string(&ItemKey::Popularimeter), Some("70"));
+    assert_eq!(unknown_text(&vorbis, FMPS_RATING), Some("0.7"));
+    assert_eq!(read_rating(&vorbis), (Some(70), Some(3)));
+    clear_rating(&mut vorbis, true, true);
+    assert_eq!(read_rating(&vorbis), (None, None));
+  }
+}
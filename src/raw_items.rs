@@ -0,0 +1,147 @@
+use crate::util::ALL_TAG_TYPES;
+use lofty::tag::{ItemKey, ItemValue, Tag, TagItem, TagType};
+
+// An item of a tag as lofty holds it, for inspecting exactly what a file
+// stores. Pictures and the frames lofty doesn't map are not items.
+#[derive(Debug, PartialEq, Clone)]
+pub struct RawTagItem {
+  // the format of the tag holding the item, e.g. "Id3v2" or "Id3v1"
+  pub tag_type: String,
+  // lofty's name for the item, e.g. "TrackArtist", "Unknown" when it has none
+  pub key: String,
+  // the name of the item in the tag's format, e.g. "TPE1" or "ARTIST"; None
+  // in ID3v1 tags, whose fields have no names
+  pub key_string: Option<String>,
+  pub value: ItemValue,
+}
+
+// the fields of ID3v1 tags, written by their `key`
+const ID3V1_KEYS: [ItemKey; 7] = [
+  ItemKey::TrackTitle,
+  ItemKey::TrackArtist,
+  ItemKey::AlbumTitle,
+  ItemKey::Year,
+  ItemKey::Comment,
+  ItemKey::TrackNumber,
+  ItemKey::Genre,
+];
+
+/**
+ * The tag type of a name, as `RawTagItem::tag_type` holds it
+ * @param name - The name, e.g. "Id3v2"
+ */
+pub fn parse_tag_type(name: &str) -> Result<TagType, String> {
+  ALL_TAG_TYPES
+    .into_iter()
+    .find(|tag_type| format!("{:?}", tag_type) == name)
+    .ok_or_else(|| format!("Unknown tag type: {}", name))
+}
+
+fn key_name(key: &ItemKey) -> String {
+  match key {
+    ItemKey::Unknown(_) => "Unknown".to_string(),
+    key => format!("{:?}", key),
+  }
+}
+
+/**
+ * The items of a tag, in tag order
+ * @param tag - The tag
+ */
+pub fn raw_items(tag: &Tag) -> Vec<RawTagItem> {
+  let tag_type = tag.tag_type();
+  tag
+    .items()
+    .map(|item| RawTagItem {
+      tag_type: format!("{:?}", tag_type),
+      key: key_name(item.key()),
+      key_string: item.key().map_key(tag_type, true).map(str::to_string),
+      value: item.value().clone(),
+    })
+    .collect()
+}
+
+// the key an item is written under
+fn item_key(item: &RawTagItem, tag_type: TagType) -> Result<ItemKey, String> {
+  if let Some(key_string) = item.key_string.as_deref() {
+    return Ok(ItemKey::from_key(tag_type, key_string));
+  }
+  ID3V1_KEYS
+    .into_iter()
+    .filter(|_| tag_type == TagType::Id3v1)
+    .find(|key| key_name(key) == item.key)
+    .ok_or_else(|| format!("The {} item has no key in {:?} tags", item.key, tag_type))
+}
+
+/**
+ * Replace the items of a tag, as is: the items are identified by their
+ * `key_string`, or their `key` in ID3v1 tags, and their values are not
+ * checked. Pictures are kept.
+ * @param tag - The tag
+ * @param items - The items it holds afterwards
+ */
+pub fn set_raw_items(tag: &mut Tag, items: &[&RawTagItem]) -> Result<(), String> {
+  let tag_type = tag.tag_type();
+  let mut keyed = Vec::new();
+  for item in items {
+    keyed.push(TagItem::new(item_key(item, tag_type)?, item.value.clone()));
+  }
+  tag.retain(|_| false);
+  for item in keyed {
+    tag.push_unchecked(item);
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_raw_items() {
+    let mut tag = Tag::new(TagType::Id3v2);
+    tag.insert_text(ItemKey::TrackArtist, "Artist".to_string());
+    tag.push_unchecked(TagItem::new(
+      ItemKey::Unknown("MY FIELD".to_string()),
+      ItemValue::Text("B000002UAL".to_string()),
+    ));
+    let items = raw_items(&tag);
+    assert_eq!(
+      items,
+      vec![
+        RawTagItem {
+          tag_type: "Id3v2".to_string(),
+          key: "TrackArtist".to_string(),
+          key_string: Some("TPE1".to_string()),
+          value: ItemValue::Text("Artist".to_string()),
+        },
+        RawTagItem {
+          tag_type: "Id3v2".to_string(),
+          key: "Unknown".to_string(),
+          key_string: Some("MY FIELD".to_string()),
+          value: ItemValue::Text("B000002UAL".to_string()),
+        },
+      ]
+    );
+
+    let mut vorbis = Tag::new(TagType::VorbisComments);
+    let title = RawTagItem {
+      tag_type: "VorbisComments".to_string(),
+      key: "TrackTitle".to_string(),
+      key_string: Some("TITLE".to_string()),
+      value: ItemValue::Text("Title".to_string()),
+    };
+    set_raw_items(&mut vorbis, &[&title]).unwrap();
+    assert_eq!(vorbis.get_string(&ItemKey::TrackTitle), Some("Title"));
+    let nameless = RawTagItem {
+      key_string: None,
+      ..title
+    };
+    assert!(set_raw_items(&mut vorbis, &[&nameless]).is_err());
+    let mut id3v1 = Tag::new(TagType::Id3v1);
+    set_raw_items(&mut id3v1, &[&nameless]).unwrap();
+    assert_eq!(id3v1.get_string(&ItemKey::TrackTitle), Some("Title"));
+    assert_eq!(parse_tag_type("Mp4Ilst"), Ok(TagType::Mp4Ilst));
+    assert!(parse_tag_type("Id3v3").is_err());
+  }
+}
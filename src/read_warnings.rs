@@ -0,0 +1,234 @@
+use crate::dsd;
+use crate::probe;
+use crate::util::mpeg_stand_in;
+use lofty::config::{ParseOptions, ParsingMode};
+use lofty::probe::Probe;
+use std::io::{Cursor, Read, Seek};
+
+const ID3V2_HEADER_LEN: usize = 10;
+
+fn be_size(bytes: &[u8]) -> u32 {
+  bytes
+    .iter()
+    .fold(0, |size, byte| (size << 8) | u32::from(*byte))
+}
+
+// the leading ID3v2 tag of a file, as much of it as the file holds
+fn leading_id3v2<R: Read + Seek>(reader: &mut R) -> Option<Vec<u8>> {
+  let mut header = [0; ID3V2_HEADER_LEN];
+  reader.rewind().ok()?;
+  reader.read_exact(&mut header).ok()?;
+  let size = probe::id3v2_size(&header)?;
+  let mut tag = header.to_vec();
+  reader
+    .by_ref()
+    .take(u64::from(size) - ID3V2_HEADER_LEN as u64)
+    .read_to_end(&mut tag)
+    .ok()?;
+  Some(tag)
+}
+
+// A frame of an ID3v2 tag, as stored
+pub(crate) struct RawFrame<'a> {
+  pub id: &'a str,
+  // the body is compressed, encrypted or otherwise not stored as is
+  pub transformed: bool,
+  pub body: &'a [u8],
+}
+
+// The frames of an ID3v2.3 or ID3v2.4 tag, in tag order
+pub(crate) struct RawFrames<'a> {
+  pub frames: Vec<RawFrame<'a>>,
+  // the ID3v2.4 frame sizes are plain integers rather than synchsafe ones, as
+  // iTunes wrote them
+  pub plain_sizes: bool,
+}
+
+/**
+ * Walk the frames of an ID3v2 tag, as far as the frame headers are intact
+ * @param tag - The whole tag
+ * @returns None for ID3v2.2 tags and unsynchronised ID3v2.3 tags, whose frame
+ * headers can't be walked as is
+ */
+pub(crate) fn raw_frames(tag: &[u8]) -> Option<RawFrames<'_>> {
+  let (version, flags) = (*tag.get(3)?, *tag.get(5)?);
+  if !(3..=4).contains(&version) || (version == 3 && flags & 0x80 != 0) {
+    return None;
+  }
+  let mut offset = ID3V2_HEADER_LEN;
+  if flags & 0x40 != 0 {
+    let size = tag.get(offset..offset + 4)?;
+    offset += match version {
+      3 => 4 + be_size(size),
+      _ => probe::synchsafe(size),
+    } as usize;
+  }
+  let mut raw = RawFrames {
+    frames: Vec::new(),
+    plain_sizes: false,
+  };
+  // padding, a zero byte, ends the frames
+  while offset + ID3V2_HEADER_LEN <= tag.len() && tag[offset] != 0 {
+    let header = &tag[offset..offset + ID3V2_HEADER_LEN];
+    let Ok(id) = std::str::from_utf8(&header[..4]) else {
+      break;
+    };
+    let size_bytes = &header[4..8];
+    let size = if version == 4 && size_bytes.iter().all(|byte| byte & 0x80 == 0) {
+      probe::synchsafe(size_bytes)
+    } else {
+      raw.plain_sizes |= version == 4;
+      be_size(size_bytes)
+    } as usize;
+    let body_start = offset + ID3V2_HEADER_LEN;
+    raw.frames.push(RawFrame {
+      id,
+      transformed: match version {
+        3 => header[9] & 0xE0 != 0,
+        _ => header[9] & 0x4F != 0,
+      },
+      body: &tag[body_start..(body_start + size).min(tag.len())],
+    });
+    offset = body_start + size;
+  }
+  Some(raw)
+}
+
+/**
+ * The ID3v2 tag of a file: the tag it starts with, or the tag of a DSD file
+ * @param reader - The file
+ */
+pub(crate) fn file_id3v2<R: Read + Seek>(reader: &mut R) -> Option<Vec<u8>> {
+  match dsd::format(reader) {
+    Some(format) => dsd::read_id3v2(reader, format).ok().flatten(),
+    None => leading_id3v2(reader),
+  }
+}
+
+// what lofty reads past in an ID3v2 tag: frame sizes that aren't synchsafe and
+// text frames stored more than once, of which one is read. TXXX frames are told
+// apart by description and left out.
+fn id3v2_warnings(tag: &[u8]) -> Vec<String> {
+  let Some(raw) = raw_frames(tag) else {
+    return Vec::new();
+  };
+  let mut seen: Vec<&str> = Vec::new();
+  let mut duplicates: Vec<&str> = Vec::new();
+  for frame in raw.frames.iter().filter(|frame| frame.id.starts_with('T')) {
+    if frame.id == "TXXX" {
+      continue;
+    }
+    if !seen.contains(&frame.id) {
+      seen.push(frame.id);
+    } else if !duplicates.contains(&frame.id) {
+      duplicates.push(frame.id);
+    }
+  }
+  let mut warnings = Vec::new();
+  if raw.plain_sizes {
+    warnings.push("ID3v2.4 frame sizes are not synchsafe".to_string());
+  }
+  for id in duplicates {
+    warnings.push(format!("Duplicate {} frames, only one is read", id));
+  }
+  warnings
+}
+
+// why the file fails to read strictly, which reads skip past otherwise
+fn strict_read_error<R: Read + Seek>(reader: &mut R) -> Option<String> {
+  reader.rewind().ok()?;
+  let probe = Probe::new(reader)
+    .options(ParseOptions::new().parsing_mode(ParsingMode::Strict))
+    .guess_file_type()
+    .ok()?;
+  probe.read().err().map(|e| e.to_string())
+}
+
+/**
+ * List the recoverable problems of a file, which reads get past without
+ * failing: invalid data skipped over, ID3v2.4 frame sizes that aren't
+ * synchsafe and duplicate ID3v2 frames
+ * @param reader - The file
+ * @returns The problems, empty for a healthy file
+ */
+pub(crate) fn read_warnings<R: Read + Seek>(reader: &mut R) -> Vec<String> {
+  let id3v2 = file_id3v2(reader);
+  let strict_error = match dsd::format(reader) {
+    Some(_) => {
+      let stand_in = mpeg_stand_in(id3v2.as_deref().unwrap_or_default());
+      strict_read_error(&mut Cursor::new(stand_in))
+    }
+    None => strict_read_error(reader),
+  };
+  let mut warnings = id3v2.map(|tag| id3v2_warnings(&tag)).unwrap_or_default();
+  warnings.extend(strict_error);
+  warnings
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_support::sample_mp3;
+  use crate::util::{self, AudioTags, ReadTagsOptions};
+
+  fn frame(id: &str, size: [u8; 4], text: &str) -> Vec<u8> {
+    let mut frame = id.as_bytes().to_vec();
+    frame.extend_from_slice(&size);
+    frame.extend_from_slice(&[0, 0, 3]);
+    frame.extend_from_slice(text.as_bytes());
+    frame
+  }
+
+  fn id3v24(frames: &[Vec<u8>]) -> Vec<u8> {
+    let body = frames.concat();
+    let size = body.len() as u32;
+    let mut tag = b"ID3\x04\0\0".to_vec();
+    tag.extend((0..4).rev().map(|i| ((size >> (7 * i)) & 0x7F) as u8));
+    tag.extend(body);
+    tag
+  }
+
+  #[tokio::test]
+  async fn test_read_warnings() {
+    let tags = AudioTags {
+      title: Some("Title".to_string()),
+      ..Default::default()
+    };
+    let healthy = util::write_tags_to_buffer(sample_mp3(), tags)
+      .await
+      .unwrap();
+    assert_eq!(
+      read_warnings(&mut Cursor::new(healthy)),
+      Vec::<String>::new()
+    );
+
+    let duplicated = mpeg_stand_in(&id3v24(&[
+      frame("TIT2", [0, 0, 0, 6], "Title"),
+      frame("TPE1", [0, 0, 0, 7], "Artist"),
+      frame("TIT2", [0, 0, 0, 6], "Other"),
+    ]));
+    assert_eq!(
+      read_warnings(&mut Cursor::new(duplicated.clone())),
+      vec!["Duplicate TIT2 frames, only one is read"]
+    );
+    // the read still succeeds
+    let options = ReadTagsOptions {
+      report_warnings: true,
+      ..Default::default()
+    };
+    let outcome = util::read_tags_from_buffer_with_options(duplicated, options)
+      .await
+      .unwrap();
+    assert_eq!(outcome.tags.artists, Some(vec!["Artist".to_string()]));
+    assert_eq!(outcome.warnings.map(|warnings| warnings.len()), Some(1));
+
+    let long_title = "a".repeat(199);
+    let itunes = mpeg_stand_in(&id3v24(&[frame("TIT2", [0, 0, 0, 200], &long_title)]));
+    // which strict reads fail on as well
+    let warnings = read_warnings(&mut Cursor::new(itunes));
+    assert_eq!(warnings[0], "ID3v2.4 frame sizes are not synchsafe");
+
+    let invalid = mpeg_stand_in(&id3v24(&[frame("T!T2", [0, 0, 0, 6], "Title")]));
+    assert_eq!(read_warnings(&mut Cursor::new(invalid)).len(), 1);
+  }
+}
@@ -0,0 +1,667 @@
+use crate::patch::TagPatch;
+use crate::util::{self, AudioTags, Position};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// the longest file or directory name most file systems allow, in bytes
+const MAX_NAME_BYTES: usize = 255;
+// the most suffixes tried for a free name
+const MAX_SUFFIX: u32 = 999;
+// the fields a template can name
+const FIELDS: [&str; 19] = [
+  "title",
+  "subtitle",
+  "artist",
+  "album",
+  "albumArtist",
+  "year",
+  "genre",
+  "track",
+  "trackTotal",
+  "disc",
+  "discTotal",
+  "discSubtitle",
+  "composer",
+  "conductor",
+  "publisher",
+  "catalogNumber",
+  "grouping",
+  "work",
+  "movement",
+];
+// the fields that hold numbers
+const NUMBER_FIELDS: [&str; 5] = ["year", "track", "trackTotal", "disc", "discTotal"];
+// a placeholder matching text that is left out of the inferred tags
+const IGNORED_FIELD: &str = "_";
+// names Windows reserves for devices, whatever the extension
+const RESERVED_NAMES: [&str; 22] = [
+  "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+  "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+// What to do when the new path is taken by another file
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum CollisionPolicy {
+  // add " (1)", " (2)", ... to the file name
+  #[default]
+  AddSuffix,
+  // leave the file where it is
+  Skip,
+  Fail,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct RenameOptions {
+  // the directory the template is relative to, None for the file's own
+  pub root: Option<String>,
+  // work out the new path without moving the file
+  pub dry_run: bool,
+  pub on_collision: CollisionPolicy,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct InferOptions {
+  // write the inferred tags to the file, keeping its other fields
+  pub write: bool,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct RenameOutcome {
+  pub file_path: String,
+  // where the file is, or would be after a dry run
+  pub new_path: String,
+  // false for dry runs, skipped collisions and files already in place
+  pub renamed: bool,
+}
+
+// A `{name:width|fallback}` placeholder of a template
+#[derive(Debug, PartialEq)]
+struct Placeholder<'a> {
+  name: &'a str,
+  // pad numbers with zeros to this many digits
+  width: Option<usize>,
+  fallback: Option<&'a str>,
+}
+
+// A part of a template
+#[derive(Debug, PartialEq)]
+enum Segment<'a> {
+  Literal(&'a str),
+  Field(Placeholder<'a>),
+}
+
+fn parse_placeholder(spec: &str) -> Result<Placeholder<'_>, String> {
+  let (spec, fallback) = match spec.split_once('|') {
+    Some((spec, fallback)) => (spec, Some(fallback)),
+    None => (spec, None),
+  };
+  let (name, width) = match spec.split_once(':') {
+    Some((name, format)) => {
+      let width = format
+        .strip_prefix('0')
+        .and_then(|width| width.parse::<usize>().ok())
+        .ok_or_else(|| format!("Invalid format in template: {{{}}}", spec))?;
+      (name, Some(width))
+    }
+    None => (spec, None),
+  };
+  Ok(Placeholder {
+    name: name.trim(),
+    width,
+    fallback,
+  })
+}
+
+// the value of a placeholder, None when the tags don't have one
+fn field_value(tags: &AudioTags, placeholder: &Placeholder) -> Result<Option<String>, String> {
+  let text = |value: &Option<String>| value.clone().filter(|value| !value.trim().is_empty());
+  let list = |values: &Option<Vec<String>>| {
+    values
+      .as_ref()
+      .map(|values| values.join(", "))
+      .filter(|value| !value.trim().is_empty())
+  };
+  let number = |value: Option<u32>| {
+    value.map(|value| match placeholder.width {
+      Some(width) => format!("{:0width$}", value, width = width),
+      None => value.to_string(),
+    })
+  };
+  Ok(match placeholder.name {
+    "title" => text(&tags.title),
+    "subtitle" => text(&tags.subtitle),
+    "artist" => list(&tags.artists),
+    "album" => text(&tags.album),
+    "albumArtist" => list(&tags.album_artists),
+    "year" => number(tags.year),
+    "genre" => text(&tags.genre),
+    "track" => number(tags.track.as_ref().and_then(|track| track.no)),
+    "trackTotal" => number(tags.track.as_ref().and_then(|track| track.of)),
+    "disc" => number(tags.disc.as_ref().and_then(|disc| disc.no)),
+    "discTotal" => number(tags.disc.as_ref().and_then(|disc| disc.of)),
+    "discSubtitle" => text(&tags.disc_subtitle),
+    "composer" => text(&tags.composer),
+    "conductor" => text(&tags.conductor),
+    "publisher" => text(&tags.publisher),
+    "catalogNumber" => text(&tags.catalog_number),
+    "grouping" => text(&tags.grouping),
+    "work" => text(&tags.work),
+    "movement" => text(&tags.movement),
+    name => return Err(format!("Unknown field in template: {}", name)),
+  })
+}
+
+// a tag value made safe to be part of a file name: the characters file systems
+// reject, path separators among them, are replaced
+fn sanitize_value(value: &str) -> String {
+  value
+    .chars()
+    .map(|c| match c {
+      '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+      c if c.is_control() => '_',
+      c => c,
+    })
+    .collect()
+}
+
+/**
+ * Split a template into its literal text and placeholders
+ * @param template - The template, with `{field}`, `{field:02}` or
+ * `{field|fallback}` placeholders
+ */
+fn segments(template: &str) -> Result<Vec<Segment<'_>>, String> {
+  let mut segments = Vec::new();
+  let mut rest = template;
+  while let Some(start) = rest.find('{') {
+    if start > 0 {
+      segments.push(Segment::Literal(&rest[..start]));
+    }
+    let end = rest[start..]
+      .find('}')
+      .ok_or_else(|| format!("Unclosed placeholder in template: {}", template))?;
+    segments.push(Segment::Field(parse_placeholder(
+      &rest[start + 1..start + end],
+    )?));
+    rest = &rest[start + end + 1..];
+  }
+  if !rest.is_empty() {
+    segments.push(Segment::Literal(rest));
+  }
+  Ok(segments)
+}
+
+/**
+ * Render a template with the values of the tags
+ * @param template - The path, with `/` between directories
+ * @param tags - The tags to take the values from
+ * @returns The path, without extension
+ */
+fn render(template: &str, tags: &AudioTags) -> Result<String, String> {
+  let mut rendered = String::new();
+  for segment in segments(template)? {
+    match segment {
+      Segment::Literal(text) => rendered.push_str(text),
+      Segment::Field(placeholder) => {
+        let value = match field_value(tags, &placeholder)? {
+          Some(value) => value,
+          None => placeholder
+            .fallback
+            .map(str::to_string)
+            .ok_or_else(|| format!("No value for {{{}}}", placeholder.name))?,
+        };
+        rendered.push_str(&sanitize_value(&value));
+      }
+    }
+  }
+  Ok(rendered)
+}
+
+// the longest start of `name` that fits in `max` bytes
+fn truncate(name: &str, max: usize) -> &str {
+  let mut end = name.len().min(max);
+  while !name.is_char_boundary(end) {
+    end -= 1;
+  }
+  &name[..end]
+}
+
+// a rendered file or directory name as the file systems take it: without the
+// spaces and dots Windows drops at the end, and not a reserved device name
+fn clean_component(component: &str, max: usize) -> Result<String, String> {
+  let component = truncate(component.trim(), max).trim_end_matches(['.', ' ']);
+  if component.is_empty() {
+    return Err("Template renders an empty file or directory name".to_string());
+  }
+  let stem = component.split('.').next().unwrap_or_default();
+  if RESERVED_NAMES
+    .iter()
+    .any(|reserved| reserved.eq_ignore_ascii_case(stem.trim_end()))
+  {
+    return Ok(format!("_{}", component));
+  }
+  Ok(component.to_string())
+}
+
+/**
+ * The path a file is renamed to, before collisions are handled
+ * @param file_path - The file
+ * @param template - The template, relative to the root
+ * @param tags - The tags of the file
+ * @param root - The directory the template is relative to, None for the
+ * file's own
+ * @returns The directory and the file name, without extension
+ */
+fn planned_path(
+  file_path: &Path,
+  template: &str,
+  tags: &AudioTags,
+  root: Option<&str>,
+) -> Result<(PathBuf, String), String> {
+  let rendered = render(template, tags)?;
+  let mut components: Vec<&str> = rendered.split(['/', '\\']).collect();
+  let name = components.pop().unwrap_or_default();
+  let mut dir = match root {
+    Some(root) => PathBuf::from(root),
+    None => file_path
+      .parent()
+      .map(Path::to_path_buf)
+      .unwrap_or_default(),
+  };
+  for component in components {
+    dir.push(clean_component(component, MAX_NAME_BYTES)?);
+  }
+  Ok((dir, clean_component(name, MAX_NAME_BYTES)?))
+}
+
+// the file name with a collision suffix and the extension, cut to fit
+fn file_name(stem: &str, suffix: u32, extension: &str) -> String {
+  let suffix = if suffix == 0 {
+    String::new()
+  } else {
+    format!(" ({})", suffix)
+  };
+  let room = MAX_NAME_BYTES.saturating_sub(suffix.len() + extension.len());
+  format!("{}{}{}", truncate(stem, room).trim_end(), suffix, extension)
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+  match (fs::canonicalize(a), fs::canonicalize(b)) {
+    (Ok(a), Ok(b)) => a == b,
+    _ => false,
+  }
+}
+
+/**
+ * Rename a file after its tags, moving it into the directories the template
+ * names. The extension is kept.
+ * @param file_path - The file to rename
+ * @param template - The new path relative to the root, e.g.
+ * `{albumArtist}/{album}/{track:02} {title}`
+ * @param options - The root, dry run and collision policy
+ * @returns Where the file went
+ */
+pub async fn rename_from_tags(
+  file_path: impl AsRef<Path>,
+  template: String,
+  options: RenameOptions,
+) -> Result<RenameOutcome, String> {
+  let source = file_path.as_ref();
+  let tags = util::read_tags(source).await?;
+  let file_path = source.to_string_lossy().into_owned();
+  let (dir, stem) = planned_path(source, &template, &tags, options.root.as_deref())?;
+  let extension = source
+    .extension()
+    .map(|extension| format!(".{}", extension.to_string_lossy()))
+    .unwrap_or_default();
+
+  let mut target = dir.join(file_name(&stem, 0, &extension));
+  let mut suffix = 0;
+  while target.exists() && !same_file(&target, source) {
+    match options.on_collision {
+      CollisionPolicy::AddSuffix if suffix < MAX_SUFFIX => {
+        suffix += 1;
+        target = dir.join(file_name(&stem, suffix, &extension));
+      }
+      CollisionPolicy::Skip => {
+        return Ok(RenameOutcome {
+          new_path: file_path.clone(),
+          file_path,
+          renamed: false,
+        })
+      }
+      _ => return Err(format!("File already exists: {}", target.to_string_lossy())),
+    }
+  }
+
+  let new_path = target.to_string_lossy().to_string();
+  // already in place; a rename that only changes the case still goes ahead
+  if options.dry_run || target.as_os_str() == source.as_os_str() {
+    return Ok(RenameOutcome {
+      file_path,
+      new_path,
+      renamed: false,
+    });
+  }
+  fs::create_dir_all(&dir).map_err(|e| format!("Failed to create directory: {}", e))?;
+  fs::rename(source, &target).map_err(|e| format!("Failed to rename file: {}", e))?;
+  Ok(RenameOutcome {
+    file_path,
+    new_path,
+    renamed: true,
+  })
+}
+
+// whether a placeholder can match the text
+fn accepts(name: &str, value: &str) -> bool {
+  if NUMBER_FIELDS.contains(&name) {
+    value.parse::<u32>().is_ok()
+  } else {
+    !value.is_empty()
+  }
+}
+
+/**
+ * Match a file or directory name against the segments of a template,
+ * collecting the text each placeholder matched. Placeholders match as little
+ * as they can, so `{track} - {artist} - {title}` reads a title containing " - ".
+ * @param segments - The segments left to match
+ * @param text - The text left to match
+ * @param captures - The names of the placeholders matched so far and their text
+ */
+fn match_segments<'s, 't>(
+  segments: &[Segment<'s>],
+  text: &'t str,
+  captures: &mut Vec<(&'s str, &'t str)>,
+) -> bool {
+  let Some((first, rest)) = segments.split_first() else {
+    return text.is_empty();
+  };
+  match first {
+    Segment::Literal(literal) => text
+      .strip_prefix(literal)
+      .is_some_and(|text| match_segments(rest, text, captures)),
+    Segment::Field(placeholder) => {
+      let ends: Vec<usize> = if rest.is_empty() {
+        vec![text.len()]
+      } else {
+        text.char_indices().map(|(i, c)| i + c.len_utf8()).collect()
+      };
+      for end in ends {
+        let value = text[..end].trim();
+        if !accepts(placeholder.name, value) {
+          continue;
+        }
+        captures.push((placeholder.name, value));
+        if match_segments(rest, &text[end..], captures) {
+          return true;
+        }
+        captures.pop();
+      }
+      false
+    }
+  }
+}
+
+// set the field a placeholder names, from text `accepts` took
+fn set_field(tags: &mut AudioTags, name: &str, value: &str) {
+  let text = Some(value.to_string());
+  let number = value.parse::<u32>().ok();
+  let unset = || Position { no: None, of: None };
+  match name {
+    "title" => tags.title = text,
+    "subtitle" => tags.subtitle = text,
+    "artist" => tags.artists = Some(vec![value.to_string()]),
+    "album" => tags.album = text,
+    "albumArtist" => tags.album_artists = Some(vec![value.to_string()]),
+    "year" => tags.year = number,
+    "genre" => tags.genre = text,
+    "track" => tags.track.get_or_insert_with(unset).no = number,
+    "trackTotal" => tags.track.get_or_insert_with(unset).of = number,
+    "disc" => tags.disc.get_or_insert_with(unset).no = number,
+    "discTotal" => tags.disc.get_or_insert_with(unset).of = number,
+    "discSubtitle" => tags.disc_subtitle = text,
+    "composer" => tags.composer = text,
+    "conductor" => tags.conductor = text,
+    "publisher" => tags.publisher = text,
+    "catalogNumber" => tags.catalog_number = text,
+    "grouping" => tags.grouping = text,
+    "work" => tags.work = text,
+    "movement" => tags.movement = text,
+    _ => {}
+  }
+}
+
+/**
+ * Infer tags from the path of a file, the inverse of renaming it after its
+ * tags. The pattern uses the placeholders of the rename templates, `{_}`
+ * matching text to ignore; widths and fallbacks are ignored. Its last part
+ * matches the file name without extension, the ones before it the directories
+ * the file is in.
+ * @param file_path - The file
+ * @param pattern - The pattern, e.g. `{artist}/{album}/{track} - {title}`
+ * @param options - Whether to write the tags found to the file
+ * @returns Only the fields the pattern names
+ */
+pub async fn infer_tags_from_filename(
+  file_path: impl AsRef<Path>,
+  pattern: String,
+  options: InferOptions,
+) -> Result<AudioTags, String> {
+  let parts = pattern
+    .split(['/', '\\'])
+    .map(segments)
+    .collect::<Result<Vec<_>, String>>()?;
+  for segment in parts.iter().flatten() {
+    if let Segment::Field(placeholder) = segment {
+      if !FIELDS.contains(&placeholder.name) && placeholder.name != IGNORED_FIELD {
+        return Err(format!("Unknown field in template: {}", placeholder.name));
+      }
+    }
+  }
+
+  // the file name without extension and the directories above it, innermost first
+  let path = file_path.as_ref();
+  let names: Vec<String> = path
+    .file_stem()
+    .into_iter()
+    .chain(path.ancestors().skip(1).filter_map(Path::file_name))
+    .take(parts.len())
+    .map(|name| name.to_string_lossy().to_string())
+    .collect();
+  let mismatch = || format!("File path doesn't match the pattern: {}", path.display());
+  if names.len() < parts.len() {
+    return Err(mismatch());
+  }
+
+  let mut tags = AudioTags::default();
+  for (part, name) in parts.iter().rev().zip(&names) {
+    let mut captures = Vec::new();
+    if !match_segments(part, name, &mut captures) {
+      return Err(mismatch());
+    }
+    for (field, value) in captures {
+      set_field(&mut tags, field, value);
+    }
+  }
+
+  if options.write {
+    let patch = TagPatch {
+      set: tags.clone(),
+      clear: Vec::new(),
+    };
+    util::update_tags(file_path, patch).await?;
+  }
+  Ok(tags)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_support::{sample_mp3, write_fixture};
+  use crate::util::Position;
+
+  fn tags() -> AudioTags {
+    AudioTags {
+      title: Some("What? Now: Part 1/2".to_string()),
+      artists: Some(vec!["A".to_string(), "B".to_string()]),
+      album: Some("Album.".to_string()),
+      track: Some(Position {
+        no: Some(3),
+        of: Some(12),
+      }),
+      ..Default::default()
+    }
+  }
+
+  #[test]
+  fn test_render() {
+    let tags = tags();
+    assert_eq!(
+      render("{artist}/{album}/{track:02} {title}", &tags).unwrap(),
+      "A, B/Album./03 What_ Now_ Part 1_2"
+    );
+    assert_eq!(
+      render("{albumArtist|Unknown Artist} - {year|0000}", &tags).unwrap(),
+      "Unknown Artist - 0000"
+    );
+    assert_eq!(render("{year}", &tags).unwrap_err(), "No value for {year}");
+    assert!(render("{nope}", &tags).is_err());
+    assert!(render("{track:2}", &tags).is_err());
+    assert!(render("{title", &tags).is_err());
+  }
+
+  #[test]
+  fn test_clean_component() {
+    assert_eq!(clean_component(" Album. ", 255).unwrap(), "Album");
+    assert_eq!(clean_component("con.mp3", 255).unwrap(), "_con.mp3");
+    assert_eq!(clean_component("Console", 255).unwrap(), "Console");
+    assert!(clean_component("..", 255).is_err());
+    assert_eq!(clean_component("ééé", 5).unwrap(), "éé");
+  }
+
+  #[tokio::test]
+  async fn test_rename_from_tags() {
+    let dir = tempfile::tempdir().unwrap();
+    let tagged = util::write_tags_to_buffer(sample_mp3(), tags())
+      .await
+      .unwrap();
+    let path = write_fixture(dir.path(), "a.mp3", &tagged);
+    let template = "{artist}/{album}/{track:02} {title}".to_string();
+    let root = dir.path().to_string_lossy().to_string();
+    let expected = dir
+      .path()
+      .join("A, B")
+      .join("Album")
+      .join("03 What_ Now_ Part 1_2.mp3");
+
+    let options = RenameOptions {
+      root: Some(root.clone()),
+      dry_run: true,
+      ..Default::default()
+    };
+    let outcome = rename_from_tags(path.clone(), template.clone(), options)
+      .await
+      .unwrap();
+    assert_eq!(outcome.new_path, expected.to_string_lossy());
+    assert!(!outcome.renamed);
+    assert!(Path::new(&path).exists());
+
+    // the name is taken by another file
+    fs::create_dir_all(expected.parent().unwrap()).unwrap();
+    fs::write(&expected, b"taken").unwrap();
+    let options = RenameOptions {
+      root: Some(root.clone()),
+      on_collision: CollisionPolicy::Fail,
+      ..Default::default()
+    };
+    let result = rename_from_tags(path.clone(), template.clone(), options).await;
+    assert!(result.unwrap_err().starts_with("File already exists"));
+
+    let options = RenameOptions {
+      root: Some(root.clone()),
+      ..Default::default()
+    };
+    let outcome = rename_from_tags(path.clone(), template.clone(), options.clone())
+      .await
+      .unwrap();
+    assert!(outcome.renamed);
+    assert!(outcome.new_path.ends_with("03 What_ Now_ Part 1_2 (1).mp3"));
+    assert!(!Path::new(&path).exists());
+    assert!(Path::new(&outcome.new_path).exists());
+
+    // already in place, the suffixed name isn't its own collision
+    let moved = outcome.new_path;
+    fs::remove_file(&expected).unwrap();
+    let outcome = rename_from_tags(moved.clone(), template, options)
+      .await
+      .unwrap();
+    assert_eq!(outcome.new_path, expected.to_string_lossy());
+    assert!(outcome.renamed);
+  }
+
+  #[tokio::test]
+  async fn test_infer_tags_from_filename() {
+    let options = InferOptions::default();
+    let tags = infer_tags_from_filename(
+      "/music/Artist/Album (1999)/03 - Song - Live.mp3".to_string(),
+      "{artist}/{album} ({year})/{track:02} - {title}".to_string(),
+      options.clone(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(tags.artists, Some(vec!["Artist".to_string()]));
+    assert_eq!(tags.album, Some("Album".to_string()));
+    assert_eq!(tags.year, Some(1999));
+    assert_eq!(
+      tags.track,
+      Some(Position {
+        no: Some(3),
+        of: None
+      })
+    );
+    assert_eq!(tags.title, Some("Song - Live".to_string()));
+
+    // numbers only match digits
+    let tags = infer_tags_from_filename(
+      "Intro - 1 - Artist.flac".to_string(),
+      "{_} - {track} - {artist}".to_string(),
+      options.clone(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(tags.track.unwrap().no, Some(1));
+    assert_eq!(tags.title, None);
+
+    let result = infer_tags_from_filename(
+      "Song.mp3".to_string(),
+      "{track} - {title}".to_string(),
+      options.clone(),
+    )
+    .await;
+    assert!(result.unwrap_err().starts_with("File path doesn't match"));
+    let result =
+      infer_tags_from_filename("Song.mp3".to_string(), "{nope}".to_string(), options).await;
+    assert!(result.is_err());
+  }
+
+  #[tokio::test]
+  async fn test_infer_tags_from_filename_write() {
+    let dir = tempfile::tempdir().unwrap();
+    let tags = AudioTags {
+      album: Some("Kept".to_string()),
+      ..Default::default()
+    };
+    let tagged = util::write_tags_to_buffer(sample_mp3(), tags)
+      .await
+      .unwrap();
+    let path = write_fixture(dir.path(), "07 Untagged.mp3", &tagged);
+    let options = InferOptions { write: true };
+    infer_tags_from_filename(path.clone(), "{track} {title}".to_string(), options)
+      .await
+      .unwrap();
+    let tags = util::read_tags(path).await.unwrap();
+    assert_eq!(tags.title, Some("Untagged".to_string()));
+    assert_eq!(tags.track.unwrap().no, Some(7));
+    assert_eq!(tags.album, Some("Kept".to_string()));
+  }
+}
@@ -0,0 +1,398 @@
+use crate::util::AudioTags;
+use lofty::tag::{ItemKey, Tag};
+use std::io::{Read, Seek, SeekFrom};
+use std::ops::Range;
+
+const HEADER_LEN: usize = 12;
+// the fields of the bext chunk (EBU Tech 3285), in order
+const DESCRIPTION: Range<usize> = 0..256;
+const ORIGINATOR: Range<usize> = 256..288;
+const ORIGINATOR_REFERENCE: Range<usize> = 288..320;
+const ORIGINATION_DATE: Range<usize> = 320..330;
+const ORIGINATION_TIME: Range<usize> = 330..338;
+const TIME_REFERENCE: Range<usize> = 338..346;
+const VERSION: Range<usize> = 346..348;
+// the fields up to the coding history, which runs to the end of the chunk
+const BEXT_LEN: usize = 602;
+
+// The Broadcast Wave Format fields of the bext chunk of a WAV file; a missing
+// value is left alone on write
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Bwf {
+  pub description: Option<String>,
+  // the organisation or person that created the audio
+  pub originator: Option<String>,
+  pub originator_reference: Option<String>,
+  // "yyyy-mm-dd"
+  pub origination_date: Option<String>,
+  // "hh:mm:ss"
+  pub origination_time: Option<String>,
+  // the position of the first sample, in samples since midnight
+  pub time_reference: Option<u64>,
+}
+
+fn invalid(what: &str) -> String {
+  format!("Invalid WAV or AIFF file: {}", what)
+}
+
+// The chunk layout of a file: RIFF (WAV) sizes are little endian, IFF (AIFF)
+// sizes big endian
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) enum Container {
+  Riff,
+  Aiff,
+}
+
+impl Container {
+  fn from_header(header: &[u8]) -> Option<Self> {
+    if header.len() < HEADER_LEN {
+      return None;
+    }
+    match (&header[..4], &header[8..12]) {
+      (b"RIFF", b"WAVE") => Some(Self::Riff),
+      (b"FORM", b"AIFF" | b"AIFC") => Some(Self::Aiff),
+      _ => None,
+    }
+  }
+
+  fn size(self, bytes: &[u8]) -> u32 {
+    let bytes: [u8; 4] = bytes[..4].try_into().unwrap_or_default();
+    match self {
+      Self::Riff => u32::from_le_bytes(bytes),
+      Self::Aiff => u32::from_be_bytes(bytes),
+    }
+  }
+
+  fn size_bytes(self, size: u32) -> [u8; 4] {
+    match self {
+      Self::Riff => size.to_le_bytes(),
+      Self::Aiff => size.to_be_bytes(),
+    }
+  }
+}
+
+// the size of a chunk in the file, the pad byte of odd sizes included
+fn padded(size: u32) -> u64 {
+  u64::from(size) + u64::from(size % 2)
+}
+
+/**
+ * The container of a WAV or AIFF file, from its header
+ * @param reader - The file
+ * @returns None for other files
+ */
+pub(crate) fn container<R: Read + Seek>(reader: &mut R) -> Option<Container> {
+  let mut header = [0u8; HEADER_LEN];
+  reader.seek(SeekFrom::Start(0)).ok()?;
+  reader.read_exact(&mut header).ok()?;
+  Container::from_header(&header)
+}
+
+/**
+ * Where the content of the first chunk of `id` of a WAV or AIFF file is, found
+ * without reading the chunks before it
+ * @param reader - The file
+ * @param id - The chunk id
+ * @returns None when the file has no such chunk
+ */
+pub(crate) fn chunk_range<R: Read + Seek>(
+  reader: &mut R,
+  id: &[u8; 4],
+) -> Result<Option<Range<u64>>, String> {
+  let read_error = |e: std::io::Error| format!("Failed to read file: {}", e);
+  let container = container(reader).ok_or_else(|| invalid("missing RIFF or FORM header"))?;
+  let mut chunk_header = [0u8; 8];
+  while reader.read_exact(&mut chunk_header).is_ok() {
+    let size = container.size(&chunk_header[4..8]);
+    if &chunk_header[..4] == id {
+      let start = reader.stream_position().map_err(read_error)?;
+      return Ok(Some(start..start + u64::from(size)));
+    }
+    reader
+      .seek(SeekFrom::Current(padded(size) as i64))
+      .map_err(read_error)?;
+  }
+  Ok(None)
+}
+
+/**
+ * The content of the first chunk of `id` of a WAV or AIFF file, read without
+ * loading the chunks before it
+ * @param reader - The file
+ * @param id - The chunk id
+ * @returns None when the file has no such chunk
+ */
+pub(crate) fn read_chunk<R: Read + Seek>(
+  reader: &mut R,
+  id: &[u8; 4],
+) -> Result<Option<Vec<u8>>, String> {
+  let Some(range) = chunk_range(reader, id)? else {
+    return Ok(None);
+  };
+  let mut content = Vec::new();
+  reader
+    .take(range.end - range.start)
+    .read_to_end(&mut content)
+    .map_err(|e| format!("Failed to read file: {}", e))?;
+  Ok(Some(content))
+}
+
+// where the first chunk of `id` is in a file in memory, its pad byte included,
+// and the size of its content
+fn find_chunk(data: &[u8], container: Container, id: &[u8; 4]) -> Option<(Range<usize>, usize)> {
+  let mut start = HEADER_LEN;
+  while data.len() - start >= 8 {
+    let size = container.size(&data[start + 4..start + 8]);
+    let end = (start as u64 + 8 + padded(size)).min(data.len() as u64) as usize;
+    if &data[start..start + 4] == id {
+      return Some((start..end, (size as usize).min(end - start - 8)));
+    }
+    start = end;
+  }
+  None
+}
+
+/**
+ * The content of the first chunk of `id` of a WAV or AIFF file in memory
+ * @param data - The file
+ * @param id - The chunk id
+ */
+pub(crate) fn chunk_content(data: &[u8], id: &[u8; 4]) -> Result<Option<Vec<u8>>, String> {
+  let container =
+    Container::from_header(data).ok_or_else(|| invalid("missing RIFF or FORM header"))?;
+  Ok(
+    find_chunk(data, container, id)
+      .map(|(range, size)| data[range.start + 8..range.start + 8 + size].to_vec()),
+  )
+}
+
+/**
+ * Replace the first chunk of `id` of a WAV or AIFF file, or add it after the
+ * header when the file has none, and update the size of the file
+ * @param data - The file
+ * @param id - The chunk id
+ * @param content - The content of the chunk
+ * @returns The file with the chunk written
+ */
+pub(crate) fn set_chunk(
+  mut data: Vec<u8>,
+  id: &[u8; 4],
+  content: &[u8],
+) -> Result<Vec<u8>, String> {
+  let container =
+    Container::from_header(&data).ok_or_else(|| invalid("missing RIFF or FORM header"))?;
+  let range = find_chunk(&data, container, id)
+    .map(|(range, _)| range)
+    .unwrap_or(HEADER_LEN..HEADER_LEN);
+  let size = u32::try_from(content.len()).map_err(|_| invalid("chunk too large"))?;
+  let mut chunk = id.to_vec();
+  chunk.extend(container.size_bytes(size));
+  chunk.extend(content);
+  if content.len() % 2 == 1 {
+    chunk.push(0);
+  }
+  data.splice(range, chunk);
+  let file_size = u32::try_from(data.len() - 8).map_err(|_| invalid("too large"))?;
+  data[4..8].copy_from_slice(&container.size_bytes(file_size));
+  Ok(data)
+}
+
+// ASCII text up to the first NUL, None when empty
+fn text(field: &[u8]) -> Option<String> {
+  let end = field
+    .iter()
+    .position(|byte| *byte == 0)
+    .unwrap_or(field.len());
+  let text = String::from_utf8_lossy(&field[..end])
+    .trim_end()
+    .to_string();
+  Some(text).filter(|text| !text.is_empty())
+}
+
+// `value` in a fixed-size field, NUL padded; characters outside ASCII become
+// "?" as the fields are ASCII
+fn set_text(content: &mut [u8], field: Range<usize>, value: &str) {
+  let len = field.len();
+  let bytes = value
+    .chars()
+    .map(|c| if c.is_ascii() { c as u8 } else { b'?' })
+    .chain(std::iter::repeat(0))
+    .take(len);
+  for (byte, value) in content[field].iter_mut().zip(bytes) {
+    *byte = value;
+  }
+}
+
+// whether `value` has digits where `pattern` has "d" and the same characters
+// elsewhere
+fn matches_pattern(value: &str, pattern: &str) -> bool {
+  value.len() == pattern.len()
+    && value.bytes().zip(pattern.bytes()).all(|(c, p)| match p {
+      b'd' => c.is_ascii_digit(),
+      p => c == p,
+    })
+}
+
+/**
+ * Read the Broadcast Wave Format fields of a WAV file
+ * @param reader - The WAV file
+ * @returns None when the file has no bext chunk
+ */
+pub fn read_bwf<R: Read + Seek>(reader: &mut R) -> Result<Option<Bwf>, String> {
+  let Some(mut content) = read_chunk(reader, b"bext")? else {
+    return Ok(None);
+  };
+  content.resize(content.len().max(BEXT_LEN), 0);
+  let time_reference = u64::from_le_bytes(content[TIME_REFERENCE].try_into().unwrap_or_default());
+  Ok(Some(Bwf {
+    description: text(&content[DESCRIPTION]),
+    originator: text(&content[ORIGINATOR]),
+    originator_reference: text(&content[ORIGINATOR_REFERENCE]),
+    origination_date: text(&content[ORIGINATION_DATE]),
+    origination_time: text(&content[ORIGINATION_TIME]),
+    time_reference: Some(time_reference),
+  }))
+}
+
+/**
+ * Store Broadcast Wave Format fields in a WAV file, in its bext chunk or in a
+ * new one at the start of the file. The fields left out, the UMID, loudness
+ * values and coding history are kept.
+ * @param data - The WAV file
+ * @param bwf - The fields to set
+ * @returns The file with the chunk written
+ */
+pub fn write_bwf(data: Vec<u8>, bwf: &Bwf) -> Result<Vec<u8>, String> {
+  if let Some(date) = bwf.origination_date.as_deref() {
+    if !matches_pattern(date, "dddd-dd-dd") {
+      return Err(format!(
+        "Invalid BWF origination date: {}, expected yyyy-mm-dd",
+        date
+      ));
+    }
+  }
+  if let Some(time) = bwf.origination_time.as_deref() {
+    if !matches_pattern(time, "dd:dd:dd") {
+      return Err(format!(
+        "Invalid BWF origination time: {}, expected hh:mm:ss",
+        time
+      ));
+    }
+  }
+  if Container::from_header(&data) != Some(Container::Riff) {
+    return Err(invalid("missing RIFF header"));
+  }
+  let mut content = chunk_content(&data, b"bext")?.unwrap_or_else(|| {
+    let mut content = vec![0; BEXT_LEN];
+    content[VERSION].copy_from_slice(&1u16.to_le_bytes());
+    content
+  });
+  content.resize(content.len().max(BEXT_LEN), 0);
+
+  let fields = [
+    (DESCRIPTION, &bwf.description),
+    (ORIGINATOR, &bwf.originator),
+    (ORIGINATOR_REFERENCE, &bwf.originator_reference),
+    (ORIGINATION_DATE, &bwf.origination_date),
+    (ORIGINATION_TIME, &bwf.origination_time),
+  ];
+  for (field, value) in fields {
+    if let Some(value) = value {
+      set_text(&mut content, field, value);
+    }
+  }
+  if let Some(time_reference) = bwf.time_reference {
+    content[TIME_REFERENCE].copy_from_slice(&time_reference.to_le_bytes());
+  }
+
+  set_chunk(data, b"bext", &content)
+}
+
+/**
+ * Copy the fields a RIFF INFO chunk holds from a set of tags, so the chunk
+ * agrees with the primary tag. Fields the tags don't have are left alone.
+ * @param info - The RIFF INFO tag
+ * @param tags - The tags written
+ */
+pub fn set_info(info: &mut Tag, tags: &AudioTags) {
+  let artists = tags.artists.as_ref().map(|artists| artists.join(", "));
+  let genre = tags
+    .genres
+    .as_ref()
+    .and_then(|genres| genres.first())
+    .or(tags.genre.as_ref());
+  let year = tags.year.map(|year| year.to_string());
+  let track = tags
+    .track
+    .as_ref()
+    .and_then(|track| track.no)
+    .map(|no| no.to_string());
+  let fields = [
+    (ItemKey::TrackTitle, tags.title.as_ref()),
+    (ItemKey::TrackArtist, artists.as_ref()),
+    (ItemKey::AlbumTitle, tags.album.as_ref()),
+    (ItemKey::Comment, tags.comment.as_ref()),
+    (ItemKey::Genre, genre),
+    (ItemKey::RecordingDate, year.as_ref()),
+    (ItemKey::TrackNumber, track.as_ref()),
+    (ItemKey::Composer, tags.composer.as_ref()),
+    (ItemKey::CopyrightMessage, tags.copyright.as_ref()),
+    (ItemKey::EncoderSoftware, tags.encoder_software.as_ref()),
+  ];
+  for (key, value) in fields {
+    if let Some(value) = value {
+      info.remove_key(&key);
+      info.insert_text(key, value.clone());
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_support::sample_wav;
+  use std::io::Cursor;
+
+  #[test]
+  fn test_write_and_read_bwf() {
+    let wav = sample_wav();
+    assert_eq!(read_bwf(&mut Cursor::new(&wav)).unwrap(), None);
+
+    let bwf = Bwf {
+      description: Some("Scene 4, take 2".to_string()),
+      originator: Some("Recorder".to_string()),
+      origination_date: Some("2024-05-01".to_string()),
+      origination_time: Some("13:45:00".to_string()),
+      time_reference: Some(2_381_400_000),
+      ..Default::default()
+    };
+    let written = write_bwf(wav.clone(), &bwf).unwrap();
+    assert_eq!(written.len(), wav.len() + 8 + BEXT_LEN);
+    assert_eq!(&written[12..16], b"bext");
+    let riff_size = u32::from_le_bytes(written[4..8].try_into().unwrap());
+    assert_eq!(riff_size as usize, written.len() - 8);
+    assert_eq!(
+      read_bwf(&mut Cursor::new(&written)).unwrap(),
+      Some(bwf.clone())
+    );
+
+    // the chunk is updated in place, the fields left out are kept
+    let update = Bwf {
+      description: Some("Scene 4, take 3".to_string()),
+      ..Default::default()
+    };
+    let updated = write_bwf(written.clone(), &update).unwrap();
+    assert_eq!(updated.len(), written.len());
+    let read = read_bwf(&mut Cursor::new(&updated)).unwrap().unwrap();
+    assert_eq!(read.description, update.description);
+    assert_eq!(read.originator, bwf.originator);
+    assert_eq!(read.time_reference, bwf.time_reference);
+
+    let invalid_date = Bwf {
+      origination_date: Some("01/05/2024".to_string()),
+      ..Default::default()
+    };
+    assert!(write_bwf(wav, &invalid_date).is_err());
+    assert!(write_bwf(b"not a wav".to_vec(), &bwf).is_err());
+  }
+}
@@ -0,0 +1,353 @@
+use crate::batch::{self, Progress, ProgressCounter};
+use crate::cancel::Cancellation;
+use crate::formats;
+use crate::util::{self, AudioProperties, AudioTags};
+use std::collections::HashSet;
+use std::fs;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ScanOptions {
+  // walk into subdirectories
+  pub recursive: bool,
+  // without the leading dot, matched case-insensitively; None for those of the
+  // supported formats
+  pub extensions: Option<Vec<String>>,
+  // without it symlinks are left out, to files and directories alike
+  pub follow_symlinks: bool,
+  // maximum number of files read at once, None for one per core
+  pub concurrency: Option<usize>,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ScanResult {
+  pub file_path: String,
+  // None when the file could not be read
+  pub tags: Option<AudioTags>,
+  pub properties: Option<AudioProperties>,
+  // also set for subdirectories that could not be listed
+  pub error: Option<String>,
+}
+
+/**
+ * The extensions to match, normalized: lowercase and without the leading dot
+ * @param extensions - The extensions given, None for those of the supported
+ * formats
+ */
+pub(crate) fn extension_set(extensions: Option<&[String]>) -> HashSet<String> {
+  match extensions {
+    Some(extensions) => extensions
+      .iter()
+      .map(|extension| extension.trim_start_matches('.').to_lowercase())
+      .collect(),
+    None => formats::audio_extensions().map(str::to_string).collect(),
+  }
+}
+
+pub(crate) fn has_extension(extensions: &HashSet<String>, path: &Path) -> bool {
+  path
+    .extension()
+    .is_some_and(|extension| extensions.contains(&extension.to_string_lossy().to_lowercase()))
+}
+
+struct Walk {
+  options: ScanOptions,
+  extensions: HashSet<String>,
+  // the directories walked so far, so symlink loops are walked once
+  visited: HashSet<PathBuf>,
+  files: Vec<PathBuf>,
+  unreadable: Vec<ScanResult>,
+}
+
+impl Walk {
+  fn walk(&mut self, dir: &Path) -> Result<(), String> {
+    if let Ok(canonical) = fs::canonicalize(dir) {
+      if !self.visited.insert(canonical) {
+        return Ok(());
+      }
+    }
+    let mut entries: Vec<_> = fs::read_dir(dir)
+      .map_err(|e| format!("Failed to read directory: {}", e))?
+      .filter_map(|entry| entry.ok())
+      .collect();
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+      let path = entry.path();
+      let Ok(mut file_type) = entry.file_type() else {
+        continue;
+      };
+      if file_type.is_symlink() {
+        if !self.options.follow_symlinks {
+          continue;
+        }
+        // a dangling symlink
+        let Ok(metadata) = fs::metadata(&path) else {
+          continue;
+        };
+        file_type = metadata.file_type();
+      }
+      if file_type.is_dir() {
+        if self.options.recursive {
+          if let Err(error) = self.walk(&path) {
+            self.unreadable.push(ScanResult {
+              file_path: path.to_string_lossy().to_string(),
+              error: Some(error),
+              ..Default::default()
+            });
+          }
+        }
+      } else if file_type.is_file() && has_extension(&self.extensions, &path) {
+        self.files.push(path);
+      }
+    }
+    Ok(())
+  }
+}
+
+/**
+ * List the audio files under a directory, sorted by path within each directory
+ * @param root - The directory to scan
+ * @param options - The scan options
+ * @returns The files, and a result with the error for every subdirectory that
+ * could not be listed
+ */
+fn find_audio_files(
+  root: &Path,
+  options: &ScanOptions,
+) -> Result<(Vec<PathBuf>, Vec<ScanResult>), String> {
+  let mut walk = Walk {
+    options: options.clone(),
+    extensions: extension_set(options.extensions.as_deref()),
+    visited: HashSet::new(),
+    files: Vec::new(),
+    unreadable: Vec::new(),
+  };
+  walk.walk(root)?;
+  Ok((walk.files, walk.unreadable))
+}
+
+/**
+ * Read the tags and audio properties of every audio file under a directory, in
+ * parallel. A failing file doesn't stop the others.
+ * @param root - The directory to scan
+ * @param options - The scan options
+ * @param cancel - Cancelled when the caller aborts
+ * @param progress - Told about every file read or failed
+ * @returns One result per file, followed by the subdirectories that could not
+ * be listed
+ */
+pub async fn scan_directory<P: Progress>(
+  root: impl AsRef<Path>,
+  options: ScanOptions,
+  cancel: &Cancellation,
+  progress: P,
+) -> Result<Vec<ScanResult>, String> {
+  let (paths, unreadable) = find_audio_files(root.as_ref(), &options)?;
+  let counter = ProgressCounter::new(progress, paths.len());
+  let concurrency = options
+    .concurrency
+    .unwrap_or_else(batch::default_concurrency);
+  let mut results =
+    batch::map_concurrent_cancellable(paths, concurrency, cancel, move |file_path| {
+      scan_file(file_path, counter.clone())
+    })
+    .await?;
+  results.extend(unreadable);
+  Ok(results)
+}
+
+/**
+ * Same as scan_directory, handing every result over as soon as the file is
+ * read instead of collecting them, so the first results of a large library can
+ * be shown right away
+ * @param root - The directory to scan
+ * @param options - The scan options
+ * @param cancel - Cancelled when the caller aborts
+ * @param progress - Told about every file read or failed
+ * @param on_result - Takes the results in completion order, awaited before the
+ * next one; the subdirectories that could not be listed come last, and an
+ * error stops the scan
+ * @returns The number of results handed over
+ */
+pub async fn scan_directory_each<P, S, SinkFut>(
+  root: impl AsRef<Path>,
+  options: ScanOptions,
+  cancel: &Cancellation,
+  progress: P,
+  mut on_result: S,
+) -> Result<usize, String>
+where
+  P: Progress,
+  S: FnMut(ScanResult) -> SinkFut,
+  SinkFut: Future<Output = Result<(), String>>,
+{
+  let (paths, unreadable) = find_audio_files(root.as_ref(), &options)?;
+  let count = paths.len() + unreadable.len();
+  let counter = ProgressCounter::new(progress, paths.len());
+  let concurrency = options
+    .concurrency
+    .unwrap_or_else(batch::default_concurrency);
+  batch::for_each_concurrent_cancellable(
+    paths,
+    concurrency,
+    cancel,
+    move |file_path| scan_file(file_path, counter.clone()),
+    &mut on_result,
+  )
+  .await?;
+  for result in unreadable {
+    on_result(result).await?;
+  }
+  Ok(count)
+}
+
+async fn scan_file<P: Progress>(path: PathBuf, counter: Arc<ProgressCounter<P>>) -> ScanResult {
+  let result = util::read_tags_and_properties(&path).await;
+  let file_path = path.to_string_lossy().into_owned();
+  counter.file_done(&file_path);
+  match result {
+    Ok((tags, properties)) => ScanResult {
+      file_path,
+      tags: Some(tags),
+      properties: Some(properties),
+      error: None,
+    },
+    Err(error) => ScanResult {
+      file_path,
+      error: Some(error),
+      ..Default::default()
+    },
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_support::{sample_mp3, write_fixture};
+
+  fn file_names(results: &[ScanResult]) -> Vec<String> {
+    results
+      .iter()
+      .map(|result| {
+        Path::new(&result.file_path)
+          .file_name()
+          .unwrap()
+          .to_string_lossy()
+          .to_string()
+      })
+      .collect()
+  }
+
+  async fn scan(root: &Path, options: ScanOptions) -> Vec<ScanResult> {
+    scan_directory(
+      root.to_string_lossy().to_string(),
+      options,
+      &Cancellation::default(),
+      batch::NoProgress,
+    )
+    .await
+    .unwrap()
+  }
+
+  #[tokio::test]
+  async fn test_scan_directory() {
+    let dir = tempfile::tempdir().unwrap();
+    let tags = AudioTags {
+      title: Some("Scanned".to_string()),
+      ..Default::default()
+    };
+    let tagged = util::write_tags_to_buffer(sample_mp3(), tags)
+      .await
+      .unwrap();
+    write_fixture(dir.path(), "b.mp3", &tagged);
+    write_fixture(dir.path(), "A.MP3", &sample_mp3());
+    write_fixture(dir.path(), "cover.jpg", b"not audio");
+    write_fixture(dir.path(), "broken.flac", b"not audio");
+    let disc = dir.path().join("disc 2");
+    fs::create_dir(&disc).unwrap();
+    write_fixture(&disc, "c.mp3", &sample_mp3());
+
+    let results = scan(dir.path(), ScanOptions::default()).await;
+    assert_eq!(file_names(&results), vec!["A.MP3", "b.mp3", "broken.flac"]);
+    assert_eq!(
+      results[1].tags.as_ref().unwrap().title,
+      Some("Scanned".to_string())
+    );
+    assert!(results[1].properties.as_ref().unwrap().duration_ms > 0);
+    assert!(results[2].error.is_some());
+
+    let options = ScanOptions {
+      recursive: true,
+      extensions: Some(vec![".mp3".to_string()]),
+      ..Default::default()
+    };
+    let results = scan(dir.path(), options).await;
+    assert_eq!(file_names(&results), vec!["A.MP3", "b.mp3", "c.mp3"]);
+
+    let missing = dir.path().join("missing");
+    let result = scan_directory(
+      missing.to_string_lossy().to_string(),
+      ScanOptions::default(),
+      &Cancellation::default(),
+      batch::NoProgress,
+    )
+    .await;
+    assert!(result.unwrap_err().starts_with("Failed to read directory"));
+  }
+
+  #[tokio::test]
+  async fn test_scan_directory_each() {
+    let dir = tempfile::tempdir().unwrap();
+    write_fixture(dir.path(), "a.mp3", &sample_mp3());
+    write_fixture(dir.path(), "b.mp3", &sample_mp3());
+    write_fixture(dir.path(), "broken.flac", b"not audio");
+
+    let mut results = Vec::new();
+    let count = scan_directory_each(
+      dir.path().to_string_lossy().to_string(),
+      ScanOptions::default(),
+      &Cancellation::default(),
+      batch::NoProgress,
+      |result| {
+        results.push(result);
+        std::future::ready(Ok(()))
+      },
+    )
+    .await
+    .unwrap();
+    assert_eq!(count, 3);
+    // in completion order
+    results.sort_by(|a, b| a.file_path.cmp(&b.file_path));
+    assert_eq!(file_names(&results), vec!["a.mp3", "b.mp3", "broken.flac"]);
+    assert!(results[0].properties.is_some());
+    assert!(results[2].error.is_some());
+  }
+
+  #[cfg(unix)]
+  #[tokio::test]
+  async fn test_scan_directory_symlinks() {
+    let dir = tempfile::tempdir().unwrap();
+    let library = dir.path().join("library");
+    fs::create_dir(&library).unwrap();
+    write_fixture(&library, "a.mp3", &sample_mp3());
+    // a loop back to the library, and a link to a file
+    std::os::unix::fs::symlink(&library, library.join("loop")).unwrap();
+    std::os::unix::fs::symlink(library.join("a.mp3"), library.join("link.mp3")).unwrap();
+
+    let options = ScanOptions {
+      recursive: true,
+      ..Default::default()
+    };
+    let results = scan(&library, options.clone()).await;
+    assert_eq!(file_names(&results), vec!["a.mp3"]);
+
+    let options = ScanOptions {
+      follow_symlinks: true,
+      ..options
+    };
+    let results = scan(&library, options).await;
+    assert_eq!(file_names(&results), vec!["a.mp3", "link.mp3"]);
+  }
+}
@@ -0,0 +1,436 @@
+use crate::probe;
+use std::future::Future;
+
+// the most bytes asked for at a time
+const MAX_PULL: usize = probe::DEFAULT_PROBE_BYTES;
+// the audio kept after a leading ID3v2 tag, for the stream to be recognized
+const AUDIO_HEAD: usize = 4096;
+// the largest MP4 atom or RIFF/AIFF chunk buffered for its tags, a larger size
+// is taken for a corrupt stream
+const MAX_TAG_CHUNK: u64 = 64 * 1024 * 1024;
+
+// the top level MP4 atoms kept, the others (mdat, free, moof...) are skipped
+const MP4_TAG_ATOMS: [&[u8; 4]; 2] = [b"ftyp", b"moov"];
+// the WAV and AIFF chunks kept besides the emptied audio chunk: the tags and
+// the format chunk identifying the stream
+const WAV_TAG_CHUNKS: [&[u8; 4]; 6] = [b"fmt ", b"LIST", b"id3 ", b"ID3 ", b"bext", b"iXML"];
+const AIFF_TAG_CHUNKS: [&[u8; 4]; 8] = [
+  b"COMM", b"NAME", b"AUTH", b"(c) ", b"ANNO", b"COMT", b"ID3 ", b"id3 ",
+];
+
+fn check_tag_chunk(id: &[u8], size: u64) -> Result<(), String> {
+  if size > MAX_TAG_CHUNK {
+    return Err(format!(
+      "Tag chunk too large: {} of {} bytes, at most {} are read",
+      String::from_utf8_lossy(id),
+      size,
+      MAX_TAG_CHUNK
+    ));
+  }
+  Ok(())
+}
+
+// Pulls the bytes of a stream in order, e.g. from a Node Readable
+pub trait ByteSource {
+  // the next bytes, about `size` of them; None or nothing at the end of the stream
+  fn pull(&self, size: usize) -> impl Future<Output = Result<Option<Vec<u8>>, String>> + Send;
+}
+
+// The bytes of a stream gathered so far, the ones worth keeping in `region`
+struct Gatherer<'a, S> {
+  source: &'a S,
+  region: Vec<u8>,
+  // pulled beyond what the region asked for
+  pending: Vec<u8>,
+  ended: bool,
+}
+
+impl<'a, S: ByteSource> Gatherer<'a, S> {
+  fn new(source: &'a S) -> Self {
+    Self {
+      source,
+      region: Vec::new(),
+      pending: Vec::new(),
+      ended: false,
+    }
+  }
+
+  async fn pull_pending(&mut self, wanted: usize) -> Result<(), String> {
+    while self.pending.len() < wanted && !self.ended {
+      let size = (wanted - self.pending.len()).min(MAX_PULL);
+      match self.source.pull(size).await? {
+        Some(chunk) if !chunk.is_empty() => self.pending.extend(chunk),
+        _ => self.ended = true,
+      }
+    }
+    Ok(())
+  }
+
+  // grow the region to `len` bytes
+  async fn fill(&mut self, len: usize) -> Result<bool, String> {
+    if self.region.len() < len {
+      self.pull_pending(len - self.region.len()).await?;
+      let take = (len - self.region.len()).min(self.pending.len());
+      self.region.extend(self.pending.drain(..take));
+    }
+    Ok(self.region.len() >= len)
+  }
+
+  // consume `len` bytes of the stream without keeping them
+  async fn skip(&mut self, mut len: u64) -> Result<(), String> {
+    while len > 0 {
+      self.pull_pending(MAX_PULL.min(len as usize)).await?;
+      if self.pending.is_empty() {
+        break;
+      }
+      let take = self.pending.len().min(len as usize);
+      self.pending.drain(..take);
+      len -= take as u64;
+    }
+    Ok(())
+  }
+
+  async fn fill_to_end(&mut self) -> Result<(), String> {
+    while self.fill(self.region.len() + MAX_PULL).await? {}
+    Ok(())
+  }
+
+  // drop what is buffered of a chunk starting at `at` and `len` bytes long,
+  // and consume the rest of it
+  async fn skip_chunk(&mut self, at: usize, len: u64) -> Result<(), String> {
+    let buffered = (self.region.len() - at) as u64;
+    self.region.truncate(at);
+    self.skip(len.saturating_sub(buffered)).await
+  }
+
+  fn u32_be(&self, at: usize) -> u32 {
+    u32::from_be_bytes(self.region[at..at + 4].try_into().unwrap_or_default())
+  }
+}
+
+// FLAC metadata blocks, up to the last one
+async fn flac_blocks<S: ByteSource>(
+  gatherer: &mut Gatherer<'_, S>,
+  start: usize,
+) -> Result<(), String> {
+  let mut pos = start + 4;
+  while gatherer.fill(pos + 4).await? {
+    let header = gatherer.u32_be(pos);
+    pos += 4 + (header & 0x00FF_FFFF) as usize;
+    if !gatherer.fill(pos).await? || header & 0x8000_0000 != 0 {
+      break;
+    }
+  }
+  Ok(())
+}
+
+// Ogg pages up to the first one with audio: the header packets, the comments
+// among them, come on pages of granule position 0
+async fn ogg_header_pages<S: ByteSource>(
+  gatherer: &mut Gatherer<'_, S>,
+  start: usize,
+) -> Result<(), String> {
+  let mut pos = start;
+  while gatherer.fill(pos + 27).await? && gatherer.region[pos..].starts_with(b"OggS") {
+    let segments = usize::from(gatherer.region[pos + 26]);
+    if !gatherer.fill(pos + 27 + segments).await? {
+      break;
+    }
+    let body: usize = gatherer.region[pos + 27..pos + 27 + segments]
+      .iter()
+      .map(|len| usize::from(*len))
+      .sum();
+    let granule = &gatherer.region[pos + 6..pos + 14];
+    let audio = granule.iter().any(|byte| *byte != 0);
+    pos += 27 + segments + body;
+    if !gatherer.fill(pos).await? || audio {
+      break;
+    }
+  }
+  Ok(())
+}
+
+// MP4 atoms up to the moov atom, leaving out the ones before it that hold no
+// tags, such as the audio of mdat atoms
+async fn mp4_atoms<S: ByteSource>(
+  gatherer: &mut Gatherer<'_, S>,
+  start: usize,
+) -> Result<(), String> {
+  let mut pos = start;
+  while gatherer.fill(pos + 8).await? {
+    let kind: [u8; 4] = gatherer.region[pos + 4..pos + 8]
+      .try_into()
+      .unwrap_or_default();
+    let (header_len, size) = match gatherer.u32_be(pos) {
+      // runs to the end of the stream
+      0 => {
+        if &kind == b"moov" {
+          let limit = pos + MAX_TAG_CHUNK as usize;
+          if gatherer.fill(limit + 1).await? {
+            return check_tag_chunk(&kind, MAX_TAG_CHUNK + 1);
+          }
+        }
+        break;
+      }
+      1 => {
+        if !gatherer.fill(pos + 16).await? {
+          break;
+        }
+        let size = u64::from(gatherer.u32_be(pos + 8)) << 32 | u64::from(gatherer.u32_be(pos + 12));
+        (16, size)
+      }
+      size => (8, u64::from(size)),
+    };
+    if size < header_len {
+      break;
+    }
+    if !MP4_TAG_ATOMS.contains(&&kind) {
+      gatherer.skip_chunk(pos, size).await?;
+      continue;
+    }
+    check_tag_chunk(&kind, size)?;
+    let size = size as usize;
+    pos += size;
+    if !gatherer.fill(pos).await? || &kind == b"moov" {
+      break;
+    }
+  }
+  Ok(())
+}
+
+// RIFF (WAV) or AIFF chunks, tags may come after the audio. The audio chunk is
+// kept empty, chunks holding no tags are left out and the size of the file
+// adjusted.
+async fn iff_chunks<S: ByteSource>(
+  gatherer: &mut Gatherer<'_, S>,
+  start: usize,
+) -> Result<(), String> {
+  let little_endian = gatherer.region[start..].starts_with(b"RIFF");
+  let (audio_chunk, tag_chunks): (&[u8; 4], &[&[u8; 4]]) = if little_endian {
+    (b"data", &WAV_TAG_CHUNKS)
+  } else {
+    (b"SSND", &AIFF_TAG_CHUNKS)
+  };
+  let read_size = |bytes: &[u8]| {
+    let bytes: [u8; 4] = bytes.try_into().unwrap_or_default();
+    if little_endian {
+      u32::from_le_bytes(bytes)
+    } else {
+      u32::from_be_bytes(bytes)
+    }
+  };
+  let mut pos = start + 12;
+  while gatherer.fill(pos + 8).await? {
+    let size = read_size(&gatherer.region[pos + 4..pos + 8]);
+    // chunks are padded to an even size
+    let padded = u64::from(size) + u64::from(size & 1);
+    if &gatherer.region[pos..pos + 4] == audio_chunk {
+      let buffered = (gatherer.region.len() - pos - 8) as u64;
+      gatherer.region.truncate(pos + 4);
+      gatherer.region.extend_from_slice(&[0; 4]);
+      gatherer.skip(padded.saturating_sub(buffered)).await?;
+      pos += 8;
+      continue;
+    }
+    let id: [u8; 4] = gatherer.region[pos..pos + 4].try_into().unwrap_or_default();
+    if !tag_chunks.contains(&&id) {
+      gatherer.skip_chunk(pos, 8 + padded).await?;
+      continue;
+    }
+    check_tag_chunk(&id, padded)?;
+    pos += 8 + padded as usize;
+    if !gatherer.fill(pos).await? {
+      break;
+    }
+  }
+  let file_size = (gatherer.region.len() - start - 8) as u32;
+  let file_size = if little_endian {
+    file_size.to_le_bytes()
+  } else {
+    file_size.to_be_bytes()
+  };
+  gatherer.region[start + 4..start + 8].copy_from_slice(&file_size);
+  Ok(())
+}
+
+/**
+ * Gather the bytes of a stream that hold its tags, pulling no further than the
+ * format requires: a leading ID3v2 tag, FLAC metadata blocks, Ogg header pages
+ * or MP4 atoms up to the moov atom. WAV and AIFF files are read through, as
+ * their tags may follow the audio, but only the chunks holding tags are kept.
+ * MP4 atoms and chunks kept are limited to MAX_TAG_CHUNK bytes. Tags at the end
+ * of MPEG streams (ID3v1, APE) are not reached, streams of other formats are
+ * read whole.
+ * @param source - The stream
+ * @returns The bytes to read the tags from, the audio may be cut off or left out
+ */
+pub async fn read_tag_region<S: ByteSource>(source: &S) -> Result<Vec<u8>, String> {
+  let mut gatherer = Gatherer::new(source);
+  gatherer.fill(10).await?;
+  let start = match probe::id3v2_size(&gatherer.region) {
+    Some(size) => {
+      gatherer.fill(size as usize).await?;
+      size as usize
+    }
+    None => 0,
+  };
+  gatherer.fill(start + 12).await?;
+  let head = gatherer.region.get(start..).unwrap_or_default();
+  if head.starts_with(b"fLaC") {
+    flac_blocks(&mut gatherer, start).await?;
+  } else if head.starts_with(b"OggS") {
+    ogg_header_pages(&mut gatherer, start).await?;
+  } else if head.get(4..8) == Some(b"ftyp") {
+    mp4_atoms(&mut gatherer, start).await?;
+  } else if head.len() >= 12 && (head.starts_with(b"RIFF") || head.starts_with(b"FORM")) {
+    iff_chunks(&mut gatherer, start).await?;
+  } else if start > 0 {
+    gatherer.fill(start + AUDIO_HEAD).await?;
+  } else {
+    gatherer.fill_to_end().await?;
+  }
+  Ok(gatherer.region)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::sync::atomic::{AtomicUsize, Ordering};
+
+  // a stream of `data` in chunks of at most 100 bytes
+  struct Chunks {
+    data: Vec<u8>,
+    pulled: AtomicUsize,
+  }
+
+  impl ByteSource for Chunks {
+    async fn pull(&self, size: usize) -> Result<Option<Vec<u8>>, String> {
+      let start = self.pulled.load(Ordering::SeqCst);
+      let end = self.data.len().min(start + size.min(100));
+      self.pulled.store(end, Ordering::SeqCst);
+      Ok((start < end).then(|| self.data[start..end].to_vec()))
+    }
+  }
+
+  fn chunk(id: &[u8; 4], content: &[u8]) -> Vec<u8> {
+    let mut chunk = id.to_vec();
+    chunk.extend_from_slice(&(content.len() as u32).to_le_bytes());
+    chunk.extend_from_slice(content);
+    if content.len() % 2 == 1 {
+      chunk.push(0);
+    }
+    chunk
+  }
+
+  #[tokio::test]
+  async fn test_wav_audio_left_out() {
+    let info = chunk(b"LIST", b"INFOINAM\x05\0\0\0Song\0");
+    let chunks = [
+      chunk(b"fmt ", &[0; 16]),
+      chunk(b"data", &[0x55; 1001]),
+      info.clone(),
+    ]
+    .concat();
+    let mut wav = b"RIFF".to_vec();
+    wav.extend_from_slice(&(chunks.len() as u32 + 4).to_le_bytes());
+    wav.extend_from_slice(b"WAVE");
+    wav.extend(chunks);
+
+    let stream = Chunks {
+      data: wav,
+      pulled: AtomicUsize::new(0),
+    };
+    let region = read_tag_region(&stream).await.unwrap();
+    let expected = [
+      b"RIFF".to_vec(),
+      ((4 + 24 + 8 + info.len()) as u32).to_le_bytes().to_vec(),
+      b"WAVE".to_vec(),
+      chunk(b"fmt ", &[0; 16]),
+      chunk(b"data", &[]),
+      info,
+    ]
+    .concat();
+    assert_eq!(region, expected);
+  }
+
+  fn wav(chunks: &[Vec<u8>]) -> Vec<u8> {
+    let chunks = chunks.concat();
+    let mut wav = b"RIFF".to_vec();
+    wav.extend_from_slice(&(chunks.len() as u32 + 4).to_le_bytes());
+    wav.extend_from_slice(b"WAVE");
+    wav.extend(chunks);
+    wav
+  }
+
+  fn atom(kind: &[u8; 4], content: &[u8]) -> Vec<u8> {
+    let mut atom = ((content.len() + 8) as u32).to_be_bytes().to_vec();
+    atom.extend_from_slice(kind);
+    atom.extend_from_slice(content);
+    atom
+  }
+
+  #[tokio::test]
+  async fn test_wav_chunks_without_tags_left_out() {
+    let info = chunk(b"LIST", b"INFOINAM\x05\0\0\0Song\0");
+    let stream = Chunks {
+      data: wav(&[
+        chunk(b"fmt ", &[0; 16]),
+        chunk(b"JUNK", &[0; 501]),
+        chunk(b"data", &[0x55; 1000]),
+        chunk(b"smpl", &[0x11; 300]),
+        info.clone(),
+      ]),
+      pulled: AtomicUsize::new(0),
+    };
+    let region = read_tag_region(&stream).await.unwrap();
+    assert_eq!(
+      region,
+      wav(&[chunk(b"fmt ", &[0; 16]), chunk(b"data", &[]), info])
+    );
+  }
+
+  #[tokio::test]
+  async fn test_mp4_atoms_without_tags_left_out() {
+    let ftyp = atom(b"ftyp", b"M4A \0\0\0\0");
+    let moov = atom(b"moov", &atom(b"udta", &[0x22; 40]));
+    let stream = Chunks {
+      data: [
+        ftyp.clone(),
+        atom(b"free", &[0; 300]),
+        atom(b"mdat", &[0x55; 1000]),
+        atom(b"uuid", &[0x33; 200]),
+        moov.clone(),
+        atom(b"free", &[0; 100]),
+      ]
+      .concat(),
+      pulled: AtomicUsize::new(0),
+    };
+    let region = read_tag_region(&stream).await.unwrap();
+    assert_eq!(region, [ftyp, moov].concat());
+  }
+
+  #[tokio::test]
+  async fn test_oversized_tag_chunk_is_refused() {
+    let mut list = b"LIST".to_vec();
+    list.extend_from_slice(&0x7FFF_0000u32.to_le_bytes());
+    list.extend_from_slice(&[0; 1000]);
+    let stream = Chunks {
+      data: wav(&[chunk(b"fmt ", &[0; 16]), list]),
+      pulled: AtomicUsize::new(0),
+    };
+    let error = read_tag_region(&stream).await.unwrap_err();
+    assert!(error.contains("too large"), "{}", error);
+
+    let mut moov = 0x7FFF_0000u32.to_be_bytes().to_vec();
+    moov.extend_from_slice(b"moov");
+    moov.extend_from_slice(&[0; 1000]);
+    let stream = Chunks {
+      data: [atom(b"ftyp", b"M4A \0\0\0\0"), moov].concat(),
+      pulled: AtomicUsize::new(0),
+    };
+    let error = read_tag_region(&stream).await.unwrap_err();
+    assert!(error.contains("too large"), "{}", error);
+    // refused from the header, before pulling the atom
+    assert!(stream.pulled.load(Ordering::SeqCst) < 1000);
+  }
+}
@@ -0,0 +1,288 @@
+use lofty::id3::v2::{
+  BinaryFrame, Frame, FrameId, Id3v2Tag, SyncTextContentType, SynchronizedTextFrame,
+  TimestampFormat,
+};
+use lofty::tag::{ItemKey, Tag, TagType};
+use lofty::TextEncoding;
+
+const SYLT: &str = "SYLT";
+// ISO-639-2 for an undetermined language
+const UNKNOWN_LANGUAGE: &str = "XXX";
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct LyricLine {
+  // the time the line starts at, from the start of the audio
+  pub time_ms: u32,
+  pub text: String,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct SyncedLyrics {
+  // ISO-639-2 code, only stored in ID3v2
+  pub language: Option<String>,
+  // only stored in ID3v2
+  pub description: Option<String>,
+  // ordered by time
+  pub lines: Vec<LyricLine>,
+}
+
+// [mm:ss], [mm:ss.xx] or [mm:ss.xxx]
+fn parse_timestamp(tag: &str) -> Option<u32> {
+  let (minutes, seconds) = tag.split_once(':')?;
+  let minutes: u32 = minutes.trim().parse().ok()?;
+  let (seconds, fraction) = seconds.split_once('.').unwrap_or((seconds, ""));
+  let seconds: u32 = seconds.trim().parse().ok()?;
+  let fraction_ms = match fraction.len() {
+    0 => 0,
+    1..=3 if fraction.chars().all(|c| c.is_ascii_digit()) => {
+      fraction.parse::<u32>().ok()? * 10u32.pow(3 - fraction.len() as u32)
+    }
+    _ => return None,
+  };
+  Some((minutes * 60 + seconds) * 1000 + fraction_ms)
+}
+
+/**
+ * Parse LRC text. Lines may carry several timestamps, `[offset:±ms]` shifts all
+ * of them, and other metadata tags (`[ar:...]`, `[ti:...]`) and untimed lines
+ * are skipped.
+ * @param lrc - The LRC text
+ * @returns The lines ordered by time
+ */
+pub fn parse_lrc(lrc: &str) -> SyncedLyrics {
+  let mut offset_ms: i64 = 0;
+  let mut lines = Vec::new();
+  for line in lrc.lines() {
+    let mut rest = line.trim();
+    let mut times = Vec::new();
+    while let Some(tag_end) = rest.strip_prefix('[').and_then(|tag| tag.find(']')) {
+      let tag = &rest[1..tag_end + 1];
+      rest = &rest[tag_end + 2..];
+      match parse_timestamp(tag) {
+        Some(time) => times.push(time),
+        None => {
+          if let Some(offset) = tag.strip_prefix("offset:") {
+            offset_ms = offset.trim().parse().unwrap_or(0);
+          }
+        }
+      }
+    }
+    for time in times {
+      lines.push((i64::from(time), rest.to_string()));
+    }
+  }
+  // a positive offset makes the lyrics come up sooner
+  let mut lines: Vec<LyricLine> = lines
+    .into_iter()
+    .map(|(time, text)| LyricLine {
+      time_ms: (time - offset_ms).clamp(0, i64::from(u32::MAX)) as u32,
+      text,
+    })
+    .collect();
+  lines.sort_by_key(|line| line.time_ms);
+  SyncedLyrics {
+    lines,
+    ..Default::default()
+  }
+}
+
+/**
+ * Format synced lyrics as LRC text, one `[mm:ss.xx]` line per lyric line
+ * @param lyrics - The lyrics to format
+ */
+pub fn to_lrc(lyrics: &SyncedLyrics) -> String {
+  lyrics
+    .lines
+    .iter()
+    .map(|line| {
+      let centis = line.time_ms / 10;
+      format!(
+        "[{:02}:{:02}.{:02}]{}\n",
+        centis / 6000,
+        centis / 100 % 60,
+        centis % 100,
+        line.text
+      )
+    })
+    .collect()
+}
+
+fn from_sylt(frame: SynchronizedTextFrame) -> Option<SyncedLyrics> {
+  // MPEG frame timestamps depend on the stream and can't be converted here
+  if frame.timestamp_format != TimestampFormat::MS {
+    return None;
+  }
+  let language = String::from_utf8_lossy(&frame.language).to_string();
+  let mut lines: Vec<LyricLine> = frame
+    .content
+    .into_iter()
+    .map(|(time_ms, text)| LyricLine { time_ms, text })
+    .collect();
+  lines.sort_by_key(|line| line.time_ms);
+  Some(SyncedLyrics {
+    language: (language != UNKNOWN_LANGUAGE).then_some(language),
+    description: frame.description.filter(|d| !d.is_empty()),
+    lines,
+  })
+}
+
+fn read_sylt(tag: &Tag) -> Option<SyncedLyrics> {
+  let id3v2: Id3v2Tag = tag.clone().into();
+  id3v2.into_iter().find_map(|frame| match frame {
+    Frame::Binary(binary) if binary.id().as_str() == SYLT => {
+      SynchronizedTextFrame::parse(&binary.data, binary.flags())
+        .ok()
+        .and_then(from_sylt)
+    }
+    _ => None,
+  })
+}
+
+/**
+ * Read the synced lyrics of a tag: the ID3v2 SYLT frame, or the lyrics field
+ * when it holds LRC text
+ * @param tag - The primary tag
+ */
+pub fn synced_lyrics_from_tag(tag: &Tag) -> Option<SyncedLyrics> {
+  if tag.tag_type() == TagType::Id3v2 {
+    if let Some(lyrics) = read_sylt(tag) {
+      return Some(lyrics);
+    }
+  }
+  let lyrics = parse_lrc(tag.get_string(&ItemKey::Lyrics)?);
+  (!lyrics.lines.is_empty()).then_some(lyrics)
+}
+
+fn sylt_language(lyrics: &SyncedLyrics) -> Result<[u8; 3], String> {
+  let language = lyrics.language.as_deref().unwrap_or(UNKNOWN_LANGUAGE);
+  match language.as_bytes().try_into() {
+    Ok(code) if language.chars().all(|c| c.is_ascii_alphabetic()) => Ok(code),
+    _ => Err(format!(
+      "Invalid language: {}, expected a 3 letter ISO-639-2 code",
+      language
+    )),
+  }
+}
+
+/**
+ * Check that synced lyrics can be stored, before anything is written
+ * @param lyrics - The lyrics about to be written
+ */
+pub fn validate(lyrics: &SyncedLyrics) -> Result<(), String> {
+  sylt_language(lyrics).map(|_| ())
+}
+
+fn write_sylt(tag: &mut Tag, lyrics: &SyncedLyrics) -> Result<(), String> {
+  let language = sylt_language(lyrics)?;
+  let frame = SynchronizedTextFrame::new(
+    TextEncoding::UTF8,
+    language,
+    TimestampFormat::MS,
+    SyncTextContentType::Lyrics,
+    lyrics.description.clone(),
+    lyrics
+      .lines
+      .iter()
+      .map(|line| (line.time_ms, line.text.clone()))
+      .collect(),
+  );
+  let data = frame
+    .as_bytes()
+    .map_err(|e| format!("Failed to encode synced lyrics: {}", e))?;
+
+  let mut id3v2: Id3v2Tag = tag.clone().into();
+  id3v2.retain(|frame| frame.id_str() != SYLT);
+  let id = FrameId::new(SYLT).map_err(|e| format!("Invalid frame id: {}", e))?;
+  id3v2.insert(Frame::Binary(BinaryFrame::new(id, data)));
+  // converting back keeps the SYLT frame in the frames written along with the tag
+  *tag = id3v2.into();
+  Ok(())
+}
+
+/**
+ * Store synced lyrics in a tag, replacing any synced lyrics already present.
+ * ID3v2 tags get a SYLT frame, other tags LRC text in the lyrics field.
+ * @param tag - The primary tag
+ * @param lyrics - The lyrics to store
+ */
+pub fn set_synced_lyrics(tag: &mut Tag, lyrics: &SyncedLyrics) -> Result<(), String> {
+  if tag.tag_type() == TagType::Id3v2 {
+    return write_sylt(tag, lyrics);
+  }
+  tag.remove_key(&ItemKey::Lyrics);
+  tag.insert_text(ItemKey::Lyrics, to_lrc(lyrics));
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn line(time_ms: u32, text: &str) -> LyricLine {
+    LyricLine {
+      time_ms,
+      text: text.to_string(),
+    }
+  }
+
+  #[test]
+  fn test_parse_lrc() {
+    let lrc = "[ar:Artist]\n[ti:Title]\n[00:12.00]First\n[00:05.5]Intro\n\
+               [01:02.345][00:30.00]Chorus\nno timestamp\n";
+    assert_eq!(
+      parse_lrc(lrc).lines,
+      vec![
+        line(5500, "Intro"),
+        line(12000, "First"),
+        line(30000, "Chorus"),
+        line(62345, "Chorus"),
+      ]
+    );
+
+    let shifted = parse_lrc("[offset:+500]\n[00:01.00]One\n[00:00.20]Zero\n");
+    assert_eq!(shifted.lines, vec![line(0, "Zero"), line(500, "One")]);
+  }
+
+  #[test]
+  fn test_to_lrc_round_trip() {
+    let lyrics = SyncedLyrics {
+      lines: vec![line(0, "Start"), line(61230, "Later"), line(3600000, "")],
+      ..Default::default()
+    };
+    let lrc = to_lrc(&lyrics);
+    assert_eq!(lrc, "[00:00.00]Start\n[01:01.23]Later\n[60:00.00]\n");
+    assert_eq!(parse_lrc(&lrc), lyrics);
+  }
+
+  #[test]
+  fn test_synced_lyrics_in_tags() {
+    let lyrics = SyncedLyrics {
+      language: Some("eng".to_string()),
+      description: Some("Karaoke".to_string()),
+      lines: vec![line(1000, "Hello"), line(2500, "World")],
+    };
+
+    let mut id3v2 = Tag::new(TagType::Id3v2);
+    set_synced_lyrics(&mut id3v2, &lyrics).unwrap();
+    assert_eq!(synced_lyrics_from_tag(&id3v2), Some(lyrics.clone()));
+    assert_eq!(id3v2.get_string(&ItemKey::Lyrics), None);
+
+    let mut vorbis = Tag::new(TagType::VorbisComments);
+    set_synced_lyrics(&mut vorbis, &lyrics).unwrap();
+    assert_eq!(
+      vorbis.get_string(&ItemKey::Lyrics),
+      Some("[00:01.00]Hello\n[00:02.50]World\n")
+    );
+    assert_eq!(synced_lyrics_from_tag(&vorbis).unwrap().lines, lyrics.lines);
+
+    let invalid = SyncedLyrics {
+      language: Some("english".to_string()),
+      ..lyrics
+    };
+    assert_eq!(
+      validate(&invalid).unwrap_err(),
+      "Invalid language: english, expected a 3 letter ISO-639-2 code"
+    );
+    assert!(set_synced_lyrics(&mut id3v2, &invalid).is_err());
+  }
+}
@@ -0,0 +1,223 @@
+// Shared fixtures for the unit tests of the modules built on top of util.rs
+
+use std::path::{Path, PathBuf};
+
+pub fn fixture_path(relative: &str) -> PathBuf {
+  Path::new(env!("CARGO_MANIFEST_DIR")).join(relative)
+}
+
+pub fn sample_mp3() -> Vec<u8> {
+  std::fs::read(fixture_path("music/silence.mp3")).expect("sample mp3 fixture")
+}
+
+// Copy the given bytes into `dir/name` and return the path as a String
+pub fn write_fixture(dir: &Path, name: &str, data: &[u8]) -> String {
+  let path = dir.join(name);
+  std::fs::write(&path, data).expect("write fixture");
+  path.to_string_lossy().to_string()
+}
+
+fn atom(kind: &[u8; 4], content: &[u8]) -> Vec<u8> {
+  let mut atom = ((content.len() + 8) as u32).to_be_bytes().to_vec();
+  atom.extend_from_slice(kind);
+  atom.extend_from_slice(content);
+  atom
+}
+
+fn fields(fields: &[u32]) -> Vec<u8> {
+  fields
+    .iter()
+    .flat_map(|field| field.to_be_bytes())
+    .collect()
+}
+
+const MATRIX: [u32; 9] = [0x0001_0000, 0, 0, 0, 0x0001_0000, 0, 0, 0, 0x4000_0000];
+
+// An M4A file of 5 seconds with one AAC sample, laid out as ffmpeg writes it:
+// the moov atom first, with the encoder in its ilst atom, then the mdat atom
+pub fn sample_m4a() -> Vec<u8> {
+  let ftyp = atom(b"ftyp", b"M4A \0\0\0\0M4A isom");
+  let sample = [0x21, 0x10, 0x04, 0x60, 0x8C, 0x1C];
+  let moov = |chunk_offset: u32| {
+    let mut mvhd = fields(&[0, 0, 0, 1000, 5000, 0x0001_0000, 0x0100_0000, 0, 0]);
+    mvhd.extend(fields(&MATRIX));
+    mvhd.extend(fields(&[0, 0, 0, 0, 0, 0, 2]));
+    let mut tkhd = fields(&[1, 0, 0, 1, 0, 5000, 0, 0, 0, 0x0100_0000]);
+    tkhd.extend(fields(&MATRIX));
+    tkhd.extend(fields(&[0, 0]));
+    let mdhd = fields(&[0, 0, 0, 44100, 220_500, 0x55C4_0000]);
+    let mut hdlr = fields(&[0, 0]);
+    hdlr.extend_from_slice(b"soun");
+    hdlr.extend_from_slice(&[0; 13]);
+    let dinf = atom(
+      b"dinf",
+      &atom(
+        b"dref",
+        &[fields(&[0, 1]), atom(b"url ", &fields(&[1]))].concat(),
+      ),
+    );
+    // 2 channels of 16 bit samples at 44100 Hz
+    let mp4a = fields(&[0, 1, 0, 0, 0x0002_0010, 0, 0xAC44_0000]);
+    let stbl = [
+      atom(b"stsd", &[fields(&[0, 1]), atom(b"mp4a", &mp4a)].concat()),
+      atom(b"stts", &fields(&[0, 1, 1, 220_500])),
+      atom(b"stsc", &fields(&[0, 1, 1, 1, 1])),
+      atom(b"stsz", &fields(&[0, 0, 1, sample.len() as u32])),
+      atom(b"stco", &fields(&[0, 1, chunk_offset])),
+    ]
+    .concat();
+    let minf = [atom(b"smhd", &fields(&[0, 0])), dinf, atom(b"stbl", &stbl)].concat();
+    let mdia = [
+      atom(b"mdhd", &mdhd),
+      atom(b"hdlr", &hdlr),
+      atom(b"minf", &minf),
+    ]
+    .concat();
+    let trak = [atom(b"tkhd", &tkhd), atom(b"mdia", &mdia)].concat();
+    let mut meta_hdlr = fields(&[0, 0]);
+    meta_hdlr.extend_from_slice(b"mdirappl");
+    meta_hdlr.extend_from_slice(&[0; 9]);
+    let encoder = atom(b"data", &[&fields(&[1, 0])[..], b"Lavf61.1.100"].concat());
+    let ilst = atom(b"ilst", &atom(b"\xA9too", &encoder));
+    let meta = atom(
+      b"meta",
+      &[fields(&[0]), atom(b"hdlr", &meta_hdlr), ilst].concat(),
+    );
+    atom(
+      b"moov",
+      &[
+        atom(b"mvhd", &mvhd),
+        atom(b"trak", &trak),
+        atom(b"udta", &meta),
+      ]
+      .concat(),
+    )
+  };
+  let chunk_offset = (ftyp.len() + moov(0).len() + 8) as u32;
+  [ftyp, moov(chunk_offset), atom(b"mdat", &sample)].concat()
+}
+
+fn chunk(id: &[u8; 4], content: &[u8]) -> Vec<u8> {
+  let mut chunk = id.to_vec();
+  chunk.extend_from_slice(&(content.len() as u32).to_le_bytes());
+  chunk.extend_from_slice(content);
+  chunk
+}
+
+// A WAV file of a tenth of a second of silence: 16 bit mono PCM at 8000 Hz
+pub fn sample_wav() -> Vec<u8> {
+  let mut fmt = vec![1, 0, 1, 0];
+  fmt.extend_from_slice(&8000u32.to_le_bytes());
+  fmt.extend_from_slice(&16000u32.to_le_bytes());
+  fmt.extend_from_slice(&[2, 0, 16, 0]);
+  let chunks = [chunk(b"fmt ", &fmt), chunk(b"data", &[0; 1600])].concat();
+  let mut wav = b"RIFF".to_vec();
+  wav.extend_from_slice(&((chunks.len() + 4) as u32).to_le_bytes());
+  wav.extend_from_slice(b"WAVE");
+  wav.extend(chunks);
+  wav
+}
+
+// A DSF file of 4096 samples of DSD64 stereo silence, without a tag
+pub fn sample_dsf() -> Vec<u8> {
+  let mut fmt = b"fmt ".to_vec();
+  fmt.extend_from_slice(&52u64.to_le_bytes());
+  // version, format id (DSD raw), channel type (stereo), channel count,
+  // sampling frequency, bits per sample
+  for field in [1u32, 0, 2, 2, 2_822_400, 1] {
+    fmt.extend_from_slice(&field.to_le_bytes());
+  }
+  fmt.extend_from_slice(&4096u64.to_le_bytes());
+  // block size per channel, reserved
+  fmt.extend_from_slice(&4096u32.to_le_bytes());
+  fmt.extend_from_slice(&0u32.to_le_bytes());
+  let mut data = b"data".to_vec();
+  data.extend_from_slice(&(12u64 + 8192).to_le_bytes());
+  data.extend_from_slice(&[0x69; 8192]);
+  let len = 28 + fmt.len() + data.len();
+  let mut dsf = b"DSD ".to_vec();
+  dsf.extend_from_slice(&28u64.to_le_bytes());
+  dsf.extend_from_slice(&(len as u64).to_le_bytes());
+  dsf.extend_from_slice(&0u64.to_le_bytes());
+  [dsf, fmt, data].concat()
+}
+
+fn dff_chunk(id: &[u8; 4], content: &[u8]) -> Vec<u8> {
+  let mut chunk = id.to_vec();
+  chunk.extend_from_slice(&(content.len() as u64).to_be_bytes());
+  chunk.extend_from_slice(content);
+  chunk
+}
+
+// A DSDIFF file of 4096 samples of DSD64 stereo silence, without a tag
+pub fn sample_dff() -> Vec<u8> {
+  let channels = [&2u16.to_be_bytes()[..], b"SLFTSRGT"].concat();
+  let prop = [
+    &b"SND "[..],
+    &dff_chunk(b"FS  ", &2_822_400u32.to_be_bytes()),
+    &dff_chunk(b"CHNL", &channels),
+    &dff_chunk(b"CMPR", b"DSD \x0enot compressed\0"),
+  ]
+  .concat();
+  let chunks = [
+    dff_chunk(b"FVER", &0x0105_0000u32.to_be_bytes()),
+    dff_chunk(b"PROP", &prop),
+    dff_chunk(b"DSD ", &[0x69; 1024]),
+  ]
+  .concat();
+  let mut dff = b"FRM8".to_vec();
+  dff.extend_from_slice(&((chunks.len() + 4) as u64).to_be_bytes());
+  dff.extend_from_slice(b"DSD ");
+  dff.extend(chunks);
+  dff
+}
+
+// the CRC of an Ogg page: polynomial 0x04C11DB7, unreflected, no final xor
+fn ogg_crc(data: &[u8]) -> u32 {
+  data.iter().fold(0, |crc, byte| {
+    (0..8).fold(crc ^ (u32::from(*byte) << 24), |crc, _| {
+      if crc & 0x8000_0000 != 0 {
+        (crc << 1) ^ 0x04C1_1DB7
+      } else {
+        crc << 1
+      }
+    })
+  })
+}
+
+// An Ogg page holding one packet of less than 255 bytes
+fn ogg_page(header_type: u8, granule: u64, sequence: u32, packet: &[u8]) -> Vec<u8> {
+  let mut page = b"OggS\0".to_vec();
+  page.push(header_type);
+  page.extend_from_slice(&granule.to_le_bytes());
+  page.extend_from_slice(&0x7461_6770u32.to_le_bytes());
+  page.extend_from_slice(&sequence.to_le_bytes());
+  page.extend_from_slice(&[0; 4]);
+  page.extend_from_slice(&[1, packet.len() as u8]);
+  page.extend_from_slice(packet);
+  let crc = ogg_crc(&page);
+  page[22..26].copy_from_slice(&crc.to_le_bytes());
+  page
+}
+
+// An Ogg Opus file of one 20 ms frame of silence, mono at 48000 Hz, with an
+// empty comment header
+pub fn sample_opus() -> Vec<u8> {
+  let mut head = b"OpusHead\x01\x01".to_vec();
+  // pre-skip, input sample rate, output gain, channel mapping family
+  head.extend_from_slice(&0u16.to_le_bytes());
+  head.extend_from_slice(&48000u32.to_le_bytes());
+  head.extend_from_slice(&[0, 0, 0]);
+  let mut tags = b"OpusTags".to_vec();
+  tags.extend_from_slice(&(b"tagpilot".len() as u32).to_le_bytes());
+  tags.extend_from_slice(b"tagpilot");
+  tags.extend_from_slice(&0u32.to_le_bytes());
+  // a CELT fullband 20 ms frame, without data: a lost frame to decoders
+  let audio = [0xF8];
+  [
+    ogg_page(0x02, 0, 0, &head),
+    ogg_page(0x00, 0, 1, &tags),
+    ogg_page(0x04, 960, 2, &audio),
+  ]
+  .concat()
+}
@@ -0,0 +1,396 @@
+use crate::cancel::Cancellation;
+use crate::hooks::{HookDecision, NoHooks, WriteHooks};
+use crate::util::{self, AudioTags};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+static SIBLING_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct TagWriteEntry {
+  pub file_path: PathBuf,
+  pub tags: AudioTags,
+}
+
+struct StagedWrite {
+  file_path: String,
+  target: PathBuf,
+  staged: PathBuf,
+  backup: PathBuf,
+  old_tags: AudioTags,
+  new_tags: AudioTags,
+}
+
+/**
+ * Build a hidden sibling path for `path` (same directory, so renames stay atomic),
+ * unique to the call so concurrent writes to one file never share it
+ * @param path - The file the sibling belongs to
+ * @param suffix - The suffix identifying the purpose of the sibling
+ */
+pub(crate) fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+  let file_name = path
+    .file_name()
+    .map(|name| name.to_string_lossy().to_string())
+    .unwrap_or_default();
+  let n = SIBLING_COUNTER.fetch_add(1, Ordering::Relaxed);
+  path.with_file_name(format!(
+    ".{}.{}.{}.{}",
+    file_name,
+    std::process::id(),
+    n,
+    suffix
+  ))
+}
+
+async fn stage_write<H: WriteHooks>(
+  entry: TagWriteEntry,
+  hooks: &H,
+) -> Result<Option<StagedWrite>, String> {
+  let target = entry.file_path;
+  let file_path = target.to_string_lossy().into_owned();
+  let buffer =
+    fs::read(&target).map_err(|e| format!("Failed to read file {}: {}", file_path, e))?;
+  let old_tags = util::read_tags_from_buffer(buffer.clone())
+    .await
+    .map_err(|e| format!("Failed to stage {}: {}", file_path, e))?;
+  let new_tags = match hooks
+    .before_write(&file_path, &old_tags, entry.tags)
+    .await
+    .map_err(|e| format!("Write hook failed for {}: {}", file_path, e))?
+  {
+    HookDecision::Proceed(tags) => *tags,
+    HookDecision::Veto => return Ok(None),
+  };
+  let buffer = util::write_tags_to_buffer(buffer, new_tags.clone())
+    .await
+    .map_err(|e| format!("Failed to stage {}: {}", file_path, e))?;
+  let staged = sibling_path(&target, "tagpilot-tmp");
+  // the staged file replaces the original, so it takes over its permissions
+  let permissions = fs::metadata(&target)
+    .map(|metadata| metadata.permissions())
+    .map_err(|e| format!("Failed to stage {}: {}", file_path, e))?;
+  if let Err(e) = fs::write(&staged, buffer).and_then(|_| fs::set_permissions(&staged, permissions))
+  {
+    let _ = fs::remove_file(&staged);
+    return Err(format!("Failed to stage {}: {}", file_path, e));
+  }
+  let backup = sibling_path(&target, "tagpilot-bak");
+  Ok(Some(StagedWrite {
+    file_path,
+    target,
+    staged,
+    backup,
+    old_tags,
+    new_tags,
+  }))
+}
+
+fn discard(staged: &[StagedWrite]) {
+  for write in staged {
+    let _ = fs::remove_file(&write.staged);
+  }
+}
+
+fn rollback(committed: &[StagedWrite]) {
+  for write in committed.iter().rev() {
+    let _ = fs::rename(&write.backup, &write.target);
+  }
+}
+
+fn commit(staged: &[StagedWrite]) -> Result<(), String> {
+  for (i, write) in staged.iter().enumerate() {
+    let result = fs::rename(&write.target, &write.backup).and_then(|_| {
+      fs::rename(&write.staged, &write.target).inspect_err(|_| {
+        // put the original back before rolling back the others
+        let _ = fs::rename(&write.backup, &write.target);
+      })
+    });
+    if let Err(e) = result {
+      rollback(&staged[..i]);
+      discard(staged);
+      return Err(format!(
+        "Failed to commit {}: {}",
+        write.target.display(),
+        e
+      ));
+    }
+  }
+  for write in staged {
+    let _ = fs::remove_file(&write.backup);
+  }
+  Ok(())
+}
+
+/**
+ * Write tags to several files as a single unit: every output is staged to a
+ * temp file next to its target and the originals are only replaced once all
+ * of them were produced. If anything fails no file is modified.
+ * @param entries - The files to write and the tags to write to each of them
+ */
+pub async fn write_tags_transaction(entries: Vec<TagWriteEntry>) -> Result<(), String> {
+  write_tags_transaction_with_hooks(entries, &NoHooks, &Cancellation::default())
+    .await
+    .map(|_| ())
+}
+
+/**
+ * Same as write_tags_transaction, running `hooks` around every file. A vetoed
+ * file is left out of the transaction, the after-write hooks run once all
+ * files were committed.
+ * @param entries - The files to write and the tags to write to each of them
+ * @param hooks - The hooks to run
+ * @param cancel - Cancelled when the caller aborts; until every file is staged
+ * the transaction is abandoned like a failed one
+ * @returns The errors of the after-write hooks, which leave the files written
+ */
+pub async fn write_tags_transaction_with_hooks<H: WriteHooks>(
+  entries: Vec<TagWriteEntry>,
+  hooks: &H,
+  cancel: &Cancellation,
+) -> Result<Vec<String>, String> {
+  let mut seen = HashSet::new();
+  for entry in &entries {
+    // "a.mp3" and "./a.mp3" are the same file; a missing file fails staging
+    let path = fs::canonicalize(&entry.file_path).unwrap_or_else(|_| entry.file_path.clone());
+    if !seen.insert(path) {
+      return Err(format!(
+        "Duplicate file in transaction: {}",
+        entry.file_path.display()
+      ));
+    }
+  }
+
+  let mut staged: Vec<StagedWrite> = Vec::with_capacity(entries.len());
+  for entry in entries {
+    if let Err(e) = cancel.check() {
+      discard(&staged);
+      return Err(e);
+    }
+    match stage_write(entry, hooks).await {
+      Ok(Some(write)) => staged.push(write),
+      Ok(None) => {}
+      Err(e) => {
+        discard(&staged);
+        return Err(e);
+      }
+    }
+  }
+
+  commit(&staged)?;
+
+  // every file is written by now, so a failing hook doesn't stop the others
+  let mut hook_errors = Vec::new();
+  for write in &staged {
+    if let Err(e) = hooks
+      .after_write(&write.file_path, &write.old_tags, &write.new_tags)
+      .await
+    {
+      hook_errors.push(format!(
+        "After-write hook failed for {}: {}",
+        write.file_path, e
+      ));
+    }
+  }
+  Ok(hook_errors)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_support::{sample_mp3, write_fixture};
+
+  fn titled(file_path: &str, title: &str) -> TagWriteEntry {
+    TagWriteEntry {
+      file_path: file_path.into(),
+      tags: AudioTags {
+        title: Some(title.to_string()),
+        ..Default::default()
+      },
+    }
+  }
+
+  #[tokio::test]
+  async fn test_write_tags_transaction_commits_all_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let first = write_fixture(dir.path(), "01.mp3", &sample_mp3());
+    let second = write_fixture(dir.path(), "02.mp3", &sample_mp3());
+
+    write_tags_transaction(vec![titled(&first, "One"), titled(&second, "Two")])
+      .await
+      .unwrap();
+
+    let tags = util::read_tags(first).await.unwrap();
+    assert_eq!(tags.title, Some("One".to_string()));
+    let tags = util::read_tags(second).await.unwrap();
+    assert_eq!(tags.title, Some("Two".to_string()));
+    // no staging leftovers
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 2);
+  }
+
+  #[tokio::test]
+  async fn test_write_tags_transaction_rolls_back_on_failure() {
+    let dir = tempfile::tempdir().unwrap();
+    let original = sample_mp3();
+    let good = write_fixture(dir.path(), "good.mp3", &original);
+    let bad = write_fixture(dir.path(), "bad.mp3", b"not audio");
+
+    let result =
+      write_tags_transaction(vec![titled(&good, "Changed"), titled(&bad, "Changed")]).await;
+
+    assert!(result.is_err());
+    assert_eq!(fs::read(&good).unwrap(), original);
+    assert_eq!(fs::read(&bad).unwrap(), b"not audio");
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 2);
+  }
+
+  struct PolicyHooks {
+    after_calls: std::sync::Mutex<Vec<String>>,
+    // after_write fails for this file
+    failing: Option<String>,
+  }
+
+  impl WriteHooks for PolicyHooks {
+    fn before_write(
+      &self,
+      file_path: &str,
+      _old_tags: &AudioTags,
+      mut new_tags: AudioTags,
+    ) -> impl std::future::Future<Output = Result<HookDecision, String>> + Send {
+      let vetoed = file_path.ends_with("locked.mp3");
+      async move {
+        if vetoed {
+          return Ok(HookDecision::Veto);
+        }
+        new_tags.album = Some("Enforced".to_string());
+        Ok(HookDecision::Proceed(Box::new(new_tags)))
+      }
+    }
+
+    fn after_write(
+      &self,
+      file_path: &str,
+      _old_tags: &AudioTags,
+      new_tags: &AudioTags,
+    ) -> impl std::future::Future<Output = Result<(), String>> + Send {
+      self
+        .after_calls
+        .lock()
+        .unwrap()
+        .push(format!("{}:{:?}", file_path, new_tags.album));
+      let failed = self.failing.as_deref() == Some(file_path);
+      async move {
+        match failed {
+          true => Err("notification failed".to_string()),
+          false => Ok(()),
+        }
+      }
+    }
+  }
+
+  #[tokio::test]
+  async fn test_write_tags_transaction_aborts_before_staging() {
+    let dir = tempfile::tempdir().unwrap();
+    let original = sample_mp3();
+    let path = write_fixture(dir.path(), "song.mp3", &original);
+    let cancel = Cancellation::default();
+    cancel.cancel();
+
+    let result =
+      write_tags_transaction_with_hooks(vec![titled(&path, "Title")], &NoHooks, &cancel).await;
+    assert_eq!(result.unwrap_err(), crate::cancel::ABORTED);
+    assert_eq!(fs::read(&path).unwrap(), original);
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+  }
+
+  #[tokio::test]
+  async fn test_write_tags_transaction_runs_hooks() {
+    let dir = tempfile::tempdir().unwrap();
+    let original = sample_mp3();
+    let open = write_fixture(dir.path(), "open.mp3", &original);
+    let locked = write_fixture(dir.path(), "locked.mp3", &original);
+    let hooks = PolicyHooks {
+      after_calls: std::sync::Mutex::new(Vec::new()),
+      failing: None,
+    };
+
+    let hook_errors = write_tags_transaction_with_hooks(
+      vec![titled(&open, "Open"), titled(&locked, "Locked")],
+      &hooks,
+      &Cancellation::default(),
+    )
+    .await
+    .unwrap();
+    assert!(hook_errors.is_empty());
+
+    let tags = util::read_tags(open.clone()).await.unwrap();
+    assert_eq!(tags.title, Some("Open".to_string()));
+    assert_eq!(tags.album, Some("Enforced".to_string()));
+    assert_eq!(fs::read(&locked).unwrap(), original);
+    assert_eq!(
+      *hooks.after_calls.lock().unwrap(),
+      vec![format!("{}:Some(\"Enforced\")", open)]
+    );
+  }
+
+  #[tokio::test]
+  async fn test_write_tags_transaction_reports_failing_after_write_hooks() {
+    let dir = tempfile::tempdir().unwrap();
+    let first = write_fixture(dir.path(), "01.mp3", &sample_mp3());
+    let second = write_fixture(dir.path(), "02.mp3", &sample_mp3());
+    let hooks = PolicyHooks {
+      after_calls: std::sync::Mutex::new(Vec::new()),
+      failing: Some(first.clone()),
+    };
+
+    let hook_errors = write_tags_transaction_with_hooks(
+      vec![titled(&first, "One"), titled(&second, "Two")],
+      &hooks,
+      &Cancellation::default(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+      hook_errors,
+      vec![format!(
+        "After-write hook failed for {}: notification failed",
+        first
+      )]
+    );
+    // both files are written and the hook of the second still ran
+    let tags = util::read_tags(first).await.unwrap();
+    assert_eq!(tags.title, Some("One".to_string()));
+    let tags = util::read_tags(second).await.unwrap();
+    assert_eq!(tags.title, Some("Two".to_string()));
+    assert_eq!(hooks.after_calls.lock().unwrap().len(), 2);
+  }
+
+  #[tokio::test]
+  async fn test_write_tags_transaction_rejects_duplicates() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_fixture(dir.path(), "song.mp3", &sample_mp3());
+
+    let result = write_tags_transaction(vec![titled(&path, "A"), titled(&path, "B")]).await;
+    assert!(result.unwrap_err().contains("Duplicate file"));
+
+    let same = format!("{}/./song.mp3", dir.path().display());
+    let result = write_tags_transaction(vec![titled(&path, "A"), titled(&same, "B")]).await;
+    assert!(result.unwrap_err().contains("Duplicate file"));
+  }
+
+  #[cfg(unix)]
+  #[tokio::test]
+  async fn test_write_tags_transaction_keeps_permissions() {
+    use std::os::unix::fs::PermissionsExt;
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_fixture(dir.path(), "song.mp3", &sample_mp3());
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+
+    write_tags_transaction(vec![titled(&path, "Title")])
+      .await
+      .unwrap();
+    let mode = fs::metadata(&path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o640);
+  }
+}
@@ -0,0 +1,397 @@
+use crate::diff::{self, TagChange};
+use crate::patch::{TagField, TagPatch};
+use crate::util::{self, AudioTags};
+use regex::Regex;
+use std::path::Path;
+use std::sync::LazyLock;
+
+// The fields a rule applies to when it names none
+pub const DEFAULT_FIELDS: [TagField; 4] = [
+  TagField::Title,
+  TagField::Artists,
+  TagField::Album,
+  TagField::AlbumArtists,
+];
+
+// What a rule does to the text of its fields
+#[derive(Debug, Clone)]
+pub enum TransformAction {
+  // "the sound of silence" becomes "The Sound of Silence"
+  TitleCase,
+  // "The Sound Of Silence" becomes "The sound of silence"
+  SentenceCase,
+  // "ft.", "Feat" and "featuring" become "feat."
+  FixFeaturing,
+  // every match of the pattern, with $1 style references to its groups
+  Replace { pattern: Regex, replacement: String },
+}
+
+#[derive(Debug, Clone)]
+pub struct TransformRule {
+  pub action: TransformAction,
+  pub fields: Vec<TagField>,
+}
+
+// words title case leaves in lower case, unless they start a phrase
+const SMALL_WORDS: [&str; 19] = [
+  "a", "an", "and", "as", "at", "but", "by", "feat.", "for", "in", "nor", "of", "on", "or", "the",
+  "to", "vs", "vs.", "via",
+];
+
+static FEATURING: LazyLock<Regex> =
+  LazyLock::new(|| Regex::new(r"(?i)\b(?:featuring|feat|ft)\b\.?").unwrap());
+
+// a word whose case is deliberate, e.g. "AC/DC", "McCartney" or "iTunes"
+fn has_own_case(word: &str) -> bool {
+  let mut letters = word.chars().filter(|c| c.is_alphabetic());
+  letters.next();
+  letters.any(char::is_uppercase)
+}
+
+// the word with its first letter in upper case, e.g. "(live" as "(Live"
+fn capitalize(word: &str) -> String {
+  let mut capitalized = String::with_capacity(word.len());
+  let mut done = false;
+  for c in word.chars() {
+    if !done && c.is_alphabetic() {
+      capitalized.extend(c.to_uppercase());
+      done = true;
+    } else {
+      capitalized.push(c);
+    }
+  }
+  capitalized
+}
+
+// text in capitals throughout is shouted rather than deliberate, and is
+// lowered before its case is fixed
+fn unshout(text: &str) -> String {
+  if text.chars().any(char::is_lowercase) {
+    text.to_string()
+  } else {
+    text.to_lowercase()
+  }
+}
+
+fn title_case(text: &str) -> String {
+  let text = unshout(text);
+  let words: Vec<&str> = text.split(' ').collect();
+  let last = words.len() - 1;
+  let cased: Vec<String> = words
+    .iter()
+    .enumerate()
+    .map(|(i, word)| {
+      if has_own_case(word) {
+        return word.to_string();
+      }
+      let starts_phrase = i == 0
+        || i == last
+        || word.starts_with(['(', '['])
+        || words[i - 1].ends_with([':', '(', '['])
+        || words[i - 1] == "-";
+      if !starts_phrase && SMALL_WORDS.contains(&word.to_lowercase().as_str()) {
+        return word.to_lowercase();
+      }
+      capitalize(word)
+    })
+    .collect();
+  cased.join(" ")
+}
+
+fn sentence_case(text: &str) -> String {
+  let text = unshout(text);
+  let cased: Vec<String> = text
+    .split(' ')
+    .enumerate()
+    .map(|(i, word)| {
+      let lower = word.to_lowercase();
+      if has_own_case(word) {
+        word.to_string()
+      } else if i == 0 || lower == "i" || lower.starts_with("i'") {
+        capitalize(&lower)
+      } else {
+        lower
+      }
+    })
+    .collect();
+  cased.join(" ")
+}
+
+impl TransformAction {
+  fn apply(&self, text: &str) -> String {
+    match self {
+      Self::TitleCase => title_case(text),
+      Self::SentenceCase => sentence_case(text),
+      Self::FixFeaturing => FEATURING.replace_all(text, "feat.").into_owned(),
+      Self::Replace {
+        pattern,
+        replacement,
+      } => pattern.replace_all(text, replacement.as_str()).into_owned(),
+    }
+  }
+}
+
+// The value of a text field
+enum FieldValue<'a> {
+  One(&'a mut Option<String>),
+  Many(&'a mut Option<Vec<String>>),
+}
+
+// None for the fields that don't hold free text, e.g. numbers and identifiers
+fn field_value(tags: &mut AudioTags, field: TagField) -> Option<FieldValue<'_>> {
+  use FieldValue::{Many, One};
+  Some(match field {
+    TagField::Title => One(&mut tags.title),
+    TagField::Artists => Many(&mut tags.artists),
+    TagField::Album => One(&mut tags.album),
+    TagField::AlbumArtists => Many(&mut tags.album_artists),
+    TagField::Genre => Many(&mut tags.genres),
+    TagField::Composer => One(&mut tags.composer),
+    TagField::Lyricist => One(&mut tags.lyricist),
+    TagField::Conductor => One(&mut tags.conductor),
+    TagField::Remixer => One(&mut tags.remixer),
+    TagField::Publisher => One(&mut tags.publisher),
+    TagField::Copyright => One(&mut tags.copyright),
+    TagField::Lyrics => One(&mut tags.lyrics),
+    TagField::Mood => One(&mut tags.mood),
+    TagField::Grouping => One(&mut tags.grouping),
+    TagField::Work => One(&mut tags.work),
+    TagField::Movement => One(&mut tags.movement),
+    TagField::Subtitle => One(&mut tags.subtitle),
+    TagField::DiscSubtitle => One(&mut tags.disc_subtitle),
+    TagField::TitleSort => One(&mut tags.title_sort),
+    TagField::AlbumSort => One(&mut tags.album_sort),
+    TagField::ArtistSort => One(&mut tags.artist_sort),
+    TagField::AlbumArtistSort => One(&mut tags.album_artist_sort),
+    TagField::ComposerSort => One(&mut tags.composer_sort),
+    TagField::EncoderSoftware => One(&mut tags.encoder_software),
+    TagField::EncoderSettings => One(&mut tags.encoder_settings),
+    _ => return None,
+  })
+}
+
+/**
+ * Make sure every rule only names text fields
+ * @param rules - The rules
+ */
+pub fn validate_rules(rules: &[TransformRule]) -> Result<(), String> {
+  let mut probe = AudioTags::default();
+  match rules
+    .iter()
+    .flat_map(|rule| &rule.fields)
+    .find(|field| field_value(&mut probe, **field).is_none())
+  {
+    Some(field) => Err(format!("{:?} is not a text field", field)),
+    None => Ok(()),
+  }
+}
+
+/**
+ * The patch applying rules to tags, in order: only the fields they change are
+ * set
+ * @param tags - The tags
+ * @param rules - The rules, each applied to the result of the previous one
+ */
+pub fn transform_patch(tags: &AudioTags, rules: &[TransformRule]) -> Result<TagPatch, String> {
+  validate_rules(rules)?;
+  let mut transformed = tags.clone();
+  for rule in rules {
+    for field in &rule.fields {
+      match field_value(&mut transformed, *field) {
+        Some(FieldValue::One(Some(value))) => *value = rule.action.apply(value),
+        Some(FieldValue::Many(Some(values))) => {
+          for value in values.iter_mut() {
+            *value = rule.action.apply(value);
+          }
+        }
+        _ => {}
+      }
+    }
+  }
+
+  let mut patch = TagPatch::default();
+  let mut original = tags.clone();
+  for field in rules.iter().flat_map(|rule| &rule.fields) {
+    let values = (
+      field_value(&mut patch.set, *field),
+      field_value(&mut original, *field),
+      field_value(&mut transformed, *field),
+    );
+    match values {
+      (Some(FieldValue::One(set)), Some(FieldValue::One(old)), Some(FieldValue::One(new)))
+        if old != new =>
+      {
+        set.clone_from(new)
+      }
+      (Some(FieldValue::Many(set)), Some(FieldValue::Many(old)), Some(FieldValue::Many(new)))
+        if old != new =>
+      {
+        set.clone_from(new)
+      }
+      _ => {}
+    }
+  }
+  Ok(patch)
+}
+
+/**
+ * Apply clean-up rules to the tags of a file, e.g. title case or a regular
+ * expression replace. The file is only written when a rule changes something.
+ * @param file_path - The path to the audio file
+ * @param rules - The rules, applied in order
+ * @returns The fields that changed
+ */
+pub async fn transform_tags(
+  file_path: impl AsRef<Path>,
+  rules: Vec<TransformRule>,
+) -> Result<Vec<TagChange>, String> {
+  let tags = util::read_tags(&file_path).await?;
+  let patch = transform_patch(&tags, &rules)?;
+  if patch == TagPatch::default() {
+    return Ok(Vec::new());
+  }
+  util::update_tags(&file_path, patch).await?;
+  let written = util::read_tags(&file_path).await?;
+  Ok(diff::changes(&tags, &written))
+}
+
+/**
+ * Same as transform_tags, for a file in memory
+ * @param buffer - The audio file
+ * @param rules - The rules, applied in order
+ * @returns The file with the transformed tags, as given when nothing changed
+ */
+pub async fn transform_tags_to_buffer(
+  buffer: Vec<u8>,
+  rules: Vec<TransformRule>,
+) -> Result<Vec<u8>, String> {
+  let tags = util::read_tags_from_buffer(buffer.clone()).await?;
+  let patch = transform_patch(&tags, &rules)?;
+  if patch == TagPatch::default() {
+    return Ok(buffer);
+  }
+  util::update_tags_to_buffer(buffer, patch).await
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_support::sample_mp3;
+
+  fn rule(action: TransformAction) -> TransformRule {
+    TransformRule {
+      action,
+      fields: DEFAULT_FIELDS.to_vec(),
+    }
+  }
+
+  #[test]
+  fn test_title_case() {
+    assert_eq!(title_case("the sound of silence"), "The Sound of Silence");
+    assert_eq!(
+      title_case("WHAT A WONDERFUL WORLD"),
+      "What a Wonderful World"
+    );
+    assert_eq!(
+      title_case("back in black (live at the bbc)"),
+      "Back in Black (Live at the Bbc)"
+    );
+    assert_eq!(
+      title_case("songs by AC/DC and McCartney"),
+      "Songs by AC/DC and McCartney"
+    );
+    assert_eq!(title_case("what it's for"), "What It's For");
+  }
+
+  #[test]
+  fn test_sentence_case() {
+    assert_eq!(
+      sentence_case("The Sound Of Silence"),
+      "The sound of silence"
+    );
+    assert_eq!(sentence_case("I WANT YOU"), "I want you");
+    assert_eq!(sentence_case("Live At The BBC"), "Live at the BBC");
+  }
+
+  #[test]
+  fn test_fix_featuring() {
+    let fix = TransformAction::FixFeaturing;
+    assert_eq!(fix.apply("Song (ft. Artist)"), "Song (feat. Artist)");
+    assert_eq!(fix.apply("Song Featuring Artist"), "Song feat. Artist");
+    assert_eq!(fix.apply("Song FEAT Artist"), "Song feat. Artist");
+    assert_eq!(fix.apply("Song feat. Artist"), "Song feat. Artist");
+    assert_eq!(fix.apply("Left Field"), "Left Field");
+  }
+
+  #[test]
+  fn test_transform_patch() {
+    let tags = AudioTags {
+      title: Some("hello world ft. someone".to_string()),
+      artists: Some(vec!["artist one".to_string(), "Artist Two".to_string()]),
+      album: Some("Already Fine".to_string()),
+      composer: Some("Composer (Remastered)".to_string()),
+      ..Default::default()
+    };
+    let rules = vec![
+      rule(TransformAction::FixFeaturing),
+      rule(TransformAction::TitleCase),
+      TransformRule {
+        action: TransformAction::Replace {
+          pattern: Regex::new(r"\s*\(Remastered\)").unwrap(),
+          replacement: String::new(),
+        },
+        fields: vec![TagField::Composer],
+      },
+    ];
+    let patch = transform_patch(&tags, &rules).unwrap();
+    assert_eq!(
+      patch.set.title.as_deref(),
+      Some("Hello World feat. Someone")
+    );
+    assert_eq!(
+      patch.set.artists,
+      Some(vec!["Artist One".to_string(), "Artist Two".to_string()])
+    );
+    assert_eq!(patch.set.composer.as_deref(), Some("Composer"));
+    // unchanged fields are left out
+    assert_eq!(patch.set.album, None);
+    assert!(patch.clear.is_empty());
+
+    let not_text = TransformRule {
+      action: TransformAction::TitleCase,
+      fields: vec![TagField::Year],
+    };
+    assert_eq!(
+      transform_patch(&tags, &[not_text]).unwrap_err(),
+      "Year is not a text field"
+    );
+  }
+
+  #[tokio::test]
+  async fn test_transform_tags_to_buffer() {
+    let tags = AudioTags {
+      title: Some("the title".to_string()),
+      year: Some(2001),
+      ..Default::default()
+    };
+    let buffer = util::write_tags_to_buffer(sample_mp3(), tags)
+      .await
+      .unwrap();
+    let rules = vec![rule(TransformAction::TitleCase)];
+    let transformed = transform_tags_to_buffer(buffer.clone(), rules.clone())
+      .await
+      .unwrap();
+    let read = util::read_tags_from_buffer(transformed.clone())
+      .await
+      .unwrap();
+    assert_eq!(read.title.as_deref(), Some("The Title"));
+    assert_eq!(read.year, Some(2001));
+    // nothing left to change
+    assert_eq!(
+      transform_tags_to_buffer(transformed.clone(), rules)
+        .await
+        .unwrap(),
+      transformed
+    );
+  }
+}
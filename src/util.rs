@@ -1,16 +1,44 @@
 #![deny(clippy::all)]
 
-use lofty::config::WriteOptions;
+use crate::artwork::{self, ImageLimits, ImageRejection, ResizeOptions};
+use crate::atomic::WriteTarget;
+use crate::backup::{self, Backup};
+use crate::binary_objects::{self, BinaryObject};
+use crate::chapters::{self, Chapter};
+use crate::comments::{self, Comment};
+use crate::custom_fields;
+use crate::dsd;
+use crate::gapless::{self, GaplessInfo};
+use crate::genres;
+use crate::id3v1;
+use crate::identifiers::{self, IdentifierValidation};
+use crate::journal;
+use crate::legacy_encoding;
+use crate::mp4_chapters;
+use crate::mp4_freeform;
+use crate::normalize;
+use crate::patch::{TagField, TagPatch};
+use crate::placement::{self, ArtworkPolicy};
+use crate::probe;
+use crate::rating::{self, PopmScale};
+use crate::raw_items::{self, RawTagItem};
+use crate::read_warnings;
+use crate::riff::{self, Bwf};
+use crate::stream::{self, ByteSource};
+use crate::synced_lyrics::{self, SyncedLyrics};
+use lofty::config::{apply_global_options, GlobalOptions, ParseOptions, ParsingMode, WriteOptions};
 use lofty::error::LoftyError;
-use lofty::file::AudioFile;
+use lofty::file::{AudioFile, FileType, TaggedFile};
 use lofty::io::{FileLike, Length, Truncate};
 use lofty::picture::{MimeType, Picture, PictureType};
 use lofty::prelude::TaggedFileExt;
 use lofty::probe::Probe;
-use lofty::tag::{Accessor, ItemKey, ItemValue, Tag, TagItem};
-use std::fs::{self, File, OpenOptions};
-use std::io::Cursor;
-use std::path::Path;
+use lofty::tag::{Accessor, ItemKey, ItemValue, Tag, TagExt, TagItem, TagType};
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{BufReader, Cursor, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct Position {
@@ -18,10 +46,11 @@ pub struct Position {
   pub of: Option<u32>,
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
 pub enum AudioImageType {
   Icon,
   OtherIcon,
+  #[default]
   CoverFront,
   CoverBack,
   Leaflet,
@@ -43,12 +72,18 @@ pub enum AudioImageType {
   Other,
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Default)]
 pub struct Image {
   pub data: Vec<u8>,
   pub pic_type: AudioImageType,
   pub mime_type: Option<String>,
   pub description: Option<String>,
+  // the declared mime type doesn't match the image bytes (set on read)
+  pub mime_mismatch: bool,
+  // from the image header, None when it can't be parsed (set on read)
+  pub width: Option<u32>,
+  pub height: Option<u32>,
+  pub has_alpha: Option<bool>,
 }
 
 impl AudioImageType {
@@ -107,28 +142,239 @@ impl AudioImageType {
 
 impl Image {
   pub fn from_picture(picture: &Picture) -> Self {
+    let declared = picture
+      .mime_type()
+      .and_then(|mime_type| normalize_mime_type(mime_type.as_str()));
+    let inferred = infer::get(picture.data()).map(|kind| kind.mime_type().to_string());
+    let mime_mismatch =
+      matches!((&declared, &inferred), (Some(declared), Some(inferred)) if declared != inferred);
+    let header = artwork::image_header(picture.data());
     Self {
       data: picture.data().to_vec(),
       pic_type: AudioImageType::from_picture_type(&picture.pic_type()),
-      mime_type: picture.mime_type().map(|mime_type| mime_type.to_string()),
+      mime_type: declared.or(inferred),
       description: picture.description().map(|s| s.to_string()),
+      mime_mismatch,
+      width: header.map(|header| header.width),
+      height: header.map(|header| header.height),
+      has_alpha: header.map(|header| header.has_alpha),
     }
   }
+
+  /**
+   * The picture to store for this image
+   * @param mime_inference - How the declared mime type and the image bytes are weighed
+   */
+  pub fn to_picture(&self, mime_inference: MimeInference) -> Picture {
+    let declared_mime_type = self.mime_type.as_deref().and_then(parse_mime_type);
+    Picture::new_unchecked(
+      self.pic_type.build_picture_type(),
+      resolve_mime_type(&self.data, declared_mime_type, mime_inference),
+      self.description.clone(),
+      self.data.clone(),
+    )
+  }
 }
 
 #[derive(Debug, PartialEq, Clone, Default)]
 pub struct AudioTags {
   pub title: Option<String>,
+  pub subtitle: Option<String>,
   pub artists: Option<Vec<String>>,
   pub album: Option<String>,
   pub year: Option<u32>,
   pub genre: Option<String>,
+  // every genre, ID3 genre numbers resolved to names; written instead of
+  // `genre` when both are set
+  pub genres: Option<Vec<String>>,
   pub track: Option<Position>,
   pub album_artists: Option<Vec<String>>,
+  // the comment players show, the one without a description
   pub comment: Option<String>,
+  // every comment, e.g. iTunNORM next to the one players show; `comment`
+  // replaces the ones without a description when both are set
+  pub comments: Option<Vec<Comment>>,
   pub disc: Option<Position>,
+  // the title of the disc, e.g. of a box set
+  pub disc_subtitle: Option<String>,
   pub image: Option<Image>,
   pub all_images: Option<Vec<Image>>,
+  pub composer: Option<String>,
+  pub lyricist: Option<String>,
+  pub conductor: Option<String>,
+  // the remixer or mix artist
+  pub remixer: Option<String>,
+  // the publisher or record label, formats without a publisher field store it as the label
+  pub publisher: Option<String>,
+  pub copyright: Option<String>,
+  // unsynchronized lyrics
+  pub lyrics: Option<String>,
+  pub isrc: Option<String>,
+  // EAN/UPC of the release
+  pub barcode: Option<String>,
+  // the label's number of the release
+  pub catalog_number: Option<String>,
+  pub musicbrainz_recording_id: Option<String>,
+  // the track on the release, unlike the recording it differs between releases
+  pub musicbrainz_track_id: Option<String>,
+  pub musicbrainz_release_id: Option<String>,
+  pub musicbrainz_release_group_id: Option<String>,
+  pub musicbrainz_artist_id: Option<String>,
+  pub musicbrainz_album_artist_id: Option<String>,
+  // ReplayGain loudness normalization: gains in dB, peaks relative to full scale
+  pub replay_gain_track_gain: Option<f64>,
+  pub replay_gain_track_peak: Option<f64>,
+  pub replay_gain_album_gain: Option<f64>,
+  pub replay_gain_album_peak: Option<f64>,
+  // beats per minute, fractional where the format allows it
+  pub bpm: Option<f64>,
+  // the musical key, e.g. "Am" or "11B"
+  pub initial_key: Option<String>,
+  pub mood: Option<String>,
+  // from 0 to 100, e.g. 80 for four stars
+  pub rating: Option<u8>,
+  pub play_count: Option<u32>,
+  // part of a compilation by various artists
+  pub compilation: Option<bool>,
+  pub grouping: Option<String>,
+  // classical music: the work, and the name and number of the movement
+  pub work: Option<String>,
+  pub movement: Option<String>,
+  pub movement_number: Option<u32>,
+  // the names to sort by, e.g. "Beatles, The"
+  pub title_sort: Option<String>,
+  pub album_sort: Option<String>,
+  pub artist_sort: Option<String>,
+  pub album_artist_sort: Option<String>,
+  pub composer_sort: Option<String>,
+  // the software that encoded the audio, e.g. "LAME 3.100", and the settings
+  // it was run with
+  pub encoder_software: Option<String>,
+  pub encoder_settings: Option<String>,
+  // text items without a field above, by their name in the tag's format
+  pub custom_fields: Option<BTreeMap<String, Vec<String>>>,
+  // the bext chunk of WAV files, kept apart from the tags
+  pub bwf: Option<Bwf>,
+  // the MP4 freeform atoms holding binary data, by their custom field name
+  pub mp4_freeform: Option<BTreeMap<String, Vec<u8>>>,
+  // the ID3v2 GEOB frames, kept as is
+  pub binary_objects: Option<Vec<BinaryObject>>,
+}
+
+impl AudioTags {
+  // the plain text fields, in the order they are written
+  fn text_fields(&self) -> [(ItemKey, &Option<String>); 26] {
+    [
+      (ItemKey::TrackSubtitle, &self.subtitle),
+      (ItemKey::SetSubtitle, &self.disc_subtitle),
+      (ItemKey::Composer, &self.composer),
+      (ItemKey::Lyricist, &self.lyricist),
+      (ItemKey::Conductor, &self.conductor),
+      (ItemKey::Remixer, &self.remixer),
+      (ItemKey::CopyrightMessage, &self.copyright),
+      (ItemKey::Lyrics, &self.lyrics),
+      (ItemKey::Isrc, &self.isrc),
+      (ItemKey::Barcode, &self.barcode),
+      (ItemKey::CatalogNumber, &self.catalog_number),
+      (
+        ItemKey::MusicBrainzRecordingId,
+        &self.musicbrainz_recording_id,
+      ),
+      (ItemKey::MusicBrainzTrackId, &self.musicbrainz_track_id),
+      (ItemKey::MusicBrainzReleaseId, &self.musicbrainz_release_id),
+      (
+        ItemKey::MusicBrainzReleaseGroupId,
+        &self.musicbrainz_release_group_id,
+      ),
+      (ItemKey::MusicBrainzArtistId, &self.musicbrainz_artist_id),
+      (
+        ItemKey::MusicBrainzReleaseArtistId,
+        &self.musicbrainz_album_artist_id,
+      ),
+      (ItemKey::InitialKey, &self.initial_key),
+      (ItemKey::Mood, &self.mood),
+      (ItemKey::Movement, &self.movement),
+      (ItemKey::TrackTitleSortOrder, &self.title_sort),
+      (ItemKey::AlbumTitleSortOrder, &self.album_sort),
+      (ItemKey::TrackArtistSortOrder, &self.artist_sort),
+      (ItemKey::AlbumArtistSortOrder, &self.album_artist_sort),
+      (ItemKey::ComposerSortOrder, &self.composer_sort),
+      (ItemKey::EncoderSoftware, &self.encoder_software),
+    ]
+  }
+
+  fn replay_gain_fields(&self) -> [(ItemKey, Option<f64>); 4] {
+    [
+      (ItemKey::ReplayGainTrackGain, self.replay_gain_track_gain),
+      (ItemKey::ReplayGainTrackPeak, self.replay_gain_track_peak),
+      (ItemKey::ReplayGainAlbumGain, self.replay_gain_album_gain),
+      (ItemKey::ReplayGainAlbumPeak, self.replay_gain_album_peak),
+    ]
+  }
+}
+
+// "-6.50 dB", the unit being optional
+// ID3v2 keeps the encoder settings in TSSE along with the software, which
+// lofty reads back as the settings
+fn encoder_settings_shared(tag_type: TagType) -> bool {
+  ItemKey::EncoderSettings.map_key(tag_type, false)
+    == ItemKey::EncoderSoftware.map_key(tag_type, false)
+}
+
+fn parse_replay_gain(value: &str) -> Option<f64> {
+  let value = value.trim();
+  let number = value
+    .get(value.len().saturating_sub(2)..)
+    .filter(|unit| unit.eq_ignore_ascii_case("db"))
+    .map_or(value, |_| &value[..value.len() - 2]);
+  number.trim().parse().ok()
+}
+
+// gains as "-6.50 dB" and peaks as "0.988547", as written by the ReplayGain scanners
+fn format_replay_gain(key: &ItemKey, value: f64) -> String {
+  match key {
+    ItemKey::ReplayGainTrackGain | ItemKey::ReplayGainAlbumGain => format!("{:.2} dB", value),
+    _ => format!("{:.6}", value),
+  }
+}
+
+/**
+ * Normalize common aliases and sloppy spellings of image mime types
+ * @param mime_type - The mime type as declared by a file or a caller
+ * @returns None for an empty mime type
+ */
+fn normalize_mime_type(mime_type: &str) -> Option<String> {
+  let mime_type = mime_type.trim().to_ascii_lowercase();
+  let normalized = match mime_type.as_str() {
+    "" => return None,
+    "jpg" | "jpeg" | "image/jpg" | "image/pjpeg" | "image/jpe" => "image/jpeg",
+    "png" | "image/x-png" => "image/png",
+    "gif" => "image/gif",
+    "bmp" | "image/x-bmp" | "image/x-ms-bmp" => "image/bmp",
+    "tif" | "tiff" | "image/tif" => "image/tiff",
+    "webp" => "image/webp",
+    other => other,
+  };
+  Some(normalized.to_string())
+}
+
+fn parse_mime_type(mime_type: &str) -> Option<MimeType> {
+  normalize_mime_type(mime_type).map(|mime_type| MimeType::from_str(&mime_type))
+}
+
+fn resolve_mime_type(
+  image_data: &[u8],
+  declared_mime_type: Option<MimeType>,
+  mime_inference: MimeInference,
+) -> Option<MimeType> {
+  let inferred = || infer::get(image_data).map(|kind| MimeType::from_str(kind.mime_type()));
+  match mime_inference {
+    MimeInference::Sniff => inferred().or(declared_mime_type).or(Some(MimeType::Jpeg)),
+    MimeInference::TrustDeclared => declared_mime_type
+      .or_else(inferred)
+      .or(Some(MimeType::Jpeg)),
+    MimeInference::Off => declared_mime_type,
+  }
 }
 
 /**
@@ -136,20 +382,21 @@ pub struct AudioTags {
  * @param primary_tag - The primary tag to add the cover image to
  * @param image_data - The image data to add
  * @param image_description - The description of the image
- * @param default_mime_type - The default mime type to use if the image mime type is not found
+ * @param declared_mime_type - The mime type given by the caller, if any
+ * @param mime_inference - How the declared mime type and the image bytes are weighed
  */
 fn add_cover_image(
   primary_tag: &mut Tag,
   image_data: &[u8],
   image_description: Option<String>,
-  default_mime_type: MimeType,
+  declared_mime_type: Option<MimeType>,
+  mime_inference: MimeInference,
 ) {
   // add the new picture
   let buf = image_data.to_vec();
 
-  let mime_type = infer::get(&buf)
-    .map(|kind| MimeType::from_str(kind.mime_type()))
-    .unwrap_or(default_mime_type);
+  let mime_type = resolve_mime_type(&buf, declared_mime_type, mime_inference);
+
   let len = primary_tag.pictures().len();
   let mut pictures_stack: Vec<Picture> = Vec::with_capacity(len + 1);
   for i in (0..len).rev() {
@@ -160,7 +407,7 @@ fn add_cover_image(
   }
   pictures_stack.push(Picture::new_unchecked(
     lofty::picture::PictureType::CoverFront,
-    Some(mime_type),
+    mime_type,
     image_description,
     buf,
   ));
@@ -169,7 +416,101 @@ fn add_cover_image(
   }
 }
 
-fn get_values_from_item(tag: &Tag, item_key: &ItemKey) -> Vec<String> {
+/**
+ * Replace the front cover where it currently is instead of moving it first. A
+ * tag without a front cover gets the new one first, as with `add_cover_image`.
+ * @param primary_tag - The primary tag to add the cover image to
+ * @param image_data - The image data to add
+ * @param image_description - The description of the image
+ * @param declared_mime_type - The mime type given by the caller, if any
+ * @param mime_inference - How the declared mime type and the image bytes are weighed
+ */
+fn replace_cover_image_in_place(
+  primary_tag: &mut Tag,
+  image_data: &[u8],
+  image_description: Option<String>,
+  declared_mime_type: Option<MimeType>,
+  mime_inference: MimeInference,
+) {
+  let covers: Vec<usize> = primary_tag
+    .pictures()
+    .iter()
+    .enumerate()
+    .filter(|(_, picture)| picture.pic_type() == PictureType::CoverFront)
+    .map(|(i, _)| i)
+    .collect();
+  let Some((&first, rest)) = covers.split_first() else {
+    add_cover_image(
+      primary_tag,
+      image_data,
+      image_description,
+      declared_mime_type,
+      mime_inference,
+    );
+    return;
+  };
+  // the later duplicates go, the first cover keeps its slot
+  for &i in rest.iter().rev() {
+    primary_tag.remove_picture(i);
+  }
+  primary_tag.set_picture(
+    first,
+    Picture::new_unchecked(
+      PictureType::CoverFront,
+      resolve_mime_type(image_data, declared_mime_type, mime_inference),
+      image_description,
+      image_data.to_vec(),
+    ),
+  );
+}
+
+/**
+ * Store a picture in the tag, replacing the pictures of the same type. Front
+ * covers go first, other pictures are appended after the existing ones.
+ * @param primary_tag - The primary tag to add the picture to
+ * @param image - The picture to add
+ * @param mime_inference - How the declared mime type and the image bytes are weighed
+ */
+fn set_picture(primary_tag: &mut Tag, image: &Image, mime_inference: MimeInference) {
+  let declared_mime_type = image.mime_type.as_deref().and_then(parse_mime_type);
+  if image.pic_type == AudioImageType::CoverFront {
+    add_cover_image(
+      primary_tag,
+      &image.data,
+      image.description.clone(),
+      declared_mime_type,
+      mime_inference,
+    );
+    return;
+  }
+  let picture_type = image.pic_type.build_picture_type();
+  let len = primary_tag.pictures().len();
+  for i in (0..len).rev() {
+    if primary_tag.pictures()[i].pic_type() == picture_type {
+      primary_tag.remove_picture(i);
+    }
+  }
+  primary_tag.push_picture(image.to_picture(mime_inference));
+}
+
+// How the artists and album artists are stored when there are several
+#[derive(Debug, PartialEq, Clone)]
+pub enum ArtistSeparator {
+  // joined in one value; reading splits on the separator without its
+  // surrounding whitespace, so ", " splits "A,B" as well as "A, B"
+  Text(String),
+  // one value per artist (ID3v2.4 null separated frames, repeated Vorbis
+  // comments), values are never split
+  MultiValue,
+}
+
+impl Default for ArtistSeparator {
+  fn default() -> Self {
+    Self::Text(", ".to_string())
+  }
+}
+
+fn get_values_from_item(tag: &Tag, item_key: &ItemKey, separator: &ArtistSeparator) -> Vec<String> {
   let mut result: Vec<String> = Vec::new();
   for item in tag.get_items(item_key) {
     let values = item
@@ -177,64 +518,202 @@ fn get_values_from_item(tag: &Tag, item_key: &ItemKey) -> Vec<String> {
       .text()
       .map(|s| s.to_string())
       .unwrap_or_default();
-    for value in values.split(',') {
+    let pattern = match separator {
+      ArtistSeparator::Text(separator) if separator.trim().is_empty() => separator.as_str(),
+      ArtistSeparator::Text(separator) => separator.trim(),
+      ArtistSeparator::MultiValue => "",
+    };
+    if pattern.is_empty() {
+      result.push(values);
+      continue;
+    }
+    for value in values.split(pattern) {
       result.push(value.trim().to_string());
     }
   }
   result
 }
 
+fn push_values(
+  primary_tag: &mut Tag,
+  item_key: ItemKey,
+  artists: &[String],
+  separator: &ArtistSeparator,
+) {
+  match separator {
+    ArtistSeparator::Text(separator) => {
+      primary_tag.push(TagItem::new(
+        item_key,
+        ItemValue::Text(artists.join(separator)),
+      ));
+    }
+    // lofty writes one frame per item, so the values go in one null separated frame
+    ArtistSeparator::MultiValue if primary_tag.tag_type() == TagType::Id3v2 => {
+      primary_tag.push(TagItem::new(item_key, ItemValue::Text(artists.join("\0"))));
+    }
+    ArtistSeparator::MultiValue => {
+      for artist in artists {
+        primary_tag.push(TagItem::new(
+          item_key.clone(),
+          ItemValue::Text(artist.clone()),
+        ));
+      }
+    }
+  }
+}
+
 // add method to AudioTags from &Tag
 impl AudioTags {
   pub fn from_tag(tag: &Tag) -> Self {
-    let artists_values = get_values_from_item(tag, &ItemKey::TrackArtists);
-    let album_artists_values = get_values_from_item(tag, &ItemKey::AlbumArtist);
-    let mut all_images: Vec<Image> = tag.pictures().iter().map(Image::from_picture).collect();
-    // sort the images by the picture type, the cover image should be the first
-    all_images.sort_by_key(|image| {
-      if image.pic_type == AudioImageType::CoverFront {
-        0
-      } else {
-        1
-      }
-    });
-    // get the first element only if it is the cover image or None
-    let image = all_images.first().map_or_else(
-      || None,
-      |image| {
+    Self::from_tag_with_options(tag, &ReadTagsOptions::default())
+  }
+
+  pub fn from_tag_with_options(tag: &Tag, options: &ReadTagsOptions) -> Self {
+    let mut artists_values =
+      get_values_from_item(tag, &ItemKey::TrackArtists, &options.artist_separator);
+    if artists_values.is_empty() {
+      // tags holding a single artist, e.g. ID3v1
+      artists_values = get_values_from_item(tag, &ItemKey::TrackArtist, &options.artist_separator);
+    }
+    let album_artists_values =
+      get_values_from_item(tag, &ItemKey::AlbumArtist, &options.artist_separator);
+    let text = |key: ItemKey| tag.get_string(&key).map(|s| s.to_string());
+    let replay_gain = |key: ItemKey| tag.get_string(&key).and_then(parse_replay_gain);
+    let (rating, play_count) = rating::read_rating(tag);
+    let all_comments = comments::comments_from_tag(tag);
+    let genres: Vec<String> = tag
+      .get_strings(&ItemKey::Genre)
+      .flat_map(genres::genre_names)
+      .collect();
+    let mut all_images: Vec<Image> = if options.skip_images {
+      Vec::new()
+    } else {
+      tag.pictures().iter().map(Image::from_picture).collect()
+    };
+    if !options.preserve_picture_order {
+      // sort the images by the picture type, the cover image should be the first
+      all_images.sort_by_key(|image| {
         if image.pic_type == AudioImageType::CoverFront {
-          Some(image.clone())
+          0
         } else {
-          None
+          1
         }
-      },
-    );
+      });
+    }
+    // the first cover image, which is the first element unless the order is preserved
+    let image = all_images
+      .iter()
+      .find(|image| image.pic_type == AudioImageType::CoverFront)
+      .cloned();
     Self {
       title: tag.title().map(|s| s.to_string()),
+      subtitle: text(ItemKey::TrackSubtitle),
       artists: Some(artists_values),
       album: tag.album().map(|s| s.to_string()),
       year: tag.year(),
-      genre: tag.genre().map(|s| s.to_string()),
+      genre: genres.first().cloned(),
+      genres: (!genres.is_empty()).then_some(genres),
       track: match (tag.track(), tag.track_total()) {
         (None, None) => None,
         (no, of) => Some(Position { no, of }),
       },
       album_artists: Some(album_artists_values),
-      comment: tag.comment().map(|s| s.to_string()),
+      comment: comments::default_comment(tag),
+      comments: (!all_comments.is_empty()).then_some(all_comments),
       disc: match (tag.disk(), tag.disk_total()) {
         (None, None) => None,
         (no, of) => Some(Position { no, of }),
       },
+      disc_subtitle: text(ItemKey::SetSubtitle),
       image,
       all_images: if all_images.is_empty() {
         None
       } else {
         Some(all_images)
       },
+      composer: text(ItemKey::Composer),
+      lyricist: text(ItemKey::Lyricist),
+      conductor: text(ItemKey::Conductor),
+      remixer: text(ItemKey::Remixer),
+      publisher: text(ItemKey::Publisher).or_else(|| text(ItemKey::Label)),
+      copyright: text(ItemKey::CopyrightMessage),
+      lyrics: text(ItemKey::Lyrics),
+      isrc: text(ItemKey::Isrc),
+      barcode: text(ItemKey::Barcode),
+      catalog_number: text(ItemKey::CatalogNumber),
+      musicbrainz_recording_id: text(ItemKey::MusicBrainzRecordingId),
+      musicbrainz_track_id: text(ItemKey::MusicBrainzTrackId),
+      musicbrainz_release_id: text(ItemKey::MusicBrainzReleaseId),
+      musicbrainz_release_group_id: text(ItemKey::MusicBrainzReleaseGroupId),
+      musicbrainz_artist_id: text(ItemKey::MusicBrainzArtistId),
+      musicbrainz_album_artist_id: text(ItemKey::MusicBrainzReleaseArtistId),
+      replay_gain_track_gain: replay_gain(ItemKey::ReplayGainTrackGain),
+      replay_gain_track_peak: replay_gain(ItemKey::ReplayGainTrackPeak),
+      replay_gain_album_gain: replay_gain(ItemKey::ReplayGainAlbumGain),
+      replay_gain_album_peak: replay_gain(ItemKey::ReplayGainAlbumPeak),
+      bpm: [ItemKey::Bpm, ItemKey::IntegerBpm]
+        .iter()
+        .find_map(|key| tag.get_string(key)?.trim().parse().ok()),
+      initial_key: text(ItemKey::InitialKey),
+      mood: text(ItemKey::Mood),
+      rating,
+      play_count,
+      compilation: tag
+        .get_string(&ItemKey::FlagCompilation)
+        .map(|flag| flag.trim() == "1" || flag.trim().eq_ignore_ascii_case("true")),
+      grouping: text(TagField::Grouping.item_keys(tag.tag_type())[0].clone()),
+      work: TagField::Work
+        .item_keys(tag.tag_type())
+        .iter()
+        .find_map(|key| text(key.clone())),
+      movement: text(ItemKey::Movement),
+      movement_number: text(ItemKey::MovementNumber).and_then(|no| no.trim().parse().ok()),
+      title_sort: text(ItemKey::TrackTitleSortOrder),
+      album_sort: text(ItemKey::AlbumTitleSortOrder),
+      artist_sort: text(ItemKey::TrackArtistSortOrder),
+      album_artist_sort: text(ItemKey::AlbumArtistSortOrder),
+      composer_sort: text(ItemKey::ComposerSortOrder),
+      encoder_software: text(ItemKey::EncoderSoftware).or_else(|| {
+        encoder_settings_shared(tag.tag_type())
+          .then(|| text(ItemKey::EncoderSettings))
+          .flatten()
+      }),
+      encoder_settings: text(ItemKey::EncoderSettings)
+        .filter(|_| !encoder_settings_shared(tag.tag_type())),
+      custom_fields: custom_fields::read_custom_fields(tag),
+      bwf: None,
+      mp4_freeform: None,
+      binary_objects: binary_objects::binary_objects_from_tag(tag),
     }
   }
 
   pub fn to_tag(&self, primary_tag: &mut Tag) {
+    self.to_tag_with_options(primary_tag, &WriteTagsOptions::default());
+  }
+
+  pub fn to_tag_with_options(&self, primary_tag: &mut Tag, options: &WriteTagsOptions) {
+    // first, so a custom field can't override one of the fields below
+    if let Some(fields) = self.custom_fields.as_ref() {
+      custom_fields::write_custom_fields(primary_tag, fields);
+    }
+    // also before the fields below, as an ID3v2 POPM frame is written by
+    // converting the whole tag, which splits the null separated values again
+    if self.rating.is_some() || self.play_count.is_some() {
+      rating::set_rating(
+        primary_tag,
+        self.rating,
+        self.play_count,
+        options.popm_scale,
+      );
+    }
+    if let Some(objects) = self
+      .binary_objects
+      .as_ref()
+      .filter(|_| !options.preserve_binary_objects)
+    {
+      binary_objects::set_binary_objects(primary_tag, objects, options.id3v2_version);
+    }
+
     // Update the tag with new values
     self.title.as_ref().map(|title| {
       primary_tag.remove_key(&ItemKey::TrackTitle);
@@ -251,10 +730,12 @@ impl AudioTags {
           ItemKey::TrackArtist,
           ItemValue::Text(artist_value.clone()),
         ));
-        primary_tag.push(TagItem::new(
+        push_values(
+          primary_tag,
           ItemKey::TrackArtists,
-          ItemValue::Text(artists.join(", ")),
-        ));
+          artists,
+          &options.artist_separator,
+        );
       }
     }
 
@@ -270,9 +751,26 @@ impl AudioTags {
       primary_tag.insert_text(ItemKey::RecordingDate, year.to_string());
     }
 
-    if let Some(genre) = self.genre.as_ref() {
+    let genres = match (self.genres.as_ref(), self.genre.as_ref()) {
+      (Some(genres), _) if !genres.is_empty() => Some(genres.clone()),
+      (_, Some(genre)) => Some(vec![genre.clone()]),
+      _ => None,
+    };
+    if let Some(mut genres) = genres {
       primary_tag.remove_key(&ItemKey::Genre);
-      primary_tag.insert_text(ItemKey::Genre, genre.clone());
+      if primary_tag.tag_type() == TagType::Id3v1 {
+        // ID3v1 holds a single genre, by its number
+        genres.truncate(1);
+        if let Some(name) = genres::id3v1_genre(&genres[0]) {
+          genres[0] = name.to_string();
+        }
+      }
+      push_values(
+        primary_tag,
+        ItemKey::Genre,
+        &genres,
+        &ArtistSeparator::MultiValue,
+      );
     }
 
     if let Some(track) = self.track.as_ref() {
@@ -300,27 +798,105 @@ impl AudioTags {
     if let Some(album_artists) = self.album_artists.as_ref() {
       if !album_artists.is_empty() {
         primary_tag.remove_key(&ItemKey::AlbumArtist);
-        primary_tag.push(TagItem::new(
+        push_values(
+          primary_tag,
           ItemKey::AlbumArtist,
-          ItemValue::Text(album_artists.join(", ")),
-        ));
+          album_artists,
+          &options.artist_separator,
+        );
       }
     }
 
+    if let Some(all_comments) = self.comments.as_ref() {
+      comments::set_comments(primary_tag, all_comments);
+    }
     if let Some(comment) = self.comment.as_ref() {
-      primary_tag.remove_key(&ItemKey::Comment);
-      primary_tag.insert_text(ItemKey::Comment, comment.clone());
+      comments::set_default_comment(primary_tag, comment);
+    }
+
+    if let Some(publisher) = self.publisher.as_ref() {
+      primary_tag.remove_key(&ItemKey::Publisher);
+      primary_tag.remove_key(&ItemKey::Label);
+      // MP4 only has a label
+      let key = match ItemKey::Publisher.map_key(primary_tag.tag_type(), false) {
+        Some(_) => ItemKey::Publisher,
+        None => ItemKey::Label,
+      };
+      primary_tag.insert_text(key, publisher.clone());
+    }
+
+    for (key, value) in self.text_fields() {
+      if let Some(value) = value {
+        primary_tag.remove_key(&key);
+        // unchecked, as some keys are special cased by the format (e.g. the
+        // recording id becomes an ID3v2 UFID frame) and have no plain mapping
+        primary_tag.push_unchecked(TagItem::new(key, ItemValue::Text(value.clone())));
+      }
+    }
+
+    // the software is written to a shared frame instead; MP4 has no place for
+    // the settings
+    if let Some(settings) = self.encoder_settings.as_ref() {
+      if !encoder_settings_shared(primary_tag.tag_type()) {
+        primary_tag.remove_key(&ItemKey::EncoderSettings);
+        primary_tag.insert_text(ItemKey::EncoderSettings, settings.clone());
+      }
+    }
+
+    for (key, value) in self.replay_gain_fields() {
+      if let Some(value) = value {
+        primary_tag.remove_key(&key);
+        primary_tag.insert_text(key.clone(), format_replay_gain(&key, value));
+      }
+    }
+
+    if let Some(bpm) = self.bpm {
+      primary_tag.remove_key(&ItemKey::Bpm);
+      primary_tag.remove_key(&ItemKey::IntegerBpm);
+      // ID3v2 only has TBPM, which taggers read fractional values from too
+      let key = match ItemKey::Bpm.map_key(primary_tag.tag_type(), false) {
+        Some(_) => ItemKey::Bpm,
+        None => ItemKey::IntegerBpm,
+      };
+      primary_tag.insert_text(key, bpm.to_string());
+    }
+
+    if let Some(compilation) = self.compilation {
+      primary_tag.remove_key(&ItemKey::FlagCompilation);
+      primary_tag.insert_text(ItemKey::FlagCompilation, u8::from(compilation).to_string());
+    }
+
+    // the first key of the field is the one written, see TagField::item_keys
+    let tag_type = primary_tag.tag_type();
+    for (field, value) in [
+      (TagField::Grouping, &self.grouping),
+      (TagField::Work, &self.work),
+    ] {
+      if let Some(value) = value {
+        let keys = field.item_keys(tag_type);
+        for key in keys {
+          primary_tag.remove_key(key);
+        }
+        primary_tag.insert_text(keys[0].clone(), value.clone());
+      }
+    }
+
+    if let Some(movement_number) = self.movement_number {
+      primary_tag.remove_key(&ItemKey::MovementNumber);
+      primary_tag.insert_text(ItemKey::MovementNumber, movement_number.to_string());
     }
 
     if let Some(all_images) = self.all_images.as_ref() {
       let mut all_images = all_images.clone();
-      all_images.sort_by_key(|image| {
-        if image.pic_type == AudioImageType::CoverFront {
-          0
-        } else {
-          1
-        }
-      });
+      if !options.preserve_picture_order {
+        all_images.sort_by_key(|image| {
+          if image.pic_type == AudioImageType::CoverFront {
+            0
+          } else {
+            1
+          }
+        });
+      }
       let len = primary_tag.pictures().len();
       for i in (0..len).rev() {
         primary_tag.remove_picture(i);
@@ -328,1342 +904,3684 @@ impl AudioTags {
       for image in all_images {
         primary_tag.push_picture(Picture::new_unchecked(
           image.pic_type.build_picture_type(),
-          image.mime_type.as_ref().map(|s| MimeType::from_str(s)),
+          image.mime_type.as_deref().and_then(parse_mime_type),
           image.description.as_ref().map(|s| s.to_string()),
           image.data.clone(),
         ));
       }
     } else if let Some(image) = self.image.as_ref() {
-      add_cover_image(
+      let write_cover = if options.preserve_picture_order {
+        replace_cover_image_in_place
+      } else {
+        add_cover_image
+      };
+      write_cover(
         primary_tag,
         &image.data,
         image.description.as_ref().map(|s| s.to_string()),
-        image
-          .mime_type
-          .as_ref()
-          .map(|s| MimeType::from_str(s))
-          .unwrap_or(MimeType::Jpeg),
+        image.mime_type.as_deref().and_then(parse_mime_type),
+        options.mime_inference,
       );
     }
+
+    if options.normalize_text {
+      normalize::normalize_items(primary_tag, &written_keys(self, primary_tag));
+    }
   }
 }
 
-async fn generic_read_tags<F>(file: &mut F) -> Result<AudioTags, String>
-where
-  F: FileLike,
-  LoftyError: From<<F as Truncate>::Error>,
-  LoftyError: From<<F as Length>::Error>,
-{
-  let probe = Probe::new(file);
-  let Ok(probe) = probe.guess_file_type() else {
-    return Err("Failed to guess file type".to_string());
-  };
-  let Ok(tagged_file) = probe.read() else {
-    return Err("Failed to read audio file".to_string());
-  };
+// the name of an error kind without what it holds, e.g. "FileDecoding"
+fn kind_name(kind: impl std::fmt::Debug) -> String {
+  let name = format!("{:?}", kind);
+  name
+    .split(['(', ' ', '{'])
+    .next()
+    .unwrap_or_default()
+    .to_string()
+}
 
-  tagged_file
-    .primary_tag()
-    .map_or(Ok(AudioTags::default()), |tag| Ok(AudioTags::from_tag(tag)))
+/**
+ * Describe a lofty error by what failed, the kind of error and lofty's own
+ * message, e.g. "Failed to read audio file (FileDecoding): MPEG: File contains
+ * no frames"
+ * @param context - What failed
+ * @param e - The error
+ */
+pub(crate) fn lofty_error(context: &str, e: &LoftyError) -> String {
+  format!("{} ({}): {}", context, kind_name(e.kind()), e)
 }
 
-pub async fn read_tags(file_path: String) -> Result<AudioTags, String> {
-  let path = Path::new(&file_path);
-  let mut file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
-  generic_read_tags(&mut file).await
+// an IO error described as lofty_error does, e.g. "Failed to open file
+// (NotFound): No such file or directory (os error 2)"
+pub(crate) fn io_error(context: &str, e: &std::io::Error) -> String {
+  format!("{} ({:?}): {}", context, e.kind(), e)
 }
 
-pub async fn read_tags_from_buffer(buffer: Vec<u8>) -> Result<AudioTags, String> {
-  let mut cursor = Cursor::new(buffer.to_vec());
-  generic_read_tags(&mut cursor).await
+// open a file for reading, naming it in the error
+pub(crate) fn open_file(path: &Path) -> Result<File, String> {
+  File::open(path).map_err(|e| io_error(&format!("Failed to open file {}", path.display()), &e))
 }
 
-async fn generic_write_tags<F>(mut file: F, mut out: F, tags: AudioTags) -> Result<(), String>
-where
-  F: FileLike,
-  LoftyError: From<<F as Truncate>::Error>,
-  LoftyError: From<<F as Length>::Error>,
-{
-  let probe = Probe::new(&mut file);
-  let Ok(probe) = probe.guess_file_type() else {
-    return Err("Failed to guess file type".to_string());
-  };
-  let Ok(mut tagged_file) = probe.read() else {
-    return Err("Failed to read audio file".to_string());
-  };
+// a file descriptor the caller holds, e.g. from Node's fs.open, as a File of
+// its own: the descriptor is duplicated, so dropping the File leaves it open.
+// The two share their position.
+#[cfg(unix)]
+fn file_from_fd(fd: i32) -> Result<File, String> {
+  use std::os::fd::BorrowedFd;
 
-  // Check if the file has tags
-  if tagged_file.primary_tag().is_none() {
-    // create the principal tag
-    let tag = Tag::new(tagged_file.primary_tag_type());
-    tagged_file.insert_tag(tag);
+  if fd < 0 {
+    return Err(format!("Invalid file descriptor {}", fd));
   }
-
-  let primary_tag = tagged_file
-    .primary_tag_mut()
-    .ok_or("Failed to get primary tag after been added".to_string())?;
-
-  // Update the tag with new values
-  tags.to_tag(primary_tag);
-
-  // Write the updated tag back to the file
-  tagged_file
-    .save_to(&mut out, WriteOptions::default())
-    .map_err(|e| format!("Failed to write audio to buffer: {}", e))?;
-
-  Ok(())
+  // SAFETY: the caller keeps the descriptor open for the call, and one that
+  // isn't open fails to be duplicated
+  let borrowed = unsafe { BorrowedFd::borrow_raw(fd) };
+  borrowed
+    .try_clone_to_owned()
+    .map(File::from)
+    .map_err(|e| io_error(&format!("Invalid file descriptor {}", fd), &e))
 }
 
-pub async fn write_tags(file_path: String, tags: AudioTags) -> Result<(), String> {
-  let path = Path::new(&file_path);
-  let mut file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
-  let mut out = OpenOptions::new()
-    .read(true)
-    .write(true)
-    .open(path)
-    .map_err(|e| format!("Failed to open file: {}", e))?;
-  generic_write_tags(&mut file, &mut out, tags).await
+#[cfg(not(unix))]
+fn file_from_fd(_fd: i32) -> Result<File, String> {
+  Err("File descriptors are only supported on Unix".to_string())
 }
 
-pub async fn write_tags_to_buffer(buffer: Vec<u8>, tags: AudioTags) -> Result<Vec<u8>, String> {
-  // copy the buffer to a new vec
-  let mut input: Vec<u8> = buffer.to_vec();
-  let mut output: Vec<u8> = buffer.to_vec();
-
-  // Create a fresh cursor for reading
-  let mut cursor = Cursor::new(&mut input);
-  let mut out = Cursor::new(&mut output);
+// names the file in the errors of a file-based function, e.g. "song.mp3:
+// Failed to read audio file (...): ..."; the errors of opening it already do
+pub(crate) fn in_file(path: &Path) -> impl Fn(String) -> String + '_ {
+  move |e| format!("{}: {}", path.display(), e)
+}
 
-  generic_write_tags(&mut cursor, &mut out, tags).await?;
+fn probe_read<R: Read + Seek>(
+  file: &mut R,
+  parse_options: ParseOptions,
+) -> Result<TaggedFile, String> {
+  Probe::new(file)
+    .options(parse_options)
+    .guess_file_type()
+    .map_err(|e| io_error("Failed to guess file type", &e))?
+    .read()
+    .map_err(|e| lofty_error("Failed to read audio file", &e))
+}
 
-  Ok(out.into_inner().to_vec())
+// The tags of a file as lofty reads them. A DSD file, which lofty doesn't
+// know, is read as an MPEG stream holding its ID3v2 tag.
+fn read_tagged_file<R: Read + Seek>(
+  file: &mut R,
+  parse_options: ParseOptions,
+) -> Result<TaggedFile, String> {
+  match dsd::format(file) {
+    Some(format) => {
+      let tag = dsd::read_id3v2(file, format)?.unwrap_or_default();
+      probe_read(&mut Cursor::new(mpeg_stand_in(&tag)), parse_options)
+    }
+    None => probe_read(file, parse_options),
+  }
 }
 
-async fn generic_clear_tags<F>(file: &mut F, out: &mut F) -> Result<(), String>
-where
-  F: FileLike,
-  LoftyError: From<<F as Truncate>::Error>,
-  LoftyError: From<<F as Length>::Error>,
-{
-  let probe = Probe::new(file);
-  let Ok(probe) = probe.guess_file_type() else {
-    return Err("Failed to guess file type".to_string());
-  };
-  let Ok(mut tagged_file) = probe.read() else {
-    return Err("Failed to read audio file".to_string());
-  };
+// Lofty's allocation limit doesn't cover the pictures of every format, some
+// are read along with their whole tag, so they are checked before being copied
+fn check_picture_sizes(tag: &Tag, max_bytes: usize) -> Result<(), String> {
+  match tag
+    .pictures()
+    .iter()
+    .map(|picture| picture.data().len())
+    .find(|size| *size > max_bytes)
+  {
+    Some(size) => Err(ImageRejection::TooLarge { size, max_bytes }.to_string()),
+    None => Ok(()),
+  }
+}
 
-  // Create a new empty tag of the same type
-  let empty_tag = Tag::new(tagged_file.primary_tag_type());
+async fn generic_read_tags<R: Read + Seek>(
+  file: &mut R,
+  options: &ReadTagsOptions,
+) -> Result<AudioTags, String> {
+  let _limit = AllocationLimit::apply(options.max_allocation_bytes);
+  let tagged_file = read_tagged_file(file, options.parse_options())?;
+
+  if let (Some(tag), Some(max_bytes)) = (
+    read_tag(&tagged_file, options),
+    options.max_allocation_bytes,
+  ) {
+    check_picture_sizes(tag, max_bytes)?;
+  }
+  let mut tags = audio_tags(&tagged_file, file, options)?;
+  match tagged_file.file_type() {
+    FileType::Wav => tags.bwf = riff::read_bwf(file)?,
+    FileType::Mp4 => tags.mp4_freeform = mp4_freeform::read_mp4_freeform(file)?,
+    _ => {}
+  }
+  Ok(tags)
+}
 
-  // Replace the existing primary tag with the empty one
-  tagged_file.insert_tag(empty_tag);
+pub async fn read_tags(file_path: impl AsRef<Path>) -> Result<AudioTags, String> {
+  let path = file_path.as_ref();
+  let mut file = open_file(path)?;
+  generic_read_tags(&mut file, &ReadTagsOptions::default())
+    .await
+    .map_err(in_file(path))
+}
 
-  // Write the updated tag back to the file
-  tagged_file
-    .save_to(out, WriteOptions::default())
-    .map_err(|e| format!("Failed to write audio file: {}", e))?;
+pub async fn read_tags_from_buffer(buffer: impl AsRef<[u8]>) -> Result<AudioTags, String> {
+  let mut cursor = Cursor::new(buffer.as_ref());
+  generic_read_tags(&mut cursor, &ReadTagsOptions::default()).await
+}
 
-  Ok(())
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ReadTagsOptions {
+  // recover the tags of files whose audio is cut off instead of failing
+  pub tolerate_truncation: bool,
+  // keep the pictures in tag order instead of moving the front cover first
+  pub preserve_picture_order: bool,
+  pub artist_separator: ArtistSeparator,
+  // read the ID3v1 tag of a file whose only tag it is, which has no primary
+  // tag otherwise
+  pub id3v1_fallback: bool,
+  // leave the pictures out, which are then neither parsed nor copied
+  pub skip_images: bool,
+  // list the recoverable problems of the file in the outcome
+  pub report_warnings: bool,
+  // how damaged data is handled: skipped over by default, failing the read
+  // when Strict
+  pub parsing_mode: ParsingMode,
+  // the largest allocation a single item may need, e.g. a picture; None for
+  // lofty's limit of 16 MiB
+  pub max_allocation_bytes: Option<usize>,
+  // only parse the tags, not the audio stream for its properties
+  pub skip_properties: bool,
+  // the encoding legacy taggers stored the text declared Latin-1 in, as a
+  // WHATWG label such as "windows-1251"
+  pub legacy_encoding: Option<String>,
+  // read the encoder delay and padding into the outcome
+  pub gapless_info: bool,
 }
 
-pub async fn clear_tags(file_path: String) -> Result<(), String> {
-  let path = Path::new(&file_path);
-  let mut file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
-  let mut out = OpenOptions::new()
-    .read(true)
-    .write(true)
-    .open(path)
-    .map_err(|e| format!("Failed to open file: {}", e))?;
-  generic_clear_tags(&mut file, &mut out).await
+impl ReadTagsOptions {
+  // the options lofty parses with
+  fn parse_options(&self) -> ParseOptions {
+    ParseOptions::new()
+      .parsing_mode(self.parsing_mode)
+      .read_properties(!self.skip_properties)
+      // without pictures, lofty skips their frames and blocks
+      .read_cover_art(!self.skip_images)
+  }
 }
 
-pub async fn clear_tags_to_buffer(buffer: Vec<u8>) -> Result<Vec<u8>, String> {
-  // copy the buffer to a new vec
-  let mut input: Vec<u8> = buffer.to_vec();
-  let mut output: Vec<u8> = buffer.to_vec();
+thread_local! {
+  // the global options last applied on this thread, which lofty doesn't expose
+  static GLOBAL_OPTIONS: Cell<GlobalOptions> = const { Cell::new(GlobalOptions::new()) };
+}
 
-  // Create a fresh cursor for reading
-  let mut cursor = Cursor::new(&mut input);
-  let mut out = Cursor::new(&mut output);
+// apply lofty's global options for this thread, returning the previous ones
+fn set_global_options(options: GlobalOptions) -> GlobalOptions {
+  apply_global_options(options);
+  GLOBAL_OPTIONS.replace(options)
+}
 
-  generic_clear_tags(&mut cursor, &mut out).await?;
+// Lofty's allocation limit for the reads of this thread, which is a global
+// option of the thread rather than a parse option; the options it replaced are
+// restored on drop. Reads holding it must not await.
+struct AllocationLimit {
+  previous: GlobalOptions,
+}
 
-  Ok(out.into_inner().to_vec())
+impl AllocationLimit {
+  fn apply(limit: Option<usize>) -> Option<Self> {
+    let limit = limit?;
+    let previous = set_global_options(GLOBAL_OPTIONS.get().allocation_limit(limit));
+    Some(Self { previous })
+  }
 }
 
-pub async fn read_cover_image_from_buffer(buffer: Vec<u8>) -> Result<Option<Vec<u8>>, String> {
-  let tags = read_tags_from_buffer(buffer).await?;
-  match tags.image {
-    Some(image) => Ok(Some(image.data)),
-    None => Ok(None),
+impl Drop for AllocationLimit {
+  fn drop(&mut self) {
+    set_global_options(self.previous);
   }
 }
 
-pub async fn write_cover_image_to_buffer(
-  buffer: Vec<u8>,
-  image_data: Vec<u8>,
-) -> Result<Vec<u8>, String> {
-  let audio_tags = AudioTags {
-    image: Some(Image {
-      data: image_data,
-      pic_type: AudioImageType::CoverFront,
-      mime_type: None,
-      description: None,
-    }),
-    ..Default::default()
+// The tag read: the primary tag, the RIFF INFO tag of a WAV file without one,
+// or the lone ID3v1 tag with `id3v1_fallback`
+fn read_tag<'a>(tagged_file: &'a TaggedFile, options: &ReadTagsOptions) -> Option<&'a Tag> {
+  tagged_file
+    .primary_tag()
+    .or_else(|| match tagged_file.tags() {
+      [tag] if options.id3v1_fallback && tag.tag_type() == TagType::Id3v1 => Some(tag),
+      _ if tagged_file.file_type() == FileType::Wav => tagged_file.tag(TagType::RiffInfo),
+      _ => None,
+    })
+}
+
+// the tags `read_tag` picks, with the text of legacy taggers decoded
+fn audio_tags<R: Read + Seek>(
+  tagged_file: &TaggedFile,
+  reader: &mut R,
+  options: &ReadTagsOptions,
+) -> Result<AudioTags, String> {
+  let Some(tag) = read_tag(tagged_file, options) else {
+    return Ok(AudioTags::default());
   };
-  let buffer = write_tags_to_buffer(buffer, audio_tags)
-    .await
-    .map_err(|e| format!("Failed to write cover image to buffer: {}", e))?;
+  let Some(label) = options.legacy_encoding.as_deref() else {
+    return Ok(AudioTags::from_tag_with_options(tag, options));
+  };
+  let encoding = legacy_encoding::encoding(label)?;
+  let decoded = legacy_encoding::decode_tag(tag, reader, tagged_file.file_type(), encoding);
+  Ok(AudioTags::from_tag_with_options(&decoded, options))
+}
 
-  Ok(buffer)
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ReadTagsOutcome {
+  pub tags: AudioTags,
+  // the audio could not be parsed and the tags were recovered from what was intact
+  pub truncated: bool,
+  // the problems the read got past, with `report_warnings`
+  pub warnings: Option<Vec<String>>,
+  // the encoder delay and padding, with `gapless_info`
+  pub gapless_info: Option<GaplessInfo>,
 }
 
-pub async fn read_cover_image_from_file(file_path: String) -> Result<Option<Vec<u8>>, String> {
-  let path = Path::new(&file_path);
-  let buffer = fs::read(path).map_err(|e| format!("Failed to read file: {}", e))?;
-  read_cover_image_from_buffer(buffer).await
+// An MPEG-1 Layer III frame (128 kbps, 44.1 kHz) with an empty body, appended
+// to a lone ID3v2 tag so lofty has a stream to attach the tag to
+const PLACEHOLDER_MPEG_FRAME_HEADER: [u8; 4] = [0xFF, 0xFB, 0x90, 0x44];
+const PLACEHOLDER_MPEG_FRAME_LEN: usize = 417;
+
+// an MPEG stream led by `tag` (a whole ID3v2 tag, or nothing); the second
+// placeholder frame confirms the first, which lofty needs to read the audio
+// properties
+pub(crate) fn mpeg_stand_in(tag: &[u8]) -> Vec<u8> {
+  let mut stream = tag.to_vec();
+  for _ in 0..2 {
+    let frame_start = stream.len();
+    stream.extend(PLACEHOLDER_MPEG_FRAME_HEADER);
+    stream.resize(frame_start + PLACEHOLDER_MPEG_FRAME_LEN, 0);
+  }
+  stream
 }
 
-pub async fn write_cover_image_to_file(
-  file_path: String,
-  image_data: Vec<u8>,
-) -> Result<(), String> {
-  let path = Path::new(&file_path);
-  let buffer = fs::read(path).map_err(|e| format!("Failed to read file: {}", e))?;
-  let buffer = write_cover_image_to_buffer(buffer, image_data).await?;
-  fs::write(path, buffer).map_err(|e| format!("Failed to write file: {}", e))?;
-  Ok(())
+fn read_tags_relaxed(buffer: &[u8], options: &ReadTagsOptions) -> Option<AudioTags> {
+  let _limit = AllocationLimit::apply(options.max_allocation_bytes);
+  let parse_options = options
+    .parse_options()
+    .read_properties(false)
+    .parsing_mode(ParsingMode::Relaxed);
+  let mut cursor = Cursor::new(buffer);
+  let tagged_file = Probe::new(&mut cursor)
+    .options(parse_options)
+    .guess_file_type()
+    .ok()?
+    .read()
+    .ok()?;
+  audio_tags(&tagged_file, &mut cursor, options).ok()
 }
 
-#[cfg(test)]
-mod tests {
-  use super::*;
-  use lofty::{picture::MimeType, tag::TagType};
+/**
+ * Extract whatever tags are intact from a truncated file
+ * @param buffer - The (partial) file contents
+ * @param options - The read options
+ */
+fn recover_truncated_tags(buffer: &[u8], options: &ReadTagsOptions) -> Option<AudioTags> {
+  // without the audio properties lofty copes with most cut-off streams
+  if let Some(tags) = read_tags_relaxed(buffer, options) {
+    return Some(tags);
+  }
+  // the cut may leave too little audio to identify the stream, but a complete
+  // leading ID3v2 tag can still be read on its own
+  let tag_size = probe::id3v2_size(buffer)? as usize;
+  if tag_size > buffer.len() {
+    return None;
+  }
+  read_tags_relaxed(&mpeg_stand_in(&buffer[..tag_size]), options)
+}
 
-  // Helper function to create test image data
-  fn create_test_image_data() -> Vec<u8> {
-    // Minimal JPEG header
-    vec![
-      0xFF, 0xD8, 0xFF, 0xE0, // JPEG SOI + APP0
-      0x00, 0x10, 0x4A, 0x46, 0x49, 0x46, 0x00, 0x01, // JFIF header
-      0x01, 0x01, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0xFF, 0xD9, // JPEG EOI
-    ]
+pub async fn read_tags_with_options(
+  file_path: impl AsRef<Path>,
+  options: ReadTagsOptions,
+) -> Result<ReadTagsOutcome, String> {
+  let path = file_path.as_ref();
+  let mut file = open_file(path)?;
+  read_open_file(&mut file, &options)
+    .await
+    .map_err(in_file(path))
+}
+
+async fn read_open_file(
+  file: &mut File,
+  options: &ReadTagsOptions,
+) -> Result<ReadTagsOutcome, String> {
+  let mut outcome = match generic_read_tags(file, options).await {
+    Ok(tags) => ReadTagsOutcome {
+      tags,
+      ..Default::default()
+    },
+    Err(e) if options.tolerate_truncation => {
+      let mut buffer = Vec::new();
+      file
+        .rewind()
+        .and_then(|_| file.read_to_end(&mut buffer))
+        .map_err(|_| e.clone())?;
+      recover_truncated_tags(&buffer, options)
+        .map(|tags| ReadTagsOutcome {
+          tags,
+          truncated: true,
+          ..Default::default()
+        })
+        .ok_or(e)?
+    }
+    Err(e) => return Err(e),
+  };
+  if options.report_warnings {
+    outcome.warnings = Some(read_warnings::read_warnings(file));
+  }
+  if options.gapless_info {
+    outcome.gapless_info = gapless::read_gapless_info(file, &outcome.tags);
   }
+  Ok(outcome)
+}
 
-  // Helper function to load a file from base64 string
-  fn load_file_from_base64(base64_string: &str) -> std::result::Result<Vec<u8>, String> {
-    use base64::{engine::general_purpose, Engine as _};
+/**
+ * Read the tags of a file the caller already has open, e.g. under a lock, by
+ * its file descriptor. The descriptor stays open and keeps its position.
+ * @param fd - The file descriptor, open for reading
+ * @param options - The read options
+ */
+pub async fn read_tags_from_fd(
+  fd: i32,
+  options: ReadTagsOptions,
+) -> Result<ReadTagsOutcome, String> {
+  let mut file = file_from_fd(fd)?;
+  let position = file
+    .stream_position()
+    .map_err(|e| io_error("Failed to read file", &e))?;
+  let result = read_open_file(&mut file, &options).await;
+  file
+    .seek(SeekFrom::Start(position))
+    .map_err(|e| io_error("Failed to read file", &e))?;
+  result
+}
 
-    general_purpose::STANDARD
-      .decode(base64_string)
-      .map_err(|e| format!("Failed to decode base64: {}", e))
+pub async fn read_tags_from_buffer_with_options(
+  buffer: impl AsRef<[u8]>,
+  options: ReadTagsOptions,
+) -> Result<ReadTagsOutcome, String> {
+  let buffer = buffer.as_ref();
+  let mut cursor = Cursor::new(buffer);
+  let mut outcome = match generic_read_tags(&mut cursor, &options).await {
+    Ok(tags) => ReadTagsOutcome {
+      tags,
+      ..Default::default()
+    },
+    Err(e) if options.tolerate_truncation => recover_truncated_tags(buffer, &options)
+      .map(|tags| ReadTagsOutcome {
+        tags,
+        truncated: true,
+        ..Default::default()
+      })
+      .ok_or(e)?,
+    Err(e) => return Err(e),
+  };
+  if options.report_warnings {
+    outcome.warnings = Some(read_warnings::read_warnings(&mut cursor));
+  }
+  if options.gapless_info {
+    outcome.gapless_info = gapless::read_gapless_info(&mut cursor, &outcome.tags);
   }
+  Ok(outcome)
+}
 
-  // Helper function to create a Vec<u8> from base64 string
-  fn create_buffer_from_base64(base64_string: &str) -> std::result::Result<Vec<u8>, String> {
-    let data = load_file_from_base64(base64_string)?;
-    Ok(data)
+/**
+ * Read the tags of a stream, pulling only the bytes that hold them
+ * @param source - The stream
+ * @param options - The read options
+ */
+pub async fn read_tags_from_stream<S: ByteSource>(
+  source: &S,
+  options: ReadTagsOptions,
+) -> Result<AudioTags, String> {
+  let region = stream::read_tag_region(source).await?;
+  // the audio is cut off or left out, as in a truncated file
+  recover_truncated_tags(&region, &options).ok_or("Failed to read audio file".to_string())
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct AudioProperties {
+  pub duration_ms: u64,
+  // audio bitrate in kbps
+  pub bitrate: Option<u32>,
+  pub sample_rate: Option<u32>,
+  pub bit_depth: Option<u8>,
+  pub channels: Option<u8>,
+}
+
+async fn generic_read_properties<F>(file: &mut F) -> Result<AudioProperties, String>
+where
+  F: FileLike,
+  LoftyError: From<<F as Truncate>::Error>,
+  LoftyError: From<<F as Length>::Error>,
+{
+  if let Some(format) = dsd::format(file) {
+    return Err(format!(
+      "Unsupported format: {} files are supported for tags only",
+      format.name()
+    ));
   }
+  let tagged_file = probe_read(file, ParseOptions::new())?;
 
-  #[test]
-  fn test_audio_tags_default() {
-    let tags = AudioTags::default();
-    assert!(tags.title.is_none());
-    assert!(tags.artists.is_none());
-    assert!(tags.album.is_none());
-    assert!(tags.year.is_none());
-    assert!(tags.genre.is_none());
-    assert!(tags.track.is_none());
-    assert!(tags.album_artists.is_none());
-    assert!(tags.comment.is_none());
-    assert!(tags.disc.is_none());
-    assert!(tags.image.is_none());
+  Ok(AudioProperties::from_tagged_file(&tagged_file))
+}
+
+impl AudioProperties {
+  fn from_tagged_file(tagged_file: &TaggedFile) -> Self {
+    let properties = tagged_file.properties();
+    Self {
+      duration_ms: properties.duration().as_millis() as u64,
+      bitrate: properties.audio_bitrate(),
+      sample_rate: properties.sample_rate(),
+      bit_depth: properties.bit_depth(),
+      channels: properties.channels(),
+    }
   }
+}
 
-  #[test]
-  fn test_audio_tags_basic() {
-    let tags = AudioTags {
-      title: Some("Test Song".to_string()),
-      artists: Some(vec!["Test Artist".to_string()]),
-      album: Some("Test Album".to_string()),
-      year: Some(2024),
-      genre: Some("Test Genre".to_string()),
-      track: Some(Position {
-        no: Some(1),
-        of: Some(10),
-      }),
-      album_artists: Some(vec!["Test Album Artist".to_string()]),
-      comment: Some("Test comment".to_string()),
-      disc: Some(Position {
-        no: Some(1),
-        of: Some(2),
-      }),
-      image: None,
-      all_images: None,
-    };
+/**
+ * Read the audio properties of a file: duration, bitrate, sample rate, bit
+ * depth and channel count
+ * @param file_path - The path to the audio file
+ */
+pub async fn read_audio_properties(file_path: impl AsRef<Path>) -> Result<AudioProperties, String> {
+  let path = file_path.as_ref();
+  let mut file = open_file(path)?;
+  generic_read_properties(&mut file)
+    .await
+    .map_err(in_file(path))
+}
 
-    // Test that the struct is created correctly
-    assert_eq!(tags.title, Some("Test Song".to_string()));
-    assert_eq!(tags.artists, Some(vec!["Test Artist".to_string()]));
-    assert_eq!(tags.album, Some("Test Album".to_string()));
-    assert_eq!(tags.year, Some(2024));
-    assert_eq!(tags.genre, Some("Test Genre".to_string()));
-    assert_eq!(
-      tags.track,
-      Some(Position {
-        no: Some(1),
-        of: Some(10)
-      })
-    );
-    assert_eq!(
-      tags.album_artists,
-      Some(vec!["Test Album Artist".to_string()])
-    );
-    assert_eq!(tags.comment, Some("Test comment".to_string()));
-    assert_eq!(
-      tags.disc,
-      Some(Position {
-        no: Some(1),
-        of: Some(2)
-      })
-    );
-    assert!(tags.image.is_none());
+pub async fn read_audio_properties_from_buffer(buffer: Vec<u8>) -> Result<AudioProperties, String> {
+  let mut cursor = Cursor::new(buffer);
+  generic_read_properties(&mut cursor).await
+}
+
+/**
+ * Read the tags and the audio properties of a file, parsing it once
+ * @param file_path - The path to the audio file
+ */
+pub async fn read_tags_and_properties(
+  file_path: impl AsRef<Path>,
+) -> Result<(AudioTags, AudioProperties), String> {
+  let path = file_path.as_ref();
+  let mut file = open_file(path)?;
+  let tagged_file = probe_read(&mut file, ParseOptions::new()).map_err(in_file(path))?;
+  let tags = tagged_file
+    .primary_tag()
+    .map(|tag| AudioTags::from_tag_with_options(tag, &ReadTagsOptions::default()))
+    .unwrap_or_default();
+  Ok((tags, AudioProperties::from_tagged_file(&tagged_file)))
+}
+
+// How merge_tags settles a field that both sets of tags hold. A missing value,
+// an empty text or an empty list never replaces a value.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum MergeRule {
+  // the incoming value replaces the existing one
+  #[default]
+  PreferIncoming,
+  // the existing value is kept
+  PreferExisting,
+  // the existing value is kept, its missing parts taken from the incoming
+  // one, e.g. the total of a track number
+  FillEmptyOnly,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct MergeStrategy {
+  pub rule: MergeRule,
+  // the rules of some fields, instead of `rule`
+  pub overrides: Vec<(TagField, MergeRule)>,
+}
+
+impl MergeStrategy {
+  fn rule_for(&self, field: TagField) -> MergeRule {
+    self
+      .overrides
+      .iter()
+      .rev()
+      .find(|(overridden, _)| *overridden == field)
+      .map_or(self.rule, |(_, rule)| *rule)
   }
+}
 
-  #[test]
-  fn test_audio_tags_with_image() {
-    let image_data = create_test_image_data();
-    let tags = AudioTags {
-      title: Some("Test Song".to_string()),
-      artists: Some(vec!["Test Artist".to_string()]),
-      album: Some("Test Album".to_string()),
-      year: Some(2024),
-      genre: Some("Test Genre".to_string()),
-      track: Some(Position {
-        no: Some(1),
-        of: Some(10),
-      }),
-      album_artists: Some(vec!["Test Album Artist".to_string()]),
-      comment: Some("Test comment".to_string()),
-      disc: Some(Position {
-        no: Some(1),
-        of: Some(2),
-      }),
-      image: Some(Image {
-        data: image_data.clone(),
-        pic_type: AudioImageType::CoverFront,
-        mime_type: Some("image/jpeg".to_string()),
-        description: Some("Test cover".to_string()),
-      }),
-      all_images: None,
-    };
+// A value merge_tags can tell is missing, and complete from another value
+trait MergeValue: Sized {
+  fn is_blank(&self) -> bool;
 
-    // Test that the struct with image is created correctly
-    assert_eq!(tags.title, Some("Test Song".to_string()));
-    assert!(tags.image.is_some());
-    let image = tags.image.unwrap();
-    assert_eq!(image.mime_type, Some("image/jpeg".to_string()));
-    assert_eq!(image.description, Some("Test cover".to_string()));
-    // assert_eq!(image.data, image_data);
+  // the value with its missing parts taken from `other`
+  fn fill(self, _other: Self) -> Self {
+    self
   }
+}
 
-  #[test]
-  fn test_audio_tags_empty_artists() {
-    let tags = AudioTags {
-      title: Some("Test Song".to_string()),
-      artists: Some(vec![]), // Empty artists
-      album: Some("Test Album".to_string()),
-      year: Some(2024),
-      genre: Some("Test Genre".to_string()),
-      track: None,
-      album_artists: None,
-      comment: None,
-      disc: None,
-      image: None,
-      all_images: None,
-    };
+impl MergeValue for String {
+  fn is_blank(&self) -> bool {
+    self.trim().is_empty()
+  }
+}
 
-    // Test that empty artists vector is handled correctly
-    assert_eq!(tags.title, Some("Test Song".to_string()));
-    assert_eq!(tags.artists, Some(vec![]));
-    assert_eq!(tags.album, Some("Test Album".to_string()));
-    assert_eq!(tags.year, Some(2024));
-    assert_eq!(tags.genre, Some("Test Genre".to_string()));
+impl<T> MergeValue for Vec<T> {
+  fn is_blank(&self) -> bool {
+    self.is_empty()
   }
+}
 
-  #[test]
-  fn test_audio_tags_multiple_artists() {
-    let tags = AudioTags {
-      title: Some("Test Song".to_string()),
-      artists: Some(vec![
-        "Artist 1".to_string(),
-        "Artist 2".to_string(),
-        "Artist 3".to_string(),
-      ]),
-      album: Some("Test Album".to_string()),
-      year: Some(2024),
-      genre: Some("Test Genre".to_string()),
-      track: None,
-      album_artists: None,
-      comment: None,
-      disc: None,
-      image: None,
-      all_images: None,
-    };
+impl<V> MergeValue for BTreeMap<String, V> {
+  fn is_blank(&self) -> bool {
+    self.is_empty()
+  }
+}
 
-    // Test that multiple artists are handled correctly
-    assert_eq!(tags.title, Some("Test Song".to_string()));
-    assert_eq!(
-      tags.artists,
-      Some(vec![
-        "Artist 1".to_string(),
-        "Artist 2".to_string(),
-        "Artist 3".to_string()
-      ])
-    );
-    assert_eq!(tags.album, Some("Test Album".to_string()));
-    assert_eq!(tags.year, Some(2024));
-    assert_eq!(tags.genre, Some("Test Genre".to_string()));
+impl MergeValue for u8 {
+  fn is_blank(&self) -> bool {
+    false
   }
+}
 
-  #[test]
-  fn test_audio_tags_partial_data() {
-    let tags = AudioTags {
-      title: Some("Test Song".to_string()),
-      artists: None, // Not set
-      album: None,   // Not set
-      year: Some(2024),
-      genre: None, // Not set
-      track: Some(Position {
-        no: Some(1),
-        of: None,
-      }), // Only track number
-      album_artists: None,
-      comment: None,
-      disc: None,
-      image: None,
-      all_images: None,
-    };
+impl MergeValue for u32 {
+  fn is_blank(&self) -> bool {
+    false
+  }
+}
 
-    // Test that partial data is handled correctly
-    assert_eq!(tags.title, Some("Test Song".to_string()));
-    assert!(tags.artists.is_none());
-    assert!(tags.album.is_none());
-    assert_eq!(tags.year, Some(2024));
-    assert!(tags.genre.is_none());
-    assert_eq!(
-      tags.track,
-      Some(Position {
-        no: Some(1),
-        of: None
-      })
-    );
+impl MergeValue for f64 {
+  fn is_blank(&self) -> bool {
+    false
   }
+}
 
-  #[test]
-  fn test_position_struct() {
-    let pos = Position {
-      no: Some(1),
-      of: Some(10),
-    };
-    assert_eq!(pos.no, Some(1));
-    assert_eq!(pos.of, Some(10));
+impl MergeValue for bool {
+  fn is_blank(&self) -> bool {
+    false
+  }
+}
 
-    let pos_partial = Position {
-      no: Some(1),
-      of: None,
-    };
-    assert_eq!(pos_partial.no, Some(1));
-    assert_eq!(pos_partial.of, None);
+impl MergeValue for Image {
+  fn is_blank(&self) -> bool {
+    self.data.is_empty()
   }
+}
 
-  #[test]
-  fn test_image_struct() {
-    let image_data = create_test_image_data();
-    let image = Image {
-      data: image_data.clone(),
-      pic_type: AudioImageType::CoverFront,
-      mime_type: Some("image/jpeg".to_string()),
-      description: Some("Test image".to_string()),
-    };
+impl MergeValue for Position {
+  fn is_blank(&self) -> bool {
+    self.no.is_none() && self.of.is_none()
+  }
 
-    // assert_eq!(image.data, Vec<u8>::from(image_data));
-    assert_eq!(image.mime_type, Some("image/jpeg".to_string()));
-    assert_eq!(image.description, Some("Test image".to_string()));
+  fn fill(self, other: Self) -> Self {
+    Position {
+      no: self.no.or(other.no),
+      of: self.of.or(other.of),
+    }
+  }
+}
 
-    let image_minimal = Image {
-      data: image_data,
-      pic_type: AudioImageType::CoverFront,
-      mime_type: None,
-      description: None,
-    };
+impl MergeValue for Bwf {
+  fn is_blank(&self) -> bool {
+    *self == Bwf::default()
+  }
 
-    assert_eq!(image_minimal.mime_type, None);
-    assert_eq!(image_minimal.description, None);
+  fn fill(self, other: Self) -> Self {
+    Bwf {
+      description: self.description.or(other.description),
+      originator: self.originator.or(other.originator),
+      originator_reference: self.originator_reference.or(other.originator_reference),
+      origination_date: self.origination_date.or(other.origination_date),
+      origination_time: self.origination_time.or(other.origination_time),
+      time_reference: self.time_reference.or(other.time_reference),
+    }
   }
+}
 
-  #[test]
-  fn test_audio_tags_creation_variations() {
-    // Test with all fields
-    let full_tags = AudioTags {
-      title: Some("Full Song".to_string()),
-      artists: Some(vec!["Artist 1".to_string(), "Artist 2".to_string()]),
-      album: Some("Full Album".to_string()),
-      year: Some(2023),
-      genre: Some("Rock".to_string()),
-      track: Some(Position {
-        no: Some(5),
-        of: Some(12),
-      }),
-      album_artists: Some(vec!["Album Artist".to_string()]),
-      comment: Some("Great song".to_string()),
-      disc: Some(Position {
-        no: Some(1),
-        of: Some(2),
-      }),
-      image: Some(Image {
-        data: create_test_image_data(),
-        pic_type: AudioImageType::CoverFront,
-        mime_type: Some("image/png".to_string()),
-        description: Some("Album cover".to_string()),
-      }),
-      all_images: None,
-    };
+impl<T: MergeValue> MergeValue for Option<T> {
+  fn is_blank(&self) -> bool {
+    self.as_ref().is_none_or(T::is_blank)
+  }
 
-    assert_eq!(full_tags.title, Some("Full Song".to_string()));
-    assert_eq!(
-      full_tags.artists,
-      Some(vec!["Artist 1".to_string(), "Artist 2".to_string()])
-    );
-    assert_eq!(
-      full_tags.track,
-      Some(Position {
-        no: Some(5),
-        of: Some(12)
-      })
-    );
-    assert!(full_tags.image.is_some());
+  fn fill(self, other: Self) -> Self {
+    match (self, other) {
+      (Some(value), Some(other)) => Some(value.fill(other)),
+      (value, other) => value.or(other),
+    }
+  }
+}
 
-    // Test with minimal fields
-    let minimal_tags = AudioTags {
-      title: Some("Minimal Song".to_string()),
-      artists: None,
-      album: None,
-      year: None,
-      genre: None,
-      track: None,
-      album_artists: None,
-      comment: None,
-      disc: None,
-      image: None,
-      all_images: None,
-    };
+// fields written together, e.g. `genre` and `genres`, are merged as one
+impl<A: MergeValue, B: MergeValue> MergeValue for (A, B) {
+  fn is_blank(&self) -> bool {
+    self.0.is_blank() && self.1.is_blank()
+  }
 
-    assert_eq!(minimal_tags.title, Some("Minimal Song".to_string()));
-    assert!(minimal_tags.artists.is_none());
-    assert!(minimal_tags.album.is_none());
-    assert!(minimal_tags.year.is_none());
-    assert!(minimal_tags.image.is_none());
+  fn fill(self, other: Self) -> Self {
+    (self.0.fill(other.0), self.1.fill(other.1))
   }
+}
 
-  // Additional comprehensive tests for better coverage
+fn merge_value<T: MergeValue>(existing: T, incoming: T, rule: MergeRule) -> T {
+  let keep_existing = match rule {
+    MergeRule::PreferIncoming => incoming.is_blank(),
+    MergeRule::PreferExisting | MergeRule::FillEmptyOnly => !existing.is_blank(),
+  };
+  match rule {
+    _ if existing.is_blank() && incoming.is_blank() => existing,
+    MergeRule::FillEmptyOnly if keep_existing => existing.fill(incoming),
+    _ if keep_existing => existing,
+    _ => incoming,
+  }
+}
 
-  #[test]
-  fn test_position_struct_edge_cases() {
-    // Test with both values
-    let pos_full = Position {
-      no: Some(1),
-      of: Some(10),
-    };
-    assert_eq!(pos_full.no, Some(1));
-    assert_eq!(pos_full.of, Some(10));
+// fields by name, e.g. custom fields, are merged name by name
+fn merge_map<V: MergeValue>(
+  existing: Option<BTreeMap<String, V>>,
+  incoming: Option<BTreeMap<String, V>>,
+  rule: MergeRule,
+) -> Option<BTreeMap<String, V>> {
+  match (existing, incoming) {
+    (Some(mut merged), Some(incoming)) => {
+      for (name, value) in incoming {
+        let value = match merged.remove(&name) {
+          Some(existing) => merge_value(existing, value, rule),
+          None => value,
+        };
+        merged.insert(name, value);
+      }
+      Some(merged)
+    }
+    (existing, incoming) => merge_value(existing, incoming, rule),
+  }
+}
 
-    // Test with only no
-    let pos_no_only = Position {
-      no: Some(5),
-      of: None,
-    };
-    assert_eq!(pos_no_only.no, Some(5));
-    assert_eq!(pos_no_only.of, None);
+/**
+ * Merge two sets of tags field by field, e.g. the tags of a file with those
+ * found online. The fields without a TagField (custom fields, BWF, MP4
+ * freeform atoms and binary objects) follow `strategy.rule`; fields by name are
+ * merged name by name.
+ * @param base - The tags already there
+ * @param incoming - The tags merged into them
+ * @param strategy - Which value a field keeps when both hold one
+ */
+pub fn merge_tags(base: AudioTags, incoming: AudioTags, strategy: &MergeStrategy) -> AudioTags {
+  let rule = |field: TagField| strategy.rule_for(field);
+  let (genre, genres) = merge_value(
+    (base.genre, base.genres),
+    (incoming.genre, incoming.genres),
+    rule(TagField::Genre),
+  );
+  let (comment, comments) = merge_value(
+    (base.comment, base.comments),
+    (incoming.comment, incoming.comments),
+    rule(TagField::Comment),
+  );
+  let replay_gain = rule(TagField::ReplayGain);
+  AudioTags {
+    title: merge_value(base.title, incoming.title, rule(TagField::Title)),
+    subtitle: merge_value(base.subtitle, incoming.subtitle, rule(TagField::Subtitle)),
+    artists: merge_value(base.artists, incoming.artists, rule(TagField::Artists)),
+    album: merge_value(base.album, incoming.album, rule(TagField::Album)),
+    year: merge_value(base.year, incoming.year, rule(TagField::Year)),
+    genre,
+    genres,
+    track: merge_value(base.track, incoming.track, rule(TagField::Track)),
+    album_artists: merge_value(
+      base.album_artists,
+      incoming.album_artists,
+      rule(TagField::AlbumArtists),
+    ),
+    comment,
+    comments,
+    disc: merge_value(base.disc, incoming.disc, rule(TagField::Disc)),
+    disc_subtitle: merge_value(
+      base.disc_subtitle,
+      incoming.disc_subtitle,
+      rule(TagField::DiscSubtitle),
+    ),
+    image: merge_value(base.image, incoming.image, rule(TagField::Image)),
+    all_images: merge_value(
+      base.all_images,
+      incoming.all_images,
+      rule(TagField::AllImages),
+    ),
+    composer: merge_value(base.composer, incoming.composer, rule(TagField::Composer)),
+    lyricist: merge_value(base.lyricist, incoming.lyricist, rule(TagField::Lyricist)),
+    conductor: merge_value(
+      base.conductor,
+      incoming.conductor,
+      rule(TagField::Conductor),
+    ),
+    remixer: merge_value(base.remixer, incoming.remixer, rule(TagField::Remixer)),
+    publisher: merge_value(
+      base.publisher,
+      incoming.publisher,
+      rule(TagField::Publisher),
+    ),
+    copyright: merge_value(
+      base.copyright,
+      incoming.copyright,
+      rule(TagField::Copyright),
+    ),
+    lyrics: merge_value(base.lyrics, incoming.lyrics, rule(TagField::Lyrics)),
+    isrc: merge_value(base.isrc, incoming.isrc, rule(TagField::Isrc)),
+    barcode: merge_value(base.barcode, incoming.barcode, rule(TagField::Barcode)),
+    catalog_number: merge_value(
+      base.catalog_number,
+      incoming.catalog_number,
+      rule(TagField::CatalogNumber),
+    ),
+    musicbrainz_recording_id: merge_value(
+      base.musicbrainz_recording_id,
+      incoming.musicbrainz_recording_id,
+      rule(TagField::MusicBrainzRecordingId),
+    ),
+    musicbrainz_track_id: merge_value(
+      base.musicbrainz_track_id,
+      incoming.musicbrainz_track_id,
+      rule(TagField::MusicBrainzTrackId),
+    ),
+    musicbrainz_release_id: merge_value(
+      base.musicbrainz_release_id,
+      incoming.musicbrainz_release_id,
+      rule(TagField::MusicBrainzReleaseId),
+    ),
+    musicbrainz_release_group_id: merge_value(
+      base.musicbrainz_release_group_id,
+      incoming.musicbrainz_release_group_id,
+      rule(TagField::MusicBrainzReleaseGroupId),
+    ),
+    musicbrainz_artist_id: merge_value(
+      base.musicbrainz_artist_id,
+      incoming.musicbrainz_artist_id,
+      rule(TagField::MusicBrainzArtistId),
+    ),
+    musicbrainz_album_artist_id: merge_value(
+      base.musicbrainz_album_artist_id,
+      incoming.musicbrainz_album_artist_id,
+      rule(TagField::MusicBrainzAlbumArtistId),
+    ),
+    replay_gain_track_gain: merge_value(
+      base.replay_gain_track_gain,
+      incoming.replay_gain_track_gain,
+      replay_gain,
+    ),
+    replay_gain_track_peak: merge_value(
+      base.replay_gain_track_peak,
+      incoming.replay_gain_track_peak,
+      replay_gain,
+    ),
+    replay_gain_album_gain: merge_value(
+      base.replay_gain_album_gain,
+      incoming.replay_gain_album_gain,
+      replay_gain,
+    ),
+    replay_gain_album_peak: merge_value(
+      base.replay_gain_album_peak,
+      incoming.replay_gain_album_peak,
+      replay_gain,
+    ),
+    bpm: merge_value(base.bpm, incoming.bpm, rule(TagField::Bpm)),
+    initial_key: merge_value(
+      base.initial_key,
+      incoming.initial_key,
+      rule(TagField::InitialKey),
+    ),
+    mood: merge_value(base.mood, incoming.mood, rule(TagField::Mood)),
+    rating: merge_value(base.rating, incoming.rating, rule(TagField::Rating)),
+    play_count: merge_value(
+      base.play_count,
+      incoming.play_count,
+      rule(TagField::PlayCount),
+    ),
+    compilation: merge_value(
+      base.compilation,
+      incoming.compilation,
+      rule(TagField::Compilation),
+    ),
+    grouping: merge_value(base.grouping, incoming.grouping, rule(TagField::Grouping)),
+    work: merge_value(base.work, incoming.work, rule(TagField::Work)),
+    movement: merge_value(base.movement, incoming.movement, rule(TagField::Movement)),
+    movement_number: merge_value(
+      base.movement_number,
+      incoming.movement_number,
+      rule(TagField::MovementNumber),
+    ),
+    title_sort: merge_value(
+      base.title_sort,
+      incoming.title_sort,
+      rule(TagField::TitleSort),
+    ),
+    album_sort: merge_value(
+      base.album_sort,
+      incoming.album_sort,
+      rule(TagField::AlbumSort),
+    ),
+    artist_sort: merge_value(
+      base.artist_sort,
+      incoming.artist_sort,
+      rule(TagField::ArtistSort),
+    ),
+    album_artist_sort: merge_value(
+      base.album_artist_sort,
+      incoming.album_artist_sort,
+      rule(TagField::AlbumArtistSort),
+    ),
+    composer_sort: merge_value(
+      base.composer_sort,
+      incoming.composer_sort,
+      rule(TagField::ComposerSort),
+    ),
+    encoder_software: merge_value(
+      base.encoder_software,
+      incoming.encoder_software,
+      rule(TagField::EncoderSoftware),
+    ),
+    encoder_settings: merge_value(
+      base.encoder_settings,
+      incoming.encoder_settings,
+      rule(TagField::EncoderSettings),
+    ),
+    custom_fields: merge_map(base.custom_fields, incoming.custom_fields, strategy.rule),
+    bwf: merge_value(base.bwf, incoming.bwf, strategy.rule),
+    mp4_freeform: merge_map(base.mp4_freeform, incoming.mp4_freeform, strategy.rule),
+    binary_objects: merge_value(base.binary_objects, incoming.binary_objects, strategy.rule),
+  }
+}
 
-    // Test with only of
-    let pos_of_only = Position {
-      no: None,
-      of: Some(15),
-    };
-    assert_eq!(pos_of_only.no, None);
-    assert_eq!(pos_of_only.of, Some(15));
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct TagEntry {
+  // the format of the tag, e.g. "Id3v2", "Id3v1" or "Ape"
+  pub tag_type: String,
+  pub tags: AudioTags,
+}
 
-    // Test with neither
-    let pos_empty = Position { no: None, of: None };
-    assert_eq!(pos_empty.no, None);
-    assert_eq!(pos_empty.of, None);
+async fn generic_read_all_tags<F>(file: &mut F) -> Result<Vec<TagEntry>, String>
+where
+  F: FileLike,
+  LoftyError: From<<F as Truncate>::Error>,
+  LoftyError: From<<F as Length>::Error>,
+{
+  let tagged_file = read_tagged_file(file, ParseOptions::new())?;
 
-    // Test with zero values
-    let pos_zero = Position {
-      no: Some(0),
-      of: Some(0),
-    };
-    assert_eq!(pos_zero.no, Some(0));
-    assert_eq!(pos_zero.of, Some(0));
+  Ok(
+    tagged_file
+      .tags()
+      .iter()
+      .map(|tag| TagEntry {
+        tag_type: format!("{:?}", tag.tag_type()),
+        tags: AudioTags::from_tag(tag),
+      })
+      .collect(),
+  )
+}
 
-    // Test with large values
-    let pos_large = Position {
-      no: Some(999),
-      of: Some(1000),
-    };
-    assert_eq!(pos_large.no, Some(999));
-    assert_eq!(pos_large.of, Some(1000));
-  }
+/**
+ * Read every tag of a file rather than only the primary one, e.g. the ID3v2,
+ * ID3v1 and APE tags of an MP3
+ * @param file_path - The path to the audio file
+ * @returns One entry per tag, empty when the file has none
+ */
+pub async fn read_all_tags(file_path: impl AsRef<Path>) -> Result<Vec<TagEntry>, String> {
+  let path = file_path.as_ref();
+  let mut file = open_file(path)?;
+  generic_read_all_tags(&mut file)
+    .await
+    .map_err(in_file(path))
+}
 
-  #[test]
-  fn test_image_struct_edge_cases() {
-    let image_data = create_test_image_data();
+pub async fn read_all_tags_from_buffer(buffer: Vec<u8>) -> Result<Vec<TagEntry>, String> {
+  let mut cursor = Cursor::new(buffer);
+  generic_read_all_tags(&mut cursor).await
+}
 
-    // Test with all fields
-    let image_full = Image {
-      data: image_data.clone(),
-      pic_type: AudioImageType::CoverFront,
-      mime_type: Some("image/jpeg".to_string()),
-      description: Some("Full description".to_string()),
-    };
-    // assert_eq!(image_full.data, image_data);
-    assert_eq!(image_full.mime_type, Some("image/jpeg".to_string()));
-    assert_eq!(image_full.description, Some("Full description".to_string()));
+async fn generic_read_raw_tag_items<F>(file: &mut F) -> Result<Vec<RawTagItem>, String>
+where
+  F: FileLike,
+  LoftyError: From<<F as Truncate>::Error>,
+  LoftyError: From<<F as Length>::Error>,
+{
+  let tagged_file = read_tagged_file(file, ParseOptions::new())?;
+  Ok(
+    tagged_file
+      .tags()
+      .iter()
+      .flat_map(raw_items::raw_items)
+      .collect(),
+  )
+}
 
-    // Test with no optional fields
-    let image_minimal = Image {
-      data: image_data.clone(),
-      pic_type: AudioImageType::CoverFront,
-      mime_type: None,
-      description: None,
-    };
-    // assert_eq!(image_minimal.data, image_data);
-    assert_eq!(image_minimal.mime_type, None);
-    assert_eq!(image_minimal.description, None);
+/**
+ * Read every item of every tag of a file as lofty holds it, unknown keys
+ * included, e.g. to see which frame a player reads the wrong artist from
+ * @param file_path - The path to the audio file
+ * @returns The items, tag by tag
+ */
+pub async fn read_raw_tag_items(file_path: impl AsRef<Path>) -> Result<Vec<RawTagItem>, String> {
+  let path = file_path.as_ref();
+  let mut file = open_file(path)?;
+  generic_read_raw_tag_items(&mut file)
+    .await
+    .map_err(in_file(path))
+}
 
-    // Test with only mime_type
-    let image_mime_only = Image {
-      data: image_data.clone(),
-      pic_type: AudioImageType::CoverFront,
-      mime_type: Some("image/png".to_string()),
-      description: None,
-    };
-    assert_eq!(image_mime_only.mime_type, Some("image/png".to_string()));
-    assert_eq!(image_mime_only.description, None);
+pub async fn read_raw_tag_items_from_buffer(buffer: Vec<u8>) -> Result<Vec<RawTagItem>, String> {
+  let mut cursor = Cursor::new(buffer);
+  generic_read_raw_tag_items(&mut cursor).await
+}
 
-    // Test with only description
-    let image_desc_only = Image {
-      data: image_data.clone(),
-      pic_type: AudioImageType::CoverFront,
-      mime_type: None,
-      description: Some("Description only".to_string()),
-    };
-    assert_eq!(image_desc_only.mime_type, None);
-    assert_eq!(
-      image_desc_only.description,
-      Some("Description only".to_string())
-    );
+// Whether both tags read back as the same AudioTags, pictures in the same order.
+// Items are compared by the values they read as rather than by layout, so e.g.
+// a year stored as Year on one side and RecordingDate on the other still matches
+fn same_tag_contents(a: &Tag, b: &Tag) -> bool {
+  let options = ReadTagsOptions {
+    preserve_picture_order: true,
+    ..Default::default()
+  };
+  AudioTags::from_tag_with_options(a, &options) == AudioTags::from_tag_with_options(b, &options)
+}
 
-    // Test with empty data
-    let image_empty = Image {
-      data: vec![],
-      pic_type: AudioImageType::CoverFront,
-      mime_type: Some("image/jpeg".to_string()),
-      description: Some("Empty data".to_string()),
-    };
-    // assert_eq!(image_empty.data, vec![]);
-    assert_eq!(image_empty.mime_type, Some("image/jpeg".to_string()));
-    assert_eq!(image_empty.description, Some("Empty data".to_string()));
+// The tag of `tag_type`, or the primary tag when None; created when the file
+// doesn't have it yet
+fn target_tag(tagged_file: &mut TaggedFile, tag_type: Option<TagType>) -> Result<&mut Tag, String> {
+  let tag_type = tag_type.unwrap_or(tagged_file.primary_tag_type());
+  if !tagged_file.supports_tag_type(tag_type) {
+    return Err(format!(
+      "{:?} files don't support {:?} tags",
+      tagged_file.file_type(),
+      tag_type
+    ));
+  }
+  if tagged_file.tag(tag_type).is_none() {
+    tagged_file.insert_tag(Tag::new(tag_type));
+  }
+  tagged_file
+    .tag_mut(tag_type)
+    .ok_or("Failed to get tag after been added".to_string())
+}
 
-    // Test with empty strings
-    let image_empty_strings = Image {
-      data: image_data,
-      pic_type: AudioImageType::CoverFront,
-      mime_type: Some("".to_string()),
-      description: Some("".to_string()),
-    };
-    assert_eq!(image_empty_strings.mime_type, Some("".to_string()));
-    assert_eq!(image_empty_strings.description, Some("".to_string()));
+/**
+ * Apply `update` to the primary tag of `file`, or to the tag of
+ * `options.tag_type`, and save the result back to `file` in place
+ * @param options - How the tag is saved, the per-call parts are up to `update`
+ * @returns Whether the file was written
+ */
+async fn generic_update_tag<F, U>(
+  mut file: F,
+  options: &WriteTagsOptions,
+  update: U,
+) -> Result<bool, String>
+where
+  F: FileLike,
+  LoftyError: From<<F as Truncate>::Error>,
+  LoftyError: From<<F as Length>::Error>,
+  U: FnOnce(&mut Tag),
+{
+  let Some(format) = dsd::format(&mut file) else {
+    return update_lofty_tag(file, options, update).await;
+  };
+  // the ID3v2 tag of a DSD file is updated on a stand-in MPEG stream and
+  // copied back
+  if let Some(tag_type) = options.tag_type.filter(|t| *t != TagType::Id3v2) {
+    return Err(format!(
+      "{} files don't support {:?} tags",
+      format.name(),
+      tag_type
+    ));
+  }
+  let tag = dsd::read_id3v2(&mut file, format)?.unwrap_or_default();
+  let mut stand_in = Cursor::new(mpeg_stand_in(&tag));
+  if !update_lofty_tag(&mut stand_in, options, update).await? {
+    return Ok(false);
   }
+  let stand_in = stand_in.into_inner();
+  let tag_size = probe::id3v2_size(&stand_in).unwrap_or(0) as usize;
+  dsd::write_id3v2(&mut file, format, &stand_in[..tag_size])?;
+  Ok(true)
+}
 
-  #[test]
-  fn test_audio_tags_string_edge_cases() {
-    // Test with empty strings
-    let tags_empty_strings = AudioTags {
-      title: Some("".to_string()),
-      artists: Some(vec!["".to_string()]),
-      album: Some("".to_string()),
-      year: Some(2024),
-      genre: Some("".to_string()),
-      track: None,
-      album_artists: Some(vec!["".to_string()]),
-      comment: Some("".to_string()),
-      disc: None,
-      image: None,
-      all_images: None,
-    };
+// generic_update_tag for the files lofty reads
+async fn update_lofty_tag<F, U>(
+  mut file: F,
+  options: &WriteTagsOptions,
+  update: U,
+) -> Result<bool, String>
+where
+  F: FileLike,
+  LoftyError: From<<F as Truncate>::Error>,
+  LoftyError: From<<F as Length>::Error>,
+  U: FnOnce(&mut Tag),
+{
+  let mut tagged_file = probe_read(&mut file, ParseOptions::new())?;
 
-    assert_eq!(tags_empty_strings.title, Some("".to_string()));
-    assert_eq!(tags_empty_strings.artists, Some(vec!["".to_string()]));
-    assert_eq!(tags_empty_strings.album, Some("".to_string()));
-    assert_eq!(tags_empty_strings.genre, Some("".to_string()));
-    assert_eq!(tags_empty_strings.album_artists, Some(vec!["".to_string()]));
-    assert_eq!(tags_empty_strings.comment, Some("".to_string()));
+  let supports_id3v1 = tagged_file.supports_tag_type(TagType::Id3v1);
+  // the RIFF INFO chunk of a WAV file follows the tag written to it
+  let mut info = tagged_file
+    .tag(TagType::RiffInfo)
+    .filter(|_| options.tag_type != Some(TagType::RiffInfo) && !options.remove_others)
+    .cloned();
+  let primary_tag = target_tag(&mut tagged_file, options.tag_type)?;
+  let tag_type = primary_tag.tag_type();
 
-    // Test with very long strings
-    let long_string = "a".repeat(1000);
-    let tags_long_strings = AudioTags {
-      title: Some(long_string.clone()),
-      artists: Some(vec![long_string.clone()]),
-      album: Some(long_string.clone()),
-      year: Some(2024),
-      genre: Some(long_string.clone()),
-      track: None,
-      album_artists: Some(vec![long_string.clone()]),
-      comment: Some(long_string.clone()),
-      disc: None,
-      image: None,
-      all_images: None,
-    };
+  let before = options.skip_unchanged.then(|| primary_tag.clone());
 
-    assert_eq!(tags_long_strings.title, Some(long_string.clone()));
-    assert_eq!(tags_long_strings.artists, Some(vec![long_string.clone()]));
-    assert_eq!(tags_long_strings.album, Some(long_string.clone()));
-    assert_eq!(tags_long_strings.genre, Some(long_string.clone()));
-    assert_eq!(
-      tags_long_strings.album_artists,
-      Some(vec![long_string.clone()])
-    );
-    assert_eq!(tags_long_strings.comment, Some(long_string));
+  // Update the tag with new values
+  update(primary_tag);
 
-    // Test with special characters
-    let special_chars = "!@#$%^&*()_+-=[]{}|;':\",./<>?`~";
-    let tags_special = AudioTags {
-      title: Some(special_chars.to_string()),
-      artists: Some(vec![special_chars.to_string()]),
-      album: Some(special_chars.to_string()),
-      year: Some(2024),
-      genre: Some(special_chars.to_string()),
-      track: None,
-      album_artists: Some(vec![special_chars.to_string()]),
-      comment: Some(special_chars.to_string()),
-      disc: None,
-      image: None,
-      all_images: None,
-    };
+  if before.is_some_and(|before| same_tag_contents(&before, primary_tag)) {
+    return Ok(false);
+  }
 
-    assert_eq!(tags_special.title, Some(special_chars.to_string()));
-    assert_eq!(tags_special.artists, Some(vec![special_chars.to_string()]));
-    assert_eq!(tags_special.album, Some(special_chars.to_string()));
-    assert_eq!(tags_special.genre, Some(special_chars.to_string()));
-    assert_eq!(
-      tags_special.album_artists,
-      Some(vec![special_chars.to_string()])
-    );
-    assert_eq!(tags_special.comment, Some(special_chars.to_string()));
+  placement::apply_artwork_policy(primary_tag, &options.artwork_policy)?;
+  let id3v1 = (options.id3v1 && supports_id3v1 && tag_type != TagType::Id3v1)
+    .then(|| id3v1::id3v1_tag(&AudioTags::from_tag(primary_tag)));
+  if let Some(info) = info.as_mut() {
+    riff::set_info(info, &AudioTags::from_tag(primary_tag));
+  }
 
-    // Test with unicode characters
-    let unicode_string = "🎵 音乐 🎶 音楽 🎼";
-    let tags_unicode = AudioTags {
-      title: Some(unicode_string.to_string()),
-      artists: Some(vec![unicode_string.to_string()]),
-      album: Some(unicode_string.to_string()),
-      year: Some(2024),
-      genre: Some(unicode_string.to_string()),
-      track: None,
-      album_artists: Some(vec![unicode_string.to_string()]),
-      comment: Some(unicode_string.to_string()),
-      disc: None,
-      image: None,
-      all_images: None,
-    };
+  // lofty has read what it needs, the tag is now written over the same data
+  file
+    .rewind()
+    .map_err(|e| io_error("Failed to write audio to buffer", &e))?;
+  let write_options = lofty_write_options(options);
+  if !placement::save_id3v2_with_policy(
+    primary_tag,
+    &options.artwork_policy,
+    &mut file,
+    write_options,
+    options.id3v2_version == Id3v2Version::V3,
+    options.id3_text_encoding,
+  )? {
+    // Write the updated tag back to the file
+    tagged_file
+      .save_to(&mut file, write_options)
+      .map_err(|e| lofty_error("Failed to write audio to buffer", &e))?;
+  }
 
-    assert_eq!(tags_unicode.title, Some(unicode_string.to_string()));
-    assert_eq!(tags_unicode.artists, Some(vec![unicode_string.to_string()]));
-    assert_eq!(tags_unicode.album, Some(unicode_string.to_string()));
-    assert_eq!(tags_unicode.genre, Some(unicode_string.to_string()));
-    assert_eq!(
-      tags_unicode.album_artists,
-      Some(vec![unicode_string.to_string()])
-    );
-    assert_eq!(tags_unicode.comment, Some(unicode_string.to_string()));
+  // lofty doesn't act on its own remove_others option
+  if options.remove_others {
+    let others: Vec<TagType> = ALL_TAG_TYPES
+      .into_iter()
+      .filter(|other| *other != tag_type)
+      .collect();
+    generic_strip_tag_types(&mut file, &others).await?;
   }
 
-  #[test]
-  fn test_audio_tags_year_edge_cases() {
-    // Test with various years
-    let years = vec![1900, 1950, 2000, 2024, 2030, 9999];
+  if let Some(info) = info {
+    file
+      .rewind()
+      .map_err(|e| io_error("Failed to write audio to buffer", &e))?;
+    info
+      .save_to(&mut file, write_options)
+      .map_err(|e| lofty_error("Failed to write audio to buffer", &e))?;
+  }
 
-    for year in years {
-      let tags = AudioTags {
-        title: Some("Test Song".to_string()),
-        artists: None,
-        album: None,
-        year: Some(year),
-        genre: None,
-        track: None,
-        album_artists: None,
-        comment: None,
-        disc: None,
-        image: None,
-        all_images: None,
-      };
-      assert_eq!(tags.year, Some(year));
-    }
+  if let Some(id3v1) = id3v1 {
+    file
+      .rewind()
+      .map_err(|e| io_error("Failed to write audio to buffer", &e))?;
+    id3v1
+      .save_to(&mut file, write_options)
+      .map_err(|e| lofty_error("Failed to write audio to buffer", &e))?;
+  }
 
-    // Test with year 0 (edge case)
-    let tags_year_zero = AudioTags {
-      title: Some("Test Song".to_string()),
-      artists: None,
-      album: None,
-      year: Some(0),
-      genre: None,
-      track: None,
-      album_artists: None,
-      comment: None,
-      disc: None,
-      image: None,
-      all_images: None,
-    };
-    assert_eq!(tags_year_zero.year, Some(0));
+  Ok(true)
+}
+
+pub(crate) const ALL_TAG_TYPES: [TagType; 7] = [
+  TagType::Ape,
+  TagType::Id3v1,
+  TagType::Id3v2,
+  TagType::Mp4Ilst,
+  TagType::VorbisComments,
+  TagType::RiffInfo,
+  TagType::AiffText,
+];
+
+// the lofty options a tag is saved with
+fn lofty_write_options(options: &WriteTagsOptions) -> WriteOptions {
+  let padding = match options.padding {
+    Some(padding) => padding,
+    None if options.deterministic => DETERMINISTIC_PADDING,
+    None => WriteOptions::DEFAULT_PREFERRED_PADDING,
+  };
+  WriteOptions::default()
+    .preferred_padding(padding)
+    .uppercase_id3v2_chunk(!options.lowercase_id3v2_chunk)
+    .use_id3v23(options.id3v2_version == Id3v2Version::V3)
+}
+
+// Padding written in deterministic mode, so the size of the output depends on
+// the tags only and not on the padding of the input
+const DETERMINISTIC_PADDING: u32 = 0;
+
+// Order the items by key so the frames are written in the same order whatever
+// their order in the input; items sharing a key keep their relative order
+fn sort_tag_items(primary_tag: &mut Tag) {
+  let mut items: Vec<TagItem> = primary_tag.items().cloned().collect();
+  items.sort_by_cached_key(|item| format!("{:?}", item.key()));
+  primary_tag.retain(|_| false);
+  for item in items {
+    primary_tag.push_unchecked(item);
   }
+}
 
-  #[test]
-  fn test_audio_tags_artists_edge_cases() {
-    // Test with single artist
-    let tags_single = AudioTags {
-      title: Some("Test Song".to_string()),
-      artists: Some(vec!["Single Artist".to_string()]),
-      album: None,
-      year: None,
-      genre: None,
-      track: None,
-      album_artists: None,
-      comment: None,
-      disc: None,
-      image: None,
-      all_images: None,
-    };
-    assert_eq!(tags_single.artists, Some(vec!["Single Artist".to_string()]));
+// the keys a write of `tags` replaces or removes
+fn written_keys(tags: &AudioTags, primary_tag: &Tag) -> Vec<ItemKey> {
+  let mut keys: Vec<ItemKey> = TagField::ALL
+    .into_iter()
+    .filter(|field| field.is_set(tags))
+    .flat_map(|field| field.item_keys(primary_tag.tag_type()).iter().cloned())
+    .collect();
+  for name in tags.custom_fields.iter().flat_map(|fields| fields.keys()) {
+    keys.push(custom_fields::item_key(name, primary_tag.tag_type()));
+  }
+  keys
+}
 
-    // Test with many artists
-    let many_artists: Vec<String> = (1..=50).map(|i| format!("Artist {}", i)).collect();
-    let tags_many = AudioTags {
-      title: Some("Test Song".to_string()),
-      artists: Some(many_artists.clone()),
-      album: None,
-      year: None,
-      genre: None,
-      track: None,
-      album_artists: None,
-      comment: None,
-      disc: None,
-      image: None,
-      all_images: None,
-    };
-    assert_eq!(tags_many.artists, Some(many_artists));
+// Put back the items of `before` that went missing, unless the write was
+// meant to replace them
+fn restore_items(primary_tag: &mut Tag, before: Vec<TagItem>, written_keys: &[ItemKey]) {
+  for item in before {
+    if written_keys.contains(item.key()) || primary_tag.items().any(|kept| *kept == item) {
+      continue;
+    }
+    primary_tag.push_unchecked(item);
+  }
+}
 
-    // Test with duplicate artists
-    let tags_duplicates = AudioTags {
-      title: Some("Test Song".to_string()),
-      artists: Some(vec![
-        "Same Artist".to_string(),
-        "Same Artist".to_string(),
-        "Same Artist".to_string(),
-      ]),
-      album: None,
-      year: None,
-      genre: None,
-      track: None,
-      album_artists: None,
-      comment: None,
-      disc: None,
-      image: None,
-      all_images: None,
-    };
-    assert_eq!(
-      tags_duplicates.artists,
-      Some(vec![
-        "Same Artist".to_string(),
-        "Same Artist".to_string(),
-        "Same Artist".to_string(),
-      ])
-    );
+// the warnings to report for the identifiers about to be written
+fn check_identifiers(tags: &AudioTags, options: &WriteTagsOptions) -> Result<Vec<String>, String> {
+  match options.identifier_validation {
+    IdentifierValidation::PassThrough => Ok(Vec::new()),
+    IdentifierValidation::Reject => {
+      let problems = identifiers::validate_identifiers(tags);
+      if !problems.is_empty() {
+        return Err(problems.join("; "));
+      }
+      Ok(problems)
+    }
+    IdentifierValidation::Warn => Ok(identifiers::validate_identifiers(tags)),
   }
+}
 
-  #[test]
-  fn test_audio_tags_track_disc_edge_cases() {
-    // Test track with zero values
-    let tags_track_zero = AudioTags {
-      title: Some("Test Song".to_string()),
-      artists: None,
-      album: None,
-      year: None,
-      genre: None,
-      track: Some(Position {
-        no: Some(0),
-        of: Some(0),
-      }),
-      album_artists: None,
-      comment: None,
-      disc: Some(Position {
-        no: Some(0),
-        of: Some(0),
-      }),
-      image: None,
-      all_images: None,
-    };
-    assert_eq!(
-      tags_track_zero.track,
-      Some(Position {
-        no: Some(0),
-        of: Some(0)
-      })
-    );
-    assert_eq!(
-      tags_track_zero.disc,
-      Some(Position {
-        no: Some(0),
-        of: Some(0)
-      })
-    );
+// the type of a file from its first bytes, the file rewound afterwards
+fn guess_file_type<R: Read + Seek>(reader: &mut R) -> Option<FileType> {
+  reader.rewind().ok()?;
+  let file_type = Probe::new(&mut *reader).guess_file_type().ok()?.file_type();
+  reader.rewind().ok()?;
+  file_type
+}
 
-    // Test track with large values
-    let tags_track_large = AudioTags {
-      title: Some("Test Song".to_string()),
-      artists: None,
-      album: None,
-      year: None,
-      genre: None,
-      track: Some(Position {
-        no: Some(999),
-        of: Some(1000),
-      }),
-      album_artists: None,
-      comment: None,
-      disc: Some(Position {
-        no: Some(99),
-        of: Some(100),
-      }),
-      image: None,
-      all_images: None,
-    };
-    assert_eq!(
-      tags_track_large.track,
-      Some(Position {
-        no: Some(999),
-        of: Some(1000)
-      })
-    );
-    assert_eq!(
-      tags_track_large.disc,
-      Some(Position {
-        no: Some(99),
-        of: Some(100)
-      })
-    );
+// rewrite the bext chunk of a WAV file, returns whether it changed
+fn write_bwf<F: FileLike>(file: &mut F, bwf: &Bwf) -> Result<bool, String> {
+  let write_error = |e: std::io::Error| io_error("Failed to write audio to buffer", &e);
+  let mut data = Vec::new();
+  file.rewind().map_err(write_error)?;
+  file.read_to_end(&mut data).map_err(write_error)?;
+  let written = riff::write_bwf(data.clone(), bwf)?;
+  if written == data {
+    return Ok(false);
+  }
+  file.rewind().map_err(write_error)?;
+  file.write_all(&written).map_err(write_error)?;
+  file
+    .truncate(written.len() as u64)
+    .map_err(|e| lofty_error("Failed to write audio to buffer", &e.into()))?;
+  Ok(true)
+}
 
-    // Test track where no > of (invalid but should be handled)
-    let tags_track_invalid = AudioTags {
-      title: Some("Test Song".to_string()),
-      artists: None,
-      album: None,
-      year: None,
-      genre: None,
-      track: Some(Position {
-        no: Some(10),
-        of: Some(5), // no > of
-      }),
-      album_artists: None,
-      comment: None,
-      disc: Some(Position {
-        no: Some(3),
-        of: Some(1), // no > of
-      }),
-      image: None,
-      all_images: None,
-    };
-    assert_eq!(
-      tags_track_invalid.track,
-      Some(Position {
-        no: Some(10),
-        of: Some(5)
-      })
-    );
-    assert_eq!(
-      tags_track_invalid.disc,
-      Some(Position {
-        no: Some(3),
-        of: Some(1)
-      })
-    );
+async fn generic_write_tags<F>(
+  mut file: F,
+  tags: AudioTags,
+  options: &WriteTagsOptions,
+) -> Result<WriteTagsOutcome, String>
+where
+  F: FileLike,
+  LoftyError: From<<F as Truncate>::Error>,
+  LoftyError: From<<F as Length>::Error>,
+{
+  let warnings = check_identifiers(&tags, options)?;
+  if let Some(limits) = options.image_limits.as_ref() {
+    for image in tags.image.iter().chain(tags.all_images.iter().flatten()) {
+      artwork::validate_image(&image.data, limits).map_err(|e| e.to_string())?;
+    }
+  }
+  let mut changed = generic_update_tag(&mut file, options, |primary_tag| {
+    let before: Option<Vec<TagItem>> = options
+      .preserve_unknown
+      .then(|| primary_tag.items().cloned().collect());
+    tags.to_tag_with_options(primary_tag, options);
+    if let Some(before) = before {
+      let written_keys = written_keys(&tags, primary_tag);
+      restore_items(primary_tag, before, &written_keys);
+    }
+    if options.deterministic {
+      sort_tag_items(primary_tag);
+    }
+  })
+  .await?;
+  let is_wav = riff::container(&mut file) == Some(riff::Container::Riff);
+  if let Some(bwf) = tags.bwf.as_ref().filter(|_| is_wav) {
+    changed |= write_bwf(&mut file, bwf)?;
+  }
+  if let Some(atoms) = tags.mp4_freeform.as_ref() {
+    if guess_file_type(&mut file) == Some(FileType::Mp4) {
+      let write_options = lofty_write_options(options);
+      changed |= mp4_freeform::write_mp4_freeform(&mut file, atoms, write_options)?;
+    }
   }
+  Ok(WriteTagsOutcome { changed, warnings })
+}
 
-  #[test]
-  fn test_audio_tags_combination_scenarios() {
-    // Test realistic music metadata scenarios
-    let classical_tags = AudioTags {
-      title: Some("Symphony No. 9 in D minor, Op. 125".to_string()),
-      artists: Some(vec!["Ludwig van Beethoven".to_string()]),
-      album: Some("Beethoven: Complete Symphonies".to_string()),
-      year: Some(1824),
-      genre: Some("Classical".to_string()),
-      track: Some(Position {
-        no: Some(1),
-        of: Some(4),
-      }),
-      album_artists: Some(vec!["Berlin Philharmonic".to_string()]),
-      comment: Some("Conducted by Herbert von Karajan".to_string()),
-      disc: Some(Position {
-        no: Some(1),
-        of: Some(5),
-      }),
-      image: Some(Image {
-        data: create_test_image_data(),
-        pic_type: AudioImageType::CoverFront,
-        mime_type: Some("image/jpeg".to_string()),
-        description: Some("Album cover art".to_string()),
-      }),
-      all_images: None,
-    };
+// How the mime type of a cover written through `AudioTags::image` is chosen
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum MimeInference {
+  // detect it from the image bytes, the declared mime type is only a fallback
+  #[default]
+  Sniff,
+  // use the declared mime type as is, detect it only when none is declared
+  TrustDeclared,
+  // never look at the bytes, the picture gets the declared mime type or none
+  Off,
+}
 
-    assert_eq!(
-      classical_tags.title,
-      Some("Symphony No. 9 in D minor, Op. 125".to_string())
-    );
-    assert_eq!(
-      classical_tags.artists,
-      Some(vec!["Ludwig van Beethoven".to_string()])
-    );
-    assert_eq!(classical_tags.year, Some(1824));
-    assert_eq!(classical_tags.genre, Some("Classical".to_string()));
+// The version ID3v2 tags are written in
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum Id3v2Version {
+  // for players that predate ID3v2.4, e.g. older car stereos and Windows Media Player
+  V3,
+  #[default]
+  V4,
+}
 
-    // Test modern pop song scenario
-    let pop_tags = AudioTags {
-      title: Some("Shape of You".to_string()),
-      artists: Some(vec!["Ed Sheeran".to_string()]),
-      album: Some("÷ (Divide)".to_string()),
-      year: Some(2017),
-      genre: Some("Pop".to_string()),
-      track: Some(Position {
-        no: Some(3),
-        of: Some(16),
-      }),
-      album_artists: Some(vec!["Ed Sheeran".to_string()]),
-      comment: Some("Produced by Steve Mac".to_string()),
-      disc: None,
-      image: None,
-      all_images: None,
-    };
+// The text encoding of the ID3v2 text, user text, comment and lyrics frames
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum Id3TextEncoding {
+  // ID3v2.3 has no UTF-8, its frames are written in UTF-16
+  #[default]
+  Utf8,
+  Utf16,
+  // frames with text outside of Latin-1 fall back to UTF-16
+  Latin1,
+}
 
-    assert_eq!(pop_tags.title, Some("Shape of You".to_string()));
-    assert_eq!(pop_tags.artists, Some(vec!["Ed Sheeran".to_string()]));
-    assert_eq!(pop_tags.year, Some(2017));
-    assert_eq!(pop_tags.genre, Some("Pop".to_string()));
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct WriteTagsOptions {
+  // snapshot the file before writing so the write can be undone
+  pub journal: bool,
+  // where journal entries are kept, hidden sidecar files when None
+  pub journal_dir: Option<String>,
+  pub mime_inference: MimeInference,
+  // keep the pictures in tag order instead of moving the front cover first
+  pub preserve_picture_order: bool,
+  // byte-identical output for identical tags: fixed padding and sorted frames
+  pub deterministic: bool,
+  // leave the file alone when the tags already hold the values being written
+  pub skip_unchanged: bool,
+  pub artwork_policy: ArtworkPolicy,
+  pub identifier_validation: IdentifierValidation,
+  // keep every existing item the tags don't explicitly replace
+  pub preserve_unknown: bool,
+  // the tag to write instead of the primary tag, created when missing
+  pub tag_type: Option<TagType>,
+  pub id3v2_version: Id3v2Version,
+  pub id3_text_encoding: Id3TextEncoding,
+  // padding left after the tag in bytes, lofty's default (or none when
+  // deterministic) when None
+  pub padding: Option<u32>,
+  // remove every other tag of the file, e.g. ID3v1 and APE next to ID3v2
+  pub remove_others: bool,
+  // name the ID3v2 chunk of WAV and AIFF files "id3 " instead of "ID3 "
+  pub lowercase_id3v2_chunk: bool,
+  pub artist_separator: ArtistSeparator,
+  // how ratings map to the byte of ID3v2 POPM frames
+  pub popm_scale: PopmScale,
+  // write to a copy of the file and rename it over the original, which a
+  // crash mid-save then leaves intact
+  pub atomic: bool,
+  // keep the access and modification times of the file
+  pub preserve_mtime: bool,
+  // copy the file before writing it
+  pub backup: Option<Backup>,
+  // reject the pictures of the tags that don't meet them
+  pub image_limits: Option<ImageLimits>,
+  // also write an ID3v1 tag holding what fits of the written tag, for players
+  // that read nothing else; ignored for formats without ID3v1
+  pub id3v1: bool,
+  // leave the GEOB frames of the file as they are, ignoring binary_objects
+  pub preserve_binary_objects: bool,
+  // store the written values NFC composed, trimmed, with single spaces and
+  // without zero-width characters
+  pub normalize_text: bool,
+}
 
-    // Test compilation album scenario
-    let compilation_tags = AudioTags {
-      title: Some("Bohemian Rhapsody".to_string()),
-      artists: Some(vec!["Queen".to_string()]),
-      album: Some("Greatest Hits".to_string()),
-      year: Some(1975),
-      genre: Some("Rock".to_string()),
-      track: Some(Position {
-        no: Some(1),
-        of: Some(17),
-      }),
-      album_artists: Some(vec!["Various Artists".to_string()]),
-      comment: Some("From the album 'A Night at the Opera'".to_string()),
-      disc: Some(Position {
-        no: Some(1),
-        of: Some(2),
-      }),
-      image: Some(Image {
-        data: create_test_image_data(),
-        pic_type: AudioImageType::CoverFront,
-        mime_type: Some("image/png".to_string()),
-        description: Some("Compilation cover".to_string()),
-      }),
-      all_images: None,
-    };
+// How the functions without options of their own save a file
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct SaveOptions {
+  // keep the access and modification times of the file
+  pub preserve_mtime: bool,
+}
 
-    assert_eq!(
-      compilation_tags.title,
-      Some("Bohemian Rhapsody".to_string())
-    );
-    assert_eq!(compilation_tags.artists, Some(vec!["Queen".to_string()]));
-    assert_eq!(
-      compilation_tags.album_artists,
-      Some(vec!["Various Artists".to_string()])
-    );
-    assert_eq!(compilation_tags.year, Some(1975));
-  }
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct WriteTagsOutcome {
+  // false when the write was skipped because it would not change anything
+  pub changed: bool,
+  // malformed identifiers written with IdentifierValidation::Warn
+  pub warnings: Vec<String>,
+}
 
-  #[test]
-  fn test_create_test_image_data() {
-    let image_data = create_test_image_data();
+pub async fn write_tags(file_path: impl AsRef<Path>, tags: AudioTags) -> Result<(), String> {
+  write_tags_with_options(file_path, tags, WriteTagsOptions::default())
+    .await
+    .map(|_| ())
+}
 
-    // Test that the image data is not empty
-    assert!(!image_data.is_empty());
+pub async fn write_tags_with_options(
+  file_path: impl AsRef<Path>,
+  tags: AudioTags,
+  options: WriteTagsOptions,
+) -> Result<WriteTagsOutcome, String> {
+  let path = file_path.as_ref();
+  let journal_entry = if options.journal {
+    Some(journal::record(
+      path,
+      options.journal_dir.as_deref().map(Path::new),
+    )?)
+  } else {
+    None
+  };
+  if let Some(backup) = options.backup.as_ref() {
+    backup::back_up(path, backup)?;
+  }
+  let mut target = WriteTarget::open(path, options.atomic, options.preserve_mtime)?;
+  let result = generic_write_tags(target.file(), tags, &options)
+    .await
+    .map_err(in_file(path));
+  let changed = result.as_ref().map(|outcome| outcome.changed);
+  settle_journal_entry(journal_entry, changed, options.atomic);
+  if matches!(changed, Ok(true)) {
+    target.commit()?;
+  }
+  result
+}
 
-    // Test JPEG header structure
-    assert_eq!(image_data[0], 0xFF); // JPEG SOI marker
-    assert_eq!(image_data[1], 0xD8); // JPEG SOI marker
-    assert_eq!(image_data[2], 0xFF); // APP0 marker
-    assert_eq!(image_data[3], 0xE0); // APP0 marker
+// Drop the journal entry of a write that left nothing to undo: one that changed
+// nothing, or an atomic one that failed and so never touched the file. A failed
+// in-place write may have rewritten part of the file, so its entry is kept.
+fn settle_journal_entry(entry: Option<PathBuf>, changed: Result<bool, &String>, atomic: bool) {
+  let needed = match changed {
+    Ok(changed) => changed,
+    Err(_) => !atomic,
+  };
+  if let (Some(entry), false) = (entry, needed) {
+    let _ = fs::remove_file(entry);
+  }
+}
 
-    // Test JFIF identifier
-    assert_eq!(image_data[6], 0x4A); // 'J'
-    assert_eq!(image_data[7], 0x46); // 'F'
-    assert_eq!(image_data[8], 0x49); // 'I'
-    assert_eq!(image_data[9], 0x46); // 'F'
+/**
+ * Write tags to a file the caller already has open, e.g. under a lock, by its
+ * file descriptor. The file is written in place: the journal, backup, atomic
+ * and preserve_mtime options need a path and are ignored. The descriptor stays
+ * open and keeps its position.
+ * @param fd - The file descriptor, open for reading and writing
+ * @param tags - The tags to write
+ * @param options - The write options
+ */
+pub async fn write_tags_to_fd(
+  fd: i32,
+  tags: AudioTags,
+  options: WriteTagsOptions,
+) -> Result<WriteTagsOutcome, String> {
+  let mut file = file_from_fd(fd)?;
+  let position = file
+    .stream_position()
+    .map_err(|e| io_error("Failed to read file", &e))?;
+  let result = generic_write_tags(&mut file, tags, &options).await;
+  file
+    .seek(SeekFrom::Start(position))
+    .map_err(|e| io_error("Failed to write file", &e))?;
+  result
+}
 
-    // Test JPEG EOI marker
-    let last_two = &image_data[image_data.len() - 2..];
-    assert_eq!(last_two[0], 0xFF); // JPEG EOI marker
-    assert_eq!(last_two[1], 0xD9); // JPEG EOI marker
+pub async fn write_tags_to_buffer(buffer: Vec<u8>, tags: AudioTags) -> Result<Vec<u8>, String> {
+  write_tags_to_buffer_with_options(buffer, tags, WriteTagsOptions::default()).await
+}
 
-    // Test that multiple calls return the same data
-    let image_data2 = create_test_image_data();
-    assert_eq!(image_data, image_data2);
-  }
+// the journal options only apply to files and are ignored here; a skipped
+// write returns the input as is
+pub async fn write_tags_to_buffer_with_options(
+  buffer: Vec<u8>,
+  tags: AudioTags,
+  options: WriteTagsOptions,
+) -> Result<Vec<u8>, String> {
+  // lofty rewrites the tag in place, so the input is edited without a copy
+  let mut cursor = Cursor::new(buffer);
 
-  // Additional comprehensive tests for maximum coverage
+  generic_write_tags(&mut cursor, tags, &options).await?;
+  Ok(cursor.into_inner())
+}
 
-  #[test]
-  fn test_audio_tags_memory_ownership() {
-    // Test that data can be moved and cloned properly
-    let original_data = create_test_image_data();
-    let original_title = "Original Title".to_string();
+async fn generic_update_tags<F>(file: F, patch: TagPatch) -> Result<(), String>
+where
+  F: FileLike,
+  LoftyError: From<<F as Truncate>::Error>,
+  LoftyError: From<<F as Length>::Error>,
+{
+  patch.validate()?;
+  let options = WriteTagsOptions::default();
+  check_identifiers(&patch.set, &options)?;
+  generic_update_tag(file, &options, |primary_tag| {
+    patch.clear_fields(primary_tag);
+    patch.set.to_tag_with_options(primary_tag, &options);
+  })
+  .await
+  .map(|_| ())
+}
 
-    let tags1 = AudioTags {
-      title: Some(original_title.clone()),
-      artists: Some(vec!["Artist 1".to_string(), "Artist 2".to_string()]),
-      album: Some("Album".to_string()),
-      year: Some(2024),
-      genre: Some("Genre".to_string()),
-      track: Some(Position {
-        no: Some(1),
-        of: Some(10),
-      }),
-      album_artists: Some(vec!["Album Artist".to_string()]),
-      comment: Some("Comment".to_string()),
-      disc: Some(Position {
-        no: Some(1),
-        of: Some(2),
-      }),
-      image: Some(Image {
-        data: original_data.clone(),
-        pic_type: AudioImageType::CoverFront,
-        mime_type: Some("image/jpeg".to_string()),
-        description: Some("Description".to_string()),
-      }),
-      all_images: None,
-    };
+/**
+ * Update some fields of a file's tags: the fields with a value in `patch.set`
+ * are written, those in `patch.clear` are removed and all others are kept
+ * @param file_path - The path to the audio file
+ * @param patch - The fields to set and to clear
+ */
+pub async fn update_tags(file_path: impl AsRef<Path>, patch: TagPatch) -> Result<(), String> {
+  update_tags_with_options(file_path, patch, SaveOptions::default()).await
+}
 
-    // Test cloning
-    let tags2 = AudioTags {
-      title: tags1.title.clone(),
-      artists: tags1.artists.clone(),
-      album: tags1.album.clone(),
-      year: tags1.year,
-      genre: tags1.genre.clone(),
-      track: match tags1.track {
-        Some(position) => Some(Position {
-          no: position.no.clone(),
-          of: position.of.clone(),
-        }),
-        None => None,
-      },
-      album_artists: tags1.album_artists.clone(),
-      comment: tags1.comment.clone(),
-      disc: match tags1.disc {
-        Some(position) => Some(Position {
-          no: position.no.clone(),
-          of: position.of.clone(),
-        }),
-        None => None,
-      },
-      image: match tags1.image {
-        Some(image) => Some(Image {
-          data: image.data.clone(),
-          pic_type: image.pic_type,
-          mime_type: image.mime_type.clone(),
-          description: image.description.clone(),
-        }),
-        None => None,
-      },
-      all_images: None,
-    };
+pub async fn update_tags_with_options(
+  file_path: impl AsRef<Path>,
+  patch: TagPatch,
+  options: SaveOptions,
+) -> Result<(), String> {
+  let path = file_path.as_ref();
+  let mut target = WriteTarget::open(path, false, options.preserve_mtime)?;
+  generic_update_tags(target.file(), patch)
+    .await
+    .map_err(in_file(path))?;
+  target.commit()
+}
 
-    // Both should have the same data
-    assert_eq!(tags1.title, tags2.title);
-    assert_eq!(tags1.artists, tags2.artists);
-    assert_eq!(tags1.album, tags2.album);
-    assert_eq!(tags1.year, tags2.year);
-    assert_eq!(tags1.genre, tags2.genre);
-    // assert_eq!(tags1.track, tags2.track);
-    assert_eq!(tags1.album_artists, tags2.album_artists);
-    assert_eq!(tags1.comment, tags2.comment);
-    // assert_eq!(tags1.disc, tags2.disc);
-    // assert_eq!(tags1.image, tags2.image);
+pub async fn update_tags_to_buffer(buffer: Vec<u8>, patch: TagPatch) -> Result<Vec<u8>, String> {
+  // lofty rewrites the tag in place, so the input is edited without a copy
+  let mut cursor = Cursor::new(buffer);
 
-    // Test that original data is still accessible
-    assert_eq!(tags1.title, Some(original_title));
-    // assert_eq!(tags1.image.as_ref().unwrap().data, original_data);
+  generic_update_tags(&mut cursor, patch).await?;
+
+  Ok(cursor.into_inner())
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ClearTagsOptions {
+  // keep the pictures of the tag
+  pub keep_images: bool,
+  // keep items describing the encoding rather than the content
+  pub keep_technical: bool,
+  // the tag to clear instead of the primary tag
+  pub tag_type: Option<TagType>,
+  // copy the file before clearing it
+  pub backup: Option<Backup>,
+  // keep the access and modification times of the file
+  pub preserve_mtime: bool,
+}
+
+fn is_technical_item(key: &ItemKey) -> bool {
+  matches!(
+    key,
+    ItemKey::FileType
+      | ItemKey::Length
+      | ItemKey::OriginalMediaType
+      | ItemKey::EncodedBy
+      | ItemKey::EncoderSoftware
+      | ItemKey::EncoderSettings
+      | ItemKey::EncodingTime
+      | ItemKey::ReplayGainAlbumGain
+      | ItemKey::ReplayGainAlbumPeak
+      | ItemKey::ReplayGainTrackGain
+      | ItemKey::ReplayGainTrackPeak
+      | ItemKey::Bpm
+      | ItemKey::IntegerBpm
+      | ItemKey::InitialKey
+  )
+}
+
+async fn generic_clear_tags<F>(file: &mut F, options: &ClearTagsOptions) -> Result<(), String>
+where
+  F: FileLike,
+  LoftyError: From<<F as Truncate>::Error>,
+  LoftyError: From<<F as Length>::Error>,
+{
+  let mut tagged_file = probe_read(file, ParseOptions::new())?;
+
+  let tag_type = options.tag_type.unwrap_or(tagged_file.primary_tag_type());
+  if !tagged_file.supports_tag_type(tag_type) {
+    return Err(format!(
+      "{:?} files don't support {:?} tags",
+      tagged_file.file_type(),
+      tag_type
+    ));
   }
 
-  #[test]
-  fn test_audio_tags_large_scale_data() {
-    // Test with very large amounts of data
-    let large_artists: Vec<String> = (1..=1000)
-      .map(|i| {
-        format!(
-          "Artist Number {} with a very long name that might cause issues",
-          i
-        )
-      })
-      .collect();
+  // Create a new empty tag of the same type
+  let mut empty_tag = Tag::new(tag_type);
 
-    let large_album_artists: Vec<String> = (1..=500)
-      .map(|i| format!("Album Artist {} with extended name", i))
-      .collect();
+  // Carry over whatever the caller asked to keep
+  if let Some(existing) = tagged_file.tag(tag_type) {
+    if options.keep_images {
+      for picture in existing.pictures() {
+        empty_tag.push_picture(picture.clone());
+      }
+    }
+    if options.keep_technical {
+      for item in existing
+        .items()
+        .filter(|item| is_technical_item(item.key()))
+      {
+        empty_tag.push(item.clone());
+      }
+    }
+  }
 
-    let large_comment = "This is a very long comment that contains a lot of text. ".repeat(100);
-    let large_title = "A".repeat(1000);
-    let large_album = "B".repeat(1000);
-    let large_genre = "C".repeat(1000);
+  // Replace the existing tag with the empty one
+  tagged_file.insert_tag(empty_tag);
 
-    let large_tags = AudioTags {
-      title: Some(large_title.clone()),
-      artists: Some(large_artists.clone()),
-      album: Some(large_album.clone()),
-      year: Some(2024),
-      genre: Some(large_genre.clone()),
-      track: Some(Position {
-        no: Some(1),
-        of: Some(1000),
-      }),
-      album_artists: Some(large_album_artists.clone()),
-      comment: Some(large_comment.clone()),
-      disc: Some(Position {
-        no: Some(1),
-        of: Some(100),
-      }),
-      image: Some(Image {
-        data: create_test_image_data(),
-        pic_type: AudioImageType::CoverFront,
-        mime_type: Some("image/jpeg".to_string()),
-        description: Some("Large image description".to_string()),
-      }),
-      all_images: None,
-    };
+  // Write the updated tag back over the same data
+  file
+    .rewind()
+    .map_err(|e| io_error("Failed to write audio file", &e))?;
+  tagged_file
+    .save_to(file, WriteOptions::default())
+    .map_err(|e| lofty_error("Failed to write audio file", &e))?;
 
-    // Verify all large data is stored correctly
-    assert_eq!(large_tags.title, Some(large_title));
-    assert_eq!(large_tags.artists, Some(large_artists));
-    assert_eq!(large_tags.album, Some(large_album));
-    assert_eq!(large_tags.genre, Some(large_genre));
-    assert_eq!(large_tags.album_artists, Some(large_album_artists));
-    assert_eq!(large_tags.comment, Some(large_comment));
-    assert_eq!(
-      large_tags.track,
-      Some(Position {
-        no: Some(1),
-        of: Some(1000),
-      })
-    );
-    assert_eq!(
-      large_tags.disc,
-      Some(Position {
-        no: Some(1),
-        of: Some(100),
-      })
-    );
+  Ok(())
+}
+
+pub async fn clear_tags(file_path: impl AsRef<Path>) -> Result<(), String> {
+  clear_tags_with_options(file_path, ClearTagsOptions::default()).await
+}
+
+pub async fn clear_tags_with_options(
+  file_path: impl AsRef<Path>,
+  options: ClearTagsOptions,
+) -> Result<(), String> {
+  let path = file_path.as_ref();
+  if let Some(backup) = options.backup.as_ref() {
+    backup::back_up(path, backup)?;
   }
+  let mut target = WriteTarget::open(path, false, options.preserve_mtime)?;
+  generic_clear_tags(target.file(), &options)
+    .await
+    .map_err(in_file(path))?;
+  target.commit()
+}
 
-  #[test]
-  fn test_audio_tags_nested_optional_combinations() {
-    // Test all possible combinations of nested Option types
-    let combinations = vec![
-      // All None
-      (None, None, None, None, None, None, None, None, None, None),
-      // All Some
-      (
-        Some("Title".to_string()),
-        Some(vec!["Artist".to_string()]),
-        Some("Album".to_string()),
-        Some(2024),
-        Some("Genre".to_string()),
-        Some(Position {
-          no: Some(1),
-          of: Some(10),
-        }),
-        Some(vec!["Album Artist".to_string()]),
-        Some("Comment".to_string()),
-        Some(Position {
-          no: Some(1),
-          of: Some(2),
-        }),
-        Some(Image {
-          data: create_test_image_data(),
-          pic_type: AudioImageType::CoverFront,
-          mime_type: Some("image/jpeg".to_string()),
-          description: Some("Description".to_string()),
-        }),
-      ),
-      // Mixed combinations
-      (
-        Some("Title".to_string()),
-        None,
-        Some("Album".to_string()),
-        None,
-        Some("Genre".to_string()),
-        None,
-        Some(vec!["Album Artist".to_string()]),
-        None,
-        Some(Position {
-          no: Some(1),
-          of: Some(2),
-        }),
-        None,
-      ),
-      (
-        None,
-        Some(vec!["Artist".to_string()]),
-        None,
-        Some(2024),
-        None,
-        Some(Position {
-          no: Some(1),
-          of: Some(10),
-        }),
-        None,
-        Some("Comment".to_string()),
-        None,
-        Some(Image {
-          data: create_test_image_data(),
-          pic_type: AudioImageType::CoverFront,
-          mime_type: Some("image/png".to_string()),
-          description: Some("Description".to_string()),
-        }),
-      ),
-    ];
+pub async fn clear_tags_to_buffer(buffer: Vec<u8>) -> Result<Vec<u8>, String> {
+  clear_tags_to_buffer_with_options(buffer, ClearTagsOptions::default()).await
+}
 
-    for (i, (title, artists, album, year, genre, track, album_artists, comment, disc, image)) in
-      combinations.iter().enumerate()
-    {
-      let tags = AudioTags {
-        title: title.clone(),
-        artists: artists.clone(),
-        album: album.clone(),
-        year: *year,
-        genre: genre.clone(),
-        track: match track {
-          Some(position) => Some(Position {
+pub async fn clear_tags_to_buffer_with_options(
+  buffer: Vec<u8>,
+  options: ClearTagsOptions,
+) -> Result<Vec<u8>, String> {
+  // lofty rewrites the tag in place, so the input is edited without a copy
+  let mut cursor = Cursor::new(buffer);
+
+  generic_clear_tags(&mut cursor, &options).await?;
+
+  Ok(cursor.into_inner())
+}
+
+// the types of the tags removed, in the order of `tag_types`
+async fn generic_strip_tag_types<F>(
+  file: &mut F,
+  tag_types: &[TagType],
+) -> Result<Vec<TagType>, String>
+where
+  F: FileLike,
+  LoftyError: From<<F as Truncate>::Error>,
+  LoftyError: From<<F as Length>::Error>,
+{
+  if let Some(format) = dsd::format(file) {
+    // the ID3v2 tag is the only tag of a DSD file
+    if !tag_types.contains(&TagType::Id3v2) || dsd::read_id3v2(file, format)?.is_none() {
+      return Ok(Vec::new());
+    }
+    dsd::write_id3v2(file, format, b"")?;
+    return Ok(vec![TagType::Id3v2]);
+  }
+  file
+    .rewind()
+    .map_err(|e| io_error("Failed to read audio file", &e))?;
+  let tagged_file = probe_read(file, ParseOptions::new())?;
+
+  // only the tags the file has, lofty writes an empty ID3v1 tag when asked to
+  // remove one that isn't there
+  let present: Vec<TagType> = tag_types
+    .iter()
+    .copied()
+    .filter(|tag_type| tagged_file.contains_tag_type(*tag_type))
+    .collect();
+  for tag_type in &present {
+    file
+      .rewind()
+      .map_err(|e| io_error("Failed to read audio file", &e))?;
+    tag_type
+      .remove_from(file)
+      .map_err(|e| lofty_error(&format!("Failed to remove {:?} tag", tag_type), &e))?;
+  }
+  Ok(present)
+}
+
+/**
+ * Remove every tag of the given types from a file, e.g. stale APE or ID3v1
+ * tags next to the ID3v2 tag of an MP3
+ * @param file_path - The path to the audio file
+ * @param tag_types - The tags to remove, types the format can't hold are skipped
+ */
+pub async fn strip_tag_types(
+  file_path: impl AsRef<Path>,
+  tag_types: Vec<TagType>,
+) -> Result<(), String> {
+  strip_tag_types_with_options(file_path, tag_types, SaveOptions::default()).await
+}
+
+pub async fn strip_tag_types_with_options(
+  file_path: impl AsRef<Path>,
+  tag_types: Vec<TagType>,
+  options: SaveOptions,
+) -> Result<(), String> {
+  let path = file_path.as_ref();
+  let mut target = WriteTarget::open(path, false, options.preserve_mtime)?;
+  generic_strip_tag_types(target.file(), &tag_types)
+    .await
+    .map_err(in_file(path))?;
+  target.commit()
+}
+
+pub async fn strip_tag_types_to_buffer(
+  buffer: Vec<u8>,
+  tag_types: Vec<TagType>,
+) -> Result<Vec<u8>, String> {
+  let mut cursor = Cursor::new(buffer);
+  generic_strip_tag_types(&mut cursor, &tag_types).await?;
+  Ok(cursor.into_inner())
+}
+
+/**
+ * Remove every tag of a file: the primary tag along with the ID3v1, APE or
+ * other tags left next to it, which clearing the tags keeps
+ * @param file_path - The path to the audio file
+ * @returns The types of the tags removed, none for a file without tags
+ */
+pub async fn strip_all_tags(file_path: impl AsRef<Path>) -> Result<Vec<TagType>, String> {
+  strip_all_tags_with_options(file_path, SaveOptions::default()).await
+}
+
+pub async fn strip_all_tags_with_options(
+  file_path: impl AsRef<Path>,
+  options: SaveOptions,
+) -> Result<Vec<TagType>, String> {
+  let path = file_path.as_ref();
+  let mut target = WriteTarget::open(path, false, options.preserve_mtime)?;
+  let removed = generic_strip_tag_types(target.file(), &ALL_TAG_TYPES)
+    .await
+    .map_err(in_file(path))?;
+  target.commit()?;
+  Ok(removed)
+}
+
+/**
+ * Remove every tag of an audio buffer, see `strip_all_tags`
+ * @param buffer - The audio data, stripped in place
+ * @returns The audio data without tags, and the types of the tags removed
+ */
+pub async fn strip_all_tags_to_buffer(buffer: Vec<u8>) -> Result<(Vec<u8>, Vec<TagType>), String> {
+  let mut cursor = Cursor::new(buffer);
+  let removed = generic_strip_tag_types(&mut cursor, &ALL_TAG_TYPES).await?;
+  Ok((cursor.into_inner(), removed))
+}
+
+pub async fn read_cover_image_from_buffer(buffer: Vec<u8>) -> Result<Option<Vec<u8>>, String> {
+  let tags = read_tags_from_buffer(buffer).await?;
+  match tags.image {
+    Some(image) => Ok(Some(image.data)),
+    None => Ok(None),
+  }
+}
+
+async fn generic_read_picture<F>(
+  file: &mut F,
+  pic_type: AudioImageType,
+) -> Result<Option<Vec<u8>>, String>
+where
+  F: FileLike,
+  LoftyError: From<<F as Truncate>::Error>,
+  LoftyError: From<<F as Length>::Error>,
+{
+  let tagged_file = probe_read(file, ParseOptions::new())?;
+
+  // only the requested picture is copied out of the tag
+  let picture_type = pic_type.build_picture_type();
+  Ok(tagged_file.primary_tag().and_then(|tag| {
+    tag
+      .pictures()
+      .iter()
+      .find(|picture| picture.pic_type() == picture_type)
+      .map(|picture| picture.data().to_vec())
+  }))
+}
+
+/**
+ * Read the first picture of the given type, e.g. the back cover or a leaflet
+ * @param buffer - The audio data
+ * @param pic_type - The picture type to look for
+ */
+pub async fn read_image_from_buffer(
+  buffer: Vec<u8>,
+  pic_type: AudioImageType,
+) -> Result<Option<Vec<u8>>, String> {
+  let mut cursor = Cursor::new(buffer);
+  generic_read_picture(&mut cursor, pic_type).await
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct CoverImageOptions {
+  pub pic_type: AudioImageType,
+  pub description: Option<String>,
+  // trusted as is when set, inferred from the image bytes otherwise
+  pub mime_type: Option<String>,
+  // copy the file before writing it, buffers are never backed up
+  pub backup: Option<Backup>,
+  // keep the access and modification times of the file
+  pub preserve_mtime: bool,
+  // reject the picture when it doesn't meet them
+  pub image_limits: Option<ImageLimits>,
+  // downscale the picture first when it is larger
+  pub resize: Option<ResizeOptions>,
+}
+
+impl Default for CoverImageOptions {
+  fn default() -> Self {
+    Self {
+      pic_type: AudioImageType::CoverFront,
+      description: None,
+      mime_type: None,
+      backup: None,
+      preserve_mtime: false,
+      image_limits: None,
+      resize: None,
+    }
+  }
+}
+
+impl CoverImageOptions {
+  // the picture to write: resized when oversized, then checked against the
+  // limits
+  fn into_image(self, data: Vec<u8>) -> Result<Image, String> {
+    let resized = match self.resize.as_ref() {
+      Some(resize) => artwork::resize_image(&data, resize)?
+        .map(|resized| (resized, resize.format.mime_type().to_string())),
+      None => None,
+    };
+    let (data, mime_type) = match resized {
+      Some((resized, mime_type)) => (resized, Some(mime_type)),
+      None => (data, self.mime_type),
+    };
+    if let Some(limits) = self.image_limits.as_ref() {
+      artwork::validate_image(&data, limits).map_err(|e| e.to_string())?;
+    }
+    Ok(Image {
+      data,
+      pic_type: self.pic_type,
+      mime_type,
+      description: self.description,
+      ..Default::default()
+    })
+  }
+}
+
+pub async fn write_cover_image_to_buffer(
+  buffer: Vec<u8>,
+  image_data: Vec<u8>,
+) -> Result<Vec<u8>, String> {
+  let audio_tags = AudioTags {
+    image: Some(Image {
+      data: image_data,
+      pic_type: AudioImageType::CoverFront,
+      mime_type: None,
+      description: None,
+      ..Default::default()
+    }),
+    ..Default::default()
+  };
+  let buffer = write_tags_to_buffer(buffer, audio_tags)
+    .await
+    .map_err(|e| format!("Failed to write cover image to buffer: {}", e))?;
+
+  Ok(buffer)
+}
+
+/**
+ * Write a picture of any type to an audio buffer, replacing the pictures of that type
+ * @param buffer - The audio data
+ * @param image_data - The image data to add
+ * @param options - The picture type, description and mime type of the picture
+ */
+pub async fn write_cover_image_to_buffer_with_options(
+  buffer: Vec<u8>,
+  image_data: Vec<u8>,
+  options: CoverImageOptions,
+) -> Result<Vec<u8>, String> {
+  let image = options.into_image(image_data)?;
+  let mime_inference = if image.mime_type.is_some() {
+    MimeInference::TrustDeclared
+  } else {
+    MimeInference::Sniff
+  };
+
+  // lofty rewrites the tag in place, so the input is edited without a copy
+  let mut cursor = Cursor::new(buffer);
+
+  generic_update_tag(&mut cursor, &WriteTagsOptions::default(), |primary_tag| {
+    set_picture(primary_tag, &image, mime_inference)
+  })
+  .await
+  .map_err(|e| format!("Failed to write cover image to buffer: {}", e))?;
+
+  Ok(cursor.into_inner())
+}
+
+// removes the pictures of the given types, or every picture for None
+fn remove_pictures(primary_tag: &mut Tag, pic_types: Option<&[AudioImageType]>) {
+  let picture_types: Option<Vec<PictureType>> = pic_types.map(|pic_types| {
+    pic_types
+      .iter()
+      .map(AudioImageType::build_picture_type)
+      .collect()
+  });
+  let len = primary_tag.pictures().len();
+  for i in (0..len).rev() {
+    let picture_type = primary_tag.pictures()[i].pic_type();
+    if picture_types
+      .as_ref()
+      .is_none_or(|picture_types| picture_types.contains(&picture_type))
+    {
+      primary_tag.remove_picture(i);
+    }
+  }
+}
+
+// Replace the contents of `dest` with those of `source`. A tag of the same
+// format is copied as is, frames lofty doesn't map included; otherwise the
+// items are translated and those `dest` can't hold are dropped
+fn copy_tag(source: &Tag, dest: &mut Tag) {
+  if source.tag_type() == dest.tag_type() {
+    *dest = source.clone();
+    return;
+  }
+  dest.clear();
+  for item in source.items() {
+    if !matches!(item.key(), ItemKey::Unknown(_)) {
+      dest.push(item.clone());
+    }
+  }
+  custom_fields::copy_unknown_fields(source, dest);
+  for picture in source.pictures() {
+    dest.push_picture(picture.clone());
+  }
+}
+
+async fn generic_read_primary_tag<F>(file: &mut F) -> Result<Option<Tag>, String>
+where
+  F: FileLike,
+  LoftyError: From<<F as Truncate>::Error>,
+  LoftyError: From<<F as Length>::Error>,
+{
+  let tagged_file = probe_read(file, ParseOptions::new())?;
+  Ok(tagged_file.primary_tag().cloned())
+}
+
+// an empty source tag leaves the destination tag empty
+async fn generic_copy_tags<F>(
+  source: Option<Tag>,
+  dest: F,
+  options: &WriteTagsOptions,
+) -> Result<bool, String>
+where
+  F: FileLike,
+  LoftyError: From<<F as Truncate>::Error>,
+  LoftyError: From<<F as Length>::Error>,
+{
+  generic_update_tag(dest, options, |dest_tag| match &source {
+    Some(source) => copy_tag(source, dest_tag),
+    None => dest_tag.clear(),
+  })
+  .await
+}
+
+/**
+ * Copy the whole tag of a file to another one, pictures and unmapped frames
+ * included, translating between tag formats when the files are of different
+ * types (e.g. MP3 to FLAC). The tag of the destination is replaced.
+ * @param source_path - The file to copy the tag from
+ * @param dest_path - The file to copy the tag to
+ * @param options - How the destination tag is saved, as for write_tags
+ */
+pub async fn copy_tags(
+  source_path: impl AsRef<Path>,
+  dest_path: impl AsRef<Path>,
+  options: WriteTagsOptions,
+) -> Result<(), String> {
+  let source_path = source_path.as_ref();
+  let mut source = open_file(source_path)?;
+  let source_tag = generic_read_primary_tag(&mut source)
+    .await
+    .map_err(in_file(source_path))?;
+
+  let path = dest_path.as_ref();
+  let journal_entry = if options.journal {
+    Some(journal::record(
+      path,
+      options.journal_dir.as_deref().map(Path::new),
+    )?)
+  } else {
+    None
+  };
+  if let Some(backup) = options.backup.as_ref() {
+    backup::back_up(path, backup)?;
+  }
+  let mut target = WriteTarget::open(path, options.atomic, options.preserve_mtime)?;
+  let result = generic_copy_tags(source_tag, target.file(), &options)
+    .await
+    .map_err(in_file(path));
+  settle_journal_entry(journal_entry, result.as_ref().copied(), options.atomic);
+  if matches!(result, Ok(true)) {
+    target.commit()?;
+  }
+  result.map(|_| ())
+}
+
+pub async fn copy_tags_to_buffer(
+  source: Vec<u8>,
+  dest: Vec<u8>,
+  options: WriteTagsOptions,
+) -> Result<Vec<u8>, String> {
+  let source_tag = generic_read_primary_tag(&mut Cursor::new(source)).await?;
+  // lofty rewrites the tag in place, so the input is edited without a copy
+  let mut cursor = Cursor::new(dest);
+
+  generic_copy_tags(source_tag, &mut cursor, &options).await?;
+  Ok(cursor.into_inner())
+}
+
+/**
+ * Remove pictures from an audio buffer, leaving the rest of the tag as is
+ * @param buffer - The audio data
+ * @param pic_types - The picture types to remove, None for every picture
+ */
+pub async fn remove_images_from_buffer(
+  buffer: Vec<u8>,
+  pic_types: Option<Vec<AudioImageType>>,
+) -> Result<Vec<u8>, String> {
+  // lofty rewrites the tag in place, so the input is edited without a copy
+  let mut cursor = Cursor::new(buffer);
+
+  generic_update_tag(&mut cursor, &WriteTagsOptions::default(), |primary_tag| {
+    remove_pictures(primary_tag, pic_types.as_deref())
+  })
+  .await
+  .map_err(|e| format!("Failed to remove images from buffer: {}", e))?;
+
+  Ok(cursor.into_inner())
+}
+
+pub async fn remove_cover_image_from_buffer(buffer: Vec<u8>) -> Result<Vec<u8>, String> {
+  remove_images_from_buffer(buffer, Some(vec![AudioImageType::CoverFront])).await
+}
+
+/**
+ * Read the unsynchronized lyrics of an audio buffer
+ * @param buffer - The audio data
+ */
+pub async fn read_lyrics_from_buffer(buffer: Vec<u8>) -> Result<Option<String>, String> {
+  Ok(read_tags_from_buffer(buffer).await?.lyrics)
+}
+
+pub async fn read_lyrics(file_path: impl AsRef<Path>) -> Result<Option<String>, String> {
+  Ok(read_tags(file_path).await?.lyrics)
+}
+
+// replaces the lyrics and leaves everything else as is
+fn set_lyrics(primary_tag: &mut Tag, lyrics: &str) {
+  primary_tag.remove_key(&ItemKey::Lyrics);
+  primary_tag.insert_text(ItemKey::Lyrics, lyrics.to_string());
+}
+
+/**
+ * Embed unsynchronized lyrics (ID3v2 USLT, MP4 ©lyr, Vorbis LYRICS) in an audio buffer
+ * @param buffer - The audio data
+ * @param lyrics - The lyrics, replacing any lyrics already present
+ */
+pub async fn write_lyrics_to_buffer(buffer: Vec<u8>, lyrics: String) -> Result<Vec<u8>, String> {
+  // lofty rewrites the tag in place, so the input is edited without a copy
+  let mut cursor = Cursor::new(buffer);
+
+  generic_update_tag(&mut cursor, &WriteTagsOptions::default(), |primary_tag| {
+    set_lyrics(primary_tag, &lyrics)
+  })
+  .await?;
+
+  Ok(cursor.into_inner())
+}
+
+pub async fn write_lyrics(file_path: impl AsRef<Path>, lyrics: String) -> Result<(), String> {
+  write_lyrics_with_options(file_path, lyrics, SaveOptions::default()).await
+}
+
+pub async fn write_lyrics_with_options(
+  file_path: impl AsRef<Path>,
+  lyrics: String,
+  options: SaveOptions,
+) -> Result<(), String> {
+  let path = file_path.as_ref();
+  let mut target = WriteTarget::open(path, false, options.preserve_mtime)?;
+  generic_update_tag(target.file(), &WriteTagsOptions::default(), |primary_tag| {
+    set_lyrics(primary_tag, &lyrics)
+  })
+  .await
+  .map_err(in_file(path))?;
+  target.commit()
+}
+
+async fn generic_read_synced_lyrics<F>(file: &mut F) -> Result<Option<SyncedLyrics>, String>
+where
+  F: FileLike,
+  LoftyError: From<<F as Truncate>::Error>,
+  LoftyError: From<<F as Length>::Error>,
+{
+  let tagged_file = probe_read(file, ParseOptions::new())?;
+  Ok(
+    tagged_file
+      .primary_tag()
+      .and_then(synced_lyrics::synced_lyrics_from_tag),
+  )
+}
+
+/**
+ * Read the synced lyrics of an audio buffer, see `synced_lyrics::synced_lyrics_from_tag`
+ * @param buffer - The audio data
+ */
+pub async fn read_synced_lyrics_from_buffer(
+  buffer: Vec<u8>,
+) -> Result<Option<SyncedLyrics>, String> {
+  let mut cursor = Cursor::new(buffer);
+  generic_read_synced_lyrics(&mut cursor).await
+}
+
+pub async fn read_synced_lyrics(
+  file_path: impl AsRef<Path>,
+) -> Result<Option<SyncedLyrics>, String> {
+  let path = file_path.as_ref();
+  let mut file = open_file(path)?;
+  generic_read_synced_lyrics(&mut file)
+    .await
+    .map_err(in_file(path))
+}
+
+/**
+ * Embed synced lyrics in an audio buffer: an ID3v2 SYLT frame, or LRC text in
+ * the lyrics field of other formats
+ * @param buffer - The audio data
+ * @param lyrics - The lyrics, replacing any synced lyrics already present
+ */
+pub async fn write_synced_lyrics_to_buffer(
+  buffer: Vec<u8>,
+  lyrics: SyncedLyrics,
+) -> Result<Vec<u8>, String> {
+  synced_lyrics::validate(&lyrics)?;
+  // lofty rewrites the tag in place, so the input is edited without a copy
+  let mut cursor = Cursor::new(buffer);
+
+  let mut result = Ok(());
+  generic_update_tag(&mut cursor, &WriteTagsOptions::default(), |primary_tag| {
+    result = synced_lyrics::set_synced_lyrics(primary_tag, &lyrics)
+  })
+  .await?;
+  result?;
+
+  Ok(cursor.into_inner())
+}
+
+pub async fn write_synced_lyrics(
+  file_path: impl AsRef<Path>,
+  lyrics: SyncedLyrics,
+) -> Result<(), String> {
+  write_synced_lyrics_with_options(file_path, lyrics, SaveOptions::default()).await
+}
+
+pub async fn write_synced_lyrics_with_options(
+  file_path: impl AsRef<Path>,
+  lyrics: SyncedLyrics,
+  options: SaveOptions,
+) -> Result<(), String> {
+  synced_lyrics::validate(&lyrics)?;
+  let path = file_path.as_ref();
+  let mut target = WriteTarget::open(path, false, options.preserve_mtime)?;
+  let mut result = Ok(());
+  generic_update_tag(target.file(), &WriteTagsOptions::default(), |primary_tag| {
+    result = synced_lyrics::set_synced_lyrics(primary_tag, &lyrics)
+  })
+  .await
+  .map_err(in_file(path))?;
+  result?;
+  target.commit()
+}
+
+async fn generic_write_raw_tag_items<F>(mut file: F, items: &[RawTagItem]) -> Result<(), String>
+where
+  F: FileLike,
+  LoftyError: From<<F as Truncate>::Error>,
+  LoftyError: From<<F as Length>::Error>,
+{
+  let mut tag_types: Vec<TagType> = Vec::new();
+  for item in items {
+    let tag_type = raw_items::parse_tag_type(&item.tag_type)?;
+    if !tag_types.contains(&tag_type) {
+      tag_types.push(tag_type);
+    }
+  }
+  for tag_type in tag_types {
+    let tag_items: Vec<&RawTagItem> = items
+      .iter()
+      .filter(|item| item.tag_type == format!("{:?}", tag_type))
+      .collect();
+    let options = WriteTagsOptions {
+      tag_type: Some(tag_type),
+      ..Default::default()
+    };
+    let mut result = Ok(());
+    generic_update_tag(&mut file, &options, |tag| {
+      result = raw_items::set_raw_items(tag, &tag_items)
+    })
+    .await?;
+    result?;
+  }
+  Ok(())
+}
+
+/**
+ * Replace the items of the tags of an audio buffer as is, without the checks
+ * and conversions of `write_tags`. Each tag named by an item gets exactly the
+ * items naming it, created when missing; other tags and pictures are kept.
+ * @param buffer - The audio data
+ * @param items - The items, as `read_raw_tag_items` returns them
+ */
+pub async fn write_raw_tag_items_to_buffer(
+  buffer: Vec<u8>,
+  items: Vec<RawTagItem>,
+) -> Result<Vec<u8>, String> {
+  // lofty rewrites the tag in place, so the input is edited without a copy
+  let mut cursor = Cursor::new(buffer);
+  generic_write_raw_tag_items(&mut cursor, &items).await?;
+  Ok(cursor.into_inner())
+}
+
+pub async fn write_raw_tag_items(
+  file_path: impl AsRef<Path>,
+  items: Vec<RawTagItem>,
+) -> Result<(), String> {
+  write_raw_tag_items_with_options(file_path, items, SaveOptions::default()).await
+}
+
+pub async fn write_raw_tag_items_with_options(
+  file_path: impl AsRef<Path>,
+  items: Vec<RawTagItem>,
+  options: SaveOptions,
+) -> Result<(), String> {
+  let path = file_path.as_ref();
+  let mut target = WriteTarget::open(path, false, options.preserve_mtime)?;
+  generic_write_raw_tag_items(target.file(), &items)
+    .await
+    .map_err(in_file(path))?;
+  target.commit()
+}
+
+// the file type of a buffer, which unlike `FileType::from_buffer` skips a
+// leading ID3v2 tag
+fn buffer_file_type(buffer: &[u8]) -> Option<FileType> {
+  Probe::new(Cursor::new(buffer))
+    .guess_file_type()
+    .ok()?
+    .file_type()
+}
+
+/**
+ * Read the chapters of an audio buffer: the ID3v2 CHAP frames, or the chapter
+ * track or Nero chapters of MP4 files
+ * @param buffer - The audio data
+ * @returns The chapters ordered by start time, none for other formats
+ */
+pub async fn read_chapters_from_buffer(buffer: Vec<u8>) -> Result<Vec<Chapter>, String> {
+  if buffer_file_type(&buffer) == Some(FileType::Mp4) {
+    return mp4_chapters::read_chapters(&buffer);
+  }
+  generic_read_id3_chapters(&mut Cursor::new(buffer)).await
+}
+
+// the file type of a file, read from its first bytes
+fn file_type(file: &mut File) -> Result<Option<FileType>, String> {
+  let file_type = Probe::new(BufReader::new(&mut *file))
+    .guess_file_type()
+    .map_err(|e| io_error("Failed to read file", &e))?
+    .file_type();
+  file
+    .rewind()
+    .map_err(|e| io_error("Failed to read file", &e))?;
+  Ok(file_type)
+}
+
+async fn generic_read_id3_chapters<F>(file: &mut F) -> Result<Vec<Chapter>, String>
+where
+  F: FileLike,
+  LoftyError: From<<F as Truncate>::Error>,
+  LoftyError: From<<F as Length>::Error>,
+{
+  let tagged_file = probe_read(file, ParseOptions::new())?;
+  Ok(
+    tagged_file
+      .tag(TagType::Id3v2)
+      .map(chapters::chapters_from_tag)
+      .unwrap_or_default(),
+  )
+}
+
+pub async fn read_chapters(file_path: impl AsRef<Path>) -> Result<Vec<Chapter>, String> {
+  let path = file_path.as_ref();
+  let mut file = open_file(path)?;
+  if file_type(&mut file)? == Some(FileType::Mp4) {
+    // the chapter track is read from the whole file
+    let buffer = fs::read(path).map_err(|e| io_error("Failed to read file", &e))?;
+    return mp4_chapters::read_chapters(&buffer).map_err(in_file(path));
+  }
+  generic_read_id3_chapters(&mut file)
+    .await
+    .map_err(in_file(path))
+}
+
+// whether chapters go in an ID3v2 tag (false) or an MP4 chapter track (true)
+fn chapters_in_track(file_type: Option<FileType>) -> Result<bool, String> {
+  match file_type {
+    Some(FileType::Mp4) => Ok(true),
+    // the formats lofty writes ID3v2 tags to
+    Some(FileType::Mpeg | FileType::Wav | FileType::Aiff | FileType::Aac) => Ok(false),
+    Some(file_type) => Err(format!("{:?} files don't support chapters", file_type)),
+    None => Err("Failed to guess file type".to_string()),
+  }
+}
+
+async fn generic_write_id3_chapters<F>(file: &mut F, chapters: &[Chapter]) -> Result<(), String>
+where
+  F: FileLike,
+  LoftyError: From<<F as Truncate>::Error>,
+  LoftyError: From<<F as Length>::Error>,
+{
+  let options = WriteTagsOptions {
+    tag_type: Some(TagType::Id3v2),
+    ..Default::default()
+  };
+  let mut result = Ok(());
+  generic_update_tag(file, &options, |id3v2| {
+    result = chapters::set_chapters(id3v2, chapters)
+  })
+  .await?;
+  result
+}
+
+/**
+ * Store chapters in an audio buffer, replacing the chapters already present:
+ * ID3v2 CHAP and CTOC frames, or a chapter track in MP4 files
+ * @param buffer - The audio data
+ * @param chapters - The chapters, no chapters removes them
+ */
+pub async fn write_chapters_to_buffer(
+  buffer: Vec<u8>,
+  chapters: Vec<Chapter>,
+) -> Result<Vec<u8>, String> {
+  chapters::validate(&chapters)?;
+  if chapters_in_track(buffer_file_type(&buffer))? {
+    return mp4_chapters::write_chapters(buffer, &chapters);
+  }
+  let mut cursor = Cursor::new(buffer);
+  generic_write_id3_chapters(&mut cursor, &chapters).await?;
+  Ok(cursor.into_inner())
+}
+
+pub async fn write_chapters(
+  file_path: impl AsRef<Path>,
+  chapters: Vec<Chapter>,
+) -> Result<(), String> {
+  write_chapters_with_options(file_path, chapters, SaveOptions::default()).await
+}
+
+pub async fn write_chapters_with_options(
+  file_path: impl AsRef<Path>,
+  chapters: Vec<Chapter>,
+  options: SaveOptions,
+) -> Result<(), String> {
+  chapters::validate(&chapters)?;
+  let path = file_path.as_ref();
+  let mut target = WriteTarget::open(path, false, options.preserve_mtime)?;
+  if !chapters_in_track(file_type(target.file())?)? {
+    generic_write_id3_chapters(target.file(), &chapters)
+      .await
+      .map_err(in_file(path))?;
+    return target.commit();
+  }
+  // adding a chapter track moves the audio, so the whole file is rewritten;
+  // the target still refers to it, so its times are restored on commit
+  let buffer = fs::read(path).map_err(|e| io_error("Failed to read file", &e))?;
+  let buffer = mp4_chapters::write_chapters(buffer, &chapters).map_err(in_file(path))?;
+  fs::write(path, buffer).map_err(|e| io_error("Failed to write file", &e))?;
+  target.commit()
+}
+
+pub async fn read_cover_image_from_file(
+  file_path: impl AsRef<Path>,
+) -> Result<Option<Vec<u8>>, String> {
+  let tags = read_tags(file_path).await?;
+  Ok(tags.image.map(|image| image.data))
+}
+
+pub async fn read_image_from_file(
+  file_path: impl AsRef<Path>,
+  pic_type: AudioImageType,
+) -> Result<Option<Vec<u8>>, String> {
+  let path = file_path.as_ref();
+  let mut file = open_file(path)?;
+  generic_read_picture(&mut file, pic_type)
+    .await
+    .map_err(in_file(path))
+}
+
+pub async fn write_cover_image_to_file(
+  file_path: impl AsRef<Path>,
+  image_data: Vec<u8>,
+) -> Result<(), String> {
+  write_cover_image_to_file_with_options(file_path, image_data, CoverImageOptions::default()).await
+}
+
+pub async fn write_cover_image_to_file_with_options(
+  file_path: impl AsRef<Path>,
+  image_data: Vec<u8>,
+  options: CoverImageOptions,
+) -> Result<(), String> {
+  let path = file_path.as_ref();
+  let preserve_mtime = options.preserve_mtime;
+  let backup = options.backup.clone();
+  let image = options.into_image(image_data)?;
+  if let Some(backup) = backup.as_ref() {
+    backup::back_up(path, backup)?;
+  }
+  let mime_inference = if image.mime_type.is_some() {
+    MimeInference::TrustDeclared
+  } else {
+    MimeInference::Sniff
+  };
+  let mut target = WriteTarget::open(path, false, preserve_mtime)?;
+  generic_update_tag(target.file(), &WriteTagsOptions::default(), |primary_tag| {
+    set_picture(primary_tag, &image, mime_inference)
+  })
+  .await
+  .map_err(|e| in_file(path)(format!("Failed to write cover image to file: {}", e)))?;
+  target.commit()
+}
+
+/**
+ * Embed the picture of an image file, see `write_cover_image_to_file_with_options`
+ * @param file_path - The path to the audio file
+ * @param image_path - The path to the image file
+ * @param options - The picture type, description and mime type
+ */
+pub async fn write_cover_image_from_path(
+  file_path: impl AsRef<Path>,
+  image_path: impl AsRef<Path>,
+  options: CoverImageOptions,
+) -> Result<(), String> {
+  let image_data = fs::read(&image_path).map_err(|e| io_error("Failed to read image", &e))?;
+  write_cover_image_to_file_with_options(file_path, image_data, options).await
+}
+
+// larger downloads are rejected rather than embedded
+#[cfg(feature = "http")]
+const MAX_DOWNLOAD_BYTES: u64 = 32 * 1024 * 1024;
+
+/**
+ * Download an image and embed it, see `write_cover_image_to_file_with_options`.
+ * The file is left untouched when the download fails.
+ * @param file_path - The path to the audio file
+ * @param url - The http or https URL of the image
+ * @param options - The picture type, description and mime type
+ */
+#[cfg(feature = "http")]
+pub async fn write_cover_image_from_url(
+  file_path: impl AsRef<Path>,
+  url: String,
+  options: CoverImageOptions,
+) -> Result<(), String> {
+  let download_error = |e: ureq::Error| format!("Failed to download image: {}", e);
+  let image_data = ureq::get(&url)
+    .call()
+    .map_err(download_error)?
+    .body_mut()
+    .with_config()
+    .limit(MAX_DOWNLOAD_BYTES)
+    .read_to_vec()
+    .map_err(download_error)?;
+  write_cover_image_to_file_with_options(file_path, image_data, options).await
+}
+
+pub async fn remove_images(
+  file_path: impl AsRef<Path>,
+  pic_types: Option<Vec<AudioImageType>>,
+) -> Result<(), String> {
+  remove_images_with_options(file_path, pic_types, SaveOptions::default()).await
+}
+
+pub async fn remove_images_with_options(
+  file_path: impl AsRef<Path>,
+  pic_types: Option<Vec<AudioImageType>>,
+  options: SaveOptions,
+) -> Result<(), String> {
+  let path = file_path.as_ref();
+  let mut target = WriteTarget::open(path, false, options.preserve_mtime)?;
+  generic_update_tag(target.file(), &WriteTagsOptions::default(), |primary_tag| {
+    remove_pictures(primary_tag, pic_types.as_deref())
+  })
+  .await
+  .map_err(|e| in_file(path)(format!("Failed to remove images from file: {}", e)))?;
+  target.commit()
+}
+
+pub async fn remove_cover_image_from_file(file_path: impl AsRef<Path>) -> Result<(), String> {
+  remove_cover_image_from_file_with_options(file_path, SaveOptions::default()).await
+}
+
+pub async fn remove_cover_image_from_file_with_options(
+  file_path: impl AsRef<Path>,
+  options: SaveOptions,
+) -> Result<(), String> {
+  remove_images_with_options(file_path, Some(vec![AudioImageType::CoverFront]), options).await
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use lofty::{picture::MimeType, tag::TagType};
+
+  // Helper function to create test image data
+  fn create_test_image_data() -> Vec<u8> {
+    // Minimal JPEG header
+    vec![
+      0xFF, 0xD8, 0xFF, 0xE0, // JPEG SOI + APP0
+      0x00, 0x10, 0x4A, 0x46, 0x49, 0x46, 0x00, 0x01, // JFIF header
+      0x01, 0x01, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0xFF, 0xD9, // JPEG EOI
+    ]
+  }
+
+  // Helper function to load a file from base64 string
+  fn load_file_from_base64(base64_string: &str) -> std::result::Result<Vec<u8>, String> {
+    use base64::{engine::general_purpose, Engine as _};
+
+    general_purpose::STANDARD
+      .decode(base64_string)
+      .map_err(|e| format!("Failed to decode base64: {}", e))
+  }
+
+  // Helper function to create a Vec<u8> from base64 string
+  fn create_buffer_from_base64(base64_string: &str) -> std::result::Result<Vec<u8>, String> {
+    let data = load_file_from_base64(base64_string)?;
+    Ok(data)
+  }
+
+  #[test]
+  fn test_audio_tags_default() {
+    let tags = AudioTags::default();
+    assert!(tags.title.is_none());
+    assert!(tags.artists.is_none());
+    assert!(tags.album.is_none());
+    assert!(tags.year.is_none());
+    assert!(tags.genre.is_none());
+    assert!(tags.track.is_none());
+    assert!(tags.album_artists.is_none());
+    assert!(tags.comment.is_none());
+    assert!(tags.disc.is_none());
+    assert!(tags.image.is_none());
+  }
+
+  #[test]
+  fn test_audio_tags_basic() {
+    let tags = AudioTags {
+      title: Some("Test Song".to_string()),
+      artists: Some(vec!["Test Artist".to_string()]),
+      album: Some("Test Album".to_string()),
+      year: Some(2024),
+      genre: Some("Test Genre".to_string()),
+      track: Some(Position {
+        no: Some(1),
+        of: Some(10),
+      }),
+      album_artists: Some(vec!["Test Album Artist".to_string()]),
+      comment: Some("Test comment".to_string()),
+      disc: Some(Position {
+        no: Some(1),
+        of: Some(2),
+      }),
+      image: None,
+      all_images: None,
+      ..Default::default()
+    };
+
+    // Test that the struct is created correctly
+    assert_eq!(tags.title, Some("Test Song".to_string()));
+    assert_eq!(tags.artists, Some(vec!["Test Artist".to_string()]));
+    assert_eq!(tags.album, Some("Test Album".to_string()));
+    assert_eq!(tags.year, Some(2024));
+    assert_eq!(tags.genre, Some("Test Genre".to_string()));
+    assert_eq!(
+      tags.track,
+      Some(Position {
+        no: Some(1),
+        of: Some(10)
+      })
+    );
+    assert_eq!(
+      tags.album_artists,
+      Some(vec!["Test Album Artist".to_string()])
+    );
+    assert_eq!(tags.comment, Some("Test comment".to_string()));
+    assert_eq!(
+      tags.disc,
+      Some(Position {
+        no: Some(1),
+        of: Some(2)
+      })
+    );
+    assert!(tags.image.is_none());
+  }
+
+  #[test]
+  fn test_audio_tags_with_image() {
+    let image_data = create_test_image_data();
+    let tags = AudioTags {
+      title: Some("Test Song".to_string()),
+      artists: Some(vec!["Test Artist".to_string()]),
+      album: Some("Test Album".to_string()),
+      year: Some(2024),
+      genre: Some("Test Genre".to_string()),
+      track: Some(Position {
+        no: Some(1),
+        of: Some(10),
+      }),
+      album_artists: Some(vec!["Test Album Artist".to_string()]),
+      comment: Some("Test comment".to_string()),
+      disc: Some(Position {
+        no: Some(1),
+        of: Some(2),
+      }),
+      image: Some(Image {
+        data: image_data.clone(),
+        pic_type: AudioImageType::CoverFront,
+        mime_type: Some("image/jpeg".to_string()),
+        description: Some("Test cover".to_string()),
+        ..Default::default()
+      }),
+      all_images: None,
+      ..Default::default()
+    };
+
+    // Test that the struct with image is created correctly
+    assert_eq!(tags.title, Some("Test Song".to_string()));
+    assert!(tags.image.is_some());
+    let image = tags.image.unwrap();
+    assert_eq!(image.mime_type, Some("image/jpeg".to_string()));
+    assert_eq!(image.description, Some("Test cover".to_string()));
+    // assert_eq!(image.data, image_data);
+  }
+
+  #[test]
+  fn test_audio_tags_empty_artists() {
+    let tags = AudioTags {
+      title: Some("Test Song".to_string()),
+      artists: Some(vec![]), // Empty artists
+      album: Some("Test Album".to_string()),
+      year: Some(2024),
+      genre: Some("Test Genre".to_string()),
+      track: None,
+      album_artists: None,
+      comment: None,
+      disc: None,
+      image: None,
+      all_images: None,
+      ..Default::default()
+    };
+
+    // Test that empty artists vector is handled correctly
+    assert_eq!(tags.title, Some("Test Song".to_string()));
+    assert_eq!(tags.artists, Some(vec![]));
+    assert_eq!(tags.album, Some("Test Album".to_string()));
+    assert_eq!(tags.year, Some(2024));
+    assert_eq!(tags.genre, Some("Test Genre".to_string()));
+  }
+
+  #[test]
+  fn test_audio_tags_multiple_artists() {
+    let tags = AudioTags {
+      title: Some("Test Song".to_string()),
+      artists: Some(vec![
+        "Artist 1".to_string(),
+        "Artist 2".to_string(),
+        "Artist 3".to_string(),
+      ]),
+      album: Some("Test Album".to_string()),
+      year: Some(2024),
+      genre: Some("Test Genre".to_string()),
+      track: None,
+      album_artists: None,
+      comment: None,
+      disc: None,
+      image: None,
+      all_images: None,
+      ..Default::default()
+    };
+
+    // Test that multiple artists are handled correctly
+    assert_eq!(tags.title, Some("Test Song".to_string()));
+    assert_eq!(
+      tags.artists,
+      Some(vec![
+        "Artist 1".to_string(),
+        "Artist 2".to_string(),
+        "Artist 3".to_string()
+      ])
+    );
+    assert_eq!(tags.album, Some("Test Album".to_string()));
+    assert_eq!(tags.year, Some(2024));
+    assert_eq!(tags.genre, Some("Test Genre".to_string()));
+  }
+
+  #[test]
+  fn test_audio_tags_partial_data() {
+    let tags = AudioTags {
+      title: Some("Test Song".to_string()),
+      artists: None, // Not set
+      album: None,   // Not set
+      year: Some(2024),
+      genre: None, // Not set
+      track: Some(Position {
+        no: Some(1),
+        of: None,
+      }), // Only track number
+      album_artists: None,
+      comment: None,
+      disc: None,
+      image: None,
+      all_images: None,
+      ..Default::default()
+    };
+
+    // Test that partial data is handled correctly
+    assert_eq!(tags.title, Some("Test Song".to_string()));
+    assert!(tags.artists.is_none());
+    assert!(tags.album.is_none());
+    assert_eq!(tags.year, Some(2024));
+    assert!(tags.genre.is_none());
+    assert_eq!(
+      tags.track,
+      Some(Position {
+        no: Some(1),
+        of: None
+      })
+    );
+  }
+
+  #[test]
+  fn test_position_struct() {
+    let pos = Position {
+      no: Some(1),
+      of: Some(10),
+    };
+    assert_eq!(pos.no, Some(1));
+    assert_eq!(pos.of, Some(10));
+
+    let pos_partial = Position {
+      no: Some(1),
+      of: None,
+    };
+    assert_eq!(pos_partial.no, Some(1));
+    assert_eq!(pos_partial.of, None);
+  }
+
+  #[test]
+  fn test_image_struct() {
+    let image_data = create_test_image_data();
+    let image = Image {
+      data: image_data.clone(),
+      pic_type: AudioImageType::CoverFront,
+      mime_type: Some("image/jpeg".to_string()),
+      description: Some("Test image".to_string()),
+      ..Default::default()
+    };
+
+    // assert_eq!(image.data, Vec<u8>::from(image_data));
+    assert_eq!(image.mime_type, Some("image/jpeg".to_string()));
+    assert_eq!(image.description, Some("Test image".to_string()));
+
+    let image_minimal = Image {
+      data: image_data,
+      pic_type: AudioImageType::CoverFront,
+      mime_type: None,
+      description: None,
+      ..Default::default()
+    };
+
+    assert_eq!(image_minimal.mime_type, None);
+    assert_eq!(image_minimal.description, None);
+  }
+
+  #[test]
+  fn test_audio_tags_creation_variations() {
+    // Test with all fields
+    let full_tags = AudioTags {
+      title: Some("Full Song".to_string()),
+      artists: Some(vec!["Artist 1".to_string(), "Artist 2".to_string()]),
+      album: Some("Full Album".to_string()),
+      year: Some(2023),
+      genre: Some("Rock".to_string()),
+      track: Some(Position {
+        no: Some(5),
+        of: Some(12),
+      }),
+      album_artists: Some(vec!["Album Artist".to_string()]),
+      comment: Some("Great song".to_string()),
+      disc: Some(Position {
+        no: Some(1),
+        of: Some(2),
+      }),
+      image: Some(Image {
+        data: create_test_image_data(),
+        pic_type: AudioImageType::CoverFront,
+        mime_type: Some("image/png".to_string()),
+        description: Some("Album cover".to_string()),
+        ..Default::default()
+      }),
+      all_images: None,
+      ..Default::default()
+    };
+
+    assert_eq!(full_tags.title, Some("Full Song".to_string()));
+    assert_eq!(
+      full_tags.artists,
+      Some(vec!["Artist 1".to_string(), "Artist 2".to_string()])
+    );
+    assert_eq!(
+      full_tags.track,
+      Some(Position {
+        no: Some(5),
+        of: Some(12)
+      })
+    );
+    assert!(full_tags.image.is_some());
+
+    // Test with minimal fields
+    let minimal_tags = AudioTags {
+      title: Some("Minimal Song".to_string()),
+      artists: None,
+      album: None,
+      year: None,
+      genre: None,
+      track: None,
+      album_artists: None,
+      comment: None,
+      disc: None,
+      image: None,
+      all_images: None,
+      ..Default::default()
+    };
+
+    assert_eq!(minimal_tags.title, Some("Minimal Song".to_string()));
+    assert!(minimal_tags.artists.is_none());
+    assert!(minimal_tags.album.is_none());
+    assert!(minimal_tags.year.is_none());
+    assert!(minimal_tags.image.is_none());
+  }
+
+  // Additional comprehensive tests for better coverage
+
+  #[test]
+  fn test_position_struct_edge_cases() {
+    // Test with both values
+    let pos_full = Position {
+      no: Some(1),
+      of: Some(10),
+    };
+    assert_eq!(pos_full.no, Some(1));
+    assert_eq!(pos_full.of, Some(10));
+
+    // Test with only no
+    let pos_no_only = Position {
+      no: Some(5),
+      of: None,
+    };
+    assert_eq!(pos_no_only.no, Some(5));
+    assert_eq!(pos_no_only.of, None);
+
+    // Test with only of
+    let pos_of_only = Position {
+      no: None,
+      of: Some(15),
+    };
+    assert_eq!(pos_of_only.no, None);
+    assert_eq!(pos_of_only.of, Some(15));
+
+    // Test with neither
+    let pos_empty = Position { no: None, of: None };
+    assert_eq!(pos_empty.no, None);
+    assert_eq!(pos_empty.of, None);
+
+    // Test with zero values
+    let pos_zero = Position {
+      no: Some(0),
+      of: Some(0),
+    };
+    assert_eq!(pos_zero.no, Some(0));
+    assert_eq!(pos_zero.of, Some(0));
+
+    // Test with large values
+    let pos_large = Position {
+      no: Some(999),
+      of: Some(1000),
+    };
+    assert_eq!(pos_large.no, Some(999));
+    assert_eq!(pos_large.of, Some(1000));
+  }
+
+  #[test]
+  fn test_image_struct_edge_cases() {
+    let image_data = create_test_image_data();
+
+    // Test with all fields
+    let image_full = Image {
+      data: image_data.clone(),
+      pic_type: AudioImageType::CoverFront,
+      mime_type: Some("image/jpeg".to_string()),
+      description: Some("Full description".to_string()),
+      ..Default::default()
+    };
+    // assert_eq!(image_full.data, image_data);
+    assert_eq!(image_full.mime_type, Some("image/jpeg".to_string()));
+    assert_eq!(image_full.description, Some("Full description".to_string()));
+
+    // Test with no optional fields
+    let image_minimal = Image {
+      data: image_data.clone(),
+      pic_type: AudioImageType::CoverFront,
+      mime_type: None,
+      description: None,
+      ..Default::default()
+    };
+    // assert_eq!(image_minimal.data, image_data);
+    assert_eq!(image_minimal.mime_type, None);
+    assert_eq!(image_minimal.description, None);
+
+    // Test with only mime_type
+    let image_mime_only = Image {
+      data: image_data.clone(),
+      pic_type: AudioImageType::CoverFront,
+      mime_type: Some("image/png".to_string()),
+      description: None,
+      ..Default::default()
+    };
+    assert_eq!(image_mime_only.mime_type, Some("image/png".to_string()));
+    assert_eq!(image_mime_only.description, None);
+
+    // Test with only description
+    let image_desc_only = Image {
+      data: image_data.clone(),
+      pic_type: AudioImageType::CoverFront,
+      mime_type: None,
+      description: Some("Description only".to_string()),
+      ..Default::default()
+    };
+    assert_eq!(image_desc_only.mime_type, None);
+    assert_eq!(
+      image_desc_only.description,
+      Some("Description only".to_string())
+    );
+
+    // Test with empty data
+    let image_empty = Image {
+      data: vec![],
+      pic_type: AudioImageType::CoverFront,
+      mime_type: Some("image/jpeg".to_string()),
+      description: Some("Empty data".to_string()),
+      ..Default::default()
+    };
+    // assert_eq!(image_empty.data, vec![]);
+    assert_eq!(image_empty.mime_type, Some("image/jpeg".to_string()));
+    assert_eq!(image_empty.description, Some("Empty data".to_string()));
+
+    // Test with empty strings
+    let image_empty_strings = Image {
+      data: image_data,
+      pic_type: AudioImageType::CoverFront,
+      mime_type: Some("".to_string()),
+      description: Some("".to_string()),
+      ..Default::default()
+    };
+    assert_eq!(image_empty_strings.mime_type, Some("".to_string()));
+    assert_eq!(image_empty_strings.description, Some("".to_string()));
+  }
+
+  #[test]
+  fn test_audio_tags_string_edge_cases() {
+    // Test with empty strings
+    let tags_empty_strings = AudioTags {
+      title: Some("".to_string()),
+      artists: Some(vec!["".to_string()]),
+      album: Some("".to_string()),
+      year: Some(2024),
+      genre: Some("".to_string()),
+      track: None,
+      album_artists: Some(vec!["".to_string()]),
+      comment: Some("".to_string()),
+      disc: None,
+      image: None,
+      all_images: None,
+      ..Default::default()
+    };
+
+    assert_eq!(tags_empty_strings.title, Some("".to_string()));
+    assert_eq!(tags_empty_strings.artists, Some(vec!["".to_string()]));
+    assert_eq!(tags_empty_strings.album, Some("".to_string()));
+    assert_eq!(tags_empty_strings.genre, Some("".to_string()));
+    assert_eq!(tags_empty_strings.album_artists, Some(vec!["".to_string()]));
+    assert_eq!(tags_empty_strings.comment, Some("".to_string()));
+
+    // Test with very long strings
+    let long_string = "a".repeat(1000);
+    let tags_long_strings = AudioTags {
+      title: Some(long_string.clone()),
+      artists: Some(vec![long_string.clone()]),
+      album: Some(long_string.clone()),
+      year: Some(2024),
+      genre: Some(long_string.clone()),
+      track: None,
+      album_artists: Some(vec![long_string.clone()]),
+      comment: Some(long_string.clone()),
+      disc: None,
+      image: None,
+      all_images: None,
+      ..Default::default()
+    };
+
+    assert_eq!(tags_long_strings.title, Some(long_string.clone()));
+    assert_eq!(tags_long_strings.artists, Some(vec![long_string.clone()]));
+    assert_eq!(tags_long_strings.album, Some(long_string.clone()));
+    assert_eq!(tags_long_strings.genre, Some(long_string.clone()));
+    assert_eq!(
+      tags_long_strings.album_artists,
+      Some(vec![long_string.clone()])
+    );
+    assert_eq!(tags_long_strings.comment, Some(long_string));
+
+    // Test with special characters
+    let special_chars = "!@#$%^&*()_+-=[]{}|;':\",./<>?`~";
+    let tags_special = AudioTags {
+      title: Some(special_chars.to_string()),
+      artists: Some(vec![special_chars.to_string()]),
+      album: Some(special_chars.to_string()),
+      year: Some(2024),
+      genre: Some(special_chars.to_string()),
+      track: None,
+      album_artists: Some(vec![special_chars.to_string()]),
+      comment: Some(special_chars.to_string()),
+      disc: None,
+      image: None,
+      all_images: None,
+      ..Default::default()
+    };
+
+    assert_eq!(tags_special.title, Some(special_chars.to_string()));
+    assert_eq!(tags_special.artists, Some(vec![special_chars.to_string()]));
+    assert_eq!(tags_special.album, Some(special_chars.to_string()));
+    assert_eq!(tags_special.genre, Some(special_chars.to_string()));
+    assert_eq!(
+      tags_special.album_artists,
+      Some(vec![special_chars.to_string()])
+    );
+    assert_eq!(tags_special.comment, Some(special_chars.to_string()));
+
+    // Test with unicode characters
+    let unicode_string = "🎵 音乐 🎶 音楽 🎼";
+    let tags_unicode = AudioTags {
+      title: Some(unicode_string.to_string()),
+      artists: Some(vec![unicode_string.to_string()]),
+      album: Some(unicode_string.to_string()),
+      year: Some(2024),
+      genre: Some(unicode_string.to_string()),
+      track: None,
+      album_artists: Some(vec![unicode_string.to_string()]),
+      comment: Some(unicode_string.to_string()),
+      disc: None,
+      image: None,
+      all_images: None,
+      ..Default::default()
+    };
+
+    assert_eq!(tags_unicode.title, Some(unicode_string.to_string()));
+    assert_eq!(tags_unicode.artists, Some(vec![unicode_string.to_string()]));
+    assert_eq!(tags_unicode.album, Some(unicode_string.to_string()));
+    assert_eq!(tags_unicode.genre, Some(unicode_string.to_string()));
+    assert_eq!(
+      tags_unicode.album_artists,
+      Some(vec![unicode_string.to_string()])
+    );
+    assert_eq!(tags_unicode.comment, Some(unicode_string.to_string()));
+  }
+
+  #[test]
+  fn test_audio_tags_year_edge_cases() {
+    // Test with various years
+    let years = vec![1900, 1950, 2000, 2024, 2030, 9999];
+
+    for year in years {
+      let tags = AudioTags {
+        title: Some("Test Song".to_string()),
+        artists: None,
+        album: None,
+        year: Some(year),
+        genre: None,
+        track: None,
+        album_artists: None,
+        comment: None,
+        disc: None,
+        image: None,
+        all_images: None,
+        ..Default::default()
+      };
+      assert_eq!(tags.year, Some(year));
+    }
+
+    // Test with year 0 (edge case)
+    let tags_year_zero = AudioTags {
+      title: Some("Test Song".to_string()),
+      artists: None,
+      album: None,
+      year: Some(0),
+      genre: None,
+      track: None,
+      album_artists: None,
+      comment: None,
+      disc: None,
+      image: None,
+      all_images: None,
+      ..Default::default()
+    };
+    assert_eq!(tags_year_zero.year, Some(0));
+  }
+
+  #[test]
+  fn test_audio_tags_artists_edge_cases() {
+    // Test with single artist
+    let tags_single = AudioTags {
+      title: Some("Test Song".to_string()),
+      artists: Some(vec!["Single Artist".to_string()]),
+      album: None,
+      year: None,
+      genre: None,
+      track: None,
+      album_artists: None,
+      comment: None,
+      disc: None,
+      image: None,
+      all_images: None,
+      ..Default::default()
+    };
+    assert_eq!(tags_single.artists, Some(vec!["Single Artist".to_string()]));
+
+    // Test with many artists
+    let many_artists: Vec<String> = (1..=50).map(|i| format!("Artist {}", i)).collect();
+    let tags_many = AudioTags {
+      title: Some("Test Song".to_string()),
+      artists: Some(many_artists.clone()),
+      album: None,
+      year: None,
+      genre: None,
+      track: None,
+      album_artists: None,
+      comment: None,
+      disc: None,
+      image: None,
+      all_images: None,
+      ..Default::default()
+    };
+    assert_eq!(tags_many.artists, Some(many_artists));
+
+    // Test with duplicate artists
+    let tags_duplicates = AudioTags {
+      title: Some("Test Song".to_string()),
+      artists: Some(vec![
+        "Same Artist".to_string(),
+        "Same Artist".to_string(),
+        "Same Artist".to_string(),
+      ]),
+      album: None,
+      year: None,
+      genre: None,
+      track: None,
+      album_artists: None,
+      comment: None,
+      disc: None,
+      image: None,
+      all_images: None,
+      ..Default::default()
+    };
+    assert_eq!(
+      tags_duplicates.artists,
+      Some(vec![
+        "Same Artist".to_string(),
+        "Same Artist".to_string(),
+        "Same Artist".to_string(),
+      ])
+    );
+  }
+
+  #[test]
+  fn test_audio_tags_track_disc_edge_cases() {
+    // Test track with zero values
+    let tags_track_zero = AudioTags {
+      title: Some("Test Song".to_string()),
+      artists: None,
+      album: None,
+      year: None,
+      genre: None,
+      track: Some(Position {
+        no: Some(0),
+        of: Some(0),
+      }),
+      album_artists: None,
+      comment: None,
+      disc: Some(Position {
+        no: Some(0),
+        of: Some(0),
+      }),
+      image: None,
+      all_images: None,
+      ..Default::default()
+    };
+    assert_eq!(
+      tags_track_zero.track,
+      Some(Position {
+        no: Some(0),
+        of: Some(0)
+      })
+    );
+    assert_eq!(
+      tags_track_zero.disc,
+      Some(Position {
+        no: Some(0),
+        of: Some(0)
+      })
+    );
+
+    // Test track with large values
+    let tags_track_large = AudioTags {
+      title: Some("Test Song".to_string()),
+      artists: None,
+      album: None,
+      year: None,
+      genre: None,
+      track: Some(Position {
+        no: Some(999),
+        of: Some(1000),
+      }),
+      album_artists: None,
+      comment: None,
+      disc: Some(Position {
+        no: Some(99),
+        of: Some(100),
+      }),
+      image: None,
+      all_images: None,
+      ..Default::default()
+    };
+    assert_eq!(
+      tags_track_large.track,
+      Some(Position {
+        no: Some(999),
+        of: Some(1000)
+      })
+    );
+    assert_eq!(
+      tags_track_large.disc,
+      Some(Position {
+        no: Some(99),
+        of: Some(100)
+      })
+    );
+
+    // Test track where no > of (invalid but should be handled)
+    let tags_track_invalid = AudioTags {
+      title: Some("Test Song".to_string()),
+      artists: None,
+      album: None,
+      year: None,
+      genre: None,
+      track: Some(Position {
+        no: Some(10),
+        of: Some(5), // no > of
+      }),
+      album_artists: None,
+      comment: None,
+      disc: Some(Position {
+        no: Some(3),
+        of: Some(1), // no > of
+      }),
+      image: None,
+      all_images: None,
+      ..Default::default()
+    };
+    assert_eq!(
+      tags_track_invalid.track,
+      Some(Position {
+        no: Some(10),
+        of: Some(5)
+      })
+    );
+    assert_eq!(
+      tags_track_invalid.disc,
+      Some(Position {
+        no: Some(3),
+        of: Some(1)
+      })
+    );
+  }
+
+  #[test]
+  fn test_audio_tags_combination_scenarios() {
+    // Test realistic music metadata scenarios
+    let classical_tags = AudioTags {
+      title: Some("Symphony No. 9 in D minor, Op. 125".to_string()),
+      artists: Some(vec!["Ludwig van Beethoven".to_string()]),
+      album: Some("Beethoven: Complete Symphonies".to_string()),
+      year: Some(1824),
+      genre: Some("Classical".to_string()),
+      track: Some(Position {
+        no: Some(1),
+        of: Some(4),
+      }),
+      album_artists: Some(vec!["Berlin Philharmonic".to_string()]),
+      comment: Some("Conducted by Herbert von Karajan".to_string()),
+      disc: Some(Position {
+        no: Some(1),
+        of: Some(5),
+      }),
+      image: Some(Image {
+        data: create_test_image_data(),
+        pic_type: AudioImageType::CoverFront,
+        mime_type: Some("image/jpeg".to_string()),
+        description: Some("Album cover art".to_string()),
+        ..Default::default()
+      }),
+      all_images: None,
+      ..Default::default()
+    };
+
+    assert_eq!(
+      classical_tags.title,
+      Some("Symphony No. 9 in D minor, Op. 125".to_string())
+    );
+    assert_eq!(
+      classical_tags.artists,
+      Some(vec!["Ludwig van Beethoven".to_string()])
+    );
+    assert_eq!(classical_tags.year, Some(1824));
+    assert_eq!(classical_tags.genre, Some("Classical".to_string()));
+
+    // Test modern pop song scenario
+    let pop_tags = AudioTags {
+      title: Some("Shape of You".to_string()),
+      artists: Some(vec!["Ed Sheeran".to_string()]),
+      album: Some("÷ (Divide)".to_string()),
+      year: Some(2017),
+      genre: Some("Pop".to_string()),
+      track: Some(Position {
+        no: Some(3),
+        of: Some(16),
+      }),
+      album_artists: Some(vec!["Ed Sheeran".to_string()]),
+      comment: Some("Produced by Steve Mac".to_string()),
+      disc: None,
+      image: None,
+      all_images: None,
+      ..Default::default()
+    };
+
+    assert_eq!(pop_tags.title, Some("Shape of You".to_string()));
+    assert_eq!(pop_tags.artists, Some(vec!["Ed Sheeran".to_string()]));
+    assert_eq!(pop_tags.year, Some(2017));
+    assert_eq!(pop_tags.genre, Some("Pop".to_string()));
+
+    // Test compilation album scenario
+    let compilation_tags = AudioTags {
+      title: Some("Bohemian Rhapsody".to_string()),
+      artists: Some(vec!["Queen".to_string()]),
+      album: Some("Greatest Hits".to_string()),
+      year: Some(1975),
+      genre: Some("Rock".to_string()),
+      track: Some(Position {
+        no: Some(1),
+        of: Some(17),
+      }),
+      album_artists: Some(vec!["Various Artists".to_string()]),
+      comment: Some("From the album 'A Night at the Opera'".to_string()),
+      disc: Some(Position {
+        no: Some(1),
+        of: Some(2),
+      }),
+      image: Some(Image {
+        data: create_test_image_data(),
+        pic_type: AudioImageType::CoverFront,
+        mime_type: Some("image/png".to_string()),
+        description: Some("Compilation cover".to_string()),
+        ..Default::default()
+      }),
+      all_images: None,
+      ..Default::default()
+    };
+
+    assert_eq!(
+      compilation_tags.title,
+      Some("Bohemian Rhapsody".to_string())
+    );
+    assert_eq!(compilation_tags.artists, Some(vec!["Queen".to_string()]));
+    assert_eq!(
+      compilation_tags.album_artists,
+      Some(vec!["Various Artists".to_string()])
+    );
+    assert_eq!(compilation_tags.year, Some(1975));
+  }
+
+  #[test]
+  fn test_create_test_image_data() {
+    let image_data = create_test_image_data();
+
+    // Test that the image data is not empty
+    assert!(!image_data.is_empty());
+
+    // Test JPEG header structure
+    assert_eq!(image_data[0], 0xFF); // JPEG SOI marker
+    assert_eq!(image_data[1], 0xD8); // JPEG SOI marker
+    assert_eq!(image_data[2], 0xFF); // APP0 marker
+    assert_eq!(image_data[3], 0xE0); // APP0 marker
+
+    // Test JFIF identifier
+    assert_eq!(image_data[6], 0x4A); // 'J'
+    assert_eq!(image_data[7], 0x46); // 'F'
+    assert_eq!(image_data[8], 0x49); // 'I'
+    assert_eq!(image_data[9], 0x46); // 'F'
+
+    // Test JPEG EOI marker
+    let last_two = &image_data[image_data.len() - 2..];
+    assert_eq!(last_two[0], 0xFF); // JPEG EOI marker
+    assert_eq!(last_two[1], 0xD9); // JPEG EOI marker
+
+    // Test that multiple calls return the same data
+    let image_data2 = create_test_image_data();
+    assert_eq!(image_data, image_data2);
+  }
+
+  // Additional comprehensive tests for maximum coverage
+
+  #[test]
+  fn test_audio_tags_memory_ownership() {
+    // Test that data can be moved and cloned properly
+    let original_data = create_test_image_data();
+    let original_title = "Original Title".to_string();
+
+    let tags1 = AudioTags {
+      title: Some(original_title.clone()),
+      artists: Some(vec!["Artist 1".to_string(), "Artist 2".to_string()]),
+      album: Some("Album".to_string()),
+      year: Some(2024),
+      genre: Some("Genre".to_string()),
+      track: Some(Position {
+        no: Some(1),
+        of: Some(10),
+      }),
+      album_artists: Some(vec!["Album Artist".to_string()]),
+      comment: Some("Comment".to_string()),
+      disc: Some(Position {
+        no: Some(1),
+        of: Some(2),
+      }),
+      image: Some(Image {
+        data: original_data.clone(),
+        pic_type: AudioImageType::CoverFront,
+        mime_type: Some("image/jpeg".to_string()),
+        description: Some("Description".to_string()),
+        ..Default::default()
+      }),
+      all_images: None,
+      ..Default::default()
+    };
+
+    // Test cloning
+    let tags2 = AudioTags {
+      title: tags1.title.clone(),
+      artists: tags1.artists.clone(),
+      album: tags1.album.clone(),
+      year: tags1.year,
+      genre: tags1.genre.clone(),
+      track: match tags1.track {
+        Some(position) => Some(Position {
+          no: position.no.clone(),
+          of: position.of.clone(),
+        }),
+        None => None,
+      },
+      album_artists: tags1.album_artists.clone(),
+      comment: tags1.comment.clone(),
+      disc: match tags1.disc {
+        Some(position) => Some(Position {
+          no: position.no.clone(),
+          of: position.of.clone(),
+        }),
+        None => None,
+      },
+      image: match tags1.image {
+        Some(image) => Some(Image {
+          data: image.data.clone(),
+          pic_type: image.pic_type,
+          mime_type: image.mime_type.clone(),
+          description: image.description.clone(),
+          ..Default::default()
+        }),
+        None => None,
+      },
+      all_images: None,
+      ..Default::default()
+    };
+
+    // Both should have the same data
+    assert_eq!(tags1.title, tags2.title);
+    assert_eq!(tags1.artists, tags2.artists);
+    assert_eq!(tags1.album, tags2.album);
+    assert_eq!(tags1.year, tags2.year);
+    assert_eq!(tags1.genre, tags2.genre);
+    // assert_eq!(tags1.track, tags2.track);
+    assert_eq!(tags1.album_artists, tags2.album_artists);
+    assert_eq!(tags1.comment, tags2.comment);
+    // assert_eq!(tags1.disc, tags2.disc);
+    // assert_eq!(tags1.image, tags2.image);
+
+    // Test that original data is still accessible
+    assert_eq!(tags1.title, Some(original_title));
+    // assert_eq!(tags1.image.as_ref().unwrap().data, original_data);
+  }
+
+  #[test]
+  fn test_audio_tags_large_scale_data() {
+    // Test with very large amounts of data
+    let large_artists: Vec<String> = (1..=1000)
+      .map(|i| {
+        format!(
+          "Artist Number {} with a very long name that might cause issues",
+          i
+        )
+      })
+      .collect();
+
+    let large_album_artists: Vec<String> = (1..=500)
+      .map(|i| format!("Album Artist {} with extended name", i))
+      .collect();
+
+    let large_comment = "This is a very long comment that contains a lot of text. ".repeat(100);
+    let large_title = "A".repeat(1000);
+    let large_album = "B".repeat(1000);
+    let large_genre = "C".repeat(1000);
+
+    let large_tags = AudioTags {
+      title: Some(large_title.clone()),
+      artists: Some(large_artists.clone()),
+      album: Some(large_album.clone()),
+      year: Some(2024),
+      genre: Some(large_genre.clone()),
+      track: Some(Position {
+        no: Some(1),
+        of: Some(1000),
+      }),
+      album_artists: Some(large_album_artists.clone()),
+      comment: Some(large_comment.clone()),
+      disc: Some(Position {
+        no: Some(1),
+        of: Some(100),
+      }),
+      image: Some(Image {
+        data: create_test_image_data(),
+        pic_type: AudioImageType::CoverFront,
+        mime_type: Some("image/jpeg".to_string()),
+        description: Some("Large image description".to_string()),
+        ..Default::default()
+      }),
+      all_images: None,
+      ..Default::default()
+    };
+
+    // Verify all large data is stored correctly
+    assert_eq!(large_tags.title, Some(large_title));
+    assert_eq!(large_tags.artists, Some(large_artists));
+    assert_eq!(large_tags.album, Some(large_album));
+    assert_eq!(large_tags.genre, Some(large_genre));
+    assert_eq!(large_tags.album_artists, Some(large_album_artists));
+    assert_eq!(large_tags.comment, Some(large_comment));
+    assert_eq!(
+      large_tags.track,
+      Some(Position {
+        no: Some(1),
+        of: Some(1000),
+      })
+    );
+    assert_eq!(
+      large_tags.disc,
+      Some(Position {
+        no: Some(1),
+        of: Some(100),
+      })
+    );
+  }
+
+  #[test]
+  fn test_audio_tags_nested_optional_combinations() {
+    // Test all possible combinations of nested Option types
+    let combinations = vec![
+      // All None
+      (None, None, None, None, None, None, None, None, None, None),
+      // All Some
+      (
+        Some("Title".to_string()),
+        Some(vec!["Artist".to_string()]),
+        Some("Album".to_string()),
+        Some(2024),
+        Some("Genre".to_string()),
+        Some(Position {
+          no: Some(1),
+          of: Some(10),
+        }),
+        Some(vec!["Album Artist".to_string()]),
+        Some("Comment".to_string()),
+        Some(Position {
+          no: Some(1),
+          of: Some(2),
+        }),
+        Some(Image {
+          data: create_test_image_data(),
+          pic_type: AudioImageType::CoverFront,
+          mime_type: Some("image/jpeg".to_string()),
+          description: Some("Description".to_string()),
+          ..Default::default()
+        }),
+      ),
+      // Mixed combinations
+      (
+        Some("Title".to_string()),
+        None,
+        Some("Album".to_string()),
+        None,
+        Some("Genre".to_string()),
+        None,
+        Some(vec!["Album Artist".to_string()]),
+        None,
+        Some(Position {
+          no: Some(1),
+          of: Some(2),
+        }),
+        None,
+      ),
+      (
+        None,
+        Some(vec!["Artist".to_string()]),
+        None,
+        Some(2024),
+        None,
+        Some(Position {
+          no: Some(1),
+          of: Some(10),
+        }),
+        None,
+        Some("Comment".to_string()),
+        None,
+        Some(Image {
+          data: create_test_image_data(),
+          pic_type: AudioImageType::CoverFront,
+          mime_type: Some("image/png".to_string()),
+          description: Some("Description".to_string()),
+          ..Default::default()
+        }),
+      ),
+    ];
+
+    for (i, (title, artists, album, year, genre, track, album_artists, comment, disc, image)) in
+      combinations.iter().enumerate()
+    {
+      let tags = AudioTags {
+        title: title.clone(),
+        artists: artists.clone(),
+        album: album.clone(),
+        year: *year,
+        genre: genre.clone(),
+        track: match track {
+          Some(position) => Some(Position {
             no: position.no.clone(),
             of: position.of.clone(),
           }),
@@ -1676,268 +4594,996 @@ mod tests {
             no: position.no.clone(),
             of: position.of.clone(),
           }),
-          None => None,
-        },
-        image: match image {
-          Some(image) => Some(Image {
-            data: image.data.clone(),
-            pic_type: AudioImageType::CoverFront,
-            mime_type: image.mime_type.clone(),
-            description: image.description.clone(),
+          None => None,
+        },
+        image: match image {
+          Some(image) => Some(Image {
+            data: image.data.clone(),
+            pic_type: AudioImageType::CoverFront,
+            mime_type: image.mime_type.clone(),
+            description: image.description.clone(),
+            ..Default::default()
+          }),
+          None => None,
+        },
+        all_images: None,
+        ..Default::default()
+      };
+
+      // Verify each field matches the expected value
+      assert_eq!(tags.title, *title, "Title mismatch in combination {}", i);
+      assert_eq!(
+        tags.artists, *artists,
+        "Artists mismatch in combination {}",
+        i
+      );
+      assert_eq!(tags.album, *album, "Album mismatch in combination {}", i);
+      assert_eq!(tags.year, *year, "Year mismatch in combination {}", i);
+      assert_eq!(tags.genre, *genre, "Genre mismatch in combination {}", i);
+      assert_eq!(tags.track, *track, "Track mismatch in combination {}", i);
+      assert_eq!(
+        tags.album_artists, *album_artists,
+        "Album artists mismatch in combination {}",
+        i
+      );
+      assert_eq!(
+        tags.comment, *comment,
+        "Comment mismatch in combination {}",
+        i
+      );
+      assert_eq!(tags.disc, *disc, "Disc mismatch in combination {}", i);
+      // assert_eq!(tags.image, *image, "Image mismatch in combination {}", i);
+    }
+  }
+
+  #[test]
+  fn test_audio_tags_data_consistency() {
+    // Test that data remains consistent across operations
+    let original_tags = AudioTags {
+      title: Some("Consistent Title".to_string()),
+      artists: Some(vec!["Artist A".to_string(), "Artist B".to_string()]),
+      album: Some("Consistent Album".to_string()),
+      year: Some(2024),
+      genre: Some("Consistent Genre".to_string()),
+      track: Some(Position {
+        no: Some(5),
+        of: Some(12),
+      }),
+      album_artists: Some(vec!["Album Artist".to_string()]),
+      comment: Some("Consistent Comment".to_string()),
+      disc: Some(Position {
+        no: Some(2),
+        of: Some(3),
+      }),
+      image: Some(Image {
+        data: create_test_image_data(),
+        pic_type: AudioImageType::CoverFront,
+        mime_type: Some("image/jpeg".to_string()),
+        description: Some("Consistent Description".to_string()),
+        ..Default::default()
+      }),
+      all_images: None,
+      ..Default::default()
+    };
+
+    // Create multiple references and verify consistency
+    let tags_ref1 = &original_tags;
+    let tags_ref2 = &original_tags;
+
+    assert_eq!(tags_ref1.title, tags_ref2.title);
+    assert_eq!(tags_ref1.artists, tags_ref2.artists);
+    assert_eq!(tags_ref1.album, tags_ref2.album);
+    assert_eq!(tags_ref1.year, tags_ref2.year);
+    assert_eq!(tags_ref1.genre, tags_ref2.genre);
+    assert_eq!(tags_ref1.track, tags_ref2.track);
+    assert_eq!(tags_ref1.album_artists, tags_ref2.album_artists);
+    assert_eq!(tags_ref1.comment, tags_ref2.comment);
+    assert_eq!(tags_ref1.disc, tags_ref2.disc);
+    // assert_eq!(tags_ref1.image, tags_ref2.image);
+
+    // Test that nested data is also consistent
+    if let (Some(track1), Some(track2)) = (&tags_ref1.track, &tags_ref2.track) {
+      assert_eq!(track1.no, track2.no);
+      assert_eq!(track1.of, track2.of);
+    }
+
+    if let (Some(disc1), Some(disc2)) = (&tags_ref1.disc, &tags_ref2.disc) {
+      assert_eq!(disc1.no, disc2.no);
+      assert_eq!(disc1.of, disc2.of);
+    }
+
+    if let (Some(image1), Some(image2)) = (&tags_ref1.image, &tags_ref2.image) {
+      assert_eq!(image1.data.to_vec(), image2.data.to_vec());
+      assert_eq!(image1.mime_type, image2.mime_type);
+      assert_eq!(image1.description, image2.description);
+    }
+  }
+
+  #[test]
+  fn test_audio_tags_boundary_conditions() {
+    // Test boundary conditions for all numeric fields
+    let boundary_years = vec![0, 1, 1900, 2000, 2024, 9999, u32::MAX];
+
+    for year in boundary_years {
+      let tags = AudioTags {
+        title: Some("Boundary Test".to_string()),
+        artists: None,
+        album: None,
+        year: Some(year),
+        genre: None,
+        track: None,
+        album_artists: None,
+        comment: None,
+        disc: None,
+        image: None,
+        all_images: None,
+        ..Default::default()
+      };
+      assert_eq!(tags.year, Some(year));
+    }
+
+    // Test boundary conditions for track/disc numbers
+    let boundary_numbers = vec![0, 1, 10, 100, 1000, u32::MAX];
+
+    for no in &boundary_numbers {
+      for of in &boundary_numbers {
+        let tags = AudioTags {
+          title: Some("Boundary Test".to_string()),
+          artists: None,
+          album: None,
+          year: None,
+          genre: None,
+          track: Some(Position {
+            no: Some(*no),
+            of: Some(*of),
+          }),
+          album_artists: None,
+          comment: None,
+          disc: Some(Position {
+            no: Some(*no),
+            of: Some(*of),
           }),
-          None => None,
-        },
+          image: None,
+          all_images: None,
+          ..Default::default()
+        };
+        assert_eq!(
+          tags.track,
+          Some(Position {
+            no: Some(*no),
+            of: Some(*of),
+          })
+        );
+        assert_eq!(
+          tags.disc,
+          Some(Position {
+            no: Some(*no),
+            of: Some(*of),
+          })
+        );
+      }
+    }
+  }
+
+  #[test]
+  fn test_audio_tags_string_boundaries() {
+    // Test string boundary conditions
+    let empty_string = "".to_string();
+    let single_char = "a".to_string();
+    let max_reasonable_length = "a".repeat(10000);
+
+    let boundary_strings = vec![
+      empty_string.clone(),
+      single_char.clone(),
+      "Hello World".to_string(),
+      max_reasonable_length.clone(),
+    ];
+
+    for string in boundary_strings {
+      let tags = AudioTags {
+        title: Some(string.clone()),
+        artists: Some(vec![string.clone()]),
+        album: Some(string.clone()),
+        year: Some(2024),
+        genre: Some(string.clone()),
+        track: None,
+        album_artists: Some(vec![string.clone()]),
+        comment: Some(string.clone()),
+        disc: None,
+        image: Some(Image {
+          data: create_test_image_data(),
+          pic_type: AudioImageType::CoverFront,
+          mime_type: Some(string.clone()),
+          description: Some(string.clone()),
+          ..Default::default()
+        }),
+        all_images: None,
+        ..Default::default()
+      };
+
+      assert_eq!(tags.title, Some(string.clone()));
+      assert_eq!(tags.artists, Some(vec![string.clone()]));
+      assert_eq!(tags.album, Some(string.clone()));
+      assert_eq!(tags.genre, Some(string.clone()));
+      assert_eq!(tags.album_artists, Some(vec![string.clone()]));
+      assert_eq!(tags.comment, Some(string.clone()));
+      assert_eq!(tags.image.as_ref().unwrap().mime_type, Some(string.clone()));
+      assert_eq!(
+        tags.image.as_ref().unwrap().description,
+        Some(string.clone())
+      );
+    }
+  }
+
+  #[test]
+  fn test_audio_tags_vector_boundaries() {
+    // Test vector boundary conditions
+    let empty_vector: Vec<String> = vec![];
+    let single_item = vec!["Single Item".to_string()];
+    let large_vector: Vec<String> = (1..=1000).map(|i| format!("Item {}", i)).collect();
+
+    let boundary_vectors = vec![
+      empty_vector.clone(),
+      single_item.clone(),
+      vec!["Item 1".to_string(), "Item 2".to_string()],
+      large_vector.clone(),
+    ];
+
+    for vector in boundary_vectors {
+      let tags = AudioTags {
+        title: Some("Vector Test".to_string()),
+        artists: Some(vector.clone()),
+        album: None,
+        year: Some(2024),
+        genre: None,
+        track: None,
+        album_artists: Some(vector.clone()),
+        comment: None,
+        disc: None,
+        image: None,
         all_images: None,
+        ..Default::default()
       };
 
-      // Verify each field matches the expected value
-      assert_eq!(tags.title, *title, "Title mismatch in combination {}", i);
-      assert_eq!(
-        tags.artists, *artists,
-        "Artists mismatch in combination {}",
-        i
+      assert_eq!(tags.artists, Some(vector.clone()));
+      assert_eq!(tags.album_artists, Some(vector.clone()));
+    }
+  }
+
+  #[test]
+  fn test_audio_tags_equality_and_comparison() {
+    // Test that identical tags are equal
+    let tags1 = AudioTags {
+      title: Some("Same Title".to_string()),
+      artists: Some(vec!["Same Artist".to_string()]),
+      album: Some("Same Album".to_string()),
+      year: Some(2024),
+      genre: Some("Same Genre".to_string()),
+      track: Some(Position {
+        no: Some(1),
+        of: Some(10),
+      }),
+      album_artists: Some(vec!["Same Album Artist".to_string()]),
+      comment: Some("Same Comment".to_string()),
+      disc: Some(Position {
+        no: Some(1),
+        of: Some(2),
+      }),
+      image: Some(Image {
+        data: create_test_image_data(),
+        pic_type: AudioImageType::CoverFront,
+        mime_type: Some("image/jpeg".to_string()),
+        description: Some("Same Description".to_string()),
+        ..Default::default()
+      }),
+      all_images: None,
+      ..Default::default()
+    };
+
+    let tags2 = AudioTags {
+      title: Some("Same Title".to_string()),
+      artists: Some(vec!["Same Artist".to_string()]),
+      album: Some("Same Album".to_string()),
+      year: Some(2024),
+      genre: Some("Same Genre".to_string()),
+      track: Some(Position {
+        no: Some(1),
+        of: Some(10),
+      }),
+      album_artists: Some(vec!["Same Album Artist".to_string()]),
+      comment: Some("Same Comment".to_string()),
+      disc: Some(Position {
+        no: Some(1),
+        of: Some(2),
+      }),
+      image: Some(Image {
+        data: create_test_image_data(),
+        pic_type: AudioImageType::CoverFront,
+        mime_type: Some("image/jpeg".to_string()),
+        description: Some("Same Description".to_string()),
+        ..Default::default()
+      }),
+      all_images: None,
+      ..Default::default()
+    };
+
+    // Test individual field equality
+    assert_eq!(tags1.title, tags2.title);
+    assert_eq!(tags1.artists, tags2.artists);
+    assert_eq!(tags1.album, tags2.album);
+    assert_eq!(tags1.year, tags2.year);
+    assert_eq!(tags1.genre, tags2.genre);
+    assert_eq!(tags1.track, tags2.track);
+    assert_eq!(tags1.album_artists, tags2.album_artists);
+    assert_eq!(tags1.comment, tags2.comment);
+    assert_eq!(tags1.disc, tags2.disc);
+    // assert_eq!(tags1.image, tags2.image);
+
+    // Test that different tags are not equal
+    let tags3 = AudioTags {
+      title: Some("Different Title".to_string()),
+      artists: Some(vec!["Different Artist".to_string()]),
+      album: Some("Different Album".to_string()),
+      year: Some(2023),
+      genre: Some("Different Genre".to_string()),
+      track: Some(Position {
+        no: Some(2),
+        of: Some(20),
+      }),
+      album_artists: Some(vec!["Different Album Artist".to_string()]),
+      comment: Some("Different Comment".to_string()),
+      disc: Some(Position {
+        no: Some(2),
+        of: Some(4),
+      }),
+      image: Some(Image {
+        data: create_test_image_data(),
+        pic_type: AudioImageType::CoverFront,
+        mime_type: Some("image/png".to_string()),
+        description: Some("Different Description".to_string()),
+        ..Default::default()
+      }),
+      all_images: None,
+      ..Default::default()
+    };
+
+    assert_ne!(tags1.title, tags3.title);
+    assert_ne!(tags1.artists, tags3.artists);
+    assert_ne!(tags1.album, tags3.album);
+    assert_ne!(tags1.year, tags3.year);
+    assert_ne!(tags1.genre, tags3.genre);
+    assert_ne!(tags1.track, tags3.track);
+    assert_ne!(tags1.album_artists, tags3.album_artists);
+    assert_ne!(tags1.comment, tags3.comment);
+    assert_ne!(tags1.disc, tags3.disc);
+    // assert_ne!(tags1.image, tags3.image);
+  }
+
+  #[test]
+  fn test_audio_tags_pattern_matching() {
+    // Test pattern matching on the struct fields
+    let tags = AudioTags {
+      title: Some("Pattern Test".to_string()),
+      artists: Some(vec!["Artist 1".to_string(), "Artist 2".to_string()]),
+      album: Some("Pattern Album".to_string()),
+      year: Some(2024),
+      genre: Some("Pattern Genre".to_string()),
+      track: Some(Position {
+        no: Some(3),
+        of: Some(15),
+      }),
+      album_artists: Some(vec!["Pattern Album Artist".to_string()]),
+      comment: Some("Pattern Comment".to_string()),
+      disc: Some(Position {
+        no: Some(2),
+        of: Some(5),
+      }),
+      image: Some(Image {
+        data: create_test_image_data(),
+        pic_type: AudioImageType::CoverFront,
+        mime_type: Some("image/jpeg".to_string()),
+        description: Some("Pattern Description".to_string()),
+        ..Default::default()
+      }),
+      all_images: None,
+      ..Default::default()
+    };
+
+    // Test pattern matching on title
+    match &tags.title {
+      Some(title) => assert_eq!(title, "Pattern Test"),
+      None => panic!("Title should be Some"),
+    }
+
+    // Test pattern matching on artists
+    match &tags.artists {
+      Some(artists) => {
+        assert_eq!(artists.len(), 2);
+        assert_eq!(artists[0], "Artist 1");
+        assert_eq!(artists[1], "Artist 2");
+      }
+      None => panic!("Artists should be Some"),
+    }
+
+    // Test pattern matching on year
+    match tags.year {
+      Some(year) => assert_eq!(year, 2024),
+      None => panic!("Year should be Some"),
+    }
+
+    // Test pattern matching on track
+    match &tags.track {
+      Some(track) => {
+        assert_eq!(track.no, Some(3));
+        assert_eq!(track.of, Some(15));
+      }
+      None => panic!("Track should be Some"),
+    }
+
+    // Test pattern matching on image
+    match &tags.image {
+      Some(image) => {
+        assert_eq!(image.mime_type, Some("image/jpeg".to_string()));
+        assert_eq!(image.description, Some("Pattern Description".to_string()));
+        assert!(!image.data.is_empty());
+      }
+      None => panic!("Image should be Some"),
+    }
+  }
+
+  #[test]
+  fn test_audio_tags_iteration_and_collection() {
+    // Test that we can iterate over and collect data from the struct
+    let tags = AudioTags {
+      title: Some("Iteration Test".to_string()),
+      artists: Some(vec![
+        "Artist A".to_string(),
+        "Artist B".to_string(),
+        "Artist C".to_string(),
+      ]),
+      album: Some("Iteration Album".to_string()),
+      year: Some(2024),
+      genre: Some("Iteration Genre".to_string()),
+      track: Some(Position {
+        no: Some(1),
+        of: Some(3),
+      }),
+      album_artists: Some(vec![
+        "Album Artist A".to_string(),
+        "Album Artist B".to_string(),
+      ]),
+      comment: Some("Iteration Comment".to_string()),
+      disc: Some(Position {
+        no: Some(1),
+        of: Some(2),
+      }),
+      image: Some(Image {
+        data: create_test_image_data(),
+        pic_type: AudioImageType::CoverFront,
+        mime_type: Some("image/jpeg".to_string()),
+        description: Some("Iteration Description".to_string()),
+        ..Default::default()
+      }),
+      all_images: None,
+      ..Default::default()
+    };
+
+    // Test iteration over artists
+    if let Some(artists) = &tags.artists {
+      let artist_count = artists.len();
+      assert_eq!(artist_count, 3);
+
+      let collected_artists: Vec<&String> = artists.iter().collect();
+      assert_eq!(collected_artists.len(), 3);
+      assert_eq!(collected_artists[0], "Artist A");
+      assert_eq!(collected_artists[1], "Artist B");
+      assert_eq!(collected_artists[2], "Artist C");
+    }
+
+    // Test iteration over album artists
+    if let Some(album_artists) = &tags.album_artists {
+      let album_artist_count = album_artists.len();
+      assert_eq!(album_artist_count, 2);
+
+      let collected_album_artists: Vec<&String> = album_artists.iter().collect();
+      assert_eq!(collected_album_artists.len(), 2);
+      assert_eq!(collected_album_artists[0], "Album Artist A");
+      assert_eq!(collected_album_artists[1], "Album Artist B");
+    }
+
+    // Test iteration over image data
+    if let Some(image) = &tags.image {
+      let image_data_len = image.data.len();
+      assert!(image_data_len > 0);
+
+      let collected_data: Vec<&u8> = image.data.iter().collect();
+      assert_eq!(collected_data.len(), image_data_len);
+    }
+  }
+
+  #[test]
+  fn test_audio_tags_to_tag_and_from_tag_roundtrip() {
+    use lofty::tag::Tag;
+    use lofty::tag::TagType;
+
+    // Create a comprehensive test struct that mirrors AudioTags but uses standard Rust types
+    let original_test_tags = AudioTags {
+      title: Some("Roundtrip Test Song".to_string()),
+      artists: Some(vec![
+        "Primary Artist".to_string(),
+        "Secondary Artist".to_string(),
+      ]),
+      album: Some("Roundtrip Test Album".to_string()),
+      year: Some(2024),
+      genre: Some("Test Genre".to_string()),
+      track: Some(Position {
+        no: Some(5),
+        of: Some(12),
+      }),
+      album_artists: Some(vec!["Album Artist".to_string()]),
+      comment: Some("This is a test comment for roundtrip testing".to_string()),
+      disc: Some(Position {
+        no: Some(2),
+        of: Some(3),
+      }),
+      image: Some(Image {
+        data: create_test_image_data(),
+        pic_type: AudioImageType::CoverFront,
+        mime_type: Some("image/jpeg".to_string()),
+        description: Some("Test cover image for roundtrip".to_string()),
+        ..Default::default()
+      }),
+      all_images: None,
+      ..Default::default()
+    };
+
+    // Create a new empty tag
+    let mut tag = Tag::new(TagType::Id3v2);
+
+    // Manually populate the tag with our test data (simulating to_tag behavior)
+    if let Some(title) = &original_test_tags.title {
+      tag.insert_text(lofty::tag::ItemKey::TrackTitle, title.clone());
+    }
+
+    if let Some(artists) = &original_test_tags.artists {
+      if !artists.is_empty() {
+        tag.insert_text(lofty::tag::ItemKey::TrackArtist, artists[0].clone());
+        if artists.len() > 1 {
+          tag.insert_text(lofty::tag::ItemKey::TrackArtists, artists.join(", "));
+        }
+      }
+    }
+
+    if let Some(album) = &original_test_tags.album {
+      tag.insert_text(lofty::tag::ItemKey::AlbumTitle, album.clone());
+    }
+
+    if let Some(year) = &original_test_tags.year {
+      tag.insert_text(lofty::tag::ItemKey::Year, year.to_string());
+      tag.insert_text(lofty::tag::ItemKey::RecordingDate, year.to_string());
+    }
+
+    if let Some(genre) = &original_test_tags.genre {
+      tag.insert_text(lofty::tag::ItemKey::Genre, genre.clone());
+    }
+
+    if let Some(track) = &original_test_tags.track {
+      if let Some(no) = track.no {
+        tag.insert_text(lofty::tag::ItemKey::TrackNumber, no.to_string());
+      }
+      if let Some(of) = track.of {
+        tag.insert_text(lofty::tag::ItemKey::TrackTotal, of.to_string());
+      }
+    }
+
+    if let Some(disc) = &original_test_tags.disc {
+      if let Some(no) = disc.no {
+        tag.insert_text(lofty::tag::ItemKey::DiscNumber, no.to_string());
+      }
+      if let Some(of) = disc.of {
+        tag.insert_text(lofty::tag::ItemKey::DiscTotal, of.to_string());
+      }
+    }
+
+    if let Some(album_artists) = &original_test_tags.album_artists {
+      if !album_artists.is_empty() {
+        tag.insert_text(lofty::tag::ItemKey::AlbumArtist, album_artists[0].clone());
+      }
+    }
+
+    if let Some(comment) = &original_test_tags.comment {
+      tag.insert_text(lofty::tag::ItemKey::Comment, comment.clone());
+    }
+
+    if let Some(image) = &original_test_tags.image {
+      let mime_type = image
+        .mime_type
+        .as_deref()
+        .map(|s| MimeType::from_str(s))
+        .unwrap();
+
+      let picture = lofty::picture::Picture::new_unchecked(
+        lofty::picture::PictureType::CoverFront,
+        Some(mime_type),
+        image.description.clone(),
+        image.data.to_vec(),
       );
-      assert_eq!(tags.album, *album, "Album mismatch in combination {}", i);
-      assert_eq!(tags.year, *year, "Year mismatch in combination {}", i);
-      assert_eq!(tags.genre, *genre, "Genre mismatch in combination {}", i);
-      assert_eq!(tags.track, *track, "Track mismatch in combination {}", i);
+      tag.set_picture(0, picture);
+    }
+
+    // Now simulate from_tag behavior by reading from the tag
+    let converted_test_tags = AudioTags {
+      title: tag.title().map(|s| s.to_string()),
+      artists: tag.artist().map(|s| vec![s.to_string()]),
+      album: tag.album().map(|s| s.to_string()),
+      year: tag.year(),
+      genre: tag.genre().map(|s| s.to_string()),
+      track: match (tag.track(), tag.track_total()) {
+        (None, None) => None,
+        (no, of) => Some(Position { no, of }),
+      },
+      album_artists: tag.artist().map(|s| vec![s.to_string()]),
+      comment: tag.comment().map(|s| s.to_string()),
+      disc: match (tag.disk(), tag.disk_total()) {
+        (None, None) => None,
+        (no, of) => Some(Position { no, of }),
+      },
+      image: {
+        let mut image = None;
+        for picture in tag.pictures() {
+          if picture.pic_type() == lofty::picture::PictureType::CoverFront {
+            image = Some(Image {
+              data: picture.data().to_vec(),
+              pic_type: AudioImageType::CoverFront,
+              mime_type: picture.mime_type().map(|mime_type| mime_type.to_string()),
+              description: picture.description().map(|s| s.to_string()),
+              ..Default::default()
+            });
+            break;
+          }
+        }
+        image
+      },
+      all_images: None,
+      ..Default::default()
+    };
+
+    // Verify that all fields match the original data
+    assert_eq!(converted_test_tags.title, original_test_tags.title);
+    assert_eq!(converted_test_tags.album, original_test_tags.album);
+    assert_eq!(converted_test_tags.year, original_test_tags.year);
+    assert_eq!(converted_test_tags.genre, original_test_tags.genre);
+    assert_eq!(converted_test_tags.comment, original_test_tags.comment);
+
+    // Verify track information
+    assert_eq!(converted_test_tags.track, original_test_tags.track);
+    assert_eq!(converted_test_tags.disc, original_test_tags.disc);
+
+    // Verify artists (note: from_tag only gets the first artist, so we check that)
+    if let (Some(original_artists), Some(converted_artists)) =
+      (&original_test_tags.artists, &converted_test_tags.artists)
+    {
+      assert_eq!(converted_artists.len(), 1);
+      assert_eq!(converted_artists[0], original_artists[0]);
+    }
+
+    // Verify album artists (note: current implementation reads from same field as artists)
+    if let (Some(_original_album_artists), Some(converted_album_artists)) = (
+      &original_test_tags.album_artists,
+      &converted_test_tags.album_artists,
+    ) {
+      assert_eq!(converted_album_artists.len(), 1);
+      // Since both artists and album_artists read from tag.artist(), they should be the same
       assert_eq!(
-        tags.album_artists, *album_artists,
-        "Album artists mismatch in combination {}",
-        i
+        converted_album_artists[0],
+        original_test_tags.artists.as_ref().unwrap()[0]
       );
+    }
+
+    // Verify image data
+    if let (Some(original_image), Some(converted_image)) =
+      (&original_test_tags.image, &converted_test_tags.image)
+    {
+      // assert_eq!(converted_image.data, original_image.data);
+      assert_eq!(converted_image.mime_type, original_image.mime_type);
+      assert_eq!(converted_image.description, original_image.description);
+    }
+
+    // Test with minimal data (only some fields)
+    let minimal_test_tags = AudioTags {
+      title: Some("Minimal Test".to_string()),
+      artists: Some(vec!["Solo Artist".to_string()]),
+      album: None,
+      year: Some(2023),
+      genre: None,
+      track: None,
+      album_artists: None,
+      comment: None,
+      disc: None,
+      image: None,
+      all_images: None,
+      ..Default::default()
+    };
+
+    let mut minimal_tag = Tag::new(TagType::Id3v2);
+    if let Some(title) = &minimal_test_tags.title {
+      minimal_tag.insert_text(lofty::tag::ItemKey::TrackTitle, title.clone());
+    }
+    if let Some(artists) = &minimal_test_tags.artists {
+      if !artists.is_empty() {
+        minimal_tag.insert_text(lofty::tag::ItemKey::TrackArtist, artists[0].clone());
+      }
+    }
+    if let Some(year) = &minimal_test_tags.year {
+      minimal_tag.insert_text(lofty::tag::ItemKey::Year, year.to_string());
+      minimal_tag.insert_text(lofty::tag::ItemKey::RecordingDate, year.to_string());
+    }
+
+    let converted_minimal = AudioTags {
+      title: minimal_tag.title().map(|s| s.to_string()),
+      artists: minimal_tag.artist().map(|s| vec![s.to_string()]),
+      album: minimal_tag.album().map(|s| s.to_string()),
+      year: minimal_tag.year(),
+      genre: minimal_tag.genre().map(|s| s.to_string()),
+      track: None,
+      album_artists: minimal_tag.artist().map(|s| vec![s.to_string()]),
+      comment: minimal_tag.comment().map(|s| s.to_string()),
+      disc: None,
+      image: None,
+      all_images: None,
+      ..Default::default()
+    };
+
+    assert_eq!(converted_minimal.title, minimal_test_tags.title);
+    assert_eq!(converted_minimal.album, minimal_test_tags.album);
+    assert_eq!(converted_minimal.year, minimal_test_tags.year);
+    assert_eq!(converted_minimal.genre, minimal_test_tags.genre);
+    assert_eq!(converted_minimal.comment, minimal_test_tags.comment);
+    assert_eq!(converted_minimal.track, minimal_test_tags.track);
+    assert_eq!(converted_minimal.disc, minimal_test_tags.disc);
+    // assert_eq!(converted_minimal.image, minimal_test_tags.image);
+
+    // Verify artists for minimal case
+    if let (Some(original_artists), Some(converted_artists)) =
+      (&minimal_test_tags.artists, &converted_minimal.artists)
+    {
+      assert_eq!(converted_artists.len(), 1);
+      assert_eq!(converted_artists[0], original_artists[0]);
+    }
+
+    // Verify album artists for minimal case (same as artists due to current implementation)
+    if let Some(converted_album_artists) = &converted_minimal.album_artists {
+      assert_eq!(converted_album_artists.len(), 1);
       assert_eq!(
-        tags.comment, *comment,
-        "Comment mismatch in combination {}",
-        i
+        converted_album_artists[0],
+        minimal_test_tags.artists.as_ref().unwrap()[0]
       );
-      assert_eq!(tags.disc, *disc, "Disc mismatch in combination {}", i);
-      // assert_eq!(tags.image, *image, "Image mismatch in combination {}", i);
     }
+
+    // Test with empty data
+    let empty_test_tags = AudioTags::default();
+    let empty_tag = Tag::new(TagType::Id3v2);
+    // No data to add to empty tag
+
+    let converted_empty = AudioTags {
+      title: empty_tag.title().map(|s| s.to_string()),
+      artists: empty_tag.artist().map(|s| vec![s.to_string()]),
+      album: empty_tag.album().map(|s| s.to_string()),
+      year: empty_tag.year(),
+      genre: empty_tag.genre().map(|s| s.to_string()),
+      track: None,
+      album_artists: empty_tag.artist().map(|s| vec![s.to_string()]),
+      comment: empty_tag.comment().map(|s| s.to_string()),
+      disc: None,
+      image: None,
+      all_images: None,
+      ..Default::default()
+    };
+
+    assert_eq!(converted_empty.title, empty_test_tags.title);
+    assert_eq!(converted_empty.artists, empty_test_tags.artists);
+    assert_eq!(converted_empty.album, empty_test_tags.album);
+    assert_eq!(converted_empty.year, empty_test_tags.year);
+    assert_eq!(converted_empty.genre, empty_test_tags.genre);
+    assert_eq!(converted_empty.track, empty_test_tags.track);
+    assert_eq!(converted_empty.album_artists, empty_test_tags.album_artists);
+    assert_eq!(converted_empty.comment, empty_test_tags.comment);
+    assert_eq!(converted_empty.disc, empty_test_tags.disc);
+    // assert_eq!(converted_empty.image, empty_test_tags.image);
+  }
+
+  // Helper function to test roundtrip conversion
+  fn test_roundtrip_conversion(audio_tags: AudioTags) {
+    let mut tag = Tag::new(TagType::Id3v2);
+    audio_tags.to_tag(&mut tag);
+    let converted_audio_tags = AudioTags::from_tag(&tag);
+
+    assert_eq!(converted_audio_tags.title, audio_tags.title);
+
+    // Handle artists comparison - from_tag returns Some([]) for empty, but original might be None
+    match (&audio_tags.artists, &converted_audio_tags.artists) {
+      (None, Some(converted)) if converted.is_empty() => {
+        // This is expected - from_tag returns Some([]) for empty artists
+      }
+      (original, converted) => {
+        assert_eq!(converted, original);
+      }
+    }
+
+    // Handle album_artists comparison - same logic as artists
+    match (
+      &audio_tags.album_artists,
+      &converted_audio_tags.album_artists,
+    ) {
+      (None, Some(converted)) if converted.is_empty() => {
+        // This is expected - from_tag returns Some([]) for empty album_artists
+      }
+      (original, converted) => {
+        assert_eq!(converted, original);
+      }
+    }
+
+    assert_eq!(converted_audio_tags.album, audio_tags.album);
+    assert_eq!(converted_audio_tags.year, audio_tags.year);
+    assert_eq!(converted_audio_tags.genre, audio_tags.genre);
+    assert_eq!(converted_audio_tags.comment, audio_tags.comment);
+    assert_eq!(converted_audio_tags.disc, audio_tags.disc);
+    // assert_eq!(converted_audio_tags.image, audio_tags.image);
+  }
+
+  #[test]
+  fn test_audio_tags_to_tag_and_from_tag_roundtrip_with_empty_image() {
+    let audio_tags = AudioTags {
+      title: Some("Roundtrip Test Song".to_string()),
+      artists: Some(vec![
+        "Primary Artist".to_string(),
+        "Secondary Artist".to_string(),
+      ]),
+      album: Some("Roundtrip Test Album".to_string()),
+      year: Some(2024),
+      genre: Some("Test Genre".to_string()),
+      track: Some(Position {
+        no: Some(1),
+        of: Some(3),
+      }),
+      album_artists: Some(vec![
+        "Album Artist".to_string(),
+        "Secondary Album Artist".to_string(),
+      ]),
+      comment: Some("This is a test comment for roundtrip testing".to_string()),
+      disc: Some(Position {
+        no: Some(2),
+        of: Some(3),
+      }),
+      image: None,
+      all_images: None,
+      ..Default::default()
+    };
+
+    test_roundtrip_conversion(audio_tags);
   }
 
   #[test]
-  fn test_audio_tags_data_consistency() {
-    // Test that data remains consistent across operations
-    let original_tags = AudioTags {
-      title: Some("Consistent Title".to_string()),
-      artists: Some(vec!["Artist A".to_string(), "Artist B".to_string()]),
-      album: Some("Consistent Album".to_string()),
-      year: Some(2024),
-      genre: Some("Consistent Genre".to_string()),
+  fn test_roundtrip_with_image() {
+    let audio_tags = AudioTags {
+      title: Some("Song with Image".to_string()),
+      artists: Some(vec!["Artist with Image".to_string()]),
+      album: Some("Album with Image".to_string()),
+      year: Some(2023),
+      genre: Some("Test Genre".to_string()),
       track: Some(Position {
-        no: Some(5),
-        of: Some(12),
+        no: Some(2),
+        of: Some(5),
       }),
-      album_artists: Some(vec!["Album Artist".to_string()]),
-      comment: Some("Consistent Comment".to_string()),
+      album_artists: Some(vec!["Album Artist with Image".to_string()]),
+      comment: Some("Comment with image".to_string()),
       disc: Some(Position {
-        no: Some(2),
-        of: Some(3),
+        no: Some(1),
+        of: Some(2),
       }),
       image: Some(Image {
         data: create_test_image_data(),
         pic_type: AudioImageType::CoverFront,
         mime_type: Some("image/jpeg".to_string()),
-        description: Some("Consistent Description".to_string()),
+        description: Some("Test cover image".to_string()),
+        ..Default::default()
       }),
       all_images: None,
+      ..Default::default()
     };
 
-    // Create multiple references and verify consistency
-    let tags_ref1 = &original_tags;
-    let tags_ref2 = &original_tags;
-
-    assert_eq!(tags_ref1.title, tags_ref2.title);
-    assert_eq!(tags_ref1.artists, tags_ref2.artists);
-    assert_eq!(tags_ref1.album, tags_ref2.album);
-    assert_eq!(tags_ref1.year, tags_ref2.year);
-    assert_eq!(tags_ref1.genre, tags_ref2.genre);
-    assert_eq!(tags_ref1.track, tags_ref2.track);
-    assert_eq!(tags_ref1.album_artists, tags_ref2.album_artists);
-    assert_eq!(tags_ref1.comment, tags_ref2.comment);
-    assert_eq!(tags_ref1.disc, tags_ref2.disc);
-    // assert_eq!(tags_ref1.image, tags_ref2.image);
-
-    // Test that nested data is also consistent
-    if let (Some(track1), Some(track2)) = (&tags_ref1.track, &tags_ref2.track) {
-      assert_eq!(track1.no, track2.no);
-      assert_eq!(track1.of, track2.of);
-    }
-
-    if let (Some(disc1), Some(disc2)) = (&tags_ref1.disc, &tags_ref2.disc) {
-      assert_eq!(disc1.no, disc2.no);
-      assert_eq!(disc1.of, disc2.of);
-    }
-
-    if let (Some(image1), Some(image2)) = (&tags_ref1.image, &tags_ref2.image) {
-      assert_eq!(image1.data.to_vec(), image2.data.to_vec());
-      assert_eq!(image1.mime_type, image2.mime_type);
-      assert_eq!(image1.description, image2.description);
-    }
+    test_roundtrip_conversion(audio_tags);
   }
 
   #[test]
-  fn test_audio_tags_boundary_conditions() {
-    // Test boundary conditions for all numeric fields
-    let boundary_years = vec![0, 1, 1900, 2000, 2024, 9999, u32::MAX];
-
-    for year in boundary_years {
-      let tags = AudioTags {
-        title: Some("Boundary Test".to_string()),
-        artists: None,
-        album: None,
-        year: Some(year),
-        genre: None,
-        track: None,
-        album_artists: None,
-        comment: None,
-        disc: None,
-        image: None,
-        all_images: None,
-      };
-      assert_eq!(tags.year, Some(year));
-    }
+  fn test_roundtrip_minimal_data() {
+    let audio_tags = AudioTags {
+      title: Some("Minimal Song".to_string()),
+      artists: Some(vec!["Minimal Artist".to_string()]),
+      album: None,
+      year: Some(2022),
+      genre: None,
+      track: None,
+      album_artists: None,
+      comment: None,
+      disc: None,
+      image: None,
+      all_images: None,
+      ..Default::default()
+    };
 
-    // Test boundary conditions for track/disc numbers
-    let boundary_numbers = vec![0, 1, 10, 100, 1000, u32::MAX];
+    test_roundtrip_conversion(audio_tags);
+  }
 
-    for no in &boundary_numbers {
-      for of in &boundary_numbers {
-        let tags = AudioTags {
-          title: Some("Boundary Test".to_string()),
-          artists: None,
-          album: None,
-          year: None,
-          genre: None,
-          track: Some(Position {
-            no: Some(*no),
-            of: Some(*of),
-          }),
-          album_artists: None,
-          comment: None,
-          disc: Some(Position {
-            no: Some(*no),
-            of: Some(*of),
-          }),
-          image: None,
-          all_images: None,
-        };
-        assert_eq!(
-          tags.track,
-          Some(Position {
-            no: Some(*no),
-            of: Some(*of),
-          })
-        );
-        assert_eq!(
-          tags.disc,
-          Some(Position {
-            no: Some(*no),
-            of: Some(*of),
-          })
-        );
-      }
-    }
+  #[test]
+  fn test_roundtrip_empty_data() {
+    let audio_tags = AudioTags::default();
+    test_roundtrip_conversion(audio_tags);
   }
 
   #[test]
-  fn test_audio_tags_string_boundaries() {
-    // Test string boundary conditions
-    let empty_string = "".to_string();
-    let single_char = "a".to_string();
-    let max_reasonable_length = "a".repeat(10000);
+  fn test_base64_helper_functions() {
+    // Test with a simple base64 string (this is "Hello, World!" in base64)
+    let base64_string = "SGVsbG8sIFdvcmxkIQ==";
 
-    let boundary_strings = vec![
-      empty_string.clone(),
-      single_char.clone(),
-      "Hello World".to_string(),
-      max_reasonable_length.clone(),
-    ];
+    // Test load_file_from_base64
+    let result = load_file_from_base64(base64_string);
+    assert!(result.is_ok());
+    let data = result.unwrap();
+    assert_eq!(data, b"Hello, World!");
 
-    for string in boundary_strings {
-      let tags = AudioTags {
-        title: Some(string.clone()),
-        artists: Some(vec![string.clone()]),
-        album: Some(string.clone()),
-        year: Some(2024),
-        genre: Some(string.clone()),
-        track: None,
-        album_artists: Some(vec![string.clone()]),
-        comment: Some(string.clone()),
-        disc: None,
-        image: Some(Image {
-          data: create_test_image_data(),
-          pic_type: AudioImageType::CoverFront,
-          mime_type: Some(string.clone()),
-          description: Some(string.clone()),
-        }),
-        all_images: None,
-      };
+    // Test create_buffer_from_base64
+    let buffer_result = create_buffer_from_base64(base64_string);
+    assert!(buffer_result.is_ok());
+    let buffer = buffer_result.unwrap();
+    assert_eq!(buffer.to_vec(), b"Hello, World!");
 
-      assert_eq!(tags.title, Some(string.clone()));
-      assert_eq!(tags.artists, Some(vec![string.clone()]));
-      assert_eq!(tags.album, Some(string.clone()));
-      assert_eq!(tags.genre, Some(string.clone()));
-      assert_eq!(tags.album_artists, Some(vec![string.clone()]));
-      assert_eq!(tags.comment, Some(string.clone()));
-      assert_eq!(tags.image.as_ref().unwrap().mime_type, Some(string.clone()));
-      assert_eq!(
-        tags.image.as_ref().unwrap().description,
-        Some(string.clone())
-      );
-    }
+    // Test with invalid base64
+    let invalid_result = load_file_from_base64("invalid_base64!");
+    assert!(invalid_result.is_err());
+
+    // Test with empty string
+    let empty_result = load_file_from_base64("");
+    assert!(empty_result.is_ok());
+    assert!(empty_result.unwrap().is_empty());
   }
 
   #[test]
-  fn test_audio_tags_vector_boundaries() {
-    // Test vector boundary conditions
-    let empty_vector: Vec<String> = vec![];
-    let single_item = vec!["Single Item".to_string()];
-    let large_vector: Vec<String> = (1..=1000).map(|i| format!("Item {}", i)).collect();
-
-    let boundary_vectors = vec![
-      empty_vector.clone(),
-      single_item.clone(),
-      vec!["Item 1".to_string(), "Item 2".to_string()],
-      large_vector.clone(),
-    ];
+  fn test_base64_with_audio_file_example() {
+    // This is a minimal MP3 file header in base64 (just the first few bytes)
+    // In a real test, you would use a complete audio file
+    let mp3_header_base64 = "SUQzBAAAAAAAI1RTU0UAAAAPAAADTGF2ZjU4Ljc2LjEwMAAAAAAAAAAAAAAA/+M4wAAAAAAAAAAAAEluZm8AAAAPAAAAAwAAAbgA";
 
-    for vector in boundary_vectors {
-      let tags = AudioTags {
-        title: Some("Vector Test".to_string()),
-        artists: Some(vector.clone()),
-        album: None,
-        year: Some(2024),
-        genre: None,
-        track: None,
-        album_artists: Some(vector.clone()),
-        comment: None,
-        disc: None,
-        image: None,
-        all_images: None,
-      };
+    // Test that we can decode it
+    let result = create_buffer_from_base64(mp3_header_base64);
+    assert!(result.is_ok());
+    let buffer = result.unwrap();
 
-      assert_eq!(tags.artists, Some(vector.clone()));
-      assert_eq!(tags.album_artists, Some(vector.clone()));
-    }
+    // Verify it's not empty and has the expected MP3 header
+    assert!(!buffer.is_empty());
+    assert!(buffer.len() > 0);
+
+    // In a real scenario, you could use this buffer with read_tags_from_buffer
+    // let tags = read_tags_from_buffer(buffer).await?;
   }
 
+  // Additional comprehensive tests for maximum coverage
+
   #[test]
-  fn test_audio_tags_equality_and_comparison() {
-    // Test that identical tags are equal
-    let tags1 = AudioTags {
-      title: Some("Same Title".to_string()),
-      artists: Some(vec!["Same Artist".to_string()]),
-      album: Some("Same Album".to_string()),
+  fn test_audio_tags_serialization_consistency() {
+    // Test that data can be serialized and deserialized consistently
+    let original_tags = AudioTags {
+      title: Some("Serialization Test".to_string()),
+      artists: Some(vec!["Artist A".to_string(), "Artist B".to_string()]),
+      album: Some("Serialization Album".to_string()),
       year: Some(2024),
-      genre: Some("Same Genre".to_string()),
+      genre: Some("Test Genre".to_string()),
       track: Some(Position {
-        no: Some(1),
-        of: Some(10),
+        no: Some(3),
+        of: Some(8),
       }),
-      album_artists: Some(vec!["Same Album Artist".to_string()]),
-      comment: Some("Same Comment".to_string()),
+      album_artists: Some(vec!["Album Artist A".to_string()]),
+      comment: Some("Serialization comment".to_string()),
       disc: Some(Position {
         no: Some(1),
         of: Some(2),
@@ -1945,179 +5591,145 @@ mod tests {
       image: Some(Image {
         data: create_test_image_data(),
         pic_type: AudioImageType::CoverFront,
-        mime_type: Some("image/jpeg".to_string()),
-        description: Some("Same Description".to_string()),
+        mime_type: Some("image/png".to_string()),
+        description: Some("Serialization image".to_string()),
+        ..Default::default()
       }),
       all_images: None,
+      ..Default::default()
     };
 
-    let tags2 = AudioTags {
-      title: Some("Same Title".to_string()),
-      artists: Some(vec!["Same Artist".to_string()]),
-      album: Some("Same Album".to_string()),
+    // Test that we can create multiple references without data corruption
+    let ref1 = &original_tags;
+    let ref2 = &original_tags;
+    let ref3 = &original_tags;
+
+    // All references should be identical
+    assert_eq!(ref1.title, ref2.title);
+    assert_eq!(ref2.title, ref3.title);
+    assert_eq!(ref1.artists, ref2.artists);
+    assert_eq!(ref2.artists, ref3.artists);
+    assert_eq!(ref1.album, ref2.album);
+    assert_eq!(ref2.album, ref3.album);
+    assert_eq!(ref1.year, ref2.year);
+    assert_eq!(ref2.year, ref3.year);
+  }
+
+  #[test]
+  fn test_audio_tags_memory_efficiency() {
+    // Test memory efficiency with large data structures
+    let large_artists: Vec<String> = (1..=100)
+      .map(|i| {
+        format!(
+          "Artist {} with a very long name that might cause memory issues",
+          i
+        )
+      })
+      .collect();
+
+    let large_tags = AudioTags {
+      title: Some("Memory Test".to_string()),
+      artists: Some(large_artists.clone()),
+      album: Some("Memory Album".to_string()),
       year: Some(2024),
-      genre: Some("Same Genre".to_string()),
+      genre: Some("Test Genre".to_string()),
       track: Some(Position {
         no: Some(1),
-        of: Some(10),
+        of: Some(100),
       }),
-      album_artists: Some(vec!["Same Album Artist".to_string()]),
-      comment: Some("Same Comment".to_string()),
+      album_artists: Some(large_artists.clone()),
+      comment: Some("Memory test comment".repeat(100)),
       disc: Some(Position {
         no: Some(1),
-        of: Some(2),
+        of: Some(10),
       }),
       image: Some(Image {
         data: create_test_image_data(),
         pic_type: AudioImageType::CoverFront,
         mime_type: Some("image/jpeg".to_string()),
-        description: Some("Same Description".to_string()),
+        description: Some("Memory test image".to_string()),
+        ..Default::default()
       }),
       all_images: None,
+      ..Default::default()
     };
 
-    // Test individual field equality
-    assert_eq!(tags1.title, tags2.title);
-    assert_eq!(tags1.artists, tags2.artists);
-    assert_eq!(tags1.album, tags2.album);
-    assert_eq!(tags1.year, tags2.year);
-    assert_eq!(tags1.genre, tags2.genre);
-    assert_eq!(tags1.track, tags2.track);
-    assert_eq!(tags1.album_artists, tags2.album_artists);
-    assert_eq!(tags1.comment, tags2.comment);
-    assert_eq!(tags1.disc, tags2.disc);
-    // assert_eq!(tags1.image, tags2.image);
+    // Verify all data is stored correctly
+    assert_eq!(large_tags.artists, Some(large_artists.clone()));
+    assert_eq!(large_tags.album_artists, Some(large_artists));
+    assert!(large_tags.comment.as_ref().unwrap().len() > 1000);
+  }
 
-    // Test that different tags are not equal
-    let tags3 = AudioTags {
-      title: Some("Different Title".to_string()),
-      artists: Some(vec!["Different Artist".to_string()]),
-      album: Some("Different Album".to_string()),
-      year: Some(2023),
-      genre: Some("Different Genre".to_string()),
-      track: Some(Position {
-        no: Some(2),
-        of: Some(20),
-      }),
-      album_artists: Some(vec!["Different Album Artist".to_string()]),
-      comment: Some("Different Comment".to_string()),
-      disc: Some(Position {
-        no: Some(2),
-        of: Some(4),
-      }),
-      image: Some(Image {
-        data: create_test_image_data(),
-        pic_type: AudioImageType::CoverFront,
-        mime_type: Some("image/png".to_string()),
-        description: Some("Different Description".to_string()),
-      }),
+  #[test]
+  fn test_audio_tags_error_handling() {
+    // Test error handling with invalid data
+    let tags_with_invalid_year = AudioTags {
+      title: Some("Invalid Year Test".to_string()),
+      artists: None,
+      album: None,
+      year: Some(u32::MAX), // Maximum possible year
+      genre: None,
+      track: None,
+      album_artists: None,
+      comment: None,
+      disc: None,
+      image: None,
       all_images: None,
+      ..Default::default()
     };
 
-    assert_ne!(tags1.title, tags3.title);
-    assert_ne!(tags1.artists, tags3.artists);
-    assert_ne!(tags1.album, tags3.album);
-    assert_ne!(tags1.year, tags3.year);
-    assert_ne!(tags1.genre, tags3.genre);
-    assert_ne!(tags1.track, tags3.track);
-    assert_ne!(tags1.album_artists, tags3.album_artists);
-    assert_ne!(tags1.comment, tags3.comment);
-    assert_ne!(tags1.disc, tags3.disc);
-    // assert_ne!(tags1.image, tags3.image);
-  }
+    // Should handle extreme year values
+    assert_eq!(tags_with_invalid_year.year, Some(u32::MAX));
 
-  #[test]
-  fn test_audio_tags_pattern_matching() {
-    // Test pattern matching on the struct fields
-    let tags = AudioTags {
-      title: Some("Pattern Test".to_string()),
-      artists: Some(vec!["Artist 1".to_string(), "Artist 2".to_string()]),
-      album: Some("Pattern Album".to_string()),
-      year: Some(2024),
-      genre: Some("Pattern Genre".to_string()),
+    // Test with empty strings
+    let tags_with_empty_strings = AudioTags {
+      title: Some("".to_string()),
+      artists: Some(vec!["".to_string()]),
+      album: Some("".to_string()),
+      year: Some(0),
+      genre: Some("".to_string()),
       track: Some(Position {
-        no: Some(3),
-        of: Some(15),
+        no: Some(0),
+        of: Some(0),
       }),
-      album_artists: Some(vec!["Pattern Album Artist".to_string()]),
-      comment: Some("Pattern Comment".to_string()),
+      album_artists: Some(vec!["".to_string()]),
+      comment: Some("".to_string()),
       disc: Some(Position {
-        no: Some(2),
-        of: Some(5),
+        no: Some(0),
+        of: Some(0),
       }),
       image: Some(Image {
-        data: create_test_image_data(),
+        data: vec![],
         pic_type: AudioImageType::CoverFront,
-        mime_type: Some("image/jpeg".to_string()),
-        description: Some("Pattern Description".to_string()),
+        mime_type: Some("".to_string()),
+        description: Some("".to_string()),
+        ..Default::default()
       }),
       all_images: None,
+      ..Default::default()
     };
 
-    // Test pattern matching on title
-    match &tags.title {
-      Some(title) => assert_eq!(title, "Pattern Test"),
-      None => panic!("Title should be Some"),
-    }
-
-    // Test pattern matching on artists
-    match &tags.artists {
-      Some(artists) => {
-        assert_eq!(artists.len(), 2);
-        assert_eq!(artists[0], "Artist 1");
-        assert_eq!(artists[1], "Artist 2");
-      }
-      None => panic!("Artists should be Some"),
-    }
-
-    // Test pattern matching on year
-    match tags.year {
-      Some(year) => assert_eq!(year, 2024),
-      None => panic!("Year should be Some"),
-    }
-
-    // Test pattern matching on track
-    match &tags.track {
-      Some(track) => {
-        assert_eq!(track.no, Some(3));
-        assert_eq!(track.of, Some(15));
-      }
-      None => panic!("Track should be Some"),
-    }
-
-    // Test pattern matching on image
-    match &tags.image {
-      Some(image) => {
-        assert_eq!(image.mime_type, Some("image/jpeg".to_string()));
-        assert_eq!(image.description, Some("Pattern Description".to_string()));
-        assert!(!image.data.is_empty());
-      }
-      None => panic!("Image should be Some"),
-    }
+    // Should handle empty strings gracefully
+    assert_eq!(tags_with_empty_strings.title, Some("".to_string()));
+    assert_eq!(tags_with_empty_strings.artists, Some(vec!["".to_string()]));
+    assert_eq!(tags_with_empty_strings.year, Some(0));
   }
 
   #[test]
-  fn test_audio_tags_iteration_and_collection() {
-    // Test that we can iterate over and collect data from the struct
-    let tags = AudioTags {
-      title: Some("Iteration Test".to_string()),
-      artists: Some(vec![
-        "Artist A".to_string(),
-        "Artist B".to_string(),
-        "Artist C".to_string(),
-      ]),
-      album: Some("Iteration Album".to_string()),
+  fn test_audio_tags_unicode_handling() {
+    // Test Unicode character handling
+    let unicode_tags = AudioTags {
+      title: Some("🎵 音乐测试 🎶".to_string()),
+      artists: Some(vec!["艺术家".to_string(), "🎤 歌手".to_string()]),
+      album: Some("专辑名称 🎼".to_string()),
       year: Some(2024),
-      genre: Some("Iteration Genre".to_string()),
+      genre: Some("音乐类型 🎸".to_string()),
       track: Some(Position {
         no: Some(1),
-        of: Some(3),
+        of: Some(10),
       }),
-      album_artists: Some(vec![
-        "Album Artist A".to_string(),
-        "Album Artist B".to_string(),
-      ]),
-      comment: Some("Iteration Comment".to_string()),
+      album_artists: Some(vec!["专辑艺术家 🎹".to_string()]),
+      comment: Some("评论内容 🎺".to_string()),
       disc: Some(Position {
         no: Some(1),
         of: Some(2),
@@ -2126,412 +5738,614 @@ mod tests {
         data: create_test_image_data(),
         pic_type: AudioImageType::CoverFront,
         mime_type: Some("image/jpeg".to_string()),
-        description: Some("Iteration Description".to_string()),
+        description: Some("图片描述 🖼️".to_string()),
+        ..Default::default()
       }),
       all_images: None,
+      ..Default::default()
     };
 
-    // Test iteration over artists
-    if let Some(artists) = &tags.artists {
-      let artist_count = artists.len();
-      assert_eq!(artist_count, 3);
-
-      let collected_artists: Vec<&String> = artists.iter().collect();
-      assert_eq!(collected_artists.len(), 3);
-      assert_eq!(collected_artists[0], "Artist A");
-      assert_eq!(collected_artists[1], "Artist B");
-      assert_eq!(collected_artists[2], "Artist C");
-    }
-
-    // Test iteration over album artists
-    if let Some(album_artists) = &tags.album_artists {
-      let album_artist_count = album_artists.len();
-      assert_eq!(album_artist_count, 2);
+    // Verify Unicode is handled correctly
+    assert_eq!(unicode_tags.title, Some("🎵 音乐测试 🎶".to_string()));
+    assert_eq!(
+      unicode_tags.artists,
+      Some(vec!["艺术家".to_string(), "🎤 歌手".to_string()])
+    );
+    assert_eq!(unicode_tags.album, Some("专辑名称 🎼".to_string()));
+    assert_eq!(unicode_tags.genre, Some("音乐类型 🎸".to_string()));
+    assert_eq!(
+      unicode_tags.album_artists,
+      Some(vec!["专辑艺术家 🎹".to_string()])
+    );
+    assert_eq!(unicode_tags.comment, Some("评论内容 🎺".to_string()));
+    assert_eq!(
+      unicode_tags.image.as_ref().unwrap().description,
+      Some("图片描述 🖼️".to_string())
+    );
+  }
 
-      let collected_album_artists: Vec<&String> = album_artists.iter().collect();
-      assert_eq!(collected_album_artists.len(), 2);
-      assert_eq!(collected_album_artists[0], "Album Artist A");
-      assert_eq!(collected_album_artists[1], "Album Artist B");
-    }
+  #[test]
+  fn test_audio_tags_ordering_and_sorting() {
+    // Test that we can sort and order data
+    let mut artists = vec![
+      "Charlie".to_string(),
+      "Alice".to_string(),
+      "Bob".to_string(),
+    ];
+    artists.sort();
 
-    // Test iteration over image data
-    if let Some(image) = &tags.image {
-      let image_data_len = image.data.len();
-      assert!(image_data_len > 0);
+    let tags = AudioTags {
+      title: Some("Sorting Test".to_string()),
+      artists: Some(artists.clone()),
+      album: Some("Sorting Album".to_string()),
+      year: Some(2024),
+      genre: Some("Test Genre".to_string()),
+      track: Some(Position {
+        no: Some(1),
+        of: Some(3),
+      }),
+      album_artists: Some(artists.clone()),
+      comment: Some("Sorting comment".to_string()),
+      disc: Some(Position {
+        no: Some(1),
+        of: Some(1),
+      }),
+      image: None,
+      all_images: None,
+      ..Default::default()
+    };
 
-      let collected_data: Vec<&u8> = image.data.iter().collect();
-      assert_eq!(collected_data.len(), image_data_len);
-    }
+    // Verify sorted order
+    assert_eq!(
+      tags.artists,
+      Some(vec![
+        "Alice".to_string(),
+        "Bob".to_string(),
+        "Charlie".to_string()
+      ])
+    );
+    assert_eq!(
+      tags.album_artists,
+      Some(vec![
+        "Alice".to_string(),
+        "Bob".to_string(),
+        "Charlie".to_string()
+      ])
+    );
   }
 
   #[test]
-  fn test_audio_tags_to_tag_and_from_tag_roundtrip() {
-    use lofty::tag::Tag;
-    use lofty::tag::TagType;
-
-    // Create a comprehensive test struct that mirrors AudioTags but uses standard Rust types
-    let original_test_tags = AudioTags {
-      title: Some("Roundtrip Test Song".to_string()),
-      artists: Some(vec![
-        "Primary Artist".to_string(),
-        "Secondary Artist".to_string(),
-      ]),
-      album: Some("Roundtrip Test Album".to_string()),
+  fn test_audio_tags_cloning_and_copying() {
+    // Test cloning behavior
+    let original_tags = AudioTags {
+      title: Some("Cloning Test".to_string()),
+      artists: Some(vec!["Original Artist".to_string()]),
+      album: Some("Original Album".to_string()),
       year: Some(2024),
-      genre: Some("Test Genre".to_string()),
+      genre: Some("Original Genre".to_string()),
       track: Some(Position {
-        no: Some(5),
-        of: Some(12),
+        no: Some(1),
+        of: Some(5),
       }),
-      album_artists: Some(vec!["Album Artist".to_string()]),
-      comment: Some("This is a test comment for roundtrip testing".to_string()),
+      album_artists: Some(vec!["Original Album Artist".to_string()]),
+      comment: Some("Original comment".to_string()),
       disc: Some(Position {
-        no: Some(2),
-        of: Some(3),
+        no: Some(1),
+        of: Some(2),
       }),
       image: Some(Image {
         data: create_test_image_data(),
         pic_type: AudioImageType::CoverFront,
         mime_type: Some("image/jpeg".to_string()),
-        description: Some("Test cover image for roundtrip".to_string()),
+        description: Some("Original image".to_string()),
+        ..Default::default()
       }),
       all_images: None,
+      ..Default::default()
     };
 
-    // Create a new empty tag
-    let mut tag = Tag::new(TagType::Id3v2);
-
-    // Manually populate the tag with our test data (simulating to_tag behavior)
-    if let Some(title) = &original_test_tags.title {
-      tag.insert_text(lofty::tag::ItemKey::TrackTitle, title.clone());
-    }
-
-    if let Some(artists) = &original_test_tags.artists {
-      if !artists.is_empty() {
-        tag.insert_text(lofty::tag::ItemKey::TrackArtist, artists[0].clone());
-        if artists.len() > 1 {
-          tag.insert_text(lofty::tag::ItemKey::TrackArtists, artists.join(", "));
-        }
-      }
-    }
-
-    if let Some(album) = &original_test_tags.album {
-      tag.insert_text(lofty::tag::ItemKey::AlbumTitle, album.clone());
-    }
-
-    if let Some(year) = &original_test_tags.year {
-      tag.insert_text(lofty::tag::ItemKey::Year, year.to_string());
-      tag.insert_text(lofty::tag::ItemKey::RecordingDate, year.to_string());
-    }
-
-    if let Some(genre) = &original_test_tags.genre {
-      tag.insert_text(lofty::tag::ItemKey::Genre, genre.clone());
-    }
-
-    if let Some(track) = &original_test_tags.track {
-      if let Some(no) = track.no {
-        tag.insert_text(lofty::tag::ItemKey::TrackNumber, no.to_string());
-      }
-      if let Some(of) = track.of {
-        tag.insert_text(lofty::tag::ItemKey::TrackTotal, of.to_string());
-      }
-    }
-
-    if let Some(disc) = &original_test_tags.disc {
-      if let Some(no) = disc.no {
-        tag.insert_text(lofty::tag::ItemKey::DiscNumber, no.to_string());
-      }
-      if let Some(of) = disc.of {
-        tag.insert_text(lofty::tag::ItemKey::DiscTotal, of.to_string());
-      }
-    }
-
-    if let Some(album_artists) = &original_test_tags.album_artists {
-      if !album_artists.is_empty() {
-        tag.insert_text(lofty::tag::ItemKey::AlbumArtist, album_artists[0].clone());
-      }
-    }
-
-    if let Some(comment) = &original_test_tags.comment {
-      tag.insert_text(lofty::tag::ItemKey::Comment, comment.clone());
-    }
-
-    if let Some(image) = &original_test_tags.image {
-      let mime_type = image
-        .mime_type
-        .as_deref()
-        .map(|s| MimeType::from_str(s))
-        .unwrap();
-
-      let picture = lofty::picture::Picture::new_unchecked(
-        lofty::picture::PictureType::CoverFront,
-        Some(mime_type),
-        image.description.clone(),
-        image.data.to_vec(),
-      );
-      tag.set_picture(0, picture);
-    }
-
-    // Now simulate from_tag behavior by reading from the tag
-    let converted_test_tags = AudioTags {
-      title: tag.title().map(|s| s.to_string()),
-      artists: tag.artist().map(|s| vec![s.to_string()]),
-      album: tag.album().map(|s| s.to_string()),
-      year: tag.year(),
-      genre: tag.genre().map(|s| s.to_string()),
-      track: match (tag.track(), tag.track_total()) {
-        (None, None) => None,
-        (no, of) => Some(Position { no, of }),
-      },
-      album_artists: tag.artist().map(|s| vec![s.to_string()]),
-      comment: tag.comment().map(|s| s.to_string()),
-      disc: match (tag.disk(), tag.disk_total()) {
-        (None, None) => None,
-        (no, of) => Some(Position { no, of }),
-      },
-      image: {
-        let mut image = None;
-        for picture in tag.pictures() {
-          if picture.pic_type() == lofty::picture::PictureType::CoverFront {
-            image = Some(Image {
-              data: picture.data().to_vec(),
-              pic_type: AudioImageType::CoverFront,
-              mime_type: picture.mime_type().map(|mime_type| mime_type.to_string()),
-              description: picture.description().map(|s| s.to_string()),
-            });
-            break;
-          }
-        }
-        image
+    // Test that we can create multiple independent copies
+    let copy1 = AudioTags {
+      title: original_tags.title.clone(),
+      artists: original_tags.artists.clone(),
+      album: original_tags.album.clone(),
+      year: original_tags.year,
+      genre: original_tags.genre.clone(),
+      track: original_tags.clone().track.map(|position| Position {
+        no: position.no,
+        of: position.of,
+      }),
+      album_artists: original_tags.album_artists.clone(),
+      comment: original_tags.comment.clone(),
+      disc: original_tags.clone().disc.map(|position| Position {
+        no: position.no,
+        of: position.of,
+      }),
+      image: match original_tags.image {
+        Some(image) => Some(Image {
+          data: image.data.clone(),
+          pic_type: AudioImageType::CoverFront,
+          mime_type: image.mime_type.clone(),
+          description: image.description.clone(),
+          ..Default::default()
+        }),
+        None => None,
       },
       all_images: None,
+      ..Default::default()
     };
 
-    // Verify that all fields match the original data
-    assert_eq!(converted_test_tags.title, original_test_tags.title);
-    assert_eq!(converted_test_tags.album, original_test_tags.album);
-    assert_eq!(converted_test_tags.year, original_test_tags.year);
-    assert_eq!(converted_test_tags.genre, original_test_tags.genre);
-    assert_eq!(converted_test_tags.comment, original_test_tags.comment);
-
-    // Verify track information
-    assert_eq!(converted_test_tags.track, original_test_tags.track);
-    assert_eq!(converted_test_tags.disc, original_test_tags.disc);
-
-    // Verify artists (note: from_tag only gets the first artist, so we check that)
-    if let (Some(original_artists), Some(converted_artists)) =
-      (&original_test_tags.artists, &converted_test_tags.artists)
-    {
-      assert_eq!(converted_artists.len(), 1);
-      assert_eq!(converted_artists[0], original_artists[0]);
-    }
-
-    // Verify album artists (note: current implementation reads from same field as artists)
-    if let (Some(_original_album_artists), Some(converted_album_artists)) = (
-      &original_test_tags.album_artists,
-      &converted_test_tags.album_artists,
-    ) {
-      assert_eq!(converted_album_artists.len(), 1);
-      // Since both artists and album_artists read from tag.artist(), they should be the same
-      assert_eq!(
-        converted_album_artists[0],
-        original_test_tags.artists.as_ref().unwrap()[0]
-      );
-    }
+    // Verify copies are identical
+    assert_eq!(original_tags.title, copy1.title);
+    assert_eq!(original_tags.artists, copy1.artists);
+    assert_eq!(original_tags.album, copy1.album);
+    assert_eq!(original_tags.year, copy1.year);
+    assert_eq!(original_tags.genre, copy1.genre);
+    assert_eq!(original_tags.track, copy1.track);
+    assert_eq!(original_tags.album_artists, copy1.album_artists);
+    assert_eq!(original_tags.comment, copy1.comment);
+    assert_eq!(original_tags.disc, copy1.disc);
+  }
 
-    // Verify image data
-    if let (Some(original_image), Some(converted_image)) =
-      (&original_test_tags.image, &converted_test_tags.image)
-    {
-      // assert_eq!(converted_image.data, original_image.data);
-      assert_eq!(converted_image.mime_type, original_image.mime_type);
-      assert_eq!(converted_image.description, original_image.description);
-    }
+  #[test]
+  fn test_audio_tags_hash_and_equality() {
+    // Test that identical tags produce the same hash and are equal
+    let tags1 = AudioTags {
+      title: Some("Hash Test".to_string()),
+      artists: Some(vec!["Hash Artist".to_string()]),
+      album: Some("Hash Album".to_string()),
+      year: Some(2024),
+      genre: Some("Hash Genre".to_string()),
+      track: Some(Position {
+        no: Some(1),
+        of: Some(3),
+      }),
+      album_artists: Some(vec!["Hash Album Artist".to_string()]),
+      comment: Some("Hash comment".to_string()),
+      disc: Some(Position {
+        no: Some(1),
+        of: Some(2),
+      }),
+      image: Some(Image {
+        data: create_test_image_data(),
+        pic_type: AudioImageType::CoverFront,
+        mime_type: Some("image/jpeg".to_string()),
+        description: Some("Hash image".to_string()),
+        ..Default::default()
+      }),
+      all_images: None,
+      ..Default::default()
+    };
 
-    // Test with minimal data (only some fields)
-    let minimal_test_tags = AudioTags {
-      title: Some("Minimal Test".to_string()),
-      artists: Some(vec!["Solo Artist".to_string()]),
-      album: None,
-      year: Some(2023),
-      genre: None,
-      track: None,
-      album_artists: None,
-      comment: None,
-      disc: None,
-      image: None,
+    let tags2 = AudioTags {
+      title: Some("Hash Test".to_string()),
+      artists: Some(vec!["Hash Artist".to_string()]),
+      album: Some("Hash Album".to_string()),
+      year: Some(2024),
+      genre: Some("Hash Genre".to_string()),
+      track: Some(Position {
+        no: Some(1),
+        of: Some(3),
+      }),
+      album_artists: Some(vec!["Hash Album Artist".to_string()]),
+      comment: Some("Hash comment".to_string()),
+      disc: Some(Position {
+        no: Some(1),
+        of: Some(2),
+      }),
+      image: Some(Image {
+        data: create_test_image_data(),
+        pic_type: AudioImageType::CoverFront,
+        mime_type: Some("image/jpeg".to_string()),
+        description: Some("Hash image".to_string()),
+        ..Default::default()
+      }),
       all_images: None,
+      ..Default::default()
     };
 
-    let mut minimal_tag = Tag::new(TagType::Id3v2);
-    if let Some(title) = &minimal_test_tags.title {
-      minimal_tag.insert_text(lofty::tag::ItemKey::TrackTitle, title.clone());
-    }
-    if let Some(artists) = &minimal_test_tags.artists {
-      if !artists.is_empty() {
-        minimal_tag.insert_text(lofty::tag::ItemKey::TrackArtist, artists[0].clone());
-      }
-    }
-    if let Some(year) = &minimal_test_tags.year {
-      minimal_tag.insert_text(lofty::tag::ItemKey::Year, year.to_string());
-      minimal_tag.insert_text(lofty::tag::ItemKey::RecordingDate, year.to_string());
-    }
+    // Test equality
+    assert_eq!(tags1.title, tags2.title);
+    assert_eq!(tags1.artists, tags2.artists);
+    assert_eq!(tags1.album, tags2.album);
+    assert_eq!(tags1.year, tags2.year);
+    assert_eq!(tags1.genre, tags2.genre);
+    assert_eq!(tags1.track, tags2.track);
+    assert_eq!(tags1.album_artists, tags2.album_artists);
+    assert_eq!(tags1.comment, tags2.comment);
+    assert_eq!(tags1.disc, tags2.disc);
+  }
 
-    let converted_minimal = AudioTags {
-      title: minimal_tag.title().map(|s| s.to_string()),
-      artists: minimal_tag.artist().map(|s| vec![s.to_string()]),
-      album: minimal_tag.album().map(|s| s.to_string()),
-      year: minimal_tag.year(),
-      genre: minimal_tag.genre().map(|s| s.to_string()),
-      track: None,
-      album_artists: minimal_tag.artist().map(|s| vec![s.to_string()]),
-      comment: minimal_tag.comment().map(|s| s.to_string()),
-      disc: None,
-      image: None,
+  #[test]
+  fn test_audio_tags_validation() {
+    // Test data validation
+    let valid_tags = AudioTags {
+      title: Some("Valid Title".to_string()),
+      artists: Some(vec!["Valid Artist".to_string()]),
+      album: Some("Valid Album".to_string()),
+      year: Some(2024),
+      genre: Some("Valid Genre".to_string()),
+      track: Some(Position {
+        no: Some(1),
+        of: Some(10),
+      }),
+      album_artists: Some(vec!["Valid Album Artist".to_string()]),
+      comment: Some("Valid comment".to_string()),
+      disc: Some(Position {
+        no: Some(1),
+        of: Some(2),
+      }),
+      image: Some(Image {
+        data: create_test_image_data(),
+        pic_type: AudioImageType::CoverFront,
+        mime_type: Some("image/jpeg".to_string()),
+        description: Some("Valid image".to_string()),
+        ..Default::default()
+      }),
       all_images: None,
+      ..Default::default()
     };
 
-    assert_eq!(converted_minimal.title, minimal_test_tags.title);
-    assert_eq!(converted_minimal.album, minimal_test_tags.album);
-    assert_eq!(converted_minimal.year, minimal_test_tags.year);
-    assert_eq!(converted_minimal.genre, minimal_test_tags.genre);
-    assert_eq!(converted_minimal.comment, minimal_test_tags.comment);
-    assert_eq!(converted_minimal.track, minimal_test_tags.track);
-    assert_eq!(converted_minimal.disc, minimal_test_tags.disc);
-    // assert_eq!(converted_minimal.image, minimal_test_tags.image);
+    // Test that valid data is accepted
+    assert!(valid_tags.title.is_some());
+    assert!(valid_tags.artists.is_some());
+    assert!(valid_tags.album.is_some());
+    assert!(valid_tags.year.is_some());
+    assert!(valid_tags.genre.is_some());
+    assert!(valid_tags.track.is_some());
+    assert!(valid_tags.album_artists.is_some());
+    assert!(valid_tags.comment.is_some());
+    assert!(valid_tags.disc.is_some());
+    assert!(valid_tags.image.is_some());
 
-    // Verify artists for minimal case
-    if let (Some(original_artists), Some(converted_artists)) =
-      (&minimal_test_tags.artists, &converted_minimal.artists)
-    {
-      assert_eq!(converted_artists.len(), 1);
-      assert_eq!(converted_artists[0], original_artists[0]);
+    // Test with None values
+    let empty_tags = AudioTags::default();
+    assert!(empty_tags.title.is_none());
+    assert!(empty_tags.artists.is_none());
+    assert!(empty_tags.album.is_none());
+    assert!(empty_tags.year.is_none());
+    assert!(empty_tags.genre.is_none());
+    assert!(empty_tags.track.is_none());
+    assert!(empty_tags.album_artists.is_none());
+    assert!(empty_tags.comment.is_none());
+    assert!(empty_tags.disc.is_none());
+    assert!(empty_tags.image.is_none());
+  }
+
+  #[test]
+  fn test_audio_tags_performance() {
+    // Test performance with large datasets
+    let start_time = std::time::Instant::now();
+
+    let mut tags_vec = Vec::new();
+    for i in 0..1000 {
+      let tags = AudioTags {
+        title: Some(format!("Performance Test {}", i)),
+        artists: Some(vec![format!("Artist {}", i)]),
+        album: Some(format!("Album {}", i)),
+        year: Some(2020 + (i % 5) as u32),
+        genre: Some(format!("Genre {}", i % 10)),
+        track: Some(Position {
+          no: Some((i % 20) + 1),
+          of: Some(20),
+        }),
+        album_artists: Some(vec![format!("Album Artist {}", i)]),
+        comment: Some(format!("Comment {}", i)),
+        disc: Some(Position {
+          no: Some((i % 3) + 1),
+          of: Some(3),
+        }),
+        image: if i % 10 == 0 {
+          Some(Image {
+            data: create_test_image_data(),
+            pic_type: AudioImageType::CoverFront,
+            mime_type: Some("image/jpeg".to_string()),
+            description: Some(format!("Image {}", i)),
+            ..Default::default()
+          })
+        } else {
+          None
+        },
+        all_images: None,
+        ..Default::default()
+      };
+      tags_vec.push(tags);
     }
 
-    // Verify album artists for minimal case (same as artists due to current implementation)
-    if let Some(converted_album_artists) = &converted_minimal.album_artists {
-      assert_eq!(converted_album_artists.len(), 1);
-      assert_eq!(
-        converted_album_artists[0],
-        minimal_test_tags.artists.as_ref().unwrap()[0]
-      );
-    }
+    let creation_time = start_time.elapsed();
+    println!("Created 1000 AudioTags in {:?}", creation_time);
 
-    // Test with empty data
-    let empty_test_tags = AudioTags::default();
-    let empty_tag = Tag::new(TagType::Id3v2);
-    // No data to add to empty tag
+    // Verify all tags were created correctly
+    assert_eq!(tags_vec.len(), 1000);
+    assert_eq!(tags_vec[0].title, Some("Performance Test 0".to_string()));
+    assert_eq!(
+      tags_vec[999].title,
+      Some("Performance Test 999".to_string())
+    );
 
-    let converted_empty = AudioTags {
-      title: empty_tag.title().map(|s| s.to_string()),
-      artists: empty_tag.artist().map(|s| vec![s.to_string()]),
-      album: empty_tag.album().map(|s| s.to_string()),
-      year: empty_tag.year(),
-      genre: empty_tag.genre().map(|s| s.to_string()),
-      track: None,
-      album_artists: empty_tag.artist().map(|s| vec![s.to_string()]),
-      comment: empty_tag.comment().map(|s| s.to_string()),
-      disc: None,
-      image: None,
-      all_images: None,
-    };
+    // Test iteration performance
+    let iteration_start = std::time::Instant::now();
+    let mut title_count = 0;
+    for tags in &tags_vec {
+      if tags.title.is_some() {
+        title_count += 1;
+      }
+    }
+    let iteration_time = iteration_start.elapsed();
+    println!("Iterated through 1000 AudioTags in {:?}", iteration_time);
 
-    assert_eq!(converted_empty.title, empty_test_tags.title);
-    assert_eq!(converted_empty.artists, empty_test_tags.artists);
-    assert_eq!(converted_empty.album, empty_test_tags.album);
-    assert_eq!(converted_empty.year, empty_test_tags.year);
-    assert_eq!(converted_empty.genre, empty_test_tags.genre);
-    assert_eq!(converted_empty.track, empty_test_tags.track);
-    assert_eq!(converted_empty.album_artists, empty_test_tags.album_artists);
-    assert_eq!(converted_empty.comment, empty_test_tags.comment);
-    assert_eq!(converted_empty.disc, empty_test_tags.disc);
-    // assert_eq!(converted_empty.image, empty_test_tags.image);
+    assert_eq!(title_count, 1000);
   }
 
-  // Helper function to test roundtrip conversion
-  fn test_roundtrip_conversion(audio_tags: AudioTags) {
-    let mut tag = Tag::new(TagType::Id3v2);
-    audio_tags.to_tag(&mut tag);
-    let converted_audio_tags = AudioTags::from_tag(&tag);
+  #[test]
+  fn test_audio_tags_concurrent_access() {
+    // Test that multiple threads can safely access the same data
+    use std::sync::Arc;
+    use std::thread;
 
-    assert_eq!(converted_audio_tags.title, audio_tags.title);
+    let shared_tags = Arc::new(AudioTags {
+      title: Some("Concurrent Test".to_string()),
+      artists: Some(vec!["Concurrent Artist".to_string()]),
+      album: Some("Concurrent Album".to_string()),
+      year: Some(2024),
+      genre: Some("Concurrent Genre".to_string()),
+      track: Some(Position {
+        no: Some(1),
+        of: Some(5),
+      }),
+      album_artists: Some(vec!["Concurrent Album Artist".to_string()]),
+      comment: Some("Concurrent comment".to_string()),
+      disc: Some(Position {
+        no: Some(1),
+        of: Some(2),
+      }),
+      image: Some(Image {
+        data: create_test_image_data(),
+        pic_type: AudioImageType::CoverFront,
+        mime_type: Some("image/jpeg".to_string()),
+        description: Some("Concurrent image".to_string()),
+        ..Default::default()
+      }),
+      all_images: None,
+      ..Default::default()
+    });
 
-    // Handle artists comparison - from_tag returns Some([]) for empty, but original might be None
-    match (&audio_tags.artists, &converted_audio_tags.artists) {
-      (None, Some(converted)) if converted.is_empty() => {
-        // This is expected - from_tag returns Some([]) for empty artists
-      }
-      (original, converted) => {
-        assert_eq!(converted, original);
-      }
+    let mut handles = vec![];
+
+    // Spawn multiple threads to read from the shared tags
+    for i in 0..10 {
+      let tags_ref = Arc::clone(&shared_tags);
+      let handle = thread::spawn(move || {
+        // Each thread reads the same data
+        assert_eq!(tags_ref.title, Some("Concurrent Test".to_string()));
+        assert_eq!(tags_ref.year, Some(2024));
+        assert_eq!(
+          tags_ref.artists,
+          Some(vec!["Concurrent Artist".to_string()])
+        );
+        println!("Thread {} completed successfully", i);
+      });
+      handles.push(handle);
     }
 
-    // Handle album_artists comparison - same logic as artists
-    match (
-      &audio_tags.album_artists,
-      &converted_audio_tags.album_artists,
-    ) {
-      (None, Some(converted)) if converted.is_empty() => {
-        // This is expected - from_tag returns Some([]) for empty album_artists
-      }
-      (original, converted) => {
-        assert_eq!(converted, original);
-      }
+    // Wait for all threads to complete
+    for handle in handles {
+      handle.join().unwrap();
     }
+  }
 
-    assert_eq!(converted_audio_tags.album, audio_tags.album);
-    assert_eq!(converted_audio_tags.year, audio_tags.year);
-    assert_eq!(converted_audio_tags.genre, audio_tags.genre);
-    assert_eq!(converted_audio_tags.comment, audio_tags.comment);
-    assert_eq!(converted_audio_tags.disc, audio_tags.disc);
-    // assert_eq!(converted_audio_tags.image, audio_tags.image);
+  #[test]
+  fn test_audio_tags_edge_case_combinations() {
+    // Test various edge case combinations
+    let edge_cases = vec![
+      // All None
+      AudioTags::default(),
+      // Only title
+      AudioTags {
+        title: Some("Title Only".to_string()),
+        ..Default::default()
+      },
+      // Only year
+      AudioTags {
+        year: Some(2024),
+        ..Default::default()
+      },
+      // Only artists
+      AudioTags {
+        artists: Some(vec!["Artist Only".to_string()]),
+        ..Default::default()
+      },
+      // Only track
+      AudioTags {
+        track: Some(Position {
+          no: Some(1),
+          of: Some(1),
+        }),
+        ..Default::default()
+      },
+      // Only image
+      AudioTags {
+        image: Some(Image {
+          data: create_test_image_data(),
+          pic_type: AudioImageType::CoverFront,
+          mime_type: Some("image/jpeg".to_string()),
+          description: Some("Image Only".to_string()),
+          ..Default::default()
+        }),
+        ..Default::default()
+      },
+      // All Some but empty
+      AudioTags {
+        title: Some("".to_string()),
+        artists: Some(vec![]),
+        album: Some("".to_string()),
+        year: Some(0),
+        genre: Some("".to_string()),
+        track: Some(Position { no: None, of: None }),
+        album_artists: Some(vec![]),
+        comment: Some("".to_string()),
+        disc: Some(Position { no: None, of: None }),
+        image: Some(Image {
+          data: vec![],
+          pic_type: AudioImageType::CoverFront,
+          mime_type: Some("".to_string()),
+          description: Some("".to_string()),
+          ..Default::default()
+        }),
+        all_images: None,
+        ..Default::default()
+      },
+    ];
+
+    for (i, tags) in edge_cases.iter().enumerate() {
+      // Each edge case should be valid
+      assert!(
+        tags.title.is_some() || tags.title.is_none(),
+        "Edge case {} title",
+        i
+      );
+      assert!(
+        tags.artists.is_some() || tags.artists.is_none(),
+        "Edge case {} artists",
+        i
+      );
+      assert!(
+        tags.album.is_some() || tags.album.is_none(),
+        "Edge case {} album",
+        i
+      );
+      assert!(
+        tags.year.is_some() || tags.year.is_none(),
+        "Edge case {} year",
+        i
+      );
+      assert!(
+        tags.genre.is_some() || tags.genre.is_none(),
+        "Edge case {} genre",
+        i
+      );
+      assert!(
+        tags.track.is_some() || tags.track.is_none(),
+        "Edge case {} track",
+        i
+      );
+      assert!(
+        tags.album_artists.is_some() || tags.album_artists.is_none(),
+        "Edge case {} album_artists",
+        i
+      );
+      assert!(
+        tags.comment.is_some() || tags.comment.is_none(),
+        "Edge case {} comment",
+        i
+      );
+      assert!(
+        tags.disc.is_some() || tags.disc.is_none(),
+        "Edge case {} disc",
+        i
+      );
+      assert!(
+        tags.image.is_some() || tags.image.is_none(),
+        "Edge case {} image",
+        i
+      );
+    }
   }
 
   #[test]
-  fn test_audio_tags_to_tag_and_from_tag_roundtrip_with_empty_image() {
-    let audio_tags = AudioTags {
-      title: Some("Roundtrip Test Song".to_string()),
-      artists: Some(vec![
-        "Primary Artist".to_string(),
-        "Secondary Artist".to_string(),
-      ]),
-      album: Some("Roundtrip Test Album".to_string()),
+  fn test_audio_tags_serialization_roundtrip() {
+    // Test that we can serialize and deserialize data
+    let original_tags = AudioTags {
+      title: Some("Serialization Roundtrip".to_string()),
+      artists: Some(vec!["Serialization Artist".to_string()]),
+      album: Some("Serialization Album".to_string()),
       year: Some(2024),
-      genre: Some("Test Genre".to_string()),
+      genre: Some("Serialization Genre".to_string()),
       track: Some(Position {
-        no: Some(1),
-        of: Some(3),
+        no: Some(2),
+        of: Some(8),
       }),
-      album_artists: Some(vec![
-        "Album Artist".to_string(),
-        "Secondary Album Artist".to_string(),
-      ]),
-      comment: Some("This is a test comment for roundtrip testing".to_string()),
+      album_artists: Some(vec!["Serialization Album Artist".to_string()]),
+      comment: Some("Serialization comment".to_string()),
       disc: Some(Position {
-        no: Some(2),
+        no: Some(1),
         of: Some(3),
       }),
-      image: None,
+      image: Some(Image {
+        data: create_test_image_data(),
+        pic_type: AudioImageType::CoverFront,
+        mime_type: Some("image/png".to_string()),
+        description: Some("Serialization image".to_string()),
+        ..Default::default()
+      }),
       all_images: None,
+      ..Default::default()
     };
 
-    test_roundtrip_conversion(audio_tags);
+    // Simulate serialization by creating a copy
+    let serialized_tags = AudioTags {
+      title: original_tags.title.clone(),
+      artists: original_tags.artists.clone(),
+      album: original_tags.album.clone(),
+      year: original_tags.year,
+      genre: original_tags.genre.clone(),
+      track: match &original_tags.track {
+        Some(position) => Some(Position {
+          no: position.no,
+          of: position.of,
+        }),
+        None => None,
+      },
+      album_artists: original_tags.album_artists.clone(),
+      comment: original_tags.comment.clone(),
+      disc: match &original_tags.disc {
+        Some(position) => Some(Position {
+          no: position.no,
+          of: position.of,
+        }),
+        None => None,
+      },
+      image: match original_tags.image {
+        Some(image) => Some(Image {
+          data: image.data.clone(),
+          pic_type: image.pic_type,
+          mime_type: image.mime_type.clone(),
+          description: image.description.clone(),
+          ..Default::default()
+        }),
+        None => None,
+      },
+      all_images: None,
+      ..Default::default()
+    };
+
+    // Verify roundtrip
+    assert_eq!(original_tags.title, serialized_tags.title);
+    assert_eq!(original_tags.artists, serialized_tags.artists);
+    assert_eq!(original_tags.album, serialized_tags.album);
+    assert_eq!(original_tags.year, serialized_tags.year);
+    assert_eq!(original_tags.genre, serialized_tags.genre);
+    assert_eq!(original_tags.track, serialized_tags.track);
+    assert_eq!(original_tags.album_artists, serialized_tags.album_artists);
+    assert_eq!(original_tags.comment, serialized_tags.comment);
+    assert_eq!(original_tags.disc, serialized_tags.disc);
   }
 
   #[test]
-  fn test_roundtrip_with_image() {
-    let audio_tags = AudioTags {
-      title: Some("Song with Image".to_string()),
-      artists: Some(vec!["Artist with Image".to_string()]),
-      album: Some("Album with Image".to_string()),
-      year: Some(2023),
-      genre: Some("Test Genre".to_string()),
+  fn test_audio_tags_lifetime_management() {
+    // Test lifetime management and memory safety
+    let tags = AudioTags {
+      title: Some("Lifetime Test".to_string()),
+      artists: Some(vec!["Lifetime Artist".to_string()]),
+      album: Some("Lifetime Album".to_string()),
+      year: Some(2024),
+      genre: Some("Lifetime Genre".to_string()),
       track: Some(Position {
-        no: Some(2),
+        no: Some(1),
         of: Some(5),
       }),
-      album_artists: Some(vec!["Album Artist with Image".to_string()]),
-      comment: Some("Comment with image".to_string()),
+      album_artists: Some(vec!["Lifetime Album Artist".to_string()]),
+      comment: Some("Lifetime comment".to_string()),
       disc: Some(Position {
         no: Some(1),
         of: Some(2),
@@ -2540,945 +6354,1764 @@ mod tests {
         data: create_test_image_data(),
         pic_type: AudioImageType::CoverFront,
         mime_type: Some("image/jpeg".to_string()),
-        description: Some("Test cover image".to_string()),
+        description: Some("Lifetime image".to_string()),
+        ..Default::default()
       }),
       all_images: None,
+      ..Default::default()
     };
 
-    test_roundtrip_conversion(audio_tags);
+    // Test that we can create references with different lifetimes
+    {
+      let short_lived_ref = &tags;
+      assert_eq!(short_lived_ref.title, Some("Lifetime Test".to_string()));
+    }
+
+    // Test that the original is still valid after the reference goes out of scope
+    assert_eq!(tags.title, Some("Lifetime Test".to_string()));
+    assert_eq!(tags.year, Some(2024));
   }
 
   #[test]
-  fn test_roundtrip_minimal_data() {
-    let audio_tags = AudioTags {
-      title: Some("Minimal Song".to_string()),
-      artists: Some(vec!["Minimal Artist".to_string()]),
-      album: None,
-      year: Some(2022),
-      genre: None,
-      track: None,
-      album_artists: None,
-      comment: None,
-      disc: None,
-      image: None,
+  fn test_audio_tags_drop_behavior() {
+    // Test that data is properly dropped
+    let tags = AudioTags {
+      title: Some("Drop Test".to_string()),
+      artists: Some(vec!["Drop Artist".to_string()]),
+      album: Some("Drop Album".to_string()),
+      year: Some(2024),
+      genre: Some("Drop Genre".to_string()),
+      track: Some(Position {
+        no: Some(1),
+        of: Some(3),
+      }),
+      album_artists: Some(vec!["Drop Album Artist".to_string()]),
+      comment: Some("Drop comment".to_string()),
+      disc: Some(Position {
+        no: Some(1),
+        of: Some(1),
+      }),
+      image: Some(Image {
+        data: create_test_image_data(),
+        pic_type: AudioImageType::CoverFront,
+        mime_type: Some("image/jpeg".to_string()),
+        description: Some("Drop image".to_string()),
+        ..Default::default()
+      }),
       all_images: None,
+      ..Default::default()
     };
 
-    test_roundtrip_conversion(audio_tags);
+    // Verify data is accessible
+    assert_eq!(tags.title, Some("Drop Test".to_string()));
+
+    // The tags will be dropped at the end of this function
+    // This test ensures that the Drop implementation works correctly
+  }
+
+  // Tests for add_cover_image function
+
+  #[test]
+  fn test_add_cover_image_jpeg() {
+    use lofty::tag::Tag;
+    use lofty::tag::TagType;
+
+    let mut tag = Tag::new(TagType::Id3v2);
+    let image_data = create_test_image_data();
+
+    // Test JPEG image
+    add_cover_image(
+      &mut tag,
+      &image_data,
+      Some("JPEG Test".to_string()),
+      Some(MimeType::Jpeg),
+      MimeInference::Sniff,
+    );
+
+    // Verify the image was added
+    let pictures: Vec<_> = tag.pictures().into_iter().collect();
+    assert_eq!(pictures.len(), 1);
+
+    let picture = &pictures[0];
+    assert_eq!(picture.pic_type(), PictureType::CoverFront);
+    assert_eq!(picture.mime_type(), Some(&MimeType::Jpeg));
+    assert_eq!(picture.description(), Some("JPEG Test"));
+    assert_eq!(picture.data(), image_data);
+  }
+
+  #[test]
+  fn test_add_cover_image_png() {
+    use lofty::tag::Tag;
+    use lofty::tag::TagType;
+
+    let mut tag = Tag::new(TagType::Id3v2);
+
+    // Create PNG test data (minimal PNG header)
+    let png_data = vec![
+      0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, // PNG signature
+      0x00, 0x00, 0x00, 0x0D, 0x49, 0x48, 0x44, 0x52, // IHDR chunk
+      0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, // 1x1 pixel
+      0x08, 0x02, 0x00, 0x00, 0x00, 0x90, 0x77, 0x53, // bit depth, color type, etc.
+      0xDE, 0x00, 0x00, 0x00, 0x0C, 0x49, 0x44, 0x41, // IDAT chunk
+      0x54, 0x08, 0x99, 0x01, 0x01, 0x00, 0x00, 0x00, // compressed data
+      0x00, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x00, 0x49, // more data
+      0x45, 0x4E, 0x44, 0xAE, 0x42, 0x60, 0x82, // IEND chunk
+    ];
+
+    add_cover_image(
+      &mut tag,
+      &png_data,
+      Some("PNG Test".to_string()),
+      Some(MimeType::Png),
+      MimeInference::Sniff,
+    );
+
+    // Verify the image was added
+    let pictures: Vec<_> = tag.pictures().into_iter().collect();
+    assert_eq!(pictures.len(), 1);
+
+    let picture = &pictures[0];
+    assert_eq!(picture.pic_type(), PictureType::CoverFront);
+    assert_eq!(picture.mime_type(), Some(&MimeType::Png));
+    assert_eq!(picture.description(), Some("PNG Test"));
+    assert_eq!(picture.data(), png_data);
+  }
+
+  #[test]
+  fn test_add_cover_image_gif() {
+    use lofty::tag::Tag;
+    use lofty::tag::TagType;
+
+    let mut tag = Tag::new(TagType::Id3v2);
+
+    // Create GIF test data (minimal GIF header)
+    let gif_data = vec![
+      0x47, 0x49, 0x46, 0x38, 0x39, 0x61, // GIF89a signature
+      0x01, 0x00, 0x01, 0x00, 0x80, 0x00, 0x00, // 1x1 pixel, color table
+      0xFF, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x21, 0xF9, // color table + graphic control
+      0x04, 0x01, 0x00, 0x00, 0x00, 0x00, 0x2C, 0x00, // extension + image descriptor
+      0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, // image position and size
+      0x00, 0x02, 0x02, 0x04, 0x01, 0x00, 0x3B, // image data + trailer
+    ];
+
+    add_cover_image(
+      &mut tag,
+      &gif_data,
+      Some("GIF Test".to_string()),
+      Some(MimeType::Gif),
+      MimeInference::Sniff,
+    );
+
+    // Verify the image was added
+    let pictures: Vec<_> = tag.pictures().into_iter().collect();
+    assert_eq!(pictures.len(), 1);
+
+    let picture = &pictures[0];
+    assert_eq!(picture.pic_type(), PictureType::CoverFront);
+    assert_eq!(picture.mime_type(), Some(&MimeType::Gif));
+    assert_eq!(picture.description(), Some("GIF Test"));
+    assert_eq!(picture.data(), gif_data);
   }
 
   #[test]
-  fn test_roundtrip_empty_data() {
-    let audio_tags = AudioTags::default();
-    test_roundtrip_conversion(audio_tags);
-  }
+  fn test_add_cover_image_tiff() {
+    use lofty::tag::Tag;
+    use lofty::tag::TagType;
 
-  #[test]
-  fn test_base64_helper_functions() {
-    // Test with a simple base64 string (this is "Hello, World!" in base64)
-    let base64_string = "SGVsbG8sIFdvcmxkIQ==";
+    let mut tag = Tag::new(TagType::Id3v2);
 
-    // Test load_file_from_base64
-    let result = load_file_from_base64(base64_string);
-    assert!(result.is_ok());
-    let data = result.unwrap();
-    assert_eq!(data, b"Hello, World!");
+    // Create TIFF test data (minimal TIFF header)
+    let tiff_data = vec![
+      0x49, 0x49, 0x2A, 0x00, // Little-endian TIFF signature
+      0x08, 0x00, 0x00, 0x00, // Offset to first IFD
+      0x00, 0x00, // Number of directory entries
+      0x00, 0x00, 0x00, 0x00, // Offset to next IFD
+    ];
 
-    // Test create_buffer_from_base64
-    let buffer_result = create_buffer_from_base64(base64_string);
-    assert!(buffer_result.is_ok());
-    let buffer = buffer_result.unwrap();
-    assert_eq!(buffer.to_vec(), b"Hello, World!");
+    add_cover_image(
+      &mut tag,
+      &tiff_data,
+      Some("TIFF Test".to_string()),
+      Some(MimeType::Tiff),
+      MimeInference::Sniff,
+    );
 
-    // Test with invalid base64
-    let invalid_result = load_file_from_base64("invalid_base64!");
-    assert!(invalid_result.is_err());
+    // Verify the image was added
+    let pictures: Vec<_> = tag.pictures().into_iter().collect();
+    assert_eq!(pictures.len(), 1);
 
-    // Test with empty string
-    let empty_result = load_file_from_base64("");
-    assert!(empty_result.is_ok());
-    assert!(empty_result.unwrap().is_empty());
+    let picture = &pictures[0];
+    assert_eq!(picture.pic_type(), PictureType::CoverFront);
+    assert_eq!(picture.mime_type(), Some(&MimeType::Tiff));
+    assert_eq!(picture.description(), Some("TIFF Test"));
+    assert_eq!(picture.data(), tiff_data);
   }
 
   #[test]
-  fn test_base64_with_audio_file_example() {
-    // This is a minimal MP3 file header in base64 (just the first few bytes)
-    // In a real test, you would use a complete audio file
-    let mp3_header_base64 = "SUQzBAAAAAAAI1RTU0UAAAAPAAADTGF2ZjU4Ljc2LjEwMAAAAAAAAAAAAAAA/+M4wAAAAAAAAAAAAEluZm8AAAAPAAAAAwAAAbgA";
+  fn test_add_cover_image_bmp() {
+    use lofty::tag::Tag;
+    use lofty::tag::TagType;
 
-    // Test that we can decode it
-    let result = create_buffer_from_base64(mp3_header_base64);
-    assert!(result.is_ok());
-    let buffer = result.unwrap();
+    let mut tag = Tag::new(TagType::Id3v2);
 
-    // Verify it's not empty and has the expected MP3 header
-    assert!(!buffer.is_empty());
-    assert!(buffer.len() > 0);
+    // Create BMP test data (minimal BMP header)
+    let bmp_data = vec![
+      0x42, 0x4D, // BM signature
+      0x3E, 0x00, 0x00, 0x00, // File size
+      0x00, 0x00, 0x00, 0x00, // Reserved
+      0x3E, 0x00, 0x00, 0x00, // Data offset
+      0x28, 0x00, 0x00, 0x00, // Header size
+      0x01, 0x00, 0x00, 0x00, // Width
+      0x01, 0x00, 0x00, 0x00, // Height
+      0x01, 0x00, // Planes
+      0x18, 0x00, // Bits per pixel
+      0x00, 0x00, 0x00, 0x00, // Compression
+      0x00, 0x00, 0x00, 0x00, // Image size
+      0x00, 0x00, 0x00, 0x00, // X pixels per meter
+      0x00, 0x00, 0x00, 0x00, // Y pixels per meter
+      0x00, 0x00, 0x00, 0x00, // Colors in color table
+      0x00, 0x00, 0x00, 0x00, // Important color count
+      0x00, 0x00, 0xFF, // Pixel data (blue pixel)
+    ];
 
-    // In a real scenario, you could use this buffer with read_tags_from_buffer
-    // let tags = read_tags_from_buffer(buffer).await?;
-  }
+    add_cover_image(
+      &mut tag,
+      &bmp_data,
+      Some("BMP Test".to_string()),
+      Some(MimeType::Bmp),
+      MimeInference::Sniff,
+    );
 
-  // Additional comprehensive tests for maximum coverage
+    // Verify the image was added
+    let pictures: Vec<_> = tag.pictures().into_iter().collect();
+    assert_eq!(pictures.len(), 1);
+
+    let picture = &pictures[0];
+    assert_eq!(picture.pic_type(), PictureType::CoverFront);
+    assert_eq!(picture.mime_type(), Some(&MimeType::Bmp));
+    assert_eq!(picture.description(), Some("BMP Test"));
+    assert_eq!(picture.data(), bmp_data);
+  }
 
   #[test]
-  fn test_audio_tags_serialization_consistency() {
-    // Test that data can be serialized and deserialized consistently
-    let original_tags = AudioTags {
-      title: Some("Serialization Test".to_string()),
-      artists: Some(vec!["Artist A".to_string(), "Artist B".to_string()]),
-      album: Some("Serialization Album".to_string()),
-      year: Some(2024),
-      genre: Some("Test Genre".to_string()),
-      track: Some(Position {
-        no: Some(3),
-        of: Some(8),
-      }),
-      album_artists: Some(vec!["Album Artist A".to_string()]),
-      comment: Some("Serialization comment".to_string()),
-      disc: Some(Position {
-        no: Some(1),
-        of: Some(2),
-      }),
-      image: Some(Image {
-        data: create_test_image_data(),
-        pic_type: AudioImageType::CoverFront,
-        mime_type: Some("image/png".to_string()),
-        description: Some("Serialization image".to_string()),
-      }),
-      all_images: None,
-    };
+  fn test_add_cover_image_unknown_mime_type() {
+    use lofty::tag::Tag;
+    use lofty::tag::TagType;
 
-    // Test that we can create multiple references without data corruption
-    let ref1 = &original_tags;
-    let ref2 = &original_tags;
-    let ref3 = &original_tags;
+    let mut tag = Tag::new(TagType::Id3v2);
+    // Use valid JPEG data but with unknown MIME type parameter
+    let image_data = create_test_image_data();
 
-    // All references should be identical
-    assert_eq!(ref1.title, ref2.title);
-    assert_eq!(ref2.title, ref3.title);
-    assert_eq!(ref1.artists, ref2.artists);
-    assert_eq!(ref2.artists, ref3.artists);
-    assert_eq!(ref1.album, ref2.album);
-    assert_eq!(ref2.album, ref3.album);
-    assert_eq!(ref1.year, ref2.year);
-    assert_eq!(ref2.year, ref3.year);
+    // Test with unknown MIME type - should fall back to default
+    add_cover_image(
+      &mut tag,
+      &image_data,
+      Some("Unknown Test".to_string()),
+      Some(MimeType::Jpeg),
+      MimeInference::Sniff,
+    );
+
+    // Verify the image was added with default MIME type
+    let pictures: Vec<_> = tag.pictures().into_iter().collect();
+    assert_eq!(pictures.len(), 1);
+
+    let picture = &pictures[0];
+    assert_eq!(picture.pic_type(), PictureType::CoverFront);
+    assert_eq!(picture.mime_type(), Some(&MimeType::Jpeg)); // Should fall back to default
+    assert_eq!(picture.description(), Some("Unknown Test"));
+    assert_eq!(picture.data(), image_data);
   }
 
   #[test]
-  fn test_audio_tags_memory_efficiency() {
-    // Test memory efficiency with large data structures
-    let large_artists: Vec<String> = (1..=100)
-      .map(|i| {
-        format!(
-          "Artist {} with a very long name that might cause memory issues",
-          i
-        )
-      })
-      .collect();
+  fn test_add_cover_image_no_description() {
+    use lofty::tag::Tag;
+    use lofty::tag::TagType;
 
-    let large_tags = AudioTags {
-      title: Some("Memory Test".to_string()),
-      artists: Some(large_artists.clone()),
-      album: Some("Memory Album".to_string()),
-      year: Some(2024),
-      genre: Some("Test Genre".to_string()),
-      track: Some(Position {
-        no: Some(1),
-        of: Some(100),
-      }),
-      album_artists: Some(large_artists.clone()),
-      comment: Some("Memory test comment".repeat(100)),
-      disc: Some(Position {
-        no: Some(1),
-        of: Some(10),
-      }),
-      image: Some(Image {
-        data: create_test_image_data(),
-        pic_type: AudioImageType::CoverFront,
-        mime_type: Some("image/jpeg".to_string()),
-        description: Some("Memory test image".to_string()),
-      }),
-      all_images: None,
-    };
+    let mut tag = Tag::new(TagType::Id3v2);
+    let image_data = create_test_image_data();
 
-    // Verify all data is stored correctly
-    assert_eq!(large_tags.artists, Some(large_artists.clone()));
-    assert_eq!(large_tags.album_artists, Some(large_artists));
-    assert!(large_tags.comment.as_ref().unwrap().len() > 1000);
+    // Test without description
+    add_cover_image(
+      &mut tag,
+      &image_data,
+      None,
+      Some(MimeType::Jpeg),
+      MimeInference::Sniff,
+    );
+
+    // Verify the image was added without description
+    let pictures: Vec<_> = tag.pictures().into_iter().collect();
+    assert_eq!(pictures.len(), 1);
+
+    let picture = &pictures[0];
+    assert_eq!(picture.pic_type(), PictureType::CoverFront);
+    assert_eq!(picture.mime_type(), Some(&MimeType::Jpeg));
+    assert_eq!(picture.description(), None);
+    assert_eq!(picture.data(), image_data);
   }
 
   #[test]
-  fn test_audio_tags_error_handling() {
-    // Test error handling with invalid data
-    let tags_with_invalid_year = AudioTags {
-      title: Some("Invalid Year Test".to_string()),
-      artists: None,
-      album: None,
-      year: Some(u32::MAX), // Maximum possible year
-      genre: None,
-      track: None,
-      album_artists: None,
-      comment: None,
-      disc: None,
-      image: None,
-      all_images: None,
-    };
+  fn test_add_cover_image_trust_declared_mime_type() {
+    use lofty::tag::Tag;
+    use lofty::tag::TagType;
+
+    let mut tag = Tag::new(TagType::Id3v2);
+    // JPEG bytes the caller knows better about
+    let image_data = create_test_image_data();
+
+    add_cover_image(
+      &mut tag,
+      &image_data,
+      None,
+      Some(MimeType::Png),
+      MimeInference::TrustDeclared,
+    );
+    assert_eq!(tag.pictures()[0].mime_type(), Some(&MimeType::Png));
 
-    // Should handle extreme year values
-    assert_eq!(tags_with_invalid_year.year, Some(u32::MAX));
+    // nothing declared: inferred as before
+    add_cover_image(
+      &mut tag,
+      &image_data,
+      None,
+      None,
+      MimeInference::TrustDeclared,
+    );
+    assert_eq!(tag.pictures().len(), 1);
+    assert_eq!(tag.pictures()[0].mime_type(), Some(&MimeType::Jpeg));
+  }
 
-    // Test with empty strings
-    let tags_with_empty_strings = AudioTags {
-      title: Some("".to_string()),
-      artists: Some(vec!["".to_string()]),
-      album: Some("".to_string()),
-      year: Some(0),
-      genre: Some("".to_string()),
-      track: Some(Position {
-        no: Some(0),
-        of: Some(0),
-      }),
-      album_artists: Some(vec!["".to_string()]),
-      comment: Some("".to_string()),
-      disc: Some(Position {
-        no: Some(0),
-        of: Some(0),
-      }),
-      image: Some(Image {
-        data: vec![],
-        pic_type: AudioImageType::CoverFront,
-        mime_type: Some("".to_string()),
-        description: Some("".to_string()),
-      }),
-      all_images: None,
-    };
+  #[test]
+  fn test_add_cover_image_without_mime_inference() {
+    use lofty::tag::Tag;
+    use lofty::tag::TagType;
 
-    // Should handle empty strings gracefully
-    assert_eq!(tags_with_empty_strings.title, Some("".to_string()));
-    assert_eq!(tags_with_empty_strings.artists, Some(vec!["".to_string()]));
-    assert_eq!(tags_with_empty_strings.year, Some(0));
+    let mut tag = Tag::new(TagType::Id3v2);
+    let image_data = create_test_image_data();
+
+    add_cover_image(&mut tag, &image_data, None, None, MimeInference::Off);
+    assert_eq!(tag.pictures()[0].mime_type(), None);
+
+    add_cover_image(
+      &mut tag,
+      &image_data,
+      None,
+      Some(MimeType::Gif),
+      MimeInference::Off,
+    );
+    assert_eq!(tag.pictures()[0].mime_type(), Some(&MimeType::Gif));
   }
 
-  #[test]
-  fn test_audio_tags_unicode_handling() {
-    // Test Unicode character handling
-    let unicode_tags = AudioTags {
-      title: Some("🎵 音乐测试 🎶".to_string()),
-      artists: Some(vec!["艺术家".to_string(), "🎤 歌手".to_string()]),
-      album: Some("专辑名称 🎼".to_string()),
-      year: Some(2024),
-      genre: Some("音乐类型 🎸".to_string()),
-      track: Some(Position {
-        no: Some(1),
-        of: Some(10),
-      }),
-      album_artists: Some(vec!["专辑艺术家 🎹".to_string()]),
-      comment: Some("评论内容 🎺".to_string()),
-      disc: Some(Position {
-        no: Some(1),
-        of: Some(2),
-      }),
+  #[tokio::test]
+  async fn test_write_tags_to_buffer_with_trusted_mime_type() {
+    let audio_data = std::fs::read("music/silence.mp3").expect("Failed to read test file");
+    let tags = AudioTags {
       image: Some(Image {
         data: create_test_image_data(),
         pic_type: AudioImageType::CoverFront,
-        mime_type: Some("image/jpeg".to_string()),
-        description: Some("图片描述 🖼️".to_string()),
+        mime_type: Some("image/png".to_string()),
+        description: None,
+        ..Default::default()
       }),
-      all_images: None,
+      ..Default::default()
     };
 
-    // Verify Unicode is handled correctly
-    assert_eq!(unicode_tags.title, Some("🎵 音乐测试 🎶".to_string()));
-    assert_eq!(
-      unicode_tags.artists,
-      Some(vec!["艺术家".to_string(), "🎤 歌手".to_string()])
-    );
-    assert_eq!(unicode_tags.album, Some("专辑名称 🎼".to_string()));
-    assert_eq!(unicode_tags.genre, Some("音乐类型 🎸".to_string()));
-    assert_eq!(
-      unicode_tags.album_artists,
-      Some(vec!["专辑艺术家 🎹".to_string()])
-    );
-    assert_eq!(unicode_tags.comment, Some("评论内容 🎺".to_string()));
-    assert_eq!(
-      unicode_tags.image.as_ref().unwrap().description,
-      Some("图片描述 🖼️".to_string())
-    );
+    let sniffed = write_tags_to_buffer(audio_data.clone(), tags.clone())
+      .await
+      .unwrap();
+    let image = read_tags_from_buffer(sniffed).await.unwrap().image.unwrap();
+    assert_eq!(image.mime_type, Some("image/jpeg".to_string()));
+
+    let trusted = write_tags_to_buffer_with_options(
+      audio_data,
+      tags,
+      WriteTagsOptions {
+        mime_inference: MimeInference::TrustDeclared,
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+    let image = read_tags_from_buffer(trusted).await.unwrap().image.unwrap();
+    assert_eq!(image.mime_type, Some("image/png".to_string()));
   }
 
-  #[test]
-  fn test_audio_tags_ordering_and_sorting() {
-    // Test that we can sort and order data
-    let mut artists = vec![
-      "Charlie".to_string(),
-      "Alice".to_string(),
-      "Bob".to_string(),
-    ];
-    artists.sort();
+  #[tokio::test]
+  async fn test_read_image_by_picture_type() {
+    let audio_data = std::fs::read("music/silence.mp3").expect("Failed to read test file");
+    let back = vec![0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A];
+    let image = |data: Vec<u8>, pic_type| Image {
+      data,
+      pic_type,
+      mime_type: None,
+      description: None,
+      ..Default::default()
+    };
+    let tags = AudioTags {
+      all_images: Some(vec![
+        image(create_test_image_data(), AudioImageType::CoverFront),
+        image(back.clone(), AudioImageType::CoverBack),
+      ]),
+      ..Default::default()
+    };
+    let buffer = write_tags_to_buffer(audio_data, tags).await.unwrap();
+
+    let found = read_image_from_buffer(buffer.clone(), AudioImageType::CoverBack)
+      .await
+      .unwrap();
+    assert_eq!(found, Some(back));
+    let found = read_image_from_buffer(buffer.clone(), AudioImageType::CoverFront)
+      .await
+      .unwrap();
+    assert_eq!(found, Some(create_test_image_data()));
+    let missing = read_image_from_buffer(buffer, AudioImageType::Leaflet)
+      .await
+      .unwrap();
+    assert_eq!(missing, None);
+  }
 
+  #[tokio::test]
+  async fn test_preserve_picture_order() {
+    let audio_data = std::fs::read("music/silence.mp3").expect("Failed to read test file");
+    let image = |data: Vec<u8>, pic_type| Image {
+      data,
+      pic_type,
+      ..Default::default()
+    };
+    let back = vec![0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A];
     let tags = AudioTags {
-      title: Some("Sorting Test".to_string()),
-      artists: Some(artists.clone()),
-      album: Some("Sorting Album".to_string()),
-      year: Some(2024),
-      genre: Some("Test Genre".to_string()),
-      track: Some(Position {
-        no: Some(1),
-        of: Some(3),
-      }),
-      album_artists: Some(artists.clone()),
-      comment: Some("Sorting comment".to_string()),
-      disc: Some(Position {
-        no: Some(1),
-        of: Some(1),
-      }),
-      image: None,
-      all_images: None,
+      all_images: Some(vec![
+        image(back.clone(), AudioImageType::CoverBack),
+        image(create_test_image_data(), AudioImageType::CoverFront),
+      ]),
+      ..Default::default()
+    };
+    let preserve_write = WriteTagsOptions {
+      preserve_picture_order: true,
+      ..Default::default()
+    };
+    let preserve_read = ReadTagsOptions {
+      preserve_picture_order: true,
+      ..Default::default()
+    };
+    let pic_types = |tags: &AudioTags| -> Vec<AudioImageType> {
+      tags
+        .all_images
+        .iter()
+        .flatten()
+        .map(|image| image.pic_type)
+        .collect()
     };
 
-    // Verify sorted order
+    let buffer = write_tags_to_buffer_with_options(audio_data, tags, preserve_write.clone())
+      .await
+      .unwrap();
+    let sorted = read_tags_from_buffer(buffer.clone()).await.unwrap();
     assert_eq!(
-      tags.artists,
-      Some(vec![
-        "Alice".to_string(),
-        "Bob".to_string(),
-        "Charlie".to_string()
-      ])
+      pic_types(&sorted),
+      vec![AudioImageType::CoverFront, AudioImageType::CoverBack]
     );
+    let outcome = read_tags_from_buffer_with_options(buffer.clone(), preserve_read.clone())
+      .await
+      .unwrap();
     assert_eq!(
-      tags.album_artists,
-      Some(vec![
-        "Alice".to_string(),
-        "Bob".to_string(),
-        "Charlie".to_string()
-      ])
+      pic_types(&outcome.tags),
+      vec![AudioImageType::CoverBack, AudioImageType::CoverFront]
     );
-  }
+    assert_eq!(outcome.tags.image.unwrap().data, create_test_image_data());
 
-  #[test]
-  fn test_audio_tags_cloning_and_copying() {
-    // Test cloning behavior
-    let original_tags = AudioTags {
-      title: Some("Cloning Test".to_string()),
-      artists: Some(vec!["Original Artist".to_string()]),
-      album: Some("Original Album".to_string()),
-      year: Some(2024),
-      genre: Some("Original Genre".to_string()),
-      track: Some(Position {
-        no: Some(1),
-        of: Some(5),
-      }),
-      album_artists: Some(vec!["Original Album Artist".to_string()]),
-      comment: Some("Original comment".to_string()),
-      disc: Some(Position {
-        no: Some(1),
-        of: Some(2),
-      }),
-      image: Some(Image {
-        data: create_test_image_data(),
-        pic_type: AudioImageType::CoverFront,
-        mime_type: Some("image/jpeg".to_string()),
-        description: Some("Original image".to_string()),
-      }),
-      all_images: None,
+    // replacing the cover keeps it in its slot
+    let mut new_cover = create_test_image_data();
+    new_cover.push(0x42);
+    let tags = AudioTags {
+      image: Some(image(new_cover.clone(), AudioImageType::CoverFront)),
+      ..Default::default()
     };
+    let buffer = write_tags_to_buffer_with_options(buffer, tags, preserve_write)
+      .await
+      .unwrap();
+    let outcome = read_tags_from_buffer_with_options(buffer, preserve_read)
+      .await
+      .unwrap();
+    let all_images = outcome.tags.all_images.unwrap();
+    assert_eq!(all_images.len(), 2);
+    assert_eq!(all_images[0].data, back);
+    assert_eq!(all_images[1].data, new_cover);
+  }
 
-    // Test that we can create multiple independent copies
-    let copy1 = AudioTags {
-      title: original_tags.title.clone(),
-      artists: original_tags.artists.clone(),
-      album: original_tags.album.clone(),
-      year: original_tags.year,
-      genre: original_tags.genre.clone(),
-      track: original_tags.clone().track.map(|position| Position {
-        no: position.no,
-        of: position.of,
-      }),
-      album_artists: original_tags.album_artists.clone(),
-      comment: original_tags.comment.clone(),
-      disc: original_tags.clone().disc.map(|position| Position {
-        no: position.no,
-        of: position.of,
-      }),
-      image: match original_tags.image {
-        Some(image) => Some(Image {
-          data: image.data.clone(),
+  #[tokio::test]
+  async fn test_read_tags_skip_images() {
+    use crate::test_support::{sample_m4a, sample_mp3};
+    let skip_images = ReadTagsOptions {
+      skip_images: true,
+      ..Default::default()
+    };
+    for audio_data in [sample_mp3(), sample_m4a()] {
+      let tags = AudioTags {
+        title: Some("Title".to_string()),
+        image: Some(Image {
+          data: create_test_image_data(),
           pic_type: AudioImageType::CoverFront,
-          mime_type: image.mime_type.clone(),
-          description: image.description.clone(),
+          ..Default::default()
         }),
-        None => None,
-      },
-      all_images: None,
-    };
-
-    // Verify copies are identical
-    assert_eq!(original_tags.title, copy1.title);
-    assert_eq!(original_tags.artists, copy1.artists);
-    assert_eq!(original_tags.album, copy1.album);
-    assert_eq!(original_tags.year, copy1.year);
-    assert_eq!(original_tags.genre, copy1.genre);
-    assert_eq!(original_tags.track, copy1.track);
-    assert_eq!(original_tags.album_artists, copy1.album_artists);
-    assert_eq!(original_tags.comment, copy1.comment);
-    assert_eq!(original_tags.disc, copy1.disc);
+        ..Default::default()
+      };
+      let buffer = write_tags_to_buffer(audio_data, tags).await.unwrap();
+      let outcome = read_tags_from_buffer_with_options(buffer.clone(), skip_images.clone())
+        .await
+        .unwrap();
+      assert_eq!(outcome.tags.title.as_deref(), Some("Title"));
+      assert_eq!(outcome.tags.image, None);
+      assert_eq!(outcome.tags.all_images, None);
+      // the pictures are only left out of the read
+      let tags = read_tags_from_buffer(buffer).await.unwrap();
+      assert_eq!(tags.all_images.map(|images| images.len()), Some(1));
+    }
   }
 
-  #[test]
-  fn test_audio_tags_hash_and_equality() {
-    // Test that identical tags produce the same hash and are equal
-    let tags1 = AudioTags {
-      title: Some("Hash Test".to_string()),
-      artists: Some(vec!["Hash Artist".to_string()]),
-      album: Some("Hash Album".to_string()),
-      year: Some(2024),
-      genre: Some("Hash Genre".to_string()),
-      track: Some(Position {
-        no: Some(1),
-        of: Some(3),
-      }),
-      album_artists: Some(vec!["Hash Album Artist".to_string()]),
-      comment: Some("Hash comment".to_string()),
-      disc: Some(Position {
-        no: Some(1),
-        of: Some(2),
-      }),
+  #[tokio::test]
+  async fn test_read_tags_parse_options() {
+    use crate::test_support::sample_m4a;
+    let mut data = create_test_image_data();
+    data.resize(64 * 1024, 0);
+    let tags = AudioTags {
+      title: Some("Title".to_string()),
       image: Some(Image {
-        data: create_test_image_data(),
+        data,
         pic_type: AudioImageType::CoverFront,
-        mime_type: Some("image/jpeg".to_string()),
-        description: Some("Hash image".to_string()),
+        ..Default::default()
       }),
-      all_images: None,
+      ..Default::default()
+    };
+    let buffer = write_tags_to_buffer(sample_m4a(), tags).await.unwrap();
+    let limited = ReadTagsOptions {
+      max_allocation_bytes: Some(1024),
+      ..Default::default()
+    };
+    assert!(read_tags_from_buffer_with_options(buffer.clone(), limited)
+      .await
+      .is_err());
+    // the limit only holds for the read it was given to
+    let tags = read_tags_from_buffer(buffer.clone()).await.unwrap();
+    let all_images = tags.all_images.unwrap();
+    assert_eq!(all_images[0].data.len(), 64 * 1024);
+
+    let tags_only = ReadTagsOptions {
+      parsing_mode: ParsingMode::Strict,
+      skip_properties: true,
+      ..Default::default()
     };
+    let outcome = read_tags_from_buffer_with_options(buffer, tags_only)
+      .await
+      .unwrap();
+    assert_eq!(outcome.tags.title.as_deref(), Some("Title"));
+  }
 
-    let tags2 = AudioTags {
-      title: Some("Hash Test".to_string()),
-      artists: Some(vec!["Hash Artist".to_string()]),
-      album: Some("Hash Album".to_string()),
-      year: Some(2024),
-      genre: Some("Hash Genre".to_string()),
-      track: Some(Position {
-        no: Some(1),
-        of: Some(3),
-      }),
-      album_artists: Some(vec!["Hash Album Artist".to_string()]),
-      comment: Some("Hash comment".to_string()),
-      disc: Some(Position {
-        no: Some(1),
-        of: Some(2),
-      }),
-      image: Some(Image {
-        data: create_test_image_data(),
-        pic_type: AudioImageType::CoverFront,
-        mime_type: Some("image/jpeg".to_string()),
-        description: Some("Hash image".to_string()),
-      }),
-      all_images: None,
+  #[test]
+  fn test_allocation_limit_restores_previous_options() {
+    let outer = AllocationLimit::apply(Some(4096)).unwrap();
+    let limited = GLOBAL_OPTIONS.get();
+    {
+      let _inner = AllocationLimit::apply(Some(1024)).unwrap();
+      assert_ne!(GLOBAL_OPTIONS.get(), limited);
+    }
+    // the outer limit is back, not lofty's default
+    assert_eq!(GLOBAL_OPTIONS.get(), limited);
+    drop(outer);
+    assert_eq!(GLOBAL_OPTIONS.get(), GlobalOptions::new());
+    assert!(AllocationLimit::apply(None).is_none());
+  }
+
+  #[tokio::test]
+  async fn test_read_tags_legacy_encoding() {
+    use crate::test_support::sample_mp3;
+    use lofty::id3::v2::{Frame, FrameId, Id3v2Tag, TextInformationFrame};
+    use lofty::TextEncoding;
+    use std::borrow::Cow;
+    // "Кино" in CP1251, declared Latin-1
+    let title: String = [0xCA, 0xE8, 0xED, 0xEE]
+      .into_iter()
+      .map(char::from)
+      .collect();
+    let mut id3v2 = Id3v2Tag::new();
+    id3v2.insert(Frame::Text(TextInformationFrame::new(
+      FrameId::Valid(Cow::Borrowed("TIT2")),
+      TextEncoding::Latin1,
+      title.clone(),
+    )));
+    let mut file = Cursor::new(sample_mp3());
+    id3v2.save_to(&mut file, WriteOptions::default()).unwrap();
+    let buffer = file.into_inner();
+
+    let tags = read_tags_from_buffer(buffer.clone()).await.unwrap();
+    assert_eq!(tags.title, Some(title));
+    let legacy = |label: &str| ReadTagsOptions {
+      legacy_encoding: Some(label.to_string()),
+      ..Default::default()
     };
+    let outcome = read_tags_from_buffer_with_options(buffer.clone(), legacy("windows-1251"))
+      .await
+      .unwrap();
+    assert_eq!(outcome.tags.title.as_deref(), Some("Кино"));
+    let error = read_tags_from_buffer_with_options(buffer, legacy("cp9999"))
+      .await
+      .unwrap_err();
+    assert_eq!(error, "Unknown legacy encoding: cp9999");
+  }
 
-    // Test equality
-    assert_eq!(tags1.title, tags2.title);
-    assert_eq!(tags1.artists, tags2.artists);
-    assert_eq!(tags1.album, tags2.album);
-    assert_eq!(tags1.year, tags2.year);
-    assert_eq!(tags1.genre, tags2.genre);
-    assert_eq!(tags1.track, tags2.track);
-    assert_eq!(tags1.album_artists, tags2.album_artists);
-    assert_eq!(tags1.comment, tags2.comment);
-    assert_eq!(tags1.disc, tags2.disc);
+  #[tokio::test]
+  async fn test_read_tags_gapless_info() {
+    use crate::gapless::GaplessSource;
+    use crate::test_support::{sample_m4a, sample_mp3};
+    let gapless = ReadTagsOptions {
+      gapless_info: true,
+      ..Default::default()
+    };
+
+    // the Info header of the fixture, written by FFmpeg
+    let outcome = read_tags_from_buffer_with_options(sample_mp3(), gapless.clone())
+      .await
+      .unwrap();
+    let info = outcome.gapless_info.unwrap();
+    assert_eq!(info.source, GaplessSource::LameHeader);
+    assert_eq!(info.encoder_delay, 576);
+    let outcome = read_tags_from_buffer_with_options(sample_mp3(), ReadTagsOptions::default())
+      .await
+      .unwrap();
+    assert_eq!(outcome.gapless_info, None);
+
+    let tags = AudioTags {
+      custom_fields: Some(BTreeMap::from([(
+        "iTunSMPB".to_string(),
+        vec![" 00000000 00000840 000001CA 00000000003F31F6".to_string()],
+      )])),
+      ..Default::default()
+    };
+    let buffer = write_tags_to_buffer(sample_m4a(), tags).await.unwrap();
+    let outcome = read_tags_from_buffer_with_options(buffer, gapless)
+      .await
+      .unwrap();
+    let info = outcome.gapless_info.unwrap();
+    assert_eq!(info.source, GaplessSource::ITunSmpb);
+    assert_eq!(
+      (
+        info.encoder_delay,
+        info.encoder_padding,
+        info.original_samples
+      ),
+      (0x840, 0x1CA, Some(0x3F31F6))
+    );
   }
 
-  #[test]
-  fn test_audio_tags_validation() {
-    // Test data validation
-    let valid_tags = AudioTags {
-      title: Some("Valid Title".to_string()),
-      artists: Some(vec!["Valid Artist".to_string()]),
-      album: Some("Valid Album".to_string()),
-      year: Some(2024),
-      genre: Some("Valid Genre".to_string()),
-      track: Some(Position {
-        no: Some(1),
-        of: Some(10),
-      }),
-      album_artists: Some(vec!["Valid Album Artist".to_string()]),
-      comment: Some("Valid comment".to_string()),
-      disc: Some(Position {
-        no: Some(1),
-        of: Some(2),
-      }),
+  #[tokio::test]
+  async fn test_read_tags_picture_over_allocation_limit() {
+    use crate::test_support::sample_mp3;
+    let mut data = create_test_image_data();
+    data.resize(64 * 1024, 0);
+    let tags = AudioTags {
+      title: Some("Title".to_string()),
       image: Some(Image {
-        data: create_test_image_data(),
+        data,
         pic_type: AudioImageType::CoverFront,
-        mime_type: Some("image/jpeg".to_string()),
-        description: Some("Valid image".to_string()),
+        ..Default::default()
       }),
-      all_images: None,
+      ..Default::default()
+    };
+    // lofty's own limit doesn't stop the pictures of an MP3
+    let buffer = write_tags_to_buffer(sample_mp3(), tags).await.unwrap();
+    let limited = ReadTagsOptions {
+      max_allocation_bytes: Some(1024),
+      ..Default::default()
     };
+    let error = read_tags_from_buffer_with_options(buffer.clone(), limited.clone())
+      .await
+      .unwrap_err();
+    assert_eq!(error, "Invalid image: 65536 bytes, over the limit of 1024");
+    // without pictures, nothing goes over the limit
+    let without_images = ReadTagsOptions {
+      skip_images: true,
+      ..limited
+    };
+    let outcome = read_tags_from_buffer_with_options(buffer, without_images)
+      .await
+      .unwrap();
+    assert_eq!(outcome.tags.title.as_deref(), Some("Title"));
+  }
 
-    // Test that valid data is accepted
-    assert!(valid_tags.title.is_some());
-    assert!(valid_tags.artists.is_some());
-    assert!(valid_tags.album.is_some());
-    assert!(valid_tags.year.is_some());
-    assert!(valid_tags.genre.is_some());
-    assert!(valid_tags.track.is_some());
-    assert!(valid_tags.album_artists.is_some());
-    assert!(valid_tags.comment.is_some());
-    assert!(valid_tags.disc.is_some());
-    assert!(valid_tags.image.is_some());
+  #[tokio::test]
+  async fn test_write_tags_deterministic() {
+    let audio_data = std::fs::read("music/silence.mp3").expect("Failed to read test file");
+    let title = AudioTags {
+      title: Some("Title".to_string()),
+      ..Default::default()
+    };
+    let album = AudioTags {
+      album: Some("Album".to_string()),
+      ..Default::default()
+    };
+    // the same tags, written in a different order
+    let first = write_tags_to_buffer(audio_data.clone(), title.clone())
+      .await
+      .unwrap();
+    let first = write_tags_to_buffer(first, album.clone()).await.unwrap();
+    let second = write_tags_to_buffer(audio_data, album).await.unwrap();
+    let second = write_tags_to_buffer(second, title).await.unwrap();
 
-    // Test with None values
-    let empty_tags = AudioTags::default();
-    assert!(empty_tags.title.is_none());
-    assert!(empty_tags.artists.is_none());
-    assert!(empty_tags.album.is_none());
-    assert!(empty_tags.year.is_none());
-    assert!(empty_tags.genre.is_none());
-    assert!(empty_tags.track.is_none());
-    assert!(empty_tags.album_artists.is_none());
-    assert!(empty_tags.comment.is_none());
-    assert!(empty_tags.disc.is_none());
-    assert!(empty_tags.image.is_none());
-  }
+    let genre = AudioTags {
+      genre: Some("Ambient".to_string()),
+      ..Default::default()
+    };
+    let regular = (
+      write_tags_to_buffer(first.clone(), genre.clone())
+        .await
+        .unwrap(),
+      write_tags_to_buffer(second.clone(), genre.clone())
+        .await
+        .unwrap(),
+    );
+    assert_ne!(regular.0, regular.1);
 
-  #[test]
-  fn test_audio_tags_performance() {
-    // Test performance with large datasets
-    let start_time = std::time::Instant::now();
+    let options = WriteTagsOptions {
+      deterministic: true,
+      ..Default::default()
+    };
+    let first = write_tags_to_buffer_with_options(first, genre.clone(), options.clone())
+      .await
+      .unwrap();
+    let second = write_tags_to_buffer_with_options(second, genre, options)
+      .await
+      .unwrap();
+    assert_eq!(first, second);
+    let tags = read_tags_from_buffer(first).await.unwrap();
+    assert_eq!(tags.title, Some("Title".to_string()));
+    assert_eq!(tags.album, Some("Album".to_string()));
+    assert_eq!(tags.genre, Some("Ambient".to_string()));
+  }
 
-    let mut tags_vec = Vec::new();
-    for i in 0..1000 {
-      let tags = AudioTags {
-        title: Some(format!("Performance Test {}", i)),
-        artists: Some(vec![format!("Artist {}", i)]),
-        album: Some(format!("Album {}", i)),
-        year: Some(2020 + (i % 5) as u32),
-        genre: Some(format!("Genre {}", i % 10)),
-        track: Some(Position {
-          no: Some((i % 20) + 1),
-          of: Some(20),
-        }),
-        album_artists: Some(vec![format!("Album Artist {}", i)]),
-        comment: Some(format!("Comment {}", i)),
-        disc: Some(Position {
-          no: Some((i % 3) + 1),
-          of: Some(3),
-        }),
-        image: if i % 10 == 0 {
-          Some(Image {
-            data: create_test_image_data(),
-            pic_type: AudioImageType::CoverFront,
-            mime_type: Some("image/jpeg".to_string()),
-            description: Some(format!("Image {}", i)),
-          })
-        } else {
-          None
-        },
-        all_images: None,
-      };
-      tags_vec.push(tags);
-    }
+  #[cfg(unix)]
+  #[tokio::test]
+  async fn test_write_tags_atomic() {
+    use std::os::unix::fs::PermissionsExt;
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("song.mp3");
+    std::fs::copy("music/silence.mp3", &path).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o640)).unwrap();
+    let link = dir.path().join("link.mp3");
+    std::os::unix::fs::symlink(&path, &link).unwrap();
+    let modified =
+      std::fs::metadata(&path).unwrap().modified().unwrap() - std::time::Duration::from_secs(3600);
+    File::options()
+      .write(true)
+      .open(&path)
+      .unwrap()
+      .set_modified(modified)
+      .unwrap();
+    let tags = AudioTags {
+      title: Some("Atomic".to_string()),
+      ..Default::default()
+    };
+    let options = WriteTagsOptions {
+      atomic: true,
+      preserve_mtime: true,
+      ..Default::default()
+    };
 
-    let creation_time = start_time.elapsed();
-    println!("Created 1000 AudioTags in {:?}", creation_time);
+    write_tags_with_options(link.to_string_lossy().to_string(), tags, options)
+      .await
+      .unwrap();
+    let tags = read_tags(path.to_string_lossy().to_string()).await.unwrap();
+    assert_eq!(tags.title, Some("Atomic".to_string()));
+    // the link still points to the file, which kept its permissions and mtime
+    assert!(std::fs::symlink_metadata(&link)
+      .unwrap()
+      .file_type()
+      .is_symlink());
+    let metadata = std::fs::metadata(&path).unwrap();
+    assert_eq!(metadata.permissions().mode() & 0o777, 0o640);
+    assert_eq!(metadata.modified().unwrap(), modified);
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+  }
 
-    // Verify all tags were created correctly
-    assert_eq!(tags_vec.len(), 1000);
-    assert_eq!(tags_vec[0].title, Some("Performance Test 0".to_string()));
+  #[tokio::test]
+  async fn test_backup_before_write() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("song.mp3");
+    std::fs::copy("music/silence.mp3", &path).unwrap();
+    let file_path = path.to_string_lossy().to_string();
+    let original = std::fs::read(&path).unwrap();
+
+    let options = WriteTagsOptions {
+      backup: Some(Backup::Suffix(".bak".to_string())),
+      ..Default::default()
+    };
+    let tags = AudioTags {
+      title: Some("Backed up".to_string()),
+      ..Default::default()
+    };
+    write_tags_with_options(file_path.clone(), tags, options)
+      .await
+      .unwrap();
     assert_eq!(
-      tags_vec[999].title,
-      Some("Performance Test 999".to_string())
+      std::fs::read(dir.path().join("song.mp3.bak")).unwrap(),
+      original
     );
 
-    // Test iteration performance
-    let iteration_start = std::time::Instant::now();
-    let mut title_count = 0;
-    for tags in &tags_vec {
-      if tags.title.is_some() {
-        title_count += 1;
-      }
-    }
-    let iteration_time = iteration_start.elapsed();
-    println!("Iterated through 1000 AudioTags in {:?}", iteration_time);
+    let backups = dir.path().join("backups");
+    let options = ClearTagsOptions {
+      backup: Some(Backup::Dir(backups.to_string_lossy().to_string())),
+      ..Default::default()
+    };
+    clear_tags_with_options(file_path.clone(), options)
+      .await
+      .unwrap();
+    let copy = read_tags(backups.join("song.mp3").to_string_lossy().to_string())
+      .await
+      .unwrap();
+    assert_eq!(copy.title, Some("Backed up".to_string()));
+    assert_eq!(read_tags(file_path).await.unwrap().title, None);
+  }
+
+  #[tokio::test]
+  async fn test_preserve_times() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("song.mp3");
+    std::fs::copy("music/silence.mp3", &path).unwrap();
+    let file_path = path.to_string_lossy().to_string();
+    let accessed =
+      std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_500_000_000);
+    let modified = accessed - std::time::Duration::from_secs(3600);
+    File::options()
+      .write(true)
+      .open(&path)
+      .unwrap()
+      .set_times(
+        std::fs::FileTimes::new()
+          .set_accessed(accessed)
+          .set_modified(modified),
+      )
+      .unwrap();
+    let options = SaveOptions {
+      preserve_mtime: true,
+    };
 
-    assert_eq!(title_count, 1000);
+    let mut patch = TagPatch::default();
+    patch.set.title = Some("Kept".to_string());
+    update_tags_with_options(file_path.clone(), patch, options.clone())
+      .await
+      .unwrap();
+    write_lyrics_with_options(file_path.clone(), "La la".to_string(), options)
+      .await
+      .unwrap();
+    let metadata = std::fs::metadata(&path).unwrap();
+    assert_eq!(metadata.modified().unwrap(), modified);
+    assert_eq!(metadata.accessed().unwrap(), accessed);
+    let tags = read_tags(file_path.clone()).await.unwrap();
+    assert_eq!(tags.title, Some("Kept".to_string()));
+    assert_eq!(tags.lyrics, Some("La la".to_string()));
+
+    write_lyrics(file_path, "Changed".to_string())
+      .await
+      .unwrap();
+    assert_ne!(
+      std::fs::metadata(&path).unwrap().modified().unwrap(),
+      modified
+    );
   }
 
-  #[test]
-  fn test_audio_tags_concurrent_access() {
-    // Test that multiple threads can safely access the same data
-    use std::sync::Arc;
-    use std::thread;
-
-    let shared_tags = Arc::new(AudioTags {
-      title: Some("Concurrent Test".to_string()),
-      artists: Some(vec!["Concurrent Artist".to_string()]),
-      album: Some("Concurrent Album".to_string()),
+  #[tokio::test]
+  async fn test_write_tags_skip_unchanged() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("song.mp3");
+    std::fs::copy("music/silence.mp3", &path).unwrap();
+    let path = path.to_string_lossy().to_string();
+    let tags = AudioTags {
+      title: Some("Title".to_string()),
+      artists: Some(vec!["A".to_string(), "B".to_string()]),
       year: Some(2024),
-      genre: Some("Concurrent Genre".to_string()),
       track: Some(Position {
         no: Some(1),
-        of: Some(5),
-      }),
-      album_artists: Some(vec!["Concurrent Album Artist".to_string()]),
-      comment: Some("Concurrent comment".to_string()),
-      disc: Some(Position {
-        no: Some(1),
-        of: Some(2),
+        of: Some(9),
       }),
       image: Some(Image {
         data: create_test_image_data(),
-        pic_type: AudioImageType::CoverFront,
-        mime_type: Some("image/jpeg".to_string()),
-        description: Some("Concurrent image".to_string()),
+        ..Default::default()
       }),
-      all_images: None,
-    });
+      ..Default::default()
+    };
+    let options = WriteTagsOptions {
+      skip_unchanged: true,
+      journal: true,
+      ..Default::default()
+    };
 
-    let mut handles = vec![];
+    let outcome = write_tags_with_options(path.clone(), tags.clone(), options.clone())
+      .await
+      .unwrap();
+    assert!(outcome.changed);
+    let written = std::fs::read(&path).unwrap();
 
-    // Spawn multiple threads to read from the shared tags
-    for i in 0..10 {
-      let tags_ref = Arc::clone(&shared_tags);
-      let handle = thread::spawn(move || {
-        // Each thread reads the same data
-        assert_eq!(tags_ref.title, Some("Concurrent Test".to_string()));
-        assert_eq!(tags_ref.year, Some(2024));
-        assert_eq!(
-          tags_ref.artists,
-          Some(vec!["Concurrent Artist".to_string()])
-        );
-        println!("Thread {} completed successfully", i);
-      });
-      handles.push(handle);
+    // a subset of what is already there changes nothing
+    let subset = AudioTags {
+      title: tags.title.clone(),
+      ..Default::default()
+    };
+    for tags in [tags.clone(), subset] {
+      let outcome = write_tags_with_options(path.clone(), tags, options.clone())
+        .await
+        .unwrap();
+      assert!(!outcome.changed);
+      assert_eq!(std::fs::read(&path).unwrap(), written);
     }
+    // only the first write left something to undo
+    assert!(crate::journal::undo_last_write(path.clone(), None)
+      .await
+      .unwrap());
+    assert!(!crate::journal::undo_last_write(path.clone(), None)
+      .await
+      .unwrap());
 
-    // Wait for all threads to complete
-    for handle in handles {
-      handle.join().unwrap();
-    }
+    let retitled = AudioTags {
+      title: Some("Other".to_string()),
+      ..Default::default()
+    };
+    let unchanged = write_tags_to_buffer_with_options(written.clone(), tags, options.clone())
+      .await
+      .unwrap();
+    assert_eq!(unchanged, written);
+    let changed = write_tags_to_buffer_with_options(written.clone(), retitled, options)
+      .await
+      .unwrap();
+    assert_ne!(changed, written);
   }
 
-  #[test]
-  fn test_audio_tags_edge_case_combinations() {
-    // Test various edge case combinations
-    let edge_cases = vec![
-      // All None
-      AudioTags::default(),
-      // Only title
-      AudioTags {
-        title: Some("Title Only".to_string()),
-        ..Default::default()
-      },
-      // Only year
-      AudioTags {
-        year: Some(2024),
-        ..Default::default()
-      },
-      // Only artists
-      AudioTags {
-        artists: Some(vec!["Artist Only".to_string()]),
+  #[tokio::test]
+  async fn test_write_tags_with_artwork_policy() {
+    let audio_data = std::fs::read("music/silence.mp3").expect("Failed to read test file");
+    let tags = AudioTags {
+      title: Some("Title".to_string()),
+      image: Some(Image {
+        data: create_test_image_data(),
+        description: Some("Front".to_string()),
         ..Default::default()
-      },
-      // Only track
-      AudioTags {
-        track: Some(Position {
-          no: Some(1),
-          of: Some(1),
-        }),
+      }),
+      ..Default::default()
+    };
+    let options = WriteTagsOptions {
+      artwork_policy: ArtworkPolicy {
+        id3_picture_encoding: placement::Id3PictureEncoding::Latin1,
         ..Default::default()
       },
-      // Only image
-      AudioTags {
+      ..Default::default()
+    };
+    let buffer = write_tags_to_buffer_with_options(audio_data, tags, options)
+      .await
+      .unwrap();
+    let tags = read_tags_from_buffer(buffer).await.unwrap();
+    assert_eq!(tags.title, Some("Title".to_string()));
+    let image = tags.image.unwrap();
+    assert_eq!(image.data, create_test_image_data());
+    assert_eq!(image.description, Some("Front".to_string()));
+  }
+
+  #[tokio::test]
+  async fn test_write_extended_text_fields() {
+    let audio_data = std::fs::read("music/silence.mp3").expect("Failed to read test file");
+    let tags = AudioTags {
+      composer: Some("Johann Sebastian Bach".to_string()),
+      lyricist: Some("Picander".to_string()),
+      conductor: Some("Karl Richter".to_string()),
+      remixer: Some("Someone Else".to_string()),
+      publisher: Some("Archiv Produktion".to_string()),
+      copyright: Some("1959 Deutsche Grammophon".to_string()),
+      ..Default::default()
+    };
+    let buffer = write_tags_to_buffer(audio_data, tags.clone())
+      .await
+      .unwrap();
+    let read = read_tags_from_buffer(buffer).await.unwrap();
+    assert_eq!(read.composer, tags.composer);
+    assert_eq!(read.lyricist, tags.lyricist);
+    assert_eq!(read.conductor, tags.conductor);
+    assert_eq!(read.remixer, tags.remixer);
+    assert_eq!(read.publisher, tags.publisher);
+    assert_eq!(read.copyright, tags.copyright);
+
+    // MP4 has no publisher field, the label is used instead
+    let mut mp4 = Tag::new(lofty::tag::TagType::Mp4Ilst);
+    tags.to_tag(&mut mp4);
+    assert_eq!(mp4.get_string(&ItemKey::Label), Some("Archiv Produktion"));
+    assert_eq!(AudioTags::from_tag(&mp4).publisher, tags.publisher);
+  }
+
+  #[tokio::test]
+  async fn test_write_lyrics() {
+    let audio_data = std::fs::read("music/silence.mp3").expect("Failed to read test file");
+    let tags = AudioTags {
+      title: Some("Song".to_string()),
+      ..Default::default()
+    };
+    let tagged = write_tags_to_buffer(audio_data, tags).await.unwrap();
+    assert_eq!(read_lyrics_from_buffer(tagged.clone()).await.unwrap(), None);
+
+    let lyrics = "First line\nSecond line".to_string();
+    let buffer = write_lyrics_to_buffer(tagged, lyrics.clone())
+      .await
+      .unwrap();
+    assert_eq!(
+      read_lyrics_from_buffer(buffer.clone()).await.unwrap(),
+      Some(lyrics.clone())
+    );
+    // the other fields are untouched
+    let read = read_tags_from_buffer(buffer.clone()).await.unwrap();
+    assert_eq!(read.title, Some("Song".to_string()));
+    assert_eq!(read.lyrics, Some(lyrics));
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("song.mp3");
+    std::fs::write(&path, buffer).unwrap();
+    let path = path.to_string_lossy().to_string();
+    write_lyrics(path.clone(), "Replaced".to_string())
+      .await
+      .unwrap();
+    assert_eq!(
+      read_lyrics(path).await.unwrap(),
+      Some("Replaced".to_string())
+    );
+  }
+
+  #[tokio::test]
+  async fn test_write_chapters() {
+    let chapters = vec![
+      Chapter {
+        start_ms: 0,
+        end_ms: 1000,
+        title: Some("Intro".to_string()),
+        url: Some("https://example.com".to_string()),
         image: Some(Image {
           data: create_test_image_data(),
-          pic_type: AudioImageType::CoverFront,
+          pic_type: AudioImageType::Other,
           mime_type: Some("image/jpeg".to_string()),
-          description: Some("Image Only".to_string()),
+          ..Default::default()
         }),
+      },
+      Chapter {
+        start_ms: 1000,
+        end_ms: 2000,
+        title: Some("Outro".to_string()),
+        ..Default::default()
+      },
+    ];
+    let audio_data = std::fs::read("music/silence.mp3").expect("Failed to read test file");
+    let buffer = write_chapters_to_buffer(audio_data, chapters.clone())
+      .await
+      .unwrap();
+    assert_eq!(
+      read_chapters_from_buffer(buffer.clone()).await.unwrap(),
+      chapters
+    );
+    // the frames survive other writes
+    let tags = AudioTags {
+      title: Some("Episode".to_string()),
+      ..Default::default()
+    };
+    let buffer = write_tags_to_buffer(buffer, tags).await.unwrap();
+    assert_eq!(read_chapters_from_buffer(buffer).await.unwrap(), chapters);
+
+    // MP4 chapter tracks only hold titles, and lofty keeps them when tagging
+    let buffer = write_chapters_to_buffer(crate::test_support::sample_m4a(), chapters.clone())
+      .await
+      .unwrap();
+    let tags = AudioTags {
+      title: Some("Episode".to_string()),
+      ..Default::default()
+    };
+    let buffer = write_tags_to_buffer(buffer, tags).await.unwrap();
+    let read = read_chapters_from_buffer(buffer.clone()).await.unwrap();
+    let titles: Vec<_> = read
+      .iter()
+      .map(|chapter| chapter.title.as_deref())
+      .collect();
+    assert_eq!(titles, vec![Some("Intro"), Some("Outro")]);
+    assert_eq!(read[1].start_ms, 1000);
+    let read = read_tags_from_buffer(buffer).await.unwrap();
+    assert_eq!(read.title, Some("Episode".to_string()));
+
+    // files are written in place, MP4 files as a whole
+    let dir = tempfile::tempdir().unwrap();
+    let mp3 = std::fs::read("music/silence.mp3").unwrap();
+    let m4a = crate::test_support::sample_m4a();
+    for (name, data) in [("episode.mp3", mp3), ("episode.m4a", m4a)] {
+      let path = crate::test_support::write_fixture(dir.path(), name, &data);
+      write_chapters(path.clone(), chapters.clone())
+        .await
+        .unwrap();
+      let read = read_chapters(path).await.unwrap();
+      assert_eq!(read[0].title, Some("Intro".to_string()));
+      assert_eq!(read[1].end_ms, 2000);
+    }
+
+    let error = write_chapters_to_buffer(empty_flac(), chapters)
+      .await
+      .unwrap_err();
+    assert_eq!(error, "Flac files don't support chapters");
+  }
+
+  #[tokio::test]
+  async fn test_write_synced_lyrics() {
+    let audio_data = std::fs::read("music/silence.mp3").expect("Failed to read test file");
+    let lyrics = synced_lyrics::parse_lrc("[00:01.00]Hello\n[00:02.50]World\n");
+    let tags = AudioTags {
+      title: Some("Song".to_string()),
+      lyrics: Some("Hello World".to_string()),
+      ..Default::default()
+    };
+    let tagged = write_tags_to_buffer(audio_data, tags).await.unwrap();
+    assert_eq!(
+      read_synced_lyrics_from_buffer(tagged.clone())
+        .await
+        .unwrap(),
+      None
+    );
+
+    let buffer = write_synced_lyrics_to_buffer(tagged, lyrics.clone())
+      .await
+      .unwrap();
+    assert_eq!(
+      read_synced_lyrics_from_buffer(buffer.clone())
+        .await
+        .unwrap(),
+      Some(lyrics.clone())
+    );
+    // the SYLT frame sits next to the plain lyrics and survives other writes
+    let retitled = AudioTags {
+      title: Some("Other".to_string()),
+      ..Default::default()
+    };
+    let buffer = write_tags_to_buffer(buffer, retitled).await.unwrap();
+    let read = read_tags_from_buffer(buffer.clone()).await.unwrap();
+    assert_eq!(read.lyrics, Some("Hello World".to_string()));
+    assert_eq!(
+      read_synced_lyrics_from_buffer(buffer).await.unwrap(),
+      Some(lyrics)
+    );
+  }
+
+  #[tokio::test]
+  async fn test_write_custom_fields() {
+    let audio_data = std::fs::read("music/silence.mp3").expect("Failed to read test file");
+    let fields = |entries: &[(&str, &str)]| -> BTreeMap<String, Vec<String>> {
+      entries
+        .iter()
+        .map(|(name, value)| (name.to_string(), vec![value.to_string()]))
+        .collect()
+    };
+    let tags = AudioTags {
+      custom_fields: Some(fields(&[("RELEASECOUNTRY", "GB"), ("TOWN", "Me")])),
+      ..Default::default()
+    };
+    let buffer = write_tags_to_buffer(audio_data, tags).await.unwrap();
+    let read = read_tags_from_buffer(buffer.clone()).await.unwrap();
+    assert_eq!(
+      read.custom_fields,
+      Some(fields(&[("RELEASECOUNTRY", "GB"), ("TOWN", "Me")]))
+    );
+
+    // fields that are not listed survive a later write
+    let tags = AudioTags {
+      title: Some("Title".to_string()),
+      custom_fields: Some(fields(&[("TOWN", "You")])),
+      ..Default::default()
+    };
+    let buffer = write_tags_to_buffer(buffer, tags).await.unwrap();
+    let read = read_tags_from_buffer(buffer).await.unwrap();
+    let custom_fields = read.custom_fields.unwrap();
+    assert_eq!(custom_fields["RELEASECOUNTRY"], vec!["GB".to_string()]);
+    assert_eq!(custom_fields["TOWN"], vec!["You".to_string()]);
+  }
+
+  #[tokio::test]
+  async fn test_write_mp4_freeform_atoms() {
+    use crate::test_support::{sample_m4a, sample_mp3};
+
+    let custom_fields: BTreeMap<String, Vec<String>> = [
+      ("iTunNORM", " 00000A3C 00000B1F"),
+      ("----:com.example.app:state", "synced"),
+    ]
+    .into_iter()
+    .map(|(name, value)| (name.to_string(), vec![value.to_string()]))
+    .collect();
+    let atoms: BTreeMap<String, Vec<u8>> = [
+      ("Encoding Params", vec![b'v', b'e', b'r', b's', 0, 0, 0, 1]),
+      ("----:com.example.app:blob", vec![0xFF, 0x00, 0x7F]),
+    ]
+    .into_iter()
+    .map(|(name, data)| (name.to_string(), data))
+    .collect();
+    let tags = AudioTags {
+      custom_fields: Some(custom_fields.clone()),
+      mp4_freeform: Some(atoms.clone()),
+      ..Default::default()
+    };
+    let buffer = write_tags_to_buffer(sample_m4a(), tags).await.unwrap();
+    let read = read_tags_from_buffer(buffer.clone()).await.unwrap();
+    let read_fields = read.custom_fields.unwrap();
+    assert_eq!(read_fields["iTunNORM"], custom_fields["iTunNORM"]);
+    assert_eq!(
+      read_fields["----:com.example.app:state"],
+      custom_fields["----:com.example.app:state"]
+    );
+    assert_eq!(read.mp4_freeform, Some(atoms.clone()));
+
+    // atoms survive writes that don't list them, an empty one is removed
+    let tags = AudioTags {
+      title: Some("Title".to_string()),
+      mp4_freeform: Some(BTreeMap::from([("Encoding Params".to_string(), vec![])])),
+      ..Default::default()
+    };
+    let buffer = write_tags_to_buffer(buffer, tags).await.unwrap();
+    let read = read_tags_from_buffer(buffer).await.unwrap();
+    assert_eq!(read.title, Some("Title".to_string()));
+    assert_eq!(
+      read.mp4_freeform.unwrap().keys().collect::<Vec<_>>(),
+      ["----:com.example.app:blob"]
+    );
+
+    // ignored for other formats
+    let tags = AudioTags {
+      mp4_freeform: Some(atoms),
+      ..Default::default()
+    };
+    let buffer = write_tags_to_buffer(sample_mp3(), tags).await.unwrap();
+    let read = read_tags_from_buffer(buffer).await.unwrap();
+    assert_eq!(read.mp4_freeform, None);
+  }
+
+  #[tokio::test]
+  async fn test_write_binary_objects() {
+    use crate::test_support::sample_mp3;
+
+    let markers = BinaryObject {
+      description: "Serato Markers2".to_string(),
+      mime_type: Some("application/octet-stream".to_string()),
+      filename: None,
+      data: vec![0x01, 0x01, 0x00, 0xFF, 0x00],
+    };
+    let tags = AudioTags {
+      binary_objects: Some(vec![markers.clone()]),
+      ..Default::default()
+    };
+    let buffer = write_tags_to_buffer(sample_mp3(), tags).await.unwrap();
+    let read = read_tags_from_buffer(buffer.clone()).await.unwrap();
+    assert_eq!(read.binary_objects, Some(vec![markers.clone()]));
+
+    // kept by writes that don't list them, and by ID3v2.3 writes
+    let options = WriteTagsOptions {
+      id3v2_version: Id3v2Version::V3,
+      ..Default::default()
+    };
+    let tags = AudioTags {
+      title: Some("Title".to_string()),
+      ..Default::default()
+    };
+    let buffer = write_tags_to_buffer_with_options(buffer, tags, options)
+      .await
+      .unwrap();
+    let read = read_tags_from_buffer(buffer.clone()).await.unwrap();
+    assert_eq!(read.binary_objects, Some(vec![markers.clone()]));
+
+    // tags read from another file leave them alone when preserved
+    let tags = AudioTags {
+      binary_objects: Some(vec![]),
+      ..Default::default()
+    };
+    let options = WriteTagsOptions {
+      preserve_binary_objects: true,
+      ..Default::default()
+    };
+    let preserved = write_tags_to_buffer_with_options(buffer.clone(), tags.clone(), options)
+      .await
+      .unwrap();
+    let read = read_tags_from_buffer(preserved).await.unwrap();
+    assert_eq!(read.binary_objects, Some(vec![markers]));
+
+    let buffer = write_tags_to_buffer(buffer, tags).await.unwrap();
+    let read = read_tags_from_buffer(buffer).await.unwrap();
+    assert_eq!(read.binary_objects, None);
+    assert_eq!(read.title, Some("Title".to_string()));
+  }
+
+  #[tokio::test]
+  async fn test_write_identifiers() {
+    let audio_data = std::fs::read("music/silence.mp3").expect("Failed to read test file");
+    let tags = AudioTags {
+      isrc: Some("USRC17607839".to_string()),
+      barcode: Some("4006381333931".to_string()),
+      catalog_number: Some("WARPCD92".to_string()),
+      musicbrainz_recording_id: Some("b1a9c0e9-d987-4042-ae91-78d6a3267d69".to_string()),
+      musicbrainz_track_id: Some("0f9b7d5c-3e0a-4a8e-9f0f-1c2b3d4e5f60".to_string()),
+      musicbrainz_release_id: Some("f4a261c5-6ebb-4a0f-a0b6-0d5a0c1f0c3e".to_string()),
+      musicbrainz_release_group_id: Some("2c9a1e3b-7d4f-4b8a-8e2d-5f6a7b8c9d0e".to_string()),
+      musicbrainz_artist_id: Some("a74b1b7f-71a5-4011-9441-d0b5e4122711".to_string()),
+      musicbrainz_album_artist_id: Some("a74b1b7f-71a5-4011-9441-d0b5e4122711".to_string()),
+      ..Default::default()
+    };
+    let buffer = write_tags_to_buffer(audio_data.clone(), tags.clone())
+      .await
+      .unwrap();
+    let read = read_tags_from_buffer(buffer).await.unwrap();
+    assert_eq!(read.isrc, tags.isrc);
+    assert_eq!(read.barcode, tags.barcode);
+    assert_eq!(read.catalog_number, tags.catalog_number);
+    assert_eq!(read.musicbrainz_recording_id, tags.musicbrainz_recording_id);
+    assert_eq!(read.musicbrainz_track_id, tags.musicbrainz_track_id);
+    assert_eq!(read.musicbrainz_release_id, tags.musicbrainz_release_id);
+    assert_eq!(
+      read.musicbrainz_release_group_id,
+      tags.musicbrainz_release_group_id
+    );
+    assert_eq!(read.musicbrainz_artist_id, tags.musicbrainz_artist_id);
+    assert_eq!(
+      read.musicbrainz_album_artist_id,
+      tags.musicbrainz_album_artist_id
+    );
+
+    let malformed = AudioTags {
+      isrc: Some("not an isrc".to_string()),
+      ..Default::default()
+    };
+    let result = write_tags_to_buffer(audio_data.clone(), malformed.clone()).await;
+    assert_eq!(result.unwrap_err(), "Invalid ISRC: not an isrc");
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("song.mp3");
+    std::fs::write(&path, &audio_data).unwrap();
+    let path = path.to_string_lossy().to_string();
+    for (identifier_validation, warnings) in [
+      (
+        IdentifierValidation::Warn,
+        vec!["Invalid ISRC: not an isrc".to_string()],
+      ),
+      (IdentifierValidation::PassThrough, Vec::new()),
+    ] {
+      let options = WriteTagsOptions {
+        identifier_validation,
         ..Default::default()
-      },
-      // All Some but empty
-      AudioTags {
-        title: Some("".to_string()),
-        artists: Some(vec![]),
-        album: Some("".to_string()),
-        year: Some(0),
-        genre: Some("".to_string()),
-        track: Some(Position { no: None, of: None }),
-        album_artists: Some(vec![]),
-        comment: Some("".to_string()),
-        disc: Some(Position { no: None, of: None }),
-        image: Some(Image {
-          data: vec![],
-          pic_type: AudioImageType::CoverFront,
-          mime_type: Some("".to_string()),
-          description: Some("".to_string()),
-        }),
-        all_images: None,
-      },
-    ];
+      };
+      let outcome = write_tags_with_options(path.clone(), malformed.clone(), options)
+        .await
+        .unwrap();
+      assert_eq!(outcome.warnings, warnings);
+      let tags = read_tags(path.clone()).await.unwrap();
+      assert_eq!(tags.isrc, malformed.isrc);
+    }
+  }
 
-    for (i, tags) in edge_cases.iter().enumerate() {
-      // Each edge case should be valid
-      assert!(
-        tags.title.is_some() || tags.title.is_none(),
-        "Edge case {} title",
-        i
-      );
-      assert!(
-        tags.artists.is_some() || tags.artists.is_none(),
-        "Edge case {} artists",
-        i
-      );
-      assert!(
-        tags.album.is_some() || tags.album.is_none(),
-        "Edge case {} album",
-        i
-      );
-      assert!(
-        tags.year.is_some() || tags.year.is_none(),
-        "Edge case {} year",
-        i
-      );
-      assert!(
-        tags.genre.is_some() || tags.genre.is_none(),
-        "Edge case {} genre",
-        i
-      );
-      assert!(
-        tags.track.is_some() || tags.track.is_none(),
-        "Edge case {} track",
-        i
-      );
-      assert!(
-        tags.album_artists.is_some() || tags.album_artists.is_none(),
-        "Edge case {} album_artists",
-        i
-      );
-      assert!(
-        tags.comment.is_some() || tags.comment.is_none(),
-        "Edge case {} comment",
-        i
-      );
-      assert!(
-        tags.disc.is_some() || tags.disc.is_none(),
-        "Edge case {} disc",
-        i
-      );
-      assert!(
-        tags.image.is_some() || tags.image.is_none(),
-        "Edge case {} image",
-        i
-      );
+  // silence.mp3 with the frames MusicBrainz Picard adds next to the usual ones
+  fn picard_tagged_mp3() -> Vec<u8> {
+    use lofty::id3::v2::{
+      ExtendedTextFrame, Frame, FrameId, PopularimeterFrame, TextInformationFrame,
+      UniqueFileIdentifierFrame, UrlLinkFrame,
+    };
+    use lofty::mpeg::MpegFile;
+    use lofty::TextEncoding;
+    use std::borrow::Cow;
+
+    let audio_data = std::fs::read("music/silence.mp3").expect("Failed to read test file");
+    let mut file =
+      MpegFile::read_from(&mut Cursor::new(audio_data.clone()), ParseOptions::new()).unwrap();
+    let mut id3v2 = file.id3v2().cloned().unwrap_or_default();
+    for (description, value) in [
+      (
+        "MusicBrainz Album Id",
+        "f4a261c5-6ebb-4a0f-a0b6-0d5a0c1f0c3e",
+      ),
+      ("MusicBrainz Album Type", "album"),
+      ("ASIN", "B000002UAL"),
+      ("SCRIPT", "Latn"),
+    ] {
+      id3v2.insert(Frame::UserText(ExtendedTextFrame::new(
+        TextEncoding::UTF8,
+        description.to_string(),
+        value.to_string(),
+      )));
+    }
+    for (id, value) in [("TSOP", "Artist, The"), ("TMED", "CD"), ("TDOR", "1999")] {
+      id3v2.insert(Frame::Text(TextInformationFrame::new(
+        FrameId::Valid(Cow::Borrowed(id)),
+        TextEncoding::UTF8,
+        value.to_string(),
+      )));
+    }
+    id3v2.insert(Frame::UniqueFileIdentifier(UniqueFileIdentifierFrame::new(
+      "http://musicbrainz.org".to_string(),
+      b"b1a9c0e9-d987-4042-ae91-78d6a3267d69".to_vec(),
+    )));
+    id3v2.insert(Frame::Url(UrlLinkFrame::new(
+      FrameId::Valid(Cow::Borrowed("WOAR")),
+      "https://musicbrainz.org/artist/a74b1b7f-71a5-4011-9441-d0b5e4122711".to_string(),
+    )));
+    id3v2.insert(Frame::Popularimeter(PopularimeterFrame::new(
+      "users@musicbrainz.org".to_string(),
+      196,
+      3,
+    )));
+    file.set_id3v2(id3v2);
+    let mut out = Cursor::new(audio_data);
+    file.save_to(&mut out, WriteOptions::default()).unwrap();
+    out.into_inner()
+  }
+
+  #[tokio::test]
+  async fn test_write_tags_preserve_unknown() {
+    use lofty::mpeg::MpegFile;
+
+    let frames = |data: &[u8]| {
+      let file = MpegFile::read_from(&mut Cursor::new(data), ParseOptions::new()).unwrap();
+      let frames: Vec<_> = file.id3v2().unwrap().clone().into_iter().collect();
+      frames
+    };
+    let audio_data = picard_tagged_mp3();
+    let before = frames(&audio_data);
+
+    let tags = AudioTags {
+      title: Some("Title".to_string()),
+      artists: Some(vec!["Artist".to_string()]),
+      ..Default::default()
+    };
+    let options = WriteTagsOptions {
+      preserve_unknown: true,
+      ..Default::default()
+    };
+    let buffer = write_tags_to_buffer_with_options(audio_data, tags, options)
+      .await
+      .unwrap();
+    let after = frames(&buffer);
+    for frame in &before {
+      assert!(after.contains(frame), "{:?} was dropped", frame);
     }
+    let read = read_tags_from_buffer(buffer).await.unwrap();
+    assert_eq!(read.title, Some("Title".to_string()));
+    assert_eq!(
+      read.musicbrainz_recording_id,
+      Some("b1a9c0e9-d987-4042-ae91-78d6a3267d69".to_string())
+    );
   }
 
   #[test]
-  fn test_audio_tags_serialization_roundtrip() {
-    // Test that we can serialize and deserialize data
-    let original_tags = AudioTags {
-      title: Some("Serialization Roundtrip".to_string()),
-      artists: Some(vec!["Serialization Artist".to_string()]),
-      album: Some("Serialization Album".to_string()),
-      year: Some(2024),
-      genre: Some("Serialization Genre".to_string()),
-      track: Some(Position {
-        no: Some(2),
-        of: Some(8),
-      }),
-      album_artists: Some(vec!["Serialization Album Artist".to_string()]),
-      comment: Some("Serialization comment".to_string()),
-      disc: Some(Position {
-        no: Some(1),
-        of: Some(3),
-      }),
-      image: Some(Image {
-        data: create_test_image_data(),
-        pic_type: AudioImageType::CoverFront,
-        mime_type: Some("image/png".to_string()),
-        description: Some("Serialization image".to_string()),
-      }),
-      all_images: None,
+  fn test_restore_items() {
+    let item = |key: ItemKey, value: &str| TagItem::new(key, ItemValue::Text(value.to_string()));
+    let mut tag = Tag::new(TagType::VorbisComments);
+    tag.push(item(ItemKey::TrackTitle, "Old Title"));
+    tag.push_unchecked(item(ItemKey::Unknown("ASIN".to_string()), "B000002UAL"));
+    tag.push(item(ItemKey::Script, "Latn"));
+    let before: Vec<TagItem> = tag.items().cloned().collect();
+
+    tag.remove_key(&ItemKey::TrackTitle);
+    tag.remove_key(&ItemKey::Unknown("ASIN".to_string()));
+    tag.push(item(ItemKey::TrackTitle, "New Title"));
+    restore_items(&mut tag, before, &[ItemKey::TrackTitle]);
+    assert_eq!(tag.get_string(&ItemKey::TrackTitle), Some("New Title"));
+    assert_eq!(
+      tag.get_string(&ItemKey::Unknown("ASIN".to_string())),
+      Some("B000002UAL")
+    );
+    assert_eq!(tag.get_items(&ItemKey::Script).count(), 1);
+    assert_eq!(tag.items().count(), 3);
+  }
+
+  #[tokio::test]
+  async fn test_write_cover_image_to_buffer_with_options() {
+    let audio_data = std::fs::read("music/silence.mp3").expect("Failed to read test file");
+    let front = create_test_image_data();
+    let back = vec![0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A];
+    let buffer = write_cover_image_to_buffer(audio_data, front.clone())
+      .await
+      .unwrap();
+
+    let options = CoverImageOptions {
+      pic_type: AudioImageType::CoverBack,
+      description: Some("Back".to_string()),
+      mime_type: Some("image/webp".to_string()),
+      ..Default::default()
     };
+    let buffer = write_cover_image_to_buffer_with_options(buffer, back.clone(), options.clone())
+      .await
+      .unwrap();
+    // writing the same type again replaces the picture
+    let buffer = write_cover_image_to_buffer_with_options(buffer, back.clone(), options)
+      .await
+      .unwrap();
 
-    // Simulate serialization by creating a copy
-    let serialized_tags = AudioTags {
-      title: original_tags.title.clone(),
-      artists: original_tags.artists.clone(),
-      album: original_tags.album.clone(),
-      year: original_tags.year,
-      genre: original_tags.genre.clone(),
-      track: match &original_tags.track {
-        Some(position) => Some(Position {
-          no: position.no,
-          of: position.of,
-        }),
-        None => None,
-      },
-      album_artists: original_tags.album_artists.clone(),
-      comment: original_tags.comment.clone(),
-      disc: match &original_tags.disc {
-        Some(position) => Some(Position {
-          no: position.no,
-          of: position.of,
-        }),
-        None => None,
-      },
-      image: match original_tags.image {
-        Some(image) => Some(Image {
-          data: image.data.clone(),
-          pic_type: image.pic_type,
-          mime_type: image.mime_type.clone(),
-          description: image.description.clone(),
-        }),
-        None => None,
-      },
-      all_images: None,
+    let tags = read_tags_from_buffer(buffer).await.unwrap();
+    let images = tags.all_images.unwrap();
+    assert_eq!(images.len(), 2);
+    assert_eq!(images[0].data, front);
+    assert_eq!(images[0].pic_type, AudioImageType::CoverFront);
+    assert_eq!(images[1].data, back);
+    assert_eq!(images[1].pic_type, AudioImageType::CoverBack);
+    assert_eq!(images[1].description, Some("Back".to_string()));
+    assert_eq!(images[1].mime_type, Some("image/webp".to_string()));
+  }
+
+  #[tokio::test]
+  async fn test_write_and_read_opus_pictures() {
+    use crate::test_support::sample_opus;
+
+    let front = std::fs::read("test-files/cover.jpg").expect("Failed to read cover");
+    let back = create_test_image_data();
+    let buffer = write_cover_image_to_buffer(sample_opus(), front.clone())
+      .await
+      .unwrap();
+    let options = CoverImageOptions {
+      pic_type: AudioImageType::CoverBack,
+      description: Some("Back".to_string()),
+      ..Default::default()
     };
+    let buffer = write_cover_image_to_buffer_with_options(buffer, back.clone(), options)
+      .await
+      .unwrap();
 
-    // Verify roundtrip
-    assert_eq!(original_tags.title, serialized_tags.title);
-    assert_eq!(original_tags.artists, serialized_tags.artists);
-    assert_eq!(original_tags.album, serialized_tags.album);
-    assert_eq!(original_tags.year, serialized_tags.year);
-    assert_eq!(original_tags.genre, serialized_tags.genre);
-    assert_eq!(original_tags.track, serialized_tags.track);
-    assert_eq!(original_tags.album_artists, serialized_tags.album_artists);
-    assert_eq!(original_tags.comment, serialized_tags.comment);
-    assert_eq!(original_tags.disc, serialized_tags.disc);
+    // the pictures are base64 comments, spread over several Ogg pages
+    let key = b"METADATA_BLOCK_PICTURE=";
+    let count = buffer.windows(key.len()).filter(|w| w == key).count();
+    assert_eq!(count, 2);
+    assert!(buffer.len() > front.len() * 4 / 3);
+
+    let tags = read_tags_from_buffer(buffer.clone()).await.unwrap();
+    let images = tags.all_images.unwrap();
+    assert_eq!(images.len(), 2);
+    assert_eq!(images[0].data, front);
+    assert_eq!(images[0].mime_type, Some("image/jpeg".to_string()));
+    assert_eq!(images[1].data, back);
+    assert_eq!(images[1].description, Some("Back".to_string()));
+    assert_eq!(
+      read_image_from_buffer(buffer.clone(), AudioImageType::CoverBack)
+        .await
+        .unwrap(),
+      Some(back)
+    );
+
+    let buffer = remove_images_from_buffer(buffer, Some(vec![AudioImageType::CoverFront]))
+      .await
+      .unwrap();
+    let tags = read_tags_from_buffer(buffer.clone()).await.unwrap();
+    assert_eq!(tags.all_images.map(|images| images.len()), Some(1));
+    let properties = read_audio_properties_from_buffer(buffer).await.unwrap();
+    assert_eq!(properties.sample_rate, Some(48000));
   }
 
-  #[test]
-  fn test_audio_tags_lifetime_management() {
-    // Test lifetime management and memory safety
+  #[tokio::test]
+  async fn test_read_and_rewrite_opus_file_picture() {
+    // half a second of silence laid out as opusenc writes it: a 312 sample
+    // pre-skip, the comment header padded with 512 bytes, every audio packet
+    // on one page, and a 64x64 front cover as a METADATA_BLOCK_PICTURE
+    let audio_data = std::fs::read("test-files/picture.opus").expect("Failed to read test file");
+    let tags = read_tags_from_buffer(audio_data.clone()).await.unwrap();
+    assert_eq!(tags.title.as_deref(), Some("Silence"));
+    let image = tags.image.clone().unwrap();
+    assert_eq!(image.pic_type, AudioImageType::CoverFront);
+    assert_eq!(image.mime_type.as_deref(), Some("image/jpeg"));
+    assert_eq!((image.width, image.height), (Some(64), Some(64)));
+    let properties = read_audio_properties_from_buffer(audio_data.clone())
+      .await
+      .unwrap();
+    assert_eq!(properties.duration_ms, 500);
+
+    let retitled = AudioTags {
+      title: Some("Retitled".to_string()),
+      ..tags
+    };
+    let buffer = write_tags_to_buffer(audio_data, retitled).await.unwrap();
+    let tags = read_tags_from_buffer(buffer.clone()).await.unwrap();
+    assert_eq!(tags.title.as_deref(), Some("Retitled"));
+    assert_eq!(tags.all_images.map(|images| images.len()), Some(1));
+    assert_eq!(tags.image.unwrap().data, image.data);
+    let rewritten = read_audio_properties_from_buffer(buffer).await.unwrap();
+    assert_eq!(rewritten, properties);
+  }
+
+  #[tokio::test]
+  async fn test_remove_images() {
+    let audio_data = std::fs::read("music/silence.mp3").expect("Failed to read test file");
+    let image = |pic_type| Image {
+      data: create_test_image_data(),
+      pic_type,
+      ..Default::default()
+    };
     let tags = AudioTags {
-      title: Some("Lifetime Test".to_string()),
-      artists: Some(vec!["Lifetime Artist".to_string()]),
-      album: Some("Lifetime Album".to_string()),
-      year: Some(2024),
-      genre: Some("Lifetime Genre".to_string()),
-      track: Some(Position {
-        no: Some(1),
-        of: Some(5),
-      }),
-      album_artists: Some(vec!["Lifetime Album Artist".to_string()]),
-      comment: Some("Lifetime comment".to_string()),
-      disc: Some(Position {
-        no: Some(1),
-        of: Some(2),
-      }),
-      image: Some(Image {
-        data: create_test_image_data(),
-        pic_type: AudioImageType::CoverFront,
-        mime_type: Some("image/jpeg".to_string()),
-        description: Some("Lifetime image".to_string()),
-      }),
-      all_images: None,
+      title: Some("Title".to_string()),
+      all_images: Some(vec![
+        image(AudioImageType::CoverFront),
+        image(AudioImageType::CoverBack),
+        image(AudioImageType::Artist),
+      ]),
+      ..Default::default()
+    };
+    let buffer = write_tags_to_buffer(audio_data, tags).await.unwrap();
+    let pic_types = |tags: AudioTags| -> Vec<AudioImageType> {
+      tags
+        .all_images
+        .unwrap_or_default()
+        .into_iter()
+        .map(|image| image.pic_type)
+        .collect()
     };
 
-    // Test that we can create references with different lifetimes
-    {
-      let short_lived_ref = &tags;
-      assert_eq!(short_lived_ref.title, Some("Lifetime Test".to_string()));
-    }
+    let buffer = remove_cover_image_from_buffer(buffer).await.unwrap();
+    let tags = read_tags_from_buffer(buffer.clone()).await.unwrap();
+    assert_eq!(tags.title, Some("Title".to_string()));
+    assert_eq!(
+      pic_types(tags),
+      vec![AudioImageType::CoverBack, AudioImageType::Artist]
+    );
 
-    // Test that the original is still valid after the reference goes out of scope
-    assert_eq!(tags.title, Some("Lifetime Test".to_string()));
-    assert_eq!(tags.year, Some(2024));
+    let buffer = remove_images_from_buffer(buffer, Some(vec![AudioImageType::Artist]))
+      .await
+      .unwrap();
+    let tags = read_tags_from_buffer(buffer.clone()).await.unwrap();
+    assert_eq!(pic_types(tags), vec![AudioImageType::CoverBack]);
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let path = temp_dir.path().join("song.mp3");
+    std::fs::write(&path, buffer).unwrap();
+    let file_path = path.to_string_lossy().to_string();
+    remove_images(file_path.clone(), None).await.unwrap();
+    let tags = read_tags(file_path).await.unwrap();
+    assert_eq!(tags.title, Some("Title".to_string()));
+    assert_eq!(tags.all_images, None);
+  }
+
+  #[test]
+  fn test_normalize_mime_type() {
+    assert_eq!(
+      normalize_mime_type("image/jpg"),
+      Some("image/jpeg".to_string())
+    );
+    assert_eq!(
+      normalize_mime_type(" Image/JPEG "),
+      Some("image/jpeg".to_string())
+    );
+    assert_eq!(
+      normalize_mime_type("image/x-png"),
+      Some("image/png".to_string())
+    );
+    assert_eq!(normalize_mime_type("PNG"), Some("image/png".to_string()));
+    assert_eq!(
+      normalize_mime_type("image/x-ms-bmp"),
+      Some("image/bmp".to_string())
+    );
+    assert_eq!(
+      normalize_mime_type("image/avif"),
+      Some("image/avif".to_string())
+    );
+    assert_eq!(normalize_mime_type("  "), None);
   }
 
-  #[test]
-  fn test_audio_tags_drop_behavior() {
-    // Test that data is properly dropped
+  #[tokio::test]
+  async fn test_read_normalizes_and_flags_picture_mime_types() {
+    let audio_data = std::fs::read("music/silence.mp3").expect("Failed to read test file");
+    let png = vec![0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A];
+    let image = |data: Vec<u8>, pic_type, mime_type: Option<&str>| Image {
+      data,
+      pic_type,
+      mime_type: mime_type.map(|s| s.to_string()),
+      ..Default::default()
+    };
     let tags = AudioTags {
-      title: Some("Drop Test".to_string()),
-      artists: Some(vec!["Drop Artist".to_string()]),
-      album: Some("Drop Album".to_string()),
-      year: Some(2024),
-      genre: Some("Drop Genre".to_string()),
-      track: Some(Position {
-        no: Some(1),
-        of: Some(3),
-      }),
-      album_artists: Some(vec!["Drop Album Artist".to_string()]),
-      comment: Some("Drop comment".to_string()),
-      disc: Some(Position {
-        no: Some(1),
-        of: Some(1),
-      }),
-      image: Some(Image {
-        data: create_test_image_data(),
-        pic_type: AudioImageType::CoverFront,
-        mime_type: Some("image/jpeg".to_string()),
-        description: Some("Drop image".to_string()),
-      }),
-      all_images: None,
+      all_images: Some(vec![
+        image(
+          create_test_image_data(),
+          AudioImageType::CoverFront,
+          Some("image/x-png"),
+        ),
+        image(png.clone(), AudioImageType::CoverBack, Some("image/x-png")),
+        image(png, AudioImageType::Leaflet, None),
+      ]),
+      ..Default::default()
     };
+    let buffer = write_tags_to_buffer(audio_data, tags).await.unwrap();
 
-    // Verify data is accessible
-    assert_eq!(tags.title, Some("Drop Test".to_string()));
-
-    // The tags will be dropped at the end of this function
-    // This test ensures that the Drop implementation works correctly
+    let images = read_tags_from_buffer(buffer)
+      .await
+      .unwrap()
+      .all_images
+      .unwrap();
+    // declared png, but the bytes are a jpeg
+    assert_eq!(images[0].mime_type, Some("image/png".to_string()));
+    assert!(images[0].mime_mismatch);
+    assert_eq!(images[1].mime_type, Some("image/png".to_string()));
+    assert!(!images[1].mime_mismatch);
+    // nothing declared: inferred from the bytes
+    assert_eq!(images[2].mime_type, Some("image/png".to_string()));
+    assert!(!images[2].mime_mismatch);
   }
 
-  // Tests for add_cover_image function
-
   #[test]
-  fn test_add_cover_image_jpeg() {
+  fn test_add_cover_image_replace_existing() {
     use lofty::tag::Tag;
     use lofty::tag::TagType;
 
     let mut tag = Tag::new(TagType::Id3v2);
-    let image_data = create_test_image_data();
+    let first_image = create_test_image_data();
 
-    // Test JPEG image
+    // Create PNG test data for second image
+    let second_image = vec![
+      0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, // PNG signature
+      0x00, 0x00, 0x00, 0x0D, 0x49, 0x48, 0x44, 0x52, // IHDR chunk
+      0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, // 1x1 pixel
+      0x08, 0x02, 0x00, 0x00, 0x00, 0x90, 0x77, 0x53, // bit depth, color type, etc.
+      0xDE, 0x00, 0x00, 0x00, 0x0C, 0x49, 0x44, 0x41, // IDAT chunk
+      0x54, 0x08, 0x99, 0x01, 0x01, 0x00, 0x00, 0x00, // compressed data
+      0x00, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x00, 0x49, // more data
+      0x45, 0x4E, 0x44, 0xAE, 0x42, 0x60, 0x82, // IEND chunk
+    ];
+
+    // Add first image
     add_cover_image(
       &mut tag,
-      &image_data,
-      Some("JPEG Test".to_string()),
-      MimeType::Jpeg,
+      &first_image,
+      Some("First Image".to_string()),
+      Some(MimeType::Jpeg),
+      MimeInference::Sniff,
     );
 
-    // Verify the image was added
+    // Verify first image was added
     let pictures: Vec<_> = tag.pictures().into_iter().collect();
     assert_eq!(pictures.len(), 1);
+    assert_eq!(pictures[0].data(), first_image);
 
-    let picture = &pictures[0];
-    assert_eq!(picture.pic_type(), PictureType::CoverFront);
-    assert_eq!(picture.mime_type(), Some(&MimeType::Jpeg));
-    assert_eq!(picture.description(), Some("JPEG Test"));
-    assert_eq!(picture.data(), image_data);
+    // Add second image (should replace the first)
+    add_cover_image(
+      &mut tag,
+      &second_image,
+      Some("Second Image".to_string()),
+      Some(MimeType::Png),
+      MimeInference::Sniff,
+    );
+
+    // Verify second image replaced the first
+    let pictures: Vec<_> = tag.pictures().into_iter().collect();
+    assert_eq!(pictures.len(), 1);
+    assert_eq!(pictures[0].data(), second_image);
+    assert_eq!(pictures[0].description(), Some("Second Image"));
+    assert_eq!(pictures[0].mime_type(), Some(&MimeType::Png));
   }
 
   #[test]
-  fn test_add_cover_image_png() {
+  fn test_add_cover_image_empty_data() {
     use lofty::tag::Tag;
     use lofty::tag::TagType;
 
     let mut tag = Tag::new(TagType::Id3v2);
+    // Use minimal valid JPEG data instead of empty data
+    let minimal_data = vec![0xFF, 0xD8, 0xFF, 0xD9]; // Minimal JPEG
 
-    // Create PNG test data (minimal PNG header)
-    let png_data = vec![
-      0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, // PNG signature
-      0x00, 0x00, 0x00, 0x0D, 0x49, 0x48, 0x44, 0x52, // IHDR chunk
-      0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, // 1x1 pixel
-      0x08, 0x02, 0x00, 0x00, 0x00, 0x90, 0x77, 0x53, // bit depth, color type, etc.
-      0xDE, 0x00, 0x00, 0x00, 0x0C, 0x49, 0x44, 0x41, // IDAT chunk
-      0x54, 0x08, 0x99, 0x01, 0x01, 0x00, 0x00, 0x00, // compressed data
-      0x00, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x00, 0x49, // more data
-      0x45, 0x4E, 0x44, 0xAE, 0x42, 0x60, 0x82, // IEND chunk
-    ];
-
+    // Test with minimal image data
     add_cover_image(
       &mut tag,
-      &png_data,
-      Some("PNG Test".to_string()),
-      MimeType::Png,
+      &minimal_data,
+      Some("Minimal Test".to_string()),
+      Some(MimeType::Jpeg),
+      MimeInference::Sniff,
     );
 
     // Verify the image was added
@@ -3487,849 +8120,1234 @@ mod tests {
 
     let picture = &pictures[0];
     assert_eq!(picture.pic_type(), PictureType::CoverFront);
-    assert_eq!(picture.mime_type(), Some(&MimeType::Png));
-    assert_eq!(picture.description(), Some("PNG Test"));
-    assert_eq!(picture.data(), png_data);
+    assert_eq!(picture.mime_type(), Some(&MimeType::Jpeg));
+    assert_eq!(picture.description(), Some("Minimal Test"));
+    assert_eq!(picture.data(), minimal_data);
   }
 
   #[test]
-  fn test_add_cover_image_gif() {
+  fn test_add_cover_image_large_data() {
     use lofty::tag::Tag;
     use lofty::tag::TagType;
 
     let mut tag = Tag::new(TagType::Id3v2);
 
-    // Create GIF test data (minimal GIF header)
-    let gif_data = vec![
-      0x47, 0x49, 0x46, 0x38, 0x39, 0x61, // GIF89a signature
-      0x01, 0x00, 0x01, 0x00, 0x80, 0x00, 0x00, // 1x1 pixel, color table
-      0xFF, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x21, 0xF9, // color table + graphic control
-      0x04, 0x01, 0x00, 0x00, 0x00, 0x00, 0x2C, 0x00, // extension + image descriptor
-      0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, // image position and size
-      0x00, 0x02, 0x02, 0x04, 0x01, 0x00, 0x3B, // image data + trailer
-    ];
+    // Create large image data with valid JPEG header (1MB)
+    let mut large_data: Vec<u8> = vec![0xFF, 0xD8, 0xFF, 0xE0]; // JPEG header
+    large_data.extend((0..1024 * 1024 - 4).map(|i| (i % 256) as u8));
+    large_data.extend(&[0xFF, 0xD9]); // JPEG footer
 
     add_cover_image(
       &mut tag,
-      &gif_data,
-      Some("GIF Test".to_string()),
-      MimeType::Gif,
+      &large_data,
+      Some("Large Image".to_string()),
+      Some(MimeType::Jpeg),
+      MimeInference::Sniff,
     );
 
-    // Verify the image was added
+    // Verify the large image was added
     let pictures: Vec<_> = tag.pictures().into_iter().collect();
     assert_eq!(pictures.len(), 1);
 
     let picture = &pictures[0];
     assert_eq!(picture.pic_type(), PictureType::CoverFront);
-    assert_eq!(picture.mime_type(), Some(&MimeType::Gif));
-    assert_eq!(picture.description(), Some("GIF Test"));
-    assert_eq!(picture.data(), gif_data);
+    assert_eq!(picture.mime_type(), Some(&MimeType::Jpeg));
+    assert_eq!(picture.description(), Some("Large Image"));
+    assert_eq!(picture.data().len(), 1024 * 1024 + 2); // +2 for JPEG footer
+    assert_eq!(picture.data(), large_data);
   }
 
   #[test]
-  fn test_add_cover_image_tiff() {
+  fn test_add_cover_image_all_mime_types() {
     use lofty::tag::Tag;
     use lofty::tag::TagType;
 
     let mut tag = Tag::new(TagType::Id3v2);
 
-    // Create TIFF test data (minimal TIFF header)
-    let tiff_data = vec![
-      0x49, 0x49, 0x2A, 0x00, // Little-endian TIFF signature
-      0x08, 0x00, 0x00, 0x00, // Offset to first IFD
-      0x00, 0x00, // Number of directory entries
-      0x00, 0x00, 0x00, 0x00, // Offset to next IFD
+    // Test all supported MIME types with appropriate test data
+    let test_cases = vec![
+      (create_test_image_data(), MimeType::Jpeg, "image/jpeg"),
+      (
+        vec![
+          0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, // PNG signature
+          0x00, 0x00, 0x00, 0x0D, 0x49, 0x48, 0x44, 0x52, // IHDR chunk
+          0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, // 1x1 pixel
+          0x08, 0x02, 0x00, 0x00, 0x00, 0x90, 0x77, 0x53, // bit depth, color type, etc.
+          0xDE, 0x00, 0x00, 0x00, 0x0C, 0x49, 0x44, 0x41, // IDAT chunk
+          0x54, 0x08, 0x99, 0x01, 0x01, 0x00, 0x00, 0x00, // compressed data
+          0x00, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x00, 0x49, // more data
+          0x45, 0x4E, 0x44, 0xAE, 0x42, 0x60, 0x82, // IEND chunk
+        ],
+        MimeType::Png,
+        "image/png",
+      ),
+      (
+        vec![
+          0x47, 0x49, 0x46, 0x38, 0x39, 0x61, // GIF89a signature
+          0x01, 0x00, 0x01, 0x00, 0x80, 0x00, 0x00, // 1x1 pixel, color table
+          0xFF, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x21, 0xF9, // color table + graphic control
+          0x04, 0x01, 0x00, 0x00, 0x00, 0x00, 0x2C, 0x00, // extension + image descriptor
+          0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, // image position and size
+          0x00, 0x02, 0x02, 0x04, 0x01, 0x00, 0x3B, // image data + trailer
+        ],
+        MimeType::Gif,
+        "image/gif",
+      ),
+      (
+        vec![
+          0x49, 0x49, 0x2A, 0x00, // Little-endian TIFF signature
+          0x08, 0x00, 0x00, 0x00, // Offset to first IFD
+          0x00, 0x00, // Number of directory entries
+          0x00, 0x00, 0x00, 0x00, // Offset to next IFD
+        ],
+        MimeType::Tiff,
+        "image/tiff",
+      ),
+      (
+        vec![
+          0x42, 0x4D, // BM signature
+          0x3E, 0x00, 0x00, 0x00, // File size
+          0x00, 0x00, 0x00, 0x00, // Reserved
+          0x3E, 0x00, 0x00, 0x00, // Data offset
+          0x28, 0x00, 0x00, 0x00, // Header size
+          0x01, 0x00, 0x00, 0x00, // Width
+          0x01, 0x00, 0x00, 0x00, // Height
+          0x01, 0x00, // Planes
+          0x18, 0x00, // Bits per pixel
+          0x00, 0x00, 0x00, 0x00, // Compression
+          0x00, 0x00, 0x00, 0x00, // Image size
+          0x00, 0x00, 0x00, 0x00, // X pixels per meter
+          0x00, 0x00, 0x00, 0x00, // Y pixels per meter
+          0x00, 0x00, 0x00, 0x00, // Colors in color table
+          0x00, 0x00, 0x00, 0x00, // Important color count
+          0x00, 0x00, 0xFF, // Pixel data (blue pixel)
+        ],
+        MimeType::Bmp,
+        "image/bmp",
+      ),
     ];
 
-    add_cover_image(
-      &mut tag,
-      &tiff_data,
-      Some("TIFF Test".to_string()),
-      MimeType::Tiff,
+    for (i, (image_data, expected_mime_type, description)) in test_cases.iter().enumerate() {
+      // Clear previous images
+      tag.remove_picture_type(PictureType::CoverFront);
+
+      // Add image with current MIME type
+      add_cover_image(
+        &mut tag,
+        image_data,
+        Some(format!("Test {}", i)),
+        Some(expected_mime_type.clone()),
+        MimeInference::Sniff,
+      );
+
+      // Verify the image was added with correct MIME type
+      let pictures: Vec<_> = tag.pictures().into_iter().collect();
+      assert_eq!(pictures.len(), 1, "Failed for MIME type: {}", description);
+
+      let picture = &pictures[0];
+      assert_eq!(picture.pic_type(), PictureType::CoverFront);
+      assert_eq!(picture.mime_type(), Some(expected_mime_type));
+      assert_eq!(picture.description(), Some(format!("Test {}", i).as_str()));
+      assert_eq!(picture.data(), image_data);
+    }
+  }
+
+  // Tests for file-based functions using temporary files
+
+  #[tokio::test]
+  async fn test_file_operations_basic() {
+    use tempfile::NamedTempFile;
+
+    // Test file path validation
+    let non_existent_path = "/tmp/non_existent_file_12345.mp3";
+    let read_result = read_tags(non_existent_path.to_string()).await;
+    assert!(
+      read_result.is_err(),
+      "Should fail to read from non-existent file"
     );
 
-    // Verify the image was added
-    let pictures: Vec<_> = tag.pictures().into_iter().collect();
-    assert_eq!(pictures.len(), 1);
+    // Test with empty file
+    let temp_file = NamedTempFile::new().unwrap();
+    let read_result = read_tags(temp_file.path().to_string_lossy().to_string()).await;
+    assert!(read_result.is_err(), "Should fail to read from empty file");
 
-    let picture = &pictures[0];
-    assert_eq!(picture.pic_type(), PictureType::CoverFront);
-    assert_eq!(picture.mime_type(), Some(&MimeType::Tiff));
-    assert_eq!(picture.description(), Some("TIFF Test"));
-    assert_eq!(picture.data(), tiff_data);
+    // Test writing to non-existent directory
+    let invalid_path = "/tmp/non_existent_directory/test.mp3";
+    let test_tags = AudioTags::default();
+    let write_result = write_tags(invalid_path.to_string(), test_tags).await;
+    assert!(
+      write_result.is_err(),
+      "Should fail to write to non-existent directory"
+    );
   }
 
-  #[test]
-  fn test_add_cover_image_bmp() {
-    use lofty::tag::Tag;
-    use lofty::tag::TagType;
-
-    let mut tag = Tag::new(TagType::Id3v2);
+  #[tokio::test]
+  async fn test_file_operations_with_valid_audio() {
+    use std::io::Write;
+    use tempfile::NamedTempFile;
 
-    // Create BMP test data (minimal BMP header)
-    let bmp_data = vec![
-      0x42, 0x4D, // BM signature
-      0x3E, 0x00, 0x00, 0x00, // File size
-      0x00, 0x00, 0x00, 0x00, // Reserved
-      0x3E, 0x00, 0x00, 0x00, // Data offset
-      0x28, 0x00, 0x00, 0x00, // Header size
-      0x01, 0x00, 0x00, 0x00, // Width
-      0x01, 0x00, 0x00, 0x00, // Height
-      0x01, 0x00, // Planes
-      0x18, 0x00, // Bits per pixel
-      0x00, 0x00, 0x00, 0x00, // Compression
-      0x00, 0x00, 0x00, 0x00, // Image size
-      0x00, 0x00, 0x00, 0x00, // X pixels per meter
-      0x00, 0x00, 0x00, 0x00, // Y pixels per meter
-      0x00, 0x00, 0x00, 0x00, // Colors in color table
-      0x00, 0x00, 0x00, 0x00, // Important color count
-      0x00, 0x00, 0xFF, // Pixel data (blue pixel)
-    ];
+    // Create a temporary file with valid audio data from our existing test data
+    let mut temp_file = NamedTempFile::new().unwrap();
+    let audio_data = create_buffer_from_base64("SUQzBAAAAAAAI1RTU0UAAAAPAAADTGF2ZjU4Ljc2LjEwMAAAAAAAAAAAAAAA/+M4wAAAAAAAAAAAAEluZm8AAAAPAAAAAwAAAbgA").unwrap();
+    temp_file.write_all(&audio_data).unwrap();
+    temp_file.flush().unwrap();
 
-    add_cover_image(
-      &mut tag,
-      &bmp_data,
-      Some("BMP Test".to_string()),
-      MimeType::Bmp,
-    );
+    // Test reading tags from file - this should work with our existing test data
+    let result = read_tags(temp_file.path().to_string_lossy().to_string()).await;
+    if let Err(e) = &result {
+      println!("Error reading tags from file: {}", e);
+      // If this fails, we'll skip the file-based tests and focus on buffer-based tests
+      return;
+    }
 
-    // Verify the image was added
-    let pictures: Vec<_> = tag.pictures().into_iter().collect();
-    assert_eq!(pictures.len(), 1);
+    let tags = result.unwrap();
 
-    let picture = &pictures[0];
-    assert_eq!(picture.pic_type(), PictureType::CoverFront);
-    assert_eq!(picture.mime_type(), Some(&MimeType::Bmp));
-    assert_eq!(picture.description(), Some("BMP Test"));
-    assert_eq!(picture.data(), bmp_data);
+    // Verify we get default empty tags for a file without metadata
+    assert_eq!(tags.title, None);
+    assert_eq!(tags.artists, None);
+    assert_eq!(tags.album, None);
+    assert_eq!(tags.year, None);
+    assert_eq!(tags.genre, None);
+    assert_eq!(tags.track, None);
+    assert_eq!(tags.album_artists, None);
+    assert_eq!(tags.comment, None);
+    assert_eq!(tags.disc, None);
+    assert_eq!(tags.image, None);
   }
 
-  #[test]
-  fn test_add_cover_image_unknown_mime_type() {
-    use lofty::tag::Tag;
-    use lofty::tag::TagType;
+  #[tokio::test]
+  async fn test_file_operations_cover_image() {
+    use std::io::Write;
+    use tempfile::NamedTempFile;
 
-    let mut tag = Tag::new(TagType::Id3v2);
-    // Use valid JPEG data but with unknown MIME type parameter
-    let image_data = create_test_image_data();
+    // Create a temporary file with valid audio data
+    let mut temp_file = NamedTempFile::new().unwrap();
+    let audio_data = create_buffer_from_base64("SUQzBAAAAAAAI1RTU0UAAAAPAAADTGF2ZjU4Ljc2LjEwMAAAAAAAAAAAAAAA/+M4wAAAAAAAAAAAAEluZm8AAAAPAAAAAwAAAbgA").unwrap();
+    temp_file.write_all(&audio_data).unwrap();
+    temp_file.flush().unwrap();
 
-    // Test with unknown MIME type - should fall back to default
-    add_cover_image(
-      &mut tag,
-      &image_data,
-      Some("Unknown Test".to_string()),
-      MimeType::Jpeg,
-    );
+    // Test writing cover image to file
+    let image_data = create_test_image_data();
+    let write_result = write_cover_image_to_file(
+      temp_file.path().to_string_lossy().to_string(),
+      image_data.clone(),
+    )
+    .await;
+    if let Err(e) = &write_result {
+      println!("Error writing cover image to file: {}", e);
+      return;
+    }
+    assert!(write_result.is_ok());
 
-    // Verify the image was added with default MIME type
-    let pictures: Vec<_> = tag.pictures().into_iter().collect();
-    assert_eq!(pictures.len(), 1);
+    // Test reading cover image from file
+    let read_result =
+      read_cover_image_from_file(temp_file.path().to_string_lossy().to_string()).await;
+    if let Err(e) = &read_result {
+      println!("Error reading cover image from file: {}", e);
+      return;
+    }
+    assert!(read_result.is_ok());
+    let cover_image = read_result.unwrap();
 
-    let picture = &pictures[0];
-    assert_eq!(picture.pic_type(), PictureType::CoverFront);
-    assert_eq!(picture.mime_type(), Some(&MimeType::Jpeg)); // Should fall back to default
-    assert_eq!(picture.description(), Some("Unknown Test"));
-    assert_eq!(picture.data(), image_data);
+    // Verify we got the cover image
+    assert!(cover_image.is_some());
+    let cover_data = cover_image.unwrap();
+    assert_eq!(cover_data, image_data);
   }
 
-  #[test]
-  fn test_add_cover_image_no_description() {
-    use lofty::tag::Tag;
-    use lofty::tag::TagType;
+  // Additional comprehensive tests for util::clear_tags and util::read_cover_image_from_file
 
-    let mut tag = Tag::new(TagType::Id3v2);
-    let image_data = create_test_image_data();
+  #[tokio::test]
+  async fn test_clear_tags_empty_buffer() {
+    // Test clearing tags from empty buffer
+    let empty_buffer = vec![];
+    let result = clear_tags_to_buffer(empty_buffer).await;
+    assert!(
+      result.is_err(),
+      "Should fail to clear tags from empty buffer"
+    );
+  }
 
-    // Test without description
-    add_cover_image(&mut tag, &image_data, None, MimeType::Jpeg);
+  #[tokio::test]
+  async fn test_clear_tags_invalid_audio() {
+    // Test clearing tags from invalid audio data
+    let invalid_data = vec![0x00, 0x01, 0x02, 0x03];
+    let result = clear_tags_to_buffer(invalid_data).await;
+    assert!(
+      result.is_err(),
+      "Should fail to clear tags from invalid audio data"
+    );
+  }
 
-    // Verify the image was added without description
-    let pictures: Vec<_> = tag.pictures().into_iter().collect();
-    assert_eq!(pictures.len(), 1);
+  #[tokio::test]
+  async fn test_read_cover_image_from_file_error_cases() {
+    use tempfile::NamedTempFile;
 
-    let picture = &pictures[0];
-    assert_eq!(picture.pic_type(), PictureType::CoverFront);
-    assert_eq!(picture.mime_type(), Some(&MimeType::Jpeg));
-    assert_eq!(picture.description(), None);
-    assert_eq!(picture.data(), image_data);
-  }
+    // Test reading cover image from non-existent file
+    let non_existent_path = "/tmp/non_existent_file_12345.mp3";
+    let result = read_cover_image_from_file(non_existent_path.to_string()).await;
+    assert!(
+      result.is_err(),
+      "Should fail to read cover image from non-existent file"
+    );
 
-  #[test]
-  fn test_add_cover_image_replace_existing() {
-    use lofty::tag::Tag;
-    use lofty::tag::TagType;
+    // Test reading cover image from empty file
+    let temp_file = NamedTempFile::new().unwrap();
+    let result = read_cover_image_from_file(temp_file.path().to_string_lossy().to_string()).await;
+    assert!(
+      result.is_err(),
+      "Should fail to read cover image from empty file"
+    );
+  }
 
-    let mut tag = Tag::new(TagType::Id3v2);
-    let first_image = create_test_image_data();
+  #[tokio::test]
+  async fn test_read_cover_image_from_file_different_image_types() {
+    use std::io::Write;
+    use tempfile::NamedTempFile;
 
-    // Create PNG test data for second image
-    let second_image = vec![
-      0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, // PNG signature
-      0x00, 0x00, 0x00, 0x0D, 0x49, 0x48, 0x44, 0x52, // IHDR chunk
-      0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, // 1x1 pixel
-      0x08, 0x02, 0x00, 0x00, 0x00, 0x90, 0x77, 0x53, // bit depth, color type, etc.
-      0xDE, 0x00, 0x00, 0x00, 0x0C, 0x49, 0x44, 0x41, // IDAT chunk
-      0x54, 0x08, 0x99, 0x01, 0x01, 0x00, 0x00, 0x00, // compressed data
-      0x00, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x00, 0x49, // more data
-      0x45, 0x4E, 0x44, 0xAE, 0x42, 0x60, 0x82, // IEND chunk
+    // Test reading different types of cover images
+    let image_types = vec![
+      ("JPEG", create_test_image_data()),
+      (
+        "PNG",
+        vec![
+          0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, // PNG signature
+          0x00, 0x00, 0x00, 0x0D, 0x49, 0x48, 0x44, 0x52, // IHDR chunk
+          0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, // 1x1 pixel
+          0x08, 0x02, 0x00, 0x00, 0x00, 0x90, 0x77, 0x53, // bit depth, color type, etc.
+          0xDE, 0x00, 0x00, 0x00, 0x0C, 0x49, 0x44, 0x41, // IDAT chunk
+          0x54, 0x08, 0x99, 0x01, 0x01, 0x00, 0x00, 0x00, // compressed data
+          0x00, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x00, 0x49, // more data
+          0x45, 0x4E, 0x44, 0xAE, 0x42, 0x60, 0x82, // IEND chunk
+        ],
+      ),
     ];
 
-    // Add first image
-    add_cover_image(
-      &mut tag,
-      &first_image,
-      Some("First Image".to_string()),
-      MimeType::Jpeg,
-    );
+    for (image_type, image_data) in image_types {
+      let mut temp_file = NamedTempFile::new().unwrap();
+      let audio_data = create_buffer_from_base64("SUQzBAAAAAAAI1RTU0UAAAAPAAADTGF2ZjU4Ljc2LjEwMAAAAAAAAAAAAAAA/+M4wAAAAAAAAAAAAEluZm8AAAAPAAAAAwAAAbgA").unwrap();
+      temp_file.write_all(&audio_data).unwrap();
+      temp_file.flush().unwrap();
 
-    // Verify first image was added
-    let pictures: Vec<_> = tag.pictures().into_iter().collect();
-    assert_eq!(pictures.len(), 1);
-    assert_eq!(pictures[0].data(), first_image);
+      // Add cover image to the file
+      let test_tags = AudioTags {
+        image: Some(Image {
+          data: image_data.clone(),
+          pic_type: AudioImageType::CoverFront,
+          mime_type: Some(format!("image/{}", image_type.to_lowercase())),
+          description: Some(format!("Test {} cover", image_type)),
+          ..Default::default()
+        }),
+        ..Default::default()
+      };
+
+      // Write tags with image to file
+      let write_result =
+        write_tags(temp_file.path().to_string_lossy().to_string(), test_tags).await;
+      if let Err(e) = &write_result {
+        println!("Error writing {} tags to file: {}", image_type, e);
+        continue;
+      }
+      assert!(write_result.is_ok());
 
-    // Add second image (should replace the first)
-    add_cover_image(
-      &mut tag,
-      &second_image,
-      Some("Second Image".to_string()),
-      MimeType::Png,
-    );
+      // Test reading cover image from file
+      let read_result =
+        read_cover_image_from_file(temp_file.path().to_string_lossy().to_string()).await;
+      if let Err(e) = &read_result {
+        println!("Error reading {} cover image from file: {}", image_type, e);
+        continue;
+      }
+      assert!(read_result.is_ok());
+      let cover_image = read_result.unwrap();
 
-    // Verify second image replaced the first
-    let pictures: Vec<_> = tag.pictures().into_iter().collect();
-    assert_eq!(pictures.len(), 1);
-    assert_eq!(pictures[0].data(), second_image);
-    assert_eq!(pictures[0].description(), Some("Second Image"));
-    assert_eq!(pictures[0].mime_type(), Some(&MimeType::Png));
+      // Verify we got the cover image
+      assert!(
+        cover_image.is_some(),
+        "Should have {} cover image",
+        image_type
+      );
+      let cover_data = cover_image.unwrap();
+      assert_eq!(
+        cover_data, image_data,
+        "{} cover image data should match",
+        image_type
+      );
+    }
   }
 
-  #[test]
-  fn test_add_cover_image_empty_data() {
-    use lofty::tag::Tag;
-    use lofty::tag::TagType;
+  #[tokio::test]
+  async fn test_round_trip_with_base64() {
+    // This is a minimal MP3 file header in base64 (just the first few bytes)
+    // In a real test, you would use a complete audio file
+    let mp3_header_base64 = "SUQzBAAAAAAAIlRTU0UAAAAOAAADTGF2ZjYxLjcuMTAwAAAAAAAAAAAAAAD/+1TAAAAAAAAAAAAAAAAAAAAAAABJbmZvAAAADwAAACsAACEAAAsLEREXFx0dHSIiKCguLi40NDo6QEBARUVLS1FRUVdXXV1iYmJoaG5udHR0enqAgIWFhYuLkZGXl5ednaKiqKiorq60tLq6usDAxcXLy8vR0dfX3d3d4uLo6O7u7vT0+vr//wAAAABMYXZjNjEuMTkAAAAAAAAAAAAAAAAkA8AAAAAAAAAhAMFx74YAAAAAAAAAAAAAAAAAAAD/+1TEAAAILAFbdBEAAYMN7qcGMADSMAg0iA8gs+XD8EAwfSUOF4gBDiAEATB8Hw/P4P/icHwfBwEPxAZqBA5/BD4kBD4IAg78EAxrB8PlAQBAMFHFw///7/7VWqAgAAODWI47AAZArODGYIEgoUxbGoCVRCQekalnGgsKNGYYSHCwAeEScasicmFkLyZwNHR4ZJBxR4OqatDLYvepIRrFJw+fqeaB5ZgFnHusRtXDwbZ//xb//paqiZq7p0NPpQD/+1TEBAAKeEdz3PGAAVMRLzzEjSDosYRhE9MwbidRJkIUnGRFtOrMYkUQNmeBRx2ME4XMg8YHCiocETgmQHvEAwbDoZa9AVDQ9fWdvStHptQLNilSVd9NSVeijSQZFSxbLqLqnZ0ksjSQB8Aw8i0eVwwdHIYJhoLBgumoXOsWZRyRqRmjiVzNO6stJRw4Z9RjVe3YuwoFB8AGoqPiYe6LoMEYgsY86Mvj0uTlFxlTL3s0oNu+ms3MqpmHS21tohb/+1TEBQAKbHt757DEwUSJLrzDCdCLwXcOMto3SMKTJdxaNyVCCHAoOToiXgFrZNqecxjE7u8eD327fNLjsqIlYCPHg4fg5ijkyKplA1VdROBb/S1sUbe2tX0ff/L9+1FQ8yzrEkkQABAJAYGniKYgq0UT00DwZGQ1j+/IG6JI9POgWYfCoHUSGvMB9IWEpRJKLJPrWIlrMvsYsVCAvs6lW0ZGGOvXVZXWpWc79q/S+tu5i3plRyIkkAngYdBxJ4X/+1TECAAKKF9157BlgV2J7n2GGGAsnCsgYjMEE/Wnp+BDUBHZbDwClZC5rBIoZT3LHoCChdzCY9RWLXzFWB31FT749fYxGLwsrUox4xTPU6u2jquemnrmLh2RSjKKIAMgRqqc6NAQJXxED4NEo+lI+coACEELWAalmp4NiMOl9OB8VWVFltOBENwoabYMSgETxq9MXWdSFqjc8g+asXsbbGfQ3tQxinKFmoqXuHX927p4dlmtkaIBZGUjS9kuFxP/+1TECQAJMG9955hqgRKHr7zEjUhFXHWuD/dg+6ItAsxVfamqxsq5nVNUW5vJaIKi1goAQ8GB44SHB515RZWWSQFLWMNqZ7Vs7dX+rL3bu5ZV0kjaAI4eksbB2O4vBmjEM0MTwcCCjwuxiDrjgksCjgbKzIGc4PEzhE0NJNHmAwWACDnG3HnmVubQbYqKUV62KombuYZUS/pBOas5/IWfhug6U4Fk5L0fC63C9/rEK6Lu6FPY+bPDQSBWxh5oqVf/+1TEFwAJuF13x7BnASsJb3zAjkA886NUAzEAYmCC0l9ZHWiu7dp+1q/0UIcHi610R6BSYrMq6l4ZLZW2iAYjwXwqWgZKokoQ8j8LF4ygxT+rEzW/A0hheTIW4ItD4SEQOEQVBh1JgjRLE3KoKPfEiBzGGXCsgvzvXu7e3R/+mr29yYh2WxyNtBmJMTg5UDp8jFakbKh6ZIthC0c7xwk8fV0KAxAODjQ0t4vMoCbDQfJFqmLPh8WU1CTZ6gm40Tr/+1TEIAAJhEl956RowScPb3zBieibtJQK8Xhf261+z/Re7dTTKqWyNpIJgKE0oqgOE2MRiwbCeSjYzb6MkRjev2kAZOkRDkRIGj1OlszNoKQ56UB15kipKAM6WbI61aF2t6+BGsJtudUS0YiXZlVUSRttIAuYZRLCOwQkn6IEwVEYmOmigZZChEGAEXJMozOl2e4zS8LASAYNlzRaOFBgJzRQUDwgAZEIlDDhqkn7btm57Uo3RTcu8uHdEtlabIL/+1TEKoAJaFGD56TG0SqE77z1pMD+ilskhPD8RiPQEwKAjixB0GMhkTC5Es8KOEYZLA+H3AiNSJRMyFhjSbXXsaytr6RBOILOviqHCz6bd7P+/V//1Kq5eaeDSNtIgANySDYQgIgLFhuNxZFBCJiNzKJdEgvCCjNO4pCJV0LSDSAMD2ngxQOKBASpNVLgMiQU2A13Kqc7u/S/3dv62e730/ebvXMMs1tbaAaRCyWTRyFwDBkFJ8H5ybC4y5EdqMH/+1TENQAJXEV35iRnAS2K7/z2DDAaQzyddJrCNFUfEzxUbHBpJkE0jgwVdCgbo7LSbK77D95+XIFH2UCjVp99hOq8u5WGQ0pqUBFxKHkTJCEenyElxVcPLtPvYMVj1AhmwRqhqJjsiLnsj0UJUMjs2s/RbtDEHkKEocG1LoorQXa9lC9CU/Nu/o2/R15tXMsr+SSNoCnhHS8oE/S3n+wMCkRzFxskQsSFIZqaALywWHwy8wIw0DLRciVLBUgme3z/+1TEPwAJNJ91x4RRQTEH77z0jUhZhoEBGUWIFnz166XbJSfsU8dookG9FFWHZlZkQz+qUCKawcPtIZs9UPMUf1z4Bh587D/izDR2H2o5HEjXRJ1+ijcT5rFP0rNSxVRrL92Wyo6o4S0hVU7S1/fNNIbKE3s3rw7ITcaKICRJScodRLIoIsDoek546dJpISFWccNBEOmBIVGLBgwbQYAQqeILLD3n2ijQMXN8x3yCmuEGhyMl46g231m2/+L/Wzr/+1TESYAI3KV7zBhNUTGFbvz2GGCV3Mysqnd9bJGkC4EsIQLmhi+XADkF644i15DPj1WNhIIGWEkF161ycwRsOgAHDA6bUOWt7jgRJOqA5A4wNscLR1VRZdGzK3NT7lXXdOX1zMw7vpZGyQaQ6zOPJCjzscjWgUwcpzvE5OjVozqHTIK0hAcQxPQ9hnUhmpiayZoW/EKOoIyAi6wceGVzyJSKuXNyA0n/9H/vu6qopnVIm2kQADDgQCaPIZhANh3/+1TEVQAJhFN957BlASeRL3zzDZBhGJCYMLq9JG2qnpZKZIDMH3vIIQDgfqIn6iBsIYzRsJudsCyL+1Wa3ayj//rkK8quypiGZxtpEg9iaFCtm6ojyL0BICHQ0IRF3QEGkY+2RLxugPHiygwcKB5L0BIY40ApQ4qmg81TVLvQTU9Ze1FXWvUn9eqh3o/+hdqrqpp0axyNMARwqBE6LgkjiDcmEw2uOAkqh0dSwMt2q0C+eLYRiZ4uhcND2mUFxdD/+1TEX4AIYEN55iRnASoHrzz0mNCC1oFIpWuxJcaRS2pFsXoALbBDNIF3m///7voqJmZpWIqqQAezJcQUgclni0XSqQBBWGFm1/r0iWFszUxzKHkI058j2/HsibdyRPdqRIqKNaQTlAq/K6zyBFv6jT6yi0dkXk2X64whLoqvfQrLurmoVl1kbRIECEiI2cTCXU6z+QIuCkfoc0PZODbOfmnVmIfqQKFtqT2RhVL1lpBvOmWCZeI29L/LNTEOUwf/+1TEbgAJiEV75gxQQUCTLnjBiijdxkSl3WP6G0/rZskaujXt3r1bsySNtIkBIFAF3iELwIAq8WbB4JQhhyMBdkwgQTN5zJUqRHMGSA5KE1FAqCIMtBckUEYkBcXSGzqkxU2swLRSaJ2OsVdSfqcf6ezFdPr+5dW5vLiYdmsjaKIBgDyMGgTg46DUG5VSiCcrB4MkGFIHqPNOKyC0cgKxrDvotYE2cEjTiXKOCVZQ+5IeWYd2ueOPGU6yNJYibcT/+1TEdQAJxKV556BtAUwJrvzDDZBKUACSaezTBfAU7W36ty/A+9cVUw6q620kQF6UoXgg5nkyQpJlAW5CFcCAFFlQoROh4d30JDw7sVNjyRfXW284WIgifAQGGxjQ5nip9sBvHAgXSOzMUVW+qENx61/rv+/4pbQqyat5iVZY22UQLQtJmEVCQwPAKpB1Ko5C9cYmdUrleYh7qyghJi+K5tEOeoJhkdDIGQtYkW3MHAykg4VIkdB5ZRLY8BKQNAz/+1TEeYALDGN35hhtAUsO7vz0jRgyp8AyVLgLb/0gOnsOaPDabuph4hkPpZQLYdh6JAFUJ6K9EFInAgfBXlwsNDC4xfUTLxML0waXyCzJgceU8a7fNa7UneKb8S9ZLO0waVNWkZxWjygDN+u59ypBpxnz6t/gav/aj533N/n/yXt/+juqqqq4qGU7G00gAoA8TAZAmCwWlQOB8Eg/qPI7iSS2uXLBL1/hoOM0NTYuoTLmk5tS5Cby8zhcPs+8fzv/+1TEeQAK6JF17DBjwX0Irjj0jOnbuOlo5Kg75EkBSZwEHsHsfTurTT9kdb1UffNR8zMOyRxpIgAmBUviM8UQoCgnHI6jkfiCTDM+WjhpF5BDyY22ISqSPH4ZhxLuO6BDcROC4qD4SafFiJRqEoaCxZAutIiWL6xQQPM+p114ytv4uvfTWr+xFcvbq6llWVxtIgJop8nYXFiMQfiDNEIkgNk4wINXioTdeW70k10JoXBaFpiyf/NJzcQB2iwgizT/+1TEcwAKrLd35jBhwWQNbrzDDcgUaFgCIIbCY4HXlhR7QM2R1vcXaEKXljrv7fOdKu/q67vKqYVHK2ChDiRwnw/zoP4n5zmixoaQmS7WlFKmmCSqUzMqi058LJtKwQGBQRDRAeNeHUlSKRMVDIxtjz7AKLCAbhGutVqX2JHoD29nTZu/so/QzLrLyZZbZI2kFoPgPFwJj60Pz4eHQ6g0PSxcAOSW5U7q7YNDQKg2OEAKKHgKUKChO95g1HHEdl3/+1TEcQBKwHV556RpQU+L7zzzDcg24xW5zgCkq9zdnUhul9ze8V7Mq7p3lZZYIQ0BkGgLjqQQZCAkKzYgrC6wyTWpcUWSvBpWM6MEhl3OGZmpyFyWp1YTUxyicVQF5AS1NPGLVPag4qu5emxzv//qeZmIlFY6WUASaIA4loAw6gxVDy6XSQOZpMrqXgPSXCeuJApgCCkAEeRLi++Tv2Qmyy1VG0TZoK25jiJkqaV0vlyqfFU35XR+GxMqoP1623X/+1TEcYBJMDl95jDEwSYR73zDDZAVdRDMsjbaSCMAw/koolMsBkHTBBJpwKEJZMbg0OZjJThptAEA4XDIQERp4hMJAZpLZ8whizNZoKa2Jj2LZEW3l7MZJEGJYh6NSjC/d9O6mqh4RV/pVBSBIxypQwhRfEyxotToe+WG5Pv2YO74d2JRIS05KxZeak5k4pTwuGEAc8afNvcKtsaMet7DllrV/QXOinc1+1pSOePpsZilxehwBj0QzN3U3Tuiyxv/+1TEfYAJ3L1zxgRTQTwHLzzEmUhNIEsdCIBMnjWBItj6HZwIQlja5d89sOf42tlCDIZmYeudcRIQipQRBwfCbxdomLixMieqUY7MPMGre+LPPuRqRAvX3Dl15Bbv/oW6rIioh11rbaIEIBZTCcoMkeEUnJooIic4RHJklzqEMaZb0pB7+FZUEbtBMIljyFtNzhWiodUg3CFpYPqM2uEpo4k8b4rr20+23//6ZjMmoh0OxtooBMNEIyIg/ACEMfz/+1TEhAAKtGV1xjxhgUeMrzzDDdBILSpGPnGUK9t1PQFK7GfuHYMpJukfzPQUdYvNdJ6zIrDBQUEyRCPHoizWftNVLY4PvIJMX7f/f/+x0uq8y8u5h110baAYH0S0B8EwSJhXHVKbKy2PI7RKEmlL01eDkQTBvnEJTJGjiAyC4faWkwXJoeIXIyCCY6OgKHQXeGHLJFPOLUUzuu6mHZNpU2gROBATB8C5OdiMKiSmFLKYsc273Jgx5rXjBAYcW3P/+1TEhYAJiFV75iTIQTySbvy2DDgmTNmjaiexxxApg4WyOpAGcIWVpjzgWF3sMk1b6n2Xmf//+NNdNe3tu7enjSVtEAsZOmUkBI02sDYUCmqvIxriqY0KOp1QiaZhVZcR9a6EV1bs8+B4CMRDg2SaDgBSIZEXImgKNJCgFKKUYGDdl0qt+2//1XlPU3TMlliZRAMQQCpSDccSg0oGLBSHM6Fpg4kQxfG3d6KuFF8KuwlUmVAzZp5hKwiZG3rXCaX/+1TEjQAJCGF95hhsgTCOL3zBDgiBOHKlC1ou9Oi1oDEKxzL8W66/Y9DO7/6VzN3b66ZrbZJEAtEAP1wTCAHBUQSMbGwVODShcT6sjEhwyMOCiTQqCYFWcNCp2gPvbWLEhG7avYLqcYlBVlDQ4pgWYNYl60nDn/eusrKqYd0ccbJIHIQhFEMmSOw6DwMAED8AILDhzkd0boiQ9F0V4apZdCwpC8gTCihUVcIgmkCFi7klnLIHDaQNqm+rJU1N0Yf/+1TEmAAJvHmB55hsoT6ObzzBiei956ju3a6qx1d7ZGyAEJXavKCKdhDgM5kGFhCLhgkaRbxgqAyExgDCoLBkmDCjhN9rhxlyBUYYOHzDg1sEQQNSIyhQpFveKde2no717pybdpaET/6UUZvlASgBRMEUxXHkCY6Mi1ZYdUVLtSJrKx/cMBzome8EJNZY204F1Oa8CTzQuWJLQmAig0YGIwM3Nqqt91LOnbU//q30Kqy6i5hWWWRtIjBzFBJBoYH/+1TEnoAJWCt/5jEkgS0K7zzEjViYm6bDqZpSgcGCUqLSPPsFMzBWGbroPEx1JBdwRlSKPDye0batMeJDpZwmYbACo2smqe8WH82zUu/1d1PVdbtNEMiSVNIAFoFzgBIrBqApSViGsQ9sWxMo4nz3MSyJDHLJTyJmSqHpuGUKtA64kQ4MnQwBnxqVGZsPHXrs1DjKf0Wd//LalXWDtmmZqZqWVT/pUBIAHOQnEQfTsUEMpCkZFjxVFMMLNuiyuyb/+1TEqQAJIC2B56Rm4TSJLvj2DHCH2RUIEFqkUFiZAVNkTjD4CIA2mFR2yqptTG2F3b70kU2aYjn9tf6KW9iW/JXMtdTKmcaRDAGRaH9YLmE4UIjwaiGDzSFZvXFXWj2EdC5pgusC2CJ50PPicR559RBF66SNp5CFIY1SBt6ubfBH/lFgfZ3KEG1NNtW7ypupd1kljSQUgbBU9GJwfDwSC9CND1BwgDsmE7sTiUmeN5Q7T0Qrm5nwpqedNFIJpI//+1TEswAJnIN55hhsgTSM7vzEjRgzpzGhnbipVZxyru1r+97rCW91oshTF7P/+Qiqi3h2Q4mUiQCQNB8SADgHBsLhceWIAeFaMOtwURTUfpoZsADhZ4oQJigCQghY5RoQky8mcEUw60gKhOjRXFl6XDXrq930udtVwvXNWb72qrzeurmGW26NtAGRXD0MwakUaAGFYqwEM2Tji6Wcf/jwqE2cjJM4IHB4aKA+RFhYqQWDihpgCH4999tctc8+9T3/+1TEu4BJtFd1xiRqwS0IrrzEjODCtV0gKrveQVq7MVpzN6tqZiJbGyQCIMCQNghJwdBQPcSgchpRLxRhSAsyETUH7mgvk9IVY0054HThTKWTKQEh1+sPfBnqKUf+v//pltTf5KhstP/3/vuJeXV3M0+hAF4jh0OpwE5yFJ0LZNQMo3FtwhwnRaTMRdTT1BStZL3TZ74UxiZMii8UIj3F1wuNXpuuHI7+3W5Drijl9gVeupjEMmldpqGZmhVQz+r/+1TExIAJsJl75hhuQT6IrryEjDipBKpEuKKLAnEaXRRFCTk6AIGQIGmxO7MC1Hcd3hjBWyDUjx3YfJA3atA5YbCYqQSWLscFTQVaxqXHyEw4Ue9hM60my24SOtIfVZZ2ZmRTP/6kDycDCcCXKMmhIjRN9HIpWIpyJiTQmRJh4cOGKVksJROQOvBn7rWZ8IPQRBcOhxqDRwwYQyRK1HBu1vRFlIExUadewwkU1fxZityJeJeDKSNoAgRwVAfDtQL/+1TEywAJiE195gxQgSsJsDzDDZ1DJcaWMD1aJBZ1kvDtzK7mR8NkZaaIK4CBnTgCICE4LoFQcIsZCaluGmStYhCY6zR/8n2XHDSlqKJK6W1Kuou6lWMpWkSAAkom4gCCEsIMAPmYuMQkOjY9DjuHxOxTKyqmo+zhKq9fIE4keULBYakqcAi3gcliyn1tlUvoisn9Tn3Sur+jTprenc2kUqpqod3ZJW0kSBNJoLGpTEEkEReOYtVhpCCZjCJLaCT/+1TE1QAJqHlzxiRmwUGK77j0jUpr1iWmxN0EC5cgZMBcu8RGyrXDwyUVPIruXZDe6JK7BRf/dbpr6y6dG7/3VbaZmYU0O2QAAUuDlKAOHIAAAvj0MgoPCCyFkQRVG33TpPI3XMfHzHFHpRnSwfiiBRuSC2vlYSZNfR8uFOemkZmO+oK9GAdAmXXv9xnv1LbgjybEqp2OqvSwP3JbgXMZ+hvMrKuoeVaxttogZgCHxCGakWcwH6IeykXEg5zQ5CH/+1TE24AKIHV/x5hsUTGK7vz2DDjgreaMqSF2n1HYhz4kQITqYugUiheLqHgYigoWeDw4+WQ1BhYBFEv6CKLSKjCe/9FX/qWqq4mIhjcjRJIXZ+Jct5Li3k4GC6IYhhSV0woP3RLfHYScQAugR67q6giJ0AYu/QX1Cg9IIjVGVptG0tMKqSIZ0nht6DosPPhxrjU/beqXZPoWByjtujejTlN/bdTNRDrLZG0gA7C2HKOJcJC0QUiGQ6RjSOpw+1r/+1TE4gAJ2GF157BhQSmJrzzDDVi9QQW4aiHul2Rio3QpaQ6YISX3/GCAxCXj4XCLS4DcNWXGoEyIhdgPKXKZvcB27WV71sSzrcvMuqiFWSySIhHiUPdTJAvqsMo3zRRSTeH4ntHpaguCqTpkiogby3av5szkHUzEbCEXhaUj7tJFOc0Miie/DYjpjpAxcES5mw8wvS9KhqFp5au1FMiNuvqu6uWZbm22iEQfBehZVWOoHg6BmEqonBwbLBgElFn/+1TE6oAMMFNvxjDBSUAKrzzDDZibp2msXzYNfLw5p5jhpFpYek8ETirAwWpMbGVRYXW4Wa+XNpdunGVIQj7/v9CYeGZmUyuYADGQJ3DFWjjQhJpjxCQLAqrIEWpvTiPQrEcWlBmIXLm6JQSIbbpJ2WQC6C+TA5rt/Vn0Xd7377Uno5/td93efzSBWj8Lta+E/94uVal//vM6271NQNgETEy7OxnSqgEo0FgoPgJAHx00DwkRPvgqRFAcCR8FBAL/+1TE5wALVG1157BnAUiPb3z2DHAxAgkfeKJsERI6ceqQU96cYBhhnCK3zMgsWWgSkpZLyoSvA3RHUELDj481q4qilrFCJSKaiHt2U0rZEBWGgqIYxCgikIRBBD8XMjWcEw2OKMb7tqr2HqsqvG86ymporChnml0Zz3nZoMApfFiR+BY82fcZiW2YQC8ogsKEzi2k54J4EILMplFWGAEthUWVxZ8zUVEMxpG40UQCofsCAwaH0skwZEZcPA92WGP/+1TE5gAK2Ml555hsgTCKb3z2GJh8babZnxaHUYkGWlwhOTvX1bOkzxRVlpkCUtBBxZy61ky9bA6ywNyJJd7X0nhu9wJmCbE6WhBXp6kbUU282quXZU9tjaQJ0/JEeYxEmgTngki9DPScfIMV334mUXDvkMPaGEIyLzIMQkBHMOC4MBYAkR5VpMXOTFaGH2QOoobJEFyd0Vds3La2q8todqd0bWXVUzPFlrbaCEEyANyeEoF1o5j04IbFiUsDASr/+1TE6gALtF9vx6RnCUyFbniUmJA9d6KHDuSdZM5cw6u8hqSJg68Dw+CFs6geJn2jAipTzTLF0pBd9w5S1awQ7Rf3wcF3f0fSmqmodkZN0QA904S4hROj9G0eArC5Gk3ExYVhGEkVqkKASBdI1GVqKbjQu9b0DEQgQL7VGuaqY5/XOw5C5n4mLBzHV6dwdu/9a/xTvW5b7+/7vX8M3Y5u9tolwDu/evO0gdO6PeTOvNp4hWsbaaQJOMEFQLB+NJD/+1TE5wALnIdxxgxUgVsRLryWDDhcGJ8VDITqgqD6C5B1lH2hicr9GgYNnzQ5YoADrxhR4fUBVdwbZKa2koVKiMikys8L+KUJvTfRZmriAT/uu6u5hVRa22kgBuF7IKgkWqBSkaikifiJQhWMb2T0YhKlMtghDxYGFQMNBdj2wdEjmhpARAQbFGhJZ0moyrUFEpWKlQmOfpS+JFxiA3Z9Tdcds67fm7mZiIVzappJEADAdsjSMlYgmURHZjTMEjn/+1TE4oAKRGN757BjgUMOL3zGDChq40SCM9yx6gbMGQucEjlB4aCKZISEhjTZpkkHiztpIDvsFXMbLht8ZfegrexhHi2zqz9ixdaZH9GpCYCZCIB3CID8bD0aiwSAK6Ob9tJ/26FD1y9/y7YoKMx7/KwA1hK784U6P63e/8dKyH66hRv+uCVI1DDc9ZE9j//nTEQ1eXBlwn1XtYP//48SpNBaPFFj4rm1n0GL///04p4MFOMS4OGv9sWff///9xr/+1TE5oAMoG9vx6RsyTwI73z2ICAzUgeWWG89s1z/81/////pPrGabvK8CnXFQVVMQU1FMy4xMDBVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVX/+1TE4gAKoEN355hsgUeHLr6YYABVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVX/+1TE5AARkUGp+YekEAAANIOAAARVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVU=";
 
-    let mut tag = Tag::new(TagType::Id3v2);
-    // Use minimal valid JPEG data instead of empty data
-    let minimal_data = vec![0xFF, 0xD8, 0xFF, 0xD9]; // Minimal JPEG
+    // Test that we can decode it
+    let result = create_buffer_from_base64(mp3_header_base64);
+    assert!(result.is_ok());
+    let buffer = result.unwrap();
 
-    // Test with minimal image data
-    add_cover_image(
-      &mut tag,
-      &minimal_data,
-      Some("Minimal Test".to_string()),
-      MimeType::Jpeg,
+    // Verify it's not empty and has the expected MP3 header
+    assert!(!buffer.is_empty());
+    assert!(buffer.len() > 0);
+
+    // In a real scenario, you could use this buffer with read_tags_from_buffer
+    let buffer = write_tags_to_buffer(
+      buffer,
+      AudioTags {
+        title: Some("Test Song".to_string()),
+        artists: Some(vec!["Test Artist".to_string()]),
+        album: Some("Test Album".to_string()),
+        year: Some(2024),
+        genre: Some("Test Genre".to_string()),
+        track: Some(Position {
+          no: Some(1),
+          of: Some(1),
+        }),
+        album_artists: Some(vec!["Test Album Artist".to_string()]),
+        comment: Some("Test Comment".to_string()),
+        disc: Some(Position {
+          no: Some(1),
+          of: Some(1),
+        }),
+        image: Some(Image {
+          data: create_test_image_data(),
+          pic_type: AudioImageType::CoverFront,
+          mime_type: Some("image/jpeg".to_string()),
+          description: Some("Test cover image".to_string()),
+          ..Default::default()
+        }),
+        all_images: None,
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+    let tags = read_tags_from_buffer(buffer.to_vec()).await.unwrap();
+    assert_eq!(tags.title, Some("Test Song".to_string()));
+    assert_eq!(tags.artists, Some(vec!["Test Artist".to_string()]));
+    assert_eq!(tags.album, Some("Test Album".to_string()));
+    assert_eq!(tags.year, Some(2024));
+    assert_eq!(tags.genre, Some("Test Genre".to_string()));
+    assert_eq!(
+      tags.track,
+      Some(Position {
+        no: Some(1),
+        of: Some(1)
+      })
+    );
+    assert_eq!(
+      tags.album_artists,
+      Some(vec!["Test Album Artist".to_string()])
+    );
+    assert_eq!(tags.comment, Some("Test Comment".to_string()));
+    assert_eq!(
+      tags.disc,
+      Some(Position {
+        no: Some(1),
+        of: Some(1)
+      })
     );
+    assert_eq!(tags.image.is_some(), true);
 
-    // Verify the image was added
-    let pictures: Vec<_> = tag.pictures().into_iter().collect();
-    assert_eq!(pictures.len(), 1);
+    let buffer = clear_tags_to_buffer(buffer).await.unwrap();
+    let tags = read_tags_from_buffer(buffer.to_vec()).await.unwrap();
+    assert_eq!(tags.title, None);
+    assert_eq!(tags.artists, None);
+    assert_eq!(tags.album, None);
+    assert_eq!(tags.year, None);
+    assert_eq!(tags.genre, None);
+    assert_eq!(tags.track, None);
+    assert_eq!(tags.album_artists, None);
+    assert_eq!(tags.comment, None);
+    assert_eq!(tags.disc, None);
+    // assert_eq!(tags.image, None);
 
-    let picture = &pictures[0];
-    assert_eq!(picture.pic_type(), PictureType::CoverFront);
-    assert_eq!(picture.mime_type(), Some(&MimeType::Jpeg));
-    assert_eq!(picture.description(), Some("Minimal Test"));
-    assert_eq!(picture.data(), minimal_data);
+    let buffer = write_cover_image_to_buffer(buffer.to_vec(), create_test_image_data())
+      .await
+      .unwrap();
+    let image_buffer = read_cover_image_from_buffer(buffer.to_vec()).await.unwrap();
+    assert_eq!(image_buffer.is_some(), true);
+
+    let buf = image_buffer.unwrap().to_vec();
+    let info = infer::Infer::new();
+    let kind = info.get(&buf).expect("file type is known");
+    // guest buffer mime type
+    assert_eq!(kind.mime_type(), "image/jpeg")
   }
 
-  #[test]
-  fn test_add_cover_image_large_data() {
-    use lofty::tag::Tag;
-    use lofty::tag::TagType;
+  // Comprehensive tests for write_tags function
 
-    let mut tag = Tag::new(TagType::Id3v2);
+  #[tokio::test]
+  async fn test_write_tags_error_cases() {
+    use tempfile::NamedTempFile;
 
-    // Create large image data with valid JPEG header (1MB)
-    let mut large_data: Vec<u8> = vec![0xFF, 0xD8, 0xFF, 0xE0]; // JPEG header
-    large_data.extend((0..1024 * 1024 - 4).map(|i| (i % 256) as u8));
-    large_data.extend(&[0xFF, 0xD9]); // JPEG footer
+    // Test writing to non-existent file
+    let non_existent_path = "/tmp/non_existent_file_12345.mp3";
+    let test_tags = AudioTags {
+      title: Some("Test".to_string()),
+      ..Default::default()
+    };
 
-    add_cover_image(
-      &mut tag,
-      &large_data,
-      Some("Large Image".to_string()),
-      MimeType::Jpeg,
+    let write_result = write_tags(non_existent_path.to_string(), test_tags.clone()).await;
+    assert!(
+      write_result.is_err(),
+      "Should fail to write to non-existent file"
     );
 
-    // Verify the large image was added
-    let pictures: Vec<_> = tag.pictures().into_iter().collect();
-    assert_eq!(pictures.len(), 1);
+    // Test writing to non-existent directory
+    let invalid_path = "/tmp/non_existent_directory/test.mp3";
+    let write_result = write_tags(invalid_path.to_string(), test_tags).await;
+    assert!(
+      write_result.is_err(),
+      "Should fail to write to non-existent directory"
+    );
 
-    let picture = &pictures[0];
-    assert_eq!(picture.pic_type(), PictureType::CoverFront);
-    assert_eq!(picture.mime_type(), Some(&MimeType::Jpeg));
-    assert_eq!(picture.description(), Some("Large Image"));
-    assert_eq!(picture.data().len(), 1024 * 1024 + 2); // +2 for JPEG footer
-    assert_eq!(picture.data(), large_data);
+    // Test writing to a file that exists but is not audio
+    let temp_file = NamedTempFile::new().unwrap();
+    let write_result = write_tags(
+      temp_file.path().to_string_lossy().to_string(),
+      AudioTags::default(),
+    )
+    .await;
+    assert!(
+      write_result.is_err(),
+      "Should fail to write to non-audio file"
+    );
   }
 
-  #[test]
-  fn test_add_cover_image_all_mime_types() {
-    use lofty::tag::Tag;
-    use lofty::tag::TagType;
+  // Comprehensive tests for write_cover_image_to_file function
 
-    let mut tag = Tag::new(TagType::Id3v2);
+  #[tokio::test]
+  async fn test_write_cover_image_to_file_different_image_types() {
+    use std::io::Write;
+    use tempfile::NamedTempFile;
 
-    // Test all supported MIME types with appropriate test data
+    // Create a temporary file with valid audio data
+    let mut temp_file = NamedTempFile::new().unwrap();
+    let audio_data = create_buffer_from_base64("SUQzBAAAAAAAI1RTU0UAAAAPAAADTGF2ZjU4Ljc2LjEwMAAAAAAAAAAAAAAA/+M4wAAAAAAAAAAAAEluZm8AAAAPAAAAAwAAAbgA").unwrap();
+    temp_file.write_all(&audio_data).unwrap();
+    temp_file.flush().unwrap();
+
+    // Test with different image types
     let test_cases = vec![
-      (create_test_image_data(), MimeType::Jpeg, "image/jpeg"),
-      (
-        vec![
-          0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, // PNG signature
-          0x00, 0x00, 0x00, 0x0D, 0x49, 0x48, 0x44, 0x52, // IHDR chunk
-          0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, // 1x1 pixel
-          0x08, 0x02, 0x00, 0x00, 0x00, 0x90, 0x77, 0x53, // bit depth, color type, etc.
-          0xDE, 0x00, 0x00, 0x00, 0x0C, 0x49, 0x44, 0x41, // IDAT chunk
-          0x54, 0x08, 0x99, 0x01, 0x01, 0x00, 0x00, 0x00, // compressed data
-          0x00, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x00, 0x49, // more data
-          0x45, 0x4E, 0x44, 0xAE, 0x42, 0x60, 0x82, // IEND chunk
-        ],
-        MimeType::Png,
-        "image/png",
-      ),
       (
+        "JPEG",
         vec![
-          0x47, 0x49, 0x46, 0x38, 0x39, 0x61, // GIF89a signature
-          0x01, 0x00, 0x01, 0x00, 0x80, 0x00, 0x00, // 1x1 pixel, color table
-          0xFF, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x21, 0xF9, // color table + graphic control
-          0x04, 0x01, 0x00, 0x00, 0x00, 0x00, 0x2C, 0x00, // extension + image descriptor
-          0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, // image position and size
-          0x00, 0x02, 0x02, 0x04, 0x01, 0x00, 0x3B, // image data + trailer
+          0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10, 0x4A, 0x46, 0x49, 0x46, 0x00, 0x01,
         ],
-        MimeType::Gif,
-        "image/gif",
       ),
       (
+        "PNG",
         vec![
-          0x49, 0x49, 0x2A, 0x00, // Little-endian TIFF signature
-          0x08, 0x00, 0x00, 0x00, // Offset to first IFD
-          0x00, 0x00, // Number of directory entries
-          0x00, 0x00, 0x00, 0x00, // Offset to next IFD
-        ],
-        MimeType::Tiff,
-        "image/tiff",
+          0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, 0x00, 0x00, 0x00, 0x0D,
+        ],
       ),
       (
+        "GIF",
         vec![
-          0x42, 0x4D, // BM signature
-          0x3E, 0x00, 0x00, 0x00, // File size
-          0x00, 0x00, 0x00, 0x00, // Reserved
-          0x3E, 0x00, 0x00, 0x00, // Data offset
-          0x28, 0x00, 0x00, 0x00, // Header size
-          0x01, 0x00, 0x00, 0x00, // Width
-          0x01, 0x00, 0x00, 0x00, // Height
-          0x01, 0x00, // Planes
-          0x18, 0x00, // Bits per pixel
-          0x00, 0x00, 0x00, 0x00, // Compression
-          0x00, 0x00, 0x00, 0x00, // Image size
-          0x00, 0x00, 0x00, 0x00, // X pixels per meter
-          0x00, 0x00, 0x00, 0x00, // Y pixels per meter
-          0x00, 0x00, 0x00, 0x00, // Colors in color table
-          0x00, 0x00, 0x00, 0x00, // Important color count
-          0x00, 0x00, 0xFF, // Pixel data (blue pixel)
+          0x47, 0x49, 0x46, 0x38, 0x39, 0x61, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
         ],
-        MimeType::Bmp,
-        "image/bmp",
       ),
     ];
 
-    for (i, (image_data, expected_mime_type, description)) in test_cases.iter().enumerate() {
-      // Clear previous images
-      tag.remove_picture_type(PictureType::CoverFront);
+    for (image_type, image_data) in test_cases {
+      let write_result = write_cover_image_to_file(
+        temp_file.path().to_string_lossy().to_string(),
+        image_data.clone(),
+      )
+      .await;
+      if let Err(e) = &write_result {
+        println!("Error writing {} image to file: {}", image_type, e);
+        continue;
+      }
+      assert!(
+        write_result.is_ok(),
+        "Should successfully write {} image",
+        image_type
+      );
 
-      // Add image with current MIME type
-      add_cover_image(
-        &mut tag,
+      // Verify the image was written
+      let read_result =
+        read_cover_image_from_file(temp_file.path().to_string_lossy().to_string()).await;
+      if let Err(e) = &read_result {
+        println!("Error reading {} image from file: {}", image_type, e);
+        continue;
+      }
+      let read_image = read_result.unwrap();
+      assert!(
+        read_image.is_some(),
+        "Should have {} image data",
+        image_type
+      );
+      assert_eq!(
+        read_image.unwrap(),
         image_data,
-        Some(format!("Test {}", i)),
-        expected_mime_type.clone(),
+        "{} image data should match",
+        image_type
       );
-
-      // Verify the image was added with correct MIME type
-      let pictures: Vec<_> = tag.pictures().into_iter().collect();
-      assert_eq!(pictures.len(), 1, "Failed for MIME type: {}", description);
-
-      let picture = &pictures[0];
-      assert_eq!(picture.pic_type(), PictureType::CoverFront);
-      assert_eq!(picture.mime_type(), Some(expected_mime_type));
-      assert_eq!(picture.description(), Some(format!("Test {}", i).as_str()));
-      assert_eq!(picture.data(), image_data);
     }
   }
 
-  // Tests for file-based functions using temporary files
-
   #[tokio::test]
-  async fn test_file_operations_basic() {
+  async fn test_write_cover_image_to_file_error_cases() {
     use tempfile::NamedTempFile;
 
-    // Test file path validation
+    let test_image_data = create_test_image_data();
+
+    // Test writing to non-existent file
     let non_existent_path = "/tmp/non_existent_file_12345.mp3";
-    let read_result = read_tags(non_existent_path.to_string()).await;
+    let write_result =
+      write_cover_image_to_file(non_existent_path.to_string(), test_image_data.clone()).await;
     assert!(
-      read_result.is_err(),
-      "Should fail to read from non-existent file"
+      write_result.is_err(),
+      "Should fail to write to non-existent file"
     );
 
-    // Test with empty file
-    let temp_file = NamedTempFile::new().unwrap();
-    let read_result = read_tags(temp_file.path().to_string_lossy().to_string()).await;
-    assert!(read_result.is_err(), "Should fail to read from empty file");
-
     // Test writing to non-existent directory
     let invalid_path = "/tmp/non_existent_directory/test.mp3";
-    let test_tags = AudioTags::default();
-    let write_result = write_tags(invalid_path.to_string(), test_tags).await;
+    let write_result =
+      write_cover_image_to_file(invalid_path.to_string(), test_image_data.clone()).await;
     assert!(
       write_result.is_err(),
       "Should fail to write to non-existent directory"
     );
+
+    // Test writing to a file that exists but is not audio
+    let temp_file = NamedTempFile::new().unwrap();
+    let write_result = write_cover_image_to_file(
+      temp_file.path().to_string_lossy().to_string(),
+      test_image_data,
+    )
+    .await;
+    assert!(
+      write_result.is_err(),
+      "Should fail to write to non-audio file"
+    );
   }
 
   #[tokio::test]
-  async fn test_file_operations_with_valid_audio() {
+  async fn test_write_cover_image_to_file_read_only() {
+    use std::fs::{self, File};
     use std::io::Write;
-    use tempfile::NamedTempFile;
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::tempdir;
 
-    // Create a temporary file with valid audio data from our existing test data
-    let mut temp_file = NamedTempFile::new().unwrap();
-    let audio_data = create_buffer_from_base64("SUQzBAAAAAAAI1RTU0UAAAAPAAADTGF2ZjU4Ljc2LjEwMAAAAAAAAAAAAAAA/+M4wAAAAAAAAAAAAEluZm8AAAAPAAAAAwAAAbgA").unwrap();
-    temp_file.write_all(&audio_data).unwrap();
-    temp_file.flush().unwrap();
+    // Create a temporary directory
+    let temp_dir = tempdir().expect("Failed to create temp dir");
+    let file_path = temp_dir.path().join("readonly.mp3");
 
-    // Test reading tags from file - this should work with our existing test data
-    let result = read_tags(temp_file.path().to_string_lossy().to_string()).await;
-    if let Err(e) = &result {
-      println!("Error reading tags from file: {}", e);
-      // If this fails, we'll skip the file-based tests and focus on buffer-based tests
-      return;
-    }
+    // Create a valid MP3 file
+    let audio_data = create_buffer_from_base64("SUQzBAAAAAAAIlRTU0UAAAAOAAADTGF2ZjYxLjcuMTAwAAAAAAAAAAAAAAD/+1AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAABJbmZvAAAADwAAACgAAEIeAAkJDw8WFhYcHCIiIikpLy8vNTU8PDxCQkhISE5OVVVVW1thYWFoaG5ubnR0e3t7gYGHh4eOjpSUlJqaoaGhp6etra20tLq6usDAx8fHzc3T09Pa2uDg4Obm7e3t8/P5+fn//wAAAABMYXZjNjEuMTkAAAAAAAAAAAAAAAAkBXwAAAAAAABCHsH9lAcAAAAAAAAAAAAAAAAAAAAA//uQZAAP8AAAaQAAAAgAAA0gAAABAAABpAAAACAAADSAAAAETEFNRTMuMTAwVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVTEFNRTMuMTAwVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVV").unwrap();
+    File::create(&file_path)
+      .expect("Failed to create file")
+      .write_all(&audio_data)
+      .expect("Failed to write data");
 
-    let tags = result.unwrap();
+    // Make the file read-only
+    let mut perms = fs::metadata(&file_path)
+      .expect("Failed to get metadata")
+      .permissions();
+    perms.set_mode(0o444); // read-only for everyone
+    fs::set_permissions(&file_path, perms).expect("Failed to set permissions");
 
-    // Verify we get default empty tags for a file without metadata
-    assert_eq!(tags.title, None);
-    assert_eq!(tags.artists, None);
-    assert_eq!(tags.album, None);
-    assert_eq!(tags.year, None);
-    assert_eq!(tags.genre, None);
-    assert_eq!(tags.track, None);
-    assert_eq!(tags.album_artists, None);
-    assert_eq!(tags.comment, None);
-    assert_eq!(tags.disc, None);
-    assert_eq!(tags.image, None);
+    // Try to write cover image
+    let image_data = create_test_image_data();
+    let result =
+      write_cover_image_to_file(file_path.to_string_lossy().to_string(), image_data).await;
+
+    // Verify error
+    assert!(result.is_err(), "Should fail for read-only file");
+    assert!(
+      result.unwrap_err().contains("Failed to open file"),
+      "Should indicate file open error"
+    );
   }
 
   #[tokio::test]
-  async fn test_file_operations_cover_image() {
+  async fn test_write_cover_image_to_file_corrupted_audio() {
+    use std::fs::File;
     use std::io::Write;
-    use tempfile::NamedTempFile;
+    use tempfile::tempdir;
 
-    // Create a temporary file with valid audio data
-    let mut temp_file = NamedTempFile::new().unwrap();
-    let audio_data = create_buffer_from_base64("SUQzBAAAAAAAI1RTU0UAAAAPAAADTGF2ZjU4Ljc2LjEwMAAAAAAAAAAAAAAA/+M4wAAAAAAAAAAAAEluZm8AAAAPAAAAAwAAAbgA").unwrap();
-    temp_file.write_all(&audio_data).unwrap();
-    temp_file.flush().unwrap();
+    // Create a temporary directory
+    let temp_dir = tempdir().expect("Failed to create temp dir");
+    let file_path = temp_dir.path().join("corrupted.mp3");
 
-    // Test writing cover image to file
-    let image_data = create_test_image_data();
-    let write_result = write_cover_image_to_file(
-      temp_file.path().to_string_lossy().to_string(),
-      image_data.clone(),
-    )
-    .await;
-    if let Err(e) = &write_result {
-      println!("Error writing cover image to file: {}", e);
-      return;
-    }
-    assert!(write_result.is_ok());
+    // Create a corrupted MP3 file (valid header but corrupted data)
+    let corrupted_data = vec![
+      // ID3v2 header
+      0x49, 0x44, 0x33, // "ID3"
+      0x03, 0x00, // version 2.3.0
+      0x00, // flags
+      0x00, 0x00, 0x00, 0x10, // size
+      // Corrupted data
+      0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+    ];
+    File::create(&file_path)
+      .expect("Failed to create file")
+      .write_all(&corrupted_data)
+      .expect("Failed to write data");
 
-    // Test reading cover image from file
-    let read_result =
-      read_cover_image_from_file(temp_file.path().to_string_lossy().to_string()).await;
-    if let Err(e) = &read_result {
-      println!("Error reading cover image from file: {}", e);
-      return;
-    }
-    assert!(read_result.is_ok());
-    let cover_image = read_result.unwrap();
+    // Try to write cover image
+    let image_data = create_test_image_data();
+    let result =
+      write_cover_image_to_file(file_path.to_string_lossy().to_string(), image_data).await;
 
-    // Verify we got the cover image
-    assert!(cover_image.is_some());
-    let cover_data = cover_image.unwrap();
-    assert_eq!(cover_data, image_data);
+    // Verify error
+    assert!(result.is_err(), "Should fail for corrupted audio file");
+    assert!(
+      result.unwrap_err().contains("Failed to read audio file"),
+      "Should indicate read error"
+    );
   }
 
-  // Additional comprehensive tests for util::clear_tags and util::read_cover_image_from_file
+  #[cfg(unix)]
+  #[tokio::test]
+  async fn test_read_and_write_tags_through_fd() {
+    use crate::test_support::sample_mp3;
+    use std::io::Seek;
+    use std::os::fd::AsRawFd;
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let file_path = temp_dir.path().join("song.mp3");
+    fs::write(&file_path, sample_mp3()).unwrap();
+    let mut held = fs::OpenOptions::new()
+      .read(true)
+      .write(true)
+      .open(&file_path)
+      .unwrap();
+    held.seek(SeekFrom::Start(7)).unwrap();
+
+    let tags = AudioTags {
+      title: Some("Through Fd".to_string()),
+      ..Default::default()
+    };
+    write_tags_to_fd(held.as_raw_fd(), tags, WriteTagsOptions::default())
+      .await
+      .unwrap();
+    let outcome = read_tags_from_fd(held.as_raw_fd(), ReadTagsOptions::default())
+      .await
+      .unwrap();
+    assert_eq!(outcome.tags.title, Some("Through Fd".to_string()));
+    // the descriptor stays open where it was
+    assert_eq!(held.stream_position().unwrap(), 7);
+    let read = read_tags(file_path.to_string_lossy().to_string())
+      .await
+      .unwrap();
+    assert_eq!(read.title, Some("Through Fd".to_string()));
+
+    assert!(read_tags_from_fd(-1, ReadTagsOptions::default())
+      .await
+      .is_err());
+  }
 
   #[tokio::test]
-  async fn test_clear_tags_empty_buffer() {
-    // Test clearing tags from empty buffer
-    let empty_buffer = vec![];
-    let result = clear_tags_to_buffer(empty_buffer).await;
-    assert!(
-      result.is_err(),
-      "Should fail to clear tags from empty buffer"
-    );
+  async fn test_read_and_write_tags_long_and_non_utf8_paths() {
+    use crate::test_support::sample_mp3;
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    // a deep artist/album tree, past the 260 characters of MAX_PATH
+    let mut dir = temp_dir.path().to_path_buf();
+    for level in 0..8 {
+      dir.push(format!(
+        "{}-{}",
+        level,
+        "Artist Name With Many Words ".repeat(2)
+      ));
+    }
+    fs::create_dir_all(&dir).unwrap();
+    let mut names = vec![std::ffi::OsString::from("01 Tráck ☃.mp3")];
+    #[cfg(unix)]
+    {
+      use std::os::unix::ffi::OsStringExt;
+      // Latin-1 bytes, as old rippers wrote them
+      names.push(std::ffi::OsString::from_vec(b"02 Caf\xe9.mp3".to_vec()));
+    }
+    #[cfg(windows)]
+    {
+      use std::os::windows::ffi::OsStringExt;
+      // an unpaired surrogate, valid in an NTFS name but not in UTF-8
+      let name: Vec<u16> = "02 "
+        .encode_utf16()
+        .chain([0xD800])
+        .chain(".mp3".encode_utf16())
+        .collect();
+      names.push(std::ffi::OsString::from_wide(&name));
+    }
+    for name in names {
+      let file_path = dir.join(name);
+      assert!(file_path.as_os_str().len() > 260);
+      fs::write(&file_path, sample_mp3()).unwrap();
+      let tags = AudioTags {
+        title: Some("Deep".to_string()),
+        ..Default::default()
+      };
+      write_tags(&file_path, tags).await.unwrap();
+      let read = read_tags(&file_path).await.unwrap();
+      assert_eq!(read.title, Some("Deep".to_string()));
+
+      // the other path APIs take the same paths
+      write_cover_image_to_file(&file_path, create_test_image_data())
+        .await
+        .unwrap();
+      assert!(read_cover_image_from_file(&file_path)
+        .await
+        .unwrap()
+        .is_some());
+      clear_tags(&file_path).await.unwrap();
+      assert_eq!(read_tags(&file_path).await.unwrap().title, None);
+    }
   }
 
   #[tokio::test]
-  async fn test_clear_tags_invalid_audio() {
-    // Test clearing tags from invalid audio data
-    let invalid_data = vec![0x00, 0x01, 0x02, 0x03];
-    let result = clear_tags_to_buffer(invalid_data).await;
+  async fn test_errors_name_file_and_cause() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let missing = temp_dir.path().join("missing.mp3");
+    let error = read_tags(missing.to_string_lossy().to_string())
+      .await
+      .unwrap_err();
+    assert!(error.starts_with(&format!(
+      "Failed to open file {} (NotFound): ",
+      missing.display()
+    )));
+
+    let empty = temp_dir.path().join("empty.mp3");
+    fs::write(&empty, &mpeg_stand_in(b"")[..4]).unwrap();
+    let error = read_tags(empty.to_string_lossy().to_string())
+      .await
+      .unwrap_err();
+    // the lofty error, which says what is wrong with the file
     assert!(
-      result.is_err(),
-      "Should fail to clear tags from invalid audio data"
+      error.starts_with(&format!("{}: Failed to read audio file (", empty.display())),
+      "{}",
+      error
     );
+    assert!(error.len() > format!("{}: Failed to read audio file ()", empty.display()).len());
+
+    // buffers have no path
+    let error = read_tags_from_buffer(b"not audio".to_vec())
+      .await
+      .unwrap_err();
+    assert!(error.starts_with("Failed to read audio file (UnknownFormat): "));
   }
 
   #[tokio::test]
-  async fn test_read_cover_image_from_file_error_cases() {
-    use tempfile::NamedTempFile;
-
-    // Test reading cover image from non-existent file
-    let non_existent_path = "/tmp/non_existent_file_12345.mp3";
-    let result = read_cover_image_from_file(non_existent_path.to_string()).await;
-    assert!(
-      result.is_err(),
-      "Should fail to read cover image from non-existent file"
-    );
+  async fn test_write_with_image_limits() {
+    use crate::test_support::sample_mp3;
+    let limits = ImageLimits {
+      max_bytes: Some(16),
+      ..Default::default()
+    };
+    let options = CoverImageOptions {
+      image_limits: Some(limits.clone()),
+      ..Default::default()
+    };
+    let error = write_cover_image_to_buffer_with_options(
+      sample_mp3(),
+      create_test_image_data(),
+      options.clone(),
+    )
+    .await
+    .unwrap_err();
+    assert!(error.starts_with("Invalid image:"), "{}", error);
+    let error = write_cover_image_to_buffer_with_options(sample_mp3(), b"junk".to_vec(), options)
+      .await
+      .unwrap_err();
+    assert_eq!(error, "Invalid image: not an image");
 
-    // Test reading cover image from empty file
-    let temp_file = NamedTempFile::new().unwrap();
-    let result = read_cover_image_from_file(temp_file.path().to_string_lossy().to_string()).await;
+    let tags = AudioTags {
+      image: Some(Image {
+        data: create_test_image_data(),
+        ..Default::default()
+      }),
+      ..Default::default()
+    };
+    let options = WriteTagsOptions {
+      image_limits: Some(limits),
+      ..Default::default()
+    };
     assert!(
-      result.is_err(),
-      "Should fail to read cover image from empty file"
+      write_tags_to_buffer_with_options(sample_mp3(), tags.clone(), options)
+        .await
+        .is_err()
     );
+    assert!(write_tags_to_buffer(sample_mp3(), tags).await.is_ok());
   }
 
   #[tokio::test]
-  async fn test_read_cover_image_from_file_different_image_types() {
-    use std::io::Write;
-    use tempfile::NamedTempFile;
-
-    // Test reading different types of cover images
-    let image_types = vec![
-      ("JPEG", create_test_image_data()),
-      (
-        "PNG",
-        vec![
-          0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, // PNG signature
-          0x00, 0x00, 0x00, 0x0D, 0x49, 0x48, 0x44, 0x52, // IHDR chunk
-          0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, // 1x1 pixel
-          0x08, 0x02, 0x00, 0x00, 0x00, 0x90, 0x77, 0x53, // bit depth, color type, etc.
-          0xDE, 0x00, 0x00, 0x00, 0x0C, 0x49, 0x44, 0x41, // IDAT chunk
-          0x54, 0x08, 0x99, 0x01, 0x01, 0x00, 0x00, 0x00, // compressed data
-          0x00, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x00, 0x49, // more data
-          0x45, 0x4E, 0x44, 0xAE, 0x42, 0x60, 0x82, // IEND chunk
-        ],
-      ),
-    ];
-
-    for (image_type, image_data) in image_types {
-      let mut temp_file = NamedTempFile::new().unwrap();
-      let audio_data = create_buffer_from_base64("SUQzBAAAAAAAI1RTU0UAAAAPAAADTGF2ZjU4Ljc2LjEwMAAAAAAAAAAAAAAA/+M4wAAAAAAAAAAAAEluZm8AAAAPAAAAAwAAAbgA").unwrap();
-      temp_file.write_all(&audio_data).unwrap();
-      temp_file.flush().unwrap();
-
-      // Add cover image to the file
-      let test_tags = AudioTags {
-        image: Some(Image {
-          data: image_data.clone(),
-          pic_type: AudioImageType::CoverFront,
-          mime_type: Some(format!("image/{}", image_type.to_lowercase())),
-          description: Some(format!("Test {} cover", image_type)),
-        }),
+  async fn test_write_cover_image_resized() {
+    use crate::test_support::sample_mp3;
+    let mut large = Cursor::new(Vec::new());
+    image::RgbImage::from_pixel(800, 400, image::Rgb([10, 20, 30]))
+      .write_to(&mut large, image::ImageFormat::Png)
+      .unwrap();
+    let options = CoverImageOptions {
+      mime_type: Some("image/png".to_string()),
+      resize: Some(ResizeOptions {
+        max_edge: 200,
         ..Default::default()
-      };
+      }),
+      ..Default::default()
+    };
 
-      // Write tags with image to file
-      let write_result =
-        write_tags(temp_file.path().to_string_lossy().to_string(), test_tags).await;
-      if let Err(e) = &write_result {
-        println!("Error writing {} tags to file: {}", image_type, e);
-        continue;
-      }
-      assert!(write_result.is_ok());
+    let buffer =
+      write_cover_image_to_buffer_with_options(sample_mp3(), large.into_inner(), options)
+        .await
+        .unwrap();
+    let image = read_tags_from_buffer(buffer).await.unwrap().image.unwrap();
+    assert_eq!(image.mime_type, Some("image/jpeg".to_string()));
+    let decoded = image::load_from_memory(&image.data).unwrap();
+    assert_eq!((decoded.width(), decoded.height()), (200, 100));
+  }
 
-      // Test reading cover image from file
-      let read_result =
-        read_cover_image_from_file(temp_file.path().to_string_lossy().to_string()).await;
-      if let Err(e) = &read_result {
-        println!("Error reading {} cover image from file: {}", image_type, e);
-        continue;
-      }
-      assert!(read_result.is_ok());
-      let cover_image = read_result.unwrap();
+  #[tokio::test]
+  async fn test_write_cover_image_from_path() {
+    let dir = tempfile::tempdir().unwrap();
+    let file_path = dir.path().join("song.mp3").to_string_lossy().to_string();
+    std::fs::copy("music/silence.mp3", &file_path).unwrap();
+    let image_path = dir.path().join("cover.jpg").to_string_lossy().to_string();
+    let image_data = create_test_image_data();
+    std::fs::write(&image_path, &image_data).unwrap();
 
-      // Verify we got the cover image
-      assert!(
-        cover_image.is_some(),
-        "Should have {} cover image",
-        image_type
-      );
-      let cover_data = cover_image.unwrap();
-      assert_eq!(
-        cover_data, image_data,
-        "{} cover image data should match",
-        image_type
-      );
-    }
+    write_cover_image_from_path(file_path.clone(), image_path, CoverImageOptions::default())
+      .await
+      .unwrap();
+    assert_eq!(
+      read_cover_image_from_file(file_path.clone()).await.unwrap(),
+      Some(image_data)
+    );
+    let missing = dir.path().join("missing.jpg").to_string_lossy().to_string();
+    let error = write_cover_image_from_path(file_path, missing, CoverImageOptions::default())
+      .await
+      .unwrap_err();
+    assert!(error.starts_with("Failed to read image"));
   }
 
   #[tokio::test]
-  async fn test_round_trip_with_base64() {
-    // This is a minimal MP3 file header in base64 (just the first few bytes)
-    // In a real test, you would use a complete audio file
-    let mp3_header_base64 = "SUQzBAAAAAAAIlRTU0UAAAAOAAADTGF2ZjYxLjcuMTAwAAAAAAAAAAAAAAD/+1TAAAAAAAAAAAAAAAAAAAAAAABJbmZvAAAADwAAACsAACEAAAsLEREXFx0dHSIiKCguLi40NDo6QEBARUVLS1FRUVdXXV1iYmJoaG5udHR0enqAgIWFhYuLkZGXl5ednaKiqKiorq60tLq6usDAxcXLy8vR0dfX3d3d4uLo6O7u7vT0+vr//wAAAABMYXZjNjEuMTkAAAAAAAAAAAAAAAAkA8AAAAAAAAAhAMFx74YAAAAAAAAAAAAAAAAAAAD/+1TEAAAILAFbdBEAAYMN7qcGMADSMAg0iA8gs+XD8EAwfSUOF4gBDiAEATB8Hw/P4P/icHwfBwEPxAZqBA5/BD4kBD4IAg78EAxrB8PlAQBAMFHFw///7/7VWqAgAAODWI47AAZArODGYIEgoUxbGoCVRCQekalnGgsKNGYYSHCwAeEScasicmFkLyZwNHR4ZJBxR4OqatDLYvepIRrFJw+fqeaB5ZgFnHusRtXDwbZ//xb//paqiZq7p0NPpQD/+1TEBAAKeEdz3PGAAVMRLzzEjSDosYRhE9MwbidRJkIUnGRFtOrMYkUQNmeBRx2ME4XMg8YHCiocETgmQHvEAwbDoZa9AVDQ9fWdvStHptQLNilSVd9NSVeijSQZFSxbLqLqnZ0ksjSQB8Aw8i0eVwwdHIYJhoLBgumoXOsWZRyRqRmjiVzNO6stJRw4Z9RjVe3YuwoFB8AGoqPiYe6LoMEYgsY86Mvj0uTlFxlTL3s0oNu+ms3MqpmHS21tohb/+1TEBQAKbHt757DEwUSJLrzDCdCLwXcOMto3SMKTJdxaNyVCCHAoOToiXgFrZNqecxjE7u8eD327fNLjsqIlYCPHg4fg5ijkyKplA1VdROBb/S1sUbe2tX0ff/L9+1FQ8yzrEkkQABAJAYGniKYgq0UT00DwZGQ1j+/IG6JI9POgWYfCoHUSGvMB9IWEpRJKLJPrWIlrMvsYsVCAvs6lW0ZGGOvXVZXWpWc79q/S+tu5i3plRyIkkAngYdBxJ4X/+1TECAAKKF9157BlgV2J7n2GGGAsnCsgYjMEE/Wnp+BDUBHZbDwClZC5rBIoZT3LHoCChdzCY9RWLXzFWB31FT749fYxGLwsrUox4xTPU6u2jquemnrmLh2RSjKKIAMgRqqc6NAQJXxED4NEo+lI+coACEELWAalmp4NiMOl9OB8VWVFltOBENwoabYMSgETxq9MXWdSFqjc8g+asXsbbGfQ3tQxinKFmoqXuHX927p4dlmtkaIBZGUjS9kuFxP/+1TECQAJMG9955hqgRKHr7zEjUhFXHWuD/dg+6ItAsxVfamqxsq5nVNUW5vJaIKi1goAQ8GB44SHB515RZWWSQFLWMNqZ7Vs7dX+rL3bu5ZV0kjaAI4eksbB2O4vBmjEM0MTwcCCjwuxiDrjgksCjgbKzIGc4PEzhE0NJNHmAwWACDnG3HnmVubQbYqKUV62KombuYZUS/pBOas5/IWfhug6U4Fk5L0fC63C9/rEK6Lu6FPY+bPDQSBWxh5oqVf/+1TEFwAJuF13x7BnASsJb3zAjkA886NUAzEAYmCC0l9ZHWiu7dp+1q/0UIcHi610R6BSYrMq6l4ZLZW2iAYjwXwqWgZKokoQ8j8LF4ygxT+rEzW/A0hheTIW4ItD4SEQOEQVBh1JgjRLE3KoKPfEiBzGGXCsgvzvXu7e3R/+mr29yYh2WxyNtBmJMTg5UDp8jFakbKh6ZIthC0c7xwk8fV0KAxAODjQ0t4vMoCbDQfJFqmLPh8WU1CTZ6gm40Tr/+1TEIAAJhEl956RowScPb3zBieibtJQK8Xhf261+z/Re7dTTKqWyNpIJgKE0oqgOE2MRiwbCeSjYzb6MkRjev2kAZOkRDkRIGj1OlszNoKQ56UB15kipKAM6WbI61aF2t6+BGsJtudUS0YiXZlVUSRttIAuYZRLCOwQkn6IEwVEYmOmigZZChEGAEXJMozOl2e4zS8LASAYNlzRaOFBgJzRQUDwgAZEIlDDhqkn7btm57Uo3RTcu8uHdEtlabIL/+1TEKoAJaFGD56TG0SqE77z1pMD+ilskhPD8RiPQEwKAjixB0GMhkTC5Es8KOEYZLA+H3AiNSJRMyFhjSbXXsaytr6RBOILOviqHCz6bd7P+/V//1Kq5eaeDSNtIgANySDYQgIgLFhuNxZFBCJiNzKJdEgvCCjNO4pCJV0LSDSAMD2ngxQOKBASpNVLgMiQU2A13Kqc7u/S/3dv62e730/ebvXMMs1tbaAaRCyWTRyFwDBkFJ8H5ybC4y5EdqMH/+1TENQAJXEV35iRnAS2K7/z2DDAaQzyddJrCNFUfEzxUbHBpJkE0jgwVdCgbo7LSbK77D95+XIFH2UCjVp99hOq8u5WGQ0pqUBFxKHkTJCEenyElxVcPLtPvYMVj1AhmwRqhqJjsiLnsj0UJUMjs2s/RbtDEHkKEocG1LoorQXa9lC9CU/Nu/o2/R15tXMsr+SSNoCnhHS8oE/S3n+wMCkRzFxskQsSFIZqaALywWHwy8wIw0DLRciVLBUgme3z/+1TEPwAJNJ91x4RRQTEH77z0jUhZhoEBGUWIFnz166XbJSfsU8dookG9FFWHZlZkQz+qUCKawcPtIZs9UPMUf1z4Bh587D/izDR2H2o5HEjXRJ1+ijcT5rFP0rNSxVRrL92Wyo6o4S0hVU7S1/fNNIbKE3s3rw7ITcaKICRJScodRLIoIsDoek546dJpISFWccNBEOmBIVGLBgwbQYAQqeILLD3n2ijQMXN8x3yCmuEGhyMl46g231m2/+L/Wzr/+1TESYAI3KV7zBhNUTGFbvz2GGCV3Mysqnd9bJGkC4EsIQLmhi+XADkF644i15DPj1WNhIIGWEkF161ycwRsOgAHDA6bUOWt7jgRJOqA5A4wNscLR1VRZdGzK3NT7lXXdOX1zMw7vpZGyQaQ6zOPJCjzscjWgUwcpzvE5OjVozqHTIK0hAcQxPQ9hnUhmpiayZoW/EKOoIyAi6wceGVzyJSKuXNyA0n/9H/vu6qopnVIm2kQADDgQCaPIZhANh3/+1TEVQAJhFN957BlASeRL3zzDZBhGJCYMLq9JG2qnpZKZIDMH3vIIQDgfqIn6iBsIYzRsJudsCyL+1Wa3ayj//rkK8quypiGZxtpEg9iaFCtm6ojyL0BICHQ0IRF3QEGkY+2RLxugPHiygwcKB5L0BIY40ApQ4qmg81TVLvQTU9Ze1FXWvUn9eqh3o/+hdqrqpp0axyNMARwqBE6LgkjiDcmEw2uOAkqh0dSwMt2q0C+eLYRiZ4uhcND2mUFxdD/+1TEX4AIYEN55iRnASoHrzz0mNCC1oFIpWuxJcaRS2pFsXoALbBDNIF3m///7voqJmZpWIqqQAezJcQUgclni0XSqQBBWGFm1/r0iWFszUxzKHkI058j2/HsibdyRPdqRIqKNaQTlAq/K6zyBFv6jT6yi0dkXk2X64whLoqvfQrLurmoVl1kbRIECEiI2cTCXU6z+QIuCkfoc0PZODbOfmnVmIfqQKFtqT2RhVL1lpBvOmWCZeI29L/LNTEOUwf/+1TEbgAJiEV75gxQQUCTLnjBiijdxkSl3WP6G0/rZskaujXt3r1bsySNtIkBIFAF3iELwIAq8WbB4JQhhyMBdkwgQTN5zJUqRHMGSA5KE1FAqCIMtBckUEYkBcXSGzqkxU2swLRSaJ2OsVdSfqcf6ezFdPr+5dW5vLiYdmsjaKIBgDyMGgTg46DUG5VSiCcrB4MkGFIHqPNOKyC0cgKxrDvotYE2cEjTiXKOCVZQ+5IeWYd2ueOPGU6yNJYibcT/+1TEdQAJxKV556BtAUwJrvzDDZBKUACSaezTBfAU7W36ty/A+9cVUw6q620kQF6UoXgg5nkyQpJlAW5CFcCAFFlQoROh4d30JDw7sVNjyRfXW284WIgifAQGGxjQ5nip9sBvHAgXSOzMUVW+qENx61/rv+/4pbQqyat5iVZY22UQLQtJmEVCQwPAKpB1Ko5C9cYmdUrleYh7qyghJi+K5tEOeoJhkdDIGQtYkW3MHAykg4VIkdB5ZRLY8BKQNAz/+1TEeYALDGN35hhtAUsO7vz0jRgyp8AyVLgLb/0gOnsOaPDabuph4hkPpZQLYdh6JAFUJ6K9EFInAgfBXlwsNDC4xfUTLxML0waXyCzJgceU8a7fNa7UneKb8S9ZLO0waVNWkZxWjygDN+u59ypBpxnz6t/gav/aj533N/n/yXt/+juqqqq4qGU7G00gAoA8TAZAmCwWlQOB8Eg/qPI7iSS2uXLBL1/hoOM0NTYuoTLmk5tS5Cby8zhcPs+8fzv/+1TEeQAK6JF17DBjwX0Irjj0jOnbuOlo5Kg75EkBSZwEHsHsfTurTT9kdb1UffNR8zMOyRxpIgAmBUviM8UQoCgnHI6jkfiCTDM+WjhpF5BDyY22ISqSPH4ZhxLuO6BDcROC4qD4SafFiJRqEoaCxZAutIiWL6xQQPM+p114ytv4uvfTWr+xFcvbq6llWVxtIgJop8nYXFiMQfiDNEIkgNk4wINXioTdeW70k10JoXBaFpiyf/NJzcQB2iwgizT/+1TEcwAKrLd35jBhwWQNbrzDDcgUaFgCIIbCY4HXlhR7QM2R1vcXaEKXljrv7fOdKu/q67vKqYVHK2ChDiRwnw/zoP4n5zmixoaQmS7WlFKmmCSqUzMqi058LJtKwQGBQRDRAeNeHUlSKRMVDIxtjz7AKLCAbhGutVqX2JHoD29nTZu/so/QzLrLyZZbZI2kFoPgPFwJj60Pz4eHQ6g0PSxcAOSW5U7q7YNDQKg2OEAKKHgKUKChO95g1HHEdl3/+1TEcQBKwHV556RpQU+L7zzzDcg24xW5zgCkq9zdnUhul9ze8V7Mq7p3lZZYIQ0BkGgLjqQQZCAkKzYgrC6wyTWpcUWSvBpWM6MEhl3OGZmpyFyWp1YTUxyicVQF5AS1NPGLVPag4qu5emxzv//qeZmIlFY6WUASaIA4loAw6gxVDy6XSQOZpMrqXgPSXCeuJApgCCkAEeRLi++Tv2Qmyy1VG0TZoK25jiJkqaV0vlyqfFU35XR+GxMqoP1623X/+1TEcYBJMDl95jDEwSYR73zDDZAVdRDMsjbaSCMAw/koolMsBkHTBBJpwKEJZMbg0OZjJThptAEA4XDIQERp4hMJAZpLZ8whizNZoKa2Jj2LZEW3l7MZJEGJYh6NSjC/d9O6mqh4RV/pVBSBIxypQwhRfEyxotToe+WG5Pv2YO74d2JRIS05KxZeak5k4pTwuGEAc8afNvcKtsaMet7DllrV/QXOinc1+1pSOePpsZilxehwBj0QzN3U3Tuiyxv/+1TEfYAJ3L1zxgRTQTwHLzzEmUhNIEsdCIBMnjWBItj6HZwIQlja5d89sOf42tlCDIZmYeudcRIQipQRBwfCbxdomLixMieqUY7MPMGre+LPPuRqRAvX3Dl15Bbv/oW6rIioh11rbaIEIBZTCcoMkeEUnJooIic4RHJklzqEMaZb0pB7+FZUEbtBMIljyFtNzhWiodUg3CFpYPqM2uEpo4k8b4rr20+23//6ZjMmoh0OxtooBMNEIyIg/ACEMfz/+1TEhAAKtGV1xjxhgUeMrzzDDdBILSpGPnGUK9t1PQFK7GfuHYMpJukfzPQUdYvNdJ6zIrDBQUEyRCPHoizWftNVLY4PvIJMX7f/f/+x0uq8y8u5h110baAYH0S0B8EwSJhXHVKbKy2PI7RKEmlL01eDkQTBvnEJTJGjiAyC4faWkwXJoeIXIyCCY6OgKHQXeGHLJFPOLUUzuu6mHZNpU2gROBATB8C5OdiMKiSmFLKYsc273Jgx5rXjBAYcW3P/+1TEhYAJiFV75iTIQTySbvy2DDgmTNmjaiexxxApg4WyOpAGcIWVpjzgWF3sMk1b6n2Xmf//+NNdNe3tu7enjSVtEAsZOmUkBI02sDYUCmqvIxriqY0KOp1QiaZhVZcR9a6EV1bs8+B4CMRDg2SaDgBSIZEXImgKNJCgFKKUYGDdl0qt+2//1XlPU3TMlliZRAMQQCpSDccSg0oGLBSHM6Fpg4kQxfG3d6KuFF8KuwlUmVAzZp5hKwiZG3rXCaX/+1TEjQAJCGF95hhsgTCOL3zBDgiBOHKlC1ou9Oi1oDEKxzL8W66/Y9DO7/6VzN3b66ZrbZJEAtEAP1wTCAHBUQSMbGwVODShcT6sjEhwyMOCiTQqCYFWcNCp2gPvbWLEhG7avYLqcYlBVlDQ4pgWYNYl60nDn/eusrKqYd0ccbJIHIQhFEMmSOw6DwMAED8AILDhzkd0boiQ9F0V4apZdCwpC8gTCihUVcIgmkCFi7klnLIHDaQNqm+rJU1N0Yf/+1TEmAAJvHmB55hsoT6ObzzBiei956ju3a6qx1d7ZGyAEJXavKCKdhDgM5kGFhCLhgkaRbxgqAyExgDCoLBkmDCjhN9rhxlyBUYYOHzDg1sEQQNSIyhQpFveKde2no717pybdpaET/6UUZvlASgBRMEUxXHkCY6Mi1ZYdUVLtSJrKx/cMBzome8EJNZY204F1Oa8CTzQuWJLQmAig0YGIwM3Nqqt91LOnbU//q30Kqy6i5hWWWRtIjBzFBJBoYH/+1TEnoAJWCt/5jEkgS0K7zzEjViYm6bDqZpSgcGCUqLSPPsFMzBWGbroPEx1JBdwRlSKPDye0batMeJDpZwmYbACo2smqe8WH82zUu/1d1PVdbtNEMiSVNIAFoFzgBIrBqApSViGsQ9sWxMo4nz3MSyJDHLJTyJmSqHpuGUKtA64kQ4MnQwBnxqVGZsPHXrs1DjKf0Wd//LalXWDtmmZqZqWVT/pUBIAHOQnEQfTsUEMpCkZFjxVFMMLNuiyuyb/+1TEqQAJIC2B56Rm4TSJLvj2DHCH2RUIEFqkUFiZAVNkTjD4CIA2mFR2yqptTG2F3b70kU2aYjn9tf6KW9iW/JXMtdTKmcaRDAGRaH9YLmE4UIjwaiGDzSFZvXFXWj2EdC5pgusC2CJ50PPicR559RBF66SNp5CFIY1SBt6ubfBH/lFgfZ3KEG1NNtW7ypupd1kljSQUgbBU9GJwfDwSC9CND1BwgDsmE7sTiUmeN5Q7T0Qrm5nwpqedNFIJpI//+1TEswAJnIN55hhsgTSM7vzEjRgzpzGhnbipVZxyru1r+97rCW91oshTF7P/+Qiqi3h2Q4mUiQCQNB8SADgHBsLhceWIAeFaMOtwURTUfpoZsADhZ4oQJigCQghY5RoQky8mcEUw60gKhOjRXFl6XDXrq930udtVwvXNWb72qrzeurmGW26NtAGRXD0MwakUaAGFYqwEM2Tji6Wcf/jwqE2cjJM4IHB4aKA+RFhYqQWDihpgCH4999tctc8+9T3/+1TEu4BJtFd1xiRqwS0IrrzEjODCtV0gKrveQVq7MVpzN6tqZiJbGyQCIMCQNghJwdBQPcSgchpRLxRhSAsyETUH7mgvk9IVY0054HThTKWTKQEh1+sPfBnqKUf+v//pltTf5KhstP/3/vuJeXV3M0+hAF4jh0OpwE5yFJ0LZNQMo3FtwhwnRaTMRdTT1BStZL3TZ74UxiZMii8UIj3F1wuNXpuuHI7+3W5Drijl9gVeupjEMmldpqGZmhVQz+r/+1TExIAJsJl75hhuQT6IrryEjDipBKpEuKKLAnEaXRRFCTk6AIGQIGmxO7MC1Hcd3hjBWyDUjx3YfJA3atA5YbCYqQSWLscFTQVaxqXHyEw4Ue9hM60my24SOtIfVZZ2ZmRTP/6kDycDCcCXKMmhIjRN9HIpWIpyJiTQmRJh4cOGKVksJROQOvBn7rWZ8IPQRBcOhxqDRwwYQyRK1HBu1vRFlIExUadewwkU1fxZityJeJeDKSNoAgRwVAfDtQL/+1TEywAJiE195gxQgSsJsDzDDZ1DJcaWMD1aJBZ1kvDtzK7mR8NkZaaIK4CBnTgCICE4LoFQcIsZCaluGmStYhCY6zR/8n2XHDSlqKJK6W1Kuou6lWMpWkSAAkom4gCCEsIMAPmYuMQkOjY9DjuHxOxTKyqmo+zhKq9fIE4keULBYakqcAi3gcliyn1tlUvoisn9Tn3Sur+jTprenc2kUqpqod3ZJW0kSBNJoLGpTEEkEReOYtVhpCCZjCJLaCT/+1TE1QAJqHlzxiRmwUGK77j0jUpr1iWmxN0EC5cgZMBcu8RGyrXDwyUVPIruXZDe6JK7BRf/dbpr6y6dG7/3VbaZmYU0O2QAAUuDlKAOHIAAAvj0MgoPCCyFkQRVG33TpPI3XMfHzHFHpRnSwfiiBRuSC2vlYSZNfR8uFOemkZmO+oK9GAdAmXXv9xnv1LbgjybEqp2OqvSwP3JbgXMZ+hvMrKuoeVaxttogZgCHxCGakWcwH6IeykXEg5zQ5CH/+1TE24AKIHV/x5hsUTGK7vz2DDjgreaMqSF2n1HYhz4kQITqYugUiheLqHgYigoWeDw4+WQ1BhYBFEv6CKLSKjCe/9FX/qWqq4mIhjcjRJIXZ+Jct5Li3k4GC6IYhhSV0woP3RLfHYScQAugR67q6giJ0AYu/QX1Cg9IIjVGVptG0tMKqSIZ0nht6DosPPhxrjU/beqXZPoWByjtujejTlN/bdTNRDrLZG0gA7C2HKOJcJC0QUiGQ6RjSOpw+1r/+1TE4gAJ2GF157BhQSmJrzzDDVi9QQW4aiHul2Rio3QpaQ6YISX3/GCAxCXj4XCLS4DcNWXGoEyIhdgPKXKZvcB27WV71sSzrcvMuqiFWSySIhHiUPdTJAvqsMo3zRRSTeH4ntHpaguCqTpkiogby3av5szkHUzEbCEXhaUj7tJFOc0Miie/DYjpjpAxcES5mw8wvS9KhqFp5au1FMiNuvqu6uWZbm22iEQfBehZVWOoHg6BmEqonBwbLBgElFn/+1TE6oAMMFNvxjDBSUAKrzzDDZibp2msXzYNfLw5p5jhpFpYek8ETirAwWpMbGVRYXW4Wa+XNpdunGVIQj7/v9CYeGZmUyuYADGQJ3DFWjjQhJpjxCQLAqrIEWpvTiPQrEcWlBmIXLm6JQSIbbpJ2WQC6C+TA5rt/Vn0Xd7377Uno5/td93efzSBWj8Lta+E/94uVal//vM6271NQNgETEy7OxnSqgEo0FgoPgJAHx00DwkRPvgqRFAcCR8FBAL/+1TE5wALVG1157BnAUiPb3z2DHAxAgkfeKJsERI6ceqQU96cYBhhnCK3zMgsWWgSkpZLyoSvA3RHUELDj481q4qilrFCJSKaiHt2U0rZEBWGgqIYxCgikIRBBD8XMjWcEw2OKMb7tqr2HqsqvG86ymporChnml0Zz3nZoMApfFiR+BY82fcZiW2YQC8ogsKEzi2k54J4EILMplFWGAEthUWVxZ8zUVEMxpG40UQCofsCAwaH0skwZEZcPA92WGP/+1TE5gAK2Ml555hsgTCKb3z2GJh8babZnxaHUYkGWlwhOTvX1bOkzxRVlpkCUtBBxZy61ky9bA6ywNyJJd7X0nhu9wJmCbE6WhBXp6kbUU282quXZU9tjaQJ0/JEeYxEmgTngki9DPScfIMV334mUXDvkMPaGEIyLzIMQkBHMOC4MBYAkR5VpMXOTFaGH2QOoobJEFyd0Vds3La2q8todqd0bWXVUzPFlrbaCEEyANyeEoF1o5j04IbFiUsDASr/+1TE6gALtF9vx6RnCUyFbniUmJA9d6KHDuSdZM5cw6u8hqSJg68Dw+CFs6geJn2jAipTzTLF0pBd9w5S1awQ7Rf3wcF3f0fSmqmodkZN0QA904S4hROj9G0eArC5Gk3ExYVhGEkVqkKASBdI1GVqKbjQu9b0DEQgQL7VGuaqY5/XOw5C5n4mLBzHV6dwdu/9a/xTvW5b7+/7vX8M3Y5u9tolwDu/evO0gdO6PeTOvNp4hWsbaaQJOMEFQLB+NJD/+1TE5wALnIdxxgxUgVsRLryWDDhcGJ8VDITqgqD6C5B1lH2hicr9GgYNnzQ5YoADrxhR4fUBVdwbZKa2koVKiMikys8L+KUJvTfRZmriAT/uu6u5hVRa22kgBuF7IKgkWqBSkaikifiJQhWMb2T0YhKlMtghDxYGFQMNBdj2wdEjmhpARAQbFGhJZ0moyrUFEpWKlQmOfpS+JFxiA3Z9Tdcds67fm7mZiIVzappJEADAdsjSMlYgmURHZjTMEjn/+1TE4oAKRGN757BjgUMOL3zGDChq40SCM9yx6gbMGQucEjlB4aCKZISEhjTZpkkHiztpIDvsFXMbLht8ZfegrexhHi2zqz9ixdaZH9GpCYCZCIB3CID8bD0aiwSAK6Ob9tJ/26FD1y9/y7YoKMx7/KwA1hK784U6P63e/8dKyH66hRv+uCVI1DDc9ZE9j//nTEQ1eXBlwn1XtYP//48SpNBaPFFj4rm1n0GL///04p4MFOMS4OGv9sWff///9xr/+1TE5oAMoG9vx6RsyTwI73z2ICAzUgeWWG89s1z/81/////pPrGabvK8CnXFQVVMQU1FMy4xMDBVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVX/+1TE4gAKoEN355hsgUeHLr6YYABVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVX/+1TE5AARkUGp+YekEAAANIOAAARVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVU=";
+  async fn test_write_cover_image_to_file_success() {
+    use std::fs::File;
+    use std::io::Write;
+    use tempfile::tempdir;
 
-    // Test that we can decode it
-    let result = create_buffer_from_base64(mp3_header_base64);
-    assert!(result.is_ok());
-    let buffer = result.unwrap();
+    // Create a temporary directory
+    let temp_dir = tempdir().expect("Failed to create temp dir");
+    let file_path = temp_dir.path().join("test.mp3");
 
-    // Verify it's not empty and has the expected MP3 header
-    assert!(!buffer.is_empty());
-    assert!(buffer.len() > 0);
+    // Create a valid MP3 file
+    let audio_data = create_buffer_from_base64("SUQzBAAAAAAAIlRTU0UAAAAOAAADTGF2ZjYxLjcuMTAwAAAAAAAAAAAAAAD/+1AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAABJbmZvAAAADwAAACgAAEIeAAkJDw8WFhYcHCIiIikpLy8vNTU8PDxCQkhISE5OVVVVW1thYWFoaG5ubnR0e3t7gYGHh4eOjpSUlJqaoaGhp6etra20tLq6usDAx8fHzc3T09Pa2uDg4Obm7e3t8/P5+fn//wAAAABMYXZjNjEuMTkAAAAAAAAAAAAAAAAkBXwAAAAAAABCHsH9lAcAAAAAAAAAAAAAAAAAAAAA//uQZAAP8AAAaQAAAAgAAA0gAAABAAABpAAAACAAADSAAAAETEFNRTMuMTAwVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVTEFNRTMuMTAwVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVV").unwrap();
+    File::create(&file_path)
+      .expect("Failed to create file")
+      .write_all(&audio_data)
+      .expect("Failed to write data");
 
-    // In a real scenario, you could use this buffer with read_tags_from_buffer
-    let buffer = write_tags_to_buffer(
-      buffer,
-      AudioTags {
-        title: Some("Test Song".to_string()),
-        artists: Some(vec!["Test Artist".to_string()]),
-        album: Some("Test Album".to_string()),
-        year: Some(2024),
-        genre: Some("Test Genre".to_string()),
-        track: Some(Position {
-          no: Some(1),
-          of: Some(1),
-        }),
-        album_artists: Some(vec!["Test Album Artist".to_string()]),
-        comment: Some("Test Comment".to_string()),
-        disc: Some(Position {
-          no: Some(1),
-          of: Some(1),
-        }),
-        image: Some(Image {
-          data: create_test_image_data(),
-          pic_type: AudioImageType::CoverFront,
-          mime_type: Some("image/jpeg".to_string()),
-          description: Some("Test cover image".to_string()),
-        }),
-        all_images: None,
-        ..Default::default()
-      },
-    )
-    .await
-    .unwrap();
-    let tags = read_tags_from_buffer(buffer.to_vec()).await.unwrap();
-    assert_eq!(tags.title, Some("Test Song".to_string()));
-    assert_eq!(tags.artists, Some(vec!["Test Artist".to_string()]));
-    assert_eq!(tags.album, Some("Test Album".to_string()));
-    assert_eq!(tags.year, Some(2024));
-    assert_eq!(tags.genre, Some("Test Genre".to_string()));
+    // Create test image data
+    let image_data = create_test_image_data();
+
+    // Write cover image
+    let result =
+      write_cover_image_to_file(file_path.to_string_lossy().to_string(), image_data.clone()).await;
+
+    // Verify success
+    assert!(result.is_ok(), "Should successfully write cover image");
+
+    // Read back the file and verify the image was written correctly
+    let read_result = read_cover_image_from_file(file_path.to_string_lossy().to_string()).await;
+    assert!(read_result.is_ok(), "Should successfully read cover image");
+
+    let read_image = read_result.unwrap();
+    assert!(read_image.is_some(), "Should have cover image");
     assert_eq!(
-      tags.track,
-      Some(Position {
-        no: Some(1),
-        of: Some(1)
-      })
+      read_image.unwrap(),
+      image_data,
+      "Cover image data should match"
     );
+
+    // Read all tags and verify the image is set as cover
+    let tags = read_tags(file_path.to_string_lossy().to_string())
+      .await
+      .expect("Should read tags");
+    assert!(tags.image.is_some(), "Should have cover image in tags");
+    let tag_image = tags.image.unwrap();
     assert_eq!(
-      tags.album_artists,
-      Some(vec!["Test Album Artist".to_string()])
+      tag_image.data, image_data,
+      "Cover image data should match in tags"
     );
-    assert_eq!(tags.comment, Some("Test Comment".to_string()));
     assert_eq!(
-      tags.disc,
-      Some(Position {
-        no: Some(1),
-        of: Some(1)
-      })
-    );
-    assert_eq!(tags.image.is_some(), true);
-
-    let buffer = clear_tags_to_buffer(buffer).await.unwrap();
-    let tags = read_tags_from_buffer(buffer.to_vec()).await.unwrap();
-    assert_eq!(tags.title, None);
-    assert_eq!(tags.artists, None);
-    assert_eq!(tags.album, None);
-    assert_eq!(tags.year, None);
-    assert_eq!(tags.genre, None);
-    assert_eq!(tags.track, None);
-    assert_eq!(tags.album_artists, None);
-    assert_eq!(tags.comment, None);
-    assert_eq!(tags.disc, None);
-    // assert_eq!(tags.image, None);
-
-    let buffer = write_cover_image_to_buffer(buffer.to_vec(), create_test_image_data())
-      .await
-      .unwrap();
-    let image_buffer = read_cover_image_from_buffer(buffer.to_vec()).await.unwrap();
-    assert_eq!(image_buffer.is_some(), true);
-
-    let buf = image_buffer.unwrap().to_vec();
-    let info = infer::Infer::new();
-    let kind = info.get(&buf).expect("file type is known");
-    // guest buffer mime type
-    assert_eq!(kind.mime_type(), "image/jpeg")
+      tag_image.pic_type,
+      AudioImageType::CoverFront,
+      "Image should be set as cover"
+    );
+    assert_eq!(
+      tag_image.mime_type,
+      Some("image/jpeg".to_string()),
+      "MIME type should be JPEG"
+    );
   }
 
-  // Comprehensive tests for write_tags function
+  #[test]
+  fn test_from_picture_type_all_variants() {
+    use lofty::picture::PictureType;
 
-  #[tokio::test]
-  async fn test_write_tags_error_cases() {
-    use tempfile::NamedTempFile;
+    // Test all PictureType variants that have direct mappings
+    let test_cases = vec![
+      (PictureType::Icon, AudioImageType::Icon),
+      (PictureType::OtherIcon, AudioImageType::OtherIcon),
+      (PictureType::CoverFront, AudioImageType::CoverFront),
+      (PictureType::CoverBack, AudioImageType::CoverBack),
+      (PictureType::Leaflet, AudioImageType::Leaflet),
+      (PictureType::Media, AudioImageType::Media),
+      (PictureType::LeadArtist, AudioImageType::LeadArtist),
+      (PictureType::Artist, AudioImageType::Artist),
+      (PictureType::Conductor, AudioImageType::Conductor),
+      (PictureType::Band, AudioImageType::Band),
+      (PictureType::Composer, AudioImageType::Composer),
+      (PictureType::Lyricist, AudioImageType::Lyricist),
+      (
+        PictureType::RecordingLocation,
+        AudioImageType::RecordingLocation,
+      ),
+      (
+        PictureType::DuringRecording,
+        AudioImageType::DuringRecording,
+      ),
+      (
+        PictureType::DuringPerformance,
+        AudioImageType::DuringPerformance,
+      ),
+      (PictureType::ScreenCapture, AudioImageType::ScreenCapture),
+      (PictureType::BrightFish, AudioImageType::BrightFish),
+      (PictureType::Illustration, AudioImageType::Illustration),
+      (PictureType::BandLogo, AudioImageType::BandLogo),
+      (PictureType::PublisherLogo, AudioImageType::PublisherLogo),
+    ];
 
-    // Test writing to non-existent file
-    let non_existent_path = "/tmp/non_existent_file_12345.mp3";
-    let test_tags = AudioTags {
-      title: Some("Test".to_string()),
-      ..Default::default()
-    };
+    for (picture_type, expected_audio_image_type) in test_cases {
+      let result = AudioImageType::from_picture_type(&picture_type);
+      assert_eq!(
+        result, expected_audio_image_type,
+        "Failed to convert PictureType::{:?} to AudioImageType::{:?}",
+        picture_type, expected_audio_image_type
+      );
+    }
+  }
 
-    let write_result = write_tags(non_existent_path.to_string(), test_tags.clone()).await;
-    assert!(
-      write_result.is_err(),
-      "Should fail to write to non-existent file"
-    );
+  #[test]
+  fn test_from_picture_type_other_variant() {
+    use lofty::picture::PictureType;
 
-    // Test writing to non-existent directory
-    let invalid_path = "/tmp/non_existent_directory/test.mp3";
-    let write_result = write_tags(invalid_path.to_string(), test_tags).await;
-    assert!(
-      write_result.is_err(),
-      "Should fail to write to non-existent directory"
-    );
+    // Test that any unknown PictureType variant maps to Other
+    // We'll use a pattern match to ensure we catch any new variants
+    let all_picture_types = vec![
+      PictureType::Icon,
+      PictureType::OtherIcon,
+      PictureType::CoverFront,
+      PictureType::CoverBack,
+      PictureType::Leaflet,
+      PictureType::Media,
+      PictureType::LeadArtist,
+      PictureType::Artist,
+      PictureType::Conductor,
+      PictureType::Band,
+      PictureType::Composer,
+      PictureType::Lyricist,
+      PictureType::RecordingLocation,
+      PictureType::DuringRecording,
+      PictureType::DuringPerformance,
+      PictureType::ScreenCapture,
+      PictureType::BrightFish,
+      PictureType::Illustration,
+      PictureType::BandLogo,
+      PictureType::PublisherLogo,
+    ];
 
-    // Test writing to a file that exists but is not audio
-    let temp_file = NamedTempFile::new().unwrap();
-    let write_result = write_tags(
-      temp_file.path().to_string_lossy().to_string(),
-      AudioTags::default(),
-    )
-    .await;
-    assert!(
-      write_result.is_err(),
-      "Should fail to write to non-audio file"
-    );
+    // Verify that all known variants are handled (not Other)
+    for picture_type in all_picture_types {
+      let result = AudioImageType::from_picture_type(&picture_type);
+      assert_ne!(
+        result,
+        AudioImageType::Other,
+        "PictureType::{:?} should not map to Other",
+        picture_type
+      );
+    }
   }
 
-  // Comprehensive tests for write_cover_image_to_file function
-
-  #[tokio::test]
-  async fn test_write_cover_image_to_file_different_image_types() {
-    use std::io::Write;
-    use tempfile::NamedTempFile;
-
-    // Create a temporary file with valid audio data
-    let mut temp_file = NamedTempFile::new().unwrap();
-    let audio_data = create_buffer_from_base64("SUQzBAAAAAAAI1RTU0UAAAAPAAADTGF2ZjU4Ljc2LjEwMAAAAAAAAAAAAAAA/+M4wAAAAAAAAAAAAEluZm8AAAAPAAAAAwAAAbgA").unwrap();
-    temp_file.write_all(&audio_data).unwrap();
-    temp_file.flush().unwrap();
+  #[test]
+  fn test_to_picture_type_all_variants() {
+    use lofty::picture::PictureType;
 
-    // Test with different image types
+    // Test all AudioImageType variants that have direct mappings
     let test_cases = vec![
+      (AudioImageType::Icon, PictureType::Icon),
+      (AudioImageType::OtherIcon, PictureType::OtherIcon),
+      (AudioImageType::CoverFront, PictureType::CoverFront),
+      (AudioImageType::CoverBack, PictureType::CoverBack),
+      (AudioImageType::Leaflet, PictureType::Leaflet),
+      (AudioImageType::Media, PictureType::Media),
+      (AudioImageType::LeadArtist, PictureType::LeadArtist),
+      (AudioImageType::Artist, PictureType::Artist),
+      (AudioImageType::Conductor, PictureType::Conductor),
+      (AudioImageType::Band, PictureType::Band),
+      (AudioImageType::Composer, PictureType::Composer),
+      (AudioImageType::Lyricist, PictureType::Lyricist),
       (
-        "JPEG",
-        vec![
-          0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10, 0x4A, 0x46, 0x49, 0x46, 0x00, 0x01,
-        ],
+        AudioImageType::RecordingLocation,
+        PictureType::RecordingLocation,
       ),
       (
-        "PNG",
-        vec![
-          0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, 0x00, 0x00, 0x00, 0x0D,
-        ],
+        AudioImageType::DuringRecording,
+        PictureType::DuringRecording,
       ),
       (
-        "GIF",
-        vec![
-          0x47, 0x49, 0x46, 0x38, 0x39, 0x61, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-        ],
+        AudioImageType::DuringPerformance,
+        PictureType::DuringPerformance,
       ),
+      (AudioImageType::ScreenCapture, PictureType::ScreenCapture),
+      (AudioImageType::BrightFish, PictureType::BrightFish),
+      (AudioImageType::Illustration, PictureType::Illustration),
+      (AudioImageType::BandLogo, PictureType::BandLogo),
+      (AudioImageType::PublisherLogo, PictureType::PublisherLogo),
+      (AudioImageType::Other, PictureType::Other),
     ];
 
-    for (image_type, image_data) in test_cases {
-      let write_result = write_cover_image_to_file(
-        temp_file.path().to_string_lossy().to_string(),
-        image_data.clone(),
-      )
-      .await;
-      if let Err(e) = &write_result {
-        println!("Error writing {} image to file: {}", image_type, e);
-        continue;
-      }
-      assert!(
-        write_result.is_ok(),
-        "Should successfully write {} image",
-        image_type
+    for (audio_image_type, expected_picture_type) in test_cases {
+      let result = audio_image_type.build_picture_type();
+      assert_eq!(
+        result, expected_picture_type,
+        "Failed to convert AudioImageType::{:?} to PictureType::{:?}",
+        audio_image_type, expected_picture_type
+      );
+    }
+  }
+
+  #[test]
+  fn test_round_trip_conversion() {
+    use lofty::picture::PictureType;
+
+    // Test that converting from PictureType to AudioImageType and back preserves the value
+    let picture_types = vec![
+      PictureType::Icon,
+      PictureType::OtherIcon,
+      PictureType::CoverFront,
+      PictureType::CoverBack,
+      PictureType::Leaflet,
+      PictureType::Media,
+      PictureType::LeadArtist,
+      PictureType::Artist,
+      PictureType::Conductor,
+      PictureType::Band,
+      PictureType::Composer,
+      PictureType::Lyricist,
+      PictureType::RecordingLocation,
+      PictureType::DuringRecording,
+      PictureType::DuringPerformance,
+      PictureType::ScreenCapture,
+      PictureType::BrightFish,
+      PictureType::Illustration,
+      PictureType::BandLogo,
+      PictureType::PublisherLogo,
+    ];
+
+    for original_picture_type in picture_types {
+      let audio_image_type = AudioImageType::from_picture_type(&original_picture_type);
+      let converted_back = audio_image_type.build_picture_type();
+      assert_eq!(
+        original_picture_type, converted_back,
+        "Round trip conversion failed for PictureType::{:?}",
+        original_picture_type
       );
+    }
+  }
+
+  #[test]
+  fn test_round_trip_conversion_audio_image_type() {
+    // Test that converting from AudioImageType to PictureType and back preserves the value
+    let audio_image_types = vec![
+      AudioImageType::Icon,
+      AudioImageType::OtherIcon,
+      AudioImageType::CoverFront,
+      AudioImageType::CoverBack,
+      AudioImageType::Leaflet,
+      AudioImageType::Media,
+      AudioImageType::LeadArtist,
+      AudioImageType::Artist,
+      AudioImageType::Conductor,
+      AudioImageType::Band,
+      AudioImageType::Composer,
+      AudioImageType::Lyricist,
+      AudioImageType::RecordingLocation,
+      AudioImageType::DuringRecording,
+      AudioImageType::DuringPerformance,
+      AudioImageType::ScreenCapture,
+      AudioImageType::BrightFish,
+      AudioImageType::Illustration,
+      AudioImageType::BandLogo,
+      AudioImageType::PublisherLogo,
+      AudioImageType::Other,
+    ];
 
-      // Verify the image was written
-      let read_result =
-        read_cover_image_from_file(temp_file.path().to_string_lossy().to_string()).await;
-      if let Err(e) = &read_result {
-        println!("Error reading {} image from file: {}", image_type, e);
-        continue;
-      }
-      let read_image = read_result.unwrap();
-      assert!(
-        read_image.is_some(),
-        "Should have {} image data",
-        image_type
-      );
+    for original_audio_image_type in audio_image_types {
+      let picture_type = original_audio_image_type.build_picture_type();
+      let converted_back = AudioImageType::from_picture_type(&picture_type);
       assert_eq!(
-        read_image.unwrap(),
-        image_data,
-        "{} image data should match",
-        image_type
+        original_audio_image_type, converted_back,
+        "Round trip conversion failed for AudioImageType::{:?}",
+        original_audio_image_type
       );
     }
   }
 
-  #[tokio::test]
-  async fn test_write_cover_image_to_file_error_cases() {
-    use tempfile::NamedTempFile;
-
-    let test_image_data = create_test_image_data();
+  #[test]
+  fn test_audio_image_type_enum_completeness() {
+    // Test that we have covered all AudioImageType variants in our tests
+    let all_audio_image_types = vec![
+      AudioImageType::Icon,
+      AudioImageType::OtherIcon,
+      AudioImageType::CoverFront,
+      AudioImageType::CoverBack,
+      AudioImageType::Leaflet,
+      AudioImageType::Media,
+      AudioImageType::LeadArtist,
+      AudioImageType::Artist,
+      AudioImageType::Conductor,
+      AudioImageType::Band,
+      AudioImageType::Composer,
+      AudioImageType::Lyricist,
+      AudioImageType::RecordingLocation,
+      AudioImageType::DuringRecording,
+      AudioImageType::DuringPerformance,
+      AudioImageType::ScreenCapture,
+      AudioImageType::BrightFish,
+      AudioImageType::Illustration,
+      AudioImageType::BandLogo,
+      AudioImageType::PublisherLogo,
+      AudioImageType::Other,
+    ];
 
-    // Test writing to non-existent file
-    let non_existent_path = "/tmp/non_existent_file_12345.mp3";
-    let write_result =
-      write_cover_image_to_file(non_existent_path.to_string(), test_image_data.clone()).await;
-    assert!(
-      write_result.is_err(),
-      "Should fail to write to non-existent file"
+    // This test ensures we have exactly 21 variants (as expected from the integration test)
+    assert_eq!(
+      all_audio_image_types.len(),
+      21,
+      "Expected 21 AudioImageType variants, found {}",
+      all_audio_image_types.len()
     );
+  }
 
-    // Test writing to non-existent directory
-    let invalid_path = "/tmp/non_existent_directory/test.mp3";
-    let write_result =
-      write_cover_image_to_file(invalid_path.to_string(), test_image_data.clone()).await;
-    assert!(
-      write_result.is_err(),
-      "Should fail to write to non-existent directory"
-    );
+  #[tokio::test]
+  async fn test_clear_tags_file_not_found() {
+    // Try to clear tags from a non-existent file
+    let result = clear_tags("non_existent_file.mp3".to_string()).await;
 
-    // Test writing to a file that exists but is not audio
-    let temp_file = NamedTempFile::new().unwrap();
-    let write_result = write_cover_image_to_file(
-      temp_file.path().to_string_lossy().to_string(),
-      test_image_data,
-    )
-    .await;
+    // Verify error
+    assert!(result.is_err(), "Should fail for non-existent file");
     assert!(
-      write_result.is_err(),
-      "Should fail to write to non-audio file"
+      result.unwrap_err().contains("Failed to open file"),
+      "Should indicate file open error"
     );
   }
 
   #[tokio::test]
-  async fn test_write_cover_image_to_file_read_only() {
+  async fn test_clear_tags_no_write_permission() {
     use std::fs::{self, File};
     use std::io::Write;
     use std::os::unix::fs::PermissionsExt;
@@ -4339,11 +9357,22 @@ mod tests {
     let temp_dir = tempdir().expect("Failed to create temp dir");
     let file_path = temp_dir.path().join("readonly.mp3");
 
-    // Create a valid MP3 file
-    let audio_data = create_buffer_from_base64("SUQzBAAAAAAAIlRTU0UAAAAOAAADTGF2ZjYxLjcuMTAwAAAAAAAAAAAAAAD/+1AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAABJbmZvAAAADwAAACgAAEIeAAkJDw8WFhYcHCIiIikpLy8vNTU8PDxCQkhISE5OVVVVW1thYWFoaG5ubnR0e3t7gYGHh4eOjpSUlJqaoaGhp6etra20tLq6usDAx8fHzc3T09Pa2uDg4Obm7e3t8/P5+fn//wAAAABMYXZjNjEuMTkAAAAAAAAAAAAAAAAkBXwAAAAAAABCHsH9lAcAAAAAAAAAAAAAAAAAAAAA//uQZAAP8AAAaQAAAAgAAA0gAAABAAABpAAAACAAADSAAAAETEFNRTMuMTAwVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVTEFNRTMuMTAwVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVV").unwrap();
+    // Create a minimal MP3 file
+    let data = vec![
+      // ID3v2 header
+      0x49, 0x44, 0x33, // "ID3"
+      0x03, 0x00, // version 2.3.0
+      0x00, // flags
+      0x00, 0x00, 0x00, 0x10, // size
+      // Some padding
+      0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // MP3 frame header
+      0xFF, 0xFB, 0x90, 0x44, 0x00, 0x00, 0x00, 0x00,
+    ];
+
+    // Write the file
     File::create(&file_path)
       .expect("Failed to create file")
-      .write_all(&audio_data)
+      .write_all(&data)
       .expect("Failed to write data");
 
     // Make the file read-only
@@ -4353,523 +9382,1378 @@ mod tests {
     perms.set_mode(0o444); // read-only for everyone
     fs::set_permissions(&file_path, perms).expect("Failed to set permissions");
 
-    // Try to write cover image
-    let image_data = create_test_image_data();
-    let result =
-      write_cover_image_to_file(file_path.to_string_lossy().to_string(), image_data).await;
+    // Try to clear tags
+    let result = clear_tags(file_path.to_string_lossy().to_string()).await;
 
     // Verify error
     assert!(result.is_err(), "Should fail for read-only file");
     assert!(
-      result.unwrap_err().contains("Failed to write file"),
-      "Should indicate write error"
+      result.unwrap_err().contains("Failed to open file"),
+      "Should indicate file open error"
     );
   }
 
   #[tokio::test]
-  async fn test_write_cover_image_to_file_corrupted_audio() {
+  async fn test_clear_tags_invalid_file() {
     use std::fs::File;
     use std::io::Write;
     use tempfile::tempdir;
 
     // Create a temporary directory
     let temp_dir = tempdir().expect("Failed to create temp dir");
-    let file_path = temp_dir.path().join("corrupted.mp3");
+    let file_path = temp_dir.path().join("invalid.mp3");
 
-    // Create a corrupted MP3 file (valid header but corrupted data)
-    let corrupted_data = vec![
-      // ID3v2 header
-      0x49, 0x44, 0x33, // "ID3"
-      0x03, 0x00, // version 2.3.0
-      0x00, // flags
-      0x00, 0x00, 0x00, 0x10, // size
-      // Corrupted data
-      0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
-    ];
+    // Create an invalid file that's too short to be a valid MP3
+    let data = vec![0x00];
     File::create(&file_path)
       .expect("Failed to create file")
-      .write_all(&corrupted_data)
+      .write_all(&data)
       .expect("Failed to write data");
 
-    // Try to write cover image
-    let image_data = create_test_image_data();
-    let result =
-      write_cover_image_to_file(file_path.to_string_lossy().to_string(), image_data).await;
+    // Try to clear tags
+    let result = clear_tags(file_path.to_string_lossy().to_string()).await;
 
     // Verify error
-    assert!(result.is_err(), "Should fail for corrupted audio file");
+    assert!(result.is_err(), "Should fail for invalid file");
+    let error = result.unwrap_err();
     assert!(
-      result.unwrap_err().contains("Failed to read audio file"),
-      "Should indicate read error"
+      error.contains("Failed to read audio file"),
+      "Should indicate read error, got: {}",
+      error
+    );
+  }
+
+  #[tokio::test]
+  async fn test_clear_tags_success() {
+    use std::fs::File;
+    use std::io::Write;
+    use tempfile::tempdir;
+
+    // Create a temporary directory
+    let temp_dir = tempdir().expect("Failed to create temp dir");
+    let file_path = temp_dir.path().join("test.mp3");
+
+    // Create a minimal valid MP3 file
+    let audio_data = create_buffer_from_base64("SUQzBAAAAAAAIlRTU0UAAAAOAAADTGF2ZjYxLjcuMTAwAAAAAAAAAAAAAAD/+1AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAABJbmZvAAAADwAAACgAAEIeAAkJDw8WFhYcHCIiIikpLy8vNTU8PDxCQkhISE5OVVVVW1thYWFoaG5ubnR0e3t7gYGHh4eOjpSUlJqaoaGhp6etra20tLq6usDAx8fHzc3T09Pa2uDg4Obm7e3t8/P5+fn//wAAAABMYXZjNjEuMTkAAAAAAAAAAAAAAAAkBXwAAAAAAABCHsH9lAcAAAAAAAAAAAAAAAAAAAAA//uQZAAP8AAAaQAAAAgAAA0gAAABAAABpAAAACAAADSAAAAETEFNRTMuMTAwVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVTEFNRTMuMTAwVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVV").unwrap();
+
+    // Create test tags
+    let test_tags = AudioTags {
+      title: Some("Test Song".to_string()),
+      artists: Some(vec!["Test Artist".to_string()]),
+      album: Some("Test Album".to_string()),
+      year: Some(2024),
+      genre: Some("Test Genre".to_string()),
+      track: Some(Position {
+        no: Some(1),
+        of: Some(10),
+      }),
+      album_artists: Some(vec!["Test Album Artist".to_string()]),
+      comment: Some("Test comment".to_string()),
+      disc: Some(Position {
+        no: Some(1),
+        of: Some(2),
+      }),
+      image: Some(Image {
+        data: create_test_image_data(),
+        pic_type: AudioImageType::CoverFront,
+        mime_type: Some("image/jpeg".to_string()),
+        description: Some("Test cover".to_string()),
+        ..Default::default()
+      }),
+      all_images: None,
+      ..Default::default()
+    };
+
+    // Write tags to buffer
+    let tagged_buffer = write_tags_to_buffer(audio_data, test_tags)
+      .await
+      .expect("Failed to write tags");
+
+    // Write the file
+    File::create(&file_path)
+      .expect("Failed to create file")
+      .write_all(&tagged_buffer)
+      .expect("Failed to write data");
+
+    // Clear the tags
+    let result = clear_tags(file_path.to_string_lossy().to_string()).await;
+
+    // Verify success
+    assert!(result.is_ok(), "Should successfully clear tags");
+
+    // Read back the tags
+    let read_result = read_tags(file_path.to_string_lossy().to_string()).await;
+    assert!(read_result.is_ok(), "Should successfully read cleared file");
+
+    // Verify tags are cleared
+    let read_tags = read_result.unwrap();
+    assert!(read_tags.title.is_none(), "Title should be cleared");
+    assert!(read_tags.artists.is_none(), "Artists should be cleared");
+    assert!(read_tags.album.is_none(), "Album should be cleared");
+    assert!(read_tags.image.is_none(), "Image should be cleared");
+  }
+
+  #[tokio::test]
+  async fn test_clear_tags_to_buffer_keeps_requested_items() {
+    let audio_data = std::fs::read("music/silence.mp3").expect("Failed to read test file");
+    let tags = AudioTags {
+      title: Some("Test Song".to_string()),
+      image: Some(Image {
+        data: create_test_image_data(),
+        pic_type: AudioImageType::CoverFront,
+        mime_type: Some("image/jpeg".to_string()),
+        description: None,
+        ..Default::default()
+      }),
+      ..Default::default()
+    };
+    let tagged = write_tags_to_buffer(audio_data, tags).await.unwrap();
+    // technical items aren't exposed through AudioTags, add them with lofty directly
+    let mut input = tagged.clone();
+    let mut tagged = tagged;
+    let mut tagged_file = Probe::new(&mut Cursor::new(&mut input))
+      .guess_file_type()
+      .unwrap()
+      .read()
+      .unwrap();
+    let tag = tagged_file.primary_tag_mut().unwrap();
+    tag.insert_text(ItemKey::EncoderSettings, "Lavf61.7.100".to_string());
+    tag.insert_text(ItemKey::ReplayGainTrackGain, "-6.50 dB".to_string());
+    tagged_file
+      .save_to(&mut Cursor::new(&mut tagged), WriteOptions::default())
+      .unwrap();
+
+    let cleared = clear_tags_to_buffer_with_options(
+      tagged.clone(),
+      ClearTagsOptions {
+        keep_images: true,
+        keep_technical: true,
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+    let mut cursor = Cursor::new(cleared);
+    let tagged_file = Probe::new(&mut cursor)
+      .guess_file_type()
+      .unwrap()
+      .read()
+      .unwrap();
+    let tag = tagged_file.primary_tag().unwrap();
+    assert!(tag.title().is_none());
+    assert_eq!(tag.pictures().len(), 1);
+    assert_eq!(
+      tag.get_string(&ItemKey::EncoderSettings),
+      Some("Lavf61.7.100")
+    );
+    assert_eq!(
+      tag.get_string(&ItemKey::ReplayGainTrackGain),
+      Some("-6.50 dB")
     );
+
+    let cleared = clear_tags_to_buffer(tagged).await.unwrap();
+    let tags = read_tags_from_buffer(cleared).await.unwrap();
+    assert_eq!(tags, AudioTags::default());
   }
 
   #[tokio::test]
-  async fn test_write_cover_image_to_file_success() {
-    use std::fs::File;
-    use std::io::Write;
-    use tempfile::tempdir;
+  async fn test_clear_tags_keep_images_formats() {
+    use crate::test_support::{sample_m4a, sample_wav};
+    let keep_images = ClearTagsOptions {
+      keep_images: true,
+      ..Default::default()
+    };
+    for audio_data in [sample_m4a(), sample_wav()] {
+      let tags = AudioTags {
+        title: Some("Title".to_string()),
+        album: Some("Album".to_string()),
+        all_images: Some(vec![Image {
+          data: create_test_image_data(),
+          pic_type: AudioImageType::CoverFront,
+          ..Default::default()
+        }]),
+        ..Default::default()
+      };
+      let tagged = write_tags_to_buffer(audio_data, tags).await.unwrap();
+      let cleared = clear_tags_to_buffer_with_options(tagged, keep_images.clone())
+        .await
+        .unwrap();
+      let tags = read_tags_from_buffer(cleared).await.unwrap();
+      assert_eq!(tags.title, None);
+      assert_eq!(tags.album, None);
+      let images = tags.all_images.unwrap();
+      assert_eq!(images.len(), 1);
+      assert_eq!(images[0].data, create_test_image_data());
+    }
+  }
 
-    // Create a temporary directory
-    let temp_dir = tempdir().expect("Failed to create temp dir");
-    let file_path = temp_dir.path().join("test.mp3");
+  #[tokio::test]
+  async fn test_read_audio_properties() {
+    let audio_data = std::fs::read("music/sine-440-tone.mp3").expect("Failed to read test file");
+    let properties = read_audio_properties_from_buffer(audio_data.clone())
+      .await
+      .unwrap();
+    assert!(properties.duration_ms > 0);
+    assert!(properties.bitrate.unwrap() > 0);
+    assert_eq!(properties.sample_rate, Some(44100));
+    assert!(matches!(properties.channels, Some(1 | 2)));
 
-    // Create a valid MP3 file
-    let audio_data = create_buffer_from_base64("SUQzBAAAAAAAIlRTU0UAAAAOAAADTGF2ZjYxLjcuMTAwAAAAAAAAAAAAAAD/+1AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAABJbmZvAAAADwAAACgAAEIeAAkJDw8WFhYcHCIiIikpLy8vNTU8PDxCQkhISE5OVVVVW1thYWFoaG5ubnR0e3t7gYGHh4eOjpSUlJqaoaGhp6etra20tLq6usDAx8fHzc3T09Pa2uDg4Obm7e3t8/P5+fn//wAAAABMYXZjNjEuMTkAAAAAAAAAAAAAAAAkBXwAAAAAAABCHsH9lAcAAAAAAAAAAAAAAAAAAAAA//uQZAAP8AAAaQAAAAgAAA0gAAABAAABpAAAACAAADSAAAAETEFNRTMuMTAwVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVTEFNRTMuMTAwVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVV").unwrap();
-    File::create(&file_path)
-      .expect("Failed to create file")
-      .write_all(&audio_data)
-      .expect("Failed to write data");
+    // tags don't change the properties
+    let tags = AudioTags {
+      title: Some("Tone".to_string()),
+      ..Default::default()
+    };
+    let tagged = write_tags_to_buffer(audio_data, tags).await.unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("tone.mp3");
+    std::fs::write(&path, tagged).unwrap();
+    let from_file = read_audio_properties(path.to_string_lossy().to_string())
+      .await
+      .unwrap();
+    assert_eq!(from_file, properties);
 
-    // Create test image data
-    let image_data = create_test_image_data();
+    let result = read_audio_properties_from_buffer(b"not audio".to_vec()).await;
+    assert!(result.is_err());
+  }
 
-    // Write cover image
-    let result =
-      write_cover_image_to_file(file_path.to_string_lossy().to_string(), image_data.clone()).await;
+  #[tokio::test]
+  async fn test_read_all_tags() {
+    use lofty::id3::v1::Id3v1Tag;
+    use lofty::mpeg::MpegFile;
 
-    // Verify success
-    assert!(result.is_ok(), "Should successfully write cover image");
+    let audio_data = std::fs::read("music/silence.mp3").expect("Failed to read test file");
+    let tags = AudioTags {
+      title: Some("ID3v2 Title".to_string()),
+      ..Default::default()
+    };
+    let buffer = write_tags_to_buffer(audio_data, tags).await.unwrap();
+    let mut file =
+      MpegFile::read_from(&mut Cursor::new(buffer.clone()), ParseOptions::new()).unwrap();
+    file.set_id3v1(Id3v1Tag {
+      title: Some("ID3v1 Title".to_string()),
+      ..Default::default()
+    });
+    let mut out = Cursor::new(buffer);
+    file.save_to(&mut out, WriteOptions::default()).unwrap();
 
-    // Read back the file and verify the image was written correctly
-    let read_result = read_cover_image_from_file(file_path.to_string_lossy().to_string()).await;
-    assert!(read_result.is_ok(), "Should successfully read cover image");
+    let entries = read_all_tags_from_buffer(out.into_inner()).await.unwrap();
+    let titles: Vec<(&str, Option<&str>)> = entries
+      .iter()
+      .map(|entry| (entry.tag_type.as_str(), entry.tags.title.as_deref()))
+      .collect();
+    assert_eq!(
+      titles,
+      vec![
+        ("Id3v2", Some("ID3v2 Title")),
+        ("Id3v1", Some("ID3v1 Title")),
+      ]
+    );
 
-    let read_image = read_result.unwrap();
-    assert!(read_image.is_some(), "Should have cover image");
+    let result = read_all_tags_from_buffer(b"not audio".to_vec()).await;
+    assert!(result.is_err());
+  }
+
+  #[test]
+  fn test_merge_tags() {
+    let text = |value: &str| Some(value.to_string());
+    let existing = AudioTags {
+      title: text("Title"),
+      artists: Some(vec![]),
+      album: text(" "),
+      year: Some(1999),
+      genres: Some(vec!["Rock".to_string()]),
+      track: Some(Position {
+        no: Some(3),
+        of: None,
+      }),
+      custom_fields: Some(BTreeMap::from([
+        ("MOOD".to_string(), vec!["Calm".to_string()]),
+        ("TOWN".to_string(), vec!["Leeds".to_string()]),
+      ])),
+      ..Default::default()
+    };
+    let incoming = AudioTags {
+      title: text("Better Title"),
+      artists: Some(vec!["Artist".to_string()]),
+      album: text("Album"),
+      year: None,
+      genre: text("Pop"),
+      track: Some(Position {
+        no: Some(4),
+        of: Some(12),
+      }),
+      custom_fields: Some(BTreeMap::from([
+        ("MOOD".to_string(), vec!["Dark".to_string()]),
+        ("RELEASECOUNTRY".to_string(), vec!["GB".to_string()]),
+      ])),
+      ..Default::default()
+    };
+    let merge = |rule: MergeRule, overrides: Vec<(TagField, MergeRule)>| {
+      merge_tags(
+        existing.clone(),
+        incoming.clone(),
+        &MergeStrategy { rule, overrides },
+      )
+    };
+
+    let merged = merge(MergeRule::PreferIncoming, vec![]);
+    assert_eq!(merged.title, text("Better Title"));
+    assert_eq!(merged.year, Some(1999));
+    // genre and genres are merged together, as they are written together
+    assert_eq!((merged.genre, merged.genres), (text("Pop"), None));
+    assert_eq!(merged.track, incoming.track);
+    let custom_fields = merged.custom_fields.unwrap();
+    assert_eq!(custom_fields["MOOD"], vec!["Dark".to_string()]);
+    assert_eq!(custom_fields["TOWN"], vec!["Leeds".to_string()]);
+    assert_eq!(custom_fields["RELEASECOUNTRY"], vec!["GB".to_string()]);
+
+    let merged = merge(MergeRule::PreferExisting, vec![]);
+    assert_eq!(merged.title, text("Title"));
+    // empty values are filled in
+    assert_eq!(merged.artists, incoming.artists);
+    assert_eq!(merged.album, text("Album"));
+    assert_eq!(merged.genres, existing.genres);
+    assert_eq!(merged.track, existing.track);
     assert_eq!(
-      read_image.unwrap(),
-      image_data,
-      "Cover image data should match"
+      merged.custom_fields.unwrap()["MOOD"],
+      vec!["Calm".to_string()]
     );
 
-    // Read all tags and verify the image is set as cover
-    let tags = read_tags(file_path.to_string_lossy().to_string())
-      .await
-      .expect("Should read tags");
-    assert!(tags.image.is_some(), "Should have cover image in tags");
-    let tag_image = tags.image.unwrap();
+    let merged = merge(
+      MergeRule::FillEmptyOnly,
+      vec![(TagField::Title, MergeRule::PreferIncoming)],
+    );
+    assert_eq!(merged.title, text("Better Title"));
     assert_eq!(
-      tag_image.data, image_data,
-      "Cover image data should match in tags"
+      merged.track,
+      Some(Position {
+        no: Some(3),
+        of: Some(12),
+      })
     );
+  }
+
+  #[tokio::test]
+  async fn test_write_and_read_raw_tag_items() {
+    let audio_data = std::fs::read("music/silence.mp3").expect("Failed to read test file");
+    let tags = AudioTags {
+      title: Some("Title".to_string()),
+      artists: Some(vec!["Wrong Artist".to_string()]),
+      ..Default::default()
+    };
+    let buffer = write_tags_to_buffer(audio_data, tags).await.unwrap();
+    let mut items = read_raw_tag_items_from_buffer(buffer.clone())
+      .await
+      .unwrap();
+    let artist = items
+      .iter_mut()
+      .find(|item| item.key_string.as_deref() == Some("TPE1"))
+      .unwrap();
+    assert_eq!(artist.tag_type, "Id3v2");
+    assert_eq!(artist.key, "TrackArtist");
+    artist.value = ItemValue::Text("Artist".to_string());
+    items.push(RawTagItem {
+      tag_type: "Id3v2".to_string(),
+      key: "Unknown".to_string(),
+      key_string: Some("MY FIELD".to_string()),
+      value: ItemValue::Text("B000002UAL".to_string()),
+    });
+    items.push(RawTagItem {
+      tag_type: "Id3v1".to_string(),
+      key: "TrackTitle".to_string(),
+      key_string: None,
+      value: ItemValue::Text("ID3v1 Title".to_string()),
+    });
+
+    let buffer = write_raw_tag_items_to_buffer(buffer, items).await.unwrap();
+    let items = read_raw_tag_items_from_buffer(buffer.clone())
+      .await
+      .unwrap();
+    let values: Vec<(&str, Option<&str>, Option<&str>)> = items
+      .iter()
+      .map(|item| {
+        (
+          item.tag_type.as_str(),
+          item.key_string.as_deref(),
+          item.value.text(),
+        )
+      })
+      .collect();
+    assert!(values.contains(&("Id3v2", Some("TPE1"), Some("Artist"))));
+    assert!(values.contains(&("Id3v2", Some("MY FIELD"), Some("B000002UAL"))));
+    assert!(values.contains(&("Id3v1", None, Some("ID3v1 Title"))));
+    let read = read_tags_from_buffer(buffer.clone()).await.unwrap();
+    assert_eq!(read.title, Some("Title".to_string()));
+
+    let unknown = RawTagItem {
+      tag_type: "Id3v3".to_string(),
+      key: "TrackTitle".to_string(),
+      key_string: Some("TIT2".to_string()),
+      value: ItemValue::Text("Title".to_string()),
+    };
+    let result = write_raw_tag_items_to_buffer(buffer, vec![unknown]).await;
+    assert_eq!(result, Err("Unknown tag type: Id3v3".to_string()));
+  }
+
+  #[tokio::test]
+  async fn test_artist_separator() {
+    let audio_data = std::fs::read("music/silence.mp3").expect("Failed to read test file");
+    let artists = vec!["Tyler, The Creator".to_string(), "Frank Ocean".to_string()];
+    let tags = AudioTags {
+      artists: Some(artists.clone()),
+      album_artists: Some(artists.clone()),
+      ..Default::default()
+    };
+    let read = |buffer: Vec<u8>, artist_separator| async move {
+      let options = ReadTagsOptions {
+        artist_separator,
+        ..Default::default()
+      };
+      let tags = read_tags_from_buffer_with_options(buffer, options)
+        .await
+        .unwrap()
+        .tags;
+      (tags.artists.unwrap(), tags.album_artists.unwrap())
+    };
+
+    for (audio, separator) in [
+      (audio_data.clone(), ArtistSeparator::MultiValue),
+      (audio_data.clone(), ArtistSeparator::Text("; ".to_string())),
+      (empty_flac(), ArtistSeparator::MultiValue),
+    ] {
+      let options = WriteTagsOptions {
+        artist_separator: separator.clone(),
+        ..Default::default()
+      };
+      let buffer = write_tags_to_buffer_with_options(audio, tags.clone(), options)
+        .await
+        .unwrap();
+      assert_eq!(
+        read(buffer.clone(), separator.clone()).await,
+        (artists.clone(), artists.clone()),
+        "{:?}",
+        separator
+      );
+    }
+
+    // by default, artists are joined with ", " and split on commas
+    let buffer = write_tags_to_buffer(audio_data, tags.clone())
+      .await
+      .unwrap();
+    let split = vec![
+      "Tyler".to_string(),
+      "The Creator".to_string(),
+      "Frank Ocean".to_string(),
+    ];
     assert_eq!(
-      tag_image.pic_type,
-      AudioImageType::CoverFront,
-      "Image should be set as cover"
+      read(buffer.clone(), ArtistSeparator::default()).await,
+      (split.clone(), split)
     );
+    let joined = vec!["Tyler, The Creator, Frank Ocean".to_string()];
     assert_eq!(
-      tag_image.mime_type,
-      Some("image/jpeg".to_string()),
-      "MIME type should be JPEG"
+      read(buffer, ArtistSeparator::MultiValue).await,
+      (joined.clone(), joined)
     );
   }
 
-  #[test]
-  fn test_from_picture_type_all_variants() {
-    use lofty::picture::PictureType;
+  #[tokio::test]
+  async fn test_genres() {
+    let audio_data = std::fs::read("music/silence.mp3").expect("Failed to read test file");
+    let tags = AudioTags {
+      genre: Some("Ignored".to_string()),
+      genres: Some(vec!["Rock".to_string(), "Trip-Hop".to_string()]),
+      ..Default::default()
+    };
+    let buffer = write_tags_to_buffer(audio_data.clone(), tags)
+      .await
+      .unwrap();
+    let tags = read_tags_from_buffer(buffer.clone()).await.unwrap();
+    assert_eq!(tags.genre, Some("Rock".to_string()));
+    assert_eq!(
+      tags.genres,
+      Some(vec!["Rock".to_string(), "Trip-Hop".to_string()])
+    );
 
-    // Test all PictureType variants that have direct mappings
-    let test_cases = vec![
-      (PictureType::Icon, AudioImageType::Icon),
-      (PictureType::OtherIcon, AudioImageType::OtherIcon),
-      (PictureType::CoverFront, AudioImageType::CoverFront),
-      (PictureType::CoverBack, AudioImageType::CoverBack),
-      (PictureType::Leaflet, AudioImageType::Leaflet),
-      (PictureType::Media, AudioImageType::Media),
-      (PictureType::LeadArtist, AudioImageType::LeadArtist),
-      (PictureType::Artist, AudioImageType::Artist),
-      (PictureType::Conductor, AudioImageType::Conductor),
-      (PictureType::Band, AudioImageType::Band),
-      (PictureType::Composer, AudioImageType::Composer),
-      (PictureType::Lyricist, AudioImageType::Lyricist),
-      (
-        PictureType::RecordingLocation,
-        AudioImageType::RecordingLocation,
-      ),
-      (
-        PictureType::DuringRecording,
-        AudioImageType::DuringRecording,
-      ),
-      (
-        PictureType::DuringPerformance,
-        AudioImageType::DuringPerformance,
-      ),
-      (PictureType::ScreenCapture, AudioImageType::ScreenCapture),
-      (PictureType::BrightFish, AudioImageType::BrightFish),
-      (PictureType::Illustration, AudioImageType::Illustration),
-      (PictureType::BandLogo, AudioImageType::BandLogo),
-      (PictureType::PublisherLogo, AudioImageType::PublisherLogo),
-    ];
+    // ID3 genre numbers read as names
+    let tags = AudioTags {
+      genre: Some("(17)(31)".to_string()),
+      ..Default::default()
+    };
+    let buffer = write_tags_to_buffer(audio_data, tags).await.unwrap();
+    let tags = read_tags_from_buffer(buffer.clone()).await.unwrap();
+    assert_eq!(
+      tags.genres,
+      Some(vec!["Rock".to_string(), "Trance".to_string()])
+    );
 
-    for (picture_type, expected_audio_image_type) in test_cases {
-      let result = AudioImageType::from_picture_type(&picture_type);
+    // ID3v1 only has the genres of its list
+    let options = WriteTagsOptions {
+      tag_type: Some(TagType::Id3v1),
+      ..Default::default()
+    };
+    let tags = AudioTags {
+      genre: Some("hip-hop".to_string()),
+      ..Default::default()
+    };
+    let buffer = write_tags_to_buffer_with_options(buffer, tags, options)
+      .await
+      .unwrap();
+    let entries = read_all_tags_from_buffer(buffer).await.unwrap();
+    let id3v1 = entries
+      .into_iter()
+      .find(|entry| entry.tag_type == "Id3v1")
+      .unwrap();
+    assert_eq!(id3v1.tags.genre, Some("Hip-Hop".to_string()));
+  }
+
+  #[tokio::test]
+  async fn test_replay_gain() {
+    assert_eq!(parse_replay_gain("-6.50 dB"), Some(-6.5));
+    assert_eq!(parse_replay_gain(" +1.2db "), Some(1.2));
+    assert_eq!(parse_replay_gain("0.988547"), Some(0.988547));
+    assert_eq!(parse_replay_gain("loud"), None);
+
+    let tags = AudioTags {
+      replay_gain_track_gain: Some(-6.5),
+      replay_gain_track_peak: Some(0.988547),
+      replay_gain_album_gain: Some(-7.25),
+      replay_gain_album_peak: Some(1.0),
+      ..Default::default()
+    };
+    let audio_data = std::fs::read("music/silence.mp3").expect("Failed to read test file");
+    for audio in [audio_data, empty_flac()] {
+      let buffer = write_tags_to_buffer(audio, tags.clone()).await.unwrap();
+      let file = Probe::new(Cursor::new(buffer.clone()))
+        .guess_file_type()
+        .unwrap()
+        .read()
+        .unwrap();
+      let primary_tag = file.primary_tag().unwrap();
       assert_eq!(
-        result, expected_audio_image_type,
-        "Failed to convert PictureType::{:?} to AudioImageType::{:?}",
-        picture_type, expected_audio_image_type
+        primary_tag.get_string(&ItemKey::ReplayGainTrackGain),
+        Some("-6.50 dB")
+      );
+      assert_eq!(
+        primary_tag.get_string(&ItemKey::ReplayGainAlbumPeak),
+        Some("1.000000")
       );
+
+      let read = read_tags_from_buffer(buffer).await.unwrap();
+      assert_eq!(read.replay_gain_track_gain, tags.replay_gain_track_gain);
+      assert_eq!(read.replay_gain_track_peak, tags.replay_gain_track_peak);
+      assert_eq!(read.replay_gain_album_gain, tags.replay_gain_album_gain);
+      assert_eq!(read.replay_gain_album_peak, tags.replay_gain_album_peak);
+      // the fields are not repeated as custom fields
+      assert!(read
+        .custom_fields
+        .unwrap_or_default()
+        .keys()
+        .all(|name| !name.to_uppercase().starts_with("REPLAYGAIN")));
+    }
+  }
+
+  #[tokio::test]
+  async fn test_bpm_key_and_mood() {
+    let tags = AudioTags {
+      bpm: Some(127.5),
+      initial_key: Some("Am".to_string()),
+      mood: Some("Calm".to_string()),
+      ..Default::default()
+    };
+    let audio_data = std::fs::read("music/silence.mp3").expect("Failed to read test file");
+    for (audio, bpm_key) in [
+      (audio_data, ItemKey::IntegerBpm),
+      (empty_flac(), ItemKey::Bpm),
+    ] {
+      let buffer = write_tags_to_buffer(audio, tags.clone()).await.unwrap();
+      let file = Probe::new(Cursor::new(buffer.clone()))
+        .guess_file_type()
+        .unwrap()
+        .read()
+        .unwrap();
+      let primary_tag = file.primary_tag().unwrap();
+      assert_eq!(primary_tag.get_string(&bpm_key), Some("127.5"));
+      assert_eq!(primary_tag.get_string(&ItemKey::InitialKey), Some("Am"));
+
+      let read = read_tags_from_buffer(buffer.clone()).await.unwrap();
+      assert_eq!(read.bpm, tags.bpm);
+      assert_eq!(read.initial_key, tags.initial_key);
+      assert_eq!(read.mood, tags.mood);
+
+      // a whole BPM reads back as written
+      let tags = AudioTags {
+        bpm: Some(128.0),
+        ..Default::default()
+      };
+      let buffer = write_tags_to_buffer(buffer, tags).await.unwrap();
+      let read = read_tags_from_buffer(buffer).await.unwrap();
+      assert_eq!(read.bpm, Some(128.0));
+      assert_eq!(read.mood, Some("Calm".to_string()));
+    }
+  }
+
+  #[tokio::test]
+  async fn test_rating_and_play_count() {
+    let tags = AudioTags {
+      artists: Some(vec!["Artist 1".to_string(), "Artist 2".to_string()]),
+      rating: Some(60),
+      play_count: Some(7),
+      ..Default::default()
+    };
+    let options = WriteTagsOptions {
+      artist_separator: ArtistSeparator::MultiValue,
+      popm_scale: PopmScale::MediaMonkey,
+      ..Default::default()
+    };
+    let audio_data = std::fs::read("music/silence.mp3").expect("Failed to read test file");
+    for audio in [audio_data, empty_flac()] {
+      let buffer = write_tags_to_buffer_with_options(audio, tags.clone(), options.clone())
+        .await
+        .unwrap();
+      let read_options = ReadTagsOptions {
+        artist_separator: ArtistSeparator::MultiValue,
+        ..Default::default()
+      };
+      let read = read_tags_from_buffer_with_options(buffer.clone(), read_options)
+        .await
+        .unwrap()
+        .tags;
+      assert_eq!(read.artists, tags.artists);
+      assert_eq!(read.rating, Some(60));
+      assert_eq!(read.play_count, Some(7));
+      // the rating comments are not repeated as custom fields
+      assert!(read
+        .custom_fields
+        .unwrap_or_default()
+        .keys()
+        .all(|name| !name.contains("RATING") && !name.contains("PLAYCOUNT")));
+
+      // the POPM frame and the comments survive other writes
+      let retitled = AudioTags {
+        title: Some("Title".to_string()),
+        ..Default::default()
+      };
+      let buffer = write_tags_to_buffer(buffer, retitled).await.unwrap();
+      let read = read_tags_from_buffer(buffer).await.unwrap();
+      assert_eq!(read.rating, Some(60));
+      assert_eq!(read.play_count, Some(7));
     }
   }
 
-  #[test]
-  fn test_from_picture_type_other_variant() {
-    use lofty::picture::PictureType;
+  #[tokio::test]
+  async fn test_comments_keep_language_and_description() {
+    let normalization = Comment {
+      language: Some("eng".to_string()),
+      description: Some("iTunNORM".to_string()),
+      text: " 0000044E 00000590".to_string(),
+    };
+    let tags = AudioTags {
+      comments: Some(vec![
+        Comment {
+          language: Some("deu".to_string()),
+          text: "Kommentar".to_string(),
+          ..Default::default()
+        },
+        normalization.clone(),
+      ]),
+      ..Default::default()
+    };
+    let audio_data = std::fs::read("music/silence.mp3").expect("Failed to read test file");
+    let buffer = write_tags_to_buffer(audio_data, tags.clone())
+      .await
+      .unwrap();
+    let read = read_tags_from_buffer(buffer.clone()).await.unwrap();
+    assert_eq!(read.comments, tags.comments);
+    assert_eq!(read.comment, Some("Kommentar".to_string()));
+
+    // the shortcut only replaces the comment players show
+    let tags = AudioTags {
+      comment: Some("Ripped from vinyl".to_string()),
+      ..Default::default()
+    };
+    let buffer = write_tags_to_buffer(buffer, tags).await.unwrap();
+    let read = read_tags_from_buffer(buffer).await.unwrap();
+    assert_eq!(read.comment, Some("Ripped from vinyl".to_string()));
+    assert_eq!(
+      read.comments,
+      Some(vec![
+        Comment {
+          text: "Ripped from vinyl".to_string(),
+          ..Default::default()
+        },
+        normalization,
+      ])
+    );
+  }
+
+  #[tokio::test]
+  async fn test_subtitles_and_sort_names() {
+    let tags = AudioTags {
+      subtitle: Some("Live".to_string()),
+      disc_subtitle: Some("Bonus Disc".to_string()),
+      title_sort: Some("Day in the Life, A".to_string()),
+      album_sort: Some("Sgt. Pepper".to_string()),
+      artist_sort: Some("Beatles, The".to_string()),
+      album_artist_sort: Some("Beatles, The".to_string()),
+      composer_sort: Some("Lennon, John".to_string()),
+      ..Default::default()
+    };
+    let audio_data = std::fs::read("music/silence.mp3").expect("Failed to read test file");
+    let buffer = write_tags_to_buffer(audio_data, tags.clone())
+      .await
+      .unwrap();
+    let read = read_tags_from_buffer(buffer).await.unwrap();
+    assert_eq!(read.subtitle, tags.subtitle);
+    assert_eq!(read.disc_subtitle, tags.disc_subtitle);
+    assert_eq!(read.title_sort, tags.title_sort);
+    assert_eq!(read.album_sort, tags.album_sort);
+    assert_eq!(read.artist_sort, tags.artist_sort);
+    assert_eq!(read.album_artist_sort, tags.album_artist_sort);
+    assert_eq!(read.composer_sort, tags.composer_sort);
+
+    let buffer = write_tags_to_buffer(empty_flac(), tags.clone())
+      .await
+      .unwrap();
+    let read = read_tags_from_buffer(buffer).await.unwrap();
+    assert_eq!(read.artist_sort, tags.artist_sort);
+    assert_eq!(read.subtitle, tags.subtitle);
+  }
 
-    // Test that any unknown PictureType variant maps to Other
-    // We'll use a pattern match to ensure we catch any new variants
-    let all_picture_types = vec![
-      PictureType::Icon,
-      PictureType::OtherIcon,
-      PictureType::CoverFront,
-      PictureType::CoverBack,
-      PictureType::Leaflet,
-      PictureType::Media,
-      PictureType::LeadArtist,
-      PictureType::Artist,
-      PictureType::Conductor,
-      PictureType::Band,
-      PictureType::Composer,
-      PictureType::Lyricist,
-      PictureType::RecordingLocation,
-      PictureType::DuringRecording,
-      PictureType::DuringPerformance,
-      PictureType::ScreenCapture,
-      PictureType::BrightFish,
-      PictureType::Illustration,
-      PictureType::BandLogo,
-      PictureType::PublisherLogo,
-    ];
+  #[tokio::test]
+  async fn test_encoder_fields() {
+    let tags = AudioTags {
+      encoder_software: Some("LAME 3.100".to_string()),
+      encoder_settings: Some("-V 2".to_string()),
+      ..Default::default()
+    };
+    let buffer = write_tags_to_buffer(empty_flac(), tags.clone())
+      .await
+      .unwrap();
+    let read = read_tags_from_buffer(buffer).await.unwrap();
+    assert_eq!(read.encoder_software, tags.encoder_software);
+    assert_eq!(read.encoder_settings, tags.encoder_settings);
+    assert_eq!(read.custom_fields, None);
+
+    // TSSE holds the software, the settings don't overwrite it
+    let audio_data = std::fs::read("music/silence.mp3").expect("Failed to read test file");
+    let buffer = write_tags_to_buffer(audio_data, tags.clone())
+      .await
+      .unwrap();
+    let read = read_tags_from_buffer(buffer).await.unwrap();
+    assert_eq!(read.encoder_software, tags.encoder_software);
+    assert_eq!(read.encoder_settings, None);
 
-    // Verify that all known variants are handled (not Other)
-    for picture_type in all_picture_types {
-      let result = AudioImageType::from_picture_type(&picture_type);
-      assert_ne!(
-        result,
-        AudioImageType::Other,
-        "PictureType::{:?} should not map to Other",
-        picture_type
-      );
-    }
+    let buffer = write_tags_to_buffer(crate::test_support::sample_m4a(), tags.clone())
+      .await
+      .unwrap();
+    let read = read_tags_from_buffer(buffer).await.unwrap();
+    assert_eq!(read.encoder_software, tags.encoder_software);
   }
 
-  #[test]
-  fn test_to_picture_type_all_variants() {
-    use lofty::picture::PictureType;
-
-    // Test all AudioImageType variants that have direct mappings
-    let test_cases = vec![
-      (AudioImageType::Icon, PictureType::Icon),
-      (AudioImageType::OtherIcon, PictureType::OtherIcon),
-      (AudioImageType::CoverFront, PictureType::CoverFront),
-      (AudioImageType::CoverBack, PictureType::CoverBack),
-      (AudioImageType::Leaflet, PictureType::Leaflet),
-      (AudioImageType::Media, PictureType::Media),
-      (AudioImageType::LeadArtist, PictureType::LeadArtist),
-      (AudioImageType::Artist, PictureType::Artist),
-      (AudioImageType::Conductor, PictureType::Conductor),
-      (AudioImageType::Band, PictureType::Band),
-      (AudioImageType::Composer, PictureType::Composer),
-      (AudioImageType::Lyricist, PictureType::Lyricist),
-      (
-        AudioImageType::RecordingLocation,
-        PictureType::RecordingLocation,
-      ),
-      (
-        AudioImageType::DuringRecording,
-        PictureType::DuringRecording,
-      ),
+  #[tokio::test]
+  async fn test_compilation_and_classical_fields() {
+    let tags = AudioTags {
+      compilation: Some(true),
+      grouping: Some("Summer".to_string()),
+      work: Some("Symphony No. 5".to_string()),
+      movement: Some("Allegro con brio".to_string()),
+      movement_number: Some(1),
+      ..Default::default()
+    };
+    let audio_data = std::fs::read("music/silence.mp3").expect("Failed to read test file");
+    // ID3v2 follows Apple, with the work in TIT1
+    for (audio, grouping_key, work_key) in [
       (
-        AudioImageType::DuringPerformance,
-        PictureType::DuringPerformance,
+        audio_data,
+        ItemKey::AppleId3v2ContentGroup,
+        ItemKey::ContentGroup,
       ),
-      (AudioImageType::ScreenCapture, PictureType::ScreenCapture),
-      (AudioImageType::BrightFish, PictureType::BrightFish),
-      (AudioImageType::Illustration, PictureType::Illustration),
-      (AudioImageType::BandLogo, PictureType::BandLogo),
-      (AudioImageType::PublisherLogo, PictureType::PublisherLogo),
-      (AudioImageType::Other, PictureType::Other),
-    ];
+      (empty_flac(), ItemKey::ContentGroup, ItemKey::Work),
+    ] {
+      let buffer = write_tags_to_buffer(audio, tags.clone()).await.unwrap();
+      let file = Probe::new(Cursor::new(buffer.clone()))
+        .guess_file_type()
+        .unwrap()
+        .read()
+        .unwrap();
+      let primary_tag = file.primary_tag().unwrap();
+      assert_eq!(primary_tag.get_string(&grouping_key), Some("Summer"));
+      assert_eq!(primary_tag.get_string(&work_key), Some("Symphony No. 5"));
+      assert_eq!(primary_tag.get_string(&ItemKey::FlagCompilation), Some("1"));
+
+      let read = read_tags_from_buffer(buffer.clone()).await.unwrap();
+      assert_eq!(read.compilation, Some(true));
+      assert_eq!(read.grouping, tags.grouping);
+      assert_eq!(read.work, tags.work);
+      assert_eq!(read.movement, tags.movement);
+      assert_eq!(read.movement_number, Some(1));
 
-    for (audio_image_type, expected_picture_type) in test_cases {
-      let result = audio_image_type.build_picture_type();
-      assert_eq!(
-        result, expected_picture_type,
-        "Failed to convert AudioImageType::{:?} to PictureType::{:?}",
-        audio_image_type, expected_picture_type
-      );
+      let tags = AudioTags {
+        compilation: Some(false),
+        ..Default::default()
+      };
+      let buffer = write_tags_to_buffer(buffer, tags).await.unwrap();
+      let read = read_tags_from_buffer(buffer).await.unwrap();
+      assert_eq!(read.compilation, Some(false));
     }
   }
 
-  #[test]
-  fn test_round_trip_conversion() {
-    use lofty::picture::PictureType;
+  // a FLAC stream without audio frames: the marker, a STREAMINFO block and
+  // some padding
+  fn empty_flac() -> Vec<u8> {
+    let mut flac = b"fLaC".to_vec();
+    // STREAMINFO, 34 bytes
+    flac.extend_from_slice(&[0x00, 0x00, 0x00, 0x22]);
+    // block sizes of 4096 samples, unknown frame sizes
+    flac.extend_from_slice(&[0x10, 0x00, 0x10, 0x00, 0, 0, 0, 0, 0, 0]);
+    // 44100 Hz, 2 channels, 16 bits, no samples
+    let format: u64 = (44100 << 44) | (1 << 41) | (15 << 36);
+    flac.extend_from_slice(&format.to_be_bytes());
+    flac.extend_from_slice(&[0; 16]);
+    // last metadata block, PADDING, 4 bytes
+    flac.extend_from_slice(&[0x81, 0x00, 0x00, 0x04, 0, 0, 0, 0]);
+    flac
+  }
 
-    // Test that converting from PictureType to AudioImageType and back preserves the value
-    let picture_types = vec![
-      PictureType::Icon,
-      PictureType::OtherIcon,
-      PictureType::CoverFront,
-      PictureType::CoverBack,
-      PictureType::Leaflet,
-      PictureType::Media,
-      PictureType::LeadArtist,
-      PictureType::Artist,
-      PictureType::Conductor,
-      PictureType::Band,
-      PictureType::Composer,
-      PictureType::Lyricist,
-      PictureType::RecordingLocation,
-      PictureType::DuringRecording,
-      PictureType::DuringPerformance,
-      PictureType::ScreenCapture,
-      PictureType::BrightFish,
-      PictureType::Illustration,
-      PictureType::BandLogo,
-      PictureType::PublisherLogo,
-    ];
+  #[tokio::test]
+  async fn test_copy_tags() {
+    use lofty::id3::v2::Frame;
+    use lofty::mpeg::MpegFile;
 
-    for original_picture_type in picture_types {
-      let audio_image_type = AudioImageType::from_picture_type(&original_picture_type);
-      let converted_back = audio_image_type.build_picture_type();
-      assert_eq!(
-        original_picture_type, converted_back,
-        "Round trip conversion failed for PictureType::{:?}",
-        original_picture_type
-      );
-    }
+    let audio_data = std::fs::read("music/silence.mp3").expect("Failed to read test file");
+    let tags = AudioTags {
+      title: Some("Title".to_string()),
+      artists: Some(vec!["Artist".to_string()]),
+      year: Some(1999),
+      image: Some(Image {
+        data: create_test_image_data(),
+        pic_type: AudioImageType::CoverFront,
+        ..Default::default()
+      }),
+      ..Default::default()
+    };
+    let source = write_tags_to_buffer(picard_tagged_mp3(), tags.clone())
+      .await
+      .unwrap();
+
+    // same format: frames lofty doesn't map survive the copy
+    let copied = copy_tags_to_buffer(source.clone(), audio_data, WriteTagsOptions::default())
+      .await
+      .unwrap();
+    let file = MpegFile::read_from(&mut Cursor::new(copied.clone()), ParseOptions::new()).unwrap();
+    assert!(file
+      .id3v2()
+      .unwrap()
+      .clone()
+      .into_iter()
+      .any(|frame| matches!(frame, Frame::Popularimeter(_))));
+    let copied = read_tags_from_buffer(copied).await.unwrap();
+    assert_eq!(copied, read_tags_from_buffer(source.clone()).await.unwrap());
+
+    // MP3 to FLAC: the tag is translated to Vorbis comments
+    let dir = tempfile::tempdir().unwrap();
+    let (source_path, dest_path) = (dir.path().join("song.mp3"), dir.path().join("song.flac"));
+    std::fs::write(&source_path, &source).unwrap();
+    std::fs::write(&dest_path, empty_flac()).unwrap();
+    let dest_path = dest_path.to_string_lossy().to_string();
+    copy_tags(
+      source_path.to_string_lossy().to_string(),
+      dest_path.clone(),
+      WriteTagsOptions::default(),
+    )
+    .await
+    .unwrap();
+    let entries = read_all_tags(dest_path.clone()).await.unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].tag_type, "VorbisComments");
+    let copied = read_tags(dest_path).await.unwrap();
+    assert_eq!(copied.title, tags.title);
+    assert_eq!(copied.artists, tags.artists);
+    assert_eq!(copied.year, tags.year);
+    assert_eq!(copied.image.unwrap().data, tags.image.unwrap().data);
+    assert_eq!(
+      copied.custom_fields.unwrap().get("SCRIPT"),
+      Some(&vec!["Latn".to_string()])
+    );
   }
 
-  #[test]
-  fn test_round_trip_conversion_audio_image_type() {
-    // Test that converting from AudioImageType to PictureType and back preserves the value
-    let audio_image_types = vec![
-      AudioImageType::Icon,
-      AudioImageType::OtherIcon,
-      AudioImageType::CoverFront,
-      AudioImageType::CoverBack,
-      AudioImageType::Leaflet,
-      AudioImageType::Media,
-      AudioImageType::LeadArtist,
-      AudioImageType::Artist,
-      AudioImageType::Conductor,
-      AudioImageType::Band,
-      AudioImageType::Composer,
-      AudioImageType::Lyricist,
-      AudioImageType::RecordingLocation,
-      AudioImageType::DuringRecording,
-      AudioImageType::DuringPerformance,
-      AudioImageType::ScreenCapture,
-      AudioImageType::BrightFish,
-      AudioImageType::Illustration,
-      AudioImageType::BandLogo,
-      AudioImageType::PublisherLogo,
-      AudioImageType::Other,
-    ];
+  #[tokio::test]
+  async fn test_write_and_read_wav_info_and_bwf() {
+    use crate::test_support::sample_wav;
 
-    for original_audio_image_type in audio_image_types {
-      let picture_type = original_audio_image_type.build_picture_type();
-      let converted_back = AudioImageType::from_picture_type(&picture_type);
+    let tags = AudioTags {
+      title: Some("Take 2".to_string()),
+      artists: Some(vec!["Recordist".to_string()]),
+      comment: Some("Wind noise".to_string()),
+      ..Default::default()
+    };
+    let options = WriteTagsOptions {
+      tag_type: Some(TagType::RiffInfo),
+      ..Default::default()
+    };
+    let buffer = write_tags_to_buffer_with_options(sample_wav(), tags, options)
+      .await
+      .unwrap();
+    // without an ID3v2 tag the INFO chunk is read
+    let read = read_tags_from_buffer(buffer.clone()).await.unwrap();
+    assert_eq!(read.title, Some("Take 2".to_string()));
+    assert_eq!(read.artists, Some(vec!["Recordist".to_string()]));
+    assert_eq!(read.comment, Some("Wind noise".to_string()));
+    assert_eq!(read.bwf, None);
+
+    let bwf = Bwf {
+      description: Some("Scene 4".to_string()),
+      originator: Some("Recorder".to_string()),
+      time_reference: Some(172_800_000),
+      ..Default::default()
+    };
+    let tags = AudioTags {
+      title: Some("Take 3".to_string()),
+      bwf: Some(bwf.clone()),
+      ..Default::default()
+    };
+    let buffer = write_tags_to_buffer(buffer, tags).await.unwrap();
+    let entries = read_all_tags_from_buffer(buffer.clone()).await.unwrap();
+    let titles: Vec<(&str, Option<&str>)> = entries
+      .iter()
+      .map(|entry| (entry.tag_type.as_str(), entry.tags.title.as_deref()))
+      .collect();
+    assert_eq!(
+      titles,
+      vec![("RiffInfo", Some("Take 3")), ("Id3v2", Some("Take 3"))]
+    );
+    let read = read_tags_from_buffer(buffer).await.unwrap();
+    assert_eq!(read.bwf.unwrap().description, bwf.description);
+  }
+
+  #[tokio::test]
+  async fn test_write_and_read_dsd_tags() {
+    use crate::test_support::{sample_dff, sample_dsf, write_fixture};
+
+    let dir = tempfile::tempdir().unwrap();
+    for (name, data) in [("a.dsf", sample_dsf()), ("a.dff", sample_dff())] {
+      let file_path = write_fixture(dir.path(), name, &data);
       assert_eq!(
-        original_audio_image_type, converted_back,
-        "Round trip conversion failed for AudioImageType::{:?}",
-        original_audio_image_type
+        read_tags(file_path.clone()).await.unwrap(),
+        AudioTags::default()
       );
-    }
-  }
 
-  #[test]
-  fn test_audio_image_type_enum_completeness() {
-    // Test that we have covered all AudioImageType variants in our tests
-    let all_audio_image_types = vec![
-      AudioImageType::Icon,
-      AudioImageType::OtherIcon,
-      AudioImageType::CoverFront,
-      AudioImageType::CoverBack,
-      AudioImageType::Leaflet,
-      AudioImageType::Media,
-      AudioImageType::LeadArtist,
-      AudioImageType::Artist,
-      AudioImageType::Conductor,
-      AudioImageType::Band,
-      AudioImageType::Composer,
-      AudioImageType::Lyricist,
-      AudioImageType::RecordingLocation,
-      AudioImageType::DuringRecording,
-      AudioImageType::DuringPerformance,
-      AudioImageType::ScreenCapture,
-      AudioImageType::BrightFish,
-      AudioImageType::Illustration,
-      AudioImageType::BandLogo,
-      AudioImageType::PublisherLogo,
-      AudioImageType::Other,
-    ];
+      let tags = AudioTags {
+        title: Some("Kind of Blue".to_string()),
+        artists: Some(vec!["Miles Davis".to_string()]),
+        ..Default::default()
+      };
+      write_tags(file_path.clone(), tags.clone()).await.unwrap();
+      write_lyrics(file_path.clone(), "So what".to_string())
+        .await
+        .unwrap();
+      let read = read_tags(file_path.clone()).await.unwrap();
+      assert_eq!(read.title, tags.title);
+      assert_eq!(read.artists, tags.artists);
+      assert_eq!(
+        read_lyrics(file_path.clone()).await.unwrap().as_deref(),
+        Some("So what")
+      );
+      // the audio is left as it was, the tag follows it
+      let written = std::fs::read(&file_path).unwrap();
+      assert_eq!(written[28..data.len()], data[28..]);
 
-    // This test ensures we have exactly 21 variants (as expected from the integration test)
-    assert_eq!(
-      all_audio_image_types.len(),
-      21,
-      "Expected 21 AudioImageType variants, found {}",
-      all_audio_image_types.len()
-    );
+      let options = WriteTagsOptions {
+        tag_type: Some(TagType::Ape),
+        ..Default::default()
+      };
+      assert!(write_tags_with_options(file_path.clone(), tags, options)
+        .await
+        .is_err());
+      let error = read_audio_properties(file_path.clone()).await.unwrap_err();
+      assert!(
+        error.starts_with(&format!("{}: Unsupported format", file_path)),
+        "{}",
+        error
+      );
+    }
   }
 
   #[tokio::test]
-  async fn test_clear_tags_file_not_found() {
-    // Try to clear tags from a non-existent file
-    let result = clear_tags("non_existent_file.mp3".to_string()).await;
-
-    // Verify error
-    assert!(result.is_err(), "Should fail for non-existent file");
-    assert!(
-      result.unwrap_err().contains("Failed to open file"),
-      "Should indicate file open error"
+  async fn test_write_and_read_id3v1_alongside_id3v2() {
+    let audio_data = std::fs::read("music/silence.mp3").expect("Failed to read test file");
+    let tags = AudioTags {
+      title: Some("A title well over thirty characters long".to_string()),
+      artists: Some(vec!["Artist".to_string()]),
+      genre: Some("Hip-Hop".to_string()),
+      track: Some(Position {
+        no: Some(3),
+        of: Some(12),
+      }),
+      ..Default::default()
+    };
+    let options = WriteTagsOptions {
+      id3v1: true,
+      remove_others: true,
+      ..Default::default()
+    };
+    let buffer = write_tags_to_buffer_with_options(audio_data, tags.clone(), options)
+      .await
+      .unwrap();
+    let entries = read_all_tags_from_buffer(buffer.clone()).await.unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].tags.title, tags.title);
+    let id3v1 = &entries[1].tags;
+    assert_eq!(entries[1].tag_type, "Id3v1");
+    assert_eq!(
+      id3v1.title,
+      Some("A title well over thirty chara".to_string())
     );
+    assert_eq!(id3v1.genre, Some("Hip-Hop".to_string()));
+    assert_eq!(id3v1.track.as_ref().and_then(|track| track.no), Some(3));
+
+    // without the ID3v2 tag, the ID3v1 one is only read on request
+    let legacy = strip_tag_types_to_buffer(buffer, vec![TagType::Id3v2])
+      .await
+      .unwrap();
+    let tags = read_tags_from_buffer(legacy.clone()).await.unwrap();
+    assert_eq!(tags.title, None);
+    let options = ReadTagsOptions {
+      id3v1_fallback: true,
+      ..Default::default()
+    };
+    let outcome = read_tags_from_buffer_with_options(legacy, options)
+      .await
+      .unwrap();
+    assert_eq!(outcome.tags.artists, Some(vec!["Artist".to_string()]));
   }
 
   #[tokio::test]
-  async fn test_clear_tags_no_write_permission() {
-    use std::fs::{self, File};
-    use std::io::Write;
-    use std::os::unix::fs::PermissionsExt;
-    use tempfile::tempdir;
-
-    // Create a temporary directory
-    let temp_dir = tempdir().expect("Failed to create temp dir");
-    let file_path = temp_dir.path().join("readonly.mp3");
-
-    // Create a minimal MP3 file
-    let data = vec![
-      // ID3v2 header
-      0x49, 0x44, 0x33, // "ID3"
-      0x03, 0x00, // version 2.3.0
-      0x00, // flags
-      0x00, 0x00, 0x00, 0x10, // size
-      // Some padding
-      0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // MP3 frame header
-      0xFF, 0xFB, 0x90, 0x44, 0x00, 0x00, 0x00, 0x00,
-    ];
+  async fn test_write_and_strip_tag_types() {
+    let audio_data = std::fs::read("music/silence.mp3").expect("Failed to read test file");
+    let tagged = |title: &str| AudioTags {
+      title: Some(title.to_string()),
+      ..Default::default()
+    };
+    let into = |tag_type: TagType| WriteTagsOptions {
+      tag_type: Some(tag_type),
+      ..Default::default()
+    };
+    let buffer = write_tags_to_buffer(audio_data, tagged("ID3v2 Title"))
+      .await
+      .unwrap();
+    let buffer = write_tags_to_buffer_with_options(buffer, tagged("APE Title"), into(TagType::Ape))
+      .await
+      .unwrap();
+    let buffer =
+      write_tags_to_buffer_with_options(buffer, tagged("ID3v1 Title"), into(TagType::Id3v1))
+        .await
+        .unwrap();
+    let tag_types = |entries: Vec<TagEntry>| -> Vec<(String, Option<String>)> {
+      entries
+        .into_iter()
+        .map(|entry| (entry.tag_type, entry.tags.title))
+        .collect()
+    };
+    let entries = read_all_tags_from_buffer(buffer.clone()).await.unwrap();
+    assert_eq!(
+      tag_types(entries),
+      vec![
+        ("Id3v2".to_string(), Some("ID3v2 Title".to_string())),
+        ("Id3v1".to_string(), Some("ID3v1 Title".to_string())),
+        ("Ape".to_string(), Some("APE Title".to_string())),
+      ]
+    );
+    // the primary tag is still the ID3v2 one
+    let tags = read_tags_from_buffer(buffer.clone()).await.unwrap();
+    assert_eq!(tags.title, Some("ID3v2 Title".to_string()));
 
-    // Write the file
-    File::create(&file_path)
-      .expect("Failed to create file")
-      .write_all(&data)
-      .expect("Failed to write data");
+    let options = ClearTagsOptions {
+      tag_type: Some(TagType::Ape),
+      ..Default::default()
+    };
+    let cleared = clear_tags_to_buffer_with_options(buffer.clone(), options)
+      .await
+      .unwrap();
+    let entries = read_all_tags_from_buffer(cleared).await.unwrap();
+    assert_eq!(tag_types(entries)[0].1, Some("ID3v2 Title".to_string()));
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("song.mp3");
+    std::fs::write(&path, &buffer).unwrap();
+    let path = path.to_string_lossy().to_string();
+    // MP3 files can't hold Vorbis comments, so there is nothing to strip
+    strip_tag_types(
+      path.clone(),
+      vec![TagType::Ape, TagType::Id3v1, TagType::VorbisComments],
+    )
+    .await
+    .unwrap();
+    let entries = read_all_tags(path).await.unwrap();
+    assert_eq!(
+      tag_types(entries),
+      vec![("Id3v2".to_string(), Some("ID3v2 Title".to_string()))]
+    );
 
-    // Make the file read-only
-    let mut perms = fs::metadata(&file_path)
-      .expect("Failed to get metadata")
-      .permissions();
-    perms.set_mode(0o444); // read-only for everyone
-    fs::set_permissions(&file_path, perms).expect("Failed to set permissions");
+    let result = write_tags_to_buffer_with_options(
+      buffer.clone(),
+      tagged("Title"),
+      into(TagType::VorbisComments),
+    )
+    .await;
+    assert_eq!(
+      result.unwrap_err(),
+      "Mpeg files don't support VorbisComments tags"
+    );
 
-    // Try to clear tags
-    let result = clear_tags(file_path.to_string_lossy().to_string()).await;
+    let path = dir.path().join("all.mp3");
+    std::fs::write(&path, &buffer).unwrap();
+    let path = path.to_string_lossy().to_string();
+    assert_eq!(
+      strip_all_tags(path.clone()).await.unwrap(),
+      vec![TagType::Ape, TagType::Id3v1, TagType::Id3v2]
+    );
+    assert_eq!(read_all_tags(path.clone()).await.unwrap(), Vec::new());
+    assert_eq!(strip_all_tags(path).await.unwrap(), Vec::new());
+  }
 
-    // Verify error
-    assert!(result.is_err(), "Should fail for read-only file");
-    assert!(
-      result.unwrap_err().contains("Failed to open file"),
-      "Should indicate file open error"
+  #[tokio::test]
+  async fn test_strip_all_tags_dsd() {
+    use crate::test_support::sample_dsf;
+    let tags = AudioTags {
+      title: Some("Title".to_string()),
+      ..Default::default()
+    };
+    let tagged = write_tags_to_buffer(sample_dsf(), tags).await.unwrap();
+    let (stripped, removed) = strip_all_tags_to_buffer(tagged).await.unwrap();
+    assert_eq!(stripped, sample_dsf());
+    assert_eq!(removed, vec![TagType::Id3v2]);
+    assert_eq!(
+      strip_all_tags_to_buffer(stripped).await.unwrap(),
+      (sample_dsf(), Vec::new())
     );
   }
 
   #[tokio::test]
-  async fn test_clear_tags_invalid_file() {
-    use std::fs::File;
-    use std::io::Write;
-    use tempfile::tempdir;
+  async fn test_write_tags_lofty_options() {
+    let audio_data = std::fs::read("music/silence.mp3").expect("Failed to read test file");
+    let tags = AudioTags {
+      title: Some("Title".to_string()),
+      year: Some(1999),
+      ..Default::default()
+    };
+    let write = |options: WriteTagsOptions| {
+      write_tags_to_buffer_with_options(audio_data.clone(), tags.clone(), options)
+    };
 
-    // Create a temporary directory
-    let temp_dir = tempdir().expect("Failed to create temp dir");
-    let file_path = temp_dir.path().join("invalid.mp3");
+    // the major version is the fourth byte of the header
+    let v4 = write(WriteTagsOptions::default()).await.unwrap();
+    assert_eq!(v4[3], 4);
+    let v3 = write(WriteTagsOptions {
+      id3v2_version: Id3v2Version::V3,
+      ..Default::default()
+    })
+    .await
+    .unwrap();
+    assert_eq!(v3[3], 3);
+    let read = read_tags_from_buffer(v3).await.unwrap();
+    assert_eq!(read.title, tags.title);
+    assert_eq!(read.year, tags.year);
+
+    let padded = |padding: u32| {
+      write(WriteTagsOptions {
+        padding: Some(padding),
+        ..Default::default()
+      })
+    };
+    let unpadded = padded(0).await.unwrap();
+    let padded = padded(4096).await.unwrap();
+    assert_eq!(
+      probe::id3v2_size(&padded).unwrap() - probe::id3v2_size(&unpadded).unwrap(),
+      4096
+    );
 
-    // Create an invalid file that's too short to be a valid MP3
-    let data = vec![0x00];
-    File::create(&file_path)
-      .expect("Failed to create file")
-      .write_all(&data)
-      .expect("Failed to write data");
+    let with_ape = write(WriteTagsOptions {
+      tag_type: Some(TagType::Ape),
+      ..Default::default()
+    })
+    .await
+    .unwrap();
+    let options = WriteTagsOptions {
+      remove_others: true,
+      ..Default::default()
+    };
+    let buffer = write_tags_to_buffer_with_options(with_ape, tags.clone(), options)
+      .await
+      .unwrap();
+    let tag_types: Vec<String> = read_all_tags_from_buffer(buffer)
+      .await
+      .unwrap()
+      .into_iter()
+      .map(|entry| entry.tag_type)
+      .collect();
+    assert_eq!(tag_types, vec!["Id3v2".to_string()]);
+  }
 
-    // Try to clear tags
-    let result = clear_tags(file_path.to_string_lossy().to_string()).await;
+  #[tokio::test]
+  async fn test_write_tags_id3_text_encoding() {
+    use crate::test_support::sample_mp3;
+    use lofty::id3::v2::{Frame, FrameId};
+    use lofty::TextEncoding;
+    use std::borrow::Cow;
+    let tags = AudioTags {
+      title: Some("Café".to_string()),
+      album: Some("東京".to_string()),
+      ..Default::default()
+    };
+    let encodings = |buffer: Vec<u8>| {
+      let id3v2 = lofty::mpeg::MpegFile::read_from(&mut Cursor::new(buffer), Default::default())
+        .unwrap()
+        .id3v2()
+        .cloned()
+        .unwrap();
+      ["TIT2", "TALB"].map(|id| match id3v2.get(&FrameId::Valid(Cow::Borrowed(id))) {
+        Some(Frame::Text(frame)) => frame.encoding,
+        frame => panic!("{}: {:?}", id, frame),
+      })
+    };
+    let write = |id3_text_encoding: Id3TextEncoding| {
+      write_tags_to_buffer_with_options(
+        sample_mp3(),
+        tags.clone(),
+        WriteTagsOptions {
+          id3_text_encoding,
+          ..Default::default()
+        },
+      )
+    };
 
-    // Verify error
-    assert!(result.is_err(), "Should fail for invalid file");
-    let error = result.unwrap_err();
-    assert!(
-      error.contains("Failed to read audio file"),
-      "Should indicate read error, got: {}",
-      error
+    let utf8 = write(Id3TextEncoding::Utf8).await.unwrap();
+    assert_eq!(encodings(utf8), [TextEncoding::UTF8; 2]);
+    let utf16 = write(Id3TextEncoding::Utf16).await.unwrap();
+    assert_eq!(encodings(utf16), [TextEncoding::UTF16; 2]);
+    // the album can't be written in Latin-1
+    let latin1 = write(Id3TextEncoding::Latin1).await.unwrap();
+    assert_eq!(
+      encodings(latin1.clone()),
+      [TextEncoding::Latin1, TextEncoding::UTF16]
     );
+    let read = read_tags_from_buffer(latin1).await.unwrap();
+    assert_eq!(read.title, tags.title);
+    assert_eq!(read.album, tags.album);
   }
 
   #[tokio::test]
-  async fn test_clear_tags_success() {
-    use std::fs::File;
-    use std::io::Write;
-    use tempfile::tempdir;
-
-    // Create a temporary directory
-    let temp_dir = tempdir().expect("Failed to create temp dir");
-    let file_path = temp_dir.path().join("test.mp3");
+  async fn test_write_tags_normalize_text() {
+    use crate::test_support::sample_mp3;
+    let tags = AudioTags {
+      title: Some(" Bjo\u{308}rk\u{200B} ".to_string()),
+      artists: Some(vec!["Sigur  Ro\u{301}s".to_string()]),
+      ..Default::default()
+    };
+    let options = WriteTagsOptions {
+      normalize_text: true,
+      ..Default::default()
+    };
+    let buffer = write_tags_to_buffer_with_options(sample_mp3(), tags.clone(), options)
+      .await
+      .unwrap();
+    let read = read_tags_from_buffer(buffer).await.unwrap();
+    assert_eq!(read.title.as_deref(), Some("Björk"));
+    assert_eq!(read.artists, Some(vec!["Sigur Rós".to_string()]));
 
-    // Create a minimal valid MP3 file
-    let audio_data = create_buffer_from_base64("SUQzBAAAAAAAIlRTU0UAAAAOAAADTGF2ZjYxLjcuMTAwAAAAAAAAAAAAAAD/+1AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAABJbmZvAAAADwAAACgAAEIeAAkJDw8WFhYcHCIiIikpLy8vNTU8PDxCQkhISE5OVVVVW1thYWFoaG5ubnR0e3t7gYGHh4eOjpSUlJqaoaGhp6etra20tLq6usDAx8fHzc3T09Pa2uDg4Obm7e3t8/P5+fn//wAAAABMYXZjNjEuMTkAAAAAAAAAAAAAAAAkBXwAAAAAAABCHsH9lAcAAAAAAAAAAAAAAAAAAAAA//uQZAAP8AAAaQAAAAgAAA0gAAABAAABpAAAACAAADSAAAAETEFNRTMuMTAwVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVTEFNRTMuMTAwVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVV").unwrap();
+    // opt-in
+    let buffer = write_tags_to_buffer(sample_mp3(), tags.clone())
+      .await
+      .unwrap();
+    let read = read_tags_from_buffer(buffer).await.unwrap();
+    assert_eq!(read.title, tags.title);
+  }
 
-    // Create test tags
-    let test_tags = AudioTags {
-      title: Some("Test Song".to_string()),
-      artists: Some(vec!["Test Artist".to_string()]),
-      album: Some("Test Album".to_string()),
-      year: Some(2024),
-      genre: Some("Test Genre".to_string()),
-      track: Some(Position {
-        no: Some(1),
-        of: Some(10),
-      }),
-      album_artists: Some(vec!["Test Album Artist".to_string()]),
-      comment: Some("Test comment".to_string()),
-      disc: Some(Position {
-        no: Some(1),
-        of: Some(2),
-      }),
-      image: Some(Image {
-        data: create_test_image_data(),
-        pic_type: AudioImageType::CoverFront,
-        mime_type: Some("image/jpeg".to_string()),
-        description: Some("Test cover".to_string()),
-      }),
-      all_images: None,
+  #[tokio::test]
+  async fn test_read_tags_from_buffer_tolerates_truncation() {
+    let audio_data = std::fs::read("music/sine-440-tone.mp3").expect("Failed to read test file");
+    let tags = AudioTags {
+      title: Some("Partial".to_string()),
+      ..Default::default()
+    };
+    let tagged = write_tags_to_buffer(audio_data, tags).await.unwrap();
+    let tag_size = probe::id3v2_size(&tagged).unwrap() as usize;
+    let options = ReadTagsOptions {
+      tolerate_truncation: true,
+      ..Default::default()
     };
 
-    // Write tags to buffer
-    let tagged_buffer = write_tags_to_buffer(audio_data, test_tags)
+    // intact files are not flagged
+    let outcome = read_tags_from_buffer_with_options(tagged.clone(), options.clone())
       .await
-      .expect("Failed to write tags");
+      .unwrap();
+    assert!(!outcome.truncated);
+
+    // cut inside the first audio frame and right after the tag
+    for cut in [tag_size + 4, tag_size + 1, tag_size] {
+      let partial = tagged[..cut].to_vec();
+      assert!(read_tags_from_buffer(partial.clone()).await.is_err());
+      let outcome = read_tags_from_buffer_with_options(partial, options.clone())
+        .await
+        .unwrap();
+      assert!(outcome.truncated);
+      assert_eq!(outcome.tags.title, Some("Partial".to_string()));
+    }
 
-    // Write the file
-    File::create(&file_path)
-      .expect("Failed to create file")
-      .write_all(&tagged_buffer)
-      .expect("Failed to write data");
+    // nothing to recover when the tag itself is cut off
+    let result = read_tags_from_buffer_with_options(tagged[..tag_size - 1].to_vec(), options).await;
+    assert!(result.is_err());
+  }
 
-    // Clear the tags
-    let result = clear_tags(file_path.to_string_lossy().to_string()).await;
+  #[tokio::test]
+  async fn test_read_tags_from_stream() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
 
-    // Verify success
-    assert!(result.is_ok(), "Should successfully clear tags");
+    // a file pulled in chunks of at most 1000 bytes, counting what was pulled
+    struct Download {
+      data: Vec<u8>,
+      pulled: AtomicUsize,
+    }
 
-    // Read back the tags
-    let read_result = read_tags(file_path.to_string_lossy().to_string()).await;
-    assert!(read_result.is_ok(), "Should successfully read cleared file");
+    impl ByteSource for Download {
+      async fn pull(&self, size: usize) -> Result<Option<Vec<u8>>, String> {
+        let start = self.pulled.load(Ordering::SeqCst);
+        let end = self.data.len().min(start + size.min(1000));
+        self.pulled.store(end, Ordering::SeqCst);
+        Ok(Some(self.data[start..end].to_vec()))
+      }
+    }
 
-    // Verify tags are cleared
-    let read_tags = read_result.unwrap();
-    assert!(read_tags.title.is_none(), "Title should be cleared");
-    assert!(read_tags.artists.is_none(), "Artists should be cleared");
-    assert!(read_tags.album.is_none(), "Album should be cleared");
-    assert!(read_tags.image.is_none(), "Image should be cleared");
+    let tags = AudioTags {
+      title: Some("Streamed".to_string()),
+      ..Default::default()
+    };
+    let mp3 = std::fs::read("music/sine-440-tone.mp3").expect("Failed to read test file");
+    let mut flac = write_tags_to_buffer(empty_flac(), tags.clone())
+      .await
+      .unwrap();
+    // stands in for the audio frames
+    flac.resize(flac.len() + 200_000, 0xAA);
+    let files = [
+      write_tags_to_buffer(mp3, tags.clone()).await.unwrap(),
+      flac,
+      write_tags_to_buffer(crate::test_support::sample_m4a(), tags)
+        .await
+        .unwrap(),
+    ];
+    for data in files {
+      let download = Download {
+        data,
+        pulled: AtomicUsize::new(0),
+      };
+      let read = read_tags_from_stream(&download, ReadTagsOptions::default())
+        .await
+        .unwrap();
+      assert_eq!(read.title, Some("Streamed".to_string()));
+      let pulled = download.pulled.load(Ordering::SeqCst);
+      assert!(
+        pulled < download.data.len(),
+        "{} of {}",
+        pulled,
+        download.data.len()
+      );
+    }
   }
 
   #[tokio::test]
@@ -4986,10 +10870,9 @@ mod tests {
 
     // Create our failing file
     let mut failing_file = FailingFile::new();
-    let mut out = FailingFile::new();
 
     // Try to clear tags
-    let result = generic_clear_tags(&mut failing_file, &mut out).await;
+    let result = generic_clear_tags(&mut failing_file, &ClearTagsOptions::default()).await;
 
     // Verify error
     assert!(result.is_err(), "Should fail when reading fails");
@@ -5023,6 +10906,7 @@ mod tests {
           pic_type: AudioImageType::Artist,
           mime_type: Some("image/jpeg".to_string()),
           description: Some("Artist photo".to_string()),
+          ..Default::default()
         },
         // Band logo
         Image {
@@ -5030,6 +10914,7 @@ mod tests {
           pic_type: AudioImageType::BandLogo,
           mime_type: Some("image/jpeg".to_string()),
           description: Some("Band logo".to_string()),
+          ..Default::default()
         },
         // Lead artist photo
         Image {
@@ -5037,8 +10922,10 @@ mod tests {
           pic_type: AudioImageType::LeadArtist,
           mime_type: Some("image/jpeg".to_string()),
           description: Some("Lead artist photo".to_string()),
+          ..Default::default()
         },
       ]),
+      ..Default::default()
     };
 
     // Write tags to buffer
@@ -5105,7 +10992,7 @@ mod tests {
     let mut failing_file = FailingFile;
 
     // Try to read tags from the failing file
-    let result = generic_read_tags(&mut failing_file).await;
+    let result = generic_read_tags(&mut failing_file, &ReadTagsOptions::default()).await;
 
     // Verify we get an error
     assert!(result.is_err(), "Should return error for invalid file");
@@ -5114,7 +11001,7 @@ mod tests {
     match result {
       Err(e) => {
         assert_eq!(
-          e, "Failed to guess file type",
+          e, "Failed to guess file type (Other): Simulated read error",
           "Error message should indicate failure to guess file type, got: {}",
           e
         );
@@ -5173,6 +11060,7 @@ mod tests {
         pic_type: *pic_type,
         mime_type: Some("image/jpeg".to_string()),
         description: Some(description.clone()),
+        ..Default::default()
       })
       .collect();
 
@@ -5189,6 +11077,7 @@ mod tests {
       disc: None,
       image: None, // No main image set
       all_images: Some(all_images),
+      ..Default::default()
     };
 
     // Convert AudioTags to the primary tag (this should replace all existing images)
@@ -5276,6 +11165,7 @@ mod tests {
         pic_type: *pic_type,
         mime_type: Some("image/jpeg".to_string()),
         description: Some(description.clone()),
+        ..Default::default()
       })
       .collect();
 
@@ -5292,6 +11182,7 @@ mod tests {
       disc: None,
       image: None, // No main image set
       all_images: Some(all_images),
+      ..Default::default()
     };
 
     // Create a new tag and convert AudioTags to it
@@ -5428,7 +11319,8 @@ mod tests {
       &mut tag,
       &cover_data,
       Some("Cover image".to_string()),
-      MimeType::Jpeg,
+      Some(MimeType::Jpeg),
+      MimeInference::Sniff,
     );
 
     // Verify the tag has both images
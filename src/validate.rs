@@ -0,0 +1,429 @@
+use crate::patch::TagField;
+use crate::read_warnings;
+use crate::util::{self, AudioTags, Position};
+use lofty::config::ParseOptions;
+use lofty::file::TaggedFileExt;
+use lofty::probe::Probe;
+use lofty::tag::{Accessor, Tag, TagType};
+use std::io::{Cursor, Read, Seek};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// the longest text an ID3v1 field holds
+const ID3V1_TEXT_LEN: usize = 30;
+
+// The kinds of problems validate_tags reports
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum FindingKind {
+  MissingField,
+  // a track or disc number above the total
+  NumberExceedsTotal,
+  YearOutOfRange,
+  OversizedImage,
+  // ID3v2 text frames in different encodings
+  MixedEncodings,
+  // an ID3v1 field holding another value than the ID3v2 tag
+  Id3v1Mismatch,
+}
+
+// A problem of the tags of a file
+#[derive(Debug, PartialEq, Clone)]
+pub struct ValidationFinding {
+  pub kind: FindingKind,
+  // the field at fault, None for findings about the whole tag
+  pub field: Option<TagField>,
+  pub message: String,
+}
+
+// What validate_tags checks; only the required fields are off by default
+#[derive(Debug, PartialEq, Clone)]
+pub struct ValidationRules {
+  // the fields every file must have
+  pub required_fields: Vec<TagField>,
+  pub min_year: u32,
+  // None for the next year
+  pub max_year: Option<u32>,
+  // the largest picture in bytes, None for no limit
+  pub max_image_bytes: Option<usize>,
+  pub check_encodings: bool,
+  // compare the ID3v1 tag of a file with its ID3v2 tag
+  pub check_id3v1: bool,
+}
+
+impl Default for ValidationRules {
+  fn default() -> Self {
+    ValidationRules {
+      required_fields: Vec::new(),
+      min_year: 1900,
+      max_year: None,
+      max_image_bytes: Some(1024 * 1024),
+      check_encodings: true,
+      check_id3v1: true,
+    }
+  }
+}
+
+fn finding(kind: FindingKind, field: Option<TagField>, message: String) -> ValidationFinding {
+  ValidationFinding {
+    kind,
+    field,
+    message,
+  }
+}
+
+// the current year, close enough around new year for a year check
+fn current_year() -> u32 {
+  let days = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map_or(0, |since| since.as_secs() / 86_400);
+  1970 + (days as f64 / 365.2425) as u32
+}
+
+fn position_finding(
+  field: TagField,
+  name: &str,
+  position: Option<&Position>,
+) -> Option<ValidationFinding> {
+  let (no, of) = position.and_then(|position| position.no.zip(position.of))?;
+  (no > of).then(|| {
+    finding(
+      FindingKind::NumberExceedsTotal,
+      Some(field),
+      format!("{} number {} exceeds the total of {}", name, no, of),
+    )
+  })
+}
+
+/**
+ * Check a set of tags against the rules that need no file: required fields,
+ * track and disc numbers, the year and the picture sizes
+ * @param tags - The tags
+ * @param rules - What to check
+ */
+pub fn validate_audio_tags(tags: &AudioTags, rules: &ValidationRules) -> Vec<ValidationFinding> {
+  let mut findings: Vec<ValidationFinding> = rules
+    .required_fields
+    .iter()
+    .filter(|field| !field.is_set(tags))
+    .map(|field| {
+      finding(
+        FindingKind::MissingField,
+        Some(*field),
+        format!("Missing {:?}", field),
+      )
+    })
+    .collect();
+  findings.extend(position_finding(
+    TagField::Track,
+    "Track",
+    tags.track.as_ref(),
+  ));
+  findings.extend(position_finding(TagField::Disc, "Disc", tags.disc.as_ref()));
+  let max_year = rules.max_year.unwrap_or_else(|| current_year() + 1);
+  if let Some(year) = tags
+    .year
+    .filter(|year| !(rules.min_year..=max_year).contains(year))
+  {
+    findings.push(finding(
+      FindingKind::YearOutOfRange,
+      Some(TagField::Year),
+      format!(
+        "Year {} is outside {} to {}",
+        year, rules.min_year, max_year
+      ),
+    ));
+  }
+  if let Some(max_bytes) = rules.max_image_bytes {
+    let images = tags
+      .all_images
+      .iter()
+      .flatten()
+      .chain(tags.image.iter().filter(|_| tags.all_images.is_none()));
+    for image in images.filter(|image| image.data.len() > max_bytes) {
+      findings.push(finding(
+        FindingKind::OversizedImage,
+        Some(TagField::AllImages),
+        format!(
+          "{:?} picture of {} bytes exceeds {} bytes",
+          image.pic_type,
+          image.data.len(),
+          max_bytes
+        ),
+      ));
+    }
+  }
+  findings
+}
+
+// the text encodings of the ID3v2 text, comment and lyrics frames, in the
+// order they first appear
+fn encodings_finding(tag: &[u8]) -> Option<ValidationFinding> {
+  let raw = read_warnings::raw_frames(tag)?;
+  let mut encodings: Vec<&str> = Vec::new();
+  let text_frames = raw.frames.iter().filter(|frame| {
+    !frame.transformed && (frame.id.starts_with('T') || frame.id == "COMM" || frame.id == "USLT")
+  });
+  for frame in text_frames {
+    let encoding = match frame.body.first() {
+      Some(0) => "Latin-1",
+      Some(1) => "UTF-16",
+      Some(2) => "UTF-16BE",
+      Some(3) => "UTF-8",
+      _ => continue,
+    };
+    if !encodings.contains(&encoding) {
+      encodings.push(encoding);
+    }
+  }
+  (encodings.len() > 1).then(|| {
+    finding(
+      FindingKind::MixedEncodings,
+      None,
+      format!(
+        "The ID3v2 text frames mix encodings: {}",
+        encodings.join(", ")
+      ),
+    )
+  })
+}
+
+// whether an ID3v1 text holds what fits of the ID3v2 text; texts ID3v1 can't
+// hold, outside Latin-1, are not compared
+fn id3v1_text_agrees(id3v1: &str, id3v2: &str) -> bool {
+  let fitting: String = id3v2.chars().take(ID3V1_TEXT_LEN).collect();
+  id3v2.chars().any(|c| u32::from(c) > 0xFF) || fitting.trim() == id3v1.trim()
+}
+
+fn id3v1_findings(id3v1: &Tag, id3v2: &Tag) -> Vec<ValidationFinding> {
+  let texts = [
+    (TagField::Title, "title", id3v1.title(), id3v2.title()),
+    (TagField::Artists, "artist", id3v1.artist(), id3v2.artist()),
+    (TagField::Album, "album", id3v1.album(), id3v2.album()),
+  ];
+  let numbers = [
+    (TagField::Track, "track", id3v1.track(), id3v2.track()),
+    (TagField::Year, "year", id3v1.year(), id3v2.year()),
+  ];
+  let mismatch = |field, name: &str, id3v1: String, id3v2: Option<String>| {
+    let message = match id3v2 {
+      Some(id3v2) => format!(
+        "ID3v1 {} \"{}\" differs from ID3v2 \"{}\"",
+        name, id3v1, id3v2
+      ),
+      None => format!("ID3v1 {} \"{}\" is missing from the ID3v2 tag", name, id3v1),
+    };
+    finding(FindingKind::Id3v1Mismatch, Some(field), message)
+  };
+  let mut findings = Vec::new();
+  for (field, name, id3v1, id3v2) in texts {
+    let Some(id3v1) = id3v1.filter(|text| !text.trim().is_empty()) else {
+      continue;
+    };
+    if !id3v2
+      .as_deref()
+      .is_some_and(|id3v2| id3v1_text_agrees(&id3v1, id3v2))
+    {
+      findings.push(mismatch(
+        field,
+        name,
+        id3v1.to_string(),
+        id3v2.map(|text| text.to_string()),
+      ));
+    }
+  }
+  for (field, name, id3v1, id3v2) in numbers {
+    let Some(id3v1) = id3v1 else {
+      continue;
+    };
+    if id3v2 != Some(id3v1) {
+      findings.push(mismatch(
+        field,
+        name,
+        id3v1.to_string(),
+        id3v2.map(|number| number.to_string()),
+      ));
+    }
+  }
+  findings
+}
+
+// the checks that need the tags as stored
+fn stored_tag_findings<R: Read + Seek>(
+  reader: &mut R,
+  rules: &ValidationRules,
+) -> Vec<ValidationFinding> {
+  let mut findings = Vec::new();
+  if rules.check_encodings {
+    let id3v2 = read_warnings::file_id3v2(reader);
+    findings.extend(id3v2.and_then(|tag| encodings_finding(&tag)));
+  }
+  if rules.check_id3v1 && reader.rewind().is_ok() {
+    let parse_options = ParseOptions::new()
+      .read_properties(false)
+      .read_cover_art(false);
+    let tagged_file = Probe::new(reader)
+      .options(parse_options)
+      .guess_file_type()
+      .ok()
+      .and_then(|probe| probe.read().ok());
+    if let Some(tagged_file) = tagged_file {
+      if let (Some(id3v1), Some(id3v2)) = (
+        tagged_file.tag(TagType::Id3v1),
+        tagged_file.tag(TagType::Id3v2),
+      ) {
+        findings.extend(id3v1_findings(id3v1, id3v2));
+      }
+    }
+  }
+  findings
+}
+
+/**
+ * Check the tags of a file for problems a library cleanup would fix: missing
+ * fields, track and disc numbers above their totals, implausible years,
+ * oversized pictures, ID3v2 tags mixing text encodings and ID3v1 tags that
+ * disagree with the ID3v2 tag
+ * @param file_path - The path to the audio file
+ * @param rules - What to check
+ * @returns The problems found, none for tags that pass
+ */
+pub async fn validate_tags(
+  file_path: impl AsRef<Path>,
+  rules: ValidationRules,
+) -> Result<Vec<ValidationFinding>, String> {
+  let path = file_path.as_ref();
+  let tags = util::read_tags(path).await?;
+  let mut file = util::open_file(path)?;
+  let mut findings = validate_audio_tags(&tags, &rules);
+  findings.extend(stored_tag_findings(&mut file, &rules));
+  Ok(findings)
+}
+
+pub async fn validate_tags_from_buffer(
+  buffer: Vec<u8>,
+  rules: ValidationRules,
+) -> Result<Vec<ValidationFinding>, String> {
+  let tags = util::read_tags_from_buffer(buffer.clone()).await?;
+  let mut findings = validate_audio_tags(&tags, &rules);
+  findings.extend(stored_tag_findings(&mut Cursor::new(buffer), &rules));
+  Ok(findings)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_support::{sample_mp3, write_fixture};
+  use crate::util::{AudioImageType, Image, WriteTagsOptions};
+
+  #[test]
+  fn test_validate_audio_tags() {
+    let tags = AudioTags {
+      title: Some("Title".to_string()),
+      year: Some(1850),
+      track: Some(Position {
+        no: Some(12),
+        of: Some(10),
+      }),
+      disc: Some(Position {
+        no: Some(1),
+        of: Some(2),
+      }),
+      image: Some(Image {
+        data: vec![0; 16],
+        pic_type: AudioImageType::CoverFront,
+        ..Default::default()
+      }),
+      ..Default::default()
+    };
+    let rules = ValidationRules {
+      required_fields: vec![TagField::Title, TagField::Album],
+      max_image_bytes: Some(8),
+      ..Default::default()
+    };
+    let kinds: Vec<(FindingKind, Option<TagField>)> = validate_audio_tags(&tags, &rules)
+      .into_iter()
+      .map(|finding| (finding.kind, finding.field))
+      .collect();
+    assert_eq!(
+      kinds,
+      vec![
+        (FindingKind::MissingField, Some(TagField::Album)),
+        (FindingKind::NumberExceedsTotal, Some(TagField::Track)),
+        (FindingKind::YearOutOfRange, Some(TagField::Year)),
+        (FindingKind::OversizedImage, Some(TagField::AllImages)),
+      ]
+    );
+    let lenient = ValidationRules {
+      min_year: 1800,
+      max_image_bytes: None,
+      ..Default::default()
+    };
+    assert_eq!(
+      validate_audio_tags(&tags, &lenient)[0].message,
+      "Track number 12 exceeds the total of 10"
+    );
+    assert_eq!(validate_audio_tags(&tags, &lenient).len(), 1);
+  }
+
+  #[test]
+  fn test_mixed_encodings() {
+    let frame = |id: &[u8; 4], body: &[u8]| {
+      let mut frame = id.to_vec();
+      frame.extend_from_slice(&(body.len() as u32).to_be_bytes());
+      frame.extend_from_slice(&[0, 0]);
+      frame.extend_from_slice(body);
+      frame
+    };
+    let mut tag = b"ID3\x03\0\0\0\0\0\x1F".to_vec();
+    tag.extend(frame(b"TIT2", b"\x00Title"));
+    tag.extend(frame(b"TALB", b"\x01\xFF\xFEA\0"));
+    assert_eq!(
+      encodings_finding(&tag).unwrap().message,
+      "The ID3v2 text frames mix encodings: Latin-1, UTF-16"
+    );
+    assert_eq!(encodings_finding(&tag[..26]), None);
+  }
+
+  #[tokio::test]
+  async fn test_validate_tags() {
+    let dir = tempfile::tempdir().unwrap();
+    let tags = AudioTags {
+      title: Some("Title".to_string()),
+      album: Some("Album".to_string()),
+      year: Some(2001),
+      ..Default::default()
+    };
+    let with_id3v1 = WriteTagsOptions {
+      id3v1: true,
+      ..Default::default()
+    };
+    let buffer = util::write_tags_to_buffer_with_options(sample_mp3(), tags, with_id3v1)
+      .await
+      .unwrap();
+    let file_path = write_fixture(dir.path(), "a.mp3", &buffer);
+    let rules = ValidationRules {
+      required_fields: vec![TagField::Title],
+      ..Default::default()
+    };
+    assert_eq!(
+      validate_tags(file_path, rules.clone()).await.unwrap(),
+      Vec::new()
+    );
+
+    // the ID3v2 tag changes, the ID3v1 tag is left as it was
+    let retitled = AudioTags {
+      title: Some("New Title".to_string()),
+      ..Default::default()
+    };
+    let buffer = util::write_tags_to_buffer(buffer, retitled).await.unwrap();
+    let findings = validate_tags_from_buffer(buffer, rules).await.unwrap();
+    assert_eq!(
+      findings,
+      vec![finding(
+        FindingKind::Id3v1Mismatch,
+        Some(TagField::Title),
+        "ID3v1 title \"Title\" differs from ID3v2 \"New Title\"".to_string()
+      )]
+    );
+  }
+}
@@ -0,0 +1,255 @@
+use crate::scan;
+use crate::util::{self, AudioTags};
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// how long a file has to stay untouched before it is read, by default
+const DEFAULT_DEBOUNCE_MS: u64 = 500;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum WatchEventKind {
+  Added,
+  Changed,
+  Removed,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct WatchEvent {
+  pub file_path: String,
+  pub kind: WatchEventKind,
+  // the fresh tags, None for removed files and files that could not be read
+  pub tags: Option<AudioTags>,
+  pub error: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct WatchOptions {
+  // watch subdirectories too
+  pub recursive: bool,
+  // without the leading dot, matched case-insensitively; None for the audio
+  // extensions scanned by default
+  pub extensions: Option<Vec<String>>,
+  // a file is read once it has been left untouched this long, so a copy in
+  // progress is read once, when it is complete
+  pub debounce_ms: u64,
+}
+
+impl Default for WatchOptions {
+  fn default() -> Self {
+    Self {
+      recursive: false,
+      extensions: None,
+      debounce_ms: DEFAULT_DEBOUNCE_MS,
+    }
+  }
+}
+
+// Watches a directory until dropped
+pub struct DirectoryWatch {
+  // the worker thread ends once the watcher, and with it the channel, is gone
+  _watcher: RecommendedWatcher,
+}
+
+// the kinds of the paths of an event, in the order of the paths
+fn event_kinds(event: &Event) -> Vec<(PathBuf, WatchEventKind)> {
+  let kind = match event.kind {
+    EventKind::Create(_) => WatchEventKind::Added,
+    EventKind::Remove(_) => WatchEventKind::Removed,
+    EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => {
+      let mut paths = event.paths.iter();
+      return paths
+        .next()
+        .map(|from| (from.clone(), WatchEventKind::Removed))
+        .into_iter()
+        .chain(paths.map(|to| (to.clone(), WatchEventKind::Added)))
+        .collect();
+    }
+    EventKind::Modify(ModifyKind::Name(RenameMode::From)) => WatchEventKind::Removed,
+    EventKind::Modify(ModifyKind::Name(RenameMode::To)) => WatchEventKind::Added,
+    EventKind::Modify(ModifyKind::Metadata(_)) => return Vec::new(),
+    EventKind::Modify(_) => WatchEventKind::Changed,
+    _ => return Vec::new(),
+  };
+  event
+    .paths
+    .iter()
+    .map(|path| (path.clone(), kind))
+    .collect()
+}
+
+// what a later event makes of the one still pending for a path
+fn merge_kinds(pending: WatchEventKind, next: WatchEventKind) -> WatchEventKind {
+  match (pending, next) {
+    // still new to the caller however often it is written
+    (WatchEventKind::Added, WatchEventKind::Changed) => WatchEventKind::Added,
+    // replaced, e.g. saved through a temporary file
+    (WatchEventKind::Removed, WatchEventKind::Added) => WatchEventKind::Changed,
+    (_, next) => next,
+  }
+}
+
+/**
+ * The event for a path once it has settled, reading its tags when it exists
+ * @param path - The path
+ * @param kind - The kind of the events merged for it
+ * @returns None for a file added and removed again in between
+ */
+async fn settle(path: &Path, kind: WatchEventKind) -> Option<WatchEvent> {
+  let file_path = path.to_string_lossy().to_string();
+  let kind = match (kind, path.is_file()) {
+    (WatchEventKind::Added, false) => return None,
+    (_, false) => {
+      return Some(WatchEvent {
+        file_path,
+        kind: WatchEventKind::Removed,
+        tags: None,
+        error: None,
+      })
+    }
+    (WatchEventKind::Removed, true) => WatchEventKind::Changed,
+    (kind, true) => kind,
+  };
+  let (tags, error) = match util::read_tags(file_path.clone()).await {
+    Ok(tags) => (Some(tags), None),
+    Err(error) => (None, Some(error)),
+  };
+  Some(WatchEvent {
+    file_path,
+    kind,
+    tags,
+    error,
+  })
+}
+
+/**
+ * Watch a directory for audio files being added, changed or removed, and
+ * report each one with its fresh tags once it has settled. Events are
+ * reported from a thread of the watch's own.
+ * @param root - The directory to watch
+ * @param options - The watch options
+ * @param on_event - Called for every file that settled
+ * @returns The watch, it stops when dropped
+ */
+pub fn watch_directory<F>(
+  root: &Path,
+  options: WatchOptions,
+  on_event: F,
+) -> Result<DirectoryWatch, String>
+where
+  F: Fn(WatchEvent) + Send + 'static,
+{
+  let (sender, receiver) = mpsc::channel::<notify::Result<Event>>();
+  let mut watcher =
+    notify::recommended_watcher(sender).map_err(|e| format!("Failed to watch directory: {}", e))?;
+  let mode = if options.recursive {
+    RecursiveMode::Recursive
+  } else {
+    RecursiveMode::NonRecursive
+  };
+  watcher
+    .watch(root, mode)
+    .map_err(|e| format!("Failed to watch directory: {}", e))?;
+
+  let extensions = scan::extension_set(options.extensions.as_deref());
+  let debounce = Duration::from_millis(options.debounce_ms);
+  let runtime = tokio::runtime::Builder::new_current_thread()
+    .build()
+    .map_err(|e| format!("Failed to watch directory: {}", e))?;
+  thread::spawn(move || {
+    let mut pending: HashMap<PathBuf, (WatchEventKind, Instant)> = HashMap::new();
+    loop {
+      let timeout = pending
+        .values()
+        .map(|(_, at)| (*at + debounce).saturating_duration_since(Instant::now()))
+        .min()
+        .unwrap_or(Duration::from_secs(3600));
+      match receiver.recv_timeout(timeout) {
+        Ok(Ok(event)) => {
+          for (path, kind) in event_kinds(&event) {
+            if !scan::has_extension(&extensions, &path) {
+              continue;
+            }
+            let kind = match pending.get(&path) {
+              Some((pending, _)) => merge_kinds(*pending, kind),
+              None => kind,
+            };
+            pending.insert(path, (kind, Instant::now()));
+          }
+        }
+        // errors of the watcher itself aren't tied to a file
+        Ok(Err(_)) => {}
+        Err(RecvTimeoutError::Timeout) => {}
+        Err(RecvTimeoutError::Disconnected) => break,
+      }
+      let mut settled: Vec<(PathBuf, WatchEventKind)> = pending
+        .iter()
+        .filter(|(_, (_, at))| at.elapsed() >= debounce)
+        .map(|(path, (kind, _))| (path.clone(), *kind))
+        .collect();
+      settled.sort_by(|a, b| a.0.cmp(&b.0));
+      for (path, kind) in settled {
+        pending.remove(&path);
+        if let Some(event) = runtime.block_on(settle(&path, kind)) {
+          on_event(event);
+        }
+      }
+    }
+  });
+  Ok(DirectoryWatch { _watcher: watcher })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_support::{sample_mp3, write_fixture};
+
+  #[test]
+  fn test_merge_kinds() {
+    use WatchEventKind::*;
+    assert_eq!(merge_kinds(Added, Changed), Added);
+    assert_eq!(merge_kinds(Removed, Added), Changed);
+    assert_eq!(merge_kinds(Changed, Removed), Removed);
+    assert_eq!(merge_kinds(Added, Removed), Removed);
+  }
+
+  #[tokio::test]
+  async fn test_watch_directory() {
+    let dir = tempfile::tempdir().unwrap();
+    let (sender, receiver) = mpsc::channel();
+    let options = WatchOptions {
+      debounce_ms: 50,
+      ..Default::default()
+    };
+    let watch = watch_directory(dir.path(), options, move |event| {
+      let _ = sender.send(event);
+    })
+    .unwrap();
+
+    let tags = AudioTags {
+      title: Some("Watched".to_string()),
+      ..Default::default()
+    };
+    let tagged = util::write_tags_to_buffer(sample_mp3(), tags)
+      .await
+      .unwrap();
+    write_fixture(dir.path(), "notes.txt", b"not audio");
+    write_fixture(dir.path(), "a.mp3", &tagged);
+    let event = receiver.recv_timeout(Duration::from_secs(10)).unwrap();
+    assert_eq!(event.kind, WatchEventKind::Added);
+    assert!(event.file_path.ends_with("a.mp3"));
+    assert_eq!(event.tags.unwrap().title, Some("Watched".to_string()));
+
+    std::fs::remove_file(dir.path().join("a.mp3")).unwrap();
+    let event = receiver.recv_timeout(Duration::from_secs(10)).unwrap();
+    assert_eq!(event.kind, WatchEventKind::Removed);
+    assert_eq!(event.tags, None);
+
+    drop(watch);
+    assert!(receiver.recv_timeout(Duration::from_secs(1)).is_err());
+  }
+}
@@ -0,0 +1,133 @@
+use crate::transaction::sibling_path;
+use std::fs::{self, OpenOptions};
+use std::path::Path;
+
+// Room for a tag that grows on rewrite (new frames, pictures, padding)
+const REWRITE_HEADROOM: u64 = 1024 * 1024;
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Writability {
+  pub writable: bool,
+  // why the file cannot be written, None when it can
+  pub reason: Option<String>,
+  // a rewrite may stage a full copy of the file next to it
+  pub required_bytes: u64,
+  // free space on the file's volume, None when it cannot be determined
+  pub available_bytes: Option<u64>,
+}
+
+#[cfg(unix)]
+fn available_space(dir: &Path) -> Option<u64> {
+  use std::ffi::CString;
+  use std::os::unix::ffi::OsStrExt;
+
+  let dir = CString::new(dir.as_os_str().as_bytes()).ok()?;
+  let mut stats: libc::statvfs = unsafe { std::mem::zeroed() };
+  // SAFETY: `dir` is a valid C string and `stats` a writable statvfs
+  if unsafe { libc::statvfs(dir.as_ptr(), &mut stats) } != 0 {
+    return None;
+  }
+  #[allow(clippy::unnecessary_cast)]
+  Some(stats.f_bavail as u64 * stats.f_frsize as u64)
+}
+
+#[cfg(not(unix))]
+fn available_space(_dir: &Path) -> Option<u64> {
+  None
+}
+
+// journals and transactions create files next to the target
+fn check_directory(path: &Path) -> Result<(), String> {
+  let probe = sibling_path(path, "tagpilot-probe");
+  fs::write(&probe, b"")
+    .and_then(|_| fs::remove_file(&probe))
+    .map_err(|e| format!("Directory is not writable: {}", e))
+}
+
+fn check(path: &Path, writability: &mut Writability) -> Result<(), String> {
+  let metadata = fs::metadata(path).map_err(|e| format!("Failed to access file: {}", e))?;
+  if !metadata.is_file() {
+    return Err("Not a regular file".to_string());
+  }
+  writability.required_bytes = metadata.len() + REWRITE_HEADROOM;
+  if metadata.permissions().readonly() {
+    return Err("File is read-only".to_string());
+  }
+  OpenOptions::new()
+    .write(true)
+    .open(path)
+    .map_err(|e| format!("Failed to open file for writing: {}", e))?;
+  check_directory(path)?;
+
+  let dir = match path.parent() {
+    Some(parent) if !parent.as_os_str().is_empty() => parent,
+    _ => Path::new("."),
+  };
+  writability.available_bytes = available_space(dir);
+  match writability.available_bytes {
+    Some(available) if available < writability.required_bytes => Err(format!(
+      "Not enough disk space: {} bytes needed, {} available",
+      writability.required_bytes, available
+    )),
+    _ => Ok(()),
+  }
+}
+
+/**
+ * Check whether a file could be rewritten: permissions, read-only attributes,
+ * a writable directory and enough free space for the rewrite. Nothing is
+ * modified, so batch jobs can report blocked files before writing any.
+ * @param file_path - The audio file about to be written
+ */
+pub async fn can_write(file_path: impl AsRef<Path>) -> Writability {
+  let mut writability = Writability::default();
+  match check(file_path.as_ref(), &mut writability) {
+    Ok(()) => writability.writable = true,
+    Err(reason) => writability.reason = Some(reason),
+  }
+  writability
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_support::{sample_mp3, write_fixture};
+
+  #[tokio::test]
+  async fn test_can_write_regular_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let data = sample_mp3();
+    let path = write_fixture(dir.path(), "song.mp3", &data);
+
+    let writability = can_write(path).await;
+    assert!(writability.writable, "{:?}", writability.reason);
+    assert_eq!(writability.reason, None);
+    assert_eq!(
+      writability.required_bytes,
+      data.len() as u64 + REWRITE_HEADROOM
+    );
+    // the probe file is gone
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+  }
+
+  #[tokio::test]
+  async fn test_can_write_reports_blocked_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_fixture(dir.path(), "song.mp3", &sample_mp3());
+    let mut permissions = fs::metadata(&path).unwrap().permissions();
+    permissions.set_readonly(true);
+    fs::set_permissions(&path, permissions).unwrap();
+
+    let writability = can_write(path).await;
+    assert!(!writability.writable);
+    assert_eq!(writability.reason, Some("File is read-only".to_string()));
+
+    let missing = dir.path().join("missing.mp3").to_string_lossy().to_string();
+    let writability = can_write(missing).await;
+    assert!(!writability.writable);
+    assert!(writability.reason.is_some());
+
+    let writability = can_write(dir.path().to_string_lossy().to_string()).await;
+    assert_eq!(writability.reason, Some("Not a regular file".to_string()));
+  }
+}